@@ -0,0 +1,50 @@
+-- Plantillas por defecto para emails de notificaciones (inmediatos y resúmenes)
+INSERT INTO organization_email_templates (organization_id, template_key, display_name, subject_template, body_template, is_html, is_enabled)
+SELECT
+    o.id as organization_id,
+    'notification_immediate' as template_key,
+    'Notificación inmediata' as display_name,
+    '{{notification_title}}' as subject_template,
+    'Hola {{recipient_name}},
+
+{{notification_message}}
+
+Ver en la plataforma: {{notification_url}}
+
+Para dejar de recibir este tipo de correos: {{unsubscribe_url}}
+
+Saludos,
+El equipo de {{organization_name}}' as body_template,
+    false as is_html,
+    true as is_enabled
+FROM organizations o
+WHERE NOT EXISTS (
+    SELECT 1 FROM organization_email_templates
+    WHERE organization_id = o.id AND template_key = 'notification_immediate'
+);
+
+INSERT INTO organization_email_templates (organization_id, template_key, display_name, subject_template, body_template, is_html, is_enabled)
+SELECT
+    o.id as organization_id,
+    'notification_digest' as template_key,
+    'Resumen de notificaciones' as display_name,
+    'Tu resumen {{digest_period}}: {{notification_count}} notificaciones' as subject_template,
+    'Hola {{recipient_name}},
+
+Tienes {{notification_count}} notificaciones sin leer:
+
+{{notification_list}}
+
+Ver todas: {{notifications_url}}
+
+Para dejar de recibir estos resúmenes: {{unsubscribe_url}}
+
+Saludos,
+El equipo de {{organization_name}}' as body_template,
+    false as is_html,
+    true as is_enabled
+FROM organizations o
+WHERE NOT EXISTS (
+    SELECT 1 FROM organization_email_templates
+    WHERE organization_id = o.id AND template_key = 'notification_digest'
+);
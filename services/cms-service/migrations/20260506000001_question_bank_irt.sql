@@ -0,0 +1,17 @@
+-- Calibración psicométrica (TRI) de las preguntas del banco
+--
+-- Los parámetros los estima el LMS a partir de las respuestas de los alumnos
+-- (GET /psychometrics/calibration) y se guardan aquí para que las plantillas de
+-- evaluación puedan seleccionar preguntas por dificultad calibrada.
+ALTER TABLE question_bank
+    ADD COLUMN IF NOT EXISTS irt_model          VARCHAR(8),        -- '1pl' | '2pl'
+    ADD COLUMN IF NOT EXISTS irt_difficulty     DOUBLE PRECISION,  -- parámetro b (escala logit)
+    ADD COLUMN IF NOT EXISTS irt_discrimination DOUBLE PRECISION,  -- parámetro a
+    ADD COLUMN IF NOT EXISTS irt_se_difficulty  DOUBLE PRECISION,
+    ADD COLUMN IF NOT EXISTS irt_sample_size    INTEGER,
+    ADD COLUMN IF NOT EXISTS point_biserial     DOUBLE PRECISION,
+    ADD COLUMN IF NOT EXISTS irt_calibrated_at  TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_question_bank_irt_difficulty
+    ON question_bank (organization_id, irt_difficulty)
+    WHERE irt_difficulty IS NOT NULL;
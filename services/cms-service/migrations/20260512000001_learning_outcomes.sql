@@ -0,0 +1,58 @@
+-- Marcos de competencias / resultados de aprendizaje (p. ej. descriptores "can-do" del MCER)
+--
+-- Los marcos son jerárquicos y se importan desde CSV o CASE JSON (1EdTech). Los
+-- resultados se alinean con lecciones, bloques de quiz, criterios de rúbrica y
+-- preguntas del banco; la alineación viaja al LMS al publicar el curso.
+
+CREATE TABLE IF NOT EXISTS outcome_frameworks (
+    id              UUID         PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID         NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    name            VARCHAR(255) NOT NULL,
+    description     TEXT,
+    source          VARCHAR(16)  NOT NULL DEFAULT 'manual',  -- manual | csv | case
+    source_identifier TEXT,                                   -- CFDocument.identifier en CASE
+    created_by      UUID,
+    created_at      TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    CONSTRAINT outcome_frameworks_source CHECK (source IN ('manual', 'csv', 'case'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_outcome_frameworks_org ON outcome_frameworks (organization_id);
+
+CREATE TABLE IF NOT EXISTS learning_outcomes (
+    id              UUID         PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID         NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    framework_id    UUID         NOT NULL REFERENCES outcome_frameworks(id) ON DELETE CASCADE,
+    parent_id       UUID         REFERENCES learning_outcomes(id) ON DELETE CASCADE,
+    code            VARCHAR(100) NOT NULL,
+    description     TEXT         NOT NULL,
+    level           VARCHAR(50),                              -- p. ej. "B1"
+    position        INTEGER      NOT NULL DEFAULT 0,
+    external_id     TEXT,                                     -- CFItem.identifier en CASE
+    created_at      TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    UNIQUE (framework_id, code)
+);
+
+CREATE INDEX IF NOT EXISTS idx_learning_outcomes_framework ON learning_outcomes (framework_id);
+CREATE INDEX IF NOT EXISTS idx_learning_outcomes_parent ON learning_outcomes (parent_id);
+
+CREATE TABLE IF NOT EXISTS outcome_alignments (
+    id              UUID         PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID         NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    outcome_id      UUID         NOT NULL REFERENCES learning_outcomes(id) ON DELETE CASCADE,
+    -- lesson | quiz_block | rubric_criterion | question
+    entity_type     VARCHAR(20)  NOT NULL,
+    -- lesson_id (lesson, quiz_block), rubric_criteria.id o question_bank.id
+    entity_id       UUID         NOT NULL,
+    block_id        TEXT,                                     -- solo para quiz_block
+    weight          REAL         NOT NULL DEFAULT 1,
+    created_by      UUID,
+    created_at      TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    CONSTRAINT outcome_alignments_entity_type CHECK (entity_type IN ('lesson', 'quiz_block', 'rubric_criterion', 'question')),
+    CONSTRAINT outcome_alignments_block CHECK ((entity_type = 'quiz_block') = (block_id IS NOT NULL)),
+    CONSTRAINT outcome_alignments_weight CHECK (weight > 0)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_outcome_alignments_unique
+    ON outcome_alignments (outcome_id, entity_type, entity_id, COALESCE(block_id, ''));
+CREATE INDEX IF NOT EXISTS idx_outcome_alignments_entity ON outcome_alignments (entity_type, entity_id);
@@ -0,0 +1,144 @@
+-- Registro de auditoría encadenado por hash.
+--
+-- Cada entrada de audit_logs guarda su posición en la cadena de su organización
+-- (chain_seq), el hash de la entrada anterior (prev_hash) y su propio hash
+-- (entry_hash = sha256(prev_hash || fn_audit_entry_payload(fila))). Editar o borrar
+-- una entrada rompe la cadena a partir de ese punto; los checkpoints firmados
+-- fijan el último hash conocido para detectar también que se reescriba la cola.
+
+ALTER TABLE audit_logs
+    ADD COLUMN IF NOT EXISTS chain_seq BIGINT,
+    ADD COLUMN IF NOT EXISTS prev_hash TEXT,
+    ADD COLUMN IF NOT EXISTS entry_hash TEXT;
+
+-- Cabeza de cada cadena; la entrada de sistema (organization_id NULL) usa el UUID nulo
+CREATE TABLE IF NOT EXISTS audit_chain_heads (
+    chain_key UUID PRIMARY KEY,
+    last_seq BIGINT NOT NULL DEFAULT 0,
+    last_hash TEXT NOT NULL
+);
+REVOKE ALL ON audit_chain_heads FROM openccb_tenant;
+
+CREATE TABLE IF NOT EXISTS audit_checkpoints (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL,
+    last_seq BIGINT NOT NULL,
+    last_hash TEXT NOT NULL,
+    key_id TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (organization_id, last_seq)
+);
+CREATE INDEX IF NOT EXISTS idx_audit_checkpoints_org ON audit_checkpoints(organization_id, created_at DESC);
+SELECT fn_enable_tenant_rls('audit_checkpoints');
+
+-- Representación canónica de una entrada (independiente de la zona horaria de la sesión)
+CREATE OR REPLACE FUNCTION fn_audit_entry_payload(r audit_logs)
+RETURNS TEXT AS $$
+    SELECT jsonb_build_array(
+        r.chain_seq,
+        r.id,
+        r.organization_id,
+        r.user_id,
+        r.action,
+        r.entity_type,
+        r.entity_id,
+        r.event_type,
+        r.changes,
+        r.old_data,
+        r.new_data,
+        host(r.ip_address),
+        host(r.public_ip),
+        r.user_agent,
+        r.metadata,
+        to_char(r.created_at AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.US"Z"')
+    )::text
+$$ LANGUAGE sql STABLE;
+
+CREATE OR REPLACE FUNCTION fn_audit_chain_genesis()
+RETURNS TEXT AS $$
+    SELECT repeat('0', 64)
+$$ LANGUAGE sql IMMUTABLE;
+
+-- Enlaza la nueva entrada con la cabeza de su cadena. SECURITY DEFINER porque las
+-- cabezas no son accesibles para el rol de tenant.
+CREATE OR REPLACE FUNCTION fn_audit_chain_link()
+RETURNS TRIGGER AS $$
+DECLARE
+    v_key  UUID := COALESCE(NEW.organization_id, '00000000-0000-0000-0000-000000000000');
+    v_head audit_chain_heads%ROWTYPE;
+BEGIN
+    INSERT INTO audit_chain_heads (chain_key, last_seq, last_hash)
+    VALUES (v_key, 0, fn_audit_chain_genesis())
+    ON CONFLICT (chain_key) DO NOTHING;
+
+    SELECT * INTO v_head FROM audit_chain_heads WHERE chain_key = v_key FOR UPDATE;
+
+    NEW.created_at := COALESCE(NEW.created_at, NOW());
+    NEW.chain_seq := v_head.last_seq + 1;
+    NEW.prev_hash := v_head.last_hash;
+    NEW.entry_hash := encode(sha256(convert_to(NEW.prev_hash || fn_audit_entry_payload(NEW), 'UTF8')), 'hex');
+
+    UPDATE audit_chain_heads
+    SET last_seq = NEW.chain_seq, last_hash = NEW.entry_hash
+    WHERE chain_key = v_key;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER SET search_path = public;
+
+CREATE OR REPLACE FUNCTION fn_audit_append_only()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION '% es de solo inserción', TG_TABLE_NAME;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Encadenar las entradas existentes en orden cronológico
+DO $$
+DECLARE
+    r      audit_logs%ROWTYPE;
+    v_key  UUID;
+    v_head audit_chain_heads%ROWTYPE;
+BEGIN
+    FOR r IN SELECT * FROM audit_logs WHERE chain_seq IS NULL ORDER BY created_at, id LOOP
+        v_key := COALESCE(r.organization_id, '00000000-0000-0000-0000-000000000000');
+        INSERT INTO audit_chain_heads (chain_key, last_seq, last_hash)
+        VALUES (v_key, 0, fn_audit_chain_genesis())
+        ON CONFLICT (chain_key) DO NOTHING;
+        SELECT * INTO v_head FROM audit_chain_heads WHERE chain_key = v_key;
+
+        r.chain_seq := v_head.last_seq + 1;
+        r.prev_hash := v_head.last_hash;
+        r.entry_hash := encode(sha256(convert_to(r.prev_hash || fn_audit_entry_payload(r), 'UTF8')), 'hex');
+
+        UPDATE audit_logs
+        SET chain_seq = r.chain_seq, prev_hash = r.prev_hash, entry_hash = r.entry_hash
+        WHERE id = r.id;
+        UPDATE audit_chain_heads
+        SET last_seq = r.chain_seq, last_hash = r.entry_hash
+        WHERE chain_key = v_key;
+    END LOOP;
+END $$;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_audit_logs_chain
+    ON audit_logs ((COALESCE(organization_id, '00000000-0000-0000-0000-000000000000'::uuid)), chain_seq);
+
+DROP TRIGGER IF EXISTS trg_audit_chain_link ON audit_logs;
+CREATE TRIGGER trg_audit_chain_link
+BEFORE INSERT ON audit_logs
+FOR EACH ROW EXECUTE FUNCTION fn_audit_chain_link();
+
+DROP TRIGGER IF EXISTS trg_audit_logs_append_only ON audit_logs;
+CREATE TRIGGER trg_audit_logs_append_only
+BEFORE UPDATE OR DELETE ON audit_logs
+FOR EACH ROW EXECUTE FUNCTION fn_audit_append_only();
+
+DROP TRIGGER IF EXISTS trg_audit_checkpoints_append_only ON audit_checkpoints;
+CREATE TRIGGER trg_audit_checkpoints_append_only
+BEFORE UPDATE OR DELETE ON audit_checkpoints
+FOR EACH ROW EXECUTE FUNCTION fn_audit_append_only();
+
+-- Borrar una organización ya no reescribe sus entradas (ON DELETE SET NULL rompería
+-- la cadena y el trigger de solo inserción lo impediría)
+ALTER TABLE audit_logs DROP CONSTRAINT IF EXISTS fk_audit_log_organization;
@@ -0,0 +1,64 @@
+-- Búsqueda, exportación y retención del registro de auditoría.
+--
+-- La retención no borra entradas sin más: audit_retention archiva el prefijo
+-- antiguo de la cadena en un archivo JSONL comprimido, registra el archivo en
+-- audit_archives (con el último hash para seguir verificando la cadena) y solo
+-- entonces elimina las filas cubiertas por ese archivo.
+
+CREATE INDEX IF NOT EXISTS idx_audit_logs_org_created ON audit_logs(organization_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_audit_logs_org_entity ON audit_logs(organization_id, entity_type, entity_id);
+CREATE INDEX IF NOT EXISTS idx_audit_logs_org_user ON audit_logs(organization_id, user_id);
+
+CREATE TABLE IF NOT EXISTS audit_retention_policies (
+    organization_id UUID PRIMARY KEY REFERENCES organizations(id) ON DELETE CASCADE,
+    retention_days INTEGER NOT NULL CHECK (retention_days >= 30),
+    updated_by UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+SELECT fn_enable_tenant_rls('audit_retention_policies');
+
+CREATE TABLE IF NOT EXISTS audit_archives (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL,
+    from_seq BIGINT NOT NULL,
+    to_seq BIGINT NOT NULL,
+    first_prev_hash TEXT NOT NULL,
+    last_hash TEXT NOT NULL,
+    entry_count INTEGER NOT NULL,
+    oldest_at TIMESTAMPTZ NOT NULL,
+    newest_at TIMESTAMPTZ NOT NULL,
+    file_path TEXT NOT NULL,
+    file_sha256 TEXT NOT NULL,
+    file_size BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (from_seq <= to_seq),
+    UNIQUE (organization_id, from_seq)
+);
+CREATE INDEX IF NOT EXISTS idx_audit_archives_org ON audit_archives(organization_id, to_seq DESC);
+SELECT fn_enable_tenant_rls('audit_archives');
+
+DROP TRIGGER IF EXISTS trg_audit_archives_append_only ON audit_archives;
+CREATE TRIGGER trg_audit_archives_append_only
+BEFORE UPDATE OR DELETE ON audit_archives
+FOR EACH ROW EXECUTE FUNCTION fn_audit_append_only();
+
+-- Solo se pueden borrar entradas cubiertas por el archivo indicado en app.audit_archive_id
+CREATE OR REPLACE FUNCTION fn_audit_logs_guard()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' AND EXISTS (
+        SELECT 1 FROM audit_archives ar
+        WHERE ar.id = NULLIF(current_setting('app.audit_archive_id', true), '')::uuid
+          AND ar.organization_id = OLD.organization_id
+          AND OLD.chain_seq BETWEEN ar.from_seq AND ar.to_seq
+    ) THEN
+        RETURN OLD;
+    END IF;
+    RAISE EXCEPTION 'audit_logs es de solo inserción';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_audit_logs_append_only ON audit_logs;
+CREATE TRIGGER trg_audit_logs_append_only
+BEFORE UPDATE OR DELETE ON audit_logs
+FOR EACH ROW EXECUTE FUNCTION fn_audit_logs_guard();
@@ -0,0 +1,22 @@
+-- Plugins de servidor en WebAssembly (WASI).
+--
+-- Un plugin 'component' sigue siendo un Web Component en iframe (component_url);
+-- un plugin 'wasm' lleva el módulo compilado, los eventos a los que se suscribe,
+-- los tipos de bloque que califica y sus límites de combustible y memoria. El CMS
+-- replica los plugins wasm en el LMS, que es quien los ejecuta.
+
+ALTER TABLE org_plugins
+    ADD COLUMN IF NOT EXISTS kind TEXT NOT NULL DEFAULT 'component',
+    ADD COLUMN IF NOT EXISTS wasm_module BYTEA,
+    ADD COLUMN IF NOT EXISTS wasm_sha256 TEXT,
+    ADD COLUMN IF NOT EXISTS events TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS block_types TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS fuel_limit BIGINT NOT NULL DEFAULT 50000000,
+    ADD COLUMN IF NOT EXISTS memory_limit_mb INTEGER NOT NULL DEFAULT 32;
+
+ALTER TABLE org_plugins
+    ADD CONSTRAINT org_plugins_kind_check CHECK (kind IN ('component', 'wasm')),
+    ADD CONSTRAINT org_plugins_wasm_module_check CHECK (kind <> 'wasm' OR wasm_module IS NOT NULL),
+    ADD CONSTRAINT org_plugins_limits_check CHECK (
+        fuel_limit BETWEEN 1000000 AND 1000000000 AND memory_limit_mb BETWEEN 1 AND 256
+    );
@@ -0,0 +1,27 @@
+-- Manifiestos firmados de plugins.
+--
+-- Un plugin instalado con manifiesto guarda el documento firmado, la versión, el
+-- editor y los permisos declarados. La firma Ed25519 se verifica con las claves
+-- de los editores de confianza de la organización (plugin_publishers).
+
+ALTER TABLE org_plugins
+    ADD COLUMN IF NOT EXISTS manifest JSONB,
+    ADD COLUMN IF NOT EXISTS version TEXT,
+    ADD COLUMN IF NOT EXISTS publisher TEXT,
+    ADD COLUMN IF NOT EXISTS permissions TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS signature TEXT,
+    ADD COLUMN IF NOT EXISTS signed_by UUID;
+
+CREATE TABLE IF NOT EXISTS plugin_publishers (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    -- Clave pública Ed25519 en hexadecimal (32 bytes)
+    public_key TEXT NOT NULL CHECK (public_key ~ '^[0-9a-f]{64}$'),
+    created_by UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (organization_id, name, public_key)
+);
+CREATE INDEX IF NOT EXISTS idx_plugin_publishers_org_name ON plugin_publishers(organization_id, name);
+
+SELECT fn_enable_tenant_rls('plugin_publishers');
@@ -0,0 +1,28 @@
+-- Pistas de subtítulos por idioma de cada lección.
+--
+-- La transcripción de la lección sigue siendo la pista del idioma original. Las
+-- traducciones (y las correcciones del original) se guardan aquí cue a cue, con
+-- los mismos tiempos, y solo se sirven a los estudiantes una vez aprobadas.
+
+CREATE TABLE IF NOT EXISTS lesson_subtitle_tracks (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    lesson_id UUID NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+    language TEXT NOT NULL,
+    label TEXT NOT NULL,
+    source_language TEXT,
+    -- machine (traducción automática) o manual (editada por un instructor)
+    origin TEXT NOT NULL DEFAULT 'machine' CHECK (origin IN ('machine', 'manual')),
+    status TEXT NOT NULL DEFAULT 'draft' CHECK (status IN ('translating', 'draft', 'approved', 'failed')),
+    cues JSONB NOT NULL DEFAULT '[]',
+    error TEXT,
+    edited_by UUID,
+    approved_by UUID,
+    approved_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (lesson_id, language)
+);
+CREATE INDEX IF NOT EXISTS idx_lesson_subtitle_tracks_org ON lesson_subtitle_tracks(organization_id);
+
+SELECT fn_enable_tenant_rls('lesson_subtitle_tracks');
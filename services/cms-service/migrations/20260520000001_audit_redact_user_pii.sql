@@ -0,0 +1,133 @@
+-- Los datos personales de users no se copian a audit_logs.
+--
+-- audit_logs es de solo inserción y encadenado, así que lo que entra no se puede
+-- borrar después: una supresión DSAR dejaría el correo, el nombre y el hash de la
+-- contraseña del titular en old_data. El trigger de auditoría reemplaza ahora esas
+-- columnas por un marcador y, cuando la actualización es la supresión
+-- (`app.event_type = 'DSAR_ERASURE'`), no guarda imagen de la fila.
+
+CREATE OR REPLACE FUNCTION fn_redact_user_pii(data JSONB)
+RETURNS JSONB AS $$
+DECLARE
+    v_key TEXT;
+BEGIN
+    IF data IS NULL THEN
+        RETURN NULL;
+    END IF;
+    data := data - 'password_hash';
+    FOREACH v_key IN ARRAY ARRAY['email', 'full_name', 'sam_student_id', 'avatar_url', 'bio'] LOOP
+        IF data ? v_key AND data->v_key <> 'null'::jsonb THEN
+            data := jsonb_set(data, ARRAY[v_key], '"[redactado]"'::jsonb);
+        END IF;
+    END LOOP;
+    RETURN data;
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+CREATE OR REPLACE FUNCTION fn_trigger_audit_log()
+RETURNS TRIGGER AS $$
+DECLARE
+    v_user_id UUID;
+    v_org_id UUID;
+    v_ip INET;
+    v_user_agent TEXT;
+    v_event_type VARCHAR(50);
+    v_old_data JSONB := NULL;
+    v_new_data JSONB := NULL;
+    v_action VARCHAR(50);
+BEGIN
+    -- Try to get context from session variables
+    BEGIN
+        v_user_id := current_setting('app.current_user_id', true)::UUID;
+    EXCEPTION WHEN OTHERS THEN
+        v_user_id := NULL;
+    END;
+
+    BEGIN
+        v_org_id := current_setting('app.current_org_id', true)::UUID;
+    EXCEPTION WHEN OTHERS THEN
+        v_org_id := NULL;
+    END;
+
+    BEGIN
+        v_ip := current_setting('app.client_ip', true)::INET;
+    EXCEPTION WHEN OTHERS THEN
+        v_ip := NULL;
+    END;
+
+    BEGIN
+        v_user_agent := current_setting('app.user_agent', true);
+    EXCEPTION WHEN OTHERS THEN
+        v_user_agent := NULL;
+    END;
+
+    BEGIN
+        v_event_type := current_setting('app.event_type', true);
+    EXCEPTION WHEN OTHERS THEN
+        v_event_type := 'USER_EVENT';
+    END;
+
+    -- Handle different operations
+    IF (TG_OP = 'DELETE') THEN
+        v_old_data := to_jsonb(OLD);
+        v_action := 'DELETE';
+    ELSIF (TG_OP = 'UPDATE') THEN
+        v_old_data := to_jsonb(OLD);
+        v_new_data := to_jsonb(NEW);
+        v_action := 'UPDATE';
+    ELSIF (TG_OP = 'INSERT') THEN
+        v_new_data := to_jsonb(NEW);
+        v_action := 'INSERT';
+        
+        -- Special case: For user registration, use the new ID if no session user is set
+        IF TG_TABLE_NAME = 'users' AND v_user_id IS NULL THEN
+            v_user_id := NEW.id;
+        END IF;
+    END IF;
+
+    -- audit_logs es de solo inserción: los datos personales de users nunca entran
+    IF TG_TABLE_NAME = 'users' THEN
+        IF v_event_type = 'DSAR_ERASURE' THEN
+            -- La supresión no deja imagen de la fila: solo constancia del evento
+            v_old_data := NULL;
+            v_new_data := jsonb_build_object('erased', true);
+        ELSE
+            v_old_data := fn_redact_user_pii(v_old_data);
+            v_new_data := fn_redact_user_pii(v_new_data);
+        END IF;
+    END IF;
+
+    -- Insert into audit_logs
+    INSERT INTO audit_logs (
+        organization_id, 
+        user_id, 
+        action, 
+        entity_type, 
+        entity_id, 
+        event_type, 
+        old_data, 
+        new_data, 
+        ip_address, 
+        user_agent,
+        changes
+    )
+    VALUES (
+        COALESCE(v_org_id, (CASE WHEN TG_OP = 'DELETE' THEN OLD.organization_id ELSE NEW.organization_id END)),
+        v_user_id,
+        v_action,
+        TG_TABLE_NAME,
+        CASE WHEN TG_OP = 'DELETE' THEN OLD.id ELSE NEW.id END,
+        COALESCE(v_event_type, 'USER_EVENT'),
+        v_old_data,
+        v_new_data,
+        v_ip,
+        v_user_agent,
+        COALESCE(v_new_data, v_old_data)
+    );
+
+    IF (TG_OP = 'DELETE') THEN
+        RETURN OLD;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,61 @@
+-- Políticas RLS separadas por comando.
+--
+-- La política única tenant_isolation usaba la misma condición para leer y escribir,
+-- así que en las tablas con organization_id opcional una organización podía
+-- modificar, borrar o crear filas globales (organization_id NULL). Ahora las filas
+-- globales solo son visibles en SELECT; INSERT, UPDATE y DELETE exigen la
+-- organización de la sesión.
+
+CREATE OR REPLACE FUNCTION fn_enable_tenant_rls(p_table REGCLASS)
+RETURNS VOID AS $$
+DECLARE
+    v_nullable BOOLEAN;
+    v_check    TEXT := 'organization_id = NULLIF(current_setting(''app.current_org_id'', true), '''')::uuid';
+    v_visible  TEXT;
+BEGIN
+    SELECT NOT a.attnotnull INTO v_nullable
+    FROM pg_attribute a
+    WHERE a.attrelid = p_table AND a.attname = 'organization_id' AND NOT a.attisdropped;
+
+    IF v_nullable IS NULL THEN
+        RAISE EXCEPTION 'La tabla % no tiene organization_id', p_table;
+    END IF;
+    v_visible := v_check;
+    IF v_nullable THEN
+        v_visible := '(' || v_check || ' OR organization_id IS NULL)';
+    END IF;
+
+    EXECUTE format('ALTER TABLE %s ENABLE ROW LEVEL SECURITY', p_table);
+    EXECUTE format('DROP POLICY IF EXISTS tenant_isolation ON %s', p_table);
+    EXECUTE format('DROP POLICY IF EXISTS tenant_select ON %s', p_table);
+    EXECUTE format('DROP POLICY IF EXISTS tenant_insert ON %s', p_table);
+    EXECUTE format('DROP POLICY IF EXISTS tenant_update ON %s', p_table);
+    EXECUTE format('DROP POLICY IF EXISTS tenant_delete ON %s', p_table);
+    EXECUTE format('DROP POLICY IF EXISTS platform_admin_bypass ON %s', p_table);
+    EXECUTE format('CREATE POLICY tenant_select ON %s FOR SELECT TO openccb_tenant USING (%s)',
+                   p_table, v_visible);
+    EXECUTE format('CREATE POLICY tenant_insert ON %s FOR INSERT TO openccb_tenant WITH CHECK (%s)',
+                   p_table, v_check);
+    EXECUTE format('CREATE POLICY tenant_update ON %s FOR UPDATE TO openccb_tenant USING (%s) WITH CHECK (%s)',
+                   p_table, v_check, v_check);
+    EXECUTE format('CREATE POLICY tenant_delete ON %s FOR DELETE TO openccb_tenant USING (%s)',
+                   p_table, v_check);
+    EXECUTE format('CREATE POLICY platform_admin_bypass ON %s TO openccb_platform_admin USING (true) WITH CHECK (true)',
+                   p_table);
+END;
+$$ LANGUAGE plpgsql;
+
+-- Rehacer las políticas de todas las tablas que ya tenían RLS de organización
+DO $$
+DECLARE
+    r RECORD;
+BEGIN
+    FOR r IN
+        SELECT DISTINCT p.polrelid::regclass AS tbl
+        FROM pg_policy p
+        WHERE p.polname = 'tenant_isolation'
+          AND p.polrelid <> 'organizations'::regclass
+    LOOP
+        PERFORM fn_enable_tenant_rls(r.tbl);
+    END LOOP;
+END $$;
@@ -0,0 +1,104 @@
+-- Enlace diferido de la cadena de auditoría.
+--
+-- fn_audit_chain_link bloqueaba la cabeza de la cadena de la organización hasta el
+-- commit. Como los triggers de auditoría se disparan dentro de cada transacción de
+-- negocio, todas las escrituras auditadas de una organización quedaban serializadas.
+-- Ahora la entrada se inserta sin enlazar (chain_seq NULL) y
+-- fn_audit_chain_link_pending, llamada por una tarea del CMS cada pocos segundos, la
+-- encadena en transacciones cortas en el orden en que se ven confirmadas.
+
+CREATE INDEX IF NOT EXISTS idx_audit_logs_unlinked
+    ON audit_logs ((COALESCE(organization_id, '00000000-0000-0000-0000-000000000000'::uuid)), created_at, id)
+    WHERE chain_seq IS NULL;
+
+-- Al insertar solo se fija la fecha; los campos de la cadena no los elige quien inserta
+CREATE OR REPLACE FUNCTION fn_audit_chain_link()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.created_at := COALESCE(NEW.created_at, NOW());
+    NEW.chain_seq := NULL;
+    NEW.prev_hash := NULL;
+    NEW.entry_hash := NULL;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Encadena hasta p_limit entradas pendientes por cadena y devuelve cuántas enlazó.
+-- El bloqueo de la cabeza dura solo esta transacción. SECURITY DEFINER porque las
+-- cabezas no son accesibles para el rol de tenant.
+CREATE OR REPLACE FUNCTION fn_audit_chain_link_pending(p_limit INTEGER DEFAULT 1000)
+RETURNS INTEGER AS $$
+DECLARE
+    v_key    UUID;
+    v_head   audit_chain_heads%ROWTYPE;
+    r        audit_logs%ROWTYPE;
+    v_linked INTEGER := 0;
+BEGIN
+    FOR v_key IN
+        SELECT DISTINCT COALESCE(organization_id, '00000000-0000-0000-0000-000000000000'::uuid)
+        FROM audit_logs
+        WHERE chain_seq IS NULL
+        ORDER BY 1
+    LOOP
+        INSERT INTO audit_chain_heads (chain_key, last_seq, last_hash)
+        VALUES (v_key, 0, fn_audit_chain_genesis())
+        ON CONFLICT (chain_key) DO NOTHING;
+
+        SELECT * INTO v_head FROM audit_chain_heads WHERE chain_key = v_key FOR UPDATE;
+
+        -- Tras esperar la cabeza se vuelve a leer: otra instancia pudo enlazar estas filas
+        FOR r IN
+            SELECT * FROM audit_logs
+            WHERE COALESCE(organization_id, '00000000-0000-0000-0000-000000000000'::uuid) = v_key
+              AND chain_seq IS NULL
+            ORDER BY created_at, id
+            LIMIT p_limit
+        LOOP
+            r.chain_seq := v_head.last_seq + 1;
+            r.prev_hash := v_head.last_hash;
+            r.entry_hash := encode(sha256(convert_to(r.prev_hash || fn_audit_entry_payload(r), 'UTF8')), 'hex');
+
+            UPDATE audit_logs
+            SET chain_seq = r.chain_seq, prev_hash = r.prev_hash, entry_hash = r.entry_hash
+            WHERE id = r.id;
+
+            v_head.last_seq := r.chain_seq;
+            v_head.last_hash := r.entry_hash;
+            v_linked := v_linked + 1;
+        END LOOP;
+
+        UPDATE audit_chain_heads
+        SET last_seq = v_head.last_seq, last_hash = v_head.last_hash
+        WHERE chain_key = v_key;
+    END LOOP;
+
+    RETURN v_linked;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER SET search_path = public;
+
+REVOKE EXECUTE ON FUNCTION fn_audit_chain_link_pending(INTEGER) FROM PUBLIC, openccb_tenant;
+
+-- La única modificación permitida es enlazar una entrada pendiente, y solo desde
+-- fn_audit_chain_link_pending (rol propietario): el resto de la fila no puede cambiar
+CREATE OR REPLACE FUNCTION fn_audit_logs_guard()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'UPDATE'
+       AND current_user NOT IN ('openccb_tenant', 'openccb_platform_admin')
+       AND OLD.chain_seq IS NULL
+       AND NEW.chain_seq IS NOT NULL
+       AND to_jsonb(NEW) - ARRAY['chain_seq', 'prev_hash', 'entry_hash']
+           = to_jsonb(OLD) - ARRAY['chain_seq', 'prev_hash', 'entry_hash'] THEN
+        RETURN NEW;
+    END IF;
+    IF TG_OP = 'DELETE' AND EXISTS (
+        SELECT 1 FROM audit_archives ar
+        WHERE ar.id = NULLIF(current_setting('app.audit_archive_id', true), '')::uuid
+          AND ar.organization_id = OLD.organization_id
+          AND OLD.chain_seq BETWEEN ar.from_seq AND ar.to_seq
+    ) THEN
+        RETURN OLD;
+    END IF;
+    RAISE EXCEPTION 'audit_logs es de solo inserción';
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,39 @@
+-- Solo el job de retención (rol propietario) archiva y borra auditoría.
+--
+-- Con RLS el rol de tenant podía insertar en audit_archives un tramo cualquiera de
+-- su organización, fijar app.audit_archive_id y borrar esas entradas. Los roles de
+-- la aplicación pierden la escritura sobre audit_archives y el borrado de
+-- audit_logs, y el guard rechaza el borrado si lo intenta uno de esos roles o si la
+-- fila no coincide con los hashes registrados en el archivo.
+
+REVOKE INSERT, UPDATE, DELETE ON audit_archives FROM openccb_tenant, openccb_platform_admin;
+REVOKE UPDATE, DELETE ON audit_logs FROM openccb_tenant, openccb_platform_admin;
+
+CREATE OR REPLACE FUNCTION fn_audit_logs_guard()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF current_user IN ('openccb_tenant', 'openccb_platform_admin') THEN
+        RAISE EXCEPTION 'audit_logs es de solo inserción';
+    END IF;
+    -- Enlace de una entrada pendiente por fn_audit_chain_link_pending
+    IF TG_OP = 'UPDATE'
+       AND OLD.chain_seq IS NULL
+       AND NEW.chain_seq IS NOT NULL
+       AND to_jsonb(NEW) - ARRAY['chain_seq', 'prev_hash', 'entry_hash']
+           = to_jsonb(OLD) - ARRAY['chain_seq', 'prev_hash', 'entry_hash'] THEN
+        RETURN NEW;
+    END IF;
+    -- Borrado de entradas cubiertas por el archivo indicado en app.audit_archive_id
+    IF TG_OP = 'DELETE' AND EXISTS (
+        SELECT 1 FROM audit_archives ar
+        WHERE ar.id = NULLIF(current_setting('app.audit_archive_id', true), '')::uuid
+          AND ar.organization_id = OLD.organization_id
+          AND OLD.chain_seq BETWEEN ar.from_seq AND ar.to_seq
+          AND (OLD.chain_seq <> ar.from_seq OR OLD.prev_hash = ar.first_prev_hash)
+          AND (OLD.chain_seq <> ar.to_seq OR OLD.entry_hash = ar.last_hash)
+    ) THEN
+        RETURN OLD;
+    END IF;
+    RAISE EXCEPTION 'audit_logs es de solo inserción';
+END;
+$$ LANGUAGE plpgsql;
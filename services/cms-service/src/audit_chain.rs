@@ -0,0 +1,564 @@
+/// Verificación y checkpoints del registro de auditoría encadenado.
+///
+/// Las entradas se insertan sin enlazar y `link_pending` las encadena en segundo plano
+/// con `fn_audit_chain_link_pending` (ver migración `audit_chain_deferred_link`), así
+/// la cabeza de la cadena no queda bloqueada durante las transacciones de negocio.
+/// Aquí se recalcula cada hash a partir de la representación
+/// canónica de la fila y se firman checkpoints periódicos con Ed25519
+/// (`AUDIT_SIGNING_KEY`, semilla de 32 bytes en hex) para que los auditores puedan
+/// comprobar la cola de la cadena con la clave pública exportada.
+use axum::{
+    Json,
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use common::{auth::Claims, middleware::Org};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const VERIFY_BATCH: i64 = 1000;
+const LINK_BATCH: i32 = 1000;
+const MAX_REPORTED_BREAKS: usize = 100;
+
+/// Hash de una entrada: sha256(prev_hash || payload) en hex, igual que en SQL.
+pub fn entry_hash(prev_hash: &str, payload: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(prev_hash.as_bytes());
+    hasher.update(payload.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+fn checkpoint_message(organization_id: Uuid, last_seq: i64, last_hash: &str) -> String {
+    format!("openccb-audit-checkpoint:v1:{}:{}:{}", organization_id, last_seq, last_hash)
+}
+
+fn signing_key() -> Option<SigningKey> {
+    let seed = hex::decode(std::env::var("AUDIT_SIGNING_KEY").ok()?.trim()).ok()?;
+    let seed: [u8; 32] = seed.try_into().ok()?;
+    Some(SigningKey::from_bytes(&seed))
+}
+
+fn key_id(key: &SigningKey) -> String {
+    hex::encode(&Sha256::digest(key.verifying_key().as_bytes())[..8])
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AuditCheckpoint {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub last_seq: i64,
+    pub last_hash: String,
+    pub key_id: String,
+    pub signature: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Firma la cabeza actual de la cadena si avanzó desde el último checkpoint.
+pub async fn create_checkpoint(
+    pool: &PgPool,
+    key: &SigningKey,
+    organization_id: Uuid,
+) -> Result<Option<AuditCheckpoint>, sqlx::Error> {
+    let head: Option<(i64, String)> = sqlx::query_as(
+        "SELECT chain_seq, entry_hash FROM audit_logs WHERE organization_id = $1 AND chain_seq IS NOT NULL ORDER BY chain_seq DESC LIMIT 1",
+    )
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+    let Some((last_seq, last_hash)) = head else {
+        return Ok(None);
+    };
+
+    let signature = key.sign(checkpoint_message(organization_id, last_seq, &last_hash).as_bytes());
+
+    sqlx::query_as::<_, AuditCheckpoint>(
+        r#"
+        INSERT INTO audit_checkpoints (organization_id, last_seq, last_hash, key_id, signature)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (organization_id, last_seq) DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(organization_id)
+    .bind(last_seq)
+    .bind(&last_hash)
+    .bind(key_id(key))
+    .bind(hex::encode(signature.to_bytes()))
+    .fetch_optional(pool)
+    .await
+}
+
+/// Encadena las entradas pendientes de todas las organizaciones (tarea en segundo plano).
+pub async fn link_pending(pool: &PgPool) {
+    loop {
+        match sqlx::query_scalar::<_, i32>("SELECT fn_audit_chain_link_pending($1)")
+            .bind(LINK_BATCH)
+            .fetch_one(pool)
+            .await
+        {
+            // Un lote completo indica que puede quedar trabajo pendiente
+            Ok(linked) if linked >= LINK_BATCH => continue,
+            Ok(_) => return,
+            Err(e) => {
+                tracing::error!("Error al encadenar el registro de auditoría: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Checkpoint de todas las cadenas de organización (tarea en segundo plano).
+pub async fn checkpoint_all(pool: &PgPool) {
+    let Some(key) = signing_key() else {
+        tracing::warn!("AUDIT_SIGNING_KEY no configurada; se omiten los checkpoints de auditoría");
+        return;
+    };
+
+    let orgs: Vec<Uuid> = match sqlx::query_scalar(
+        "SELECT chain_key FROM audit_chain_heads WHERE chain_key <> '00000000-0000-0000-0000-000000000000'",
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(orgs) => orgs,
+        Err(e) => {
+            tracing::error!("Error al leer las cadenas de auditoría: {}", e);
+            return;
+        }
+    };
+
+    for org_id in orgs {
+        if let Err(e) = create_checkpoint(pool, &key, org_id).await {
+            tracing::error!("Error al crear el checkpoint de auditoría de {}: {}", org_id, e);
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChainBreak {
+    pub chain_seq: i64,
+    pub audit_log_id: Option<Uuid>,
+    /// `gap`, `broken_link`, `hash_mismatch`, `checkpoint_mismatch`, `bad_signature` o `truncated`
+    pub kind: &'static str,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChainReport {
+    pub organization_id: Uuid,
+    pub valid: bool,
+    pub entries_checked: i64,
+    pub last_seq: i64,
+    pub last_hash: Option<String>,
+    pub archived_through_seq: i64,
+    pub checkpoints_checked: usize,
+    pub checkpoints_unverified: usize,
+    pub breaks: Vec<ChainBreak>,
+    pub breaks_truncated: bool,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ChainRow {
+    id: Uuid,
+    chain_seq: i64,
+    prev_hash: String,
+    entry_hash: String,
+    payload: String,
+}
+
+/// Posición del recorrido de la cadena: la siguiente secuencia y el hash que debe
+/// enlazar.
+struct ChainCursor {
+    expected_seq: i64,
+    prev_hash: String,
+}
+
+impl ChainCursor {
+    /// Compara la fila con la anterior y recalcula su hash; avanza a la fila.
+    fn check(&mut self, row: &ChainRow, breaks: &mut Vec<ChainBreak>) {
+        if row.chain_seq != self.expected_seq {
+            breaks.push(ChainBreak {
+                chain_seq: self.expected_seq,
+                audit_log_id: None,
+                kind: "gap",
+                detail: format!("Faltan las entradas {} a {}", self.expected_seq, row.chain_seq - 1),
+            });
+        }
+        if row.prev_hash != self.prev_hash {
+            breaks.push(ChainBreak {
+                chain_seq: row.chain_seq,
+                audit_log_id: Some(row.id),
+                kind: "broken_link",
+                detail: "prev_hash no coincide con la entrada anterior".to_string(),
+            });
+        }
+        if entry_hash(&row.prev_hash, &row.payload) != row.entry_hash {
+            breaks.push(ChainBreak {
+                chain_seq: row.chain_seq,
+                audit_log_id: Some(row.id),
+                kind: "hash_mismatch",
+                detail: "El contenido de la entrada fue modificado".to_string(),
+            });
+        }
+        self.expected_seq = row.chain_seq + 1;
+        self.prev_hash.clone_from(&row.entry_hash);
+    }
+}
+
+/// Recorre la cadena de la organización y compara cada enlace, hash y checkpoint.
+pub async fn verify_chain(pool: &PgPool, organization_id: Uuid) -> Result<ChainReport, sqlx::Error> {
+    let checkpoints = sqlx::query_as::<_, AuditCheckpoint>(
+        "SELECT * FROM audit_checkpoints WHERE organization_id = $1 ORDER BY last_seq",
+    )
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut breaks = Vec::new();
+    let key = signing_key();
+    let mut unverified = 0;
+    let mut pending: HashMap<i64, Vec<&AuditCheckpoint>> = HashMap::new();
+    for cp in &checkpoints {
+        match &key {
+            Some(key) if key_id(key) == cp.key_id => {
+                let valid = hex::decode(&cp.signature)
+                    .ok()
+                    .and_then(|bytes| Signature::from_slice(&bytes).ok())
+                    .is_some_and(|sig| {
+                        let message = checkpoint_message(organization_id, cp.last_seq, &cp.last_hash);
+                        key.verifying_key().verify(message.as_bytes(), &sig).is_ok()
+                    });
+                if !valid {
+                    breaks.push(ChainBreak {
+                        chain_seq: cp.last_seq,
+                        audit_log_id: None,
+                        kind: "bad_signature",
+                        detail: format!("Firma inválida en el checkpoint {}", cp.id),
+                    });
+                }
+            }
+            _ => unverified += 1,
+        }
+        pending.entry(cp.last_seq).or_default().push(cp);
+    }
+
+    // Las entradas archivadas por retención ya no están en línea: la cadena sigue
+    // desde el último hash archivado
+    let (archived_through_seq, archived_hash) = crate::audit_retention::archived_head(pool, organization_id)
+        .await?
+        .unwrap_or((0, "0".repeat(64)));
+    pending.retain(|seq, cps| {
+        if *seq > archived_through_seq {
+            return true;
+        }
+        if *seq == archived_through_seq {
+            for cp in cps.iter().filter(|cp| cp.last_hash != archived_hash) {
+                breaks.push(ChainBreak {
+                    chain_seq: *seq,
+                    audit_log_id: None,
+                    kind: "checkpoint_mismatch",
+                    detail: format!("No coincide con el checkpoint {}", cp.id),
+                });
+            }
+        }
+        false
+    });
+
+    let mut cursor = ChainCursor {
+        expected_seq: archived_through_seq + 1,
+        prev_hash: archived_hash,
+    };
+    let mut entries_checked = 0;
+    let mut last_seq = archived_through_seq;
+
+    loop {
+        let rows = sqlx::query_as::<_, ChainRow>(
+            r#"
+            SELECT a.id, a.chain_seq, a.prev_hash, a.entry_hash, fn_audit_entry_payload(a) AS payload
+            FROM audit_logs a
+            WHERE a.organization_id = $1 AND a.chain_seq > $2
+            ORDER BY a.chain_seq
+            LIMIT $3
+            "#,
+        )
+        .bind(organization_id)
+        .bind(last_seq)
+        .bind(VERIFY_BATCH)
+        .fetch_all(pool)
+        .await?;
+
+        if rows.is_empty() {
+            break;
+        }
+
+        for row in rows {
+            cursor.check(&row, &mut breaks);
+            for cp in pending.remove(&row.chain_seq).unwrap_or_default() {
+                if cp.last_hash != row.entry_hash {
+                    breaks.push(ChainBreak {
+                        chain_seq: row.chain_seq,
+                        audit_log_id: Some(row.id),
+                        kind: "checkpoint_mismatch",
+                        detail: format!("No coincide con el checkpoint {}", cp.id),
+                    });
+                }
+            }
+
+            last_seq = row.chain_seq;
+            entries_checked += 1;
+        }
+    }
+
+    let mut missing: Vec<i64> = pending.into_keys().collect();
+    missing.sort_unstable();
+    for seq in missing {
+        breaks.push(ChainBreak {
+            chain_seq: seq,
+            audit_log_id: None,
+            kind: "truncated",
+            detail: "Un checkpoint firmado apunta a una entrada que ya no existe".to_string(),
+        });
+    }
+
+    breaks.sort_by_key(|b| b.chain_seq);
+    let breaks_truncated = breaks.len() > MAX_REPORTED_BREAKS;
+    breaks.truncate(MAX_REPORTED_BREAKS);
+
+    Ok(ChainReport {
+        organization_id,
+        valid: breaks.is_empty(),
+        entries_checked,
+        last_seq,
+        last_hash: (last_seq > 0).then_some(cursor.prev_hash),
+        archived_through_seq,
+        checkpoints_checked: checkpoints.len(),
+        checkpoints_unverified: unverified,
+        breaks,
+        breaks_truncated,
+    })
+}
+
+fn require_admin(claims: &Claims) -> Result<(), (StatusCode, String)> {
+    if claims.role != "admin" {
+        return Err((StatusCode::FORBIDDEN, "Solo los administradores pueden auditar el registro".to_string()));
+    }
+    Ok(())
+}
+
+/// GET /audit-logs/verify - Recorre la cadena de la organización e informa las rupturas
+pub async fn verify_audit_chain(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+) -> Result<Json<ChainReport>, (StatusCode, String)> {
+    require_admin(&claims)?;
+
+    let report = verify_chain(&pool, org_ctx.id).await.map_err(|e| {
+        tracing::error!("Error al verificar la cadena de auditoría: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+    })?;
+
+    Ok(Json(report))
+}
+
+/// GET /audit-logs/checkpoints - Checkpoints firmados de la organización
+pub async fn list_audit_checkpoints(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+) -> Result<Json<Vec<AuditCheckpoint>>, (StatusCode, String)> {
+    require_admin(&claims)?;
+
+    let checkpoints = sqlx::query_as::<_, AuditCheckpoint>(
+        "SELECT * FROM audit_checkpoints WHERE organization_id = $1 ORDER BY last_seq DESC",
+    )
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(checkpoints))
+}
+
+/// POST /audit-logs/checkpoints - Firma la cabeza actual de la cadena
+pub async fn create_audit_checkpoint(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+) -> Result<(StatusCode, Json<Option<AuditCheckpoint>>), (StatusCode, String)> {
+    require_admin(&claims)?;
+
+    let key = signing_key().ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        "La firma de auditoría no está configurada".to_string(),
+    ))?;
+
+    let checkpoint = create_checkpoint(&pool, &key, org_ctx.id).await.map_err(|e| {
+        tracing::error!("Error al crear el checkpoint de auditoría: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+    })?;
+
+    let status = if checkpoint.is_some() { StatusCode::CREATED } else { StatusCode::OK };
+    Ok((status, Json(checkpoint)))
+}
+
+#[derive(Serialize)]
+struct ExportedCheckpoint {
+    #[serde(flatten)]
+    checkpoint: AuditCheckpoint,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct CheckpointExport {
+    organization_id: Uuid,
+    exported_at: DateTime<Utc>,
+    algorithm: &'static str,
+    public_key: Option<String>,
+    key_id: Option<String>,
+    message_format: &'static str,
+    hash_format: &'static str,
+    checkpoints: Vec<ExportedCheckpoint>,
+}
+
+/// GET /audit-logs/checkpoints/export - Documento JSON verificable fuera de la plataforma
+pub async fn export_audit_checkpoints(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin(&claims)?;
+
+    let checkpoints = sqlx::query_as::<_, AuditCheckpoint>(
+        "SELECT * FROM audit_checkpoints WHERE organization_id = $1 ORDER BY last_seq",
+    )
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let key = signing_key();
+    let export = CheckpointExport {
+        organization_id: org_ctx.id,
+        exported_at: Utc::now(),
+        algorithm: "ed25519",
+        public_key: key.as_ref().map(|k| hex::encode(k.verifying_key().as_bytes())),
+        key_id: key.as_ref().map(key_id),
+        message_format: "openccb-audit-checkpoint:v1:{organization_id}:{last_seq}:{last_hash}",
+        hash_format: "entry_hash = sha256(prev_hash || fn_audit_entry_payload(entry)), prev_hash de la primera entrada = 64 ceros",
+        checkpoints: checkpoints
+            .into_iter()
+            .map(|cp| ExportedCheckpoint {
+                message: checkpoint_message(cp.organization_id, cp.last_seq, &cp.last_hash),
+                checkpoint: cp,
+            })
+            .collect(),
+    };
+
+    let disposition = format!(
+        "attachment; filename=\"audit-checkpoints-{}.json\"",
+        org_ctx.id
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        Json(export),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+    fn chain(payloads: &[&str]) -> Vec<ChainRow> {
+        let mut prev = GENESIS.to_string();
+        payloads
+            .iter()
+            .zip(1..)
+            .map(|(payload, seq)| {
+                let hash = entry_hash(&prev, payload);
+                let row = ChainRow {
+                    id: Uuid::new_v4(),
+                    chain_seq: seq,
+                    prev_hash: prev.clone(),
+                    entry_hash: hash.clone(),
+                    payload: payload.to_string(),
+                };
+                prev = hash;
+                row
+            })
+            .collect()
+    }
+
+    fn verify(rows: &[ChainRow]) -> Vec<ChainBreak> {
+        let mut breaks = Vec::new();
+        let mut cursor = ChainCursor {
+            expected_seq: 1,
+            prev_hash: GENESIS.to_string(),
+        };
+        for row in rows {
+            cursor.check(row, &mut breaks);
+        }
+        breaks
+    }
+
+    #[test]
+    fn entry_hash_matches_sql_digest() {
+        // encode(sha256(convert_to(prev_hash || payload, 'UTF8')), 'hex') en PostgreSQL
+        let payload = r#"[1, "650f863e-240a-4e63-86a0-d6afa1f20352", "11111111-1111-1111-1111-111111111111", null, "a", "x", "2a04f5b9-5ab0-4dde-bc16-088585ef2b23", "USER_EVENT", null, null, null, null, null, null, {}, "2026-10-18T23:38:23.413331Z"]"#;
+        assert_eq!(
+            entry_hash(GENESIS, payload),
+            "7e9dd94ecfc726de377d7b2ce47ca28412b712b376ce5825e4e87d31a0d3ee32"
+        );
+        assert_eq!(
+            entry_hash(GENESIS, r#"[1, "Año académico", {"nota": 0.5}]"#),
+            "5f50c01116967ab4bf087f6c0eefd668c5f65a3e53823d85421c87b64a408df4"
+        );
+    }
+
+    #[test]
+    fn intact_chain_has_no_breaks() {
+        assert!(verify(&chain(&["[1]", "[2]", "[3]"])).is_empty());
+    }
+
+    #[test]
+    fn edited_entry_is_detected() {
+        let mut rows = chain(&["[1]", "[2]", "[3]"]);
+        rows[1].payload = "[2, \"editado\"]".to_string();
+
+        let breaks = verify(&rows);
+        assert_eq!(breaks.len(), 1);
+        assert_eq!((breaks[0].chain_seq, breaks[0].kind), (2, "hash_mismatch"));
+    }
+
+    #[test]
+    fn rehashed_entry_breaks_the_next_link() {
+        let mut rows = chain(&["[1]", "[2]", "[3]"]);
+        rows[1].payload = "[2, \"editado\"]".to_string();
+        rows[1].entry_hash = entry_hash(&rows[1].prev_hash, &rows[1].payload);
+
+        let breaks = verify(&rows);
+        assert_eq!(breaks.len(), 1);
+        assert_eq!((breaks[0].chain_seq, breaks[0].kind), (3, "broken_link"));
+    }
+
+    #[test]
+    fn deleted_entry_leaves_a_gap() {
+        let mut rows = chain(&["[1]", "[2]", "[3]"]);
+        rows.remove(1);
+
+        let kinds: Vec<_> = verify(&rows).iter().map(|b| (b.chain_seq, b.kind)).collect();
+        assert_eq!(kinds, vec![(2, "gap"), (3, "broken_link")]);
+    }
+}
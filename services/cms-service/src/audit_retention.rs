@@ -0,0 +1,397 @@
+/// Retención del registro de auditoría.
+///
+/// Cada organización puede fijar cuántos días conserva la auditoría en línea. El
+/// job diario archiva el prefijo de la cadena más antiguo que ese plazo en un JSONL
+/// comprimido (`AUDIT_ARCHIVE_DIR`, por defecto `uploads/audit-archives`), verifica
+/// los hashes antes de escribirlo y solo borra las filas una vez registrado el
+/// archivo en `audit_archives`; `audit_chain::verify_chain` continúa desde el último
+/// hash archivado. El job corre con el rol propietario: los roles de tenant no pueden
+/// escribir en `audit_archives` ni borrar de `audit_logs`.
+use axum::{
+    Json,
+    body::Body,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use common::{auth::Claims, middleware::Org};
+use flate2::{Compression, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::io::Write;
+use uuid::Uuid;
+
+use crate::audit_chain;
+
+const ARCHIVE_BATCH: i64 = 10_000;
+const MIN_RETENTION_DAYS: i32 = 30;
+
+fn archive_dir() -> String {
+    std::env::var("AUDIT_ARCHIVE_DIR").unwrap_or_else(|_| "uploads/audit-archives".to_string())
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct RetentionPolicy {
+    pub organization_id: Uuid,
+    pub retention_days: i32,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AuditArchive {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub from_seq: i64,
+    pub to_seq: i64,
+    pub first_prev_hash: String,
+    pub last_hash: String,
+    pub entry_count: i32,
+    pub oldest_at: DateTime<Utc>,
+    pub newest_at: DateTime<Utc>,
+    #[serde(skip)]
+    pub file_path: String,
+    pub file_sha256: String,
+    pub file_size: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Último tramo archivado de la cadena: (to_seq, last_hash).
+pub async fn archived_head(pool: &PgPool, organization_id: Uuid) -> Result<Option<(i64, String)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT to_seq, last_hash FROM audit_archives WHERE organization_id = $1 ORDER BY to_seq DESC LIMIT 1",
+    )
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await
+}
+
+#[derive(sqlx::FromRow)]
+struct ArchiveRow {
+    chain_seq: i64,
+    prev_hash: String,
+    entry_hash: String,
+    payload: String,
+    created_at: DateTime<Utc>,
+    row: Value,
+}
+
+/// Primera entrada del tramo que no continúa la cadena desde `(last_seq, last_hash)`.
+fn first_invalid_entry(rows: &[ArchiveRow], last_seq: i64, last_hash: &str) -> Option<i64> {
+    let mut prev = last_hash;
+    for (expected, row) in (last_seq + 1..).zip(rows.iter()) {
+        if row.chain_seq != expected
+            || row.prev_hash != prev
+            || audit_chain::entry_hash(&row.prev_hash, &row.payload) != row.entry_hash
+        {
+            return Some(row.chain_seq);
+        }
+        prev = &row.entry_hash;
+    }
+    None
+}
+
+/// Archiva un tramo de la cadena; devuelve `None` si no hay nada vencido.
+async fn archive_next_batch(
+    pool: &PgPool,
+    organization_id: Uuid,
+    cutoff: DateTime<Utc>,
+) -> anyhow::Result<Option<AuditArchive>> {
+    let (last_seq, last_hash) = archived_head(pool, organization_id)
+        .await?
+        .unwrap_or((0, "0".repeat(64)));
+
+    // Solo un prefijo contiguo: se detiene en la primera entrada no vencida
+    let rows = sqlx::query_as::<_, ArchiveRow>(
+        r#"
+        SELECT a.chain_seq, a.prev_hash, a.entry_hash, fn_audit_entry_payload(a) AS payload, a.created_at,
+               to_jsonb(a) || jsonb_build_object('payload', fn_audit_entry_payload(a)) AS row
+        FROM audit_logs a
+        WHERE a.organization_id = $1
+          AND a.chain_seq > $2
+          AND a.chain_seq < COALESCE(
+                (SELECT MIN(b.chain_seq) FROM audit_logs b
+                 WHERE b.organization_id = $1 AND b.chain_seq > $2 AND b.created_at >= $3),
+                9223372036854775807)
+        ORDER BY a.chain_seq
+        LIMIT $4
+        "#,
+    )
+    .bind(organization_id)
+    .bind(last_seq)
+    .bind(cutoff)
+    .bind(ARCHIVE_BATCH)
+    .fetch_all(pool)
+    .await?;
+
+    let (Some(first), Some(last)) = (rows.first(), rows.last()) else {
+        return Ok(None);
+    };
+
+    // No se archiva una cadena rota: el archivo ocultaría la manipulación
+    if let Some(seq) = first_invalid_entry(&rows, last_seq, &last_hash) {
+        anyhow::bail!("cadena de auditoría inválida en la entrada {}", seq);
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    for row in &rows {
+        serde_json::to_writer(&mut encoder, &row.row)?;
+        encoder.write_all(b"\n")?;
+    }
+    let bytes = encoder.finish()?;
+    let file_sha256 = hex::encode(Sha256::digest(&bytes));
+
+    let dir = format!("{}/{}", archive_dir(), organization_id);
+    tokio::fs::create_dir_all(&dir).await?;
+    let file_path = format!("{}/{:012}-{:012}.jsonl.gz", dir, first.chain_seq, last.chain_seq);
+    tokio::fs::write(&file_path, &bytes).await?;
+
+    let mut tx = pool.begin().await?;
+    let archive = sqlx::query_as::<_, AuditArchive>(
+        r#"
+        INSERT INTO audit_archives (organization_id, from_seq, to_seq, first_prev_hash, last_hash, entry_count,
+                                    oldest_at, newest_at, file_path, file_sha256, file_size)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        RETURNING *
+        "#,
+    )
+    .bind(organization_id)
+    .bind(first.chain_seq)
+    .bind(last.chain_seq)
+    .bind(&first.prev_hash)
+    .bind(&last.entry_hash)
+    .bind(rows.len() as i32)
+    .bind(first.created_at)
+    .bind(last.created_at)
+    .bind(&file_path)
+    .bind(&file_sha256)
+    .bind(bytes.len() as i64)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query("SELECT set_config('app.audit_archive_id', $1, true)")
+        .bind(archive.id.to_string())
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM audit_logs WHERE organization_id = $1 AND chain_seq BETWEEN $2 AND $3")
+        .bind(organization_id)
+        .bind(archive.from_seq)
+        .bind(archive.to_seq)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Some(archive))
+}
+
+/// Aplica la política de retención de todas las organizaciones (tarea diaria).
+pub async fn apply_retention(pool: &PgPool) {
+    let policies = match sqlx::query_as::<_, RetentionPolicy>("SELECT * FROM audit_retention_policies")
+        .fetch_all(pool)
+        .await
+    {
+        Ok(policies) => policies,
+        Err(e) => {
+            tracing::error!("Error al leer las políticas de retención de auditoría: {}", e);
+            return;
+        }
+    };
+
+    for policy in policies {
+        let cutoff = Utc::now() - Duration::days(policy.retention_days as i64);
+        loop {
+            match archive_next_batch(pool, policy.organization_id, cutoff).await {
+                Ok(Some(archive)) => tracing::info!(
+                    "Auditoría de {} archivada: entradas {}-{}",
+                    policy.organization_id,
+                    archive.from_seq,
+                    archive.to_seq
+                ),
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::error!("Error al archivar la auditoría de {}: {}", policy.organization_id, e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn require_admin(claims: &Claims) -> Result<(), (StatusCode, String)> {
+    if claims.role != "admin" {
+        return Err((StatusCode::FORBIDDEN, "Solo los administradores pueden gestionar la auditoría".to_string()));
+    }
+    Ok(())
+}
+
+/// GET /audit-logs/retention - Política de retención de la organización
+pub async fn get_retention_policy(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+) -> Result<Json<Option<RetentionPolicy>>, (StatusCode, String)> {
+    require_admin(&claims)?;
+
+    let policy = sqlx::query_as::<_, RetentionPolicy>(
+        "SELECT * FROM audit_retention_policies WHERE organization_id = $1",
+    )
+    .bind(org_ctx.id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(policy))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RetentionPayload {
+    /// `null` desactiva la retención (la auditoría se conserva en línea indefinidamente)
+    pub retention_days: Option<i32>,
+}
+
+/// PUT /audit-logs/retention - Fija o desactiva la retención
+pub async fn update_retention_policy(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Json(payload): Json<RetentionPayload>,
+) -> Result<Json<Option<RetentionPolicy>>, (StatusCode, String)> {
+    require_admin(&claims)?;
+
+    let Some(days) = payload.retention_days else {
+        sqlx::query("DELETE FROM audit_retention_policies WHERE organization_id = $1")
+            .bind(org_ctx.id)
+            .execute(&pool)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+        return Ok(Json(None));
+    };
+
+    if days < MIN_RETENTION_DAYS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("La retención mínima es de {} días", MIN_RETENTION_DAYS),
+        ));
+    }
+
+    let policy = sqlx::query_as::<_, RetentionPolicy>(
+        r#"
+        INSERT INTO audit_retention_policies (organization_id, retention_days, updated_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (organization_id) DO UPDATE
+        SET retention_days = EXCLUDED.retention_days, updated_by = EXCLUDED.updated_by, updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(org_ctx.id)
+    .bind(days)
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(Some(policy)))
+}
+
+/// GET /audit-logs/archives - Archivos comprimidos de la organización
+pub async fn list_audit_archives(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+) -> Result<Json<Vec<AuditArchive>>, (StatusCode, String)> {
+    require_admin(&claims)?;
+
+    let archives = sqlx::query_as::<_, AuditArchive>(
+        "SELECT * FROM audit_archives WHERE organization_id = $1 ORDER BY from_seq DESC",
+    )
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(archives))
+}
+
+/// GET /audit-logs/archives/{id}/download - Descarga un archivo `.jsonl.gz`
+pub async fn download_audit_archive(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin(&claims)?;
+
+    let archive = sqlx::query_as::<_, AuditArchive>(
+        "SELECT * FROM audit_archives WHERE id = $1 AND organization_id = $2",
+    )
+    .bind(id)
+    .bind(org_ctx.id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Archivo de auditoría no encontrado".to_string()))?;
+
+    let bytes = tokio::fs::read(&archive.file_path).await.map_err(|e| {
+        tracing::error!("No se pudo leer el archivo de auditoría {}: {}", archive.file_path, e);
+        (StatusCode::NOT_FOUND, "El archivo de auditoría no está disponible".to_string())
+    })?;
+
+    let disposition = format!(
+        "attachment; filename=\"audit-{}-{}-{}.jsonl.gz\"",
+        archive.organization_id, archive.from_seq, archive.to_seq
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/gzip".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+            (header::HeaderName::from_static("x-content-sha256"), archive.file_sha256),
+        ],
+        Body::from(bytes),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows_from(last_seq: i64, last_hash: &str, payloads: &[&str]) -> Vec<ArchiveRow> {
+        let mut prev = last_hash.to_string();
+        payloads
+            .iter()
+            .zip(last_seq + 1..)
+            .map(|(payload, seq)| {
+                let hash = audit_chain::entry_hash(&prev, payload);
+                ArchiveRow {
+                    chain_seq: seq,
+                    prev_hash: std::mem::replace(&mut prev, hash.clone()),
+                    entry_hash: hash,
+                    payload: payload.to_string(),
+                    created_at: Utc::now(),
+                    row: Value::Null,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn batch_continues_from_the_archived_head() {
+        let head = "ab".repeat(32);
+        let rows = rows_from(40, &head, &["[41]", "[42]"]);
+        assert_eq!(first_invalid_entry(&rows, 40, &head), None);
+        // Un tramo que no empieza en el último hash archivado no se archiva
+        assert_eq!(first_invalid_entry(&rows, 40, &"0".repeat(64)), Some(41));
+        assert_eq!(first_invalid_entry(&rows, 39, &head), Some(41));
+    }
+
+    #[test]
+    fn tampered_batch_is_not_archived() {
+        let head = "0".repeat(64);
+        let mut rows = rows_from(0, &head, &["[1]", "[2]", "[3]"]);
+        rows[2].payload = "[3, \"editado\"]".to_string();
+        assert_eq!(first_invalid_entry(&rows, 0, &head), Some(3));
+    }
+}
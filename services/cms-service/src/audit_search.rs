@@ -0,0 +1,358 @@
+/// Búsqueda y exportación del registro de auditoría.
+///
+/// Los filtros (actor, tipo y ID de entidad, acción, rango de fechas, IP o red CIDR)
+/// se comparten entre la búsqueda paginada por cursor (`chain_seq` descendente) y la
+/// exportación en streaming a CSV o JSONL. Cada entrada incluye un diff campo a
+/// campo armado desde `old_data`/`new_data` o, en las entradas de `log_action`,
+/// desde `changes`. Las entradas recién insertadas aparecen cuando la tarea de
+/// `audit_chain::link_pending` les asigna su `chain_seq`.
+use axum::{
+    Json,
+    body::{Body, Bytes},
+    extract::{Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use common::{auth::Claims, middleware::Org, tenancy};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use std::collections::BTreeSet;
+use uuid::Uuid;
+
+const EXPORT_BATCH: i64 = 500;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuditFilter {
+    pub user_id: Option<Uuid>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<Uuid>,
+    pub action: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Dirección exacta o red CIDR (`10.0.0.0/8`)
+    pub ip: Option<String>,
+    pub cursor: Option<i64>,
+    pub limit: Option<i64>,
+    /// Solo exportación: `csv` (por defecto) o `jsonl`
+    pub format: Option<String>,
+}
+
+impl AuditFilter {
+    fn validate(&self) -> Result<(), (StatusCode, String)> {
+        if let Some(ip) = &self.ip {
+            let (addr, prefix) = ip.split_once('/').unwrap_or((ip, ""));
+            let addr_ok = addr.parse::<std::net::IpAddr>().is_ok();
+            let prefix_ok = prefix.is_empty() || prefix.parse::<u8>().is_ok_and(|p| p <= 128);
+            if !addr_ok || !prefix_ok {
+                return Err((StatusCode::BAD_REQUEST, "Filtro de IP inválido".to_string()));
+            }
+        }
+        if let (Some(from), Some(to)) = (self.from, self.to)
+            && from > to
+        {
+            return Err((StatusCode::BAD_REQUEST, "El rango de fechas es inválido".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AuditLogEntry {
+    pub id: Uuid,
+    pub chain_seq: i64,
+    pub user_id: Option<Uuid>,
+    pub user_full_name: Option<String>,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: Uuid,
+    pub event_type: Option<String>,
+    pub changes: Option<Value>,
+    #[serde(skip)]
+    pub old_data: Option<Value>,
+    #[serde(skip)]
+    pub new_data: Option<Value>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub entry_hash: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditLogView {
+    #[serde(flatten)]
+    pub entry: AuditLogEntry,
+    pub diff: Vec<FieldChange>,
+}
+
+impl From<AuditLogEntry> for AuditLogView {
+    fn from(entry: AuditLogEntry) -> Self {
+        let diff = render_diff(&entry);
+        AuditLogView { entry, diff }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditLogPage {
+    pub entries: Vec<AuditLogView>,
+    pub next_cursor: Option<i64>,
+}
+
+/// Diff campo a campo. Las filas de los triggers traen `old_data`/`new_data`; en las
+/// de `log_action` se acepta `changes` con forma `{before, after}` o `{old, new}`, y
+/// cualquier otro objeto se muestra como valores nuevos.
+pub fn render_diff(entry: &AuditLogEntry) -> Vec<FieldChange> {
+    let (before, after) = match (&entry.old_data, &entry.new_data, &entry.changes) {
+        (None, None, Some(Value::Object(changes))) => {
+            match (
+                changes.get("before").or_else(|| changes.get("old")),
+                changes.get("after").or_else(|| changes.get("new")),
+            ) {
+                (Some(b), Some(a)) => (Some(b), Some(a)),
+                _ => (None, entry.changes.as_ref()),
+            }
+        }
+        (old, new, _) => (old.as_ref(), new.as_ref()),
+    };
+
+    let empty = serde_json::Map::new();
+    let before_obj = before.and_then(Value::as_object).unwrap_or(&empty);
+    let after_obj = after.and_then(Value::as_object).unwrap_or(&empty);
+    let fields: BTreeSet<&String> = before_obj.keys().chain(after_obj.keys()).collect();
+
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let b = before_obj.get(field);
+            let a = after_obj.get(field);
+            (b != a).then(|| FieldChange {
+                field: field.clone(),
+                before: b.cloned(),
+                after: a.cloned(),
+            })
+        })
+        .collect()
+}
+
+fn filtered_query<'a>(org_id: Uuid, filter: &'a AuditFilter, limit: i64) -> QueryBuilder<'a, Postgres> {
+    let mut qb = QueryBuilder::new(
+        r#"
+        SELECT a.id, a.chain_seq, a.user_id, u.full_name AS user_full_name, a.action, a.entity_type,
+               a.entity_id, a.event_type, a.changes, a.old_data, a.new_data,
+               host(a.ip_address) AS ip_address, a.user_agent, a.entry_hash, a.created_at
+        FROM audit_logs a
+        LEFT JOIN users u ON u.id = a.user_id
+        WHERE a.chain_seq IS NOT NULL AND a.organization_id = "#,
+    );
+    qb.push_bind(org_id);
+
+    if let Some(user_id) = filter.user_id {
+        qb.push(" AND a.user_id = ").push_bind(user_id);
+    }
+    if let Some(entity_type) = &filter.entity_type {
+        qb.push(" AND a.entity_type = ").push_bind(entity_type);
+    }
+    if let Some(entity_id) = filter.entity_id {
+        qb.push(" AND a.entity_id = ").push_bind(entity_id);
+    }
+    if let Some(action) = &filter.action {
+        qb.push(" AND a.action = ").push_bind(action);
+    }
+    if let Some(from) = filter.from {
+        qb.push(" AND a.created_at >= ").push_bind(from);
+    }
+    if let Some(to) = filter.to {
+        qb.push(" AND a.created_at < ").push_bind(to);
+    }
+    if let Some(ip) = &filter.ip {
+        qb.push(" AND (a.ip_address <<= ")
+            .push_bind(ip)
+            .push("::inet OR a.public_ip <<= ")
+            .push_bind(ip)
+            .push("::inet)");
+    }
+    if let Some(cursor) = filter.cursor {
+        qb.push(" AND a.chain_seq < ").push_bind(cursor);
+    }
+
+    qb.push(" ORDER BY a.chain_seq DESC LIMIT ").push_bind(limit);
+    qb
+}
+
+async fn fetch_page(
+    pool: &PgPool,
+    org_id: Uuid,
+    filter: &AuditFilter,
+    limit: i64,
+) -> Result<Vec<AuditLogEntry>, sqlx::Error> {
+    filtered_query(org_id, filter, limit)
+        .build_query_as::<AuditLogEntry>()
+        .fetch_all(pool)
+        .await
+}
+
+fn require_admin(claims: &Claims) -> Result<(), (StatusCode, String)> {
+    if claims.role != "admin" {
+        return Err((StatusCode::FORBIDDEN, "Solo los administradores pueden consultar la auditoría".to_string()));
+    }
+    Ok(())
+}
+
+/// GET /audit-logs/search - Búsqueda filtrada con paginación por cursor
+pub async fn search_audit_logs(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Query(filter): Query<AuditFilter>,
+) -> Result<Json<AuditLogPage>, (StatusCode, String)> {
+    require_admin(&claims)?;
+    filter.validate()?;
+
+    let limit = filter.limit.unwrap_or(50).clamp(1, 500);
+    let entries = fetch_page(&pool, org_ctx.id, &filter, limit).await.map_err(|e| {
+        tracing::error!("Error al buscar en la auditoría: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+    })?;
+
+    let next_cursor = (entries.len() as i64 == limit)
+        .then(|| entries.last().map(|e| e.chain_seq))
+        .flatten();
+
+    Ok(Json(AuditLogPage {
+        entries: entries.into_iter().map(AuditLogView::from).collect(),
+        next_cursor,
+    }))
+}
+
+const CSV_HEADER: [&str; 14] = [
+    "chain_seq",
+    "created_at",
+    "user_id",
+    "user_full_name",
+    "action",
+    "entity_type",
+    "entity_id",
+    "event_type",
+    "ip_address",
+    "user_agent",
+    "changes",
+    "diff",
+    "entry_hash",
+    "id",
+];
+
+fn csv_chunk(views: &[AuditLogView], with_header: bool) -> Result<Vec<u8>, csv::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    if with_header {
+        writer.write_record(CSV_HEADER)?;
+    }
+    for view in views {
+        let e = &view.entry;
+        let opt = |v: &Option<String>| v.clone().unwrap_or_default();
+        writer.write_record([
+            e.chain_seq.to_string(),
+            e.created_at.to_rfc3339(),
+            e.user_id.map(|id| id.to_string()).unwrap_or_default(),
+            opt(&e.user_full_name),
+            e.action.clone(),
+            e.entity_type.clone(),
+            e.entity_id.to_string(),
+            opt(&e.event_type),
+            opt(&e.ip_address),
+            opt(&e.user_agent),
+            e.changes.as_ref().map(Value::to_string).unwrap_or_default(),
+            serde_json::to_string(&view.diff).unwrap_or_default(),
+            opt(&e.entry_hash),
+            e.id.to_string(),
+        ])?;
+    }
+    writer.into_inner().map_err(|e| e.into_error().into())
+}
+
+fn jsonl_chunk(views: &[AuditLogView]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for view in views {
+        if let Ok(line) = serde_json::to_vec(view) {
+            out.extend_from_slice(&line);
+            out.push(b'\n');
+        }
+    }
+    out
+}
+
+/// GET /audit-logs/export?format=csv|jsonl - Exportación en streaming con los mismos filtros
+pub async fn export_audit_logs(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Query(mut filter): Query<AuditFilter>,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin(&claims)?;
+    filter.validate()?;
+
+    let jsonl = match filter.format.as_deref().unwrap_or("csv") {
+        "csv" => false,
+        "jsonl" => true,
+        _ => return Err((StatusCode::BAD_REQUEST, "Formato no soportado (csv o jsonl)".to_string())),
+    };
+
+    let (tx, rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(4);
+    let org_id = org_ctx.id;
+    // La tarea conserva el ámbito RLS de la petición
+    let scope = tenancy::current_scope();
+    tokio::spawn(tenancy::with_scope(scope, async move {
+        let mut first = true;
+        loop {
+            let entries = match fetch_page(&pool, org_id, &filter, EXPORT_BATCH).await {
+                Ok(entries) => entries,
+                Err(e) => {
+                    tracing::error!("Error al exportar la auditoría: {}", e);
+                    let _ = tx.send(Err(std::io::Error::other("Error al leer la auditoría"))).await;
+                    return;
+                }
+            };
+            let done = (entries.len() as i64) < EXPORT_BATCH;
+            filter.cursor = entries.last().map(|e| e.chain_seq).or(filter.cursor);
+
+            let views: Vec<AuditLogView> = entries.into_iter().map(AuditLogView::from).collect();
+            let chunk = if jsonl {
+                Ok(jsonl_chunk(&views))
+            } else {
+                csv_chunk(&views, first).map_err(std::io::Error::other)
+            };
+            first = false;
+
+            if tx.send(chunk.map(Bytes::from)).await.is_err() || done {
+                return;
+            }
+        }
+    }));
+
+    let (content_type, extension) = if jsonl {
+        ("application/x-ndjson", "jsonl")
+    } else {
+        ("text/csv; charset=utf-8", "csv")
+    };
+    let disposition = format!(
+        "attachment; filename=\"audit-logs-{}.{}\"",
+        Utc::now().format("%Y%m%d%H%M%S"),
+        extension
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx)),
+    )
+        .into_response())
+}
@@ -64,7 +64,7 @@ pub struct PublishPayload {
     pub target_organization_id: Option<Uuid>,
 }
 
-fn get_ai_url(var_base: &str, default: &str) -> String {
+pub(crate) fn get_ai_url(var_base: &str, default: &str) -> String {
     let env = env::var("ENVIRONMENT").unwrap_or_else(|_| "prod".to_string());
     if env == "dev" {
         env::var(format!("DEV_{}", var_base))
@@ -268,7 +268,7 @@ pub async fn publish_course(
 
     // 5. Obtener lecciones de cada módulo
     for module in modules {
-        let lessons = sqlx::query_as::<_, Lesson>(
+        let mut lessons = sqlx::query_as::<_, Lesson>(
             "SELECT * FROM lessons WHERE module_id = $1 ORDER BY position",
         )
         .bind(module.id)
@@ -276,6 +276,16 @@ pub async fn publish_course(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+        // Instantánea de los pools de preguntas del banco para el LMS
+        for lesson in &mut lessons {
+            crate::handlers_question_bank::resolve_lesson_question_pools(&pool, course.organization_id, lesson)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Error al resolver los pools de preguntas de la lección {}: {}", lesson.id, e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+        }
+
         pub_modules.push(PublishedModule { module, lessons });
     }
 
@@ -294,13 +304,44 @@ pub async fn publish_course(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    // 6. Dependencias y ramas adaptativas entre lecciones del curso
+    let dependencies = sqlx::query_as::<_, common::models::LessonDependency>(
+        r#"
+        SELECT ld.*
+        FROM lesson_dependencies ld
+        JOIN lessons l ON ld.lesson_id = l.id
+        JOIN modules m ON l.module_id = m.id
+        WHERE m.course_id = $1
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // 7. Resultados de aprendizaje alineados con el contenido publicado
+    let mut question_ids = std::collections::HashSet::new();
+    for lesson in pub_modules.iter().flat_map(|m| &m.lessons) {
+        for value in [&lesson.metadata, &lesson.content_blocks].into_iter().flatten() {
+            crate::handlers_outcomes::collect_question_bank_ids(value, &mut question_ids);
+        }
+    }
+    let question_ids: Vec<Uuid> = question_ids.into_iter().collect();
+    let outcomes = crate::handlers_outcomes::course_outcomes_snapshot(&pool, course.organization_id, id, &question_ids)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error al obtener los resultados de aprendizaje del curso: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
     let payload = PublishedCourse {
         course: course_for_pub,
         organization,
         grading_categories,
         modules: pub_modules,
         instructors: Some(instructors),
-        dependencies: None,
+        dependencies: Some(dependencies),
+        outcomes: Some(outcomes),
     };
 
     // 4. Enviar al LMS
@@ -845,7 +886,7 @@ async fn trigger_transcription(pool: PgPool, lesson_id: Uuid) {
         .await;
 
     // Iniciar tarea en segundo plano
-    tokio::spawn(async move {
+    common::tenancy::spawn(async move {
         if let Err(e) = run_transcription_task(pool, lesson_id).await {
             tracing::error!("La tarea de autotranscripción falló para la lección {}: {}", lesson_id, e);
         }
@@ -908,7 +949,7 @@ pub async fn process_transcription(
 
     // 4. Iniciar tarea en segundo plano
     let pool_clone = pool.clone();
-    tokio::spawn(async move {
+    common::tenancy::spawn(async move {
         if let Err(e) = run_transcription_task(pool_clone, id).await {
             tracing::error!("La tarea de transcripción falló para la lección {}: {}", id, e);
         }
@@ -917,11 +958,11 @@ pub async fn process_transcription(
     Ok(Json(updated_lesson))
 }
 
-async fn translate_text(text: &str, target_lang: &str) -> Result<String, String> {
+/// Endpoint, cabecera de autorización y modelo de chat según `AI_PROVIDER`.
+pub(crate) fn chat_completion_endpoint() -> Result<(String, String, String), String> {
     let provider = env::var("AI_PROVIDER").unwrap_or_else(|_| "openai".to_string());
-    let client = reqwest::Client::new();
 
-    let (url, auth_header, model) = if provider == "local" {
+    Ok(if provider == "local" {
         let base_url = get_ai_url("OLLAMA_URL", "http://localhost:11434");
         let model = env::var("LOCAL_LLM_MODEL").unwrap_or_else(|_| "llama3".to_string());
         (
@@ -936,7 +977,12 @@ async fn translate_text(text: &str, target_lang: &str) -> Result<String, String>
             format!("Bearer {}", api_key),
             "gpt-4o".to_string(),
         )
-    };
+    })
+}
+
+pub(crate) async fn translate_text(text: &str, target_lang: &str) -> Result<String, String> {
+    let client = reqwest::Client::new();
+    let (url, auth_header, model) = chat_completion_endpoint()?;
 
     let prompt = format!(
         "Translate the following transcription into {}. Maintain the same tone and context. Only return the translated text, nothing else.\n\nText: {}",
@@ -1021,44 +1067,28 @@ pub async fn run_transcription_task(pool: PgPool, lesson_id: Uuid) -> Result<(),
             err
         })?;
     
-    tracing::info!("File read successfully ({} bytes). Sending to Whisper...", file_data.len());
+    tracing::info!("File read successfully ({} bytes). Sending to speech-to-text...", file_data.len());
 
-    // 4. Send to Whisper
-    let whisper_url = get_ai_url("WHISPER_URL", "http://localhost:8000");
-    let client = reqwest::Client::new();
-    
-    // We assume a standard Whisper API (like faster-whisper-server or openai-compatible)
-    let form = reqwest::multipart::Form::new()
-        .part("file", reqwest::multipart::Part::bytes(file_data).file_name(filename_for_whisper))
-        .text("model", "whisper-1")
-        .text("response_format", "json");
-
-    let response = client.post(format!("{}/v1/audio/transcriptions", whisper_url))
-        .multipart(form)
-        .send()
+    // 4. Transcribe with the configured STT backend (chunked for long media)
+    let stt_config = crate::stt::SttConfig::from_env()?;
+    let transcript = crate::stt::transcribe(&stt_config, file_data, &filename_for_whisper)
         .await
         .map_err(|e| {
-            let err = format!("Whisper request failed: {}", e);
-            tracing::error!("{}", err);
-            err
+            tracing::error!("{}", e);
+            e
         })?;
+    let mut transcription_result = transcript.to_json(stt_config.backend);
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let err_body = response.text().await.unwrap_or_default();
-        let err = format!("Whisper API error: {} - {}", status, err_body);
-        tracing::error!("{}", err);
-        return Err(err);
-    }
-
-    let mut transcription_result: serde_json::Value = response.json().await
-        .map_err(|e| format!("Failed to parse Whisper response: {}", e))?;
-
-    tracing::info!("Transcription received successfully for lesson {}", lesson_id);
+    tracing::info!(
+        "Transcription received successfully for lesson {} ({} words, backend {})",
+        lesson_id,
+        transcript.words.len(),
+        stt_config.backend.as_str()
+    );
 
     // 5. Bilingual translation with Ollama
     let text = transcription_result["text"].as_str().unwrap_or("").to_string();
-    let detected_lang = transcription_result["language"].as_str().unwrap_or("es").to_string();
+    let detected_lang = transcript.language.clone().unwrap_or_else(|| "es".to_string());
 
     // Ensure the detected language text is stored in its own key
     transcription_result[detected_lang.clone()] = serde_json::json!(text);
@@ -1171,67 +1201,39 @@ async fn generate_summary_with_ollama(text: &str, lesson_id: Uuid, pool: &PgPool
     Ok((summary, input_tokens, output_tokens))
 }
 
+/// Subtítulos WebVTT; `?lang=` elige una pista aprobada (por defecto, el idioma original).
 pub async fn get_lesson_vtt(
     Org(org_ctx): Org,
     State(pool): State<PgPool>,
     Path(id): Path<Uuid>,
-    Query(params): Query<serde_json::Value>,
+    Query(query): Query<crate::handlers_subtitles::SubtitleQuery>,
 ) -> Result<(axum::http::HeaderMap, String), StatusCode> {
-    let lesson =
-        sqlx::query_as::<_, Lesson>("SELECT * FROM lessons WHERE id = $1 AND organization_id = $2")
-            .bind(id)
-            .bind(org_ctx.id)
-            .fetch_one(&pool)
-            .await
-            .map_err(|_| StatusCode::NOT_FOUND)?;
-
-    let lang = params.get("lang").and_then(|v| v.as_str()).unwrap_or("en");
-
-    let transcription = lesson.transcription.ok_or(StatusCode::NOT_FOUND)?;
-    let cues = transcription["cues"]
-        .as_array()
-        .ok_or(StatusCode::NOT_FOUND)?;
-
-    let mut vtt = String::from("WEBVTT\n\n");
-
-    for (index, cue) in cues.iter().enumerate() {
-        let start = cue["start"].as_f64().unwrap_or(0.0);
-        let end = cue["end"].as_f64().unwrap_or(0.0);
-        let text = if lang == "es" && !transcription["es"].as_str().unwrap_or("").is_empty() {
-            // Simplified: in a real scenario we might want translated cues
-            // For now, if we have a full translation we could try to split it,
-            // but usually Whisper gives us segments.
-            // If we only have English segments, we'll use them.
-            cue["text"].as_str().unwrap_or("")
-        } else {
-            cue["text"].as_str().unwrap_or("")
-        };
-
-        vtt.push_str(&format!("{}\n", index + 1));
-        vtt.push_str(&format!(
-            "{} --> {}\n",
-            format_vtt_timestamp(start),
-            format_vtt_timestamp(end)
-        ));
-        vtt.push_str(&format!("{}\n\n", text.trim()));
-    }
+    let cues = crate::handlers_subtitles::resolve_cues(&pool, org_ctx.id, id, query.lang.as_deref()).await?;
 
     let mut headers = axum::http::HeaderMap::new();
     headers.insert(
         axum::http::header::CONTENT_TYPE,
-        "text/vtt".parse().unwrap(),
+        "text/vtt; charset=utf-8".parse().unwrap(),
     );
 
-    Ok((headers, vtt))
+    Ok((headers, crate::stt::to_webvtt(&cues)))
 }
 
-fn format_vtt_timestamp(seconds: f64) -> String {
-    let hours = (seconds / 3600.0).floor() as u32;
-    let mins = ((seconds % 3600.0) / 60.0).floor() as u32;
-    let secs = (seconds % 60.0).floor() as u32;
-    let millis = ((seconds.fract() * 1000.0).round()) as u32;
+pub async fn get_lesson_srt(
+    Org(org_ctx): Org,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<crate::handlers_subtitles::SubtitleQuery>,
+) -> Result<(axum::http::HeaderMap, String), StatusCode> {
+    let cues = crate::handlers_subtitles::resolve_cues(&pool, org_ctx.id, id, query.lang.as_deref()).await?;
+
+    let mut headers = axum::http::HeaderMap::new();
+    headers.insert(
+        axum::http::header::CONTENT_TYPE,
+        "application/x-subrip; charset=utf-8".parse().unwrap(),
+    );
 
-    format!("{:02}:{:02}:{:02}.{:03}", hours, mins, secs, millis)
+    Ok((headers, crate::stt::to_srt(&cues)))
 }
 
 fn extract_filename_from_content_url(url: &str) -> String {
@@ -114,7 +114,7 @@ pub async fn retry_task(
     if let Some(l) = lesson {
         let pool_clone = pool.clone();
         if l.transcription_status.as_deref() == Some("failed") {
-            tokio::spawn(async move {
+            common::tenancy::spawn(async move {
                 let _ = sqlx::query("UPDATE lessons SET transcription_status = 'queued' WHERE id = $1").bind(id).execute(&pool_clone).await;
                 let _ = run_transcription_task(pool_clone, id).await;
             });
@@ -210,7 +210,7 @@ pub async fn retry_task(
         }
 
         let pool_clone = pool.clone();
-        tokio::spawn(async move {
+        common::tenancy::spawn(async move {
             let total = assets.len();
             let mut processed = 0usize;
             let mut failed = 0usize;
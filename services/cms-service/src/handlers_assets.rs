@@ -1792,7 +1792,7 @@ pub async fn import_assets_zip(
         rag_background_started = true;
         rag_background_items = queued_count;
 
-        tokio::spawn(async move {
+        common::tenancy::spawn(async move {
             if let Some(tid) = task_id {
                 let _ = set_zip_rag_task_status(&pool_bg, tid, "processing", 0, 0, 0, None).await;
             }
@@ -13,6 +13,10 @@ use uuid::Uuid;
 pub struct AssignDependencyPayload {
     pub prerequisite_lesson_id: Uuid,
     pub min_score_percentage: Option<f64>,
+    /// `prerequisite` (por defecto), `remedial` o `enrichment`
+    pub branch_type: Option<String>,
+    pub max_score_percentage: Option<f64>,
+    pub time_threshold_minutes: Option<i32>,
 }
 
 pub async fn assign_dependency(
@@ -21,6 +25,13 @@ pub async fn assign_dependency(
     Path(lesson_id): Path<Uuid>,
     Json(payload): Json<AssignDependencyPayload>,
 ) -> Result<Json<LessonDependency>, StatusCode> {
+    let branch_type = payload.branch_type.as_deref().unwrap_or("prerequisite");
+    if !matches!(branch_type, "prerequisite" | "remedial" | "enrichment")
+        || payload.time_threshold_minutes.is_some_and(|m| m <= 0)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
     // 1. Validar que ambas lecciones pertenecen a la organización
     let count: i64 = sqlx::query_scalar(
         "SELECT COUNT(*) FROM lessons WHERE id IN ($1, $2) AND organization_id = $3",
@@ -39,17 +50,23 @@ pub async fn assign_dependency(
     // 2. Insertar la dependencia
     let dependency: LessonDependency = sqlx::query_as(
         r#"
-        INSERT INTO lesson_dependencies (organization_id, lesson_id, prerequisite_lesson_id, min_score_percentage)
-        VALUES ($1, $2, $3, $4)
+        INSERT INTO lesson_dependencies (organization_id, lesson_id, prerequisite_lesson_id, min_score_percentage, branch_type, max_score_percentage, time_threshold_minutes)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
         ON CONFLICT (lesson_id, prerequisite_lesson_id) 
-        DO UPDATE SET min_score_percentage = EXCLUDED.min_score_percentage
-        RETURNING id, organization_id, lesson_id, prerequisite_lesson_id, min_score_percentage, created_at
+        DO UPDATE SET min_score_percentage = EXCLUDED.min_score_percentage,
+                      branch_type = EXCLUDED.branch_type,
+                      max_score_percentage = EXCLUDED.max_score_percentage,
+                      time_threshold_minutes = EXCLUDED.time_threshold_minutes
+        RETURNING id, organization_id, lesson_id, prerequisite_lesson_id, min_score_percentage, branch_type, max_score_percentage, time_threshold_minutes, created_at
         "#
     )
     .bind(org_ctx.id)
     .bind(lesson_id)
     .bind(payload.prerequisite_lesson_id)
     .bind(payload.min_score_percentage)
+    .bind(branch_type)
+    .bind(payload.max_score_percentage)
+    .bind(payload.time_threshold_minutes)
     .fetch_one(&pool)
     .await
     .map_err(|e| {
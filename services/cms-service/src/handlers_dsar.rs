@@ -0,0 +1,196 @@
+/// Parte del CMS de las solicitudes de titulares de datos (GDPR/LGPD).
+///
+/// El LMS orquesta las solicitudes (`dsar.rs` en el LMS) y llama a estos endpoints
+/// con un token entre servicios de alcance `dsar.process`; los tokens de usuario,
+/// incluso de administrador, no sirven. El registro de auditoría no se
+/// suprime: es append-only, está encadenado por hashes y se conserva por
+/// obligación legal; la supresión queda registrada en él.
+use axum::{
+    Extension, Json,
+    extract::{Path, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use common::{
+    auth::ServiceClaims,
+    tenancy::{self, TenantScope},
+};
+use serde_json::{Value, json};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::handlers::log_action;
+
+/// Secciones exportadas: (nombre, consulta filtrada por titular y organización).
+const EXPORT_SECTIONS: &[(&str, &str)] = &[
+    (
+        "ai_usage_logs",
+        "SELECT COALESCE(jsonb_agg(to_jsonb(t) ORDER BY t.created_at), '[]'::jsonb) FROM ai_usage_logs t WHERE t.user_id = $1 AND t.organization_id = $2",
+    ),
+    (
+        "rubric_assessments",
+        "SELECT COALESCE(jsonb_agg(to_jsonb(t) ORDER BY t.created_at), '[]'::jsonb) FROM rubric_assessments t JOIN users u ON u.id = t.user_id WHERE t.user_id = $1 AND u.organization_id = $2",
+    ),
+    (
+        "token_limit_alerts",
+        "SELECT COALESCE(jsonb_agg(to_jsonb(t) ORDER BY t.created_at), '[]'::jsonb) FROM token_limit_alerts t WHERE t.user_id = $1 AND t.organization_id = $2",
+    ),
+    (
+        "lti_grade_passback_events",
+        "SELECT COALESCE(jsonb_agg(to_jsonb(t) ORDER BY t.created_at), '[]'::jsonb) FROM lti_grade_passback_events t WHERE t.user_id = $1 AND t.organization_id = $2",
+    ),
+    (
+        "course_instructors",
+        "SELECT COALESCE(jsonb_agg(to_jsonb(t) ORDER BY t.created_at), '[]'::jsonb) FROM course_instructors t WHERE t.user_id = $1 AND t.organization_id = $2",
+    ),
+    (
+        "audit_logs",
+        "SELECT COALESCE(jsonb_agg(to_jsonb(t) - 'prev_hash' - 'entry_hash' ORDER BY t.chain_seq), '[]'::jsonb) FROM audit_logs t WHERE t.user_id = $1 AND t.organization_id = $2",
+    ),
+];
+
+/// Valida el token entre servicios del LMS (`scope = dsar.process`) y limita las
+/// consultas a su organización.
+pub async fn dsar_token_middleware(mut req: Request, next: Next) -> Result<Response, StatusCode> {
+    let token = req
+        .headers()
+        .get("authorization")
+        .and_then(|header| header.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let claims = common::auth::decode_service_token(token, common::auth::SERVICE_SCOPE_DSAR)
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let scope = TenantScope::Organization(claims.org);
+    req.extensions_mut().insert(claims);
+
+    Ok(tenancy::scoped_response(scope, next.run(req)).await)
+}
+
+/// GET /dsar/users/{id}/data - Datos del titular en el CMS
+pub async fn export_user_data(
+    Extension(service): Extension<ServiceClaims>,
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let organization_id = service.org;
+
+    let mut data = serde_json::Map::new();
+
+    let profile: Option<Value> = sqlx::query_scalar(
+        "SELECT to_jsonb(u) - 'password_hash' FROM users u WHERE u.id = $1 AND u.organization_id = $2",
+    )
+    .bind(user_id)
+    .bind(organization_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    data.insert("profile".to_string(), profile.unwrap_or(Value::Null));
+
+    for (name, sql) in EXPORT_SECTIONS {
+        let rows: Value = sqlx::query_scalar(sql)
+            .bind(user_id)
+            .bind(organization_id)
+            .fetch_one(&pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error al exportar {} del titular {}: {}", name, user_id, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+            })?;
+        data.insert(name.to_string(), rows);
+    }
+
+    log_action(&pool, organization_id, service.sub, "DSAR_EXPORT", "user", user_id, json!({})).await;
+
+    Ok(Json(Value::Object(data)))
+}
+
+/// POST /dsar/users/{id}/erase - Suprime los datos personales del titular en el CMS
+pub async fn erase_user_data(
+    Extension(service): Extension<ServiceClaims>,
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let organization_id = service.org;
+
+    let internal = |e: sqlx::Error| {
+        tracing::error!("Error al suprimir los datos del titular {}: {}", user_id, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+    };
+
+    let mut tx = pool.begin().await.map_err(internal)?;
+    // El trigger de auditoría no guarda la imagen de la fila de users en la supresión
+    crate::db_util::set_session_context(
+        &mut tx,
+        Some(service.sub),
+        Some(organization_id),
+        None,
+        None,
+        Some("DSAR_ERASURE".to_string()),
+    )
+    .await
+    .map_err(internal)?;
+
+    let users = sqlx::query(
+        r#"
+        UPDATE users
+        SET email = 'erased+' || id || '@invalid',
+            full_name = 'Usuario eliminado',
+            password_hash = '!',
+            avatar_url = NULL,
+            bio = NULL,
+            sam_student_id = NULL,
+            updated_at = NOW()
+        WHERE id = $1 AND organization_id = $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(organization_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal)?
+    .rows_affected();
+
+    let ai_usage_logs = sqlx::query(
+        "UPDATE ai_usage_logs SET prompt = NULL, response = NULL, request_metadata = '{}'::jsonb WHERE user_id = $1 AND organization_id = $2",
+    )
+    .bind(user_id)
+    .bind(organization_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal)?
+    .rows_affected();
+
+    let token_limit_alerts = sqlx::query("DELETE FROM token_limit_alerts WHERE user_id = $1 AND organization_id = $2")
+        .bind(user_id)
+        .bind(organization_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal)?
+        .rows_affected();
+
+    let rubric_feedback = sqlx::query(
+        "UPDATE rubric_assessments SET feedback = NULL WHERE user_id = $1 AND feedback IS NOT NULL AND EXISTS (SELECT 1 FROM users u WHERE u.id = $1 AND u.organization_id = $2)",
+    )
+    .bind(user_id)
+    .bind(organization_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal)?
+    .rows_affected();
+
+    tx.commit().await.map_err(internal)?;
+
+    let result = json!({
+        "pseudonymized.users": users,
+        "scrubbed.ai_usage_logs": ai_usage_logs,
+        "deleted.token_limit_alerts": token_limit_alerts,
+        "scrubbed.rubric_assessments": rubric_feedback,
+        "retained.audit_logs": "obligación legal",
+    });
+
+    log_action(&pool, organization_id, service.sub, "DSAR_ERASURE", "user", user_id, result.clone()).await;
+
+    Ok(Json(result))
+}
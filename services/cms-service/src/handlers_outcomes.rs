@@ -0,0 +1,901 @@
+/// Marcos de competencias y resultados de aprendizaje.
+///
+/// Un marco es un árbol de resultados (`learning_outcomes.parent_id`) que se crea a
+/// mano o se importa desde CSV (`code,parent_code,description,level`) o desde un
+/// paquete CASE JSON (CFDocument/CFItems/CFAssociations `isChildOf`). Los resultados
+/// se alinean con lecciones, bloques de quiz, criterios de rúbrica y preguntas del
+/// banco; `course_outcomes_snapshot` arma la instantánea que se publica al LMS,
+/// donde se calcula el dominio por alumno.
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use common::models::{PublishedOutcome, PublishedOutcomeAlignment, PublishedOutcomes};
+use common::{auth::Claims, middleware::Org};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct OutcomeFramework {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub source: String,
+    pub source_identifier: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub outcome_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct LearningOutcome {
+    pub id: Uuid,
+    pub framework_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub code: String,
+    pub description: String,
+    pub level: Option<String>,
+    pub position: i32,
+    pub external_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutcomeNode {
+    #[serde(flatten)]
+    pub outcome: LearningOutcome,
+    pub children: Vec<OutcomeNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FrameworkTree {
+    pub framework: OutcomeFramework,
+    pub outcomes: Vec<OutcomeNode>,
+}
+
+const FRAMEWORK_SELECT: &str = r#"
+SELECT f.id, f.name, f.description, f.source, f.source_identifier, f.created_at,
+       (SELECT COUNT(*) FROM learning_outcomes o WHERE o.framework_id = f.id) AS outcome_count
+FROM outcome_frameworks f
+"#;
+
+fn require_staff(claims: &Claims) -> Result<(), (StatusCode, String)> {
+    if claims.role != "admin" && claims.role != "instructor" {
+        return Err((StatusCode::FORBIDDEN, "Se requiere rol instructor o admin".to_string()));
+    }
+    Ok(())
+}
+
+fn internal_error(e: sqlx::Error) -> (StatusCode, String) {
+    tracing::error!("Error en marcos de competencias: {}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+}
+
+// ─── Importación ─────────────────────────────────────────────────────────────
+
+/// Resultado leído de un archivo antes de insertarse; `key` y `parent_key` son las
+/// referencias del origen (código en CSV, identificador en CASE).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedOutcome {
+    pub key: String,
+    pub parent_key: Option<String>,
+    pub code: String,
+    pub description: String,
+    pub level: Option<String>,
+    pub position: i32,
+    pub external_id: Option<String>,
+}
+
+/// CSV con encabezado; columnas reconocidas: `code`, `parent_code`,
+/// `description` (o `statement`) y `level`.
+pub fn parse_outcomes_csv(bytes: &[u8]) -> Result<Vec<ImportedOutcome>, String> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).trim(csv::Trim::All).from_reader(bytes);
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|_| "No se pudo leer el encabezado del CSV".to_string())?
+        .iter()
+        .map(|h| h.to_lowercase())
+        .collect();
+    let column = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+    let code_col = column(&["code", "codigo", "código"]).ok_or("Falta la columna 'code'".to_string())?;
+    let description_col = column(&["description", "statement", "descripcion", "descripción"])
+        .ok_or("Falta la columna 'description'".to_string())?;
+    let parent_col = column(&["parent_code", "parent"]);
+    let level_col = column(&["level", "nivel"]);
+
+    let mut outcomes = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| format!("Fila {} inválida: {}", index + 2, e))?;
+        let field = |col: Option<usize>| {
+            col.and_then(|c| record.get(c)).map(str::to_string).filter(|v| !v.is_empty())
+        };
+        let Some(code) = field(Some(code_col)) else {
+            continue;
+        };
+        let description = field(Some(description_col))
+            .ok_or_else(|| format!("Fila {}: el resultado '{}' no tiene descripción", index + 2, code))?;
+        outcomes.push(ImportedOutcome {
+            key: code.clone(),
+            parent_key: field(parent_col),
+            code,
+            description,
+            level: field(level_col),
+            position: index as i32,
+            external_id: None,
+        });
+    }
+    Ok(outcomes)
+}
+
+/// Paquete CASE 1.0: devuelve título, identificador del documento y resultados.
+pub fn parse_case_package(package: &Value) -> Result<(String, Option<String>, Vec<ImportedOutcome>), String> {
+    let document = package.get("CFDocument").ok_or("Falta CFDocument en el paquete CASE".to_string())?;
+    let title = document
+        .get("title")
+        .and_then(|t| t.as_str())
+        .unwrap_or("Marco CASE")
+        .to_string();
+    let document_id = document.get("identifier").and_then(|i| i.as_str()).map(str::to_string);
+
+    // isChildOf: origen = hijo, destino = padre (o el documento si es raíz)
+    let mut parents: HashMap<String, (String, i32)> = HashMap::new();
+    for association in package.get("CFAssociations").and_then(|a| a.as_array()).into_iter().flatten() {
+        if association.get("associationType").and_then(|t| t.as_str()) != Some("isChildOf") {
+            continue;
+        }
+        let node = |key: &str| {
+            association
+                .get(key)
+                .and_then(|n| n.get("identifier"))
+                .and_then(|i| i.as_str())
+                .map(str::to_string)
+        };
+        if let (Some(child), Some(parent)) = (node("originNodeURI"), node("destinationNodeURI")) {
+            let sequence = association.get("sequenceNumber").and_then(|s| s.as_i64()).unwrap_or(0) as i32;
+            parents.insert(child, (parent, sequence));
+        }
+    }
+
+    let items = package
+        .get("CFItems")
+        .and_then(|i| i.as_array())
+        .ok_or("Falta CFItems en el paquete CASE".to_string())?;
+    let mut outcomes = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let text = |key: &str| item.get(key).and_then(|v| v.as_str()).map(str::to_string).filter(|v| !v.is_empty());
+        let identifier = text("identifier").ok_or(format!("CFItem {} sin identifier", index))?;
+        let description = text("fullStatement").ok_or(format!("CFItem {} sin fullStatement", identifier))?;
+        let (parent_key, position) = match parents.get(&identifier) {
+            Some((parent, sequence)) if Some(parent) != document_id.as_ref() => (Some(parent.clone()), *sequence),
+            Some((_, sequence)) => (None, *sequence),
+            None => (None, index as i32),
+        };
+        let level = item
+            .get("educationLevel")
+            .and_then(|l| l.as_array())
+            .map(|levels| levels.iter().filter_map(|l| l.as_str()).collect::<Vec<_>>().join(","))
+            .filter(|l| !l.is_empty());
+        outcomes.push(ImportedOutcome {
+            key: identifier.clone(),
+            parent_key,
+            code: text("humanCodingScheme").unwrap_or_else(|| identifier.clone()),
+            description,
+            level,
+            position,
+            external_id: Some(identifier),
+        });
+    }
+    Ok((title, document_id, outcomes))
+}
+
+/// Orden de inserción en que cada padre precede a sus hijos; falla ante padres
+/// desconocidos, ciclos o claves repetidas.
+pub fn insertion_order(outcomes: &[ImportedOutcome]) -> Result<Vec<usize>, String> {
+    let mut seen = HashSet::new();
+    for outcome in outcomes {
+        if !seen.insert(outcome.key.as_str()) {
+            return Err(format!("El resultado '{}' está repetido", outcome.key));
+        }
+    }
+    if let Some(orphan) = outcomes
+        .iter()
+        .find(|o| o.parent_key.as_ref().is_some_and(|p| !seen.contains(p.as_str())))
+    {
+        return Err(format!("El resultado '{}' referencia un padre inexistente", orphan.key));
+    }
+
+    let mut placed: HashSet<&str> = HashSet::new();
+    let mut order = Vec::with_capacity(outcomes.len());
+    while order.len() < outcomes.len() {
+        let before = order.len();
+        for (index, outcome) in outcomes.iter().enumerate() {
+            if placed.contains(outcome.key.as_str()) {
+                continue;
+            }
+            if outcome.parent_key.as_ref().is_none_or(|p| placed.contains(p.as_str())) {
+                placed.insert(outcome.key.as_str());
+                order.push(index);
+            }
+        }
+        if order.len() == before {
+            return Err("La jerarquía de resultados contiene ciclos".to_string());
+        }
+    }
+    Ok(order)
+}
+
+async fn create_framework_with_outcomes(
+    pool: &PgPool,
+    org_id: Uuid,
+    claims: &Claims,
+    name: &str,
+    source: &str,
+    source_identifier: Option<String>,
+    outcomes: Vec<ImportedOutcome>,
+) -> Result<Json<FrameworkTree>, (StatusCode, String)> {
+    if outcomes.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "El archivo no contiene resultados".to_string()));
+    }
+    let order = insertion_order(&outcomes).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let mut tx = pool.begin().await.map_err(internal_error)?;
+    let framework_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO outcome_frameworks (organization_id, name, source, source_identifier, created_by)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+    )
+    .bind(org_id)
+    .bind(name)
+    .bind(source)
+    .bind(&source_identifier)
+    .bind(claims.sub)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+
+    let mut ids: HashMap<&str, Uuid> = HashMap::new();
+    for index in order {
+        let outcome = &outcomes[index];
+        let parent_id = outcome.parent_key.as_deref().and_then(|p| ids.get(p).copied());
+        let id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO learning_outcomes
+                (organization_id, framework_id, parent_id, code, description, level, position, external_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id
+            "#,
+        )
+        .bind(org_id)
+        .bind(framework_id)
+        .bind(parent_id)
+        .bind(&outcome.code)
+        .bind(&outcome.description)
+        .bind(&outcome.level)
+        .bind(outcome.position)
+        .bind(&outcome.external_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => (
+                StatusCode::BAD_REQUEST,
+                format!("El código '{}' está repetido en el marco", outcome.code),
+            ),
+            e => internal_error(e),
+        })?;
+        ids.insert(outcome.key.as_str(), id);
+    }
+    tx.commit().await.map_err(internal_error)?;
+
+    load_framework_tree(pool, org_id, framework_id).await.map(Json)
+}
+
+/// POST /outcome-frameworks/import-csv - Multipart con `file` y `name` opcional
+pub async fn import_framework_csv(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    mut multipart: axum::extract::Multipart,
+) -> Result<Json<FrameworkTree>, (StatusCode, String)> {
+    require_staff(&claims)?;
+    const MAX_FILE_SIZE: usize = 5 * 1024 * 1024;
+
+    let mut file_bytes: Option<Vec<u8>> = None;
+    let mut name: Option<String> = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Error leyendo multipart".to_string()))?
+    {
+        match field.name().unwrap_or("") {
+            "file" => {
+                let file_name = field.file_name().map(str::to_string);
+                let bytes = field
+                    .bytes()
+                    .await
+                    .map_err(|_| (StatusCode::BAD_REQUEST, "Error leyendo bytes del archivo".to_string()))?;
+                if bytes.len() > MAX_FILE_SIZE {
+                    return Err((StatusCode::PAYLOAD_TOO_LARGE, "El archivo supera el límite de 5MB".to_string()));
+                }
+                if name.is_none() {
+                    name = file_name.map(|f| f.trim_end_matches(".csv").to_string());
+                }
+                file_bytes = Some(bytes.to_vec());
+            }
+            "name" => {
+                name = field.text().await.ok().filter(|n| !n.trim().is_empty());
+            }
+            _ => {}
+        }
+    }
+    let bytes = file_bytes.ok_or((StatusCode::BAD_REQUEST, "No se recibió ningún archivo".to_string()))?;
+    let outcomes = parse_outcomes_csv(&bytes).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let name = name.unwrap_or_else(|| "Marco importado".to_string());
+
+    create_framework_with_outcomes(&pool, org_ctx.id, &claims, &name, "csv", None, outcomes).await
+}
+
+/// POST /outcome-frameworks/import-case - Cuerpo: paquete CASE JSON (CFPackage)
+pub async fn import_framework_case(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Json(package): Json<Value>,
+) -> Result<Json<FrameworkTree>, (StatusCode, String)> {
+    require_staff(&claims)?;
+    let (title, document_id, outcomes) = parse_case_package(&package).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    create_framework_with_outcomes(&pool, org_ctx.id, &claims, &title, "case", document_id, outcomes).await
+}
+
+// ─── Marcos y resultados ─────────────────────────────────────────────────────
+
+async fn load_framework_tree(pool: &PgPool, org_id: Uuid, framework_id: Uuid) -> Result<FrameworkTree, (StatusCode, String)> {
+    let framework = sqlx::query_as::<_, OutcomeFramework>(&format!(
+        "{FRAMEWORK_SELECT} WHERE f.id = $1 AND f.organization_id = $2"
+    ))
+    .bind(framework_id)
+    .bind(org_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(internal_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Marco no encontrado".to_string()))?;
+
+    let outcomes = sqlx::query_as::<_, LearningOutcome>(
+        r#"
+        SELECT id, framework_id, parent_id, code, description, level, position, external_id
+        FROM learning_outcomes WHERE framework_id = $1
+        ORDER BY position, code
+        "#,
+    )
+    .bind(framework_id)
+    .fetch_all(pool)
+    .await
+    .map_err(internal_error)?;
+
+    Ok(FrameworkTree { framework, outcomes: build_tree(outcomes) })
+}
+
+fn build_tree(outcomes: Vec<LearningOutcome>) -> Vec<OutcomeNode> {
+    let mut children: HashMap<Option<Uuid>, Vec<LearningOutcome>> = HashMap::new();
+    for outcome in outcomes {
+        children.entry(outcome.parent_id).or_default().push(outcome);
+    }
+    fn attach(parent: Option<Uuid>, children: &mut HashMap<Option<Uuid>, Vec<LearningOutcome>>) -> Vec<OutcomeNode> {
+        children
+            .remove(&parent)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|outcome| {
+                let nested = attach(Some(outcome.id), children);
+                OutcomeNode { outcome, children: nested }
+            })
+            .collect()
+    }
+    attach(None, &mut children)
+}
+
+/// GET /outcome-frameworks
+pub async fn list_frameworks(
+    Org(org_ctx): Org,
+    State(pool): State<PgPool>,
+) -> Result<Json<Vec<OutcomeFramework>>, (StatusCode, String)> {
+    let frameworks = sqlx::query_as::<_, OutcomeFramework>(&format!(
+        "{FRAMEWORK_SELECT} WHERE f.organization_id = $1 ORDER BY f.name"
+    ))
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(internal_error)?;
+    Ok(Json(frameworks))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateFrameworkPayload {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// POST /outcome-frameworks - Marco vacío para cargar resultados a mano
+pub async fn create_framework(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Json(payload): Json<CreateFrameworkPayload>,
+) -> Result<Json<FrameworkTree>, (StatusCode, String)> {
+    require_staff(&claims)?;
+    if payload.name.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "El nombre es obligatorio".to_string()));
+    }
+    let id: Uuid = sqlx::query_scalar(
+        "INSERT INTO outcome_frameworks (organization_id, name, description, created_by) VALUES ($1, $2, $3, $4) RETURNING id",
+    )
+    .bind(org_ctx.id)
+    .bind(payload.name.trim())
+    .bind(&payload.description)
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await
+    .map_err(internal_error)?;
+
+    load_framework_tree(&pool, org_ctx.id, id).await.map(Json)
+}
+
+/// GET /outcome-frameworks/{id} - Marco con su árbol de resultados
+pub async fn get_framework(
+    Org(org_ctx): Org,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<FrameworkTree>, (StatusCode, String)> {
+    load_framework_tree(&pool, org_ctx.id, id).await.map(Json)
+}
+
+/// DELETE /outcome-frameworks/{id}
+pub async fn delete_framework(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_staff(&claims)?;
+    let result = sqlx::query("DELETE FROM outcome_frameworks WHERE id = $1 AND organization_id = $2")
+        .bind(id)
+        .bind(org_ctx.id)
+        .execute(&pool)
+        .await
+        .map_err(internal_error)?;
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Marco no encontrado".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOutcomePayload {
+    pub code: String,
+    pub description: String,
+    pub parent_id: Option<Uuid>,
+    pub level: Option<String>,
+    pub position: Option<i32>,
+}
+
+/// POST /outcome-frameworks/{id}/outcomes
+pub async fn create_outcome(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(framework_id): Path<Uuid>,
+    Json(payload): Json<CreateOutcomePayload>,
+) -> Result<Json<LearningOutcome>, (StatusCode, String)> {
+    require_staff(&claims)?;
+    if payload.code.trim().is_empty() || payload.description.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "El código y la descripción son obligatorios".to_string()));
+    }
+
+    // El padre debe pertenecer al mismo marco
+    let valid: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (SELECT 1 FROM outcome_frameworks WHERE id = $1 AND organization_id = $2)
+           AND ($3::uuid IS NULL OR EXISTS (SELECT 1 FROM learning_outcomes WHERE id = $3 AND framework_id = $1))
+        "#,
+    )
+    .bind(framework_id)
+    .bind(org_ctx.id)
+    .bind(payload.parent_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(internal_error)?;
+    if !valid {
+        return Err((StatusCode::NOT_FOUND, "Marco o resultado padre no encontrado".to_string()));
+    }
+
+    let outcome = sqlx::query_as::<_, LearningOutcome>(
+        r#"
+        INSERT INTO learning_outcomes (organization_id, framework_id, parent_id, code, description, level, position)
+        VALUES ($1, $2, $3, $4, $5, $6, COALESCE($7, (SELECT COUNT(*)::int FROM learning_outcomes WHERE framework_id = $2)))
+        RETURNING id, framework_id, parent_id, code, description, level, position, external_id
+        "#,
+    )
+    .bind(org_ctx.id)
+    .bind(framework_id)
+    .bind(payload.parent_id)
+    .bind(payload.code.trim())
+    .bind(payload.description.trim())
+    .bind(&payload.level)
+    .bind(payload.position)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            (StatusCode::CONFLICT, "Ya existe un resultado con ese código en el marco".to_string())
+        }
+        e => internal_error(e),
+    })?;
+
+    Ok(Json(outcome))
+}
+
+/// DELETE /learning-outcomes/{id} - Elimina el resultado, sus hijos y alineaciones
+pub async fn delete_outcome(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_staff(&claims)?;
+    let result = sqlx::query("DELETE FROM learning_outcomes WHERE id = $1 AND organization_id = $2")
+        .bind(id)
+        .bind(org_ctx.id)
+        .execute(&pool)
+        .await
+        .map_err(internal_error)?;
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Resultado no encontrado".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ─── Alineaciones ────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct OutcomeAlignment {
+    pub id: Uuid,
+    pub outcome_id: Uuid,
+    pub outcome_code: String,
+    pub outcome_description: String,
+    pub entity_type: String,
+    pub entity_id: Uuid,
+    pub block_id: Option<String>,
+    pub weight: f32,
+    pub created_at: DateTime<Utc>,
+}
+
+const ALIGNMENT_SELECT: &str = r#"
+SELECT a.id, a.outcome_id, o.code AS outcome_code, o.description AS outcome_description,
+       a.entity_type, a.entity_id, a.block_id, a.weight, a.created_at
+FROM outcome_alignments a
+JOIN learning_outcomes o ON o.id = a.outcome_id
+"#;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAlignmentPayload {
+    pub outcome_id: Uuid,
+    /// lesson | quiz_block | rubric_criterion | question
+    pub entity_type: String,
+    pub entity_id: Uuid,
+    /// Identificador del bloque dentro de la lección (solo `quiz_block`)
+    pub block_id: Option<String>,
+    pub weight: Option<f32>,
+}
+
+/// POST /outcome-alignments - Etiqueta una lección, bloque, criterio o pregunta
+pub async fn create_alignment(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Json(payload): Json<CreateAlignmentPayload>,
+) -> Result<Json<OutcomeAlignment>, (StatusCode, String)> {
+    require_staff(&claims)?;
+    let entity_query = match payload.entity_type.as_str() {
+        "lesson" | "quiz_block" => "SELECT EXISTS (SELECT 1 FROM lessons WHERE id = $1 AND organization_id = $2)",
+        "question" => "SELECT EXISTS (SELECT 1 FROM question_bank WHERE id = $1 AND organization_id = $2)",
+        "rubric_criterion" => {
+            "SELECT EXISTS (SELECT 1 FROM rubric_criteria c JOIN rubrics r ON r.id = c.rubric_id WHERE c.id = $1 AND r.organization_id = $2)"
+        }
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "entity_type debe ser lesson, quiz_block, rubric_criterion o question".to_string(),
+            ));
+        }
+    };
+    let block_id = payload.block_id.filter(|b| !b.trim().is_empty());
+    if (payload.entity_type == "quiz_block") != block_id.is_some() {
+        return Err((StatusCode::BAD_REQUEST, "block_id es obligatorio solo para quiz_block".to_string()));
+    }
+    if payload.weight.is_some_and(|w| w <= 0.0) {
+        return Err((StatusCode::BAD_REQUEST, "El peso debe ser positivo".to_string()));
+    }
+
+    let entity_exists: bool = sqlx::query_scalar(entity_query)
+        .bind(payload.entity_id)
+        .bind(org_ctx.id)
+        .fetch_one(&pool)
+        .await
+        .map_err(internal_error)?;
+    let outcome_exists: bool =
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM learning_outcomes WHERE id = $1 AND organization_id = $2)")
+            .bind(payload.outcome_id)
+            .bind(org_ctx.id)
+            .fetch_one(&pool)
+            .await
+            .map_err(internal_error)?;
+    if !entity_exists || !outcome_exists {
+        return Err((StatusCode::NOT_FOUND, "Resultado o elemento no encontrado".to_string()));
+    }
+
+    let id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO outcome_alignments (organization_id, outcome_id, entity_type, entity_id, block_id, weight, created_by)
+        VALUES ($1, $2, $3, $4, $5, COALESCE($6, 1), $7)
+        ON CONFLICT (outcome_id, entity_type, entity_id, COALESCE(block_id, ''))
+        DO UPDATE SET weight = EXCLUDED.weight
+        RETURNING id
+        "#,
+    )
+    .bind(org_ctx.id)
+    .bind(payload.outcome_id)
+    .bind(&payload.entity_type)
+    .bind(payload.entity_id)
+    .bind(&block_id)
+    .bind(payload.weight)
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await
+    .map_err(internal_error)?;
+
+    let alignment = sqlx::query_as::<_, OutcomeAlignment>(&format!("{ALIGNMENT_SELECT} WHERE a.id = $1"))
+        .bind(id)
+        .fetch_one(&pool)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(alignment))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlignmentFilter {
+    pub entity_type: Option<String>,
+    pub entity_id: Option<Uuid>,
+    pub outcome_id: Option<Uuid>,
+}
+
+/// GET /outcome-alignments?entity_type=&entity_id=&outcome_id=
+pub async fn list_alignments(
+    Org(org_ctx): Org,
+    State(pool): State<PgPool>,
+    Query(filter): Query<AlignmentFilter>,
+) -> Result<Json<Vec<OutcomeAlignment>>, (StatusCode, String)> {
+    let alignments = sqlx::query_as::<_, OutcomeAlignment>(&format!(
+        r#"{ALIGNMENT_SELECT}
+        WHERE a.organization_id = $1
+          AND ($2::text IS NULL OR a.entity_type = $2)
+          AND ($3::uuid IS NULL OR a.entity_id = $3)
+          AND ($4::uuid IS NULL OR a.outcome_id = $4)
+        ORDER BY o.position, o.code
+        LIMIT 1000"#
+    ))
+    .bind(org_ctx.id)
+    .bind(&filter.entity_type)
+    .bind(filter.entity_id)
+    .bind(filter.outcome_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(internal_error)?;
+    Ok(Json(alignments))
+}
+
+/// DELETE /outcome-alignments/{id}
+pub async fn delete_alignment(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_staff(&claims)?;
+    let result = sqlx::query("DELETE FROM outcome_alignments WHERE id = $1 AND organization_id = $2")
+        .bind(id)
+        .bind(org_ctx.id)
+        .execute(&pool)
+        .await
+        .map_err(internal_error)?;
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Alineación no encontrada".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ─── Publicación ─────────────────────────────────────────────────────────────
+
+/// Recoge los `question_bank_id` presentes en el metadata publicado de una lección
+/// (quizzes del banco y pools ya resueltos).
+pub fn collect_question_bank_ids(value: &Value, ids: &mut HashSet<Uuid>) {
+    match value {
+        Value::Object(map) => {
+            for (key, nested) in map {
+                if key == "question_bank_id"
+                    && let Some(id) = nested.as_str().and_then(|s| Uuid::parse_str(s).ok())
+                {
+                    ids.insert(id);
+                }
+                collect_question_bank_ids(nested, ids);
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| collect_question_bank_ids(item, ids)),
+        _ => {}
+    }
+}
+
+/// Alineaciones del curso y resultados involucrados (con sus ancestros) para el LMS.
+/// Los criterios de rúbrica se traducen a las lecciones que tienen asignada la rúbrica.
+pub async fn course_outcomes_snapshot(
+    pool: &PgPool,
+    org_id: Uuid,
+    course_id: Uuid,
+    question_ids: &[Uuid],
+) -> Result<PublishedOutcomes, sqlx::Error> {
+    let alignments = sqlx::query_as::<_, PublishedOutcomeAlignment>(
+        r#"
+        WITH course_lessons AS (
+            SELECT l.id FROM lessons l JOIN modules m ON m.id = l.module_id WHERE m.course_id = $1
+        )
+        SELECT a.outcome_id,
+               CASE WHEN a.entity_type IN ('lesson', 'quiz_block') THEN a.entity_id ELSE lr.lesson_id END AS lesson_id,
+               a.block_id,
+               CASE WHEN a.entity_type = 'question' THEN a.entity_id END AS question_bank_id,
+               rc.rubric_id,
+               CASE WHEN a.entity_type = 'rubric_criterion' THEN a.entity_id END AS rubric_criterion_id,
+               a.weight
+        FROM outcome_alignments a
+        LEFT JOIN rubric_criteria rc ON a.entity_type = 'rubric_criterion' AND rc.id = a.entity_id
+        LEFT JOIN lesson_rubrics lr ON lr.rubric_id = rc.rubric_id AND lr.is_active
+            AND lr.lesson_id IN (SELECT id FROM course_lessons)
+        WHERE a.organization_id = $2
+          AND (
+            (a.entity_type IN ('lesson', 'quiz_block') AND a.entity_id IN (SELECT id FROM course_lessons))
+            OR (a.entity_type = 'question' AND a.entity_id = ANY($3))
+            OR (a.entity_type = 'rubric_criterion' AND lr.lesson_id IS NOT NULL)
+          )
+        "#,
+    )
+    .bind(course_id)
+    .bind(org_id)
+    .bind(question_ids)
+    .fetch_all(pool)
+    .await?;
+
+    let outcome_ids: Vec<Uuid> = alignments
+        .iter()
+        .map(|a| a.outcome_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let outcomes = if outcome_ids.is_empty() {
+        Vec::new()
+    } else {
+        sqlx::query_as::<_, PublishedOutcome>(
+            r#"
+            WITH RECURSIVE tree AS (
+                SELECT o.id, o.framework_id, o.parent_id, o.code, o.description, o.level, o.position
+                FROM learning_outcomes o WHERE o.id = ANY($1)
+                UNION
+                SELECT p.id, p.framework_id, p.parent_id, p.code, p.description, p.level, p.position
+                FROM learning_outcomes p JOIN tree t ON p.id = t.parent_id
+            )
+            SELECT t.id, t.framework_id, f.name AS framework_name, t.parent_id, t.code, t.description, t.level, t.position
+            FROM tree t JOIN outcome_frameworks f ON f.id = t.framework_id
+            ORDER BY f.name, t.position, t.code
+            "#,
+        )
+        .bind(&outcome_ids)
+        .fetch_all(pool)
+        .await?
+    };
+
+    Ok(PublishedOutcomes { outcomes, alignments })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn keys(outcomes: &[ImportedOutcome], order: &[usize]) -> Vec<String> {
+        order.iter().map(|&i| outcomes[i].key.clone()).collect()
+    }
+
+    #[test]
+    fn csv_reads_quoted_fields_and_alias_headers() {
+        let csv = "Codigo,Statement,Parent_Code,Nivel\n\
+                   MAT.1,\"Resuelve ecuaciones, lineales y cuadráticas\",,Básico\n\
+                   MAT.1.a,\"Usa \"\"x\"\" como incógnita\",MAT.1,\n\
+                   ,fila sin código,,\n";
+        let outcomes = parse_outcomes_csv(csv.as_bytes()).unwrap();
+
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(outcomes[0].description, "Resuelve ecuaciones, lineales y cuadráticas");
+        assert_eq!(outcomes[0].level.as_deref(), Some("Básico"));
+        assert_eq!(outcomes[0].parent_key, None);
+        assert_eq!(outcomes[1].description, "Usa \"x\" como incógnita");
+        assert_eq!(outcomes[1].parent_key.as_deref(), Some("MAT.1"));
+        assert_eq!(outcomes[1].level, None);
+
+        assert!(parse_outcomes_csv(b"code,level\nA,1\n").is_err());
+        assert!(parse_outcomes_csv(b"code,description\nA,\n").is_err());
+    }
+
+    #[test]
+    fn case_package_maps_parents_and_document_roots() {
+        let package = json!({
+            "CFDocument": { "identifier": "doc", "title": "Matemáticas" },
+            "CFItems": [
+                { "identifier": "child", "fullStatement": "Suma fracciones", "humanCodingScheme": "M.1.1" },
+                { "identifier": "root", "fullStatement": "Números", "educationLevel": ["05", "06"] },
+            ],
+            "CFAssociations": [
+                {
+                    "associationType": "isChildOf",
+                    "originNodeURI": { "identifier": "child" },
+                    "destinationNodeURI": { "identifier": "root" },
+                    "sequenceNumber": 2,
+                },
+                {
+                    "associationType": "isChildOf",
+                    "originNodeURI": { "identifier": "root" },
+                    "destinationNodeURI": { "identifier": "doc" },
+                    "sequenceNumber": 1,
+                },
+                {
+                    "associationType": "isRelatedTo",
+                    "originNodeURI": { "identifier": "root" },
+                    "destinationNodeURI": { "identifier": "child" },
+                },
+            ],
+        });
+        let (title, document_id, outcomes) = parse_case_package(&package).unwrap();
+
+        assert_eq!((title.as_str(), document_id.as_deref()), ("Matemáticas", Some("doc")));
+        assert_eq!(outcomes[0].code, "M.1.1");
+        assert_eq!((outcomes[0].parent_key.as_deref(), outcomes[0].position), (Some("root"), 2));
+        assert_eq!(outcomes[1].code, "root");
+        assert_eq!((outcomes[1].parent_key.as_deref(), outcomes[1].position), (None, 1));
+        assert_eq!(outcomes[1].level.as_deref(), Some("05,06"));
+        assert_eq!(outcomes[1].external_id.as_deref(), Some("root"));
+
+        assert!(parse_case_package(&json!({ "CFItems": [] })).is_err());
+    }
+
+    #[test]
+    fn insertion_order_places_parents_first_and_rejects_cycles() {
+        // El hijo aparece antes que su padre en el archivo
+        let csv = "code,description,parent_code\nA.1.a,Hoja,A.1\nA.1,Rama,A\nA,Raíz,\nB,Otra raíz,\n";
+        let outcomes = parse_outcomes_csv(csv.as_bytes()).unwrap();
+        let order = insertion_order(&outcomes).unwrap();
+        assert_eq!(keys(&outcomes, &order), ["A", "B", "A.1", "A.1.a"]);
+
+        let cycle = parse_outcomes_csv(b"code,description,parent_code\nA,a,B\nB,b,A\nC,c,\n").unwrap();
+        assert_eq!(insertion_order(&cycle).unwrap_err(), "La jerarquía de resultados contiene ciclos");
+
+        let own_parent = parse_outcomes_csv(b"code,description,parent_code\nA,a,A\n").unwrap();
+        assert!(insertion_order(&own_parent).is_err());
+
+        let orphan = parse_outcomes_csv(b"code,description,parent_code\nA,a,Z\n").unwrap();
+        assert!(insertion_order(&orphan).unwrap_err().contains("padre inexistente"));
+
+        let repeated = parse_outcomes_csv(b"code,description\nA,a\nA,b\n").unwrap();
+        assert!(insertion_order(&repeated).unwrap_err().contains("repetido"));
+    }
+}
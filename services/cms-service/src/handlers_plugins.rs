@@ -3,12 +3,24 @@ use axum::{
     extract::{Path, State},
     http::StatusCode,
 };
+use base64::Engine as _;
 use common::middleware::Org;
 use common::auth::Claims;
 use serde::{Deserialize, Serialize};
-use sqlx::{PgPool, Row};
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Row, postgres::PgRow};
 use uuid::Uuid;
 
+use crate::plugin_manifest::{self, PluginManifest};
+
+/// Eventos a los que puede suscribirse un plugin wasm (los ejecuta el LMS)
+const SUPPORTED_EVENTS: &[&str] = &["user.enrolled", "grade.submitted", "course.published"];
+const MAX_WASM_MODULE_BYTES: usize = 10 * 1024 * 1024;
+
+const PLUGIN_COLUMNS: &str = "id, organization_id, name, description, component_url, icon_url, config, enabled, \
+     kind, wasm_sha256, events, block_types, fuel_limit, memory_limit_mb, manifest, version, publisher, \
+     permissions, signed_by, created_at, updated_at";
+
 // ─────────────────────────────────────────────────────────────────────────────
 // Tipos
 // ─────────────────────────────────────────────────────────────────────────────
@@ -23,17 +35,72 @@ pub struct OrgPlugin {
     pub icon_url: Option<String>,
     pub config: serde_json::Value,
     pub enabled: bool,
+    /// `component` (Web Component en iframe) o `wasm` (plugin de servidor)
+    pub kind: String,
+    pub wasm_sha256: Option<String>,
+    pub events: Vec<String>,
+    pub block_types: Vec<String>,
+    pub fuel_limit: i64,
+    pub memory_limit_mb: i32,
+    /// Manifiesto firmado (None en plugins instalados sin manifiesto)
+    pub manifest: Option<serde_json::Value>,
+    pub version: Option<String>,
+    pub publisher: Option<String>,
+    pub permissions: Vec<String>,
+    /// Clave de `plugin_publishers` que verificó la firma
+    pub signed_by: Option<Uuid>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
+impl OrgPlugin {
+    fn from_row(r: &PgRow) -> Self {
+        OrgPlugin {
+            id: r.get("id"),
+            organization_id: r.get("organization_id"),
+            name: r.get("name"),
+            description: r.get("description"),
+            component_url: r.get("component_url"),
+            icon_url: r.get("icon_url"),
+            config: r.get("config"),
+            enabled: r.get("enabled"),
+            kind: r.get("kind"),
+            wasm_sha256: r.get("wasm_sha256"),
+            events: r.get("events"),
+            block_types: r.get("block_types"),
+            fuel_limit: r.get("fuel_limit"),
+            memory_limit_mb: r.get("memory_limit_mb"),
+            manifest: r.get("manifest"),
+            version: r.get("version"),
+            publisher: r.get("publisher"),
+            permissions: r.get("permissions"),
+            signed_by: r.get("signed_by"),
+            created_at: r.get("created_at"),
+            updated_at: r.get("updated_at"),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreatePluginPayload {
+    #[serde(default)]
     pub name: String,
     pub description: Option<String>,
+    #[serde(default)]
     pub component_url: String,
     pub icon_url: Option<String>,
     pub config: Option<serde_json::Value>,
+    pub kind: Option<String>,
+    /// Módulo WebAssembly en base64 (solo `kind = "wasm"`)
+    pub wasm_module: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub block_types: Option<Vec<String>>,
+    pub fuel_limit: Option<i64>,
+    pub memory_limit_mb: Option<i32>,
+    /// Manifiesto firmado; sus campos prevalecen sobre los sueltos del payload
+    pub manifest: Option<serde_json::Value>,
+    /// Firma Ed25519 del manifiesto en base64
+    pub signature: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -44,6 +111,15 @@ pub struct UpdatePluginPayload {
     pub icon_url: Option<String>,
     pub config: Option<serde_json::Value>,
     pub enabled: Option<bool>,
+    pub wasm_module: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub block_types: Option<Vec<String>>,
+    pub fuel_limit: Option<i64>,
+    pub memory_limit_mb: Option<i32>,
+    /// Manifiesto firmado; sus campos prevalecen sobre los sueltos del payload
+    pub manifest: Option<serde_json::Value>,
+    /// Firma Ed25519 del manifiesto en base64
+    pub signature: Option<String>,
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -54,32 +130,16 @@ pub async fn list_plugins(
     Org(org_ctx): Org,
     State(pool): State<PgPool>,
 ) -> Result<Json<Vec<OrgPlugin>>, (StatusCode, String)> {
-    let rows = sqlx::query(
-        "SELECT id, organization_id, name, description, component_url, icon_url, config, enabled, created_at, updated_at
-         FROM org_plugins
-         WHERE organization_id = $1
-         ORDER BY created_at ASC",
-    )
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM org_plugins WHERE organization_id = $1 ORDER BY created_at ASC",
+        PLUGIN_COLUMNS
+    ))
     .bind(org_ctx.id)
     .fetch_all(&pool)
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
 
-    let plugins = rows
-        .into_iter()
-        .map(|r| OrgPlugin {
-            id: r.get("id"),
-            organization_id: r.get("organization_id"),
-            name: r.get("name"),
-            description: r.get("description"),
-            component_url: r.get("component_url"),
-            icon_url: r.get("icon_url"),
-            config: r.get("config"),
-            enabled: r.get("enabled"),
-            created_at: r.get("created_at"),
-            updated_at: r.get("updated_at"),
-        })
-        .collect();
+    let plugins = rows.iter().map(OrgPlugin::from_row).collect();
 
     Ok(Json(plugins))
 }
@@ -92,83 +152,257 @@ pub async fn list_enabled_plugins(
     Org(org_ctx): Org,
     State(pool): State<PgPool>,
 ) -> Result<Json<Vec<OrgPlugin>>, (StatusCode, String)> {
-    let rows = sqlx::query(
-        "SELECT id, organization_id, name, description, component_url, icon_url, config, enabled, created_at, updated_at
-         FROM org_plugins
-         WHERE organization_id = $1 AND enabled = TRUE
-         ORDER BY created_at ASC",
-    )
+    // Los plugins wasm se ejecutan en el servidor: Experience solo monta Web Components
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM org_plugins WHERE organization_id = $1 AND enabled = TRUE AND kind = 'component' ORDER BY created_at ASC",
+        PLUGIN_COLUMNS
+    ))
     .bind(org_ctx.id)
     .fetch_all(&pool)
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
 
-    let plugins = rows
-        .into_iter()
-        .map(|r| OrgPlugin {
-            id: r.get("id"),
-            organization_id: r.get("organization_id"),
-            name: r.get("name"),
-            description: r.get("description"),
-            component_url: r.get("component_url"),
-            icon_url: r.get("icon_url"),
-            config: r.get("config"),
-            enabled: r.get("enabled"),
-            created_at: r.get("created_at"),
-            updated_at: r.get("updated_at"),
-        })
-        .collect();
+    let plugins = rows.iter().map(OrgPlugin::from_row).collect();
 
     Ok(Json(plugins))
 }
 
+// ─────────────────────────────────────────────────────────────────────────────
+// Validación y réplica en el LMS
+// ─────────────────────────────────────────────────────────────────────────────
+
+fn decode_wasm_module(encoded: &str) -> Result<Vec<u8>, (StatusCode, String)> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|_| (StatusCode::UNPROCESSABLE_ENTITY, "wasm_module no es base64 válido".to_string()))?;
+    if bytes.len() > MAX_WASM_MODULE_BYTES {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, "El módulo supera los 10 MB".to_string()));
+    }
+    if !bytes.starts_with(b"\0asm") {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, "wasm_module no es un módulo WebAssembly".to_string()));
+    }
+    Ok(bytes)
+}
+
+fn validate_wasm_settings(
+    events: Option<&[String]>,
+    fuel_limit: Option<i64>,
+    memory_limit_mb: Option<i32>,
+) -> Result<(), (StatusCode, String)> {
+    if let Some(event) = events
+        .unwrap_or_default()
+        .iter()
+        .find(|e| !SUPPORTED_EVENTS.contains(&e.as_str()))
+    {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, format!("Evento no soportado: {}", event)));
+    }
+    if fuel_limit.is_some_and(|f| !(1_000_000..=1_000_000_000).contains(&f)) {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "fuel_limit debe estar entre 1.000.000 y 1.000.000.000".to_string(),
+        ));
+    }
+    if memory_limit_mb.is_some_and(|m| !(1..=256).contains(&m)) {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, "memory_limit_mb debe estar entre 1 y 256".to_string()));
+    }
+    Ok(())
+}
+
+/// El módulo subido debe ser el que fija el manifiesto firmado.
+fn check_module_hash(manifest: &PluginManifest, wasm_module: Option<&[u8]>) -> Result<(), (StatusCode, String)> {
+    match (manifest.wasm_sha256.as_deref(), wasm_module) {
+        (Some(expected), Some(module)) if hex::encode(Sha256::digest(module)) != expected.to_lowercase() => Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "El módulo no coincide con el wasm_sha256 del manifiesto".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Replica el plugin en el LMS, que emite sus tokens y ejecuta los módulos wasm
+/// (y rechaza los que no compilan). Usa un token entre servicios: el LMS no acepta
+/// permisos de plugin desde tokens de usuario.
+async fn sync_plugin_to_lms(
+    claims: &Claims,
+    organization_id: Uuid,
+    plugin: &OrgPlugin,
+    wasm_module: Option<&[u8]>,
+) -> Result<(), (StatusCode, String)> {
+    let lms_url = std::env::var("LMS_INTERNAL_URL").unwrap_or_else(|_| "http://experience:3002".to_string());
+    let token = common::auth::create_service_token(claims.sub, organization_id, common::auth::SERVICE_SCOPE_PLUGIN_SYNC)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let res = reqwest::Client::new()
+        .put(format!("{}/plugins/{}/sync", lms_url, plugin.id))
+        .bearer_auth(token)
+        .json(&serde_json::json!({
+            "name": plugin.name,
+            "kind": plugin.kind,
+            "component_url": plugin.component_url,
+            "config": plugin.config,
+            "enabled": plugin.enabled,
+            "version": plugin.version,
+            "permissions": plugin.permissions,
+            "wasm_module": wasm_module.map(|m| base64::engine::general_purpose::STANDARD.encode(m)),
+            "events": plugin.events,
+            "block_types": plugin.block_types,
+            "fuel_limit": plugin.fuel_limit,
+            "memory_limit_mb": plugin.memory_limit_mb,
+        }))
+        .send()
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+    match res.status() {
+        status if status.is_success() => Ok(()),
+        StatusCode::UNPROCESSABLE_ENTITY => Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            res.text().await.unwrap_or_else(|_| "Módulo inválido".to_string()),
+        )),
+        status => {
+            tracing::error!("El LMS rechazó la réplica del plugin {}: {}", plugin.id, status);
+            Err((StatusCode::BAD_GATEWAY, "No se pudo replicar el plugin en el LMS".to_string()))
+        }
+    }
+}
+
+async fn delete_plugin_from_lms(claims: &Claims, organization_id: Uuid, plugin_id: Uuid) -> Result<(), (StatusCode, String)> {
+    let lms_url = std::env::var("LMS_INTERNAL_URL").unwrap_or_else(|_| "http://experience:3002".to_string());
+    let token = common::auth::create_service_token(claims.sub, organization_id, common::auth::SERVICE_SCOPE_PLUGIN_SYNC)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let res = reqwest::Client::new()
+        .delete(format!("{}/plugins/{}/sync", lms_url, plugin_id))
+        .bearer_auth(token)
+        .send()
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+    if !res.status().is_success() {
+        tracing::error!("El LMS no retiró el plugin {}: {}", plugin_id, res.status());
+        return Err((StatusCode::BAD_GATEWAY, "No se pudo retirar el plugin del LMS".to_string()));
+    }
+    Ok(())
+}
+
 // ─────────────────────────────────────────────────────────────────────────────
 // POST /plugins — crear plugin
 // ─────────────────────────────────────────────────────────────────────────────
 
 pub async fn create_plugin(
     Org(org_ctx): Org,
-    _claims: Claims,
+    claims: Claims,
     State(pool): State<PgPool>,
-    Json(payload): Json<CreatePluginPayload>,
+    Json(mut payload): Json<CreatePluginPayload>,
 ) -> Result<(StatusCode, Json<OrgPlugin>), (StatusCode, String)> {
-    // Validación básica de URL (solo https permitido para componentes externos)
-    if !payload.component_url.starts_with("https://") {
-        return Err((
-            StatusCode::UNPROCESSABLE_ENTITY,
-            "component_url debe usar HTTPS".to_string(),
-        ));
+    // Un manifiesto firmado define el plugin y concede permisos: solo administradores
+    let verified = match &payload.manifest {
+        Some(manifest) => {
+            if claims.role != "admin" {
+                return Err((StatusCode::FORBIDDEN, "Solo los administradores pueden instalar plugins con manifiesto".to_string()));
+            }
+            Some(plugin_manifest::verify_manifest(&pool, org_ctx.id, manifest, payload.signature.as_deref()).await?)
+        }
+        None => None,
+    };
+    let mut permissions = Vec::new();
+    if let Some(verified) = &verified {
+        let manifest = &verified.manifest;
+        payload.name = manifest.name.clone();
+        payload.description = Some(manifest.description.clone());
+        payload.component_url = manifest.component_url.clone().unwrap_or_default();
+        payload.icon_url = manifest.icon_url.clone();
+        payload.kind = Some(manifest.kind.clone());
+        payload.events = Some(manifest.events.clone());
+        payload.block_types = Some(manifest.block_types.clone());
+        permissions = manifest.permissions.clone();
+    }
+    if payload.name.trim().is_empty() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, "name es obligatorio".to_string()));
+    }
+
+    let kind = payload.kind.as_deref().unwrap_or("component");
+    let wasm_module = match kind {
+        "component" => {
+            // Validación básica de URL (solo https permitido para componentes externos)
+            if !payload.component_url.starts_with("https://") {
+                return Err((
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "component_url debe usar HTTPS".to_string(),
+                ));
+            }
+            None
+        }
+        "wasm" => {
+            // Código ejecutado en el servidor: solo administradores
+            if claims.role != "admin" {
+                return Err((StatusCode::FORBIDDEN, "Solo los administradores pueden instalar plugins wasm".to_string()));
+            }
+            let encoded = payload
+                .wasm_module
+                .as_deref()
+                .ok_or((StatusCode::UNPROCESSABLE_ENTITY, "wasm_module es obligatorio".to_string()))?;
+            Some(decode_wasm_module(encoded)?)
+        }
+        _ => {
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, "kind debe ser component o wasm".to_string()));
+        }
+    };
+    validate_wasm_settings(payload.events.as_deref(), payload.fuel_limit, payload.memory_limit_mb)?;
+
+    let config = payload.config.take().unwrap_or(serde_json::json!({}));
+    if let Some(verified) = &verified {
+        check_module_hash(&verified.manifest, wasm_module.as_deref())?;
+        plugin_manifest::validate_config(verified.manifest.config_schema.as_ref(), &config)?;
     }
 
-    let row = sqlx::query(
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let row = sqlx::query(&format!(
         r#"
-        INSERT INTO org_plugins (organization_id, name, description, component_url, icon_url, config)
-        VALUES ($1, $2, $3, $4, $5, $6)
-        RETURNING id, organization_id, name, description, component_url, icon_url, config, enabled, created_at, updated_at
+        INSERT INTO org_plugins (organization_id, name, description, component_url, icon_url, config, kind,
+                                 wasm_module, wasm_sha256, events, block_types, fuel_limit, memory_limit_mb,
+                                 manifest, version, publisher, permissions, signature, signed_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, COALESCE($12, 50000000), COALESCE($13, 32),
+                $14, $15, $16, $17, $18, $19)
+        RETURNING {}
         "#,
-    )
+        PLUGIN_COLUMNS
+    ))
     .bind(org_ctx.id)
     .bind(&payload.name)
     .bind(payload.description.as_deref().unwrap_or(""))
     .bind(&payload.component_url)
     .bind(&payload.icon_url)
-    .bind(payload.config.unwrap_or(serde_json::json!({})))
-    .fetch_one(&pool)
+    .bind(&config)
+    .bind(kind)
+    .bind(&wasm_module)
+    .bind(wasm_module.as_ref().map(|m| hex::encode(Sha256::digest(m))))
+    .bind(payload.events.clone().unwrap_or_default())
+    .bind(payload.block_types.clone().unwrap_or_default())
+    .bind(payload.fuel_limit)
+    .bind(payload.memory_limit_mb)
+    .bind(&payload.manifest)
+    .bind(verified.as_ref().map(|v| v.manifest.version.clone()))
+    .bind(verified.as_ref().map(|v| v.manifest.publisher.clone()))
+    .bind(&permissions)
+    .bind(verified.as_ref().and(payload.signature.as_ref()))
+    .bind(verified.as_ref().map(|v| v.publisher_key_id))
+    .fetch_one(&mut *tx)
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let plugin = OrgPlugin::from_row(&row);
+
+    sync_plugin_to_lms(&claims, org_ctx.id, &plugin, wasm_module.as_deref()).await?;
+
+    tx.commit()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
 
-    Ok((StatusCode::CREATED, Json(OrgPlugin {
-        id: row.get("id"),
-        organization_id: row.get("organization_id"),
-        name: row.get("name"),
-        description: row.get("description"),
-        component_url: row.get("component_url"),
-        icon_url: row.get("icon_url"),
-        config: row.get("config"),
-        enabled: row.get("enabled"),
-        created_at: row.get("created_at"),
-        updated_at: row.get("updated_at"),
-    })))
+    Ok((StatusCode::CREATED, Json(plugin)))
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -177,28 +411,32 @@ pub async fn create_plugin(
 
 pub async fn update_plugin(
     Org(org_ctx): Org,
-    _claims: Claims,
+    claims: Claims,
     State(pool): State<PgPool>,
     Path(plugin_id): Path<Uuid>,
-    Json(payload): Json<UpdatePluginPayload>,
+    Json(mut payload): Json<UpdatePluginPayload>,
 ) -> Result<Json<OrgPlugin>, (StatusCode, String)> {
     // Verificar que pertenece a esta org
-    let exists: bool = sqlx::query_scalar(
-        "SELECT EXISTS(SELECT 1 FROM org_plugins WHERE id = $1 AND organization_id = $2)",
+    let existing = sqlx::query(
+        "SELECT kind, manifest, config FROM org_plugins WHERE id = $1 AND organization_id = $2",
     )
     .bind(plugin_id)
     .bind(org_ctx.id)
-    .fetch_one(&pool)
+    .fetch_optional(&pool)
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
 
-    if !exists {
+    let Some(existing) = existing else {
         return Err((StatusCode::NOT_FOUND, "Plugin no encontrado".to_string()));
-    }
+    };
+    let kind: String = existing.get("kind");
+    let existing_manifest: Option<serde_json::Value> = existing.get("manifest");
+    let existing_config: serde_json::Value = existing.get("config");
+    let is_wasm = kind == "wasm";
 
     // Validar URL si se actualiza
     if let Some(url) = &payload.component_url {
-        if !url.starts_with("https://") {
+        if !is_wasm && !url.starts_with("https://") {
             return Err((
                 StatusCode::UNPROCESSABLE_ENTITY,
                 "component_url debe usar HTTPS".to_string(),
@@ -206,19 +444,104 @@ pub async fn update_plugin(
         }
     }
 
-    let row = sqlx::query(
+    if (is_wasm || payload.manifest.is_some()) && claims.role != "admin" {
+        return Err((StatusCode::FORBIDDEN, "Solo los administradores pueden modificar este plugin".to_string()));
+    }
+    if !is_wasm && payload.wasm_module.is_some() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, "Un plugin component no admite wasm_module".to_string()));
+    }
+
+    // Con manifiesto firmado, lo que define el plugin solo cambia con otro manifiesto
+    let verified = match &payload.manifest {
+        Some(manifest) => {
+            let verified =
+                plugin_manifest::verify_manifest(&pool, org_ctx.id, manifest, payload.signature.as_deref()).await?;
+            if verified.manifest.kind != kind {
+                return Err((StatusCode::UNPROCESSABLE_ENTITY, "El manifiesto no puede cambiar el tipo del plugin".to_string()));
+            }
+            Some(verified)
+        }
+        None => {
+            if existing_manifest.is_some()
+                && (payload.component_url.is_some()
+                    || payload.wasm_module.is_some()
+                    || payload.events.is_some()
+                    || payload.block_types.is_some())
+            {
+                return Err((
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "Estos campos solo cambian con un nuevo manifiesto firmado".to_string(),
+                ));
+            }
+            None
+        }
+    };
+    let mut permissions = None;
+    if let Some(verified) = &verified {
+        let manifest = &verified.manifest;
+        payload.name = Some(manifest.name.clone());
+        payload.description = Some(manifest.description.clone());
+        payload.component_url = manifest.component_url.clone().or(payload.component_url.take());
+        payload.icon_url = manifest.icon_url.clone();
+        payload.events = Some(manifest.events.clone());
+        payload.block_types = Some(manifest.block_types.clone());
+        permissions = Some(manifest.permissions.clone());
+    }
+
+    let wasm_module = payload.wasm_module.as_deref().map(decode_wasm_module).transpose()?;
+    validate_wasm_settings(payload.events.as_deref(), payload.fuel_limit, payload.memory_limit_mb)?;
+
+    // La configuración vigente debe cumplir el esquema vigente
+    let schema = match &verified {
+        Some(verified) => verified.manifest.config_schema.clone(),
+        None => existing_manifest
+            .as_ref()
+            .and_then(|m| m.get("config_schema"))
+            .filter(|s| !s.is_null())
+            .cloned(),
+    };
+    if let Some(verified) = &verified {
+        if verified.manifest.kind == "wasm" && wasm_module.is_none() {
+            return Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Un nuevo manifiesto wasm requiere subir el módulo".to_string(),
+            ));
+        }
+        check_module_hash(&verified.manifest, wasm_module.as_deref())?;
+    }
+    plugin_manifest::validate_config(schema.as_ref(), payload.config.as_ref().unwrap_or(&existing_config))?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let row = sqlx::query(&format!(
         r#"
         UPDATE org_plugins SET
-            name          = COALESCE($3, name),
-            description   = COALESCE($4, description),
-            component_url = COALESCE($5, component_url),
-            icon_url      = COALESCE($6, icon_url),
-            config        = COALESCE($7, config),
-            enabled       = COALESCE($8, enabled)
+            name            = COALESCE($3, name),
+            description     = COALESCE($4, description),
+            component_url   = COALESCE($5, component_url),
+            icon_url        = COALESCE($6, icon_url),
+            config          = COALESCE($7, config),
+            enabled         = COALESCE($8, enabled),
+            wasm_module     = COALESCE($9, wasm_module),
+            wasm_sha256     = COALESCE($10, wasm_sha256),
+            events          = COALESCE($11, events),
+            block_types     = COALESCE($12, block_types),
+            fuel_limit      = COALESCE($13, fuel_limit),
+            memory_limit_mb = COALESCE($14, memory_limit_mb),
+            manifest        = COALESCE($15, manifest),
+            version         = COALESCE($16, version),
+            publisher       = COALESCE($17, publisher),
+            permissions     = COALESCE($18, permissions),
+            signature       = COALESCE($19, signature),
+            signed_by       = COALESCE($20, signed_by)
         WHERE id = $1 AND organization_id = $2
-        RETURNING id, organization_id, name, description, component_url, icon_url, config, enabled, created_at, updated_at
+        RETURNING {}, wasm_module
         "#,
-    )
+        PLUGIN_COLUMNS
+    ))
     .bind(plugin_id)
     .bind(org_ctx.id)
     .bind(&payload.name)
@@ -227,22 +550,31 @@ pub async fn update_plugin(
     .bind(&payload.icon_url)
     .bind(&payload.config)
     .bind(payload.enabled)
-    .fetch_one(&pool)
+    .bind(&wasm_module)
+    .bind(wasm_module.as_ref().map(|m| hex::encode(Sha256::digest(m))))
+    .bind(&payload.events)
+    .bind(&payload.block_types)
+    .bind(payload.fuel_limit)
+    .bind(payload.memory_limit_mb)
+    .bind(verified.as_ref().and(payload.manifest.as_ref()))
+    .bind(verified.as_ref().map(|v| v.manifest.version.clone()))
+    .bind(verified.as_ref().map(|v| v.manifest.publisher.clone()))
+    .bind(&permissions)
+    .bind(verified.as_ref().and(payload.signature.as_ref()))
+    .bind(verified.as_ref().map(|v| v.publisher_key_id))
+    .fetch_one(&mut *tx)
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let plugin = OrgPlugin::from_row(&row);
 
-    Ok(Json(OrgPlugin {
-        id: row.get("id"),
-        organization_id: row.get("organization_id"),
-        name: row.get("name"),
-        description: row.get("description"),
-        component_url: row.get("component_url"),
-        icon_url: row.get("icon_url"),
-        config: row.get("config"),
-        enabled: row.get("enabled"),
-        created_at: row.get("created_at"),
-        updated_at: row.get("updated_at"),
-    }))
+    let module: Option<Vec<u8>> = row.get("wasm_module");
+    sync_plugin_to_lms(&claims, org_ctx.id, &plugin, module.as_deref()).await?;
+
+    tx.commit()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(plugin))
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -251,22 +583,35 @@ pub async fn update_plugin(
 
 pub async fn delete_plugin(
     Org(org_ctx): Org,
-    _claims: Claims,
+    claims: Claims,
     State(pool): State<PgPool>,
     Path(plugin_id): Path<Uuid>,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    let result = sqlx::query(
-        "DELETE FROM org_plugins WHERE id = $1 AND organization_id = $2",
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let kind: Option<String> = sqlx::query_scalar(
+        "DELETE FROM org_plugins WHERE id = $1 AND organization_id = $2 RETURNING kind",
     )
     .bind(plugin_id)
     .bind(org_ctx.id)
-    .execute(&pool)
+    .fetch_optional(&mut *tx)
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
 
-    if result.rows_affected() == 0 {
-        return Err((StatusCode::NOT_FOUND, "Plugin no encontrado".to_string()));
+    match kind.as_deref() {
+        None => return Err((StatusCode::NOT_FOUND, "Plugin no encontrado".to_string())),
+        Some("wasm") if claims.role != "admin" => {
+            return Err((StatusCode::FORBIDDEN, "Solo los administradores pueden eliminar plugins wasm".to_string()));
+        }
+        Some(_) => delete_plugin_from_lms(&claims, org_ctx.id, plugin_id).await?,
     }
 
+    tx.commit()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
     Ok(StatusCode::NO_CONTENT)
 }
@@ -44,7 +44,14 @@ updated_at,
 embedding::text AS embedding,
 embedding_updated_at,
 source_asset_id,
-unit_number
+unit_number,
+irt_model,
+irt_difficulty,
+irt_discrimination,
+irt_se_difficulty,
+irt_sample_size,
+point_biserial,
+irt_calibrated_at
 "#;
 
 fn normalize_answer_keywords_value(value: serde_json::Value) -> serde_json::Value {
@@ -397,6 +404,8 @@ pub async fn list_questions(
         && filters.source.is_none()
         && filters.search.is_none()
         && filters.has_audio.is_none()
+        && filters.min_irt_difficulty.is_none()
+        && filters.max_irt_difficulty.is_none()
     {
         // Sin filtros - consulta simple
         sqlx::query_as::<_, QuestionBank>(
@@ -489,6 +498,19 @@ pub async fn list_questions(
         .bind(org_ctx.id)
         .fetch_all(&pool)
         .await
+    } else if filters.min_irt_difficulty.is_some() || filters.max_irt_difficulty.is_some() {
+        // Selección por dificultad calibrada (TRI) para plantillas de evaluación
+        sqlx::query_as::<_, QuestionBank>(
+            &format!(
+                "SELECT {} FROM question_bank WHERE organization_id = $1 AND is_archived = false AND irt_difficulty IS NOT NULL AND irt_difficulty >= COALESCE($2, irt_difficulty) AND irt_difficulty <= COALESCE($3, irt_difficulty) ORDER BY irt_difficulty ASC",
+                QUESTION_BANK_SELECT_COLUMNS
+            )
+        )
+        .bind(org_ctx.id)
+        .bind(filters.min_irt_difficulty)
+        .bind(filters.max_irt_difficulty)
+        .fetch_all(&pool)
+        .await
     } else {
         // Default fallback
         sqlx::query_as::<_, QuestionBank>(
@@ -636,6 +658,268 @@ pub async fn delete_question(
     Ok(StatusCode::NO_CONTENT)
 }
 
+// ==================== Calibración TRI ====================
+
+#[derive(Debug, Deserialize)]
+pub struct CalibrateQuestionsQuery {
+    pub model: Option<String>,
+    pub min_responses: Option<i64>,
+}
+
+/// Parámetros estimados por el LMS (`GET /psychometrics/calibration`).
+#[derive(Debug, Deserialize)]
+struct LmsItemCalibration {
+    question_bank_id: Uuid,
+    model: String,
+    responses: i64,
+    point_biserial: Option<f64>,
+    difficulty: f64,
+    discrimination: f64,
+    se_difficulty: Option<f64>,
+}
+
+/// POST /api/question-bank/calibrate - Actualizar la calibración TRI desde las respuestas del LMS
+pub async fn calibrate_questions(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    headers: axum::http::HeaderMap,
+    Query(query): Query<CalibrateQuestionsQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    if claims.role != "admin" && claims.role != "instructor" {
+        return Err((StatusCode::FORBIDDEN, "Se requiere rol instructor o admin".to_string()));
+    }
+
+    let lms_url =
+        std::env::var("LMS_INTERNAL_URL").unwrap_or_else(|_| "http://experience:3002".to_string());
+    let mut request = reqwest::Client::new()
+        .get(format!("{}/psychometrics/calibration", lms_url))
+        .query(&[
+            ("model", query.model.clone().unwrap_or_else(|| "2pl".to_string())),
+            ("min_responses", query.min_responses.unwrap_or(30).to_string()),
+        ])
+        .header("X-Organization-Id", org_ctx.id.to_string());
+    // El LMS comparte el secreto JWT: se reenvía la sesión del usuario
+    if let Some(auth) = headers.get(axum::http::header::AUTHORIZATION) {
+        request = request.header(axum::http::header::AUTHORIZATION, auth.clone());
+    }
+
+    let res = request
+        .send()
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+    if !res.status().is_success() {
+        return Err((
+            StatusCode::BAD_GATEWAY,
+            format!("El LMS respondió {} al calcular la calibración", res.status()),
+        ));
+    }
+    let calibrations = res
+        .json::<Vec<LmsItemCalibration>>()
+        .await
+        .map_err(|_| (StatusCode::BAD_GATEWAY, "Respuesta de calibración inválida".to_string()))?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let mut updated = 0u64;
+    for item in &calibrations {
+        let result = sqlx::query(
+            r#"
+            UPDATE question_bank
+            SET irt_model = $1,
+                irt_difficulty = $2,
+                irt_discrimination = $3,
+                irt_se_difficulty = $4,
+                irt_sample_size = $5,
+                point_biserial = $6,
+                irt_calibrated_at = NOW()
+            WHERE id = $7 AND organization_id = $8
+            "#
+        )
+        .bind(&item.model)
+        .bind(item.difficulty)
+        .bind(item.discrimination)
+        .bind(item.se_difficulty)
+        .bind(item.responses as i32)
+        .bind(item.point_biserial)
+        .bind(item.question_bank_id)
+        .bind(org_ctx.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+        updated += result.rows_affected();
+    }
+
+    tx.commit()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(serde_json::json!({
+        "calibrated": updated,
+        "received": calibrations.len(),
+    })))
+}
+
+// ==================== Pools de Preguntas ====================
+
+/// Criterios de un pool en un bloque de cuestionario (`quiz_data.pool`). El LMS sortea
+/// `count` ítems por alumno e intento; `shuffle_options` lo interpreta el LMS.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QuestionPoolSpec {
+    pub count: i32,
+    pub tags: Option<Vec<String>>,
+    pub difficulty: Option<Vec<String>>,
+    pub question_types: Option<Vec<String>>,
+    pub skills: Option<Vec<String>>,
+}
+
+/// Tope de ítems copiados a la lección por pool, para acotar el tamaño de los metadatos.
+const MAX_POOL_CANDIDATES: i64 = 500;
+
+#[derive(sqlx::FromRow)]
+struct PoolCandidateRow {
+    id: Uuid,
+    question_text: String,
+    question_type: String,
+    options: Option<serde_json::Value>,
+    correct_answer: Option<serde_json::Value>,
+    explanation: Option<String>,
+    points: i32,
+    difficulty: Option<String>,
+    skill_assessed: Option<String>,
+    audio_url: Option<String>,
+    media_url: Option<String>,
+    media_type: Option<String>,
+}
+
+/// Ítems del banco que cumplen los criterios. Solo tipos con opciones (opción múltiple
+/// y verdadero/falso), que el LMS puede barajar y calificar en el servidor.
+async fn pool_candidates(
+    pool: &PgPool,
+    org_id: Uuid,
+    spec: &QuestionPoolSpec,
+) -> Result<Vec<serde_json::Value>, sqlx::Error> {
+    let rows: Vec<PoolCandidateRow> = sqlx::query_as(
+        r#"
+        SELECT id, question_text, question_type::text AS question_type, options, correct_answer,
+            explanation, points, difficulty, skill_assessed, audio_url, media_url, media_type
+        FROM question_bank
+        WHERE organization_id = $1
+          AND is_active = true AND is_archived = false
+          AND question_type IN ('multiple-choice', 'true-false')
+          AND ($2::text[] IS NULL OR tags && $2)
+          AND ($3::text[] IS NULL OR difficulty = ANY($3))
+          AND ($4::text[] IS NULL OR question_type::text = ANY($4))
+          AND ($5::text[] IS NULL OR skill_assessed = ANY($5))
+        ORDER BY created_at
+        LIMIT $6
+        "#,
+    )
+    .bind(org_id)
+    .bind(&spec.tags)
+    .bind(&spec.difficulty)
+    .bind(&spec.question_types)
+    .bind(&spec.skills)
+    .bind(MAX_POOL_CANDIDATES)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            let options = match row.options.as_ref().and_then(|o| o.as_array()) {
+                Some(options) if !options.is_empty() => options.clone(),
+                _ if row.question_type == "true-false" => vec![
+                    serde_json::Value::String("True".to_string()),
+                    serde_json::Value::String("False".to_string()),
+                ],
+                _ => return None,
+            };
+            let correct = correct_option_indices(&options, row.correct_answer.as_ref()?);
+            if correct.is_empty() || correct.iter().any(|&i| i < 0 || i as usize >= options.len()) {
+                return None;
+            }
+            Some(serde_json::json!({
+                "id": row.id.to_string(),
+                "question_bank_id": row.id.to_string(),
+                "type": row.question_type,
+                "question": row.question_text,
+                "options": options,
+                "correct": correct,
+                "explanation": row.explanation.unwrap_or_default(),
+                "points": row.points,
+                "difficulty": row.difficulty,
+                "skill": row.skill_assessed,
+                "audio_url": row.audio_url,
+                "media_url": row.media_url,
+                "media_type": row.media_type,
+            }))
+        })
+        .collect())
+}
+
+/// Copia en cada bloque de cuestionario con `quiz_data.pool` los ítems candidatos
+/// (`pool.items`), de modo que el LMS pueda sortear variantes sin acceder al banco.
+/// Se ejecuta al publicar: el LMS recibe una instantánea del banco.
+pub async fn resolve_lesson_question_pools(
+    pool: &PgPool,
+    org_id: Uuid,
+    lesson: &mut common::models::Lesson,
+) -> Result<(), sqlx::Error> {
+    let block_lists = [
+        lesson.metadata.as_mut().and_then(|m| m.get_mut("blocks")),
+        lesson.content_blocks.as_mut(),
+    ];
+    for blocks in block_lists.into_iter().flatten() {
+        let Some(blocks) = blocks.as_array_mut() else {
+            continue;
+        };
+        for block in blocks {
+            let Some(pool_value) = block.get_mut("quiz_data").and_then(|q| q.get_mut("pool")) else {
+                continue;
+            };
+            let Ok(spec) = serde_json::from_value::<QuestionPoolSpec>(pool_value.clone()) else {
+                tracing::warn!("Pool de preguntas inválido en la lección {}", lesson.id);
+                continue;
+            };
+            let items = pool_candidates(pool, org_id, &spec).await?;
+            if (items.len() as i32) < spec.count {
+                tracing::warn!(
+                    "El pool de la lección {} pide {} ítems pero solo hay {} disponibles",
+                    lesson.id,
+                    spec.count,
+                    items.len()
+                );
+            }
+            pool_value["items"] = serde_json::Value::Array(items);
+        }
+    }
+    Ok(())
+}
+
+/// POST /api/question-bank/pool-preview - Cuántos ítems cumplen los criterios de un pool
+pub async fn preview_question_pool(
+    Org(org_ctx): Org,
+    State(pool): State<PgPool>,
+    Json(spec): Json<QuestionPoolSpec>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    if spec.count < 1 {
+        return Err((StatusCode::BAD_REQUEST, "El pool debe sortear al menos un ítem".to_string()));
+    }
+    let items = pool_candidates(&pool, org_ctx.id, &spec)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(serde_json::json!({
+        "available": items.len(),
+        "requested": spec.count,
+        "sufficient": items.len() as i32 >= spec.count,
+    })))
+}
+
 // ==================== Importar desde MySQL ====================
 
 /// POST /api/question-bank/import-mysql - Importar preguntas desde el banco de preguntas de MySQL
@@ -905,6 +1189,42 @@ pub async fn import_from_mysql(
 
 // ==================== Auxiliares ====================
 
+/// Valor de verdad de una opción de verdadero/falso según su texto.
+fn boolean_option(option: &serde_json::Value) -> Option<bool> {
+    if let Some(value) = option.as_bool() {
+        return Some(value);
+    }
+    match option.as_str()?.trim().to_lowercase().as_str() {
+        "true" | "verdadero" | "v" | "t" | "sí" | "si" | "yes" => Some(true),
+        "false" | "falso" | "f" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Índices de las opciones correctas. Acepta índices, textos de opción y booleanos;
+/// un booleano se busca por el texto de la opción, sin suponer su orden.
+pub fn correct_option_indices(options: &[serde_json::Value], correct: &serde_json::Value) -> Vec<i64> {
+    let index_of = |value: &serde_json::Value| -> Option<i64> {
+        match value {
+            serde_json::Value::Number(n) => n.as_i64(),
+            serde_json::Value::Bool(b) => options
+                .iter()
+                .position(|o| boolean_option(o) == Some(*b))
+                .map(|i| i as i64),
+            serde_json::Value::String(text) => options
+                .iter()
+                .position(|o| o.as_str().is_some_and(|o| o.trim().eq_ignore_ascii_case(text.trim())))
+                .map(|i| i as i64),
+            _ => None,
+        }
+    };
+    match correct {
+        serde_json::Value::Array(values) => values.iter().filter_map(index_of).collect(),
+        value => index_of(value).into_iter().collect(),
+    }
+}
+
+
 fn map_mysql_question_type(mysql_type: i32, tipo_nombre: Option<&str>) -> QuestionBankType {
     // Mapear tipos de preguntas de MySQL a tipos de plataforma
     // Primero intentar por nombre, luego por ID como respaldo
@@ -2358,3 +2678,26 @@ pub async fn import_from_sam_diagnostico(
         "errors":   errors,
     })))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn boolean_answers_match_option_text_not_position() {
+        let false_first = [json!("Falso"), json!("Verdadero")];
+        assert_eq!(correct_option_indices(&false_first, &json!(true)), vec![1]);
+        assert_eq!(correct_option_indices(&false_first, &json!(false)), vec![0]);
+
+        let english = [json!("True"), json!("False")];
+        assert_eq!(correct_option_indices(&english, &json!(false)), vec![1]);
+
+        // Sin opciones reconocibles no se adivina la respuesta
+        let unrelated = [json!("Madrid"), json!("Lima")];
+        assert!(correct_option_indices(&unrelated, &json!(true)).is_empty());
+
+        assert_eq!(correct_option_indices(&english, &json!(["false"])), vec![1]);
+        assert_eq!(correct_option_indices(&english, &json!(0)), vec![0]);
+    }
+}
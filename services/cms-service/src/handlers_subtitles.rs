@@ -0,0 +1,909 @@
+/// Pistas de subtítulos multilingües por lección.
+///
+/// La transcripción de la lección es la pista del idioma original. Las demás se
+/// generan traduciendo cue a cue en lotes: el modelo recibe los textos numerados
+/// (más unos cues previos como contexto) y debe devolver el mismo número de
+/// traducciones, de modo que cada una conserva los tiempos y el hablante de su cue.
+/// Si un lote vuelve incompleto se traduce cue por cue, y una traducción que no cabe
+/// en las líneas de un subtítulo se reparte en varios cues. Las pistas quedan en
+/// borrador hasta que un instructor las revisa y aprueba (con las mismas reglas de
+/// cues que una edición); solo las aprobadas se sirven en `/lessons/{id}/vtt?lang=`
+/// y `/lessons/{id}/srt?lang=`.
+///
+/// La traducción corre en segundo plano con un límite de tiempo; las pistas que siguen
+/// en `translating` pasado ese plazo (p. ej. tras un reinicio) se marcan como
+/// fallidas en `fail_stale_translations` y pueden volver a encolarse.
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use common::{
+    auth::Claims,
+    middleware::Org,
+    tenancy,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::stt::{Cue, MAX_LINE_CHARS, MAX_LINES, cues_from_json, wrap_lines};
+
+/// Cues por petición de traducción
+const TRANSLATION_BATCH: usize = 25;
+/// Cues anteriores que acompañan a cada lote como contexto
+const TRANSLATION_CONTEXT: usize = 3;
+const MAX_CUES: usize = 5000;
+/// Tiempo máximo de una traducción en segundo plano
+const TRANSLATION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30 * 60);
+/// Antigüedad a partir de la cual una pista en `translating` se da por interrumpida
+const STALE_TRANSLATION_MINUTES: i32 = 35;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct SubtitleTrack {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub lesson_id: Uuid,
+    pub language: String,
+    pub label: String,
+    pub source_language: Option<String>,
+    pub origin: String,
+    pub status: String,
+    pub cues: Value,
+    pub error: Option<String>,
+    pub edited_by: Option<Uuid>,
+    pub approved_by: Option<Uuid>,
+    pub approved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct SubtitleTrackSummary {
+    pub language: String,
+    pub label: String,
+    pub source_language: Option<String>,
+    pub origin: String,
+    pub status: String,
+    pub cue_count: i32,
+    pub error: Option<String>,
+    pub approved_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubtitleTrackList {
+    /// Idioma detectado en la transcripción
+    pub original_language: Option<String>,
+    pub original_cue_count: usize,
+    pub tracks: Vec<SubtitleTrackSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AvailableSubtitle {
+    pub language: String,
+    pub label: String,
+    pub original: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubtitleQuery {
+    pub lang: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TranslateSubtitlesPayload {
+    pub languages: Vec<String>,
+    /// Rehace también las pistas existentes (incluidas las editadas o aprobadas)
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateSubtitleTrackPayload {
+    pub label: Option<String>,
+    pub cues: Vec<Cue>,
+}
+
+fn internal_error() -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+}
+
+/// Nombre en inglés del idioma, para las instrucciones del modelo.
+fn language_name(code: &str) -> &str {
+    match code {
+        "en" => "English",
+        "es" => "Spanish",
+        "pt" => "Portuguese",
+        "fr" => "French",
+        "de" => "German",
+        "it" => "Italian",
+        "nl" => "Dutch",
+        "ja" => "Japanese",
+        "zh" => "Chinese",
+        "ko" => "Korean",
+        "ru" => "Russian",
+        "ar" => "Arabic",
+        other => other,
+    }
+}
+
+/// Nombre del idioma en ese mismo idioma, para el selector de Experience.
+pub fn language_label(code: &str) -> String {
+    match code {
+        "en" => "English",
+        "es" => "Español",
+        "pt" => "Português",
+        "fr" => "Français",
+        "de" => "Deutsch",
+        "it" => "Italiano",
+        "nl" => "Nederlands",
+        "ja" => "日本語",
+        "zh" => "中文",
+        "ko" => "한국어",
+        "ru" => "Русский",
+        "ar" => "العربية",
+        other => return other.to_uppercase(),
+    }
+    .to_string()
+}
+
+/// Código ISO 639-1 (o 639-2), opcionalmente con región: `en`, `pt-BR`.
+fn parse_language(code: &str) -> Result<String, (StatusCode, String)> {
+    let code = code.trim();
+    let (base, region) = code.split_once('-').map_or((code, None), |(b, r)| (b, Some(r)));
+    let valid = (2..=3).contains(&base.len())
+        && base.chars().all(|c| c.is_ascii_alphabetic())
+        && region.is_none_or(|r| r.len() == 2 && r.chars().all(|c| c.is_ascii_alphabetic()));
+    if !valid {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, format!("Código de idioma inválido: {}", code)));
+    }
+    Ok(match region {
+        Some(region) => format!("{}-{}", base.to_lowercase(), region.to_uppercase()),
+        None => base.to_lowercase(),
+    })
+}
+
+/// Curso y transcripción de la lección dentro de la organización.
+async fn lesson_context(
+    pool: &PgPool,
+    organization_id: Uuid,
+    lesson_id: Uuid,
+) -> Result<(Uuid, Option<Value>), (StatusCode, String)> {
+    sqlx::query_as(
+        "SELECT m.course_id, l.transcription FROM lessons l JOIN modules m ON l.module_id = m.id WHERE l.id = $1 AND l.organization_id = $2",
+    )
+    .bind(lesson_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| internal_error())?
+    .ok_or((StatusCode::NOT_FOUND, "Lección no encontrada".to_string()))
+}
+
+async fn require_course_staff(pool: &PgPool, claims: &Claims, course_id: Uuid) -> Result<(), (StatusCode, String)> {
+    if claims.role != "admin" && claims.role != "instructor" {
+        return Err((StatusCode::FORBIDDEN, "Se requiere rol instructor o admin".to_string()));
+    }
+    if !crate::handlers::check_course_access(pool, course_id, claims.sub, &claims.role).await? {
+        return Err((StatusCode::FORBIDDEN, "No tienes acceso a este curso".to_string()));
+    }
+    Ok(())
+}
+
+fn original_language(transcription: Option<&Value>) -> Option<String> {
+    transcription
+        .and_then(|t| t["language"].as_str())
+        .map(crate::stt::normalize_language)
+}
+
+/// Cues del idioma pedido: la pista aprobada o, para el idioma original (o sin
+/// `lang`), la transcripción de la lección.
+pub async fn resolve_cues(
+    pool: &PgPool,
+    organization_id: Uuid,
+    lesson_id: Uuid,
+    lang: Option<&str>,
+) -> Result<Vec<Cue>, StatusCode> {
+    let (_, transcription) = lesson_context(pool, organization_id, lesson_id)
+        .await
+        .map_err(|(status, _)| status)?;
+    let original = original_language(transcription.as_ref());
+    let lang = match lang {
+        Some(lang) => Some(parse_language(lang).map_err(|_| StatusCode::BAD_REQUEST)?),
+        None => original.clone(),
+    };
+
+    if let Some(lang) = &lang {
+        let approved: Option<Value> = sqlx::query_scalar(
+            "SELECT cues FROM lesson_subtitle_tracks WHERE lesson_id = $1 AND organization_id = $2 AND language = $3 AND status = 'approved'",
+        )
+        .bind(lesson_id)
+        .bind(organization_id)
+        .bind(lang)
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        if let Some(cues) = approved {
+            return Ok(cues_from_json(&cues));
+        }
+    }
+
+    // Sin idioma detectado la transcripción se sirve para cualquier idioma sin pista propia
+    if lang.is_none() || original.is_none() || lang == original {
+        let cues = transcription.map(|t| cues_from_json(&t["cues"])).unwrap_or_default();
+        if !cues.is_empty() {
+            return Ok(cues);
+        }
+    }
+    Err(StatusCode::NOT_FOUND)
+}
+
+/// GET /lessons/{id}/subtitles - Pistas de la lección (instructores)
+pub async fn list_subtitle_tracks(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(lesson_id): Path<Uuid>,
+) -> Result<Json<SubtitleTrackList>, (StatusCode, String)> {
+    let (course_id, transcription) = lesson_context(&pool, org_ctx.id, lesson_id).await?;
+    require_course_staff(&pool, &claims, course_id).await?;
+
+    let tracks = sqlx::query_as::<_, SubtitleTrackSummary>(
+        r#"
+        SELECT language, label, source_language, origin, status, jsonb_array_length(cues) AS cue_count,
+               error, approved_at, updated_at
+        FROM lesson_subtitle_tracks
+        WHERE lesson_id = $1 AND organization_id = $2
+        ORDER BY language
+        "#,
+    )
+    .bind(lesson_id)
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| internal_error())?;
+
+    Ok(Json(SubtitleTrackList {
+        original_language: original_language(transcription.as_ref()),
+        original_cue_count: transcription.map(|t| cues_from_json(&t["cues"]).len()).unwrap_or(0),
+        tracks,
+    }))
+}
+
+/// GET /lessons/{id}/subtitles/available - Idiomas con subtítulos publicados
+pub async fn list_available_subtitles(
+    Org(org_ctx): Org,
+    State(pool): State<PgPool>,
+    Path(lesson_id): Path<Uuid>,
+) -> Result<Json<Vec<AvailableSubtitle>>, (StatusCode, String)> {
+    let (_, transcription) = lesson_context(&pool, org_ctx.id, lesson_id).await?;
+    let original = original_language(transcription.as_ref());
+    let has_transcription = transcription.is_some_and(|t| !cues_from_json(&t["cues"]).is_empty());
+
+    let approved: Vec<(String, String)> = sqlx::query_as(
+        "SELECT language, label FROM lesson_subtitle_tracks WHERE lesson_id = $1 AND organization_id = $2 AND status = 'approved' ORDER BY language",
+    )
+    .bind(lesson_id)
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| internal_error())?;
+
+    let mut available = Vec::new();
+    if let Some(language) = original.as_ref().filter(|_| has_transcription) {
+        available.push(AvailableSubtitle {
+            language: language.clone(),
+            label: language_label(language),
+            original: true,
+        });
+    }
+    for (language, label) in approved {
+        if Some(&language) != original.as_ref() || !has_transcription {
+            available.push(AvailableSubtitle {
+                original: Some(&language) == original.as_ref(),
+                language,
+                label,
+            });
+        }
+    }
+    Ok(Json(available))
+}
+
+/// GET /lessons/{id}/subtitles/{lang} - Pista completa para editarla
+pub async fn get_subtitle_track(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path((lesson_id, lang)): Path<(Uuid, String)>,
+) -> Result<Json<SubtitleTrack>, (StatusCode, String)> {
+    let (course_id, _) = lesson_context(&pool, org_ctx.id, lesson_id).await?;
+    require_course_staff(&pool, &claims, course_id).await?;
+    let lang = parse_language(&lang)?;
+
+    let track = sqlx::query_as::<_, SubtitleTrack>(
+        "SELECT * FROM lesson_subtitle_tracks WHERE lesson_id = $1 AND organization_id = $2 AND language = $3",
+    )
+    .bind(lesson_id)
+    .bind(org_ctx.id)
+    .bind(&lang)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| internal_error())?
+    .ok_or((StatusCode::NOT_FOUND, "Pista de subtítulos no encontrada".to_string()))?;
+
+    Ok(Json(track))
+}
+
+/// Ordena y valida los cues editados y aplica las reglas de líneas.
+fn normalize_cues(mut cues: Vec<Cue>) -> Result<Vec<Cue>, (StatusCode, String)> {
+    let invalid = |message: String| (StatusCode::UNPROCESSABLE_ENTITY, message);
+    if cues.is_empty() || cues.len() > MAX_CUES {
+        return Err(invalid(format!("La pista debe tener entre 1 y {} cues", MAX_CUES)));
+    }
+    cues.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    let mut previous_end = 0.0_f64;
+    for (index, cue) in cues.iter_mut().enumerate() {
+        let number = index + 1;
+        if !cue.start.is_finite() || !cue.end.is_finite() || cue.start < 0.0 || cue.end <= cue.start {
+            return Err(invalid(format!("El cue {} tiene tiempos inválidos", number)));
+        }
+        if cue.start < previous_end - 0.001 {
+            return Err(invalid(format!("El cue {} se superpone con el anterior", number)));
+        }
+        previous_end = cue.end;
+
+        let lines: Vec<&str> = cue.text.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+        if lines.is_empty() {
+            return Err(invalid(format!("El cue {} está vacío", number)));
+        }
+        let fits = lines.len() <= MAX_LINES && lines.iter().all(|l| l.chars().count() <= MAX_LINE_CHARS);
+        let lines = if fits { lines.iter().map(|l| l.to_string()).collect() } else { wrap_lines(&lines.join(" ")) };
+        if lines.len() > MAX_LINES {
+            return Err(invalid(format!(
+                "El cue {} supera {} líneas de {} caracteres",
+                number, MAX_LINES, MAX_LINE_CHARS
+            )));
+        }
+        cue.text = lines.join("\n");
+        cue.speaker = cue.speaker.take().map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    }
+    Ok(cues)
+}
+
+/// PUT /lessons/{id}/subtitles/{lang} - Guarda la pista editada (vuelve a borrador)
+pub async fn update_subtitle_track(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path((lesson_id, lang)): Path<(Uuid, String)>,
+    Json(payload): Json<UpdateSubtitleTrackPayload>,
+) -> Result<Json<SubtitleTrack>, (StatusCode, String)> {
+    let (course_id, transcription) = lesson_context(&pool, org_ctx.id, lesson_id).await?;
+    require_course_staff(&pool, &claims, course_id).await?;
+    let lang = parse_language(&lang)?;
+    let cues = normalize_cues(payload.cues)?;
+    let label = payload
+        .label
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .unwrap_or_else(|| language_label(&lang));
+
+    let track = sqlx::query_as::<_, SubtitleTrack>(
+        r#"
+        INSERT INTO lesson_subtitle_tracks
+            (organization_id, lesson_id, language, label, source_language, origin, status, cues, edited_by)
+        VALUES ($1, $2, $3, $4, $5, 'manual', 'draft', $6, $7)
+        ON CONFLICT (lesson_id, language) DO UPDATE SET
+            label = EXCLUDED.label,
+            origin = 'manual',
+            status = 'draft',
+            cues = EXCLUDED.cues,
+            error = NULL,
+            edited_by = EXCLUDED.edited_by,
+            approved_by = NULL,
+            approved_at = NULL,
+            updated_at = NOW()
+        WHERE lesson_subtitle_tracks.organization_id = EXCLUDED.organization_id
+          AND lesson_subtitle_tracks.status <> 'translating'
+        RETURNING *
+        "#,
+    )
+    .bind(org_ctx.id)
+    .bind(lesson_id)
+    .bind(&lang)
+    .bind(&label)
+    .bind(original_language(transcription.as_ref()))
+    .bind(json!(cues))
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| internal_error())?
+    .ok_or((StatusCode::CONFLICT, "La pista se está traduciendo".to_string()))?;
+
+    crate::handlers::log_action(
+        &pool,
+        org_ctx.id,
+        claims.sub,
+        "SUBTITLE_TRACK_EDITED",
+        "Lesson",
+        lesson_id,
+        json!({ "language": lang, "cues": cues.len() }),
+    )
+    .await;
+
+    Ok(Json(track))
+}
+
+/// POST /lessons/{id}/subtitles/{lang}/approve - Publica la pista para los estudiantes
+pub async fn approve_subtitle_track(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path((lesson_id, lang)): Path<(Uuid, String)>,
+) -> Result<Json<SubtitleTrack>, (StatusCode, String)> {
+    let (course_id, _) = lesson_context(&pool, org_ctx.id, lesson_id).await?;
+    require_course_staff(&pool, &claims, course_id).await?;
+    let lang = parse_language(&lang)?;
+
+    let not_approvable = || {
+        (
+            StatusCode::CONFLICT,
+            "Solo se pueden aprobar pistas en borrador con cues".to_string(),
+        )
+    };
+    let current = sqlx::query_as::<_, SubtitleTrack>(
+        r#"
+        SELECT * FROM lesson_subtitle_tracks
+        WHERE lesson_id = $1 AND organization_id = $2 AND language = $3
+          AND status IN ('draft', 'approved') AND jsonb_array_length(cues) > 0
+        "#,
+    )
+    .bind(lesson_id)
+    .bind(org_ctx.id)
+    .bind(&lang)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| internal_error())?
+    .ok_or_else(not_approvable)?;
+
+    // Las pistas traducidas o antiguas pueden no cumplir las reglas de una edición
+    let cues: Vec<Cue> = serde_json::from_value(current.cues.clone())
+        .map_err(|_| (StatusCode::UNPROCESSABLE_ENTITY, "La pista tiene cues inválidos".to_string()))?;
+    let cues = normalize_cues(cues)?;
+
+    // Solo si la pista no cambió desde que se validó
+    let track = sqlx::query_as::<_, SubtitleTrack>(
+        r#"
+        UPDATE lesson_subtitle_tracks
+        SET status = 'approved', cues = $5, approved_by = $4, approved_at = NOW(), updated_at = NOW()
+        WHERE lesson_id = $1 AND organization_id = $2 AND language = $3
+          AND status IN ('draft', 'approved') AND updated_at = $6
+        RETURNING *
+        "#,
+    )
+    .bind(lesson_id)
+    .bind(org_ctx.id)
+    .bind(&lang)
+    .bind(claims.sub)
+    .bind(json!(cues))
+    .bind(current.updated_at)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| internal_error())?
+    .ok_or_else(not_approvable)?;
+
+    crate::handlers::log_action(
+        &pool,
+        org_ctx.id,
+        claims.sub,
+        "SUBTITLE_TRACK_APPROVED",
+        "Lesson",
+        lesson_id,
+        json!({ "language": lang }),
+    )
+    .await;
+
+    Ok(Json(track))
+}
+
+/// DELETE /lessons/{id}/subtitles/{lang}
+pub async fn delete_subtitle_track(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path((lesson_id, lang)): Path<(Uuid, String)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let (course_id, _) = lesson_context(&pool, org_ctx.id, lesson_id).await?;
+    require_course_staff(&pool, &claims, course_id).await?;
+    let lang = parse_language(&lang)?;
+
+    let result = sqlx::query("DELETE FROM lesson_subtitle_tracks WHERE lesson_id = $1 AND organization_id = $2 AND language = $3")
+        .bind(lesson_id)
+        .bind(org_ctx.id)
+        .bind(&lang)
+        .execute(&pool)
+        .await
+        .map_err(|_| internal_error())?;
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Pista de subtítulos no encontrada".to_string()));
+    }
+
+    crate::handlers::log_action(
+        &pool,
+        org_ctx.id,
+        claims.sub,
+        "SUBTITLE_TRACK_DELETED",
+        "Lesson",
+        lesson_id,
+        json!({ "language": lang }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /lessons/{id}/subtitles/translate - Traduce la pista original a otros idiomas en segundo plano
+pub async fn translate_subtitles(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(lesson_id): Path<Uuid>,
+    Json(payload): Json<TranslateSubtitlesPayload>,
+) -> Result<(StatusCode, Json<Vec<SubtitleTrackSummary>>), (StatusCode, String)> {
+    let (course_id, transcription) = lesson_context(&pool, org_ctx.id, lesson_id).await?;
+    require_course_staff(&pool, &claims, course_id).await?;
+
+    let source_language = original_language(transcription.as_ref());
+    // Se traduce desde la pista original corregida si está aprobada
+    let source_cues = resolve_cues(&pool, org_ctx.id, lesson_id, source_language.as_deref())
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "La lección no tiene una transcripción con cues para traducir".to_string(),
+            )
+        })?;
+
+    let mut languages = Vec::new();
+    for language in &payload.languages {
+        let language = parse_language(language)?;
+        if Some(&language) != source_language.as_ref() && !languages.contains(&language) {
+            languages.push(language);
+        }
+    }
+    if languages.is_empty() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Indica al menos un idioma distinto del original".to_string(),
+        ));
+    }
+
+    let mut queued = Vec::new();
+    for language in languages {
+        let track = sqlx::query_as::<_, SubtitleTrackSummary>(
+            r#"
+            INSERT INTO lesson_subtitle_tracks
+                (organization_id, lesson_id, language, label, source_language, origin, status)
+            VALUES ($1, $2, $3, $4, $5, 'machine', 'translating')
+            ON CONFLICT (lesson_id, language) DO UPDATE SET
+                source_language = EXCLUDED.source_language,
+                origin = 'machine',
+                status = 'translating',
+                cues = '[]',
+                error = NULL,
+                edited_by = NULL,
+                approved_by = NULL,
+                approved_at = NULL,
+                updated_at = NOW()
+            WHERE lesson_subtitle_tracks.organization_id = EXCLUDED.organization_id
+              AND lesson_subtitle_tracks.status <> 'translating'
+              AND ($6 OR lesson_subtitle_tracks.status = 'failed')
+            RETURNING language, label, source_language, origin, status, 0 AS cue_count, error, approved_at, updated_at
+            "#,
+        )
+        .bind(org_ctx.id)
+        .bind(lesson_id)
+        .bind(&language)
+        .bind(language_label(&language))
+        .bind(&source_language)
+        .bind(payload.overwrite)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| internal_error())?;
+
+        // Pista existente sin overwrite: se conserva
+        let Some(track) = track else { continue };
+
+        let pool = pool.clone();
+        let cues = source_cues.clone();
+        let source = source_language.clone();
+        let organization_id = org_ctx.id;
+        let target = language.clone();
+        tenancy::spawn(async move {
+            let result = tokio::time::timeout(TRANSLATION_TIMEOUT, translate_cues(&cues, source.as_deref(), &target))
+                .await
+                .unwrap_or_else(|_| Err("La traducción superó el tiempo máximo".to_string()));
+            let query = match &result {
+                Ok(translated) => sqlx::query(
+                    "UPDATE lesson_subtitle_tracks SET status = 'draft', cues = $1, updated_at = NOW() WHERE lesson_id = $2 AND organization_id = $3 AND language = $4 AND status = 'translating'",
+                )
+                .bind(json!(translated)),
+                Err(e) => {
+                    tracing::error!("Subtitle translation to {} failed for lesson {}: {}", target, lesson_id, e);
+                    sqlx::query(
+                        "UPDATE lesson_subtitle_tracks SET status = 'failed', error = $1, updated_at = NOW() WHERE lesson_id = $2 AND organization_id = $3 AND language = $4 AND status = 'translating'",
+                    )
+                    .bind(e)
+                }
+            };
+            if let Err(e) = query
+                .bind(lesson_id)
+                .bind(organization_id)
+                .bind(&target)
+                .execute(&pool)
+                .await
+            {
+                tracing::error!("Failed to store subtitle track {} for lesson {}: {}", target, lesson_id, e);
+            }
+        });
+        queued.push(track);
+    }
+
+    crate::handlers::log_action(
+        &pool,
+        org_ctx.id,
+        claims.sub,
+        "SUBTITLE_TRANSLATION_QUEUED",
+        "Lesson",
+        lesson_id,
+        json!({ "languages": queued.iter().map(|t| &t.language).collect::<Vec<_>>() }),
+    )
+    .await;
+
+    Ok((StatusCode::ACCEPTED, Json(queued)))
+}
+
+/// Marca como fallidas las traducciones interrumpidas (tarea en segundo plano). Sin
+/// esto una pista quedaría en `translating` para siempre y no se podría reintentar.
+pub async fn fail_stale_translations(pool: &PgPool) {
+    let result = sqlx::query(
+        r#"
+        UPDATE lesson_subtitle_tracks
+        SET status = 'failed', error = 'La traducción se interrumpió', updated_at = NOW()
+        WHERE status = 'translating'
+          AND updated_at < NOW() - make_interval(mins => $1)
+        "#,
+    )
+    .bind(STALE_TRANSLATION_MINUTES)
+    .execute(pool)
+    .await;
+
+    match result {
+        Ok(done) if done.rows_affected() > 0 => {
+            tracing::warn!("{} subtitle translations marked as failed after timing out", done.rows_affected())
+        }
+        Ok(_) => {}
+        Err(e) => tracing::error!("Failed to reap stale subtitle translations: {}", e),
+    }
+}
+
+/// Traduce los cues conservando tiempos y hablantes.
+async fn translate_cues(cues: &[Cue], source: Option<&str>, target: &str) -> Result<Vec<Cue>, String> {
+    let plain = |cue: &Cue| cue.text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let client = reqwest::Client::new();
+    let mut translated = Vec::with_capacity(cues.len());
+
+    for (batch_index, batch) in cues.chunks(TRANSLATION_BATCH).enumerate() {
+        let offset = batch_index * TRANSLATION_BATCH;
+        let context: Vec<String> = cues[offset.saturating_sub(TRANSLATION_CONTEXT)..offset]
+            .iter()
+            .map(plain)
+            .collect();
+        let texts: Vec<String> = batch.iter().map(plain).collect();
+
+        let texts_out = match translate_batch(&client, &context, &texts, source, target).await {
+            Ok(out) if out.len() == texts.len() => out,
+            other => {
+                if let Err(e) = other {
+                    tracing::warn!("Batch subtitle translation failed, retrying cue by cue: {}", e);
+                } else {
+                    tracing::warn!("Batch subtitle translation returned a different number of cues, retrying cue by cue");
+                }
+                let mut out = Vec::with_capacity(texts.len());
+                for text in &texts {
+                    out.push(crate::handlers::translate_text(text, target).await?);
+                }
+                out
+            }
+        };
+
+        translated.extend(batch.iter().zip(texts_out).flat_map(|(cue, text)| fit_translated_cue(cue, &text)));
+    }
+    Ok(translated)
+}
+
+/// Aplica las reglas de líneas a la traducción de un cue. Si no cabe en `MAX_LINES`
+/// líneas se reparte en cues consecutivos, con el tiempo del original dividido según
+/// el largo de cada parte.
+fn fit_translated_cue(cue: &Cue, text: &str) -> Vec<Cue> {
+    let lines = wrap_lines(text);
+    if lines.len() <= MAX_LINES {
+        return vec![Cue { text: lines.join("\n"), ..cue.clone() }];
+    }
+
+    let mut pieces: Vec<String> = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for word in text.split_whitespace() {
+        current.push(word);
+        if current.len() > 1 && wrap_lines(&current.join(" ")).len() > MAX_LINES {
+            current.pop();
+            pieces.push(current.join(" "));
+            current = vec![word];
+        }
+    }
+    pieces.push(current.join(" "));
+
+    let total: usize = pieces.iter().map(|p| p.chars().count()).sum();
+    let duration = cue.end - cue.start;
+    let mut start = cue.start;
+    let last = pieces.len() - 1;
+    pieces
+        .iter()
+        .enumerate()
+        .map(|(index, piece)| {
+            let end = if index == last {
+                cue.end
+            } else {
+                start + duration * piece.chars().count() as f64 / total as f64
+            };
+            let part = Cue { start, end, text: wrap_lines(piece).join("\n"), speaker: cue.speaker.clone() };
+            start = end;
+            part
+        })
+        .collect()
+}
+
+async fn translate_batch(
+    client: &reqwest::Client,
+    context: &[String],
+    texts: &[String],
+    source: Option<&str>,
+    target: &str,
+) -> Result<Vec<String>, String> {
+    let (url, auth_header, model) = crate::handlers::chat_completion_endpoint()?;
+
+    let system = format!(
+        "You are a professional subtitle translator. Translate each subtitle from {} into {}. \
+        Return ONLY a JSON object {{\"translations\": [string]}} with exactly one translation per input subtitle, in the same order. \
+        Never merge or split subtitles: each one is shown on screen at a fixed time. \
+        Keep each translation about as short as the original so it can be read in the same time. \
+        The context subtitles come before the batch and must not be translated.",
+        source.map(language_name).unwrap_or("the original language"),
+        language_name(target)
+    );
+    let user = json!({ "context": context, "subtitles": texts }).to_string();
+
+    let mut request = client.post(&url).json(&json!({
+        "model": model,
+        "messages": [
+            { "role": "system", "content": system },
+            { "role": "user", "content": user }
+        ],
+        "temperature": 0.2,
+        "response_format": { "type": "json_object" }
+    }));
+    if !auth_header.is_empty() {
+        request = request.header("Authorization", auth_header);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Translation request failed: {}", e))?;
+    if !response.status().is_success() {
+        let err_body = response.text().await.unwrap_or_default();
+        return Err(format!("Translation API error: {}", err_body));
+    }
+    let data: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Translation JSON parse failed: {}", e))?;
+    let content: Value = data["choices"][0]["message"]["content"]
+        .as_str()
+        .and_then(|c| serde_json::from_str(c).ok())
+        .ok_or("Translation response is not JSON")?;
+
+    content["translations"]
+        .as_array()
+        .ok_or_else(|| "Translation response without translations".to_string())?
+        .iter()
+        .map(|t| t.as_str().map(|s| s.trim().to_string()).ok_or_else(|| "Non-string translation".to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(start: f64, end: f64, text: &str) -> Cue {
+        Cue {
+            start,
+            end,
+            text: text.to_string(),
+            speaker: None,
+        }
+    }
+
+    #[test]
+    fn language_codes_are_normalized() {
+        assert_eq!(parse_language(" EN ").unwrap(), "en");
+        assert_eq!(parse_language("pt-br").unwrap(), "pt-BR");
+        assert_eq!(parse_language("fil").unwrap(), "fil");
+        for invalid in ["", "e", "english", "en-", "en-USA", "e1", "en_US"] {
+            assert!(parse_language(invalid).is_err(), "{} debería rechazarse", invalid);
+        }
+        assert_eq!(language_label("es"), "Español");
+        assert_eq!(language_label("pt-BR"), "PT-BR");
+    }
+
+    #[test]
+    fn edited_cues_are_sorted_and_trimmed() {
+        let mut second = cue(2.0, 3.0, "  Segundo  \n\n");
+        second.speaker = Some("  ".to_string());
+        let cues = normalize_cues(vec![second, cue(0.0, 1.5, "Primero")]).unwrap();
+
+        assert_eq!(cues[0].text, "Primero");
+        assert_eq!(cues[1].text, "Segundo");
+        assert_eq!(cues[1].speaker, None);
+    }
+
+    #[test]
+    fn long_cues_are_rewrapped_within_the_line_rules() {
+        let text = "Esta frase es bastante larga y no entra en una sola línea de subtítulo";
+        let cues = normalize_cues(vec![cue(0.0, 4.0, text)]).unwrap();
+
+        let lines: Vec<&str> = cues[0].text.lines().collect();
+        assert!(lines.len() <= MAX_LINES);
+        assert!(lines.iter().all(|l| l.chars().count() <= MAX_LINE_CHARS));
+        assert_eq!(lines.join(" "), text);
+
+        let too_long = "palabra ".repeat(30);
+        assert!(normalize_cues(vec![cue(0.0, 4.0, &too_long)]).is_err());
+    }
+
+    #[test]
+    fn long_translations_are_split_into_cues_that_fit() {
+        let mut original = cue(10.0, 16.0, "Hola");
+        original.speaker = Some("Ana".to_string());
+        let text = "Esta traducción es mucho más larga que el original y no cabe en las dos líneas que permite un subtítulo en pantalla";
+
+        let parts = fit_translated_cue(&original, text);
+        assert!(parts.len() > 1);
+        assert_eq!(parts[0].start, 10.0);
+        assert_eq!(parts.last().unwrap().end, 16.0);
+        assert!(parts.windows(2).all(|w| w[0].end == w[1].start && w[0].start < w[0].end));
+        assert!(parts.iter().all(|p| p.speaker.as_deref() == Some("Ana")));
+        assert!(parts.iter().all(|p| p.text.lines().count() <= MAX_LINES));
+        let words: Vec<&str> = parts.iter().flat_map(|p| p.text.split_whitespace()).collect();
+        assert_eq!(words.join(" "), text);
+        assert!(normalize_cues(parts).is_ok());
+
+        assert_eq!(fit_translated_cue(&original, "Hello").len(), 1);
+    }
+
+    #[test]
+    fn invalid_cue_timing_is_rejected() {
+        assert!(normalize_cues(vec![]).is_err());
+        assert!(normalize_cues(vec![cue(1.0, 1.0, "Cero")]).is_err());
+        assert!(normalize_cues(vec![cue(-1.0, 1.0, "Negativo")]).is_err());
+        assert!(normalize_cues(vec![cue(0.0, f64::NAN, "NaN")]).is_err());
+        assert!(normalize_cues(vec![cue(0.0, 2.0, "Uno"), cue(1.0, 3.0, "Dos")]).is_err());
+        assert!(normalize_cues(vec![cue(0.0, 1.0, "Texto"), cue(1.0, 2.0, " \n ")]).is_err());
+        // Cues contiguos no se consideran superpuestos
+        assert!(normalize_cues(vec![cue(0.0, 1.0, "Uno"), cue(1.0, 2.0, "Dos")]).is_ok());
+    }
+}
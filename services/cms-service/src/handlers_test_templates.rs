@@ -8,7 +8,7 @@ use common::models::{
     TestTemplateSection, TestTemplateWithQuestions, TestType, UpdateTestTemplatePayload,
 };
 use common::{auth::Claims, middleware::Org};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::time::Duration;
 use uuid::Uuid;
@@ -577,6 +577,10 @@ pub async fn apply_template_to_lesson(
         return Err((StatusCode::NOT_FOUND, "Lección no encontrada".to_string()));
     }
 
+    if payload.delivery_mode.as_deref() == Some("adaptive") {
+        return apply_adaptive_template(&pool, org_ctx.id, &template, &payload).await;
+    }
+
     // Obtener las preguntas de la plantilla con sus secciones
     let template_questions: Vec<TestTemplateQuestion> = sqlx::query_as(
         r#"
@@ -608,6 +612,8 @@ pub async fn apply_template_to_lesson(
                 "correct": q.correct_answer.clone().unwrap_or(serde_json::Value::Null),
                 "explanation": q.explanation.clone().unwrap_or_default(),
                 "points": q.points,
+                // Permite al LMS asociar las respuestas al ítem del banco (calibración TRI)
+                "question_bank_id": q.metadata.as_ref().and_then(|m| m.get("question_bank_id")).cloned(),
             })
         })
         .collect();
@@ -670,6 +676,218 @@ pub async fn apply_template_to_lesson(
 pub struct ApplyTemplatePayload {
     pub lesson_id: Uuid,
     pub grading_category_id: Option<Uuid>,
+    /// "fixed" (por defecto) o "adaptive" (prueba adaptativa computarizada)
+    pub delivery_mode: Option<String>,
+    /// Reglas del modo adaptativo; si se omiten se usan las de `template_data.adaptive`
+    pub adaptive: Option<AdaptiveSettings>,
+}
+
+// ==================== Modo Adaptativo (CAT) ====================
+
+/// Configuración de la prueba adaptativa. Los campos omitidos toman los valores por
+/// defecto del LMS (error estándar 0.3, 5 a 30 ítems, cortes MCER estándar).
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct AdaptiveSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub se_threshold: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_items: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<i32>,
+    /// `[{ "level": "A2", "min_theta": -1.5 }, ...]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cefr_cutoffs: Option<serde_json::Value>,
+    /// Restringe el banco a estas habilidades (skill_assessed)
+    #[serde(default, skip_serializing)]
+    pub skills: Option<Vec<String>>,
+    /// Restringe el banco a ítems con al menos una de estas etiquetas
+    #[serde(default, skip_serializing)]
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(sqlx::FromRow)]
+struct AdaptivePoolRow {
+    id: Uuid,
+    question_text: String,
+    question_type: String,
+    options: Option<serde_json::Value>,
+    correct_answer: Option<serde_json::Value>,
+    explanation: Option<String>,
+    audio_url: Option<String>,
+    media_url: Option<String>,
+    media_type: Option<String>,
+    difficulty: Option<String>,
+    skill_assessed: Option<String>,
+    irt_difficulty: Option<f64>,
+    irt_discrimination: Option<f64>,
+}
+
+/// Dificultad provisional para ítems aún sin calibrar a partir de su etiqueta.
+fn provisional_difficulty(label: Option<&str>) -> f64 {
+    match label.map(|l| l.to_lowercase()).as_deref() {
+        Some("easy") | Some("facil") | Some("fácil") => -1.0,
+        Some("hard") | Some("dificil") | Some("difícil") => 1.0,
+        _ => 0.0,
+    }
+}
+
+/// Aplica la plantilla como prueba adaptativa: en lugar de un conjunto fijo de
+/// preguntas, la lección recibe un banco de ítems con sus parámetros TRI y el LMS
+/// elige cada pregunta según la habilidad estimada del alumno.
+async fn apply_adaptive_template(
+    pool: &PgPool,
+    org_id: Uuid,
+    template: &TestTemplate,
+    payload: &ApplyTemplatePayload,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let settings = match &payload.adaptive {
+        Some(settings) => settings.clone(),
+        None => template
+            .template_data
+            .get("adaptive")
+            .and_then(|a| serde_json::from_value::<AdaptiveSettings>(a.clone()).ok())
+            .unwrap_or_default(),
+    };
+
+    if settings.se_threshold.is_some_and(|se| se <= 0.0) {
+        return Err((StatusCode::BAD_REQUEST, "El umbral de error estándar debe ser positivo".to_string()));
+    }
+    let min_items = settings.min_items.unwrap_or(5);
+    if min_items < 1 || settings.max_items.is_some_and(|max| max < min_items) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "El número máximo de ítems debe ser mayor o igual al mínimo".to_string(),
+        ));
+    }
+
+    // Solo ítems de calificación automática: el siguiente ítem depende de la respuesta
+    let rows: Vec<AdaptivePoolRow> = sqlx::query_as(
+        r#"
+        SELECT id, question_text, question_type::text AS question_type, options, correct_answer,
+            explanation, audio_url, media_url, media_type, difficulty, skill_assessed,
+            irt_difficulty, irt_discrimination
+        FROM question_bank
+        WHERE organization_id = $1
+          AND is_active = true AND is_archived = false
+          AND question_type IN ('multiple-choice', 'true-false')
+          AND ($2::text[] IS NULL OR skill_assessed = ANY($2))
+          AND ($3::text[] IS NULL OR tags && $3)
+        "#,
+    )
+    .bind(org_id)
+    .bind(&settings.skills)
+    .bind(&settings.tags)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error al obtener el banco para la prueba adaptativa: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+    })?;
+
+    let mut calibrated = 0;
+    let item_pool: Vec<serde_json::Value> = rows
+        .into_iter()
+        .filter_map(|row| {
+            let options = match row.options.as_ref().and_then(|o| o.as_array()) {
+                Some(options) if !options.is_empty() => options.clone(),
+                _ if row.question_type == "true-false" => vec![
+                    serde_json::Value::String("True".to_string()),
+                    serde_json::Value::String("False".to_string()),
+                ],
+                _ => return None,
+            };
+            let correct = crate::handlers_question_bank::correct_option_indices(&options, row.correct_answer.as_ref()?);
+            if correct.is_empty() || correct.iter().any(|&i| i < 0 || i as usize >= options.len()) {
+                return None;
+            }
+            if row.irt_difficulty.is_some() {
+                calibrated += 1;
+            }
+            Some(serde_json::json!({
+                "id": row.id.to_string(),
+                "question_bank_id": row.id.to_string(),
+                "type": row.question_type,
+                "question": row.question_text,
+                "options": options,
+                "correct": correct,
+                "explanation": row.explanation.unwrap_or_default(),
+                "audio_url": row.audio_url,
+                "media_url": row.media_url,
+                "media_type": row.media_type,
+                "skill": row.skill_assessed,
+                "difficulty": row.irt_difficulty.unwrap_or_else(|| provisional_difficulty(row.difficulty.as_deref())),
+                "discrimination": row.irt_discrimination.unwrap_or(1.0),
+                "calibrated": row.irt_difficulty.is_some(),
+            }))
+        })
+        .collect();
+
+    if item_pool.len() < min_items as usize {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "El banco solo tiene {} ítems de calificación automática; se requieren al menos {}",
+                item_pool.len(),
+                min_items
+            ),
+        ));
+    }
+
+    let pool_size = item_pool.len();
+    let mut adaptive = serde_json::to_value(&settings)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    adaptive["pool"] = serde_json::Value::Array(item_pool);
+
+    let quiz_data = serde_json::json!({
+        "delivery_mode": "adaptive",
+        "adaptive": adaptive,
+        "template_id": template.id.to_string(),
+        "template_name": template.name,
+        "test_type": template.test_type.to_string(),
+        "duration_minutes": template.duration_minutes,
+        "passing_score": template.passing_score,
+        "instructions": template.instructions,
+        "max_attempts": 1,
+        "show_feedback": false, // La retroalimentación por ítem revelaría el banco
+    });
+
+    sqlx::query(
+        r#"
+        UPDATE lessons
+        SET content_type = 'quiz',
+            content_url = NULL,
+            metadata = $1,
+            is_graded = true,
+            max_attempts = 1,
+            allow_retry = false,
+            grading_category_id = $2,
+            updated_at = NOW()
+        WHERE id = $3 AND organization_id = $4
+        "#
+    )
+    .bind(&quiz_data)
+    .bind(payload.grading_category_id)
+    .bind(payload.lesson_id)
+    .bind(org_id)
+    .execute(pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    sqlx::query("SELECT increment_template_usage($1)")
+        .bind(template.id)
+        .execute(pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    tracing::info!(
+        "Plantilla '{}' aplicada en modo adaptativo a la lección '{}' con {} ítems ({} calibrados)",
+        template.name,
+        payload.lesson_id,
+        pool_size,
+        calibrated
+    );
+
+    Ok(StatusCode::OK)
 }
 
 // ==================== Generación de Preguntas RAG ====================
@@ -1601,7 +1819,7 @@ pub async fn generate_questions_with_rag(
     };
 
     // Convert to TestTemplateQuestion format and skip invalid LLM entries
-    let generated_questions: Vec<TestTemplateQuestion> = questions_data
+    let mut generated_questions: Vec<TestTemplateQuestion> = questions_data
         .iter()
         .enumerate()
         .filter_map(|(idx, q)| {
@@ -1765,7 +1983,7 @@ pub async fn generate_questions_with_rag(
 
     // Save generated questions to question bank
     let mut saved_count = 0;
-    for question in &generated_questions {
+    for question in &mut generated_questions {
         let question_type = match question.question_type.as_str() {
             "true-false" => common::models::QuestionBankType::TrueFalse,
             "short-answer" => common::models::QuestionBankType::ShortAnswer,
@@ -1783,7 +2001,7 @@ pub async fn generate_questions_with_rag(
                 question.correct_answer.clone(),
             );
 
-        let result = sqlx::query(
+        let result: Result<Uuid, _> = sqlx::query_scalar(
             r#"
             INSERT INTO question_bank (
                 organization_id, created_by, question_text, question_type,
@@ -1791,6 +2009,7 @@ pub async fn generate_questions_with_rag(
                 source, source_metadata, is_active
             )
             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, true)
+            RETURNING id
             "#
         )
         .bind(org_ctx.id)
@@ -1810,10 +2029,15 @@ pub async fn generate_questions_with_rag(
             "question_type": requested_question_type.clone(),
             "generated_at": chrono::Utc::now().to_rfc3339(),
         }))
-        .execute(&pool)
+        .fetch_one(&pool)
         .await;
 
-        if result.is_ok() {
+        // El id del banco viaja en el metadata de la pregunta hasta la lección, donde
+        // el LMS lo usa para asociar las respuestas al ítem calibrado
+        if let Ok(bank_id) = result {
+            if let Some(metadata) = question.metadata.as_mut() {
+                metadata["question_bank_id"] = json!(bank_id.to_string());
+            }
             saved_count += 1;
         }
     }
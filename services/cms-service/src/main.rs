@@ -1,3 +1,6 @@
+mod audit_chain;
+mod audit_retention;
+mod audit_search;
 mod db_util;
 pub mod exporter;
 mod external_handlers;
@@ -8,7 +11,9 @@ mod handlers_email_templates;
 mod handlers_exercise_settings;
 mod handlers_assets;
 mod handlers_dependencies;
+mod handlers_dsar;
 mod handlers_library;
+mod handlers_outcomes;
 mod handlers_rubrics;
 mod handlers_test_templates;
 mod handlers_question_bank;
@@ -16,7 +21,10 @@ mod handlers_admin;
 mod handlers_embeddings;
 mod handlers_sam;
 mod handlers_plugins;
+mod handlers_subtitles;
 mod openapi;
+mod plugin_manifest;
+mod stt;
 mod webhooks;
 
 use axum::{
@@ -28,7 +36,6 @@ use axum::{
 use common::health::{self, HealthState};
 use dotenvy::dotenv;
 use http::{Method, header};
-use sqlx::postgres::PgPoolOptions;
 use std::env;
 use std::net::SocketAddr;
 use std::time::Duration;
@@ -52,7 +59,7 @@ async fn main() {
     tracing_subscriber::fmt::init();
 
     let db_url = env::var("DATABASE_URL").expect("DATABASE_URL debe estar configurada");
-    let pool = PgPoolOptions::new()
+    let pool = common::tenancy::pool_options()
         .max_connections(10)
         .min_connections(2)
         .acquire_timeout(Duration::from_secs(30))
@@ -121,6 +128,42 @@ async fn main() {
         }
     });
 
+    // Enlace de las entradas de auditoría pendientes en la cadena de su organización
+    let link_pool = pool.clone();
+    tokio::spawn(async move {
+        loop {
+            audit_chain::link_pending(&link_pool).await;
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    });
+
+    // Traducciones de subtítulos interrumpidas (cada 5 minutos)
+    let subtitles_pool = pool.clone();
+    tokio::spawn(async move {
+        loop {
+            handlers_subtitles::fail_stale_translations(&subtitles_pool).await;
+            tokio::time::sleep(Duration::from_secs(300)).await;
+        }
+    });
+
+    // Checkpoints firmados del registro de auditoría (cada hora)
+    let audit_pool = pool.clone();
+    tokio::spawn(async move {
+        loop {
+            audit_chain::checkpoint_all(&audit_pool).await;
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+        }
+    });
+
+    // Archivado de la auditoría vencida según la retención de cada organización (diario)
+    let retention_pool = pool.clone();
+    tokio::spawn(async move {
+        loop {
+            audit_retention::apply_retention(&retention_pool).await;
+            tokio::time::sleep(Duration::from_secs(24 * 3600)).await;
+        }
+    });
+
     // Configuración de CORS - Permitir múltiples orígenes para desarrollo y producción
     // Uso de un cierre de predicado para soportar subdominios comodín para norteamericano.cl
     use tower_http::cors::AllowOrigin;
@@ -237,6 +280,26 @@ async fn main() {
             post(handlers::process_transcription),
         )
         .route("/lessons/{id}/vtt", get(handlers::get_lesson_vtt))
+        .route("/lessons/{id}/srt", get(handlers::get_lesson_srt))
+        .route("/lessons/{id}/subtitles", get(handlers_subtitles::list_subtitle_tracks))
+        .route(
+            "/lessons/{id}/subtitles/available",
+            get(handlers_subtitles::list_available_subtitles),
+        )
+        .route(
+            "/lessons/{id}/subtitles/translate",
+            post(handlers_subtitles::translate_subtitles),
+        )
+        .route(
+            "/lessons/{id}/subtitles/{lang}",
+            get(handlers_subtitles::get_subtitle_track)
+                .put(handlers_subtitles::update_subtitle_track)
+                .delete(handlers_subtitles::delete_subtitle_track),
+        )
+        .route(
+            "/lessons/{id}/subtitles/{lang}/approve",
+            post(handlers_subtitles::approve_subtitle_track),
+        )
         .route("/lessons/{id}/summarize", post(handlers::summarize_lesson))
         .route("/lessons/{id}/generate-quiz", post(handlers::generate_quiz))
         .route("/lessons/{id}/generate-role-play", post(handlers::generate_role_play))
@@ -267,6 +330,26 @@ async fn main() {
         )
         .route("/users/{id}", axum::routing::put(handlers::update_user).delete(handlers::delete_user))
         .route("/audit-logs", get(handlers::get_audit_logs))
+        .route("/audit-logs/search", get(audit_search::search_audit_logs))
+        .route("/audit-logs/export", get(audit_search::export_audit_logs))
+        .route(
+            "/audit-logs/retention",
+            get(audit_retention::get_retention_policy).put(audit_retention::update_retention_policy),
+        )
+        .route("/audit-logs/archives", get(audit_retention::list_audit_archives))
+        .route(
+            "/audit-logs/archives/{id}/download",
+            get(audit_retention::download_audit_archive),
+        )
+        .route("/audit-logs/verify", get(audit_chain::verify_audit_chain))
+        .route(
+            "/audit-logs/checkpoints",
+            get(audit_chain::list_audit_checkpoints).post(audit_chain::create_audit_checkpoint),
+        )
+        .route(
+            "/audit-logs/checkpoints/export",
+            get(audit_chain::export_audit_checkpoints),
+        )
         .route("/api/ai/review-text", post(handlers::review_text))
         .route("/api/assets", get(handlers_assets::list_assets))
         .route("/api/assets/import-history", get(handlers_assets::list_asset_import_history))
@@ -442,6 +525,39 @@ async fn main() {
             "/test-templates/generate-with-rag",
             post(handlers_test_templates::generate_questions_with_rag),
         )
+        // Marcos de competencias y resultados de aprendizaje
+        .route(
+            "/outcome-frameworks",
+            get(handlers_outcomes::list_frameworks).post(handlers_outcomes::create_framework),
+        )
+        .route(
+            "/outcome-frameworks/import-csv",
+            post(handlers_outcomes::import_framework_csv),
+        )
+        .route(
+            "/outcome-frameworks/import-case",
+            post(handlers_outcomes::import_framework_case),
+        )
+        .route(
+            "/outcome-frameworks/{id}",
+            get(handlers_outcomes::get_framework).delete(handlers_outcomes::delete_framework),
+        )
+        .route(
+            "/outcome-frameworks/{id}/outcomes",
+            post(handlers_outcomes::create_outcome),
+        )
+        .route(
+            "/learning-outcomes/{id}",
+            delete(handlers_outcomes::delete_outcome),
+        )
+        .route(
+            "/outcome-alignments",
+            get(handlers_outcomes::list_alignments).post(handlers_outcomes::create_alignment),
+        )
+        .route(
+            "/outcome-alignments/{id}",
+            delete(handlers_outcomes::delete_alignment),
+        )
         // Rutas del banco de preguntas
         .route(
             "/question-bank",
@@ -454,6 +570,14 @@ async fn main() {
                 .put(handlers_question_bank::update_question)
                 .delete(handlers_question_bank::delete_question),
         )
+        .route(
+            "/question-bank/calibrate",
+            post(handlers_question_bank::calibrate_questions),
+        )
+        .route(
+            "/question-bank/pool-preview",
+            post(handlers_question_bank::preview_question_pool),
+        )
         .route(
             "/question-bank/import-mysql",
             post(handlers_question_bank::import_from_mysql),
@@ -548,15 +672,24 @@ async fn main() {
         // Fase 35: Ecosistema de Plugins
         .route(
             "/plugins",
-            get(handlers_plugins::list_plugins).post(handlers_plugins::create_plugin),
+            post(handlers_plugins::create_plugin)
+                .layer(DefaultBodyLimit::max(16 * 1024 * 1024))
+                .get(handlers_plugins::list_plugins),
         )
+        .route(
+            "/plugins/publishers",
+            get(plugin_manifest::list_publishers).post(plugin_manifest::create_publisher),
+        )
+        .route("/plugins/publishers/{id}", delete(plugin_manifest::delete_publisher))
         .route(
             "/plugins/enabled",
             get(handlers_plugins::list_enabled_plugins),
         )
         .route(
             "/plugins/{id}",
-            put(handlers_plugins::update_plugin).delete(handlers_plugins::delete_plugin),
+            put(handlers_plugins::update_plugin)
+                .layer(DefaultBodyLimit::max(16 * 1024 * 1024))
+                .delete(handlers_plugins::delete_plugin),
         )
         .route_layer(middleware::from_fn(
             common::middleware::org_extractor_middleware,
@@ -621,6 +754,13 @@ async fn main() {
         .merge(health::health_routes(pool.clone()).with_state(health_state))
         .nest_service("/assets", tower_http::services::ServeDir::new("uploads"))
         .merge(auth_routes)
+        // Solicitudes de titulares de datos, llamadas por el LMS con tokens entre servicios
+        .merge(
+            Router::new()
+                .route("/dsar/users/{id}/data", get(handlers_dsar::export_user_data))
+                .route("/dsar/users/{id}/erase", post(handlers_dsar::erase_user_data))
+                .route_layer(middleware::from_fn(handlers_dsar::dsar_token_middleware)),
+        )
         .merge(protected_routes)
         // Cabeceras de seguridad
         .layer(SetResponseHeaderLayer::overriding(
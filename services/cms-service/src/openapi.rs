@@ -65,6 +65,14 @@ pub struct ExternalCreateCoursePayloadSchema {
         delete_lesson,
         process_transcription,
         get_lesson_vtt,
+        get_lesson_srt,
+        list_subtitle_tracks,
+        list_available_subtitles,
+        translate_subtitles,
+        get_subtitle_track,
+        update_subtitle_track,
+        delete_subtitle_track,
+        approve_subtitle_track,
         summarize_lesson,
         generate_quiz,
         generate_role_play,
@@ -293,6 +301,14 @@ protected_ok_path!(update_lesson, put, "/lessons/{id}", "Courses");
 protected_ok_path!(delete_lesson, delete, "/lessons/{id}", "Courses");
 protected_ok_path!(process_transcription, post, "/lessons/{id}/transcribe", "Courses");
 protected_ok_path!(get_lesson_vtt, get, "/lessons/{id}/vtt", "Courses");
+protected_ok_path!(get_lesson_srt, get, "/lessons/{id}/srt", "Courses");
+protected_ok_path!(list_subtitle_tracks, get, "/lessons/{id}/subtitles", "Courses");
+protected_ok_path!(list_available_subtitles, get, "/lessons/{id}/subtitles/available", "Courses");
+protected_ok_path!(translate_subtitles, post, "/lessons/{id}/subtitles/translate", "Courses");
+protected_ok_path!(get_subtitle_track, get, "/lessons/{id}/subtitles/{lang}", "Courses");
+protected_ok_path!(update_subtitle_track, put, "/lessons/{id}/subtitles/{lang}", "Courses");
+protected_ok_path!(delete_subtitle_track, delete, "/lessons/{id}/subtitles/{lang}", "Courses");
+protected_ok_path!(approve_subtitle_track, post, "/lessons/{id}/subtitles/{lang}/approve", "Courses");
 protected_ok_path!(get_lesson_heatmap, get, "/lessons/{id}/heatmap", "Courses");
 protected_ok_path!(summarize_lesson, post, "/lessons/{id}/summarize", "Courses");
 protected_ok_path!(generate_quiz, post, "/lessons/{id}/generate-quiz", "Courses");
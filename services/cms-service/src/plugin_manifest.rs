@@ -0,0 +1,327 @@
+/// Manifiestos firmados de plugins.
+///
+/// El manifiesto describe el plugin (versión semántica, editor, tipo, permisos,
+/// JSON Schema de su configuración, eventos y tipos de bloque). El editor lo firma
+/// con Ed25519 sobre su forma canónica: JSON compacto con las claves de los objetos
+/// ordenadas. La firma se verifica con las claves públicas que la organización
+/// registró para ese editor en `plugin_publishers`; en plugins wasm el manifiesto
+/// fija además el `wasm_sha256` del módulo.
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+use common::{
+    auth::{Claims, PLUGIN_PERMISSIONS},
+    middleware::Org,
+};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginManifest {
+    pub manifest_version: u32,
+    pub name: String,
+    pub version: String,
+    pub publisher: String,
+    #[serde(default)]
+    pub description: String,
+    pub kind: String,
+    #[serde(default)]
+    pub component_url: Option<String>,
+    #[serde(default)]
+    pub icon_url: Option<String>,
+    #[serde(default)]
+    pub wasm_sha256: Option<String>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    #[serde(default)]
+    pub config_schema: Option<Value>,
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub block_types: Vec<String>,
+}
+
+/// Manifiesto verificado junto con la clave del editor que lo firmó.
+pub struct VerifiedManifest {
+    pub manifest: PluginManifest,
+    pub publisher_key_id: Uuid,
+}
+
+fn unprocessable(message: impl Into<String>) -> (StatusCode, String) {
+    (StatusCode::UNPROCESSABLE_ENTITY, message.into())
+}
+
+/// Serialización canónica firmada por el editor.
+pub fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            let body: Vec<String> = entries
+                .into_iter()
+                .map(|(key, value)| format!("{}:{}", Value::String(key.clone()), canonical_json(value)))
+                .collect();
+            format!("{{{}}}", body.join(","))
+        }
+        Value::Array(items) => {
+            format!("[{}]", items.iter().map(canonical_json).collect::<Vec<_>>().join(","))
+        }
+        other => other.to_string(),
+    }
+}
+
+/// `MAJOR.MINOR.PATCH` con sufijo opcional de pre-release o build.
+fn is_semver(version: &str) -> bool {
+    let core = version.split(['-', '+']).next().unwrap_or_default();
+    let parts: Vec<&str> = core.split('.').collect();
+    parts.len() == 3
+        && parts
+            .iter()
+            .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()) && (p.len() == 1 || !p.starts_with('0')))
+}
+
+fn validate_manifest(manifest: &PluginManifest) -> Result<(), (StatusCode, String)> {
+    if manifest.manifest_version != MANIFEST_VERSION {
+        return Err(unprocessable(format!(
+            "manifest_version no soportada: {}",
+            manifest.manifest_version
+        )));
+    }
+    if manifest.name.trim().is_empty() || manifest.publisher.trim().is_empty() {
+        return Err(unprocessable("El manifiesto requiere name y publisher"));
+    }
+    if !is_semver(&manifest.version) {
+        return Err(unprocessable("version debe seguir el formato semántico MAJOR.MINOR.PATCH"));
+    }
+    if let Some(permission) = manifest
+        .permissions
+        .iter()
+        .find(|p| !PLUGIN_PERMISSIONS.contains(&p.as_str()))
+    {
+        return Err(unprocessable(format!("Permiso desconocido: {}", permission)));
+    }
+    match manifest.kind.as_str() {
+        "component" => {
+            if !manifest.component_url.as_deref().is_some_and(|url| url.starts_with("https://")) {
+                return Err(unprocessable("component_url debe usar HTTPS"));
+            }
+        }
+        "wasm" => {
+            if manifest.wasm_sha256.is_none() {
+                return Err(unprocessable("Un manifiesto wasm requiere wasm_sha256"));
+            }
+        }
+        _ => return Err(unprocessable("kind debe ser component o wasm")),
+    }
+    if let Some(schema) = &manifest.config_schema {
+        jsonschema::validator_for(schema).map_err(|e| unprocessable(format!("config_schema inválido: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Valida el manifiesto y su firma con las claves del editor en la organización.
+pub async fn verify_manifest(
+    pool: &PgPool,
+    organization_id: Uuid,
+    manifest: &Value,
+    signature: Option<&str>,
+) -> Result<VerifiedManifest, (StatusCode, String)> {
+    let parsed: PluginManifest =
+        serde_json::from_value(manifest.clone()).map_err(|e| unprocessable(format!("Manifiesto inválido: {}", e)))?;
+    validate_manifest(&parsed)?;
+
+    let signature = signature
+        .and_then(|s| base64::engine::general_purpose::STANDARD.decode(s).ok())
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or_else(|| unprocessable("Se requiere la firma Ed25519 del manifiesto en base64"))?;
+
+    let keys: Vec<(Uuid, String)> =
+        sqlx::query_as("SELECT id, public_key FROM plugin_publishers WHERE organization_id = $1 AND name = $2")
+            .bind(organization_id)
+            .bind(&parsed.publisher)
+            .fetch_all(pool)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    if keys.is_empty() {
+        return Err(unprocessable(format!("El editor {} no es de confianza", parsed.publisher)));
+    }
+
+    let message = canonical_json(manifest);
+    let publisher_key_id = keys
+        .iter()
+        .find(|(_, public_key)| {
+            hex::decode(public_key)
+                .ok()
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+                .is_some_and(|key| key.verify(message.as_bytes(), &signature).is_ok())
+        })
+        .map(|(id, _)| *id)
+        .ok_or_else(|| unprocessable("La firma del manifiesto no es válida"))?;
+
+    Ok(VerifiedManifest {
+        manifest: parsed,
+        publisher_key_id,
+    })
+}
+
+/// Valida la configuración del plugin con el `config_schema` de su manifiesto.
+pub fn validate_config(schema: Option<&Value>, config: &Value) -> Result<(), (StatusCode, String)> {
+    let Some(schema) = schema else {
+        return Ok(());
+    };
+    let validator =
+        jsonschema::validator_for(schema).map_err(|e| unprocessable(format!("config_schema inválido: {}", e)))?;
+    let errors: Vec<String> = validator
+        .iter_errors(config)
+        .take(5)
+        .map(|e| format!("{}: {}", e.instance_path, e))
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(unprocessable(format!("Configuración inválida: {}", errors.join("; "))))
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Editores de confianza
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct PluginPublisher {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub name: String,
+    pub public_key: String,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePublisherPayload {
+    pub name: String,
+    /// Clave pública Ed25519 en hexadecimal
+    pub public_key: String,
+}
+
+/// GET /plugins/publishers - Editores de confianza de la organización
+pub async fn list_publishers(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+) -> Result<Json<Vec<PluginPublisher>>, (StatusCode, String)> {
+    if claims.role != "admin" {
+        return Err((StatusCode::FORBIDDEN, "Acceso denegado".to_string()));
+    }
+
+    let publishers = sqlx::query_as::<_, PluginPublisher>(
+        "SELECT * FROM plugin_publishers WHERE organization_id = $1 ORDER BY name, created_at",
+    )
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(publishers))
+}
+
+/// POST /plugins/publishers - Registra la clave de un editor de confianza
+pub async fn create_publisher(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Json(payload): Json<CreatePublisherPayload>,
+) -> Result<(StatusCode, Json<PluginPublisher>), (StatusCode, String)> {
+    if claims.role != "admin" {
+        return Err((StatusCode::FORBIDDEN, "Acceso denegado".to_string()));
+    }
+
+    let public_key = payload.public_key.trim().to_lowercase();
+    let valid_key = hex::decode(&public_key)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .is_some_and(|bytes| VerifyingKey::from_bytes(&bytes).is_ok());
+    if !valid_key || payload.name.trim().is_empty() {
+        return Err(unprocessable("Se requiere un nombre y una clave pública Ed25519 en hexadecimal"));
+    }
+
+    let publisher = sqlx::query_as::<_, PluginPublisher>(
+        r#"
+        INSERT INTO plugin_publishers (organization_id, name, public_key, created_by)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+    )
+    .bind(org_ctx.id)
+    .bind(payload.name.trim())
+    .bind(&public_key)
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            (StatusCode::CONFLICT, "La clave ya está registrada para este editor".to_string())
+        }
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()),
+    })?;
+
+    crate::handlers::log_action(
+        &pool,
+        org_ctx.id,
+        claims.sub,
+        "PLUGIN_PUBLISHER_TRUSTED",
+        "plugin_publisher",
+        publisher.id,
+        serde_json::json!({ "name": publisher.name, "public_key": publisher.public_key }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(publisher)))
+}
+
+/// DELETE /plugins/publishers/{id} - Retira la confianza en una clave de editor
+pub async fn delete_publisher(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if claims.role != "admin" {
+        return Err((StatusCode::FORBIDDEN, "Acceso denegado".to_string()));
+    }
+
+    let result = sqlx::query("DELETE FROM plugin_publishers WHERE id = $1 AND organization_id = $2")
+        .bind(id)
+        .bind(org_ctx.id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Editor no encontrado".to_string()));
+    }
+
+    crate::handlers::log_action(
+        &pool,
+        org_ctx.id,
+        claims.sub,
+        "PLUGIN_PUBLISHER_REVOKED",
+        "plugin_publisher",
+        id,
+        serde_json::json!({}),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,860 @@
+/// Transcripción de audio y video (speech-to-text) con backends intercambiables.
+///
+/// `STT_BACKEND` elige el servidor:
+///
+/// - `faster-whisper` (por defecto): API compatible con OpenAI en
+///   `{WHISPER_URL}/v1/audio/transcriptions` (faster-whisper-server, speaches).
+/// - `whisper-cpp`: servidor de whisper.cpp en `{WHISPER_URL}/inference`.
+/// - `openai`: API de OpenAI con `OPENAI_API_KEY`.
+///
+/// Todos se piden en `verbose_json` con marcas de tiempo por palabra. Los medios
+/// largos se cortan con ffmpeg en tramos de `STT_CHUNK_SECONDS` que se solapan
+/// `STT_CHUNK_OVERLAP_SECONDS`; al unirlos, cada palabra del solape se queda en el
+/// tramo donde cae su punto medio respecto de la mitad del solape. El idioma se
+/// detecta en el primer tramo (salvo que `STT_LANGUAGE` lo fije) y se reutiliza en
+/// los siguientes. Con `STT_DIARIZATION_URL` se identifica además a los hablantes.
+///
+/// Los subtítulos se arman desde las palabras: como máximo 2 líneas de 42
+/// caracteres y 7 segundos por cue, con cortes al final de frase, en silencios
+/// largos y en cada cambio de hablante.
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::path::Path;
+use std::time::Duration;
+use tokio::process::Command;
+use uuid::Uuid;
+
+pub const MAX_LINE_CHARS: usize = 42;
+pub const MAX_LINES: usize = 2;
+const MAX_CUE_SECONDS: f64 = 7.0;
+const MIN_CUE_SECONDS: f64 = 1.0;
+/// Silencio que obliga a empezar un cue nuevo
+const CUE_BREAK_GAP_SECONDS: f64 = 1.5;
+/// Límite de subida de la API de OpenAI (25 MB) con margen
+const MAX_UPLOAD_BYTES: usize = 24 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SttBackend {
+    FasterWhisper,
+    WhisperCpp,
+    OpenAi,
+}
+
+impl SttBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SttBackend::FasterWhisper => "faster-whisper",
+            SttBackend::WhisperCpp => "whisper-cpp",
+            SttBackend::OpenAi => "openai",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SttConfig {
+    pub backend: SttBackend,
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub model: String,
+    /// `None` para detección automática
+    pub language: Option<String>,
+    pub chunk_seconds: f64,
+    pub overlap_seconds: f64,
+    pub diarization_url: Option<String>,
+    pub timeout: Duration,
+}
+
+impl SttConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let var = |name: &str| std::env::var(name).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let seconds = |name: &str, default: f64| var(name).and_then(|v| v.parse::<f64>().ok()).unwrap_or(default);
+
+        let backend = match var("STT_BACKEND").as_deref() {
+            None | Some("faster-whisper") => SttBackend::FasterWhisper,
+            Some("whisper-cpp") => SttBackend::WhisperCpp,
+            Some("openai") => SttBackend::OpenAi,
+            Some(other) => return Err(format!("STT_BACKEND desconocido: {}", other)),
+        };
+        let (base_url, api_key) = match backend {
+            SttBackend::OpenAi => (
+                "https://api.openai.com".to_string(),
+                Some(var("OPENAI_API_KEY").ok_or("Missing OPENAI_API_KEY")?),
+            ),
+            _ => (
+                crate::handlers::get_ai_url("WHISPER_URL", "http://localhost:8000"),
+                var("WHISPER_API_KEY"),
+            ),
+        };
+        let chunk_seconds = seconds("STT_CHUNK_SECONDS", 600.0).max(60.0);
+
+        Ok(Self {
+            backend,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model: var("STT_MODEL").unwrap_or_else(|| "whisper-1".to_string()),
+            language: var("STT_LANGUAGE").filter(|l| l != "auto").map(|l| normalize_language(&l)),
+            chunk_seconds,
+            overlap_seconds: seconds("STT_CHUNK_OVERLAP_SECONDS", 5.0).clamp(0.0, chunk_seconds / 4.0),
+            diarization_url: var("STT_DIARIZATION_URL").map(|u| u.trim_end_matches('/').to_string()),
+            timeout: Duration::from_secs(var("STT_TIMEOUT_SECONDS").and_then(|v| v.parse().ok()).unwrap_or(900)),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptWord {
+    pub text: String,
+    pub start: f64,
+    pub end: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probability: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptSegment {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cue {
+    pub start: f64,
+    pub end: f64,
+    /// Texto ya partido en líneas con `\n`
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Transcript {
+    pub language: Option<String>,
+    pub duration: f64,
+    pub text: String,
+    pub words: Vec<TranscriptWord>,
+    pub segments: Vec<TranscriptSegment>,
+    pub speakers: Vec<String>,
+}
+
+impl Transcript {
+    /// Forma guardada en `lessons.transcription`; conserva `text`, `language` y `cues`
+    /// que ya leen Studio y Experience.
+    pub fn to_json(&self, backend: SttBackend) -> Value {
+        json!({
+            "text": self.text,
+            "language": self.language,
+            "duration": self.duration,
+            "backend": backend.as_str(),
+            "speakers": self.speakers,
+            "segments": self.segments,
+            "words": self.words,
+            "cues": build_cues(&self.words),
+        })
+    }
+}
+
+/// Códigos ISO 639-1 para los nombres que devuelve la API de OpenAI (`"english"`).
+pub fn normalize_language(language: &str) -> String {
+    let language = language.trim().to_lowercase();
+    let code = match language.as_str() {
+        "english" => "en",
+        "spanish" | "castilian" => "es",
+        "portuguese" => "pt",
+        "french" => "fr",
+        "german" => "de",
+        "italian" => "it",
+        "dutch" => "nl",
+        "japanese" => "ja",
+        "chinese" => "zh",
+        "korean" => "ko",
+        "russian" => "ru",
+        "arabic" => "ar",
+        other => other.split(['-', '_']).next().unwrap_or(other),
+    };
+    code.to_string()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Transcripción
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Transcribe un medio completo, cortándolo en tramos si es largo.
+pub async fn transcribe(config: &SttConfig, media: Vec<u8>, filename: &str) -> Result<Transcript, String> {
+    let client = reqwest::Client::builder()
+        .timeout(config.timeout)
+        .build()
+        .map_err(|e| format!("STT client build failed: {}", e))?;
+    let diarization_media = config.diarization_url.as_ref().map(|_| media.clone());
+
+    let work_dir = std::env::temp_dir().join(format!("stt-{}", Uuid::new_v4()));
+    tokio::fs::create_dir_all(&work_dir)
+        .await
+        .map_err(|e| format!("STT temp dir failed: {}", e))?;
+    let result = transcribe_in(config, &client, &work_dir, media, filename).await;
+    let _ = tokio::fs::remove_dir_all(&work_dir).await;
+    let mut transcript = result?;
+
+    if let (Some(url), Some(media)) = (&config.diarization_url, diarization_media) {
+        // La diarización es opcional: si falla se conserva la transcripción sin hablantes
+        match diarize(&client, url, media, filename).await {
+            Ok(turns) => assign_speakers(&mut transcript, &turns),
+            Err(e) => tracing::error!("Diarization failed: {}", e),
+        }
+    }
+    Ok(transcript)
+}
+
+async fn transcribe_in(
+    config: &SttConfig,
+    client: &reqwest::Client,
+    work_dir: &Path,
+    media: Vec<u8>,
+    filename: &str,
+) -> Result<Transcript, String> {
+    let input = work_dir.join(sanitize_filename(filename));
+    tokio::fs::write(&input, &media)
+        .await
+        .map_err(|e| format!("STT temp write failed: {}", e))?;
+
+    let duration = probe_duration(&input).await;
+    let needs_chunks = duration.is_some_and(|d| d > config.chunk_seconds) || media.len() > MAX_UPLOAD_BYTES;
+
+    let Some(duration) = duration.filter(|_| needs_chunks) else {
+        if duration.is_none() {
+            tracing::warn!("ffprobe unavailable or failed; sending {} without chunking", filename);
+        }
+        let value = request(config, client, media, filename, config.language.as_deref()).await?;
+        return Ok(parse_verbose_json(&value, 0.0));
+    };
+
+    // Tramos [inicio, inicio + chunk + solape); la frontera entre dos tramos es la mitad del solape
+    let starts: Vec<f64> = (0..)
+        .map(|i| i as f64 * config.chunk_seconds)
+        .take_while(|start| *start < duration)
+        .collect();
+    tracing::info!("Transcribing {} in {} chunks ({:.0}s)", filename, starts.len(), duration);
+
+    let mut language = config.language.clone();
+    let mut merged = Transcript {
+        duration,
+        ..Default::default()
+    };
+    for (index, start) in starts.iter().enumerate() {
+        let length = config.chunk_seconds + config.overlap_seconds;
+        let chunk_path = work_dir.join(format!("chunk-{:04}.wav", index));
+        extract_chunk(&input, &chunk_path, *start, length).await?;
+        let bytes = tokio::fs::read(&chunk_path)
+            .await
+            .map_err(|e| format!("Chunk read failed: {}", e))?;
+
+        let value = request(config, client, bytes, "chunk.wav", language.as_deref()).await?;
+        let chunk = parse_verbose_json(&value, *start);
+        if language.is_none() {
+            language = chunk.language.clone();
+        }
+        stitch_chunk(&mut merged, chunk, &starts, index, config.overlap_seconds);
+    }
+
+    merged.language = language;
+    merged.text = merged.segments.iter().map(|s| s.text.trim()).collect::<Vec<_>>().join(" ");
+    Ok(merged)
+}
+
+/// Añade al resultado las palabras y segmentos del tramo `index` que caen en su
+/// ventana; la frontera con los tramos vecinos es la mitad del solape, así cada
+/// palabra del solape entra una sola vez.
+fn stitch_chunk(merged: &mut Transcript, chunk: Transcript, starts: &[f64], index: usize, overlap_seconds: f64) {
+    let keep_from = if index == 0 { f64::MIN } else { starts[index] + overlap_seconds / 2.0 };
+    let keep_until = starts
+        .get(index + 1)
+        .map(|next| next + overlap_seconds / 2.0)
+        .unwrap_or(f64::MAX);
+    let in_window = |s: f64, e: f64| {
+        let mid = (s + e) / 2.0;
+        mid >= keep_from && mid < keep_until
+    };
+    merged.words.extend(chunk.words.into_iter().filter(|w| in_window(w.start, w.end)));
+    merged
+        .segments
+        .extend(chunk.segments.into_iter().filter(|s| in_window(s.start, s.start)));
+}
+
+fn sanitize_filename(filename: &str) -> String {
+    let clean: String = filename
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if clean.trim_matches('.').is_empty() { "media.bin".to_string() } else { clean }
+}
+
+async fn probe_duration(input: &Path) -> Option<f64> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-show_entries", "format=duration", "-of", "default=noprint_wrappers=1:nokey=1"])
+        .arg(input)
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+/// Extrae un tramo como WAV mono de 16 kHz, el formato nativo de Whisper.
+async fn extract_chunk(input: &Path, output: &Path, start: f64, length: f64) -> Result<(), String> {
+    let result = Command::new("ffmpeg")
+        .args(["-y", "-v", "error", "-ss", &format!("{:.3}", start), "-t", &format!("{:.3}", length), "-i"])
+        .arg(input)
+        .args(["-vn", "-ac", "1", "-ar", "16000", "-c:a", "pcm_s16le"])
+        .arg(output)
+        .output()
+        .await
+        .map_err(|e| format!("ffmpeg no disponible: {}", e))?;
+    if !result.status.success() {
+        return Err(format!("ffmpeg chunk failed: {}", String::from_utf8_lossy(&result.stderr).trim()));
+    }
+    Ok(())
+}
+
+async fn request(
+    config: &SttConfig,
+    client: &reqwest::Client,
+    bytes: Vec<u8>,
+    filename: &str,
+    language: Option<&str>,
+) -> Result<Value, String> {
+    let part = reqwest::multipart::Part::bytes(bytes).file_name(filename.to_string());
+    let (endpoint, form) = match config.backend {
+        SttBackend::WhisperCpp => {
+            let form = reqwest::multipart::Form::new()
+                .part("file", part)
+                .text("response_format", "verbose_json")
+                .text("temperature", "0.0")
+                .text("language", language.unwrap_or("auto").to_string());
+            (format!("{}/inference", config.base_url), form)
+        }
+        SttBackend::FasterWhisper | SttBackend::OpenAi => {
+            let mut form = reqwest::multipart::Form::new()
+                .part("file", part)
+                .text("model", config.model.clone())
+                .text("response_format", "verbose_json")
+                .text("timestamp_granularities[]", "word")
+                .text("timestamp_granularities[]", "segment");
+            if let Some(language) = language {
+                form = form.text("language", language.to_string());
+            }
+            (format!("{}/v1/audio/transcriptions", config.base_url), form)
+        }
+    };
+
+    let mut builder = client.post(&endpoint).multipart(form);
+    if let Some(key) = &config.api_key {
+        builder = builder.bearer_auth(key);
+    }
+    let response = builder
+        .send()
+        .await
+        .map_err(|e| format!("STT request failed ({}): {}", config.backend.as_str(), e))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("STT API error ({}): {} - {}", config.backend.as_str(), status, body));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse STT response: {}", e))
+}
+
+/// Interpreta `verbose_json` de cualquiera de los backends desplazando los tiempos `offset` segundos.
+///
+/// OpenAI devuelve las palabras en `words`; faster-whisper y whisper.cpp las anidan
+/// en `segments[].words`. Si el servidor no da palabras, se reparten por segmento
+/// en proporción a su longitud.
+pub fn parse_verbose_json(value: &Value, offset: f64) -> Transcript {
+    let word = |w: &Value| -> Option<TranscriptWord> {
+        let text = w["word"].as_str().or_else(|| w["text"].as_str())?.trim().to_string();
+        (!text.is_empty()).then_some(())?;
+        Some(TranscriptWord {
+            text,
+            start: w["start"].as_f64()? + offset,
+            end: w["end"].as_f64()? + offset,
+            probability: w["probability"].as_f64(),
+            speaker: None,
+        })
+    };
+
+    let raw_segments = value["segments"].as_array().cloned().unwrap_or_default();
+    let segments: Vec<TranscriptSegment> = raw_segments
+        .iter()
+        .filter_map(|s| {
+            Some(TranscriptSegment {
+                start: s["start"].as_f64()? + offset,
+                end: s["end"].as_f64()? + offset,
+                text: s["text"].as_str()?.trim().to_string(),
+                speaker: None,
+            })
+        })
+        .filter(|s| !s.text.is_empty())
+        .collect();
+
+    let mut words: Vec<TranscriptWord> = match value["words"].as_array() {
+        Some(items) => items.iter().filter_map(word).collect(),
+        None => raw_segments
+            .iter()
+            .filter_map(|s| s["words"].as_array())
+            .flatten()
+            .filter_map(word)
+            .collect(),
+    };
+    if words.is_empty() {
+        words = segments.iter().flat_map(spread_segment_words).collect();
+    }
+
+    let text = value["text"]
+        .as_str()
+        .map(|t| t.trim().to_string())
+        .unwrap_or_else(|| segments.iter().map(|s| s.text.as_str()).collect::<Vec<_>>().join(" "));
+
+    Transcript {
+        language: value["language"].as_str().map(normalize_language),
+        duration: value["duration"].as_f64().unwrap_or(0.0),
+        text,
+        words,
+        segments,
+        speakers: Vec::new(),
+    }
+}
+
+fn spread_segment_words(segment: &TranscriptSegment) -> Vec<TranscriptWord> {
+    let tokens: Vec<&str> = segment.text.split_whitespace().collect();
+    let total_chars: usize = tokens.iter().map(|t| t.chars().count()).sum();
+    let span = (segment.end - segment.start).max(0.0);
+    let mut cursor = segment.start;
+    tokens
+        .into_iter()
+        .map(|token| {
+            let length = span * token.chars().count() as f64 / total_chars.max(1) as f64;
+            let word = TranscriptWord {
+                text: token.to_string(),
+                start: cursor,
+                end: cursor + length,
+                probability: None,
+                speaker: None,
+            };
+            cursor += length;
+            word
+        })
+        .collect()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Diarización
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+struct SpeakerTurn {
+    start: f64,
+    end: f64,
+    speaker: String,
+}
+
+/// Servicio de diarización (p. ej. pyannote tras una API HTTP): recibe el medio en
+/// `file` y responde `{"segments": [{"start", "end", "speaker"}]}`.
+async fn diarize(
+    client: &reqwest::Client,
+    url: &str,
+    media: Vec<u8>,
+    filename: &str,
+) -> Result<Vec<SpeakerTurn>, String> {
+    let form = reqwest::multipart::Form::new()
+        .part("file", reqwest::multipart::Part::bytes(media).file_name(filename.to_string()));
+    let response = client
+        .post(url)
+        .multipart(form)
+        .send()
+        .await
+        .map_err(|e| format!("Diarization request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Diarization API error: {}", response.status()));
+    }
+    let value: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse diarization response: {}", e))?;
+    serde_json::from_value(value["segments"].clone()).map_err(|e| format!("Invalid diarization segments: {}", e))
+}
+
+/// Asigna a cada palabra el hablante con más solape temporal y numera los
+/// hablantes por orden de aparición (`Speaker 1`, `Speaker 2`, …).
+fn assign_speakers(transcript: &mut Transcript, turns: &[SpeakerTurn]) {
+    if turns.is_empty() {
+        return;
+    }
+    let mut labels: Vec<(String, String)> = Vec::new();
+    let overlap = |start: f64, end: f64, turn: &SpeakerTurn| (end.min(turn.end) - start.max(turn.start)).max(0.0);
+
+    for word in &mut transcript.words {
+        let best = turns
+            .iter()
+            .map(|turn| (overlap(word.start, word.end, turn), turn))
+            .filter(|(o, _)| *o > 0.0)
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, turn)| turn)
+            // Palabra en un hueco entre turnos: el turno más cercano
+            .or_else(|| {
+                turns.iter().min_by(|a, b| {
+                    let distance = |t: &SpeakerTurn| (t.start - word.end).abs().min((word.start - t.end).abs());
+                    distance(a).total_cmp(&distance(b))
+                })
+            });
+        word.speaker = best.map(|turn| {
+            if let Some((_, label)) = labels.iter().find(|(raw, _)| *raw == turn.speaker) {
+                return label.clone();
+            }
+            let label = format!("Speaker {}", labels.len() + 1);
+            labels.push((turn.speaker.clone(), label.clone()));
+            label
+        });
+    }
+
+    for segment in &mut transcript.segments {
+        let mut totals: Vec<(&str, f64)> = Vec::new();
+        for word in transcript
+            .words
+            .iter()
+            .filter(|w| w.start >= segment.start - 0.01 && w.end <= segment.end + 0.01)
+        {
+            if let Some(speaker) = word.speaker.as_deref() {
+                match totals.iter_mut().find(|(s, _)| *s == speaker) {
+                    Some((_, total)) => *total += word.end - word.start,
+                    None => totals.push((speaker, word.end - word.start)),
+                }
+            }
+        }
+        segment.speaker = totals
+            .into_iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(speaker, _)| speaker.to_string());
+    }
+
+    transcript.speakers = labels.into_iter().map(|(_, label)| label).collect();
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Subtítulos
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Parte el texto en líneas de hasta `MAX_LINE_CHARS`. Si cabe en dos líneas
+/// las equilibra, prefiriendo cortar tras una coma o un punto.
+pub fn wrap_lines(text: &str) -> Vec<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let joined = words.join(" ");
+    if joined.chars().count() <= MAX_LINE_CHARS || words.len() < 2 {
+        return vec![joined];
+    }
+
+    let best_split = (1..words.len())
+        .filter_map(|split| {
+            let first = words[..split].join(" ");
+            let second = words[split..].join(" ");
+            let (a, b) = (first.chars().count(), second.chars().count());
+            if a > MAX_LINE_CHARS || b > MAX_LINE_CHARS {
+                return None;
+            }
+            let punctuation_bonus = if first.ends_with([',', '.', ';', ':', '?', '!']) { 8 } else { 0 };
+            Some((a.abs_diff(b) as i64 - punctuation_bonus, first, second))
+        })
+        .min_by_key(|(cost, _, _)| *cost);
+    if let Some((_, first, second)) = best_split {
+        return vec![first, second];
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    for word in words {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= MAX_LINE_CHARS => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+/// Agrupa las palabras en cues que respetan líneas, duración, pausas y hablantes.
+pub fn build_cues(words: &[TranscriptWord]) -> Vec<Cue> {
+    let mut cues: Vec<Cue> = Vec::new();
+    let mut current: Vec<&TranscriptWord> = Vec::new();
+
+    let flush = |current: &mut Vec<&TranscriptWord>, cues: &mut Vec<Cue>| {
+        if let (Some(first), Some(last)) = (current.first(), current.last()) {
+            let text = current.iter().map(|w| w.text.as_str()).collect::<Vec<_>>().join(" ");
+            cues.push(Cue {
+                start: first.start,
+                end: last.end.max(first.start),
+                text: wrap_lines(&text).join("\n"),
+                speaker: first.speaker.clone(),
+            });
+        }
+        current.clear();
+    };
+
+    for word in words {
+        if let (Some(first), Some(last)) = (current.first(), current.last()) {
+            let candidate = current
+                .iter()
+                .map(|w| w.text.as_str())
+                .chain(std::iter::once(word.text.as_str()))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let must_break = word.speaker != first.speaker
+                || word.start - last.end > CUE_BREAK_GAP_SECONDS
+                || word.end - first.start > MAX_CUE_SECONDS
+                || wrap_lines(&candidate).len() > MAX_LINES;
+            if must_break {
+                flush(&mut current, &mut cues);
+            }
+        }
+        current.push(word);
+
+        // Final de frase: cortar si el cue ya tiene una línea razonable
+        let length: usize = current.iter().map(|w| w.text.chars().count() + 1).sum();
+        if word.text.ends_with(['.', '?', '!']) && length >= MAX_LINE_CHARS / 2 {
+            flush(&mut current, &mut cues);
+        }
+    }
+    flush(&mut current, &mut cues);
+
+    // Duración mínima de lectura sin pisar el cue siguiente
+    for index in 0..cues.len() {
+        let next_start = cues.get(index + 1).map(|c| c.start).unwrap_or(f64::MAX);
+        let cue = &mut cues[index];
+        if cue.end - cue.start < MIN_CUE_SECONDS {
+            cue.end = (cue.start + MIN_CUE_SECONDS).min(next_start);
+        }
+    }
+    cues
+}
+
+/// Cues guardados en `lessons.transcription`, reenvueltos si venían de versiones sin líneas.
+pub fn cues_from_json(value: &Value) -> Vec<Cue> {
+    value
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|cue| {
+                    let text = cue["text"].as_str()?.trim();
+                    let text = if text.contains('\n') { text.to_string() } else { wrap_lines(text).join("\n") };
+                    Some(Cue {
+                        start: cue["start"].as_f64().unwrap_or(0.0),
+                        end: cue["end"].as_f64().unwrap_or(0.0),
+                        text,
+                        speaker: cue["speaker"].as_str().map(str::to_string),
+                    })
+                })
+                .filter(|cue| !cue.text.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// `HH:MM:SS.mmm` (WebVTT) o `HH:MM:SS,mmm` (SRT).
+fn format_timestamp(seconds: f64, separator: char) -> String {
+    let total_millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    let (hours, rest) = (total_millis / 3_600_000, total_millis % 3_600_000);
+    let (minutes, rest) = (rest / 60_000, rest % 60_000);
+    let (secs, millis) = (rest / 1000, rest % 1000);
+    format!("{:02}:{:02}:{:02}{}{:03}", hours, minutes, secs, separator, millis)
+}
+
+fn escape_vtt(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// WebVTT con el hablante como etiqueta de voz (`<v Speaker 1>`).
+pub fn to_webvtt(cues: &[Cue]) -> String {
+    let mut vtt = String::from("WEBVTT\n\n");
+    for (index, cue) in cues.iter().enumerate() {
+        let text = escape_vtt(&cue.text);
+        let text = match &cue.speaker {
+            Some(speaker) => format!("<v {}>{}", escape_vtt(speaker), text),
+            None => text,
+        };
+        vtt.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            index + 1,
+            format_timestamp(cue.start, '.'),
+            format_timestamp(cue.end, '.'),
+            text
+        ));
+    }
+    vtt
+}
+
+/// SRT; SubRip no tiene etiquetas de voz, así que el hablante se antepone entre
+/// corchetes cuando cambia.
+pub fn to_srt(cues: &[Cue]) -> String {
+    let mut srt = String::new();
+    let mut previous_speaker: Option<&str> = None;
+    for (index, cue) in cues.iter().enumerate() {
+        let text = match cue.speaker.as_deref() {
+            Some(speaker) if previous_speaker != Some(speaker) => format!("[{}] {}", speaker, cue.text),
+            _ => cue.text.clone(),
+        };
+        previous_speaker = cue.speaker.as_deref();
+        srt.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            index + 1,
+            format_timestamp(cue.start, ','),
+            format_timestamp(cue.end, ','),
+            text
+        ));
+    }
+    srt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, start: f64, end: f64) -> TranscriptWord {
+        TranscriptWord {
+            text: text.to_string(),
+            start,
+            end,
+            probability: None,
+            speaker: None,
+        }
+    }
+
+    /// Palabras consecutivas de 0,3 s separadas por 0,1 s.
+    fn words(text: &str, from: f64) -> Vec<TranscriptWord> {
+        text.split_whitespace()
+            .enumerate()
+            .map(|(i, t)| word(t, from + i as f64 * 0.4, from + i as f64 * 0.4 + 0.3))
+            .collect()
+    }
+
+    fn assert_fits(lines: &[String]) {
+        assert!(lines.len() <= MAX_LINES, "{:?}", lines);
+        assert!(lines.iter().all(|l| l.chars().count() <= MAX_LINE_CHARS), "{:?}", lines);
+    }
+
+    #[test]
+    fn short_text_stays_on_one_line() {
+        assert_eq!(wrap_lines("  Hola   a todos  "), vec!["Hola a todos"]);
+        let exact = "a".repeat(MAX_LINE_CHARS);
+        assert_eq!(wrap_lines(&exact), vec![exact.clone()]);
+    }
+
+    #[test]
+    fn two_lines_are_balanced_and_prefer_punctuation() {
+        let lines = wrap_lines("Bienvenidos al curso de introducción a la programación en Rust");
+        assert_eq!(lines, ["Bienvenidos al curso de introducción", "a la programación en Rust"]);
+
+        let lines = wrap_lines("Hola a todos y bienvenidos, hoy vamos a ver las funciones");
+        assert_eq!(lines[0], "Hola a todos y bienvenidos,");
+        assert_fits(&lines);
+    }
+
+    #[test]
+    fn text_longer_than_two_lines_is_filled_greedily() {
+        let text = "palabra ".repeat(15);
+        let lines = wrap_lines(&text);
+        assert!(lines.len() > MAX_LINES);
+        assert!(lines.iter().all(|l| l.chars().count() <= MAX_LINE_CHARS));
+        assert_eq!(lines.join(" "), text.trim());
+    }
+
+    #[test]
+    fn cues_respect_the_line_rules_and_duration() {
+        let text = "uno dos tres cuatro cinco seis siete ocho nueve diez once doce trece catorce \
+                    quince dieciséis diecisiete dieciocho diecinueve veinte veintiuno veintidós";
+        let cues = build_cues(&words(text, 0.0));
+        assert!(cues.len() > 1);
+        for cue in &cues {
+            assert_fits(&cue.text.lines().map(str::to_string).collect::<Vec<_>>());
+            assert!(cue.end - cue.start <= MAX_CUE_SECONDS);
+        }
+        let rebuilt: Vec<&str> = cues.iter().flat_map(|c| c.text.split_whitespace()).collect();
+        assert_eq!(rebuilt, text.split_whitespace().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn cues_break_at_sentence_end_pauses_and_speakers() {
+        // Final de frase con al menos media línea
+        let cues = build_cues(&words("Esta es la primera frase completa. Y esta la segunda", 0.0));
+        assert_eq!(cues[0].text, "Esta es la primera frase completa.");
+        assert_eq!(cues[1].text, "Y esta la segunda");
+
+        // Una frase muy corta no se corta sola
+        let cues = build_cues(&words("Sí. Claro que sí", 0.0));
+        assert_eq!(cues.len(), 1);
+
+        // Silencio largo
+        let mut ws = words("antes de la pausa", 0.0);
+        ws.extend(words("después", 10.0));
+        let cues = build_cues(&ws);
+        assert_eq!(cues.iter().map(|c| c.text.as_str()).collect::<Vec<_>>(), ["antes de la pausa", "después"]);
+
+        // Cambio de hablante
+        let mut ws = words("hola qué tal", 0.0);
+        ws.iter_mut().for_each(|w| w.speaker = Some("Speaker 1".to_string()));
+        let mut reply = words("bien gracias", 1.2);
+        reply.iter_mut().for_each(|w| w.speaker = Some("Speaker 2".to_string()));
+        ws.extend(reply);
+        let cues = build_cues(&ws);
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[1].speaker.as_deref(), Some("Speaker 2"));
+    }
+
+    #[test]
+    fn short_cues_get_minimum_duration_without_overlapping() {
+        let cues = build_cues(&[word("Hola.", 0.0, 0.2), word("Adiós.", 2.0, 2.3)]);
+        assert_eq!(cues.len(), 2);
+        assert!((cues[0].end - MIN_CUE_SECONDS).abs() < 1e-9);
+
+        // Sin pisar el cue siguiente
+        let mut reply = word("dos", 0.5, 0.7);
+        reply.speaker = Some("Speaker 2".to_string());
+        let cues = build_cues(&[word("Uno", 0.0, 0.2), reply]);
+        assert_eq!(cues[0].end, 0.5);
+    }
+
+    #[test]
+    fn overlapping_chunks_keep_each_word_once() {
+        // Tramos de 10 s con 4 s de solape: la frontera está en 12 s
+        let starts = [0.0, 10.0];
+        let overlap = 4.0;
+        let first = Transcript {
+            words: vec![word("a", 9.0, 9.5), word("b", 11.5, 11.9), word("c", 12.1, 12.5), word("d", 13.0, 13.4)],
+            segments: vec![TranscriptSegment { start: 9.0, end: 13.4, text: "a b c d".to_string(), speaker: None }],
+            ..Default::default()
+        };
+        // El segundo tramo vuelve a transcribir el solape [10, 14)
+        let second = Transcript {
+            words: vec![word("b", 11.6, 11.9), word("c", 12.0, 12.5), word("d", 13.0, 13.4), word("e", 15.0, 15.5)],
+            segments: vec![TranscriptSegment { start: 12.0, end: 15.5, text: "c d e".to_string(), speaker: None }],
+            ..Default::default()
+        };
+
+        let mut merged = Transcript::default();
+        stitch_chunk(&mut merged, first, &starts, 0, overlap);
+        stitch_chunk(&mut merged, second, &starts, 1, overlap);
+
+        let texts: Vec<&str> = merged.words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(texts, ["a", "b", "c", "d", "e"]);
+        assert_eq!(merged.segments.len(), 2);
+        // b viene del primer tramo y c del segundo
+        assert_eq!(merged.words[1].start, 11.5);
+        assert_eq!(merged.words[2].start, 12.0);
+    }
+}
@@ -0,0 +1,48 @@
+-- Log de operaciones incremental para la pizarra colaborativa
+--
+-- El snapshot en lesson_collaborative_canvases.canvas_state refleja todas las
+-- operaciones hasta compacted_seq; las operaciones posteriores se aplican al leer.
+ALTER TABLE lesson_collaborative_canvases
+    ADD COLUMN IF NOT EXISTS head_seq      BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS compacted_seq BIGINT NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS lesson_canvas_ops (
+    id              UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    lesson_id       UUID        NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+    organization_id UUID        NOT NULL,
+    seq             BIGINT      NOT NULL,            -- secuencia por lección asignada por el servidor
+    user_id         UUID        REFERENCES users(id) ON DELETE SET NULL,
+    op_type         TEXT        NOT NULL,            -- 'add' | 'update' | 'delete' | 'replace'
+    element_id      TEXT,                            -- NULL solo para 'replace'
+    payload         JSONB       NOT NULL DEFAULT '{}'::jsonb,
+    prev_element    JSONB,                           -- estado previo del elemento (para deshacer)
+    undo_of         BIGINT,                          -- seq de la operación que esta deshace
+    undone_at       TIMESTAMPTZ,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT lesson_canvas_ops_type_check CHECK (op_type IN ('add', 'update', 'delete', 'replace')),
+    CONSTRAINT lesson_canvas_ops_seq_unique UNIQUE (lesson_id, seq)
+);
+
+CREATE INDEX IF NOT EXISTS idx_lesson_canvas_ops_element
+    ON lesson_canvas_ops (lesson_id, element_id, seq);
+
+CREATE INDEX IF NOT EXISTS idx_lesson_canvas_ops_user
+    ON lesson_canvas_ops (lesson_id, user_id, seq DESC);
+
+CREATE INDEX IF NOT EXISTS idx_lesson_canvas_ops_created
+    ON lesson_canvas_ops (lesson_id, created_at);
+
+-- Checkpoints generados por la compactación; permiten reproducir el historial
+-- sin conservar todas las operaciones desde el inicio.
+CREATE TABLE IF NOT EXISTS lesson_canvas_snapshots (
+    id              UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    lesson_id       UUID        NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+    organization_id UUID        NOT NULL,
+    seq             BIGINT      NOT NULL,
+    canvas_state    JSONB       NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT lesson_canvas_snapshots_seq_unique UNIQUE (lesson_id, seq)
+);
+
+CREATE INDEX IF NOT EXISTS idx_lesson_canvas_snapshots_lesson
+    ON lesson_canvas_snapshots (lesson_id, seq DESC);
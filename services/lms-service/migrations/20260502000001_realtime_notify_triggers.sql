@@ -0,0 +1,60 @@
+-- Push en tiempo real vía LISTEN/NOTIFY
+--
+-- Cada cambio relevante emite un evento en el canal 'openccb_events' con el
+-- formato {"topic": "...", "data": {...}}. El payload solo lleva identificadores:
+-- los streams SSE consultan el estado actualizado al recibir el aviso.
+CREATE OR REPLACE FUNCTION fn_notify_realtime_event() RETURNS trigger AS $$
+DECLARE
+    v_topic TEXT;
+    v_data  JSONB;
+BEGIN
+    IF TG_TABLE_NAME = 'notifications' THEN
+        v_topic := 'notifications:' || NEW.user_id;
+        v_data := jsonb_build_object('id', NEW.id);
+    ELSIF TG_TABLE_NAME = 'lesson_collaborative_docs' THEN
+        IF TG_OP = 'UPDATE' AND OLD.revision = NEW.revision THEN
+            RETURN NEW;
+        END IF;
+        v_topic := 'doc:' || NEW.lesson_id;
+        v_data := jsonb_build_object('revision', NEW.revision);
+    ELSIF TG_TABLE_NAME = 'lesson_collaborative_canvases' THEN
+        -- La compactación no cambia la revisión: no genera eventos
+        IF TG_OP = 'UPDATE' AND OLD.revision = NEW.revision THEN
+            RETURN NEW;
+        END IF;
+        v_topic := 'canvas:' || NEW.lesson_id;
+        v_data := jsonb_build_object('revision', NEW.revision);
+    ELSIF TG_TABLE_NAME = 'lesson_canvas_ops' THEN
+        v_topic := 'canvas-ops:' || NEW.lesson_id;
+        v_data := jsonb_build_object('seq', NEW.seq);
+    ELSE
+        RETURN NEW;
+    END IF;
+
+    PERFORM pg_notify(
+        'openccb_events',
+        jsonb_build_object('topic', v_topic, 'data', v_data)::text
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_notifications_realtime ON notifications;
+CREATE TRIGGER trg_notifications_realtime
+    AFTER INSERT OR UPDATE ON notifications
+    FOR EACH ROW EXECUTE FUNCTION fn_notify_realtime_event();
+
+DROP TRIGGER IF EXISTS trg_collaborative_docs_realtime ON lesson_collaborative_docs;
+CREATE TRIGGER trg_collaborative_docs_realtime
+    AFTER INSERT OR UPDATE ON lesson_collaborative_docs
+    FOR EACH ROW EXECUTE FUNCTION fn_notify_realtime_event();
+
+DROP TRIGGER IF EXISTS trg_collaborative_canvases_realtime ON lesson_collaborative_canvases;
+CREATE TRIGGER trg_collaborative_canvases_realtime
+    AFTER INSERT OR UPDATE ON lesson_collaborative_canvases
+    FOR EACH ROW EXECUTE FUNCTION fn_notify_realtime_event();
+
+DROP TRIGGER IF EXISTS trg_canvas_ops_realtime ON lesson_canvas_ops;
+CREATE TRIGGER trg_canvas_ops_realtime
+    AFTER INSERT ON lesson_canvas_ops
+    FOR EACH ROW EXECUTE FUNCTION fn_notify_realtime_event();
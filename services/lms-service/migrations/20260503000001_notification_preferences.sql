@@ -0,0 +1,54 @@
+-- Preferencias de notificación por usuario y tipo, y envío de resúmenes por email
+CREATE TABLE IF NOT EXISTS notification_preferences (
+    user_id           UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    organization_id   UUID        NOT NULL,
+    notification_type VARCHAR(50) NOT NULL,
+    channel           TEXT        NOT NULL DEFAULT 'in_app',
+    updated_at        TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, notification_type),
+    CONSTRAINT notification_preferences_channel_check
+        CHECK (channel IN ('in_app', 'email_immediate', 'daily_digest', 'weekly_digest', 'off'))
+);
+
+-- Último resumen enviado por usuario (también sirve como "claim" entre instancias)
+CREATE TABLE IF NOT EXISTS notification_digest_state (
+    user_id        UUID        PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    last_daily_at  TIMESTAMPTZ,
+    last_weekly_at TIMESTAMPTZ
+);
+
+-- Canal resuelto al crear la notificación y marca de envío por email
+ALTER TABLE notifications
+    ADD COLUMN IF NOT EXISTS delivery_channel TEXT NOT NULL DEFAULT 'in_app',
+    ADD COLUMN IF NOT EXISTS emailed_at       TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_notifications_pending_email
+    ON notifications (delivery_channel, created_at)
+    WHERE emailed_at IS NULL AND delivery_channel <> 'in_app';
+
+-- Aplica la preferencia del usuario a toda notificación, sin importar qué handler
+-- o tarea la cree. 'off' descarta la fila.
+CREATE OR REPLACE FUNCTION fn_apply_notification_preferences() RETURNS trigger AS $$
+DECLARE
+    v_channel TEXT;
+BEGIN
+    SELECT channel INTO v_channel
+    FROM notification_preferences
+    WHERE user_id = NEW.user_id
+      AND notification_type = COALESCE(NEW.notification_type, 'info');
+
+    v_channel := COALESCE(v_channel, 'in_app');
+
+    IF v_channel = 'off' THEN
+        RETURN NULL;
+    END IF;
+
+    NEW.delivery_channel := v_channel;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_apply_notification_preferences ON notifications;
+CREATE TRIGGER trg_apply_notification_preferences
+    BEFORE INSERT ON notifications
+    FOR EACH ROW EXECUTE FUNCTION fn_apply_notification_preferences();
@@ -0,0 +1,28 @@
+-- Suscripciones Web Push (VAPID) por dispositivo
+CREATE TABLE IF NOT EXISTS push_subscriptions (
+    id               UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id          UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    organization_id  UUID        NOT NULL,
+    endpoint         TEXT        NOT NULL,
+    p256dh           TEXT        NOT NULL,            -- clave pública del navegador (base64url)
+    auth             TEXT        NOT NULL,            -- secreto de autenticación (base64url)
+    user_agent       TEXT,
+    failure_count    INTEGER     NOT NULL DEFAULT 0,
+    last_success_at  TIMESTAMPTZ,
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at       TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT push_subscriptions_endpoint_unique UNIQUE (endpoint)
+);
+
+CREATE INDEX IF NOT EXISTS idx_push_subscriptions_user ON push_subscriptions (user_id);
+
+-- Interruptor de push por tipo de notificación (independiente del canal de email)
+ALTER TABLE notification_preferences
+    ADD COLUMN IF NOT EXISTS push_enabled BOOLEAN NOT NULL DEFAULT TRUE;
+
+ALTER TABLE notifications
+    ADD COLUMN IF NOT EXISTS pushed_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_notifications_pending_push
+    ON notifications (created_at)
+    WHERE pushed_at IS NULL;
@@ -0,0 +1,19 @@
+-- Tokens secretos de los feeds iCalendar (.ics)
+--
+-- course_id NULL: feed personal (todos los cursos del usuario).
+-- course_id definido: feed del curso para instructores.
+CREATE TABLE IF NOT EXISTS calendar_feed_tokens (
+    id              UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id         UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    organization_id UUID        NOT NULL,
+    course_id       UUID        REFERENCES courses(id) ON DELETE CASCADE,
+    token           VARCHAR(64) NOT NULL UNIQUE,
+    last_accessed_at TIMESTAMPTZ,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at      TIMESTAMPTZ
+);
+
+-- Un único token activo por usuario y alcance
+CREATE UNIQUE INDEX IF NOT EXISTS idx_calendar_feed_tokens_active
+    ON calendar_feed_tokens (user_id, COALESCE(course_id, '00000000-0000-0000-0000-000000000000'::uuid))
+    WHERE revoked_at IS NULL;
@@ -0,0 +1,26 @@
+-- Respuestas por ítem de los cuestionarios, base de los análisis psicométricos (TRI)
+--
+-- item_id es el id de la pregunta dentro de lessons.metadata; question_bank_id se
+-- resuelve al registrar la respuesta cuando la pregunta proviene del banco del CMS.
+CREATE TABLE IF NOT EXISTS quiz_item_responses (
+    id               UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id  UUID        NOT NULL,
+    user_id          UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    course_id        UUID        NOT NULL,
+    lesson_id        UUID        NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+    item_id          TEXT        NOT NULL,
+    question_bank_id UUID,
+    attempt          INTEGER     NOT NULL DEFAULT 1,
+    selected         JSONB,                       -- índice(s) de opción elegidos
+    score            REAL        NOT NULL,        -- 0.0 a 1.0
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT quiz_item_responses_unique UNIQUE (user_id, lesson_id, item_id, attempt),
+    CONSTRAINT quiz_item_responses_score_range CHECK (score >= 0 AND score <= 1)
+);
+
+CREATE INDEX IF NOT EXISTS idx_quiz_item_responses_lesson
+    ON quiz_item_responses (lesson_id, item_id);
+
+CREATE INDEX IF NOT EXISTS idx_quiz_item_responses_bank
+    ON quiz_item_responses (organization_id, question_bank_id)
+    WHERE question_bank_id IS NOT NULL;
@@ -0,0 +1,31 @@
+-- Intentos de pruebas adaptativas computarizadas (CAT)
+--
+-- responses guarda cada ítem administrado con sus parámetros TRI y la estimación de
+-- habilidad tras responderlo: [{ item_id, question_bank_id, selected, correct,
+-- difficulty, discrimination, theta, standard_error, answered_at }].
+CREATE TABLE IF NOT EXISTS adaptive_test_attempts (
+    id                 UUID             PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id    UUID             NOT NULL,
+    user_id            UUID             NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    course_id          UUID             NOT NULL,
+    lesson_id          UUID             NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+    status             VARCHAR(16)      NOT NULL DEFAULT 'in_progress',
+    theta              DOUBLE PRECISION NOT NULL DEFAULT 0,
+    standard_error     DOUBLE PRECISION NOT NULL DEFAULT 1,
+    items_administered INTEGER          NOT NULL DEFAULT 0,
+    current_item_id    TEXT,
+    responses          JSONB            NOT NULL DEFAULT '[]'::jsonb,
+    stop_reason        VARCHAR(32),                 -- se_threshold | max_items | pool_exhausted
+    cefr_level         VARCHAR(8),
+    started_at         TIMESTAMPTZ      NOT NULL DEFAULT NOW(),
+    completed_at       TIMESTAMPTZ,
+    CONSTRAINT adaptive_test_attempts_status CHECK (status IN ('in_progress', 'completed'))
+);
+
+-- Un único intento en curso por alumno y lección
+CREATE UNIQUE INDEX IF NOT EXISTS idx_adaptive_test_attempts_in_progress
+    ON adaptive_test_attempts (user_id, lesson_id)
+    WHERE status = 'in_progress';
+
+CREATE INDEX IF NOT EXISTS idx_adaptive_test_attempts_lesson
+    ON adaptive_test_attempts (lesson_id, completed_at DESC);
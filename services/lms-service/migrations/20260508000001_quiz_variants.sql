@@ -0,0 +1,23 @@
+-- Variantes de cuestionario sorteadas por alumno e intento desde un pool del banco
+--
+-- questions guarda exactamente lo que vio el alumno (ítems, orden y opciones
+-- barajadas con su orden original en option_order) para calificar y revisar.
+CREATE TABLE IF NOT EXISTS quiz_variants (
+    id              UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID        NOT NULL,
+    user_id         UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    course_id       UUID        NOT NULL,
+    lesson_id       UUID        NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+    block_id        TEXT        NOT NULL,
+    attempt         INTEGER     NOT NULL,
+    seed            BIGINT      NOT NULL,
+    questions       JSONB       NOT NULL,
+    responses       JSONB,
+    score           REAL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    submitted_at    TIMESTAMPTZ,
+    CONSTRAINT quiz_variants_unique UNIQUE (user_id, lesson_id, block_id, attempt)
+);
+
+CREATE INDEX IF NOT EXISTS idx_quiz_variants_lesson
+    ON quiz_variants (lesson_id, attempt);
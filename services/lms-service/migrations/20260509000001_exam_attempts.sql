@@ -0,0 +1,55 @@
+-- Exámenes con tiempo: intentos con plazo controlado por el servidor
+--
+-- Una lección es un examen cuando lessons.metadata incluye el objeto "exam"
+-- ({ time_limit_minutes, available_from, available_until }).
+
+-- Tiempo extra por alumno (adecuaciones). lesson_id NULL aplica a todo el curso.
+CREATE TABLE IF NOT EXISTS exam_accommodations (
+    id                 UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id    UUID        NOT NULL,
+    user_id            UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    course_id          UUID        NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    lesson_id          UUID        REFERENCES lessons(id) ON DELETE CASCADE,
+    extra_time_percent INTEGER     NOT NULL,
+    notes              TEXT,
+    created_by         UUID,
+    created_at         TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at         TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT exam_accommodations_percent_range CHECK (extra_time_percent BETWEEN 0 AND 400)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_exam_accommodations_scope
+    ON exam_accommodations (user_id, course_id, COALESCE(lesson_id, '00000000-0000-0000-0000-000000000000'::uuid));
+
+CREATE TABLE IF NOT EXISTS exam_attempts (
+    id                 UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id    UUID        NOT NULL,
+    user_id            UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    course_id          UUID        NOT NULL,
+    lesson_id          UUID        NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+    status             VARCHAR(16) NOT NULL DEFAULT 'in_progress',
+    session_token      VARCHAR(64),                -- NULL tras reabrir: la próxima sesión lo reclama
+    extra_time_percent INTEGER     NOT NULL DEFAULT 0,
+    started_at         TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deadline_at        TIMESTAMPTZ,                -- NULL: sin límite de tiempo ni cierre
+    saved_answers      JSONB,                      -- { score, metadata } del último autoguardado
+    saved_at           TIMESTAMPTZ,
+    submitted_at       TIMESTAMPTZ,
+    auto_submitted     BOOLEAN     NOT NULL DEFAULT false,
+    reopened_by        UUID,
+    reopened_at        TIMESTAMPTZ,
+    reopen_count       INTEGER     NOT NULL DEFAULT 0,
+    CONSTRAINT exam_attempts_status CHECK (status IN ('in_progress', 'submitted', 'expired'))
+);
+
+-- Un único intento en curso por alumno y examen
+CREATE UNIQUE INDEX IF NOT EXISTS idx_exam_attempts_in_progress
+    ON exam_attempts (user_id, lesson_id)
+    WHERE status = 'in_progress';
+
+CREATE INDEX IF NOT EXISTS idx_exam_attempts_deadline
+    ON exam_attempts (deadline_at)
+    WHERE status = 'in_progress';
+
+CREATE INDEX IF NOT EXISTS idx_exam_attempts_lesson
+    ON exam_attempts (lesson_id, started_at DESC);
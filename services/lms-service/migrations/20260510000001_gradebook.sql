@@ -0,0 +1,34 @@
+-- Libro de calificaciones: penalizaciones, ajustes manuales y escalas de letras
+
+-- Fracción (0.0 a 1.0) descontada de la calificación por entrega tardía
+ALTER TABLE user_grades ADD COLUMN IF NOT EXISTS late_penalty REAL NOT NULL DEFAULT 0;
+
+-- Ajustes manuales del instructor. Tabla de solo inserción: es el historial de
+-- auditoría y el ajuste vigente es el más reciente por (alumno, curso, lección).
+-- lesson_id NULL ajusta la nota final del curso; score NULL elimina el ajuste.
+CREATE TABLE IF NOT EXISTS grade_overrides (
+    id              UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID        NOT NULL,
+    course_id       UUID        NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    user_id         UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    lesson_id       UUID        REFERENCES lessons(id) ON DELETE CASCADE,
+    score           REAL,                              -- 0.0 a 1.0
+    previous_score  REAL,                              -- calificación efectiva antes del ajuste
+    comment         TEXT        NOT NULL,
+    created_by      UUID        NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT grade_overrides_score_range CHECK (score IS NULL OR (score >= 0 AND score <= 1))
+);
+
+CREATE INDEX IF NOT EXISTS idx_grade_overrides_course
+    ON grade_overrides (course_id, user_id, lesson_id, created_at DESC);
+
+-- Escala de letras por curso: [{ "letter": "A", "min_percent": 90 }, ...]
+CREATE TABLE IF NOT EXISTS grading_schemes (
+    course_id       UUID        PRIMARY KEY REFERENCES courses(id) ON DELETE CASCADE,
+    organization_id UUID        NOT NULL,
+    name            VARCHAR(100) NOT NULL DEFAULT 'Personalizada',
+    bands           JSONB       NOT NULL,
+    updated_by      UUID,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
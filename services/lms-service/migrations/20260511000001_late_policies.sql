@@ -0,0 +1,48 @@
+-- Políticas de entrega tardía y prórrogas individuales de la fecha límite
+--
+-- Una política se define por lección o por categoría de calificación; la de la
+-- lección prevalece. Sin política, las entregas tardías se aceptan sin penalización.
+
+CREATE TABLE IF NOT EXISTS late_policies (
+    id                      UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id         UUID        NOT NULL,
+    course_id               UUID        NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    lesson_id               UUID        UNIQUE REFERENCES lessons(id) ON DELETE CASCADE,
+    grading_category_id     UUID        UNIQUE REFERENCES grading_categories(id) ON DELETE CASCADE,
+    accept_late             BOOLEAN     NOT NULL DEFAULT TRUE,
+    penalty_percent_per_day REAL        NOT NULL DEFAULT 0,
+    max_penalty_percent     REAL        NOT NULL DEFAULT 100,
+    grace_minutes           INTEGER     NOT NULL DEFAULT 0,
+    -- Días tras la fecha límite a partir de los cuales no se aceptan entregas
+    hard_cutoff_days        INTEGER,
+    updated_by              UUID,
+    created_at              TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at              TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT late_policies_single_scope CHECK ((lesson_id IS NULL) <> (grading_category_id IS NULL)),
+    CONSTRAINT late_policies_penalty_range CHECK (
+        penalty_percent_per_day BETWEEN 0 AND 100 AND max_penalty_percent BETWEEN 0 AND 100
+    ),
+    CONSTRAINT late_policies_grace_positive CHECK (grace_minutes >= 0),
+    CONSTRAINT late_policies_cutoff_positive CHECK (hard_cutoff_days IS NULL OR hard_cutoff_days >= 0)
+);
+
+CREATE INDEX IF NOT EXISTS idx_late_policies_course ON late_policies (course_id);
+
+CREATE TABLE IF NOT EXISTS due_date_extensions (
+    id              UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID        NOT NULL,
+    course_id       UUID        NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    lesson_id       UUID        NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+    user_id         UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    due_date        TIMESTAMPTZ NOT NULL,
+    reason          TEXT,
+    granted_by      UUID,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (lesson_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_due_date_extensions_course ON due_date_extensions (course_id);
+
+-- Penalización aplicada a la entrega de evaluación por pares (0.0 a 1.0)
+ALTER TABLE course_submissions ADD COLUMN IF NOT EXISTS late_penalty REAL NOT NULL DEFAULT 0;
@@ -0,0 +1,30 @@
+-- Resultados de aprendizaje publicados desde el CMS y su alineación con la evidencia
+-- calificada del curso. Se reemplazan completos en cada publicación.
+
+CREATE TABLE IF NOT EXISTS course_outcomes (
+    course_id       UUID         NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    outcome_id      UUID         NOT NULL,
+    organization_id UUID         NOT NULL,
+    framework_id    UUID         NOT NULL,
+    framework_name  VARCHAR(255) NOT NULL,
+    parent_id       UUID,
+    code            VARCHAR(100) NOT NULL,
+    description     TEXT         NOT NULL,
+    level           VARCHAR(50),
+    position        INTEGER      NOT NULL DEFAULT 0,
+    PRIMARY KEY (course_id, outcome_id)
+);
+
+CREATE TABLE IF NOT EXISTS course_outcome_alignments (
+    id                  UUID  PRIMARY KEY DEFAULT gen_random_uuid(),
+    course_id           UUID  NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    outcome_id          UUID  NOT NULL,
+    lesson_id           UUID,
+    block_id            TEXT,
+    question_bank_id    UUID,
+    rubric_id           UUID,
+    rubric_criterion_id UUID,
+    weight              REAL  NOT NULL DEFAULT 1
+);
+
+CREATE INDEX IF NOT EXISTS idx_course_outcome_alignments_course ON course_outcome_alignments (course_id);
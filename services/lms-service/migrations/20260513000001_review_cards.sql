@@ -0,0 +1,36 @@
+-- Repaso espaciado (SM-2) de las preguntas falladas en evaluaciones calificadas
+
+CREATE TABLE IF NOT EXISTS review_cards (
+    id               UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id  UUID        NOT NULL,
+    user_id          UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    course_id        UUID        NOT NULL,
+    lesson_id        UUID        NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+    block_id         TEXT,
+    item_id          TEXT        NOT NULL,
+    -- Instantánea de la pregunta: { question, options, correct, explanation }
+    content          JSONB       NOT NULL,
+    ease_factor      REAL        NOT NULL DEFAULT 2.5,
+    interval_days    INTEGER     NOT NULL DEFAULT 0,
+    repetitions      INTEGER     NOT NULL DEFAULT 0,
+    lapses           INTEGER     NOT NULL DEFAULT 0,
+    due_at           TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_reviewed_at TIMESTAMPTZ,
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT review_cards_unique UNIQUE (user_id, lesson_id, item_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_review_cards_due ON review_cards (user_id, due_at);
+
+CREATE TABLE IF NOT EXISTS review_log (
+    id              UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID        NOT NULL,
+    card_id         UUID        NOT NULL REFERENCES review_cards(id) ON DELETE CASCADE,
+    user_id         UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    quality         SMALLINT    NOT NULL,            -- 0 a 5 (SM-2)
+    interval_days   INTEGER     NOT NULL,            -- intervalo resultante
+    reviewed_at     TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT review_log_quality_range CHECK (quality BETWEEN 0 AND 5)
+);
+
+CREATE INDEX IF NOT EXISTS idx_review_log_user ON review_log (user_id, reviewed_at DESC);
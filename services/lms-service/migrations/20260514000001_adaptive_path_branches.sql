@@ -0,0 +1,13 @@
+-- Ramas adaptativas sobre las dependencias de lecciones.
+-- Espejo de la tabla del CMS; se reemplaza en cada publicación.
+-- prerequisite: la lección se desbloquea al aprobar la previa (min_score_percentage).
+-- remedial:     se desbloquea si la previa quedó por debajo de max_score_percentage
+--               o si el alumno le dedicó más de time_threshold_minutes.
+-- enrichment:   se desbloquea si la previa alcanzó min_score_percentage
+--               dentro de time_threshold_minutes (cuando se define).
+
+ALTER TABLE lesson_dependencies
+    ADD COLUMN branch_type VARCHAR(20) NOT NULL DEFAULT 'prerequisite'
+        CHECK (branch_type IN ('prerequisite', 'remedial', 'enrichment')),
+    ADD COLUMN max_score_percentage DOUBLE PRECISION,
+    ADD COLUMN time_threshold_minutes INTEGER CHECK (time_threshold_minutes > 0);
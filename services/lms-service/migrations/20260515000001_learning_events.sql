@@ -0,0 +1,318 @@
+-- Almacén unificado de eventos de aprendizaje (solo inserción, particionado por mes)
+-- y rollups incrementales para analítica.
+--
+-- Los eventos se capturan con triggers sobre lesson_interactions, user_grades y
+-- discussion_posts, de modo que cualquier escritor queda registrado. Los rollups
+-- se actualizan con fn_refresh_learning_rollups() a partir de la marca `seq`.
+
+CREATE TABLE IF NOT EXISTS learning_events (
+    seq                   BIGSERIAL,
+    id                    UUID             NOT NULL DEFAULT gen_random_uuid(),
+    organization_id       UUID             NOT NULL,
+    user_id               UUID             NOT NULL,
+    course_id             UUID,
+    lesson_id             UUID,
+    -- lesson.<tipo de interacción> | grade.recorded | discussion.post
+    event_type            VARCHAR(60)      NOT NULL,
+    -- Nota (0-1) en grade.recorded
+    value                 DOUBLE PRECISION,
+    -- Segundos desde el evento anterior del alumno en la lección (≤ 5 min)
+    duration_seconds      DOUBLE PRECISION NOT NULL DEFAULT 0,
+    metadata              JSONB,
+    occurred_at           TIMESTAMPTZ      NOT NULL,
+    recorded_at           TIMESTAMPTZ      NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (seq, occurred_at)
+) PARTITION BY RANGE (occurred_at);
+
+CREATE TABLE IF NOT EXISTS learning_events_default PARTITION OF learning_events DEFAULT;
+
+CREATE INDEX IF NOT EXISTS idx_learning_events_seq ON learning_events (seq);
+CREATE INDEX IF NOT EXISTS idx_learning_events_user_lesson ON learning_events (user_id, lesson_id, occurred_at DESC);
+CREATE INDEX IF NOT EXISTS idx_learning_events_course ON learning_events (course_id, occurred_at);
+
+-- Crea las particiones mensuales [p_from, p_from + p_months)
+CREATE OR REPLACE FUNCTION fn_ensure_learning_event_partitions(p_from DATE, p_months INTEGER)
+RETURNS VOID AS $$
+DECLARE
+    v_start DATE := date_trunc('month', p_from)::date;
+    v_name  TEXT;
+BEGIN
+    FOR i IN 0..GREATEST(p_months - 1, 0) LOOP
+        v_name := 'learning_events_' || to_char(v_start, 'YYYYMM');
+        IF to_regclass(v_name) IS NULL THEN
+            EXECUTE format(
+                'CREATE TABLE %I PARTITION OF learning_events FOR VALUES FROM (%L) TO (%L)',
+                v_name, v_start, (v_start + INTERVAL '1 month')::date
+            );
+        END IF;
+        v_start := (v_start + INTERVAL '1 month')::date;
+    END LOOP;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Los eventos no se modifican ni se borran
+CREATE OR REPLACE FUNCTION fn_learning_events_append_only()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'learning_events es de solo inserción';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trg_learning_events_append_only
+    BEFORE UPDATE OR DELETE ON learning_events
+    FOR EACH ROW EXECUTE FUNCTION fn_learning_events_append_only();
+
+-- ─── Captura ────────────────────────────────────────────────────────────────
+
+CREATE OR REPLACE FUNCTION fn_capture_lesson_interaction()
+RETURNS TRIGGER AS $$
+DECLARE
+    v_at       TIMESTAMPTZ := COALESCE(NEW.created_at, NOW());
+    v_course   UUID;
+    v_previous TIMESTAMPTZ;
+BEGIN
+    SELECT m.course_id INTO v_course
+    FROM lessons l JOIN modules m ON m.id = l.module_id
+    WHERE l.id = NEW.lesson_id;
+
+    SELECT occurred_at INTO v_previous
+    FROM learning_events
+    WHERE user_id = NEW.user_id AND lesson_id = NEW.lesson_id
+      AND occurred_at > v_at - INTERVAL '5 minutes' AND occurred_at <= v_at
+    ORDER BY occurred_at DESC
+    LIMIT 1;
+
+    INSERT INTO learning_events
+        (organization_id, user_id, course_id, lesson_id, event_type, duration_seconds, metadata, occurred_at)
+    VALUES (
+        NEW.organization_id, NEW.user_id, v_course, NEW.lesson_id,
+        'lesson.' || NEW.event_type,
+        COALESCE(EXTRACT(EPOCH FROM v_at - v_previous), 0),
+        jsonb_strip_nulls(jsonb_build_object('video_timestamp', NEW.video_timestamp, 'data', NEW.metadata)),
+        v_at
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trg_capture_lesson_interaction
+    AFTER INSERT ON lesson_interactions
+    FOR EACH ROW EXECUTE FUNCTION fn_capture_lesson_interaction();
+
+CREATE OR REPLACE FUNCTION fn_capture_user_grade()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'UPDATE'
+       AND NEW.score IS NOT DISTINCT FROM OLD.score
+       AND NEW.attempts_count IS NOT DISTINCT FROM OLD.attempts_count THEN
+        RETURN NEW;
+    END IF;
+
+    INSERT INTO learning_events
+        (organization_id, user_id, course_id, lesson_id, event_type, value, metadata, occurred_at)
+    VALUES (
+        NEW.organization_id, NEW.user_id, NEW.course_id, NEW.lesson_id,
+        'grade.recorded', NEW.score,
+        jsonb_build_object('attempts', NEW.attempts_count),
+        NOW()
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trg_capture_user_grade
+    AFTER INSERT OR UPDATE ON user_grades
+    FOR EACH ROW EXECUTE FUNCTION fn_capture_user_grade();
+
+CREATE OR REPLACE FUNCTION fn_capture_discussion_post()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO learning_events
+        (organization_id, user_id, course_id, lesson_id, event_type, metadata, occurred_at)
+    SELECT NEW.organization_id, NEW.author_id, t.course_id, t.lesson_id,
+           'discussion.post', jsonb_build_object('thread_id', NEW.thread_id), NEW.created_at
+    FROM discussion_threads t
+    WHERE t.id = NEW.thread_id;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trg_capture_discussion_post
+    AFTER INSERT ON discussion_posts
+    FOR EACH ROW EXECUTE FUNCTION fn_capture_discussion_post();
+
+-- ─── Rollups ────────────────────────────────────────────────────────────────
+
+CREATE TABLE IF NOT EXISTS la_daily_learner_activity (
+    course_id            UUID             NOT NULL,
+    user_id              UUID             NOT NULL,
+    day                  DATE             NOT NULL,
+    organization_id      UUID             NOT NULL,
+    events               INTEGER          NOT NULL DEFAULT 0,
+    posts                INTEGER          NOT NULL DEFAULT 0,
+    time_on_task_seconds DOUBLE PRECISION NOT NULL DEFAULT 0,
+    PRIMARY KEY (course_id, user_id, day)
+);
+
+CREATE INDEX IF NOT EXISTS idx_la_daily_course_day ON la_daily_learner_activity (course_id, day);
+
+CREATE TABLE IF NOT EXISTS la_lesson_progress (
+    lesson_id            UUID             NOT NULL,
+    user_id              UUID             NOT NULL,
+    course_id            UUID             NOT NULL,
+    organization_id      UUID             NOT NULL,
+    first_seen_at        TIMESTAMPTZ      NOT NULL,
+    last_seen_at         TIMESTAMPTZ      NOT NULL,
+    events               INTEGER          NOT NULL DEFAULT 0,
+    time_on_task_seconds DOUBLE PRECISION NOT NULL DEFAULT 0,
+    attempts             INTEGER,
+    last_score           DOUBLE PRECISION,
+    best_score           DOUBLE PRECISION,
+    completed            BOOLEAN          NOT NULL DEFAULT false,
+    PRIMARY KEY (lesson_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_la_lesson_progress_course ON la_lesson_progress (course_id, user_id);
+
+CREATE TABLE IF NOT EXISTS la_video_heatmap (
+    lesson_id UUID    NOT NULL,
+    second    INTEGER NOT NULL,
+    views     BIGINT  NOT NULL DEFAULT 0,
+    PRIMARY KEY (lesson_id, second)
+);
+
+CREATE TABLE IF NOT EXISTS la_rollup_state (
+    name         VARCHAR(50) PRIMARY KEY,
+    last_seq     BIGINT      NOT NULL DEFAULT 0,
+    refreshed_at TIMESTAMPTZ
+);
+
+INSERT INTO la_rollup_state (name) VALUES ('learning_events') ON CONFLICT DO NOTHING;
+
+-- Aplica a los rollups los eventos nuevos desde la última marca. Solo considera
+-- eventos registrados hace más de 30 s para no saltarse secuencias de transacciones
+-- aún abiertas. Devuelve la cantidad de eventos procesados.
+CREATE OR REPLACE FUNCTION fn_refresh_learning_rollups(p_batch_size INTEGER DEFAULT 50000)
+RETURNS INTEGER AS $$
+DECLARE
+    v_from      BIGINT;
+    v_to        BIGINT;
+    v_processed INTEGER;
+BEGIN
+    SELECT last_seq INTO v_from FROM la_rollup_state WHERE name = 'learning_events' FOR UPDATE;
+
+    SELECT MAX(seq) INTO v_to
+    FROM (
+        SELECT seq FROM learning_events
+        WHERE seq > v_from AND recorded_at < NOW() - INTERVAL '30 seconds'
+        ORDER BY seq
+        LIMIT p_batch_size
+    ) batch;
+
+    IF v_to IS NULL THEN
+        UPDATE la_rollup_state SET refreshed_at = NOW() WHERE name = 'learning_events';
+        RETURN 0;
+    END IF;
+
+    CREATE TEMP TABLE tmp_learning_batch ON COMMIT DROP AS
+        SELECT * FROM learning_events WHERE seq > v_from AND seq <= v_to;
+    GET DIAGNOSTICS v_processed = ROW_COUNT;
+
+    INSERT INTO la_daily_learner_activity
+        (course_id, user_id, day, organization_id, events, posts, time_on_task_seconds)
+    SELECT course_id, user_id, (occurred_at AT TIME ZONE 'UTC')::date, MIN(organization_id::text)::uuid,
+           COUNT(*), COUNT(*) FILTER (WHERE event_type = 'discussion.post'), SUM(duration_seconds)
+    FROM tmp_learning_batch
+    WHERE course_id IS NOT NULL
+    GROUP BY course_id, user_id, (occurred_at AT TIME ZONE 'UTC')::date
+    ON CONFLICT (course_id, user_id, day) DO UPDATE SET
+        events = la_daily_learner_activity.events + EXCLUDED.events,
+        posts = la_daily_learner_activity.posts + EXCLUDED.posts,
+        time_on_task_seconds = la_daily_learner_activity.time_on_task_seconds + EXCLUDED.time_on_task_seconds;
+
+    INSERT INTO la_lesson_progress
+        (lesson_id, user_id, course_id, organization_id, first_seen_at, last_seen_at, events,
+         time_on_task_seconds, attempts, last_score, best_score, completed)
+    SELECT b.lesson_id, b.user_id, MIN(b.course_id::text)::uuid, MIN(b.organization_id::text)::uuid,
+           MIN(b.occurred_at), MAX(b.occurred_at), COUNT(*), SUM(b.duration_seconds),
+           MAX((b.metadata->>'attempts')::int) FILTER (WHERE b.event_type = 'grade.recorded'),
+           (ARRAY_AGG(b.value ORDER BY b.seq DESC) FILTER (WHERE b.event_type = 'grade.recorded'))[1],
+           MAX(b.value) FILTER (WHERE b.event_type = 'grade.recorded'),
+           BOOL_OR(
+               b.event_type = 'lesson.complete'
+               OR (b.event_type = 'grade.recorded' AND b.value * 100.0 >= COALESCE(c.passing_percentage, 70))
+           )
+    FROM tmp_learning_batch b
+    LEFT JOIN courses c ON c.id = b.course_id
+    WHERE b.lesson_id IS NOT NULL AND b.course_id IS NOT NULL
+    GROUP BY b.lesson_id, b.user_id
+    ON CONFLICT (lesson_id, user_id) DO UPDATE SET
+        first_seen_at = LEAST(la_lesson_progress.first_seen_at, EXCLUDED.first_seen_at),
+        last_seen_at = GREATEST(la_lesson_progress.last_seen_at, EXCLUDED.last_seen_at),
+        events = la_lesson_progress.events + EXCLUDED.events,
+        time_on_task_seconds = la_lesson_progress.time_on_task_seconds + EXCLUDED.time_on_task_seconds,
+        attempts = GREATEST(la_lesson_progress.attempts, EXCLUDED.attempts),
+        last_score = COALESCE(EXCLUDED.last_score, la_lesson_progress.last_score),
+        best_score = GREATEST(la_lesson_progress.best_score, EXCLUDED.best_score),
+        completed = la_lesson_progress.completed OR EXCLUDED.completed;
+
+    INSERT INTO la_video_heatmap (lesson_id, second, views)
+    SELECT lesson_id, floor((metadata->>'video_timestamp')::float8)::int, COUNT(*)
+    FROM tmp_learning_batch
+    WHERE lesson_id IS NOT NULL AND metadata ? 'video_timestamp'
+    GROUP BY 1, 2
+    ON CONFLICT (lesson_id, second) DO UPDATE SET views = la_video_heatmap.views + EXCLUDED.views;
+
+    UPDATE la_rollup_state SET last_seq = v_to, refreshed_at = NOW() WHERE name = 'learning_events';
+    DROP TABLE tmp_learning_batch;
+    RETURN v_processed;
+END;
+$$ LANGUAGE plpgsql;
+
+-- ─── Carga inicial desde las tablas de origen ───────────────────────────────
+
+DO $$
+DECLARE
+    v_first DATE := LEAST(
+        COALESCE((SELECT MIN(created_at) FROM lesson_interactions), NOW()),
+        COALESCE((SELECT MIN(updated_at) FROM user_grades), NOW()),
+        COALESCE((SELECT MIN(created_at) FROM discussion_posts), NOW())
+    )::date;
+BEGIN
+    -- Desde el primer evento hasta el mes siguiente al actual
+    PERFORM fn_ensure_learning_event_partitions(
+        v_first,
+        ((EXTRACT(YEAR FROM NOW()) - EXTRACT(YEAR FROM v_first)) * 12
+            + EXTRACT(MONTH FROM NOW()) - EXTRACT(MONTH FROM v_first))::int + 2
+    );
+END $$;
+
+INSERT INTO learning_events
+    (organization_id, user_id, course_id, lesson_id, event_type, duration_seconds, metadata, occurred_at, recorded_at)
+SELECT li.organization_id, li.user_id, m.course_id, li.lesson_id, 'lesson.' || li.event_type,
+       CASE WHEN li.at - li.previous <= INTERVAL '5 minutes' THEN EXTRACT(EPOCH FROM li.at - li.previous) ELSE 0 END,
+       jsonb_strip_nulls(jsonb_build_object('video_timestamp', li.video_timestamp, 'data', li.metadata)),
+       li.at, li.at
+FROM (
+    SELECT *, COALESCE(created_at, NOW()) AS at,
+           LAG(COALESCE(created_at, NOW())) OVER (PARTITION BY user_id, lesson_id ORDER BY created_at) AS previous
+    FROM lesson_interactions
+) li
+LEFT JOIN lessons l ON l.id = li.lesson_id
+LEFT JOIN modules m ON m.id = l.module_id
+ORDER BY li.at;
+
+INSERT INTO learning_events
+    (organization_id, user_id, course_id, lesson_id, event_type, value, metadata, occurred_at, recorded_at)
+SELECT organization_id, user_id, course_id, lesson_id, 'grade.recorded', score,
+       jsonb_build_object('attempts', attempts_count), updated_at, updated_at
+FROM user_grades
+ORDER BY updated_at;
+
+INSERT INTO learning_events
+    (organization_id, user_id, course_id, lesson_id, event_type, metadata, occurred_at, recorded_at)
+SELECT p.organization_id, p.author_id, t.course_id, t.lesson_id, 'discussion.post',
+       jsonb_build_object('thread_id', p.thread_id), p.created_at, p.created_at
+FROM discussion_posts p
+JOIN discussion_threads t ON t.id = p.thread_id
+ORDER BY p.created_at;
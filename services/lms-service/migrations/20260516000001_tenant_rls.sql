@@ -0,0 +1,86 @@
+-- Aislamiento de organizaciones con row-level security.
+--
+-- Las peticiones autenticadas usan el rol openccb_tenant con app.current_org_id
+-- fijado por common::tenancy; solo ven y escriben filas de su organización, más las
+-- globales (organization_id NULL) en las tablas donde la columna es opcional. El
+-- superadministrador usa openccb_platform_admin, que tiene una política de bypass.
+-- Los procesos internos (migraciones, jobs, ingesta) usan el rol propietario, al que
+-- RLS no aplica porque las tablas no usan FORCE.
+--
+-- Las tablas nuevas con organization_id deben llamar a fn_enable_tenant_rls().
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_roles WHERE rolname = 'openccb_tenant') THEN
+        CREATE ROLE openccb_tenant NOLOGIN;
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_roles WHERE rolname = 'openccb_platform_admin') THEN
+        CREATE ROLE openccb_platform_admin NOLOGIN;
+    END IF;
+END $$;
+
+GRANT openccb_tenant TO openccb_platform_admin;
+GRANT openccb_tenant, openccb_platform_admin TO CURRENT_USER;
+
+GRANT USAGE ON SCHEMA public TO openccb_tenant;
+GRANT SELECT, INSERT, UPDATE, DELETE ON ALL TABLES IN SCHEMA public TO openccb_tenant;
+GRANT USAGE, SELECT, UPDATE ON ALL SEQUENCES IN SCHEMA public TO openccb_tenant;
+GRANT EXECUTE ON ALL FUNCTIONS IN SCHEMA public TO openccb_tenant;
+ALTER DEFAULT PRIVILEGES IN SCHEMA public GRANT SELECT, INSERT, UPDATE, DELETE ON TABLES TO openccb_tenant;
+ALTER DEFAULT PRIVILEGES IN SCHEMA public GRANT USAGE, SELECT, UPDATE ON SEQUENCES TO openccb_tenant;
+
+CREATE OR REPLACE FUNCTION fn_enable_tenant_rls(p_table REGCLASS)
+RETURNS VOID AS $$
+DECLARE
+    v_nullable BOOLEAN;
+    v_check    TEXT := 'organization_id = NULLIF(current_setting(''app.current_org_id'', true), '''')::uuid';
+BEGIN
+    SELECT NOT a.attnotnull INTO v_nullable
+    FROM pg_attribute a
+    WHERE a.attrelid = p_table AND a.attname = 'organization_id' AND NOT a.attisdropped;
+
+    IF v_nullable IS NULL THEN
+        RAISE EXCEPTION 'La tabla % no tiene organization_id', p_table;
+    END IF;
+    IF v_nullable THEN
+        v_check := '(' || v_check || ' OR organization_id IS NULL)';
+    END IF;
+
+    EXECUTE format('ALTER TABLE %s ENABLE ROW LEVEL SECURITY', p_table);
+    EXECUTE format('DROP POLICY IF EXISTS tenant_isolation ON %s', p_table);
+    EXECUTE format('DROP POLICY IF EXISTS platform_admin_bypass ON %s', p_table);
+    EXECUTE format('CREATE POLICY tenant_isolation ON %s TO openccb_tenant USING (%s) WITH CHECK (%s)',
+                   p_table, v_check, v_check);
+    EXECUTE format('CREATE POLICY platform_admin_bypass ON %s TO openccb_platform_admin USING (true) WITH CHECK (true)',
+                   p_table);
+END;
+$$ LANGUAGE plpgsql;
+
+-- Todas las tablas (no particiones) con organization_id UUID
+DO $$
+DECLARE
+    r RECORD;
+BEGIN
+    FOR r IN
+        SELECT c.oid::regclass AS tbl
+        FROM pg_class c
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        JOIN pg_attribute a ON a.attrelid = c.oid AND a.attname = 'organization_id' AND NOT a.attisdropped
+        WHERE n.nspname = 'public'
+          AND c.relkind IN ('r', 'p')
+          AND NOT c.relispartition
+          AND a.atttypid = 'uuid'::regtype
+    LOOP
+        PERFORM fn_enable_tenant_rls(r.tbl);
+    END LOOP;
+END $$;
+
+-- La organización propia es la única visible
+ALTER TABLE organizations ENABLE ROW LEVEL SECURITY;
+DROP POLICY IF EXISTS tenant_isolation ON organizations;
+DROP POLICY IF EXISTS platform_admin_bypass ON organizations;
+CREATE POLICY tenant_isolation ON organizations TO openccb_tenant
+    USING (id = NULLIF(current_setting('app.current_org_id', true), '')::uuid)
+    WITH CHECK (id = NULLIF(current_setting('app.current_org_id', true), '')::uuid);
+CREATE POLICY platform_admin_bypass ON organizations TO openccb_platform_admin
+    USING (true) WITH CHECK (true);
@@ -0,0 +1,44 @@
+-- Solicitudes de titulares de datos (GDPR/LGPD): exportación y supresión.
+--
+-- Ciclo de vida: awaiting_approval -> pending -> processing -> completed | failed,
+-- o rejected. Cada transición queda en dsar_request_events.
+
+CREATE TABLE IF NOT EXISTS dsar_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL,
+    request_type TEXT NOT NULL CHECK (request_type IN ('export', 'erasure')),
+    status TEXT NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('awaiting_approval', 'pending', 'processing', 'completed', 'failed', 'rejected')),
+    requested_by UUID NOT NULL,
+    reason TEXT,
+    result JSONB NOT NULL DEFAULT '{}'::jsonb,
+    archive_path TEXT,
+    archive_sha256 TEXT,
+    error TEXT,
+    expires_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ
+);
+
+-- Sin FK a users: la solicitud debe sobrevivir a la supresión del titular
+CREATE INDEX IF NOT EXISTS idx_dsar_requests_queue ON dsar_requests(status, created_at);
+CREATE INDEX IF NOT EXISTS idx_dsar_requests_user ON dsar_requests(organization_id, user_id);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_dsar_requests_open
+    ON dsar_requests(user_id, request_type)
+    WHERE status IN ('awaiting_approval', 'pending', 'processing');
+
+CREATE TABLE IF NOT EXISTS dsar_request_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    request_id UUID NOT NULL REFERENCES dsar_requests(id) ON DELETE CASCADE,
+    organization_id UUID NOT NULL,
+    status TEXT NOT NULL,
+    actor_id UUID,
+    detail TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS idx_dsar_request_events_request ON dsar_request_events(request_id, created_at);
+
+SELECT fn_enable_tenant_rls('dsar_requests');
+SELECT fn_enable_tenant_rls('dsar_request_events');
@@ -0,0 +1,40 @@
+-- Host de plugins WebAssembly (lms-service mirror de org_plugins + estado de ejecución)
+
+ALTER TABLE org_plugins
+    ADD COLUMN IF NOT EXISTS kind TEXT NOT NULL DEFAULT 'component',
+    ADD COLUMN IF NOT EXISTS wasm_module BYTEA,
+    ADD COLUMN IF NOT EXISTS wasm_sha256 TEXT,
+    ADD COLUMN IF NOT EXISTS events TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS block_types TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS fuel_limit BIGINT NOT NULL DEFAULT 50000000,
+    ADD COLUMN IF NOT EXISTS memory_limit_mb INTEGER NOT NULL DEFAULT 32;
+
+CREATE INDEX IF NOT EXISTS idx_org_plugins_wasm_events ON org_plugins USING GIN(events) WHERE kind = 'wasm';
+CREATE INDEX IF NOT EXISTS idx_org_plugins_wasm_blocks ON org_plugins USING GIN(block_types) WHERE kind = 'wasm';
+
+-- Almacén clave-valor con espacio de nombres por plugin
+CREATE TABLE IF NOT EXISTS plugin_kv (
+    plugin_id UUID NOT NULL REFERENCES org_plugins(id) ON DELETE CASCADE,
+    organization_id UUID NOT NULL,
+    key TEXT NOT NULL,
+    value JSONB NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (plugin_id, key)
+);
+
+-- Registro de ejecuciones (eventos y calificaciones)
+CREATE TABLE IF NOT EXISTS plugin_invocations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    plugin_id UUID NOT NULL REFERENCES org_plugins(id) ON DELETE CASCADE,
+    organization_id UUID NOT NULL,
+    hook TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('ok', 'error', 'out_of_fuel')),
+    fuel_consumed BIGINT NOT NULL DEFAULT 0,
+    duration_ms INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS idx_plugin_invocations_plugin ON plugin_invocations(plugin_id, created_at DESC);
+
+SELECT fn_enable_tenant_rls('plugin_kv');
+SELECT fn_enable_tenant_rls('plugin_invocations');
@@ -0,0 +1,6 @@
+-- Permisos declarados en el manifiesto del plugin (lms-service mirror); el LMS los
+-- usa para emitir tokens de plugin con alcance limitado.
+
+ALTER TABLE org_plugins
+    ADD COLUMN IF NOT EXISTS version TEXT,
+    ADD COLUMN IF NOT EXISTS permissions TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,36 @@
+-- Ejecución de bloques code-lab: casos de prueba ocultos y registro de ejecuciones.
+--
+-- Los casos ocultos llegan en el bloque (`hidden_test_cases`) y la ingesta los
+-- separa aquí para que nunca se sirvan al estudiante; se recrean al republicar.
+
+CREATE TABLE IF NOT EXISTS code_lab_tests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL,
+    lesson_id UUID NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+    block_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    input TEXT NOT NULL DEFAULT '',
+    expected_output TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_code_lab_tests_block ON code_lab_tests(lesson_id, block_id, position);
+
+-- Sin FK a lessons: las ejecuciones sobreviven a la republicación del curso
+CREATE TABLE IF NOT EXISTS code_lab_runs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    lesson_id UUID NOT NULL,
+    block_id TEXT NOT NULL,
+    language TEXT NOT NULL,
+    code TEXT NOT NULL,
+    passed INTEGER NOT NULL,
+    total INTEGER NOT NULL,
+    score DOUBLE PRECISION NOT NULL,
+    results JSONB NOT NULL DEFAULT '[]'::jsonb,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS idx_code_lab_runs_user ON code_lab_runs(user_id, lesson_id, block_id, created_at DESC);
+
+SELECT fn_enable_tenant_rls('code_lab_tests');
+SELECT fn_enable_tenant_rls('code_lab_runs');
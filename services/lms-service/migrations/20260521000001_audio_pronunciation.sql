@@ -0,0 +1,7 @@
+-- Evaluación de pronunciación por palabra de las respuestas de audio.
+--
+-- Se calcula alineando las marcas de tiempo de Whisper con el texto de
+-- referencia del bloque (`expected_text`): precisión por palabra, fluidez
+-- (velocidad y pausas) y completitud.
+
+ALTER TABLE audio_responses ADD COLUMN IF NOT EXISTS pronunciation JSONB;
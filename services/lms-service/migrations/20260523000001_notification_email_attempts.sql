@@ -0,0 +1,4 @@
+-- Intentos de envío del email inmediato. El envío reclama la fila marcando
+-- `emailed_at` y la devuelve a la cola si falla, hasta un máximo de intentos.
+ALTER TABLE notifications
+    ADD COLUMN IF NOT EXISTS email_attempts INTEGER NOT NULL DEFAULT 0;
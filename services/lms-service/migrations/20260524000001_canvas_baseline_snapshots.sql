@@ -0,0 +1,12 @@
+-- Checkpoint inicial de los canvas creados antes del log de operaciones
+--
+-- Esos canvas tienen su contenido en canvas_state con compacted_seq = 0 y ningún
+-- checkpoint, así que la reproducción partía de un canvas vacío. El checkpoint en
+-- seq 0 conserva ese contenido como punto de partida del historial.
+INSERT INTO lesson_canvas_snapshots (lesson_id, organization_id, seq, canvas_state, created_at)
+SELECT c.lesson_id, c.organization_id, 0, c.canvas_state, c.created_at
+FROM lesson_collaborative_canvases c
+WHERE c.compacted_seq = 0
+  AND c.canvas_state <> '{}'::jsonb
+  AND NOT EXISTS (SELECT 1 FROM lesson_canvas_snapshots s WHERE s.lesson_id = c.lesson_id)
+ON CONFLICT (lesson_id, seq) DO NOTHING;
@@ -0,0 +1,43 @@
+-- El tópico 'canvas:' solo avisa de reemplazos completos del canvas
+--
+-- append_op sube revision (el PUT la usa para detectar ediciones concurrentes), por
+-- lo que el trigger emitía 'canvas:' en cada trazo y los streams de snapshot volvían
+-- a enviar el canvas completo por cada operación.
+CREATE OR REPLACE FUNCTION fn_notify_realtime_event() RETURNS trigger AS $$
+DECLARE
+    v_topic TEXT;
+    v_data  JSONB;
+BEGIN
+    IF TG_TABLE_NAME = 'notifications' THEN
+        v_topic := 'notifications:' || NEW.user_id;
+        v_data := jsonb_build_object('id', NEW.id);
+    ELSIF TG_TABLE_NAME = 'lesson_collaborative_docs' THEN
+        IF TG_OP = 'UPDATE' AND OLD.revision = NEW.revision THEN
+            RETURN NEW;
+        END IF;
+        v_topic := 'doc:' || NEW.lesson_id;
+        v_data := jsonb_build_object('revision', NEW.revision);
+    ELSIF TG_TABLE_NAME = 'lesson_collaborative_canvases' THEN
+        -- La compactación no cambia la revisión, y cada operación del log sube la
+        -- revisión sin reemplazar el snapshot (compacted_seq igual): esas ya se
+        -- difunden en 'canvas-ops:'. Solo los reemplazos completos generan evento.
+        IF TG_OP = 'UPDATE'
+           AND (OLD.revision = NEW.revision OR OLD.compacted_seq = NEW.compacted_seq) THEN
+            RETURN NEW;
+        END IF;
+        v_topic := 'canvas:' || NEW.lesson_id;
+        v_data := jsonb_build_object('revision', NEW.revision);
+    ELSIF TG_TABLE_NAME = 'lesson_canvas_ops' THEN
+        v_topic := 'canvas-ops:' || NEW.lesson_id;
+        v_data := jsonb_build_object('seq', NEW.seq);
+    ELSE
+        RETURN NEW;
+    END IF;
+
+    PERFORM pg_notify(
+        'openccb_events',
+        jsonb_build_object('topic', v_topic, 'data', v_data)::text
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,157 @@
+-- Marca de los rollups por transacción y particiones con datos en DEFAULT
+--
+-- La marca `last_seq` con `recorded_at < NOW() - 30 s` se saltaba para siempre los
+-- eventos de transacciones de más de 30 s: su `seq` es menor que el de eventos
+-- confirmados antes. Ahora cada evento guarda el id de su transacción y los rollups
+-- avanzan por (xact_id, seq) hasta el horizonte `xmin` de la instantánea: toda
+-- transacción con id menor ya terminó, y las que sigan abiertas tendrán un id mayor
+-- que la marca cuando se confirmen.
+
+ALTER TABLE learning_events
+    ADD COLUMN IF NOT EXISTS xact_id xid8 NOT NULL DEFAULT pg_current_xact_id();
+
+CREATE INDEX IF NOT EXISTS idx_learning_events_xact ON learning_events (xact_id, seq);
+
+-- Los eventos existentes quedan con el id de esta transacción: se conserva la marca
+-- por seq para no volver a aplicar los ya procesados
+ALTER TABLE la_rollup_state
+    ADD COLUMN IF NOT EXISTS last_xact_id xid8 NOT NULL DEFAULT '0';
+
+UPDATE la_rollup_state SET last_xact_id = pg_current_xact_id() WHERE name = 'learning_events';
+
+CREATE OR REPLACE FUNCTION fn_refresh_learning_rollups(p_batch_size INTEGER DEFAULT 50000)
+RETURNS INTEGER AS $$
+DECLARE
+    v_from_xact xid8;
+    v_from_seq  BIGINT;
+    v_horizon   xid8 := pg_snapshot_xmin(pg_current_snapshot());
+    v_last      RECORD;
+    v_processed INTEGER;
+BEGIN
+    SELECT last_xact_id, last_seq INTO v_from_xact, v_from_seq
+    FROM la_rollup_state WHERE name = 'learning_events' FOR UPDATE;
+
+    CREATE TEMP TABLE tmp_learning_batch ON COMMIT DROP AS
+        SELECT * FROM learning_events
+        WHERE (xact_id, seq) > (v_from_xact, v_from_seq)
+          AND xact_id < v_horizon
+        ORDER BY xact_id, seq
+        LIMIT p_batch_size;
+    GET DIAGNOSTICS v_processed = ROW_COUNT;
+
+    IF v_processed = 0 THEN
+        DROP TABLE tmp_learning_batch;
+        UPDATE la_rollup_state SET refreshed_at = NOW() WHERE name = 'learning_events';
+        RETURN 0;
+    END IF;
+
+    SELECT xact_id, seq INTO v_last FROM tmp_learning_batch ORDER BY xact_id DESC, seq DESC LIMIT 1;
+
+    INSERT INTO la_daily_learner_activity
+        (course_id, user_id, day, organization_id, events, posts, time_on_task_seconds)
+    SELECT course_id, user_id, (occurred_at AT TIME ZONE 'UTC')::date, MIN(organization_id::text)::uuid,
+           COUNT(*), COUNT(*) FILTER (WHERE event_type = 'discussion.post'), SUM(duration_seconds)
+    FROM tmp_learning_batch
+    WHERE course_id IS NOT NULL
+    GROUP BY course_id, user_id, (occurred_at AT TIME ZONE 'UTC')::date
+    ON CONFLICT (course_id, user_id, day) DO UPDATE SET
+        events = la_daily_learner_activity.events + EXCLUDED.events,
+        posts = la_daily_learner_activity.posts + EXCLUDED.posts,
+        time_on_task_seconds = la_daily_learner_activity.time_on_task_seconds + EXCLUDED.time_on_task_seconds;
+
+    INSERT INTO la_lesson_progress
+        (lesson_id, user_id, course_id, organization_id, first_seen_at, last_seen_at, events,
+         time_on_task_seconds, attempts, last_score, best_score, completed)
+    SELECT b.lesson_id, b.user_id, MIN(b.course_id::text)::uuid, MIN(b.organization_id::text)::uuid,
+           MIN(b.occurred_at), MAX(b.occurred_at), COUNT(*), SUM(b.duration_seconds),
+           MAX((b.metadata->>'attempts')::int) FILTER (WHERE b.event_type = 'grade.recorded'),
+           (ARRAY_AGG(b.value ORDER BY b.seq DESC) FILTER (WHERE b.event_type = 'grade.recorded'))[1],
+           MAX(b.value) FILTER (WHERE b.event_type = 'grade.recorded'),
+           BOOL_OR(
+               b.event_type = 'lesson.complete'
+               OR (b.event_type = 'grade.recorded' AND b.value * 100.0 >= COALESCE(c.passing_percentage, 70))
+           )
+    FROM tmp_learning_batch b
+    LEFT JOIN courses c ON c.id = b.course_id
+    WHERE b.lesson_id IS NOT NULL AND b.course_id IS NOT NULL
+    GROUP BY b.lesson_id, b.user_id
+    ON CONFLICT (lesson_id, user_id) DO UPDATE SET
+        first_seen_at = LEAST(la_lesson_progress.first_seen_at, EXCLUDED.first_seen_at),
+        last_seen_at = GREATEST(la_lesson_progress.last_seen_at, EXCLUDED.last_seen_at),
+        events = la_lesson_progress.events + EXCLUDED.events,
+        time_on_task_seconds = la_lesson_progress.time_on_task_seconds + EXCLUDED.time_on_task_seconds,
+        attempts = GREATEST(la_lesson_progress.attempts, EXCLUDED.attempts),
+        last_score = COALESCE(EXCLUDED.last_score, la_lesson_progress.last_score),
+        best_score = GREATEST(la_lesson_progress.best_score, EXCLUDED.best_score),
+        completed = la_lesson_progress.completed OR EXCLUDED.completed;
+
+    INSERT INTO la_video_heatmap (lesson_id, second, views)
+    SELECT lesson_id, floor((metadata->>'video_timestamp')::float8)::int, COUNT(*)
+    FROM tmp_learning_batch
+    WHERE lesson_id IS NOT NULL AND metadata ? 'video_timestamp'
+    GROUP BY 1, 2
+    ON CONFLICT (lesson_id, second) DO UPDATE SET views = la_video_heatmap.views + EXCLUDED.views;
+
+    UPDATE la_rollup_state
+    SET last_xact_id = v_last.xact_id, last_seq = v_last.seq, refreshed_at = NOW()
+    WHERE name = 'learning_events';
+    DROP TABLE tmp_learning_batch;
+    RETURN v_processed;
+END;
+$$ LANGUAGE plpgsql;
+
+-- El trigger de solo inserción admite mover filas de la partición DEFAULT a su
+-- partición mensual, y solo desde fn_ensure_learning_event_partitions
+CREATE OR REPLACE FUNCTION fn_learning_events_append_only()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE'
+       AND TG_TABLE_NAME = 'learning_events_default'
+       AND current_setting('openccb.learning_events_repartition', true) = 'on'
+       AND current_user NOT IN ('openccb_tenant', 'openccb_platform_admin') THEN
+        RETURN OLD;
+    END IF;
+    RAISE EXCEPTION 'learning_events es de solo inserción';
+END;
+$$ LANGUAGE plpgsql;
+
+-- Crear una partición cuyo rango ya tiene filas en DEFAULT fallaba. Esas filas se
+-- copian a la tabla nueva, se borran de DEFAULT y luego se adjunta la partición.
+CREATE OR REPLACE FUNCTION fn_ensure_learning_event_partitions(p_from DATE, p_months INTEGER)
+RETURNS VOID AS $$
+DECLARE
+    v_start DATE := date_trunc('month', p_from)::date;
+    v_end   DATE;
+    v_name  TEXT;
+BEGIN
+    FOR i IN 0..GREATEST(p_months - 1, 0) LOOP
+        v_name := 'learning_events_' || to_char(v_start, 'YYYYMM');
+        v_end := (v_start + INTERVAL '1 month')::date;
+        IF to_regclass(v_name) IS NULL THEN
+            IF EXISTS (
+                SELECT 1 FROM learning_events_default
+                WHERE occurred_at >= v_start AND occurred_at < v_end
+            ) THEN
+                EXECUTE format('CREATE TABLE %I (LIKE learning_events INCLUDING DEFAULTS)', v_name);
+                EXECUTE format(
+                    'INSERT INTO %I SELECT * FROM learning_events_default WHERE occurred_at >= %L AND occurred_at < %L',
+                    v_name, v_start, v_end
+                );
+                PERFORM set_config('openccb.learning_events_repartition', 'on', true);
+                DELETE FROM learning_events_default WHERE occurred_at >= v_start AND occurred_at < v_end;
+                PERFORM set_config('openccb.learning_events_repartition', 'off', true);
+                EXECUTE format(
+                    'ALTER TABLE learning_events ATTACH PARTITION %I FOR VALUES FROM (%L) TO (%L)',
+                    v_name, v_start, v_end
+                );
+            ELSE
+                EXECUTE format(
+                    'CREATE TABLE %I PARTITION OF learning_events FOR VALUES FROM (%L) TO (%L)',
+                    v_name, v_start, v_end
+                );
+            END IF;
+        END IF;
+        v_start := v_end;
+    END LOOP;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,7 @@
+-- Entrega explícita de ejecuciones de code-lab
+--
+-- Ejecutar el código ya no califica: cada ejecución contaba como intento, sumaba XP
+-- y reemplazaba el puntaje del bloque por el último resultado. El alumno entrega una
+-- ejecución concreta, y cada ejecución solo puede entregarse una vez.
+
+ALTER TABLE code_lab_runs ADD COLUMN IF NOT EXISTS submitted_at TIMESTAMPTZ;
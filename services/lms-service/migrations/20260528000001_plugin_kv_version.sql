@@ -0,0 +1,3 @@
+-- Versión del almacén clave-valor de cada plugin: las ejecuciones leen una copia sin
+-- bloquear y solo guardan sus cambios si nadie escribió entretanto
+ALTER TABLE org_plugins ADD COLUMN IF NOT EXISTS kv_version BIGINT NOT NULL DEFAULT 0;
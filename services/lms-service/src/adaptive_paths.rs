@@ -0,0 +1,557 @@
+/// Rutas de aprendizaje adaptativas y deterministas.
+///
+/// Combina el grafo de `lesson_dependencies` (prerrequisitos y ramas `remedial` /
+/// `enrichment`), las notas de `user_grades` y el tiempo dedicado a cada lección
+/// (intervalos entre interacciones de `lesson_interactions` de hasta 5 minutos).
+///
+/// - Una lección troncal se bloquea mientras algún prerrequisito no alcance su
+///   `min_score_percentage` (o no esté completado si no se califica).
+/// - Una rama remedial se abre si la lección previa quedó por debajo de
+///   `max_score_percentage` (70 % por defecto) o llevó más de `time_threshold_minutes`.
+/// - Una rama de profundización se abre si la previa alcanzó `min_score_percentage`
+///   (90 % por defecto) dentro de `time_threshold_minutes`, cuando se define.
+/// - Si la lección previa no se califica, la rama se decide al completarla y solo por
+///   tiempo: la remedial se abre si superó el umbral y la de profundización si no.
+///
+/// La siguiente lección recomendada prioriza las remediales abiertas, luego la primera
+/// lección troncal disponible en el orden del curso y por último las de profundización.
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use common::{auth::Claims, middleware::Org};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+const DEFAULT_REMEDIAL_BELOW: f64 = 70.0;
+const DEFAULT_ENRICHMENT_FROM: f64 = 90.0;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct PathLesson {
+    pub id: Uuid,
+    pub title: String,
+    pub is_graded: bool,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct PathEdge {
+    pub lesson_id: Uuid,
+    pub prerequisite_lesson_id: Uuid,
+    pub branch_type: String,
+    pub min_score_percentage: Option<f64>,
+    pub max_score_percentage: Option<f64>,
+    pub time_threshold_minutes: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LessonProgress {
+    /// Nota de la última entrega en porcentaje (0-100), la que guarda `user_grades`
+    pub score: Option<f64>,
+    /// Marcada como completada (lecciones no calificadas)
+    pub completed_event: bool,
+    pub minutes: f64,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PathStep {
+    pub lesson_id: Uuid,
+    pub title: String,
+    /// `core`, `remedial` o `enrichment`
+    pub kind: &'static str,
+    /// `completed`, `available`, `locked` o `not_needed`
+    pub status: &'static str,
+    pub score: Option<f64>,
+    pub minutes_spent: f64,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdaptivePath {
+    pub course_id: Uuid,
+    pub user_id: Uuid,
+    pub passing_percentage: f64,
+    pub steps: Vec<PathStep>,
+    pub next_lesson_id: Option<Uuid>,
+    pub next_reason: Option<String>,
+}
+
+fn is_completed(lesson: &PathLesson, progress: &LessonProgress, passing: f64) -> bool {
+    if lesson.is_graded {
+        progress.score.is_some_and(|s| s >= passing)
+    } else {
+        progress.completed_event
+    }
+}
+
+/// La lección previa ya permite decidir sus ramas: tiene nota o, si no se califica,
+/// fue completada.
+fn branch_decided(graded: bool, prerequisite: &LessonProgress) -> bool {
+    prerequisite.score.is_some() || (!graded && prerequisite.completed_event)
+}
+
+/// Indica si la rama `edge` está abierta dado el avance en su lección previa.
+fn branch_open(edge: &PathEdge, graded: bool, prerequisite: &LessonProgress) -> bool {
+    if !branch_decided(graded, prerequisite) {
+        return false;
+    }
+    let within_time = |limit: Option<i32>| limit.is_none_or(|m| prerequisite.minutes <= m as f64);
+    match (edge.branch_type.as_str(), prerequisite.score) {
+        ("remedial", Some(score)) => {
+            score < edge.max_score_percentage.unwrap_or(DEFAULT_REMEDIAL_BELOW)
+                || !within_time(edge.time_threshold_minutes)
+        }
+        ("remedial", None) => edge.time_threshold_minutes.is_some() && !within_time(edge.time_threshold_minutes),
+        ("enrichment", Some(score)) => {
+            score >= edge.min_score_percentage.unwrap_or(DEFAULT_ENRICHMENT_FROM)
+                && within_time(edge.time_threshold_minutes)
+        }
+        ("enrichment", None) => within_time(edge.time_threshold_minutes),
+        _ => false,
+    }
+}
+
+enum BranchState<'a> {
+    Open(&'a PathEdge),
+    /// El alumno ya la había abierto; no se vuelve a bloquear
+    Started,
+    NotNeeded,
+    Pending,
+}
+
+/// Estado de una lección de rama según sus aristas de entrada. `prerequisite`
+/// devuelve si la lección previa se califica y el avance del alumno en ella.
+fn branch_state<'a, 'p>(
+    branches: &[&'a PathEdge],
+    own: &LessonProgress,
+    prerequisite: impl Fn(&Uuid) -> (bool, &'p LessonProgress),
+) -> BranchState<'a> {
+    let decided = |edge: &PathEdge| {
+        let (graded, progress) = prerequisite(&edge.prerequisite_lesson_id);
+        branch_decided(graded, progress)
+    };
+    if let Some(edge) = branches.iter().find(|e| {
+        let (graded, progress) = prerequisite(&e.prerequisite_lesson_id);
+        branch_open(e, graded, progress)
+    }) {
+        BranchState::Open(edge)
+    } else if own.score.is_some() || own.completed_event {
+        BranchState::Started
+    } else if branches.iter().all(|e| decided(e)) {
+        BranchState::NotNeeded
+    } else {
+        BranchState::Pending
+    }
+}
+
+/// Calcula la ruta de un alumno. `lessons` debe venir en el orden del curso.
+pub fn compute_path(
+    lessons: &[PathLesson],
+    edges: &[PathEdge],
+    progress: &HashMap<Uuid, LessonProgress>,
+    passing: f64,
+) -> (Vec<PathStep>, Option<(Uuid, String)>) {
+    let empty = LessonProgress::default();
+    let progress_of = |id: &Uuid| progress.get(id).unwrap_or(&empty);
+    let titles: HashMap<Uuid, &str> = lessons.iter().map(|l| (l.id, l.title.as_str())).collect();
+    let graded: HashMap<Uuid, bool> = lessons.iter().map(|l| (l.id, l.is_graded)).collect();
+    let prerequisite = |id: &Uuid| (graded.get(id).copied().unwrap_or(true), progress_of(id));
+    let completed: HashSet<Uuid> = lessons
+        .iter()
+        .filter(|l| is_completed(l, progress_of(&l.id), passing))
+        .map(|l| l.id)
+        .collect();
+    let prerequisite_met = |edge: &PathEdge| {
+        let p = progress_of(&edge.prerequisite_lesson_id);
+        match edge.min_score_percentage {
+            Some(min) => p.score.is_some_and(|s| s >= min),
+            None => completed.contains(&edge.prerequisite_lesson_id) || p.score.is_some(),
+        }
+    };
+
+    let mut steps = Vec::with_capacity(lessons.len());
+    for lesson in lessons {
+        let incoming: Vec<&PathEdge> = edges.iter().filter(|e| e.lesson_id == lesson.id).collect();
+        let branches: Vec<&PathEdge> = incoming.iter().copied().filter(|e| e.branch_type != "prerequisite").collect();
+        let kind = match branches.first().map(|e| e.branch_type.as_str()) {
+            Some("remedial") => "remedial",
+            Some(_) => "enrichment",
+            None => "core",
+        };
+        let progress = progress_of(&lesson.id);
+        let title_of = |id: &Uuid| titles.get(id).copied().unwrap_or("lección previa").to_string();
+
+        let unmet: Vec<String> = incoming
+            .iter()
+            .filter(|e| e.branch_type == "prerequisite" && !prerequisite_met(e))
+            .map(|e| title_of(&e.prerequisite_lesson_id))
+            .collect();
+
+        let (status, reason) = if completed.contains(&lesson.id) {
+            ("completed", None)
+        } else if !unmet.is_empty() {
+            ("locked", Some(format!("Requiere: {}", unmet.join(", "))))
+        } else if kind == "core" {
+            ("available", None)
+        } else {
+            match branch_state(&branches, progress, prerequisite) {
+                BranchState::Open(edge) => {
+                    let reason = if kind == "remedial" {
+                        format!("Refuerzo de \"{}\"", title_of(&edge.prerequisite_lesson_id))
+                    } else {
+                        format!("Profundización de \"{}\"", title_of(&edge.prerequisite_lesson_id))
+                    };
+                    ("available", Some(reason))
+                }
+                BranchState::Started => ("available", None),
+                BranchState::NotNeeded => ("not_needed", None),
+                BranchState::Pending => ("locked", Some("Se define al completar la lección previa".to_string())),
+            }
+        };
+
+        steps.push(PathStep {
+            lesson_id: lesson.id,
+            title: lesson.title.clone(),
+            kind,
+            status,
+            score: progress.score,
+            minutes_spent: (progress.minutes * 10.0).round() / 10.0,
+            reason,
+        });
+    }
+
+    let pick = |kind: &str| steps.iter().find(|s| s.kind == kind && s.status == "available");
+    let next = pick("remedial")
+        .map(|s| (s.lesson_id, s.reason.clone().unwrap_or_else(|| "Lección de refuerzo".to_string())))
+        .or_else(|| {
+            pick("core").map(|s| {
+                let reason = match s.score {
+                    Some(score) => format!("Reintentar: {score:.0} % < {passing:.0} % requerido"),
+                    None => "Siguiente lección del curso".to_string(),
+                };
+                (s.lesson_id, reason)
+            })
+        })
+        .or_else(|| {
+            pick("enrichment")
+                .map(|s| (s.lesson_id, s.reason.clone().unwrap_or_else(|| "Lección de profundización".to_string())))
+        });
+
+    (steps, next)
+}
+
+pub async fn load_adaptive_path(
+    pool: &PgPool,
+    org_id: Uuid,
+    course_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<AdaptivePath>, sqlx::Error> {
+    let passing: Option<i32> =
+        sqlx::query_scalar("SELECT passing_percentage FROM courses WHERE id = $1 AND organization_id = $2")
+            .bind(course_id)
+            .bind(org_id)
+            .fetch_optional(pool)
+            .await?;
+    let Some(passing) = passing else {
+        return Ok(None);
+    };
+
+    let lessons = sqlx::query_as::<_, PathLesson>(
+        r#"
+        SELECT l.id, l.title, l.is_graded
+        FROM lessons l
+        JOIN modules m ON m.id = l.module_id
+        WHERE m.course_id = $1
+        ORDER BY m.position, l.position
+        "#,
+    )
+    .bind(course_id)
+    .fetch_all(pool)
+    .await?;
+    let lesson_ids: Vec<Uuid> = lessons.iter().map(|l| l.id).collect();
+
+    let edges = sqlx::query_as::<_, PathEdge>(
+        r#"
+        SELECT lesson_id, prerequisite_lesson_id, branch_type, min_score_percentage,
+               max_score_percentage, time_threshold_minutes
+        FROM lesson_dependencies
+        WHERE lesson_id = ANY($1)
+        "#,
+    )
+    .bind(&lesson_ids)
+    .fetch_all(pool)
+    .await?;
+
+    let progress = load_progress(pool, user_id, &lesson_ids).await?;
+
+    let passing = passing as f64;
+    let (steps, next) = compute_path(&lessons, &edges, &progress, passing);
+    let (next_lesson_id, next_reason) = next.unzip();
+    Ok(Some(AdaptivePath {
+        course_id,
+        user_id,
+        passing_percentage: passing,
+        steps,
+        next_lesson_id,
+        next_reason,
+    }))
+}
+
+/// Nota, finalización y minutos dedicados del alumno en las lecciones indicadas.
+async fn load_progress(
+    pool: &PgPool,
+    user_id: Uuid,
+    lesson_ids: &[Uuid],
+) -> Result<HashMap<Uuid, LessonProgress>, sqlx::Error> {
+    let mut progress: HashMap<Uuid, LessonProgress> = HashMap::new();
+
+    let scores: Vec<(Uuid, f64)> = sqlx::query_as(
+        "SELECT lesson_id, (score * 100.0)::float8 FROM user_grades WHERE user_id = $1 AND lesson_id = ANY($2)",
+    )
+    .bind(user_id)
+    .bind(lesson_ids)
+    .fetch_all(pool)
+    .await?;
+    for (lesson_id, score) in scores {
+        progress.entry(lesson_id).or_default().score = Some(score);
+    }
+
+    let activity: Vec<(Uuid, bool, f64)> = sqlx::query_as(
+        r#"
+        WITH ev AS (
+            SELECT lesson_id, event_type, created_at,
+                   created_at - LAG(created_at) OVER (PARTITION BY lesson_id ORDER BY created_at) AS gap
+            FROM lesson_interactions
+            WHERE user_id = $1 AND lesson_id = ANY($2)
+        )
+        SELECT lesson_id,
+               BOOL_OR(event_type = 'complete'),
+               (COALESCE(SUM(EXTRACT(EPOCH FROM gap)) FILTER (WHERE gap <= INTERVAL '5 minutes'), 0) / 60.0)::float8
+        FROM ev
+        GROUP BY lesson_id
+        "#,
+    )
+    .bind(user_id)
+    .bind(lesson_ids)
+    .fetch_all(pool)
+    .await?;
+    for (lesson_id, completed_event, minutes) in activity {
+        let entry = progress.entry(lesson_id).or_default();
+        entry.completed_event = completed_event;
+        entry.minutes = minutes;
+    }
+    Ok(progress)
+}
+
+#[derive(sqlx::FromRow)]
+struct BranchEdgeRow {
+    #[sqlx(flatten)]
+    edge: PathEdge,
+    prerequisite_graded: bool,
+}
+
+/// Rama remedial o de profundización aún no abierta para el alumno. Solo evalúa las
+/// aristas de la lección, sin calcular la ruta completa del curso.
+pub async fn is_branch_locked(pool: &PgPool, org_id: Uuid, user_id: Uuid, lesson_id: Uuid) -> Result<bool, sqlx::Error> {
+    let rows = sqlx::query_as::<_, BranchEdgeRow>(
+        r#"
+        SELECT ld.lesson_id, ld.prerequisite_lesson_id, ld.branch_type, ld.min_score_percentage,
+               ld.max_score_percentage, ld.time_threshold_minutes,
+               COALESCE(p.is_graded, true) AS prerequisite_graded
+        FROM lesson_dependencies ld
+        LEFT JOIN lessons p ON p.id = ld.prerequisite_lesson_id
+        WHERE ld.lesson_id = $1 AND ld.organization_id = $2 AND ld.branch_type <> 'prerequisite'
+        "#,
+    )
+    .bind(lesson_id)
+    .bind(org_id)
+    .fetch_all(pool)
+    .await?;
+    if rows.is_empty() {
+        return Ok(false);
+    }
+
+    let mut lesson_ids: Vec<Uuid> = rows.iter().map(|r| r.edge.prerequisite_lesson_id).collect();
+    lesson_ids.push(lesson_id);
+    let progress = load_progress(pool, user_id, &lesson_ids).await?;
+    let empty = LessonProgress::default();
+    let graded: HashMap<Uuid, bool> =
+        rows.iter().map(|r| (r.edge.prerequisite_lesson_id, r.prerequisite_graded)).collect();
+    let branches: Vec<&PathEdge> = rows.iter().map(|r| &r.edge).collect();
+
+    let state = branch_state(&branches, progress.get(&lesson_id).unwrap_or(&empty), |id| {
+        (graded.get(id).copied().unwrap_or(true), progress.get(id).unwrap_or(&empty))
+    });
+    Ok(matches!(state, BranchState::NotNeeded | BranchState::Pending))
+}
+
+/// GET /courses/{id}/adaptive-path - Ruta y siguiente lección del alumno autenticado
+pub async fn get_my_adaptive_path(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+) -> Result<Json<AdaptivePath>, (StatusCode, String)> {
+    load_adaptive_path(&pool, org_ctx.id, course_id, claims.sub)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error al calcular la ruta adaptativa: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+        })?
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, "Curso no encontrado".to_string()))
+}
+
+/// GET /courses/{id}/adaptive-path/students/{user_id} - Vista previa para el equipo docente
+pub async fn get_student_adaptive_path(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path((course_id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<AdaptivePath>, (StatusCode, String)> {
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+
+    load_adaptive_path(&pool, org_ctx.id, course_id, user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error al calcular la ruta adaptativa: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+        })?
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, "Curso no encontrado".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lesson(n: u128, graded: bool) -> PathLesson {
+        PathLesson { id: Uuid::from_u128(n), title: format!("L{n}"), is_graded: graded }
+    }
+
+    fn edge(to: u128, from: u128, branch: &str, min: Option<f64>) -> PathEdge {
+        PathEdge {
+            lesson_id: Uuid::from_u128(to),
+            prerequisite_lesson_id: Uuid::from_u128(from),
+            branch_type: branch.to_string(),
+            min_score_percentage: min,
+            max_score_percentage: None,
+            time_threshold_minutes: None,
+        }
+    }
+
+    #[test]
+    fn low_score_opens_remedial_branch_before_next_core_lesson() {
+        let lessons = vec![lesson(1, true), lesson(2, false), lesson(3, false), lesson(4, true)];
+        let edges = vec![
+            edge(2, 1, "remedial", None),
+            edge(3, 1, "enrichment", None),
+            edge(4, 1, "prerequisite", Some(50.0)),
+        ];
+        let mut progress = HashMap::new();
+        progress.insert(Uuid::from_u128(1), LessonProgress { score: Some(55.0), ..Default::default() });
+
+        let (steps, next) = compute_path(&lessons, &edges, &progress, 70.0);
+        let status: Vec<_> = steps.iter().map(|s| (s.kind, s.status)).collect();
+        assert_eq!(
+            status,
+            vec![("core", "available"), ("remedial", "available"), ("enrichment", "not_needed"), ("core", "available")]
+        );
+        assert_eq!(next.map(|(id, _)| id), Some(Uuid::from_u128(2)));
+
+        progress.insert(Uuid::from_u128(1), LessonProgress { score: Some(95.0), ..Default::default() });
+        let (steps, next) = compute_path(&lessons, &edges, &progress, 70.0);
+        assert_eq!(steps[1].status, "not_needed");
+        assert_eq!(steps[2].status, "available");
+        assert_eq!(next.map(|(id, _)| id), Some(Uuid::from_u128(4)));
+    }
+
+    #[test]
+    fn ungraded_prerequisite_decides_branches_on_completion_by_time() {
+        let lessons = vec![lesson(1, false), lesson(2, false), lesson(3, false)];
+        let mut remedial = edge(2, 1, "remedial", None);
+        remedial.time_threshold_minutes = Some(10);
+        let mut enrichment = edge(3, 1, "enrichment", None);
+        enrichment.time_threshold_minutes = Some(10);
+        let edges = vec![remedial, enrichment];
+        let mut progress = HashMap::new();
+
+        let (steps, _) = compute_path(&lessons, &edges, &progress, 70.0);
+        assert_eq!((steps[1].status, steps[2].status), ("locked", "locked"));
+
+        let slow = LessonProgress { completed_event: true, minutes: 25.0, ..Default::default() };
+        progress.insert(Uuid::from_u128(1), slow);
+        let (steps, next) = compute_path(&lessons, &edges, &progress, 70.0);
+        assert_eq!((steps[1].status, steps[2].status), ("available", "not_needed"));
+        assert_eq!(next.map(|(id, _)| id), Some(Uuid::from_u128(2)));
+
+        let quick = LessonProgress { completed_event: true, minutes: 4.0, ..Default::default() };
+        progress.insert(Uuid::from_u128(1), quick);
+        let (steps, _) = compute_path(&lessons, &edges, &progress, 70.0);
+        assert_eq!((steps[1].status, steps[2].status), ("not_needed", "available"));
+    }
+
+    #[tokio::test]
+    async fn branch_lock_follows_the_prerequisite_progress() {
+        let Some(pool) = crate::db_util::test_pool(1).await else {
+            return;
+        };
+        let org_id = common::tenancy::DEFAULT_ORG_ID;
+        let (course_id, module_id, base, branch) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let user_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO courses (id, title, instructor_id, organization_id) VALUES ($1, 'Ruta', $2, $3)")
+            .bind(course_id)
+            .bind(Uuid::new_v4())
+            .bind(org_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO modules (id, organization_id, course_id, title, position) VALUES ($1, $2, $3, 'M', 1)")
+            .bind(module_id)
+            .bind(org_id)
+            .bind(course_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        for (id, graded, position) in [(base, false, 1), (branch, false, 2)] {
+            sqlx::query(
+                "INSERT INTO lessons (id, organization_id, module_id, title, content_type, position, is_graded) VALUES ($1, $2, $3, 'L', 'activity', $4, $5)",
+            )
+            .bind(id)
+            .bind(org_id)
+            .bind(module_id)
+            .bind(position)
+            .bind(graded)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        sqlx::query(
+            "INSERT INTO lesson_dependencies (organization_id, lesson_id, prerequisite_lesson_id, branch_type) VALUES ($1, $2, $3, 'enrichment')",
+        )
+        .bind(org_id)
+        .bind(branch)
+        .bind(base)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert!(is_branch_locked(&pool, org_id, user_id, branch).await.unwrap());
+        assert!(!is_branch_locked(&pool, org_id, user_id, base).await.unwrap());
+
+        sqlx::query(
+            "INSERT INTO lesson_interactions (organization_id, user_id, lesson_id, event_type) VALUES ($1, $2, $3, 'complete')",
+        )
+        .bind(org_id)
+        .bind(user_id)
+        .bind(base)
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert!(!is_branch_locked(&pool, org_id, user_id, branch).await.unwrap());
+
+        sqlx::query("DELETE FROM courses WHERE id = $1").bind(course_id).execute(&pool).await.unwrap();
+    }
+}
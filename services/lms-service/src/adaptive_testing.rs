@@ -0,0 +1,693 @@
+/// Pruebas adaptativas computarizadas (CAT) para lecciones con
+/// `metadata.delivery_mode = "adaptive"` (exámenes de ubicación).
+///
+/// - Habilidad estimada por EAP (esperanza a posteriori) con prior N(0, 1) sobre el
+///   modelo 2PL; a diferencia de la máxima verosimilitud, es finita desde el primer ítem.
+/// - Selección por máxima información de Fisher con control de exposición
+///   "randomesque" (se elige al azar entre los ítems más informativos).
+/// - Se detiene al alcanzar el error estándar objetivo (tras el mínimo de ítems),
+///   el máximo de ítems o al agotar el banco.
+/// - El nivel MCER final se guarda en el intento y la calificación en `user_grades`.
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use common::{auth::Claims, middleware::Org};
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+const QUADRATURE_POINTS: usize = 81;
+const THETA_RANGE: f64 = 4.0;
+/// Ítems candidatos entre los que se sortea el siguiente (control de exposición).
+const RANDOMESQUE_SIZE: usize = 3;
+
+fn default_se_threshold() -> f64 {
+    0.3
+}
+
+fn default_min_items() -> usize {
+    5
+}
+
+fn default_max_items() -> usize {
+    30
+}
+
+fn default_discrimination() -> f64 {
+    1.0
+}
+
+/// Cortes de habilidad por nivel MCER (escala N(0, 1) de la población de referencia).
+fn default_cefr_cutoffs() -> Vec<CefrCutoff> {
+    [("A1", None), ("A2", Some(-1.5)), ("B1", Some(-0.5)), ("B2", Some(0.5)), ("C1", Some(1.5)), ("C2", Some(2.5))]
+        .into_iter()
+        .map(|(level, min_theta)| CefrCutoff { level: level.to_string(), min_theta })
+        .collect()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CefrCutoff {
+    pub level: String,
+    /// `None` para el nivel base
+    pub min_theta: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PoolItem {
+    pub id: String,
+    #[serde(default)]
+    pub question_bank_id: Option<Uuid>,
+    #[serde(default, rename = "type")]
+    pub question_type: Option<String>,
+    pub question: String,
+    #[serde(default)]
+    pub options: Vec<Value>,
+    #[serde(default)]
+    pub correct: Vec<i64>,
+    #[serde(default)]
+    pub audio_url: Option<String>,
+    #[serde(default)]
+    pub media_url: Option<String>,
+    #[serde(default)]
+    pub media_type: Option<String>,
+    #[serde(default)]
+    pub skill: Option<String>,
+    pub difficulty: f64,
+    #[serde(default = "default_discrimination")]
+    pub discrimination: f64,
+}
+
+/// `metadata.adaptive` de la lección, escrito por el CMS al aplicar la plantilla.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdaptiveConfig {
+    #[serde(default = "default_se_threshold")]
+    pub se_threshold: f64,
+    #[serde(default = "default_min_items")]
+    pub min_items: usize,
+    #[serde(default = "default_max_items")]
+    pub max_items: usize,
+    #[serde(default = "default_cefr_cutoffs")]
+    pub cefr_cutoffs: Vec<CefrCutoff>,
+    #[serde(default)]
+    pub pool: Vec<PoolItem>,
+}
+
+impl AdaptiveConfig {
+    pub fn from_metadata(metadata: &Value) -> Option<Self> {
+        if metadata.get("delivery_mode").and_then(|m| m.as_str()) != Some("adaptive") {
+            return None;
+        }
+        serde_json::from_value(metadata.get("adaptive")?.clone()).ok()
+    }
+
+    fn item(&self, id: &str) -> Option<&PoolItem> {
+        self.pool.iter().find(|item| item.id == id)
+    }
+}
+
+/// Quita el banco de ítems (con sus respuestas correctas) de los metadatos que se
+/// envían a los alumnos; solo queda el tamaño del banco.
+pub fn redact_item_pool(metadata: &mut Option<Value>) {
+    let Some(adaptive) = metadata.as_mut().and_then(|m| m.get_mut("adaptive")).and_then(|a| a.as_object_mut())
+    else {
+        return;
+    };
+    if let Some(Value::Array(pool)) = adaptive.remove("pool") {
+        adaptive.insert("pool_size".to_string(), Value::from(pool.len()));
+    }
+}
+
+// ─── Modelo 2PL ──────────────────────────────────────────────────────────────
+
+fn probability(discrimination: f64, difficulty: f64, theta: f64) -> f64 {
+    1.0 / (1.0 + (-discrimination * (theta - difficulty)).exp())
+}
+
+pub fn item_information(discrimination: f64, difficulty: f64, theta: f64) -> f64 {
+    let p = probability(discrimination, difficulty, theta);
+    discrimination * discrimination * p * (1.0 - p)
+}
+
+/// Estimación EAP de la habilidad y su error estándar (desviación a posteriori)
+/// a partir de `(discriminación, dificultad, correcta)`.
+pub fn estimate_ability(responses: &[(f64, f64, bool)]) -> (f64, f64) {
+    let step = 2.0 * THETA_RANGE / (QUADRATURE_POINTS - 1) as f64;
+    let mut weight_sum = 0.0;
+    let mut first_moment = 0.0;
+    let mut second_moment = 0.0;
+    for k in 0..QUADRATURE_POINTS {
+        let theta = -THETA_RANGE + k as f64 * step;
+        // Log-verosimilitud + log-prior normal estándar
+        let log_weight = responses.iter().fold(-0.5 * theta * theta, |acc, &(a, b, correct)| {
+            let p = probability(a, b, theta).clamp(1e-12, 1.0 - 1e-12);
+            acc + if correct { p.ln() } else { (1.0 - p).ln() }
+        });
+        let weight = log_weight.exp();
+        weight_sum += weight;
+        first_moment += weight * theta;
+        second_moment += weight * theta * theta;
+    }
+    let theta = first_moment / weight_sum;
+    let variance = (second_moment / weight_sum - theta * theta).max(0.0);
+    (theta, variance.sqrt())
+}
+
+/// Nivel MCER más alto cuyo corte no supera la habilidad estimada.
+pub fn cefr_level(theta: f64, cutoffs: &[CefrCutoff]) -> Option<String> {
+    cutoffs
+        .iter()
+        .filter(|c| c.min_theta.is_none_or(|min| theta >= min))
+        .max_by(|a, b| {
+            let (a, b) = (a.min_theta.unwrap_or(f64::NEG_INFINITY), b.min_theta.unwrap_or(f64::NEG_INFINITY));
+            a.total_cmp(&b)
+        })
+        .map(|c| c.level.clone())
+}
+
+/// Calificación 0.0 a 1.0: percentil de la habilidad en la población de referencia
+/// (aproximación logística de la normal acumulada).
+fn ability_score(theta: f64) -> f32 {
+    (1.0 / (1.0 + (-1.702 * theta).exp())) as f32
+}
+
+fn stop_reason(config: &AdaptiveConfig, administered: usize, standard_error: f64, remaining: usize) -> Option<&'static str> {
+    if administered >= config.max_items {
+        Some("max_items")
+    } else if administered >= config.min_items && standard_error <= config.se_threshold {
+        Some("se_threshold")
+    } else if remaining == 0 {
+        Some("pool_exhausted")
+    } else {
+        None
+    }
+}
+
+/// Siguiente ítem: sorteo entre los más informativos en la habilidad actual.
+fn select_item<'a>(config: &'a AdaptiveConfig, administered: &HashSet<&str>, theta: f64) -> Option<&'a PoolItem> {
+    let mut candidates: Vec<(f64, &PoolItem)> = config
+        .pool
+        .iter()
+        .filter(|item| !administered.contains(item.id.as_str()))
+        .map(|item| (item_information(item.discrimination, item.difficulty, theta), item))
+        .collect();
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+    candidates.truncate(RANDOMESQUE_SIZE);
+    candidates.choose(&mut rand::thread_rng()).map(|(_, item)| *item)
+}
+
+// ─── Intentos ────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdministeredItem {
+    pub item_id: String,
+    pub question_bank_id: Option<Uuid>,
+    pub selected: Vec<i64>,
+    pub correct: bool,
+    pub difficulty: f64,
+    pub discrimination: f64,
+    /// Habilidad estimada tras responder este ítem
+    pub theta: f64,
+    pub standard_error: f64,
+    pub answered_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AdaptiveAttempt {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub user_id: Uuid,
+    pub course_id: Uuid,
+    pub lesson_id: Uuid,
+    pub status: String,
+    pub theta: f64,
+    pub standard_error: f64,
+    pub items_administered: i32,
+    pub current_item_id: Option<String>,
+    pub responses: Value,
+    pub stop_reason: Option<String>,
+    pub cefr_level: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Pregunta tal como la ve el alumno (sin respuesta correcta ni parámetros TRI).
+#[derive(Debug, Serialize)]
+pub struct PublicItem {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub question_type: Option<String>,
+    pub question: String,
+    pub options: Vec<Value>,
+    pub audio_url: Option<String>,
+    pub media_url: Option<String>,
+    pub media_type: Option<String>,
+    pub skill: Option<String>,
+}
+
+impl From<&PoolItem> for PublicItem {
+    fn from(item: &PoolItem) -> Self {
+        PublicItem {
+            id: item.id.clone(),
+            question_type: item.question_type.clone(),
+            question: item.question.clone(),
+            options: item.options.clone(),
+            audio_url: item.audio_url.clone(),
+            media_url: item.media_url.clone(),
+            media_type: item.media_type.clone(),
+            skill: item.skill.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdaptiveAttemptView {
+    pub id: Uuid,
+    pub lesson_id: Uuid,
+    pub status: String,
+    pub items_administered: i32,
+    pub max_items: usize,
+    pub current_item: Option<PublicItem>,
+    /// Resultado: solo al finalizar
+    pub theta: Option<f64>,
+    pub standard_error: Option<f64>,
+    pub cefr_level: Option<String>,
+    pub stop_reason: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl AdaptiveAttemptView {
+    fn new(attempt: &AdaptiveAttempt, config: &AdaptiveConfig) -> Self {
+        let completed = attempt.status == "completed";
+        AdaptiveAttemptView {
+            id: attempt.id,
+            lesson_id: attempt.lesson_id,
+            status: attempt.status.clone(),
+            items_administered: attempt.items_administered,
+            max_items: config.max_items,
+            current_item: attempt
+                .current_item_id
+                .as_deref()
+                .and_then(|id| config.item(id))
+                .map(PublicItem::from),
+            theta: completed.then_some(attempt.theta),
+            standard_error: completed.then_some(attempt.standard_error),
+            cefr_level: attempt.cefr_level.clone(),
+            stop_reason: attempt.stop_reason.clone(),
+            started_at: attempt.started_at,
+            completed_at: attempt.completed_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdaptiveAnswerPayload {
+    pub item_id: String,
+    /// Índice(s) de opción elegidos
+    pub selected: Value,
+}
+
+async fn load_adaptive_lesson(
+    pool: &PgPool,
+    org_id: Uuid,
+    lesson_id: Uuid,
+) -> Result<(Uuid, AdaptiveConfig, Option<i32>), (StatusCode, String)> {
+    let lesson: Option<(Uuid, Option<Value>, Option<i32>)> = sqlx::query_as(
+        "SELECT m.course_id, l.metadata, l.max_attempts FROM lessons l JOIN modules m ON m.id = l.module_id WHERE l.id = $1 AND l.organization_id = $2",
+    )
+    .bind(lesson_id)
+    .bind(org_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let (course_id, metadata, max_attempts) =
+        lesson.ok_or((StatusCode::NOT_FOUND, "Lección no encontrada".to_string()))?;
+    let config = metadata
+        .as_ref()
+        .and_then(AdaptiveConfig::from_metadata)
+        .filter(|c| !c.pool.is_empty())
+        .ok_or((StatusCode::BAD_REQUEST, "La lección no es una prueba adaptativa".to_string()))?;
+    Ok((course_id, config, max_attempts))
+}
+
+/// POST /lessons/{id}/adaptive-attempts - Inicia (o reanuda) la prueba adaptativa
+pub async fn start_adaptive_attempt(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(lesson_id): Path<Uuid>,
+) -> Result<Json<AdaptiveAttemptView>, (StatusCode, String)> {
+    let (course_id, config, max_attempts) = load_adaptive_lesson(&pool, org_ctx.id, lesson_id).await?;
+
+    let enrolled: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM enrollments WHERE user_id = $1 AND course_id = $2)")
+            .bind(claims.sub)
+            .bind(course_id)
+            .fetch_one(&pool)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    if !enrolled {
+        return Err((StatusCode::FORBIDDEN, "No estás inscrito en este curso".to_string()));
+    }
+
+    let in_progress: Option<AdaptiveAttempt> = sqlx::query_as(
+        "SELECT * FROM adaptive_test_attempts WHERE user_id = $1 AND lesson_id = $2 AND status = 'in_progress'",
+    )
+    .bind(claims.sub)
+    .bind(lesson_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    if let Some(attempt) = in_progress {
+        return Ok(Json(AdaptiveAttemptView::new(&attempt, &config)));
+    }
+
+    let completed: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM adaptive_test_attempts WHERE user_id = $1 AND lesson_id = $2 AND status = 'completed'",
+    )
+    .bind(claims.sub)
+    .bind(lesson_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    if max_attempts.is_some_and(|max| completed >= max as i64) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Se ha alcanzado el número máximo de intentos para esta evaluación".to_string(),
+        ));
+    }
+
+    let first_item = select_item(&config, &HashSet::new(), 0.0).map(|item| item.id.clone());
+    let inserted: Option<AdaptiveAttempt> = sqlx::query_as(
+        r#"
+        INSERT INTO adaptive_test_attempts (organization_id, user_id, course_id, lesson_id, current_item_id)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(org_ctx.id)
+    .bind(claims.sub)
+    .bind(course_id)
+    .bind(lesson_id)
+    .bind(first_item)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error al iniciar la prueba adaptativa: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+    })?;
+
+    // Otra petición concurrente ya creó el intento en curso
+    let attempt = match inserted {
+        Some(attempt) => attempt,
+        None => sqlx::query_as(
+            "SELECT * FROM adaptive_test_attempts WHERE user_id = $1 AND lesson_id = $2 AND status = 'in_progress'",
+        )
+        .bind(claims.sub)
+        .bind(lesson_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?,
+    };
+
+    Ok(Json(AdaptiveAttemptView::new(&attempt, &config)))
+}
+
+/// GET /adaptive-attempts/{id} - Estado del intento (alumno propietario o personal)
+pub async fn get_adaptive_attempt(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(attempt_id): Path<Uuid>,
+) -> Result<Json<AdaptiveAttemptView>, (StatusCode, String)> {
+    let attempt: AdaptiveAttempt =
+        sqlx::query_as("SELECT * FROM adaptive_test_attempts WHERE id = $1 AND organization_id = $2")
+            .bind(attempt_id)
+            .bind(org_ctx.id)
+            .fetch_optional(&pool)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+            .ok_or((StatusCode::NOT_FOUND, "Intento no encontrado".to_string()))?;
+
+    if attempt.user_id != claims.sub && claims.role != "admin" && claims.role != "instructor" {
+        return Err((StatusCode::FORBIDDEN, "No tienes acceso a este intento".to_string()));
+    }
+
+    let (_, config, _) = load_adaptive_lesson(&pool, org_ctx.id, attempt.lesson_id).await?;
+    Ok(Json(AdaptiveAttemptView::new(&attempt, &config)))
+}
+
+/// POST /adaptive-attempts/{id}/responses - Responde el ítem actual y recibe el siguiente
+pub async fn answer_adaptive_item(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(attempt_id): Path<Uuid>,
+    Json(payload): Json<AdaptiveAnswerPayload>,
+) -> Result<Json<AdaptiveAttemptView>, (StatusCode, String)> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let attempt: AdaptiveAttempt = sqlx::query_as(
+        "SELECT * FROM adaptive_test_attempts WHERE id = $1 AND organization_id = $2 FOR UPDATE",
+    )
+    .bind(attempt_id)
+    .bind(org_ctx.id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Intento no encontrado".to_string()))?;
+
+    if attempt.user_id != claims.sub {
+        return Err((StatusCode::FORBIDDEN, "No tienes acceso a este intento".to_string()));
+    }
+    if attempt.status != "in_progress" {
+        return Err((StatusCode::CONFLICT, "El intento ya finalizó".to_string()));
+    }
+    if attempt.current_item_id.as_deref() != Some(payload.item_id.as_str()) {
+        return Err((StatusCode::CONFLICT, "La respuesta no corresponde a la pregunta actual".to_string()));
+    }
+
+    let (_, config, _) = load_adaptive_lesson(&pool, org_ctx.id, attempt.lesson_id).await?;
+    let mut responses: Vec<AdministeredItem> = serde_json::from_value(attempt.responses.clone()).unwrap_or_default();
+    // Si la pregunta se quitó del banco durante el intento, la respuesta se descarta y
+    // se elige otra (o se cierra el intento) para que el intento no quede bloqueado
+    let (theta, standard_error) = match config.item(&payload.item_id) {
+        Some(item) => {
+            let mut selected = crate::psychometrics::value_indices(&payload.selected);
+            selected.sort_unstable();
+            let mut correct_options = item.correct.clone();
+            correct_options.sort_unstable();
+            let correct = !selected.is_empty() && selected == correct_options;
+
+            let mut scored: Vec<(f64, f64, bool)> =
+                responses.iter().map(|r| (r.discrimination, r.difficulty, r.correct)).collect();
+            scored.push((item.discrimination, item.difficulty, correct));
+            let (theta, standard_error) = estimate_ability(&scored);
+
+            responses.push(AdministeredItem {
+                item_id: item.id.clone(),
+                question_bank_id: item.question_bank_id,
+                selected,
+                correct,
+                difficulty: item.difficulty,
+                discrimination: item.discrimination,
+                theta,
+                standard_error,
+                answered_at: Utc::now(),
+            });
+            (theta, standard_error)
+        }
+        None => (attempt.theta, attempt.standard_error),
+    };
+
+    let administered: HashSet<&str> = responses.iter().map(|r| r.item_id.as_str()).collect();
+    let remaining = config.pool.iter().filter(|item| !administered.contains(item.id.as_str())).count();
+    let stop = stop_reason(&config, responses.len(), standard_error, remaining);
+    let next_item = match stop {
+        Some(_) => None,
+        None => select_item(&config, &administered, theta).map(|item| item.id.clone()),
+    };
+    let level = stop.and_then(|_| cefr_level(theta, &config.cefr_cutoffs));
+    let responses_json = serde_json::to_value(&responses)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let updated: AdaptiveAttempt = sqlx::query_as(
+        r#"
+        UPDATE adaptive_test_attempts
+        SET theta = $2,
+            standard_error = $3,
+            items_administered = $4,
+            current_item_id = $5,
+            responses = $6,
+            stop_reason = $7,
+            cefr_level = $8,
+            status = CASE WHEN $7::text IS NULL THEN 'in_progress' ELSE 'completed' END,
+            completed_at = CASE WHEN $7::text IS NULL THEN NULL ELSE NOW() END
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(attempt.id)
+    .bind(theta)
+    .bind(standard_error)
+    .bind(responses.len() as i32)
+    .bind(&next_item)
+    .bind(stop)
+    .bind(&level)
+    .bind(&responses_json)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error al actualizar el intento adaptativo {}: {}", attempt.id, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+    })?;
+
+    if stop.is_some() {
+        finish_attempt(&mut tx, &updated, &responses).await.map_err(|e| {
+            tracing::error!("Error al registrar el resultado de la prueba adaptativa {}: {}", attempt.id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+        })?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(AdaptiveAttemptView::new(&updated, &config)))
+}
+
+/// Registra la calificación (con el nivel MCER en los metadatos), las respuestas por
+/// ítem, que alimentan la calibración TRI del banco, y las tarjetas de repaso de los fallos.
+async fn finish_attempt(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    attempt: &AdaptiveAttempt,
+    responses: &[AdministeredItem],
+) -> Result<(), sqlx::Error> {
+    let metadata = serde_json::json!({
+        "delivery_mode": "adaptive",
+        "adaptive_attempt_id": attempt.id,
+        "theta": attempt.theta,
+        "standard_error": attempt.standard_error,
+        "cefr_level": attempt.cefr_level,
+        "items_administered": attempt.items_administered,
+        "stop_reason": attempt.stop_reason,
+        "item_responses": responses
+            .iter()
+            .map(|r| serde_json::json!({ "item_id": r.item_id, "selected": r.selected }))
+            .collect::<Vec<_>>(),
+    });
+
+    let grade = sqlx::query_as::<_, common::models::UserGrade>(
+        "SELECT * FROM fn_upsert_user_grade($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(attempt.organization_id)
+    .bind(attempt.user_id)
+    .bind(attempt.course_id)
+    .bind(attempt.lesson_id)
+    .bind(ability_score(attempt.theta))
+    .bind(&metadata)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    crate::psychometrics::record_item_responses(
+        tx,
+        attempt.organization_id,
+        attempt.user_id,
+        attempt.course_id,
+        attempt.lesson_id,
+        grade.attempts_count,
+        &metadata,
+        None,
+    )
+    .await?;
+
+    crate::spaced_repetition::enqueue_missed_items(
+        tx,
+        attempt.organization_id,
+        attempt.user_id,
+        attempt.course_id,
+        attempt.lesson_id,
+        grade.attempts_count,
+    )
+    .await?;
+    Ok(())
+}
+
+/// GET /lessons/{id}/adaptive-attempts - Resultados de la prueba (instructor/admin)
+pub async fn list_lesson_adaptive_attempts(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(lesson_id): Path<Uuid>,
+) -> Result<Json<Vec<AdaptiveAttempt>>, (StatusCode, String)> {
+    if claims.role != "admin" && claims.role != "instructor" {
+        return Err((StatusCode::FORBIDDEN, "Se requiere rol instructor o admin".to_string()));
+    }
+
+    let (course_id, _, _) = load_adaptive_lesson(&pool, org_ctx.id, lesson_id).await?;
+    if claims.role == "instructor"
+        && !crate::handlers::instructor_has_course_access(&pool, org_ctx.id, claims.sub, course_id)
+            .await
+            .map_err(|status| (status, "Error interno del servidor".to_string()))?
+    {
+        return Err((StatusCode::FORBIDDEN, "No tienes acceso a este curso".to_string()));
+    }
+
+    let attempts = sqlx::query_as::<_, AdaptiveAttempt>(
+        "SELECT * FROM adaptive_test_attempts WHERE lesson_id = $1 AND organization_id = $2 ORDER BY started_at DESC",
+    )
+    .bind(lesson_id)
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(attempts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ability_rises_with_correct_answers_and_error_shrinks() {
+        let (theta0, se0) = estimate_ability(&[]);
+        assert!(theta0.abs() < 1e-6);
+        assert!((se0 - 1.0).abs() < 0.01);
+
+        let right: Vec<(f64, f64, bool)> = (0..8).map(|i| (1.5, -1.0 + i as f64 * 0.25, true)).collect();
+        let (theta, se) = estimate_ability(&right);
+        assert!(theta > 1.0);
+        assert!(se < se0);
+
+        let mixed: Vec<(f64, f64, bool)> = (0..20).map(|i| (1.5, -2.0 + i as f64 * 0.2, i < 10)).collect();
+        let (theta, se) = estimate_ability(&mixed);
+        assert!(theta.abs() < 0.5);
+        assert!(se < 0.6);
+    }
+
+    #[test]
+    fn maps_ability_to_cefr_and_stops_on_rules() {
+        let cutoffs = default_cefr_cutoffs();
+        assert_eq!(cefr_level(-3.0, &cutoffs).as_deref(), Some("A1"));
+        assert_eq!(cefr_level(0.0, &cutoffs).as_deref(), Some("B1"));
+        assert_eq!(cefr_level(0.5, &cutoffs).as_deref(), Some("B2"));
+        assert_eq!(cefr_level(4.0, &cutoffs).as_deref(), Some("C2"));
+
+        let config: AdaptiveConfig = serde_json::from_value(serde_json::json!({ "max_items": 10 })).unwrap();
+        assert_eq!(stop_reason(&config, 3, 0.1, 20), None);
+        assert_eq!(stop_reason(&config, 5, 0.29, 20), Some("se_threshold"));
+        assert_eq!(stop_reason(&config, 10, 0.8, 20), Some("max_items"));
+        assert_eq!(stop_reason(&config, 6, 0.8, 0), Some("pool_exhausted"));
+    }
+}
@@ -0,0 +1,1121 @@
+/// Ejecución de bloques code-lab.
+///
+/// El LMS no ejecuta código de estudiantes: lo envía a un ejecutor aparte
+/// (`lms-service code-runner`, contenedor `code-runner`) que no tiene credenciales
+/// de base de datos, secretos ni volúmenes, y que corre como usuario sin
+/// privilegios. Allí cada programa se lanza con bubblewrap en espacios de nombres
+/// propios (montaje, PID, red, IPC, UTS y usuario) como `nobody`, viendo solo los
+/// directorios del sistema en modo lectura y su directorio temporal, con un filtro
+/// seccomp que bloquea montajes, nuevos espacios de nombres, ptrace y llamadas de
+/// administración del kernel. Se mantienen los límites de CPU (`RLIMIT_CPU`),
+/// memoria (`RLIMIT_AS`), archivos, descriptores y procesos, y el tiempo máximo de
+/// reloj que mata todo el grupo de procesos.
+///
+/// Los casos visibles son los `test_cases` del bloque; los ocultos se guardan en
+/// `code_lab_tests` al ingerir el curso. Cada caso envía `input` por stdin y compara
+/// stdout con `expected`. Ejecutar no califica: el alumno entrega una ejecución
+/// guardada y el LMS registra su puntaje en la calificación de la lección con
+/// `grading::record_block_score`; el cliente no puede fijarlo.
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header},
+    routing::post,
+};
+use common::{auth::Claims, middleware::Org, models::UserGrade};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sqlx::PgPool;
+use std::io::{Seek, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path as FsPath, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+
+/// Tipos de bloque ejecutables (`code` es el formato anterior a code-lab)
+const CODE_BLOCK_TYPES: &[&str] = &["code-lab", "code"];
+const MAX_CODE_BYTES: usize = 64 * 1024;
+const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+const MAX_FILE_BYTES: u64 = 16 * 1024 * 1024;
+const MAX_OPEN_FILES: u64 = 64;
+const MAX_PROCESSES: u64 = 256;
+/// Reservas de memoria virtual de V8 que no son heap del programa
+const NODE_ADDRESS_SPACE_OVERHEAD_MB: u64 = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Python,
+    JavaScript,
+    Rust,
+}
+
+impl Language {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "python" | "python3" | "py" => Some(Self::Python),
+            "javascript" | "js" | "node" => Some(Self::JavaScript),
+            "rust" | "rs" => Some(Self::Rust),
+            _ => None,
+        }
+    }
+
+    fn source_file(self) -> &'static str {
+        match self {
+            Self::Python => "main.py",
+            Self::JavaScript => "main.js",
+            Self::Rust => "main.rs",
+        }
+    }
+}
+
+/// Configuración del sandbox (variables `CODE_RUNNER_*`).
+#[derive(Debug, Clone)]
+pub struct SandboxConfig {
+    pub python: String,
+    pub node: String,
+    pub rustc: String,
+    pub timeout: Duration,
+    pub compile_timeout: Duration,
+    pub memory_mb: u64,
+    pub compile_memory_mb: u64,
+    /// Ruta de bubblewrap; `None` ejecuta sin aislamiento (solo en pruebas)
+    pub bwrap: Option<String>,
+}
+
+impl SandboxConfig {
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: &str| std::env::var(name).unwrap_or_else(|_| default.to_string());
+        let number = |name: &str, default: u64| {
+            std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        };
+        Self {
+            python: var("CODE_RUNNER_PYTHON", "python3"),
+            node: var("CODE_RUNNER_NODE", "node"),
+            rustc: var("CODE_RUNNER_RUSTC", "rustc"),
+            timeout: Duration::from_secs(number("CODE_RUNNER_TIMEOUT_SECONDS", 5)),
+            compile_timeout: Duration::from_secs(number("CODE_RUNNER_COMPILE_TIMEOUT_SECONDS", 30)),
+            memory_mb: number("CODE_RUNNER_MEMORY_MB", 256),
+            compile_memory_mb: number("CODE_RUNNER_COMPILE_MEMORY_MB", 1536),
+            bwrap: Some(var("CODE_RUNNER_BWRAP", "bwrap")),
+        }
+    }
+}
+
+/// Limita las ejecuciones simultáneas (`CODE_RUNNER_MAX_CONCURRENCY`).
+fn permits() -> &'static Semaphore {
+    static PERMITS: OnceLock<Semaphore> = OnceLock::new();
+    PERMITS.get_or_init(|| {
+        let max = std::env::var("CODE_RUNNER_MAX_CONCURRENCY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(4);
+        Semaphore::new(max)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessStatus {
+    Exited,
+    Timeout,
+    /// No se pudo lanzar el proceso o montar el sandbox
+    SandboxError,
+}
+
+#[derive(Debug)]
+pub struct ProcessOutput {
+    pub status: ProcessStatus,
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration_ms: u64,
+}
+
+impl ProcessOutput {
+    fn success(&self) -> bool {
+        self.status == ProcessStatus::Exited && self.exit_code == Some(0)
+    }
+}
+
+struct ProcessLimits {
+    wall: Duration,
+    memory_mb: u64,
+}
+
+fn set_limit(resource: libc::__rlimit_resource_t, value: u64) -> std::io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: value,
+        rlim_max: value,
+    };
+    // SAFETY: setrlimit solo lee la estructura recibida
+    if unsafe { libc::setrlimit(resource, &limit) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+// ==================== Filtro seccomp ====================
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = 0xc000_003e;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: u32 = 0xc000_00b7;
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+compile_error!("El filtro seccomp de code-lab solo está definido para x86_64 y aarch64");
+
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_JEQ_K: u16 = 0x15;
+const BPF_JGE_K: u16 = 0x35;
+const BPF_JSET_K: u16 = 0x45;
+const BPF_RET_K: u16 = 0x06;
+const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+/// Desplazamientos en `struct seccomp_data`
+const SECCOMP_DATA_NR: u32 = 0;
+const SECCOMP_DATA_ARCH: u32 = 4;
+const SECCOMP_DATA_ARG0: u32 = 16;
+
+/// Llamadas que un programa de estudiante nunca necesita
+const DENIED_SYSCALLS: &[libc::c_long] = &[
+    libc::SYS_ptrace,
+    libc::SYS_process_vm_readv,
+    libc::SYS_process_vm_writev,
+    libc::SYS_mount,
+    libc::SYS_umount2,
+    libc::SYS_pivot_root,
+    libc::SYS_chroot,
+    libc::SYS_fsopen,
+    libc::SYS_fsconfig,
+    libc::SYS_fsmount,
+    libc::SYS_fspick,
+    libc::SYS_open_tree,
+    libc::SYS_move_mount,
+    libc::SYS_unshare,
+    libc::SYS_setns,
+    libc::SYS_kexec_load,
+    libc::SYS_kexec_file_load,
+    libc::SYS_init_module,
+    libc::SYS_finit_module,
+    libc::SYS_delete_module,
+    libc::SYS_bpf,
+    libc::SYS_perf_event_open,
+    libc::SYS_keyctl,
+    libc::SYS_add_key,
+    libc::SYS_request_key,
+    libc::SYS_userfaultfd,
+    libc::SYS_io_uring_setup,
+    libc::SYS_open_by_handle_at,
+    libc::SYS_name_to_handle_at,
+    libc::SYS_reboot,
+    libc::SYS_swapon,
+    libc::SYS_swapoff,
+];
+
+const CLONE_NAMESPACE_FLAGS: u32 = (libc::CLONE_NEWNS
+    | libc::CLONE_NEWCGROUP
+    | libc::CLONE_NEWUTS
+    | libc::CLONE_NEWIPC
+    | libc::CLONE_NEWUSER
+    | libc::CLONE_NEWPID
+    | libc::CLONE_NEWNET) as u32;
+
+fn bpf(code: u16, jt: u8, jf: u8, k: u32) -> libc::sock_filter {
+    libc::sock_filter { code, jt, jf, k }
+}
+
+/// Programa BPF del filtro: otra arquitectura mata el proceso, las llamadas de
+/// `DENIED_SYSCALLS` y `clone` con espacios de nombres devuelven EPERM, y `clone3`
+/// devuelve ENOSYS para que la libc use `clone`, cuyos indicadores sí se pueden
+/// inspeccionar.
+fn seccomp_filter() -> Vec<libc::sock_filter> {
+    let mut program = vec![
+        bpf(BPF_LD_W_ABS, 0, 0, SECCOMP_DATA_ARCH),
+        bpf(BPF_JEQ_K, 1, 0, AUDIT_ARCH),
+        bpf(BPF_RET_K, 0, 0, SECCOMP_RET_KILL_PROCESS),
+        bpf(BPF_LD_W_ABS, 0, 0, SECCOMP_DATA_NR),
+    ];
+    // La ABI x32 comparte arquitectura con x86_64 pero usa otros números
+    #[cfg(target_arch = "x86_64")]
+    program.extend([
+        bpf(BPF_JGE_K, 0, 1, 0x4000_0000),
+        bpf(BPF_RET_K, 0, 0, SECCOMP_RET_ERRNO | libc::EPERM as u32),
+    ]);
+    for nr in DENIED_SYSCALLS {
+        program.push(bpf(BPF_JEQ_K, 0, 1, *nr as u32));
+        program.push(bpf(BPF_RET_K, 0, 0, SECCOMP_RET_ERRNO | libc::EPERM as u32));
+    }
+    program.extend([
+        bpf(BPF_JEQ_K, 0, 1, libc::SYS_clone3 as u32),
+        bpf(BPF_RET_K, 0, 0, SECCOMP_RET_ERRNO | libc::ENOSYS as u32),
+        bpf(BPF_JEQ_K, 0, 3, libc::SYS_clone as u32),
+        bpf(BPF_LD_W_ABS, 0, 0, SECCOMP_DATA_ARG0),
+        bpf(BPF_JSET_K, 0, 1, CLONE_NAMESPACE_FLAGS),
+        bpf(BPF_RET_K, 0, 0, SECCOMP_RET_ERRNO | libc::EPERM as u32),
+        bpf(BPF_RET_K, 0, 0, SECCOMP_RET_ALLOW),
+    ]);
+    program
+}
+
+/// Escribe el filtro en un memfd para `bwrap --seccomp <fd>`.
+fn seccomp_memfd() -> std::io::Result<OwnedFd> {
+    // SAFETY: el nombre es una cadena C válida; el descriptor pasa a OwnedFd
+    let fd = unsafe { libc::memfd_create(c"code-lab-seccomp".as_ptr(), libc::MFD_CLOEXEC) };
+    if fd == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: memfd_create devolvió un descriptor nuevo que nadie más posee
+    let mut file = std::fs::File::from(unsafe { OwnedFd::from_raw_fd(fd) });
+    let bytes: Vec<u8> = seccomp_filter()
+        .iter()
+        .flat_map(|f| {
+            let mut raw = Vec::with_capacity(8);
+            raw.extend_from_slice(&f.code.to_ne_bytes());
+            raw.extend([f.jt, f.jf]);
+            raw.extend_from_slice(&f.k.to_ne_bytes());
+            raw
+        })
+        .collect();
+    file.write_all(&bytes)?;
+    file.rewind()?;
+    Ok(file.into())
+}
+
+/// Argumentos de bubblewrap: el programa ve solo el sistema en lectura y `dir`
+/// montado en `/sandbox`, sin red ni acceso a otros procesos.
+fn bwrap_args(dir: &FsPath, seccomp_fd: i32) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "--unshare-all",
+        "--unshare-user",
+        "--die-with-parent",
+        "--new-session",
+        "--uid",
+        "65534",
+        "--gid",
+        "65534",
+        "--cap-drop",
+        "ALL",
+        "--ro-bind",
+        "/usr",
+        "/usr",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+    for path in ["/lib", "/lib64", "/bin", "/sbin", "/etc/alternatives", "/etc/ld.so.cache"] {
+        args.extend(["--ro-bind-try".to_string(), path.to_string(), path.to_string()]);
+    }
+    args.extend(
+        [
+            "--proc",
+            "/proc",
+            "--dev",
+            "/dev",
+            "--tmpfs",
+            "/tmp",
+            "--bind",
+            &dir.to_string_lossy(),
+            "/sandbox",
+            "--chdir",
+            "/sandbox",
+            "--setenv",
+            "HOME",
+            "/sandbox",
+            "--seccomp",
+            &seccomp_fd.to_string(),
+            "--",
+        ]
+        .into_iter()
+        .map(String::from),
+    );
+    args
+}
+
+/// Lee todo el flujo y conserva los primeros `MAX_OUTPUT_BYTES`.
+async fn read_capped<R: AsyncRead + Unpin>(mut reader: R) -> String {
+    let mut kept = Vec::new();
+    let mut chunk = [0u8; 8192];
+    while let Ok(n) = reader.read(&mut chunk).await {
+        if n == 0 {
+            break;
+        }
+        let room = MAX_OUTPUT_BYTES.saturating_sub(kept.len());
+        kept.extend_from_slice(&chunk[..n.min(room)]);
+    }
+    String::from_utf8_lossy(&kept).into_owned()
+}
+
+async fn run_sandboxed(
+    config: &SandboxConfig,
+    program: &str,
+    args: &[&str],
+    dir: &FsPath,
+    stdin: &str,
+    limits: ProcessLimits,
+    env: &[(&str, String)],
+) -> ProcessOutput {
+    let started = Instant::now();
+    let failed = |message: String| ProcessOutput {
+        status: ProcessStatus::SandboxError,
+        exit_code: None,
+        stdout: String::new(),
+        stderr: message,
+        duration_ms: started.elapsed().as_millis() as u64,
+    };
+
+    let seccomp = match config.bwrap.as_ref().map(|_| seccomp_memfd()).transpose() {
+        Ok(fd) => fd,
+        Err(e) => return failed(format!("No se pudo preparar el filtro seccomp: {}", e)),
+    };
+    let seccomp_fd = seccomp.as_ref().map(|fd| fd.as_raw_fd());
+    let mut command = match (&config.bwrap, seccomp_fd) {
+        (Some(bwrap), Some(fd)) => {
+            let mut command = tokio::process::Command::new(bwrap);
+            command.args(bwrap_args(dir, fd)).arg(program);
+            command
+        }
+        _ => tokio::process::Command::new(program),
+    };
+    command
+        .args(args)
+        .current_dir(dir)
+        .env_clear()
+        .env("PATH", std::env::var("PATH").unwrap_or_else(|_| "/usr/local/bin:/usr/bin:/bin".to_string()))
+        .env("HOME", dir)
+        .env("LANG", "C.UTF-8")
+        .envs(env.iter().map(|(k, v)| (*k, v.as_str())))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let cpu_seconds = limits.wall.as_secs().max(1);
+    let address_space = limits.memory_mb * 1024 * 1024;
+    // SAFETY: el cierre se ejecuta en el hijo tras fork y solo hace llamadas al sistema
+    unsafe {
+        command.pre_exec(move || {
+            if libc::setsid() == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            // bwrap hereda el memfd del filtro
+            if let Some(fd) = seccomp_fd
+                && libc::fcntl(fd, libc::F_SETFD, 0) == -1
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+            set_limit(libc::RLIMIT_CPU, cpu_seconds)?;
+            set_limit(libc::RLIMIT_AS, address_space)?;
+            set_limit(libc::RLIMIT_FSIZE, MAX_FILE_BYTES)?;
+            set_limit(libc::RLIMIT_NOFILE, MAX_OPEN_FILES)?;
+            set_limit(libc::RLIMIT_NPROC, MAX_PROCESSES)?;
+            set_limit(libc::RLIMIT_CORE, 0)?;
+            Ok(())
+        });
+    }
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => return failed(format!("No se pudo iniciar {}: {}", program, e)),
+    };
+    drop(seccomp);
+    let pid = child.id();
+
+    if let Some(mut pipe) = child.stdin.take() {
+        let input = stdin.as_bytes().to_vec();
+        tokio::spawn(async move {
+            let _ = pipe.write_all(&input).await;
+        });
+    }
+    let stdout = tokio::spawn(read_capped(child.stdout.take().expect("stdout")));
+    let stderr = tokio::spawn(read_capped(child.stderr.take().expect("stderr")));
+
+    let (status, exit_code) = match tokio::time::timeout(limits.wall, child.wait()).await {
+        Ok(Ok(exit)) if exit.signal() == Some(libc::SIGXCPU) => (ProcessStatus::Timeout, None),
+        Ok(Ok(exit)) => (ProcessStatus::Exited, exit.code().or(exit.signal().map(|s| 128 + s))),
+        Ok(Err(e)) => return failed(e.to_string()),
+        Err(_) => {
+            // Matar el grupo completo: el programa pudo lanzar subprocesos
+            if let Some(pid) = pid {
+                // SAFETY: kill no tiene precondiciones de memoria
+                unsafe { libc::kill(-(pid as i32), libc::SIGKILL) };
+            }
+            let _ = child.kill().await;
+            (ProcessStatus::Timeout, None)
+        }
+    };
+
+    ProcessOutput {
+        status,
+        exit_code,
+        stdout: stdout.await.unwrap_or_default(),
+        stderr: stderr.await.unwrap_or_default(),
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestCase {
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub input: String,
+    pub expected: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    RuntimeError,
+    Timeout,
+    CompileError,
+    SandboxError,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TestResult {
+    pub description: String,
+    pub hidden: bool,
+    pub status: TestStatus,
+    /// Entrada, salida esperada y obtenida: solo en casos visibles
+    pub input: Option<String>,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+    pub stderr: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Normaliza la salida: sin espacios al final de cada línea ni líneas vacías finales.
+fn normalize_output(output: &str) -> String {
+    output
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_end()
+        .to_string()
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((index, _)) => format!("{}…", &text[..index]),
+        None => text.to_string(),
+    }
+}
+
+/// Ejecuta el código contra los casos `(caso, oculto)` en un directorio temporal.
+pub async fn run_tests(
+    config: &SandboxConfig,
+    language: Language,
+    code: &str,
+    tests: &[(TestCase, bool)],
+) -> std::io::Result<Vec<TestResult>> {
+    let _permit = permits().acquire().await.map_err(std::io::Error::other)?;
+
+    let dir: PathBuf = std::env::temp_dir().join(format!("openccb-code-lab-{}", Uuid::new_v4()));
+    tokio::fs::create_dir_all(&dir).await?;
+    tokio::fs::write(dir.join(language.source_file()), code).await?;
+
+    let results = execute_in(config, language, &dir, tests).await;
+
+    if let Err(e) = tokio::fs::remove_dir_all(&dir).await {
+        tracing::warn!("No se pudo limpiar {}: {}", dir.display(), e);
+    }
+    Ok(results)
+}
+
+async fn execute_in(
+    config: &SandboxConfig,
+    language: Language,
+    dir: &FsPath,
+    tests: &[(TestCase, bool)],
+) -> Vec<TestResult> {
+    let run_limits = || ProcessLimits {
+        wall: config.timeout,
+        memory_mb: config.memory_mb,
+    };
+
+    // Rust se compila una vez; un fallo de compilación se refleja en todos los casos
+    if language == Language::Rust {
+        let toolchain_env: Vec<(&str, String)> = ["RUSTUP_HOME", "CARGO_HOME", "RUSTUP_TOOLCHAIN"]
+            .into_iter()
+            .filter_map(|name| std::env::var(name).ok().map(|value| (name, value)))
+            .collect();
+        let compiled = run_sandboxed(
+            config,
+            &config.rustc,
+            &["--edition", "2021", "-O", "-o", "main", "main.rs"],
+            dir,
+            "",
+            ProcessLimits {
+                wall: config.compile_timeout,
+                memory_mb: config.compile_memory_mb,
+            },
+            &toolchain_env,
+        )
+        .await;
+        if !compiled.success() {
+            let status = match compiled.status {
+                ProcessStatus::SandboxError => TestStatus::SandboxError,
+                _ => TestStatus::CompileError,
+            };
+            let stderr = truncate(&compiled.stderr, 4000);
+            return tests
+                .iter()
+                .map(|(test, hidden)| TestResult {
+                    description: test.description.clone(),
+                    hidden: *hidden,
+                    status,
+                    input: None,
+                    expected: None,
+                    actual: None,
+                    stderr: Some(stderr.clone()),
+                    duration_ms: 0,
+                })
+                .collect();
+        }
+    }
+
+    let node_heap = format!("--max-old-space-size={}", config.memory_mb);
+    let mut results = Vec::with_capacity(tests.len());
+    for (test, hidden) in tests {
+        let output = match language {
+            Language::Python => {
+                run_sandboxed(config, &config.python, &["-I", "main.py"], dir, &test.input, run_limits(), &[]).await
+            }
+            Language::JavaScript => {
+                let limits = ProcessLimits {
+                    memory_mb: config.memory_mb + NODE_ADDRESS_SPACE_OVERHEAD_MB,
+                    ..run_limits()
+                };
+                run_sandboxed(config, &config.node, &[&node_heap, "main.js"], dir, &test.input, limits, &[]).await
+            }
+            Language::Rust => {
+                let binary = match config.bwrap {
+                    Some(_) => "./main".to_string(),
+                    None => dir.join("main").to_string_lossy().into_owned(),
+                };
+                run_sandboxed(config, &binary, &[], dir, &test.input, run_limits(), &[]).await
+            }
+        };
+
+        let status = match output.status {
+            ProcessStatus::Timeout => TestStatus::Timeout,
+            ProcessStatus::SandboxError => TestStatus::SandboxError,
+            ProcessStatus::Exited if output.exit_code != Some(0) => TestStatus::RuntimeError,
+            ProcessStatus::Exited if normalize_output(&output.stdout) == normalize_output(&test.expected) => {
+                TestStatus::Passed
+            }
+            ProcessStatus::Exited => TestStatus::Failed,
+        };
+        if status == TestStatus::SandboxError {
+            tracing::error!("Sandbox de code-lab no disponible: {}", output.stderr);
+        }
+
+        results.push(TestResult {
+            description: test.description.clone(),
+            hidden: *hidden,
+            status,
+            input: (!hidden).then(|| test.input.clone()),
+            expected: (!hidden).then(|| test.expected.clone()),
+            actual: (!hidden).then(|| truncate(&output.stdout, 4000)),
+            stderr: (!hidden && !output.stderr.is_empty()).then(|| truncate(&output.stderr, 4000)),
+            duration_ms: output.duration_ms,
+        });
+    }
+    results
+}
+
+/// Separa los `hidden_test_cases` de los bloques code-lab para que no se sirvan al
+/// estudiante. Devuelve `(block_id, casos)` por bloque.
+pub fn take_hidden_tests(blocks: Option<&mut Value>) -> Vec<(String, Vec<TestCase>)> {
+    let Some(blocks) = blocks.and_then(Value::as_array_mut) else {
+        return Vec::new();
+    };
+    blocks
+        .iter_mut()
+        .filter(|block| {
+            block
+                .get("type")
+                .and_then(Value::as_str)
+                .is_some_and(|t| CODE_BLOCK_TYPES.contains(&t))
+        })
+        .filter_map(|block| {
+            let block_id = block.get("id").and_then(Value::as_str)?.to_string();
+            let hidden = block.as_object_mut()?.remove("hidden_test_cases")?;
+            let tests: Vec<TestCase> = serde_json::from_value(hidden).unwrap_or_default();
+            Some((block_id, tests))
+        })
+        .collect()
+}
+
+/// Guarda los casos ocultos de una lección recién ingerida.
+pub async fn store_hidden_tests(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    organization_id: Uuid,
+    lesson_id: Uuid,
+    hidden: Vec<(String, Vec<TestCase>)>,
+) -> Result<(), sqlx::Error> {
+    for (block_id, tests) in hidden {
+        for (position, test) in tests.into_iter().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO code_lab_tests (organization_id, lesson_id, block_id, position, description, input, expected_output)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+            )
+            .bind(organization_id)
+            .bind(lesson_id)
+            .bind(&block_id)
+            .bind(position as i32)
+            .bind(&test.description)
+            .bind(&test.input)
+            .bind(&test.expected)
+            .execute(&mut **tx)
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+// ==================== Ejecutor aislado ====================
+
+/// Petición del LMS al ejecutor: código y casos `(caso, oculto)`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunRequest {
+    pub language: Language,
+    pub code: String,
+    pub tests: Vec<(TestCase, bool)>,
+}
+
+/// Comparación en tiempo constante del token compartido.
+fn token_matches(expected: &str, received: &str) -> bool {
+    expected.len() == received.len()
+        && expected
+            .bytes()
+            .zip(received.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+async fn handle_run(
+    State(token): State<Arc<String>>,
+    headers: HeaderMap,
+    Json(request): Json<RunRequest>,
+) -> Result<Json<Vec<TestResult>>, (StatusCode, String)> {
+    let received = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !token_matches(&token, received) {
+        return Err((StatusCode::UNAUTHORIZED, "Token del ejecutor inválido".to_string()));
+    }
+    if request.code.len() > MAX_CODE_BYTES {
+        return Err((StatusCode::PAYLOAD_TOO_LARGE, "El código supera los 64 KB".to_string()));
+    }
+
+    let results = run_tests(&SandboxConfig::from_env(), request.language, &request.code, &request.tests)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error al preparar la ejecución de code-lab: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+        })?;
+    Ok(Json(results))
+}
+
+/// `lms-service code-runner`: servidor HTTP del ejecutor aislado (`CODE_RUNNER_LISTEN`).
+pub async fn serve() {
+    let token = std::env::var("CODE_RUNNER_TOKEN").unwrap_or_default();
+    if token.is_empty() {
+        panic!("CODE_RUNNER_TOKEN debe estar configurada");
+    }
+    let addr = std::env::var("CODE_RUNNER_LISTEN").unwrap_or_else(|_| "0.0.0.0:3010".to_string());
+
+    let app = Router::new()
+        .route("/run", post(handle_run))
+        .with_state(Arc::new(token));
+
+    tracing::info!("Ejecutor de code-lab escuchando en {}", addr);
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .expect("Error al abrir el puerto del ejecutor");
+    axum::serve(listener, app).await.unwrap();
+}
+
+/// Envía la ejecución al ejecutor aislado (`CODE_RUNNER_URL`).
+async fn run_remote(request: &RunRequest) -> Result<Vec<TestResult>, (StatusCode, String)> {
+    let unavailable = || (StatusCode::SERVICE_UNAVAILABLE, "El ejecutor de código no está disponible".to_string());
+    let url = std::env::var("CODE_RUNNER_URL").unwrap_or_default();
+    if url.is_empty() {
+        return Err(unavailable());
+    }
+    let timeout = std::env::var("CODE_RUNNER_REQUEST_TIMEOUT_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(120);
+
+    let response = reqwest::Client::builder()
+        .timeout(Duration::from_secs(timeout))
+        .build()
+        .map_err(|_| unavailable())?
+        .post(format!("{}/run", url.trim_end_matches('/')))
+        .bearer_auth(std::env::var("CODE_RUNNER_TOKEN").unwrap_or_default())
+        .json(request)
+        .send()
+        .await
+        .map_err(|e| {
+            tracing::error!("No se pudo contactar al ejecutor de code-lab: {}", e);
+            unavailable()
+        })?;
+    if !response.status().is_success() {
+        tracing::error!("El ejecutor de code-lab respondió {}", response.status());
+        return Err(unavailable());
+    }
+    response.json().await.map_err(|e| {
+        tracing::error!("Respuesta inválida del ejecutor de code-lab: {}", e);
+        unavailable()
+    })
+}
+
+// ==================== Endpoints ====================
+
+#[derive(Debug, Deserialize)]
+pub struct RunCodeLabPayload {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CodeLabRunResult {
+    pub run_id: Uuid,
+    pub language: Language,
+    pub passed: usize,
+    pub total: usize,
+    /// Porcentaje de casos superados (0-100)
+    pub score: f64,
+    pub results: Vec<TestResult>,
+}
+
+/// POST /lessons/{id}/code-lab/{block_id}/run - Ejecuta el código contra los casos del bloque
+pub async fn run_code_lab(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path((lesson_id, block_id)): Path<(Uuid, String)>,
+    Json(payload): Json<RunCodeLabPayload>,
+) -> Result<Json<CodeLabRunResult>, (StatusCode, String)> {
+    if payload.code.len() > MAX_CODE_BYTES {
+        return Err((StatusCode::PAYLOAD_TOO_LARGE, "El código supera los 64 KB".to_string()));
+    }
+
+    let lesson: Option<(Option<Value>, Option<Value>, bool, bool)> = sqlx::query_as(
+        r#"
+        SELECT l.metadata, l.content_blocks, COALESCE(l.is_previewable, false),
+               EXISTS(SELECT 1 FROM enrollments e WHERE e.user_id = $3 AND e.course_id = m.course_id)
+        FROM lessons l
+        JOIN modules m ON l.module_id = m.id
+        WHERE l.id = $1 AND l.organization_id = $2
+        "#,
+    )
+    .bind(lesson_id)
+    .bind(org_ctx.id)
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let (metadata, content_blocks, is_previewable, is_enrolled) =
+        lesson.ok_or((StatusCode::NOT_FOUND, "Lección no encontrada".to_string()))?;
+
+    let is_staff = claims.role == "admin" || claims.role == "instructor";
+    if !is_staff && !is_enrolled && !is_previewable {
+        return Err((StatusCode::FORBIDDEN, "No tienes acceso a esta lección".into()));
+    }
+
+    let block = [content_blocks.as_ref(), metadata.as_ref().and_then(|m| m.get("blocks"))]
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_array)
+        .flatten()
+        .find(|b| {
+            b.get("id").and_then(Value::as_str) == Some(block_id.as_str())
+                && b.get("type").and_then(Value::as_str).is_some_and(|t| CODE_BLOCK_TYPES.contains(&t))
+        })
+        .ok_or((StatusCode::NOT_FOUND, "Bloque no encontrado".to_string()))?;
+
+    let language = Language::parse(block.get("language").and_then(Value::as_str).unwrap_or("python"))
+        .ok_or((StatusCode::UNPROCESSABLE_ENTITY, "Lenguaje no soportado por el ejecutor".to_string()))?;
+
+    let visible: Vec<TestCase> = block
+        .get("test_cases")
+        .cloned()
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    let hidden: Vec<(String, String, String)> = sqlx::query_as(
+        "SELECT description, input, expected_output FROM code_lab_tests WHERE lesson_id = $1 AND block_id = $2 ORDER BY position",
+    )
+    .bind(lesson_id)
+    .bind(&block_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let tests: Vec<(TestCase, bool)> = visible
+        .into_iter()
+        .map(|test| (test, false))
+        .chain(
+            hidden
+                .into_iter()
+                .map(|(description, input, expected)| (TestCase { description, input, expected }, true)),
+        )
+        .collect();
+    if tests.is_empty() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, "El bloque no tiene casos de prueba".to_string()));
+    }
+
+    let request = RunRequest {
+        language,
+        code: payload.code,
+        tests,
+    };
+    let results = run_remote(&request).await?;
+    if results.iter().any(|r| r.status == TestStatus::SandboxError) {
+        return Err((StatusCode::SERVICE_UNAVAILABLE, "El ejecutor de código no está disponible".to_string()));
+    }
+
+    let total = results.len();
+    let passed = results.iter().filter(|r| r.status == TestStatus::Passed).count();
+    let score = passed as f64 * 100.0 / total as f64;
+
+    let run_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO code_lab_runs (organization_id, user_id, lesson_id, block_id, language, code, passed, total, score, results)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        RETURNING id
+        "#,
+    )
+    .bind(org_ctx.id)
+    .bind(claims.sub)
+    .bind(lesson_id)
+    .bind(&block_id)
+    .bind(serde_json::to_value(language).ok().and_then(|v| v.as_str().map(String::from)))
+    .bind(&request.code)
+    .bind(passed as i32)
+    .bind(total as i32)
+    .bind(score)
+    .bind(serde_json::to_value(&results).unwrap_or_default())
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(CodeLabRunResult {
+        run_id,
+        language,
+        passed,
+        total,
+        score,
+        results,
+    }))
+}
+
+/// POST /lessons/{id}/code-lab/{block_id}/runs/{run_id}/submit - Entrega una ejecución
+/// para calificar el bloque. Cuenta como intento de la lección; cada ejecución se
+/// entrega una sola vez.
+pub async fn submit_code_lab_run(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path((lesson_id, block_id, run_id)): Path<(Uuid, String, Uuid)>,
+) -> Result<Json<UserGrade>, (StatusCode, String)> {
+    let enrolled: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM enrollments e
+            JOIN modules m ON m.course_id = e.course_id
+            JOIN lessons l ON l.module_id = m.id
+            WHERE e.user_id = $1 AND l.id = $2 AND l.organization_id = $3
+        )
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(lesson_id)
+    .bind(org_ctx.id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    if !enrolled {
+        return Err((StatusCode::FORBIDDEN, "Solo los estudiantes inscritos pueden entregar".to_string()));
+    }
+
+    let claimed: Option<(i32, i32)> = sqlx::query_as(
+        r#"
+        UPDATE code_lab_runs SET submitted_at = NOW()
+        WHERE id = $1 AND user_id = $2 AND lesson_id = $3 AND block_id = $4 AND organization_id = $5
+          AND submitted_at IS NULL
+        RETURNING passed, total
+        "#,
+    )
+    .bind(run_id)
+    .bind(claims.sub)
+    .bind(lesson_id)
+    .bind(&block_id)
+    .bind(org_ctx.id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let Some((passed, total)) = claimed else {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM code_lab_runs WHERE id = $1 AND user_id = $2 AND lesson_id = $3 AND block_id = $4)",
+        )
+        .bind(run_id)
+        .bind(claims.sub)
+        .bind(lesson_id)
+        .bind(&block_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+        return Err(if exists {
+            (StatusCode::CONFLICT, "Esta ejecución ya fue entregada".to_string())
+        } else {
+            (StatusCode::NOT_FOUND, "Ejecución no encontrada".to_string())
+        });
+    };
+
+    let graded = crate::grading::record_block_score(
+        &pool,
+        org_ctx.id,
+        claims.sub,
+        lesson_id,
+        &block_id,
+        passed as f32 / total.max(1) as f32,
+        json!({ "run_id": run_id, "passed": passed, "total": total }),
+    )
+    .await;
+    if graded.is_err() {
+        // Intentos agotados, plazo vencido…: la ejecución puede entregarse más adelante
+        let _ = sqlx::query("UPDATE code_lab_runs SET submitted_at = NULL WHERE id = $1")
+            .bind(run_id)
+            .execute(&pool)
+            .await;
+    }
+    graded.map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SandboxConfig {
+        SandboxConfig {
+            timeout: Duration::from_secs(2),
+            bwrap: None,
+            ..SandboxConfig::from_env()
+        }
+    }
+
+    fn case(input: &str, expected: &str) -> TestCase {
+        TestCase {
+            description: String::new(),
+            input: input.to_string(),
+            expected: expected.to_string(),
+        }
+    }
+
+    #[test]
+    fn hidden_tests_are_removed_from_blocks() {
+        let mut blocks = serde_json::json!([
+            {"id": "b1", "type": "code-lab", "test_cases": [], "hidden_test_cases": [{"input": "1", "expected": "2"}]},
+            {"id": "b2", "type": "quiz", "hidden_test_cases": []}
+        ]);
+        let hidden = take_hidden_tests(Some(&mut blocks));
+
+        assert_eq!(hidden.len(), 1);
+        assert_eq!(hidden[0].0, "b1");
+        assert_eq!(hidden[0].1[0].expected, "2");
+        assert!(blocks[0].get("hidden_test_cases").is_none());
+        assert!(blocks[1].get("hidden_test_cases").is_some());
+    }
+
+    #[tokio::test]
+    async fn python_runs_against_tests_with_time_limit() {
+        if std::process::Command::new("python3").arg("--version").output().is_err() {
+            return;
+        }
+        let code = "import sys\nline = sys.stdin.readline().strip()\nif line == 'loop':\n    while True: pass\nprint(line[::-1])\n";
+        let tests = vec![(case("hola\n", "aloh\n"), false), (case("abc", "abc"), true), (case("loop", ""), true)];
+
+        let results = run_tests(&config(), Language::Python, code, &tests).await.unwrap();
+
+        assert_eq!(results[0].status, TestStatus::Passed);
+        assert_eq!(results[1].status, TestStatus::Failed);
+        assert!(results[1].actual.is_none());
+        assert_eq!(results[2].status, TestStatus::Timeout);
+    }
+
+    #[test]
+    fn seccomp_filter_blocks_namespaces() {
+        use std::os::unix::process::CommandExt;
+
+        let run = |program: &str, args: &[&str]| {
+            let filter = seccomp_filter();
+            let mut command = std::process::Command::new(program);
+            command.args(args);
+            // SAFETY: solo prctl en el hijo; `filter` vive hasta después del exec
+            unsafe {
+                command.pre_exec(move || {
+                    let prog = libc::sock_fprog {
+                        len: filter.len() as u16,
+                        filter: filter.as_ptr() as *mut libc::sock_filter,
+                    };
+                    if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) == -1
+                        || libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &prog) == -1
+                    {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+            command.output().map(|o| o.status.success())
+        };
+        if run("unshare", &["--version"]).is_err() {
+            return;
+        }
+
+        assert_eq!(run("true", &[]).ok(), Some(true));
+        // clone3 devuelve ENOSYS y la libc crea hilos con clone
+        if let Ok(threads) = run("python3", &["-c", "import threading; t = threading.Thread(target=print); t.start(); t.join()"]) {
+            assert!(threads);
+        }
+        assert_eq!(run("unshare", &["-U", "true"]).ok(), Some(false));
+        assert_eq!(run("unshare", &["-n", "true"]).ok(), Some(false));
+    }
+
+    #[tokio::test]
+    async fn bwrap_hides_host_and_drops_privileges() {
+        let has = |program: &str| std::process::Command::new(program).arg("--version").output().is_ok();
+        if !has("bwrap") || !has("python3") {
+            return;
+        }
+        let host_dir = env!("CARGO_MANIFEST_DIR");
+        let code = format!(
+            "import os\npids = [p for p in os.listdir('/proc') if p.isdigit()]\nprint(os.getuid(), os.path.exists({:?}), len(pids) <= 2)\n",
+            host_dir
+        );
+        let config = SandboxConfig {
+            timeout: Duration::from_secs(5),
+            ..SandboxConfig::from_env()
+        };
+
+        let results = run_tests(&config, Language::Python, &code, &[(case("", "65534 False True"), false)])
+            .await
+            .unwrap();
+
+        assert_eq!(results[0].status, TestStatus::Passed, "{:?}", results[0]);
+    }
+}
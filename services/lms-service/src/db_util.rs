@@ -41,3 +41,147 @@ pub async fn set_session_context(
     }
     Ok(())
 }
+
+/// Pool de pruebas sobre `TEST_DATABASE_URL` con las migraciones aplicadas. Sin la
+/// variable la prueba se omite, salvo en CI (`CI=true`), donde falla.
+#[cfg(test)]
+pub async fn test_pool(max_connections: u32) -> Option<sqlx::PgPool> {
+    let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+        assert!(
+            std::env::var("CI").is_err(),
+            "TEST_DATABASE_URL es obligatoria en CI para las pruebas con base de datos"
+        );
+        return None;
+    };
+    let pool = common::tenancy::pool_options()
+        .max_connections(max_connections)
+        .connect(&url)
+        .await
+        .unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+    Some(pool)
+}
+
+#[cfg(test)]
+mod tests {
+    use common::tenancy::{self, TenantScope};
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn rls_blocks_cross_org_reads_and_writes() {
+        // Una sola conexión: el mismo socket pasa por los tres ámbitos
+        let Some(pool) = super::test_pool(1).await else {
+            return;
+        };
+
+        let (org_a, org_b) = (Uuid::new_v4(), Uuid::new_v4());
+        let (course_a, course_b) = (Uuid::new_v4(), Uuid::new_v4());
+        for (org, course) in [(org_a, course_a), (org_b, course_b)] {
+            sqlx::query("INSERT INTO organizations (id, name) VALUES ($1, $2)")
+                .bind(org)
+                .bind(format!("rls-test-{}", org))
+                .execute(&pool)
+                .await
+                .unwrap();
+            sqlx::query("INSERT INTO courses (id, title, instructor_id, organization_id) VALUES ($1, 'RLS', $2, $3)")
+                .bind(course)
+                .bind(Uuid::new_v4())
+                .bind(org)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let visible = |scope| {
+            let pool = pool.clone();
+            tenancy::with_scope(scope, async move {
+                sqlx::query_scalar::<_, Uuid>("SELECT id FROM courses WHERE id = ANY($1) ORDER BY id")
+                    .bind(vec![course_a, course_b])
+                    .fetch_all(&pool)
+                    .await
+                    .unwrap()
+            })
+        };
+
+        assert_eq!(visible(TenantScope::Organization(org_a)).await, vec![course_a]);
+        assert_eq!(visible(TenantScope::Organization(org_b)).await, vec![course_b]);
+        assert_eq!(visible(TenantScope::PlatformAdmin(org_a)).await.len(), 2);
+
+        let cross_insert = tenancy::with_scope(TenantScope::Organization(org_a), async {
+            sqlx::query("INSERT INTO courses (id, title, instructor_id, organization_id) VALUES ($1, 'RLS', $2, $3)")
+                .bind(Uuid::new_v4())
+                .bind(Uuid::new_v4())
+                .bind(org_b)
+                .execute(&pool)
+                .await
+        })
+        .await;
+        assert!(cross_insert.is_err());
+
+        let cross_delete = tenancy::with_scope(TenantScope::Organization(org_a), async {
+            sqlx::query("DELETE FROM courses WHERE id = $1").bind(course_b).execute(&pool).await.unwrap()
+        })
+        .await;
+        assert_eq!(cross_delete.rows_affected(), 0);
+
+        sqlx::query("DELETE FROM courses WHERE id = ANY($1)")
+            .bind(vec![course_a, course_b])
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM organizations WHERE id = ANY($1)")
+            .bind(vec![org_a, org_b])
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn rls_global_rows_are_read_only_for_tenants() {
+        let Some(pool) = super::test_pool(1).await else {
+            return;
+        };
+        let mut conn = pool.acquire().await.unwrap();
+
+        // Tabla temporal con organization_id opcional: vive en la única conexión del pool
+        sqlx::query("CREATE TEMP TABLE rls_probe (id UUID PRIMARY KEY, organization_id UUID)")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        sqlx::query("GRANT SELECT, INSERT, UPDATE, DELETE ON rls_probe TO openccb_tenant")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        sqlx::query("SELECT fn_enable_tenant_rls('rls_probe')").execute(&mut *conn).await.unwrap();
+        sqlx::query("INSERT INTO rls_probe VALUES ($1, NULL)")
+            .bind(Uuid::new_v4())
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        drop(conn);
+
+        let org = Uuid::new_v4();
+        tenancy::with_scope(TenantScope::Organization(org), async {
+            let visible: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM rls_probe").fetch_one(&pool).await.unwrap();
+            assert_eq!(visible, 1);
+
+            let updated = sqlx::query("UPDATE rls_probe SET organization_id = NULL").execute(&pool).await.unwrap();
+            assert_eq!(updated.rows_affected(), 0);
+            let deleted = sqlx::query("DELETE FROM rls_probe").execute(&pool).await.unwrap();
+            assert_eq!(deleted.rows_affected(), 0);
+
+            let global_insert = sqlx::query("INSERT INTO rls_probe VALUES ($1, NULL)")
+                .bind(Uuid::new_v4())
+                .execute(&pool)
+                .await;
+            assert!(global_insert.is_err());
+            let own_insert = sqlx::query("INSERT INTO rls_probe VALUES ($1, $2)")
+                .bind(Uuid::new_v4())
+                .bind(org)
+                .execute(&pool)
+                .await;
+            assert!(own_insert.is_ok());
+        })
+        .await;
+    }
+}
@@ -0,0 +1,857 @@
+/// Solicitudes de titulares de datos (GDPR/LGPD).
+///
+/// Un alumno puede pedir la exportación de sus datos o su supresión; un
+/// administrador puede crearlas para cualquier usuario de la organización y debe
+/// aprobar las supresiones pedidas por el propio titular. El worker
+/// `process_dsar_queue` ejecuta las solicitudes pendientes:
+///
+/// - Exportación: un ZIP con un JSON por sección del LMS, los datos del CMS
+///   (`GET /dsar/users/{id}/data`) y un `manifest.json`, disponible durante
+///   `EXPORT_TTL_DAYS` días.
+/// - Supresión: borra el contenido personal (notas, chats, audio, tokens…),
+///   reemplaza el texto de foros y entregas, y seudonimiza el perfil. Las filas de
+///   calificaciones, interacciones y eventos se conservan con el ID seudónimo para
+///   que la analítica agregada no cambie. El registro de auditoría del CMS se
+///   conserva por obligación legal.
+use axum::{
+    Json,
+    body::Body,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use common::{auth::Claims, middleware::Org};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::io::Write;
+use uuid::Uuid;
+
+const EXPORT_TTL_DAYS: i64 = 7;
+pub const ERASED_TEXT: &str = "[Contenido eliminado a solicitud del titular]";
+
+/// Minutos tras los que una solicitud en `processing` se da por abandonada
+const STALE_PROCESSING_MINUTES: i32 = 30;
+
+fn export_dir() -> String {
+    std::env::var("DSAR_EXPORT_DIR").unwrap_or_else(|_| "uploads/dsar".to_string())
+}
+
+/// Secciones de la exportación: (archivo, tabla, columna del titular).
+const EXPORT_SECTIONS: &[(&str, &str, &str)] = &[
+    ("enrollments", "enrollments", "user_id"),
+    ("grades", "user_grades", "user_id"),
+    ("grade_overrides", "grade_overrides", "user_id"),
+    ("submissions", "course_submissions", "user_id"),
+    ("quiz_responses", "quiz_item_responses", "user_id"),
+    ("exam_attempts", "exam_attempts", "user_id"),
+    ("adaptive_test_attempts", "adaptive_test_attempts", "user_id"),
+    ("code_lab_runs", "code_lab_runs", "user_id"),
+    ("xapi_statements", "xapi_statements", "user_id"),
+    ("ai_usage_logs", "ai_usage_logs", "user_id"),
+    ("notes", "student_notes", "user_id"),
+    ("annotations", "lesson_annotations", "user_id"),
+    ("forum_threads", "discussion_threads", "author_id"),
+    ("forum_posts", "discussion_posts", "author_id"),
+    ("forum_votes", "discussion_votes", "user_id"),
+    ("lesson_interactions", "lesson_interactions", "user_id"),
+    ("review_cards", "review_cards", "user_id"),
+    ("points", "points_log", "user_id"),
+    ("badges", "user_badges", "user_id"),
+    ("certificates", "issued_certificates", "user_id"),
+    ("transactions", "transactions", "user_id"),
+    ("notifications", "notifications", "user_id"),
+    ("notification_preferences", "notification_preferences", "user_id"),
+    ("bookmarks", "user_bookmarks", "user_id"),
+    ("cohorts", "user_cohorts", "user_id"),
+    ("exam_accommodations", "exam_accommodations", "user_id"),
+    ("due_date_extensions", "due_date_extensions", "user_id"),
+    ("dropout_risks", "dropout_risks", "user_id"),
+    ("peer_reviews", "peer_reviews", "reviewer_id"),
+];
+
+/// Contenido personal que se borra en la supresión: (tabla, columna del titular).
+const ERASURE_DELETES: &[(&str, &str)] = &[
+    ("chat_sessions", "user_id"),
+    ("student_notes", "user_id"),
+    ("lesson_annotations", "user_id"),
+    ("audio_responses", "user_id"),
+    ("code_lab_runs", "user_id"),
+    ("user_bookmarks", "user_id"),
+    ("notifications", "user_id"),
+    ("notification_preferences", "user_id"),
+    ("notification_digest_state", "user_id"),
+    ("push_subscriptions", "user_id"),
+    ("calendar_feed_tokens", "user_id"),
+    ("password_reset_tokens", "user_id"),
+    ("discussion_subscriptions", "user_id"),
+    ("review_cards", "user_id"),
+];
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct DsarRequest {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub user_id: Uuid,
+    pub request_type: String,
+    pub status: String,
+    pub requested_by: Uuid,
+    pub reason: Option<String>,
+    pub result: Value,
+    #[serde(skip)]
+    pub archive_path: Option<String>,
+    pub archive_sha256: Option<String>,
+    pub error: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct DsarRequestEvent {
+    pub id: Uuid,
+    pub status: String,
+    pub actor_id: Option<Uuid>,
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+async fn record_event(
+    pool: &PgPool,
+    request: &DsarRequest,
+    status: &str,
+    actor_id: Option<Uuid>,
+    detail: Option<String>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO dsar_request_events (request_id, organization_id, status, actor_id, detail) VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(request.id)
+    .bind(request.organization_id)
+    .bind(status)
+    .bind(actor_id)
+    .bind(detail)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// ==================== Exportación ====================
+
+/// Token entre servicios de corta duración, limitado a los endpoints DSAR del CMS;
+/// `sub` es quien registró la solicitud.
+fn cms_token(request: &DsarRequest) -> anyhow::Result<String> {
+    Ok(common::auth::create_service_token(
+        request.requested_by,
+        request.organization_id,
+        common::auth::SERVICE_SCOPE_DSAR,
+    )?)
+}
+
+async fn fetch_cms_data(request: &DsarRequest) -> anyhow::Result<Value> {
+    let cms_url = std::env::var("CMS_API_URL").unwrap_or_else(|_| "http://studio:3001".to_string());
+    let token = cms_token(request)?;
+    let user_id = request.user_id;
+
+    let res = reqwest::Client::new()
+        .get(format!("{}/dsar/users/{}/data", cms_url.trim_end_matches('/'), user_id))
+        .bearer_auth(token)
+        .timeout(std::time::Duration::from_secs(60))
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        anyhow::bail!("el CMS respondió {}", res.status());
+    }
+    Ok(res.json().await?)
+}
+
+/// Datos del titular en el LMS, por sección.
+pub async fn collect_user_data(pool: &PgPool, user_id: Uuid) -> Result<Vec<(String, Value)>, sqlx::Error> {
+    let mut sections = Vec::new();
+
+    let profile: Option<Value> =
+        sqlx::query_scalar("SELECT to_jsonb(u) - 'password_hash' FROM users u WHERE u.id = $1")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+    sections.push(("profile".to_string(), profile.unwrap_or(Value::Null)));
+
+    let chats: Value = sqlx::query_scalar(
+        r#"
+        SELECT COALESCE(jsonb_agg(to_jsonb(s) || jsonb_build_object('messages', (
+                   SELECT COALESCE(jsonb_agg(to_jsonb(m) ORDER BY m.created_at), '[]'::jsonb)
+                   FROM chat_messages m WHERE m.session_id = s.id)) ORDER BY s.created_at), '[]'::jsonb)
+        FROM chat_sessions s
+        WHERE s.user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    sections.push(("chat_sessions".to_string(), chats));
+
+    let audio: Value = sqlx::query_scalar(
+        r#"
+        SELECT COALESCE(jsonb_agg((to_jsonb(a) - 'audio_data')
+                   || jsonb_build_object('audio_file', CASE WHEN a.audio_data IS NULL THEN NULL
+                                                          ELSE 'lms/audio/' || a.id || '.bin' END)
+                   ORDER BY a.created_at), '[]'::jsonb)
+        FROM audio_responses a
+        WHERE a.user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    sections.push(("audio_responses".to_string(), audio));
+
+    for (name, table, column) in EXPORT_SECTIONS {
+        let rows: Value = sqlx::query_scalar(&format!(
+            "SELECT COALESCE(jsonb_agg(to_jsonb(t)), '[]'::jsonb) FROM {} t WHERE t.{} = $1",
+            table, column
+        ))
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+        sections.push((name.to_string(), rows));
+    }
+
+    Ok(sections)
+}
+
+async fn run_export(pool: &PgPool, request: &DsarRequest) -> anyhow::Result<Value> {
+    let sections = collect_user_data(pool, request.user_id).await?;
+    let cms = fetch_cms_data(request).await?;
+    let audio: Vec<(Uuid, Vec<u8>)> = sqlx::query_as(
+        "SELECT id, audio_data FROM audio_responses WHERE user_id = $1 AND audio_data IS NOT NULL",
+    )
+    .bind(request.user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let counts: serde_json::Map<String, Value> = sections
+        .iter()
+        .map(|(name, value)| {
+            let count = value.as_array().map(|rows| rows.len()).unwrap_or(usize::from(!value.is_null()));
+            (name.clone(), json!(count))
+        })
+        .collect();
+
+    let manifest = json!({
+        "request_id": request.id,
+        "user_id": request.user_id,
+        "organization_id": request.organization_id,
+        "generated_at": Utc::now(),
+        "lms_sections": counts,
+        "cms_sections": cms.as_object().map(|o| o.keys().cloned().collect::<Vec<_>>()).unwrap_or_default(),
+        "audio_files": audio.len(),
+    });
+
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    zip.start_file("manifest.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+    for (name, value) in &sections {
+        zip.start_file(format!("lms/{}.json", name), options)?;
+        zip.write_all(&serde_json::to_vec_pretty(value)?)?;
+    }
+    if let Some(cms_sections) = cms.as_object() {
+        for (name, value) in cms_sections {
+            zip.start_file(format!("cms/{}.json", name), options)?;
+            zip.write_all(&serde_json::to_vec_pretty(value)?)?;
+        }
+    }
+    for (id, bytes) in &audio {
+        zip.start_file(format!("lms/audio/{}.bin", id), options)?;
+        zip.write_all(bytes)?;
+    }
+    let bytes = zip.finish()?.into_inner();
+
+    let dir = format!("{}/{}", export_dir(), request.organization_id);
+    tokio::fs::create_dir_all(&dir).await?;
+    let path = format!("{}/{}.zip", dir, request.id);
+    tokio::fs::write(&path, &bytes).await?;
+    let sha256 = hex::encode(Sha256::digest(&bytes));
+
+    sqlx::query(
+        "UPDATE dsar_requests SET archive_path = $2, archive_sha256 = $3, expires_at = $4 WHERE id = $1",
+    )
+    .bind(request.id)
+    .bind(&path)
+    .bind(&sha256)
+    .bind(Utc::now() + Duration::days(EXPORT_TTL_DAYS))
+    .execute(pool)
+    .await?;
+
+    Ok(manifest)
+}
+
+// ==================== Supresión ====================
+
+async fn exec_count(tx: &mut Transaction<'_, Postgres>, sql: &str, user_id: Uuid) -> Result<u64, sqlx::Error> {
+    Ok(sqlx::query(sql).bind(user_id).execute(&mut **tx).await?.rows_affected())
+}
+
+/// Suprime los datos personales del titular en el LMS; devuelve las filas afectadas por paso.
+pub async fn erase_user_data(pool: &PgPool, user_id: Uuid) -> Result<Value, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut result = serde_json::Map::new();
+
+    for (table, column) in ERASURE_DELETES {
+        let sql = format!("DELETE FROM {} WHERE {} = $1", table, column);
+        let n = exec_count(&mut tx, &sql, user_id).await?;
+        result.insert(format!("deleted.{}", table), json!(n));
+    }
+
+    let scrubs: [(&str, String); 6] = [
+        (
+            "discussion_posts",
+            format!("UPDATE discussion_posts SET content = '{}' WHERE author_id = $1", ERASED_TEXT),
+        ),
+        (
+            "discussion_threads",
+            format!(
+                "UPDATE discussion_threads SET title = '{0}', content = '{0}' WHERE author_id = $1",
+                ERASED_TEXT
+            ),
+        ),
+        (
+            "course_submissions",
+            format!("UPDATE course_submissions SET content = '{}' WHERE user_id = $1", ERASED_TEXT),
+        ),
+        (
+            "ai_usage_logs",
+            "UPDATE ai_usage_logs SET prompt = NULL, response = NULL, request_metadata = '{}'::jsonb WHERE user_id = $1"
+                .to_string(),
+        ),
+        (
+            "issued_certificates",
+            "UPDATE issued_certificates SET certificate_html = '', metadata = '{}'::jsonb WHERE user_id = $1"
+                .to_string(),
+        ),
+        (
+            // El actor de xAPI lleva nombre y correo: se reemplaza por una cuenta seudónima
+            "xapi_statements",
+            r#"UPDATE xapi_statements
+               SET raw_statement = jsonb_set(raw_statement, '{actor}', jsonb_build_object(
+                   'objectType', 'Agent',
+                   'account', jsonb_build_object('homePage', 'urn:openccb:erased', 'name', user_id::text)))
+               WHERE user_id = $1 AND raw_statement ? 'actor'"#
+                .to_string(),
+        ),
+    ];
+    for (table, sql) in scrubs.iter() {
+        let n = exec_count(&mut tx, sql, user_id).await?;
+        result.insert(format!("scrubbed.{}", table), json!(n));
+    }
+
+    let n = exec_count(
+        &mut tx,
+        r#"
+        UPDATE users
+        SET email = 'erased+' || id || '@invalid',
+            full_name = 'Usuario eliminado',
+            password_hash = '!',
+            avatar_url = NULL,
+            bio = NULL,
+            linkedin_url = NULL,
+            github_url = NULL,
+            is_public_profile = FALSE,
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+        user_id,
+    )
+    .await?;
+    result.insert("pseudonymized.users".to_string(), json!(n));
+
+    tx.commit().await?;
+    Ok(Value::Object(result))
+}
+
+async fn erase_cms_data(request: &DsarRequest) -> anyhow::Result<Value> {
+    let cms_url = std::env::var("CMS_API_URL").unwrap_or_else(|_| "http://studio:3001".to_string());
+    let token = cms_token(request)?;
+    let user_id = request.user_id;
+
+    let res = reqwest::Client::new()
+        .post(format!("{}/dsar/users/{}/erase", cms_url.trim_end_matches('/'), user_id))
+        .bearer_auth(token)
+        .timeout(std::time::Duration::from_secs(60))
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        anyhow::bail!("el CMS respondió {}", res.status());
+    }
+    Ok(res.json().await?)
+}
+
+async fn run_erasure(pool: &PgPool, request: &DsarRequest) -> anyhow::Result<Value> {
+    // Primero el CMS: si falla, el LMS queda intacto y la solicitud se puede reintentar
+    let cms = erase_cms_data(request).await?;
+    let lms = erase_user_data(pool, request.user_id).await?;
+    Ok(json!({ "lms": lms, "cms": cms }))
+}
+
+// ==================== Worker ====================
+
+async fn process_request(pool: &PgPool, request: DsarRequest) {
+    let _ = record_event(pool, &request, "processing", None, None).await;
+
+    let outcome = match request.request_type.as_str() {
+        "export" => run_export(pool, &request).await,
+        _ => run_erasure(pool, &request).await,
+    };
+
+    match outcome {
+        Ok(result) => {
+            let _ = sqlx::query(
+                "UPDATE dsar_requests SET status = 'completed', result = $2, error = NULL, completed_at = NOW() WHERE id = $1",
+            )
+            .bind(request.id)
+            .bind(&result)
+            .execute(pool)
+            .await;
+            let _ = record_event(pool, &request, "completed", None, None).await;
+        }
+        Err(e) => {
+            tracing::error!("La solicitud DSAR {} falló: {}", request.id, e);
+            let _ = sqlx::query("UPDATE dsar_requests SET status = 'failed', error = $2 WHERE id = $1")
+                .bind(request.id)
+                .bind(e.to_string())
+                .execute(pool)
+                .await;
+            let _ = record_event(pool, &request, "failed", None, Some(e.to_string())).await;
+        }
+    }
+}
+
+/// Procesa las solicitudes pendientes y borra las exportaciones vencidas.
+/// Toma la siguiente solicitud pendiente, o una que quedó en `processing` más de
+/// `STALE_PROCESSING_MINUTES` porque el worker que la tenía se cayó (si no, el
+/// índice de solicitudes abiertas bloquearía para siempre las nuevas del titular).
+/// Exportar y suprimir son idempotentes, así que repetirla es seguro.
+async fn claim_next_request(pool: &PgPool) -> Result<Option<DsarRequest>, sqlx::Error> {
+    sqlx::query_as::<_, DsarRequest>(
+        r#"
+        UPDATE dsar_requests SET status = 'processing', started_at = NOW()
+        WHERE id = (
+            SELECT id FROM dsar_requests
+            WHERE status = 'pending'
+               OR (status = 'processing' AND started_at < NOW() - make_interval(mins => $1))
+            ORDER BY created_at
+            FOR UPDATE SKIP LOCKED
+            LIMIT 1
+        )
+        RETURNING *
+        "#,
+    )
+    .bind(STALE_PROCESSING_MINUTES)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn process_dsar_queue(pool: PgPool) {
+    loop {
+        match claim_next_request(&pool).await {
+            Ok(Some(request)) => process_request(&pool, request).await,
+            Ok(None) => break,
+            Err(e) => {
+                tracing::error!("Error al leer la cola DSAR: {}", e);
+                break;
+            }
+        }
+    }
+
+    let expired = sqlx::query_as::<_, DsarRequest>(
+        "SELECT * FROM dsar_requests WHERE archive_path IS NOT NULL AND expires_at < NOW()",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap_or_default();
+    for request in expired {
+        if let Some(path) = &request.archive_path
+            && let Err(e) = tokio::fs::remove_file(path).await
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            tracing::error!("No se pudo borrar la exportación DSAR {}: {}", path, e);
+            continue;
+        }
+        let _ = sqlx::query("UPDATE dsar_requests SET archive_path = NULL WHERE id = $1")
+            .bind(request.id)
+            .execute(&pool)
+            .await;
+        let _ = record_event(&pool, &request, "expired", None, Some("Exportación eliminada".to_string())).await;
+    }
+}
+
+// ==================== Endpoints ====================
+
+#[derive(Debug, Deserialize)]
+pub struct CreateDsarPayload {
+    pub request_type: String,
+    /// Solo administradores: titular distinto del solicitante
+    pub user_id: Option<Uuid>,
+    pub reason: Option<String>,
+}
+
+/// POST /dsar/requests - Solicita la exportación o supresión de datos
+pub async fn create_dsar_request(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Json(payload): Json<CreateDsarPayload>,
+) -> Result<(StatusCode, Json<DsarRequest>), (StatusCode, String)> {
+    if payload.request_type != "export" && payload.request_type != "erasure" {
+        return Err((StatusCode::BAD_REQUEST, "Tipo de solicitud inválido".to_string()));
+    }
+
+    let is_admin = claims.role == "admin";
+    let user_id = payload.user_id.unwrap_or(claims.sub);
+    if user_id != claims.sub && !is_admin {
+        return Err((StatusCode::FORBIDDEN, "Solo puede solicitar sus propios datos".to_string()));
+    }
+
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1 AND organization_id = $2)")
+        .bind(user_id)
+        .bind(org_ctx.id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    if !exists {
+        return Err((StatusCode::NOT_FOUND, "Usuario no encontrado".to_string()));
+    }
+
+    // La supresión pedida por el propio titular la aprueba un administrador
+    let status = if payload.request_type == "erasure" && !is_admin {
+        "awaiting_approval"
+    } else {
+        "pending"
+    };
+
+    let request = sqlx::query_as::<_, DsarRequest>(
+        r#"
+        INSERT INTO dsar_requests (organization_id, user_id, request_type, status, requested_by, reason)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(org_ctx.id)
+    .bind(user_id)
+    .bind(&payload.request_type)
+    .bind(status)
+    .bind(claims.sub)
+    .bind(&payload.reason)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => (
+            StatusCode::CONFLICT,
+            "Ya existe una solicitud abierta de este tipo".to_string(),
+        ),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()),
+    })?;
+
+    let _ = record_event(&pool, &request, status, Some(claims.sub), payload.reason.clone()).await;
+
+    Ok((StatusCode::CREATED, Json(request)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DsarListQuery {
+    pub user_id: Option<Uuid>,
+    pub status: Option<String>,
+}
+
+/// GET /dsar/requests - Solicitudes propias, o de toda la organización para administradores
+pub async fn list_dsar_requests(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Query(query): Query<DsarListQuery>,
+) -> Result<Json<Vec<DsarRequest>>, (StatusCode, String)> {
+    let user_filter = if claims.role == "admin" { query.user_id } else { Some(claims.sub) };
+
+    let requests = sqlx::query_as::<_, DsarRequest>(
+        r#"
+        SELECT * FROM dsar_requests
+        WHERE organization_id = $1
+          AND ($2::uuid IS NULL OR user_id = $2)
+          AND ($3::text IS NULL OR status = $3)
+        ORDER BY created_at DESC
+        LIMIT 200
+        "#,
+    )
+    .bind(org_ctx.id)
+    .bind(user_filter)
+    .bind(&query.status)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(requests))
+}
+
+async fn load_visible_request(
+    pool: &PgPool,
+    org_id: Uuid,
+    claims: &Claims,
+    id: Uuid,
+) -> Result<DsarRequest, (StatusCode, String)> {
+    let request = sqlx::query_as::<_, DsarRequest>("SELECT * FROM dsar_requests WHERE id = $1 AND organization_id = $2")
+        .bind(id)
+        .bind(org_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Solicitud no encontrada".to_string()))?;
+
+    if claims.role != "admin" && request.user_id != claims.sub {
+        return Err((StatusCode::NOT_FOUND, "Solicitud no encontrada".to_string()));
+    }
+    Ok(request)
+}
+
+#[derive(Debug, Serialize)]
+pub struct DsarRequestDetail {
+    #[serde(flatten)]
+    pub request: DsarRequest,
+    pub events: Vec<DsarRequestEvent>,
+}
+
+/// GET /dsar/requests/{id} - Solicitud con su historial
+pub async fn get_dsar_request(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<DsarRequestDetail>, (StatusCode, String)> {
+    let request = load_visible_request(&pool, org_ctx.id, &claims, id).await?;
+
+    let events = sqlx::query_as::<_, DsarRequestEvent>(
+        "SELECT id, status, actor_id, detail, created_at FROM dsar_request_events WHERE request_id = $1 ORDER BY created_at",
+    )
+    .bind(id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(DsarRequestDetail { request, events }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DsarDecisionPayload {
+    pub approve: bool,
+    pub detail: Option<String>,
+}
+
+/// POST /dsar/requests/{id}/decision - Aprueba o rechaza una supresión (administradores)
+pub async fn decide_dsar_request(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<DsarDecisionPayload>,
+) -> Result<Json<DsarRequest>, (StatusCode, String)> {
+    if claims.role != "admin" {
+        return Err((StatusCode::FORBIDDEN, "Solo los administradores pueden decidir solicitudes".to_string()));
+    }
+
+    let status = if payload.approve { "pending" } else { "rejected" };
+    let request = sqlx::query_as::<_, DsarRequest>(
+        r#"
+        UPDATE dsar_requests SET status = $3, completed_at = CASE WHEN $3 = 'rejected' THEN NOW() END
+        WHERE id = $1 AND organization_id = $2 AND status = 'awaiting_approval'
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(org_ctx.id)
+    .bind(status)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+    .ok_or((StatusCode::CONFLICT, "La solicitud no está pendiente de aprobación".to_string()))?;
+
+    let event = if payload.approve { "approved" } else { "rejected" };
+    let _ = record_event(&pool, &request, event, Some(claims.sub), payload.detail).await;
+
+    Ok(Json(request))
+}
+
+/// POST /dsar/requests/{id}/retry - Vuelve a encolar una solicitud fallida (administradores)
+pub async fn retry_dsar_request(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<DsarRequest>, (StatusCode, String)> {
+    if claims.role != "admin" {
+        return Err((StatusCode::FORBIDDEN, "Solo los administradores pueden reintentar solicitudes".to_string()));
+    }
+
+    let request = sqlx::query_as::<_, DsarRequest>(
+        "UPDATE dsar_requests SET status = 'pending', error = NULL WHERE id = $1 AND organization_id = $2 AND status = 'failed' RETURNING *",
+    )
+    .bind(id)
+    .bind(org_ctx.id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+    .ok_or((StatusCode::CONFLICT, "Solo se pueden reintentar solicitudes fallidas".to_string()))?;
+
+    let _ = record_event(&pool, &request, "pending", Some(claims.sub), Some("Reintento".to_string())).await;
+
+    Ok(Json(request))
+}
+
+/// GET /dsar/requests/{id}/download - Descarga el ZIP de una exportación completada
+pub async fn download_dsar_export(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Response, (StatusCode, String)> {
+    let request = load_visible_request(&pool, org_ctx.id, &claims, id).await?;
+
+    if request.request_type != "export" || request.status != "completed" {
+        return Err((StatusCode::CONFLICT, "La exportación no está disponible".to_string()));
+    }
+    let path = request
+        .archive_path
+        .as_ref()
+        .filter(|_| request.expires_at.is_none_or(|at| at > Utc::now()))
+        .ok_or((StatusCode::GONE, "La exportación ha caducado".to_string()))?;
+
+    let bytes = tokio::fs::read(path).await.map_err(|e| {
+        tracing::error!("No se pudo leer la exportación DSAR {}: {}", path, e);
+        (StatusCode::GONE, "La exportación ha caducado".to_string())
+    })?;
+
+    let _ = record_event(&pool, &request, "downloaded", Some(claims.sub), None).await;
+
+    let disposition = format!("attachment; filename=\"datos-{}.zip\"", request.user_id);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from(bytes),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn erasure_scrubs_personal_data_and_keeps_aggregates() {
+        let Some(pool) = crate::db_util::test_pool(5).await else {
+            return;
+        };
+        let org_id = common::tenancy::DEFAULT_ORG_ID;
+        let user_id = Uuid::new_v4();
+
+        sqlx::query("INSERT INTO users (id, email, password_hash, full_name, organization_id, role, bio) VALUES ($1, $2, 'x', 'Ana Pérez', $3, 'student', 'bio')")
+            .bind(user_id)
+            .bind(format!("{}@example.com", user_id))
+            .bind(org_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO student_notes (user_id, lesson_id, content) SELECT $1, id, 'nota' FROM lessons LIMIT 1")
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO points_log (user_id, organization_id, amount, reason) VALUES ($1, $2, 10, 'test')")
+            .bind(user_id)
+            .bind(org_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let exported = collect_user_data(&pool, user_id).await.unwrap();
+        let profile = &exported.iter().find(|(name, _)| name == "profile").unwrap().1;
+        assert_eq!(profile["full_name"], "Ana Pérez");
+        assert!(profile.get("password_hash").is_none());
+
+        erase_user_data(&pool, user_id).await.unwrap();
+
+        let (name, bio): (String, Option<String>) = sqlx::query_as("SELECT full_name, bio FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((name.as_str(), bio), ("Usuario eliminado", None));
+        let notes: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM student_notes WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(notes, 0);
+        let points: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM points_log WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(points, 1);
+
+        sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(&pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn stale_processing_requests_are_reclaimed() {
+        let Some(pool) = crate::db_util::test_pool(5).await else {
+            return;
+        };
+        let org_id = common::tenancy::DEFAULT_ORG_ID;
+        let (stale_user, busy_user) = (Uuid::new_v4(), Uuid::new_v4());
+        // Solo estas dos solicitudes en `processing`: las pendientes de otras pruebas no interfieren
+        let mut ids = Vec::new();
+        for (user_id, minutes_ago) in [(stale_user, 120), (busy_user, 1)] {
+            let id: Uuid = sqlx::query_scalar(
+                r#"
+                INSERT INTO dsar_requests (organization_id, user_id, request_type, status, requested_by, started_at, created_at)
+                VALUES ($1, $2, 'export', 'processing', $2, NOW() - make_interval(mins => $3), NOW() - INTERVAL '1 day')
+                RETURNING id
+                "#,
+            )
+            .bind(org_id)
+            .bind(user_id)
+            .bind(minutes_ago)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            ids.push(id);
+        }
+
+        let mut claimed = Vec::new();
+        while let Some(request) = claim_next_request(&pool).await.unwrap() {
+            if !ids.contains(&request.id) {
+                // Devolver las solicitudes ajenas a la prueba
+                sqlx::query("UPDATE dsar_requests SET status = 'pending' WHERE id = $1")
+                    .bind(request.id)
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+                break;
+            }
+            claimed.push(request);
+        }
+
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].user_id, stale_user);
+        assert!(claimed[0].started_at.unwrap() > chrono::Utc::now() - chrono::Duration::minutes(1));
+
+        sqlx::query("DELETE FROM dsar_requests WHERE id = ANY($1)")
+            .bind(&ids)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
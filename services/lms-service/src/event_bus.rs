@@ -0,0 +1,253 @@
+/// Bus de eventos en proceso alimentado por Postgres `LISTEN/NOTIFY`.
+///
+/// Los triggers de `fn_notify_realtime_event` publican en el canal `openccb_events`
+/// un JSON `{topic, data}`. Un único listener por instancia reenvía cada aviso a los
+/// suscriptores del tópico, de modo que los streams SSE solo consultan la BD cuando
+/// algo cambió en lugar de hacer polling por cliente.
+use axum::{Extension, Json, http::StatusCode};
+use common::auth::Claims;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::postgres::PgListener;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+pub const EVENT_CHANNEL: &str = "openccb_events";
+
+/// Intervalo de resincronización de seguridad para los streams, por si se pierde
+/// un aviso durante una reconexión del listener.
+pub const RESYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BusEvent {
+    pub topic: String,
+    #[serde(default)]
+    pub data: Value,
+}
+
+struct TopicEntry {
+    sender: broadcast::Sender<BusEvent>,
+    connections: usize,
+}
+
+#[derive(Clone, Default)]
+pub struct EventBus {
+    topics: Arc<Mutex<HashMap<String, TopicEntry>>>,
+}
+
+impl EventBus {
+    /// Suscribe una conexión a `topic`. La suscripción se da de baja al soltarla.
+    pub fn subscribe(&self, topic: impl Into<String>) -> Subscription {
+        let topic = topic.into();
+        let mut topics = self.topics.lock().unwrap();
+        let entry = topics.entry(topic.clone()).or_insert_with(|| TopicEntry {
+            sender: broadcast::channel(64).0,
+            connections: 0,
+        });
+        entry.connections += 1;
+
+        Subscription {
+            rx: entry.sender.subscribe(),
+            topic,
+            bus: self.clone(),
+        }
+    }
+
+    fn publish(&self, event: BusEvent) {
+        tracing::debug!("event_bus: {} {}", event.topic, event.data);
+        let topics = self.topics.lock().unwrap();
+        if let Some(entry) = topics.get(&event.topic) {
+            let _ = entry.sender.send(event);
+        }
+    }
+
+    /// Avisa a todos los tópicos que deben volver a consultar su estado.
+    fn publish_resync(&self) {
+        let topics = self.topics.lock().unwrap();
+        for (topic, entry) in topics.iter() {
+            let _ = entry.sender.send(BusEvent {
+                topic: topic.clone(),
+                data: serde_json::json!({ "resync": true }),
+            });
+        }
+    }
+
+    fn unsubscribe(&self, topic: &str) {
+        let mut topics = self.topics.lock().unwrap();
+        let remove = match topics.get_mut(topic) {
+            Some(entry) => {
+                entry.connections = entry.connections.saturating_sub(1);
+                entry.connections == 0
+            }
+            None => false,
+        };
+        if remove {
+            topics.remove(topic);
+        }
+    }
+
+    pub fn stats(&self) -> EventBusStats {
+        let topics = self.topics.lock().unwrap();
+        let mut by_kind: BTreeMap<String, usize> = BTreeMap::new();
+        let mut per_topic: Vec<TopicStats> = topics
+            .iter()
+            .map(|(topic, entry)| {
+                let kind = topic.split(':').next().unwrap_or(topic).to_string();
+                *by_kind.entry(kind).or_default() += entry.connections;
+                TopicStats {
+                    topic: topic.clone(),
+                    connections: entry.connections,
+                }
+            })
+            .collect();
+        per_topic.sort_by(|a, b| b.connections.cmp(&a.connections).then(a.topic.cmp(&b.topic)));
+
+        EventBusStats {
+            total_connections: by_kind.values().sum(),
+            by_kind,
+            topics: per_topic,
+        }
+    }
+}
+
+pub struct Subscription {
+    rx: broadcast::Receiver<BusEvent>,
+    topic: String,
+    bus: EventBus,
+}
+
+impl Subscription {
+    /// Espera el próximo cambio del tópico o, como máximo, `RESYNC_INTERVAL`.
+    /// Un receptor rezagado también cuenta como cambio: el stream vuelve a consultar.
+    /// Los avisos acumulados se agrupan en un único cambio.
+    pub async fn next_change(&mut self) {
+        let _ = tokio::time::timeout(RESYNC_INTERVAL, self.rx.recv()).await;
+        while self.rx.try_recv().is_ok() {}
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.bus.unsubscribe(&self.topic);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopicStats {
+    pub topic: String,
+    pub connections: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventBusStats {
+    pub total_connections: usize,
+    pub by_kind: BTreeMap<String, usize>,
+    pub topics: Vec<TopicStats>,
+}
+
+/// Inicia el listener de `LISTEN openccb_events` con reconexión automática.
+pub fn spawn_listener(database_url: String, bus: EventBus) {
+    tokio::spawn(async move {
+        loop {
+            match run_listener(&database_url, &bus).await {
+                Ok(()) => tracing::warn!("event_bus: listener connection closed, reconnecting"),
+                Err(e) => tracing::error!("event_bus: listener error: {}", e),
+            }
+            // Los avisos emitidos durante la desconexión se perdieron
+            bus.publish_resync();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    });
+}
+
+async fn run_listener(database_url: &str, bus: &EventBus) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect(database_url).await?;
+    listener.listen(EVENT_CHANNEL).await?;
+    tracing::info!("event_bus: escuchando el canal {}", EVENT_CHANNEL);
+
+    while let Some(notification) = listener.try_recv().await? {
+        match serde_json::from_str::<BusEvent>(notification.payload()) {
+            Ok(event) => bus.publish(event),
+            Err(e) => tracing::warn!(
+                "event_bus: payload inválido en {}: {}",
+                notification.channel(),
+                e
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// GET /realtime/stats
+/// Conexiones SSE activas por tópico en esta instancia.
+pub async fn get_event_bus_stats(
+    claims: Claims,
+    Extension(bus): Extension<EventBus>,
+) -> Result<Json<EventBusStats>, StatusCode> {
+    if claims.role != "admin" {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(Json(bus.stats()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(topic: &str) -> BusEvent {
+        BusEvent {
+            topic: topic.to_string(),
+            data: serde_json::json!({ "seq": 1 }),
+        }
+    }
+
+    #[test]
+    fn connections_are_counted_per_topic_and_released_on_drop() {
+        let bus = EventBus::default();
+        let first = bus.subscribe("canvas-ops:a");
+        let second = bus.subscribe("canvas-ops:a");
+        let other = bus.subscribe("notifications:u");
+
+        let stats = bus.stats();
+        assert_eq!(stats.total_connections, 3);
+        assert_eq!(stats.by_kind["canvas-ops"], 2);
+        assert_eq!(stats.topics[0].topic, "canvas-ops:a");
+        assert_eq!(stats.topics[0].connections, 2);
+
+        drop(first);
+        assert_eq!(bus.stats().by_kind["canvas-ops"], 1);
+
+        // El tópico desaparece con la última conexión
+        drop(second);
+        drop(other);
+        let stats = bus.stats();
+        assert_eq!(stats.total_connections, 0);
+        assert!(stats.topics.is_empty());
+    }
+
+    #[tokio::test]
+    async fn events_reach_only_their_topic_and_are_coalesced() {
+        let bus = EventBus::default();
+        let mut canvas = bus.subscribe("canvas:a");
+        let mut doc = bus.subscribe("doc:a");
+
+        bus.publish(event("canvas:a"));
+        bus.publish(event("canvas:a"));
+        bus.publish(event("sin-suscriptores"));
+
+        tokio::time::timeout(Duration::from_secs(1), canvas.next_change())
+            .await
+            .expect("el aviso del tópico llega");
+        // Los dos avisos acumulados cuentan como un único cambio
+        assert!(canvas.rx.try_recv().is_err());
+        assert!(doc.rx.try_recv().is_err());
+
+        bus.publish_resync();
+        tokio::time::timeout(Duration::from_secs(1), doc.next_change())
+            .await
+            .expect("la resincronización llega a todos los tópicos");
+    }
+}
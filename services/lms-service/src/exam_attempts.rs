@@ -0,0 +1,1000 @@
+/// Exámenes con tiempo e intentos controlados por el servidor.
+///
+/// Una lección es un examen cuando `metadata.exam` existe:
+/// `{ "time_limit_minutes": 60, "available_from": "...", "available_until": "..." }`
+/// (sin `time_limit_minutes` se usa el `duration_minutes` de la plantilla).
+///
+/// - Cada intento es un registro con inicio y plazo calculado en el servidor, que
+///   incluye el tiempo extra del alumno (`exam_accommodations`) y no supera el cierre.
+/// - Un solo intento en curso y una sola sesión activa (cabecera `X-Exam-Session`).
+/// - La entrega sigue pasando por `POST /grades`, que exige un intento vigente.
+/// - Al vencer el plazo, `expire_overdue_attempts` entrega las respuestas autoguardadas,
+///   calificadas en el servidor (el puntaje autoguardado por el cliente no se usa).
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::{DateTime, Duration, Utc};
+use common::{auth::Claims, middleware::Org};
+use rand::distributions::Alphanumeric;
+use rand::{Rng, thread_rng};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Margen para la latencia de red al entregar o autoguardar.
+const SUBMISSION_GRACE_SECONDS: i64 = 30;
+const SESSION_HEADER: &str = "x-exam-session";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExamConfig {
+    pub time_limit_minutes: Option<i64>,
+    pub available_from: Option<DateTime<Utc>>,
+    pub available_until: Option<DateTime<Utc>>,
+}
+
+impl ExamConfig {
+    pub fn from_metadata(metadata: &Value) -> Option<Self> {
+        let exam = metadata.get("exam")?.as_object()?;
+        let date = |key: &str| {
+            exam.get(key)
+                .and_then(|v| v.as_str())
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|d| d.with_timezone(&Utc))
+        };
+        Some(ExamConfig {
+            time_limit_minutes: exam
+                .get("time_limit_minutes")
+                .or_else(|| metadata.get("duration_minutes"))
+                .and_then(|v| v.as_i64())
+                .filter(|minutes| *minutes > 0),
+            available_from: date("available_from"),
+            available_until: date("available_until"),
+        })
+    }
+
+    /// Plazo de un intento: límite de tiempo ampliado por la adecuación, sin superar
+    /// el cierre del examen.
+    pub fn deadline(&self, started_at: DateTime<Utc>, extra_time_percent: i32) -> Option<DateTime<Utc>> {
+        let limit = self.time_limit_minutes.map(|minutes| {
+            let seconds = minutes * 60 * (100 + extra_time_percent as i64) / 100;
+            started_at + Duration::seconds(seconds)
+        });
+        match (limit, self.available_until) {
+            (Some(limit), Some(until)) => Some(limit.min(until)),
+            (limit, until) => limit.or(until),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ExamAttempt {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub user_id: Uuid,
+    pub course_id: Uuid,
+    pub lesson_id: Uuid,
+    pub status: String,
+    #[serde(skip_serializing)]
+    pub session_token: Option<String>,
+    pub extra_time_percent: i32,
+    pub started_at: DateTime<Utc>,
+    pub deadline_at: Option<DateTime<Utc>>,
+    pub saved_answers: Option<Value>,
+    pub saved_at: Option<DateTime<Utc>>,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub auto_submitted: bool,
+    pub reopened_by: Option<Uuid>,
+    pub reopened_at: Option<DateTime<Utc>>,
+    pub reopen_count: i32,
+}
+
+impl ExamAttempt {
+    fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        self.deadline_at
+            .is_some_and(|deadline| now > deadline + Duration::seconds(SUBMISSION_GRACE_SECONDS))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExamAttemptView {
+    #[serde(flatten)]
+    pub attempt: ExamAttempt,
+    pub remaining_seconds: Option<i64>,
+    pub server_time: DateTime<Utc>,
+}
+
+impl From<ExamAttempt> for ExamAttemptView {
+    fn from(attempt: ExamAttempt) -> Self {
+        let now = Utc::now();
+        let remaining_seconds = match attempt.status.as_str() {
+            "in_progress" => attempt.deadline_at.map(|d| (d - now).num_seconds().max(0)),
+            _ => None,
+        };
+        ExamAttemptView { attempt, remaining_seconds, server_time: now }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExamSessionResponse {
+    #[serde(flatten)]
+    pub attempt: ExamAttemptView,
+    /// Se envía en `X-Exam-Session` al autoguardar y al entregar
+    pub session_token: String,
+}
+
+struct ExamLesson {
+    course_id: Uuid,
+    config: ExamConfig,
+    max_attempts: Option<i32>,
+}
+
+async fn load_exam_lesson(pool: &PgPool, org_id: Uuid, lesson_id: Uuid) -> Result<ExamLesson, (StatusCode, String)> {
+    let lesson: Option<(Uuid, Option<Value>, Option<i32>)> = sqlx::query_as(
+        "SELECT m.course_id, l.metadata, l.max_attempts FROM lessons l JOIN modules m ON m.id = l.module_id WHERE l.id = $1 AND l.organization_id = $2",
+    )
+    .bind(lesson_id)
+    .bind(org_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let (course_id, metadata, max_attempts) =
+        lesson.ok_or((StatusCode::NOT_FOUND, "Lección no encontrada".to_string()))?;
+    let config = metadata
+        .as_ref()
+        .and_then(ExamConfig::from_metadata)
+        .ok_or((StatusCode::BAD_REQUEST, "La lección no es un examen con tiempo".to_string()))?;
+    Ok(ExamLesson { course_id, config, max_attempts })
+}
+
+fn new_session_token() -> String {
+    thread_rng().sample_iter(&Alphanumeric).take(48).map(char::from).collect()
+}
+
+async fn extra_time_percent(pool: &PgPool, user_id: Uuid, course_id: Uuid, lesson_id: Uuid) -> Result<i32, sqlx::Error> {
+    // La adecuación específica del examen prevalece sobre la del curso
+    let percent: Option<i32> = sqlx::query_scalar(
+        r#"
+        SELECT extra_time_percent FROM exam_accommodations
+        WHERE user_id = $1 AND course_id = $2 AND (lesson_id = $3 OR lesson_id IS NULL)
+        ORDER BY lesson_id NULLS LAST
+        LIMIT 1
+        "#,
+    )
+    .bind(user_id)
+    .bind(course_id)
+    .bind(lesson_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(percent.unwrap_or(0))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct StartExamPayload {
+    /// Toma el intento en curso desde esta sesión, invalidando la anterior
+    #[serde(default)]
+    pub takeover: bool,
+}
+
+/// POST /lessons/{id}/exam-attempts - Inicia el examen o retoma el intento en curso
+pub async fn start_exam_attempt(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(lesson_id): Path<Uuid>,
+    payload: Option<Json<StartExamPayload>>,
+) -> Result<Json<ExamSessionResponse>, (StatusCode, String)> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    let lesson = load_exam_lesson(&pool, org_ctx.id, lesson_id).await?;
+
+    let enrolled: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM enrollments WHERE user_id = $1 AND course_id = $2)")
+            .bind(claims.sub)
+            .bind(lesson.course_id)
+            .fetch_one(&pool)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    if !enrolled {
+        return Err((StatusCode::FORBIDDEN, "No estás inscrito en este curso".to_string()));
+    }
+
+    let now = Utc::now();
+    let existing: Option<ExamAttempt> = sqlx::query_as(
+        "SELECT * FROM exam_attempts WHERE user_id = $1 AND lesson_id = $2 AND status = 'in_progress'",
+    )
+    .bind(claims.sub)
+    .bind(lesson_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    if let Some(attempt) = existing {
+        if attempt.is_overdue(now) {
+            expire_attempt(&pool, attempt.id).await?;
+        } else if attempt.session_token.is_some() && !payload.takeover {
+            return Err((
+                StatusCode::CONFLICT,
+                "Ya tienes un intento en curso en otra sesión".to_string(),
+            ));
+        } else {
+            let token = new_session_token();
+            let attempt: ExamAttempt =
+                sqlx::query_as("UPDATE exam_attempts SET session_token = $2 WHERE id = $1 RETURNING *")
+                    .bind(attempt.id)
+                    .bind(&token)
+                    .fetch_one(&pool)
+                    .await
+                    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+            return Ok(Json(ExamSessionResponse { attempt: attempt.into(), session_token: token }));
+        }
+    }
+
+    if lesson.config.available_from.is_some_and(|from| now < from) {
+        return Err((StatusCode::FORBIDDEN, "El examen aún no está disponible".to_string()));
+    }
+    if lesson.config.available_until.is_some_and(|until| now >= until) {
+        return Err((StatusCode::FORBIDDEN, "El periodo del examen ya terminó".to_string()));
+    }
+
+    let finished: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM exam_attempts WHERE user_id = $1 AND lesson_id = $2 AND status <> 'in_progress'",
+    )
+    .bind(claims.sub)
+    .bind(lesson_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    if lesson.max_attempts.is_some_and(|max| finished >= max as i64) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Se ha alcanzado el número máximo de intentos para esta evaluación".to_string(),
+        ));
+    }
+
+    let extra = extra_time_percent(&pool, claims.sub, lesson.course_id, lesson_id)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let token = new_session_token();
+    let attempt: ExamAttempt = sqlx::query_as(
+        r#"
+        INSERT INTO exam_attempts
+            (organization_id, user_id, course_id, lesson_id, session_token, extra_time_percent, started_at, deadline_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING *
+        "#,
+    )
+    .bind(org_ctx.id)
+    .bind(claims.sub)
+    .bind(lesson.course_id)
+    .bind(lesson_id)
+    .bind(&token)
+    .bind(extra)
+    .bind(now)
+    .bind(lesson.config.deadline(now, extra))
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => (
+            StatusCode::CONFLICT,
+            "Ya tienes un intento en curso en otra sesión".to_string(),
+        ),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()),
+    })?;
+
+    Ok(Json(ExamSessionResponse { attempt: attempt.into(), session_token: token }))
+}
+
+/// GET /exam-attempts/{id} - Estado y tiempo restante (alumno propietario o personal)
+pub async fn get_exam_attempt(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(attempt_id): Path<Uuid>,
+) -> Result<Json<ExamAttemptView>, (StatusCode, String)> {
+    let attempt: ExamAttempt = sqlx::query_as("SELECT * FROM exam_attempts WHERE id = $1 AND organization_id = $2")
+        .bind(attempt_id)
+        .bind(org_ctx.id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Intento no encontrado".to_string()))?;
+
+    if attempt.user_id != claims.sub {
+        crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, attempt.course_id).await?;
+    }
+    Ok(Json(attempt.into()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SaveExamAnswersPayload {
+    pub score: Option<f32>,
+    /// Mismo formato que la entrega (`item_responses`, etc.)
+    pub metadata: Option<Value>,
+}
+
+/// PUT /exam-attempts/{id}/answers - Autoguardado; es lo que se entrega si vence el plazo
+pub async fn save_exam_answers(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    headers: HeaderMap,
+    Path(attempt_id): Path<Uuid>,
+    Json(payload): Json<SaveExamAnswersPayload>,
+) -> Result<Json<ExamAttemptView>, (StatusCode, String)> {
+    let session = headers.get(SESSION_HEADER).and_then(|h| h.to_str().ok());
+    let attempt: ExamAttempt = sqlx::query_as(
+        "SELECT * FROM exam_attempts WHERE id = $1 AND organization_id = $2 AND user_id = $3",
+    )
+    .bind(attempt_id)
+    .bind(org_ctx.id)
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Intento no encontrado".to_string()))?;
+
+    check_open_session(&attempt, session)?;
+
+    let saved = serde_json::json!({ "score": payload.score, "metadata": payload.metadata });
+    let attempt: ExamAttempt = sqlx::query_as(
+        "UPDATE exam_attempts SET saved_answers = $2, saved_at = NOW() WHERE id = $1 AND status = 'in_progress' RETURNING *",
+    )
+    .bind(attempt.id)
+    .bind(&saved)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+    .ok_or((StatusCode::CONFLICT, "El intento ya fue entregado".to_string()))?;
+
+    Ok(Json(attempt.into()))
+}
+
+fn check_open_session(attempt: &ExamAttempt, session: Option<&str>) -> Result<(), (StatusCode, String)> {
+    if attempt.status != "in_progress" {
+        return Err((StatusCode::CONFLICT, "El intento ya fue entregado".to_string()));
+    }
+    if session.is_none() || attempt.session_token.as_deref() != session {
+        return Err((
+            StatusCode::CONFLICT,
+            "El examen se abrió en otra sesión; esta sesión ya no es válida".to_string(),
+        ));
+    }
+    if attempt.is_overdue(Utc::now()) {
+        return Err((StatusCode::FORBIDDEN, "El tiempo del examen terminó".to_string()));
+    }
+    Ok(())
+}
+
+/// Llamado por `submit_lesson_score` antes de registrar la calificación. En un examen,
+/// la entrega del alumno debe indicar `metadata.exam_attempt_id` y la sesión activa;
+/// el intento queda entregado en la misma transacción. Devuelve `true` si la entrega
+/// corresponde a un intento de examen (que ya validó el número de intentos al iniciar).
+pub async fn authorize_exam_submission(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    claims: &Claims,
+    headers: &HeaderMap,
+    user_id: Uuid,
+    lesson_id: Uuid,
+    metadata: Option<&Value>,
+) -> Result<bool, (StatusCode, String)> {
+    let lesson_metadata: Option<Value> = sqlx::query_scalar("SELECT metadata FROM lessons WHERE id = $1")
+        .bind(lesson_id)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+        .flatten();
+    if lesson_metadata.as_ref().and_then(ExamConfig::from_metadata).is_none() {
+        return Ok(false);
+    }
+    // Calificación manual de un instructor sobre otro usuario
+    if user_id != claims.sub && (claims.role == "admin" || claims.role == "instructor") {
+        return Ok(false);
+    }
+
+    let attempt_id = metadata
+        .and_then(|m| m.get("exam_attempt_id"))
+        .and_then(|v| v.as_str())
+        .and_then(|s| Uuid::parse_str(s).ok())
+        .ok_or((
+            StatusCode::FORBIDDEN,
+            "Esta evaluación requiere iniciar un intento de examen".to_string(),
+        ))?;
+
+    let attempt: ExamAttempt = sqlx::query_as(
+        "SELECT * FROM exam_attempts WHERE id = $1 AND user_id = $2 AND lesson_id = $3 FOR UPDATE",
+    )
+    .bind(attempt_id)
+    .bind(user_id)
+    .bind(lesson_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Intento no encontrado".to_string()))?;
+
+    check_open_session(&attempt, headers.get(SESSION_HEADER).and_then(|h| h.to_str().ok()))?;
+
+    sqlx::query("UPDATE exam_attempts SET status = 'submitted', submitted_at = NOW(), session_token = NULL WHERE id = $1")
+        .bind(attempt.id)
+        .execute(&mut **tx)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(true)
+}
+
+/// Para puntajes de bloque calculados en el servidor durante un examen: exige un
+/// intento en curso y dentro de plazo, sin entregarlo. Devuelve su ID, o `None` si
+/// la lección no es un examen.
+pub async fn require_open_attempt(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: Uuid,
+    lesson_id: Uuid,
+    lesson_metadata: Option<&Value>,
+) -> Result<Option<Uuid>, (StatusCode, String)> {
+    if lesson_metadata.and_then(ExamConfig::from_metadata).is_none() {
+        return Ok(None);
+    }
+    let attempt: ExamAttempt = sqlx::query_as(
+        "SELECT * FROM exam_attempts WHERE user_id = $1 AND lesson_id = $2 AND status = 'in_progress' FOR UPDATE",
+    )
+    .bind(user_id)
+    .bind(lesson_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+    .ok_or((
+        StatusCode::FORBIDDEN,
+        "Esta evaluación requiere iniciar un intento de examen".to_string(),
+    ))?;
+    if attempt.is_overdue(Utc::now()) {
+        return Err((StatusCode::FORBIDDEN, "El tiempo del examen terminó".to_string()));
+    }
+    Ok(Some(attempt.id))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReopenExamPayload {
+    /// Minutos disponibles desde la reapertura; por defecto, el límite del examen
+    pub extra_minutes: Option<i64>,
+}
+
+/// POST /exam-attempts/{id}/reopen - Reabre un intento entregado o vencido (instructor/admin)
+pub async fn reopen_exam_attempt(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(attempt_id): Path<Uuid>,
+    Json(payload): Json<ReopenExamPayload>,
+) -> Result<Json<ExamAttemptView>, (StatusCode, String)> {
+    let attempt: ExamAttempt = sqlx::query_as("SELECT * FROM exam_attempts WHERE id = $1 AND organization_id = $2")
+        .bind(attempt_id)
+        .bind(org_ctx.id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Intento no encontrado".to_string()))?;
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, attempt.course_id).await?;
+
+    if attempt.status == "in_progress" {
+        return Err((StatusCode::CONFLICT, "El intento sigue en curso".to_string()));
+    }
+    if payload.extra_minutes.is_some_and(|m| m <= 0) {
+        return Err((StatusCode::BAD_REQUEST, "Los minutos deben ser positivos".to_string()));
+    }
+
+    let lesson = load_exam_lesson(&pool, org_ctx.id, attempt.lesson_id).await?;
+    let now = Utc::now();
+    // La reapertura no queda limitada por el cierre del examen
+    let deadline = match payload.extra_minutes {
+        Some(minutes) => Some(now + Duration::minutes(minutes)),
+        None => ExamConfig { available_until: None, ..lesson.config }.deadline(now, attempt.extra_time_percent),
+    };
+
+    let attempt: ExamAttempt = sqlx::query_as(
+        r#"
+        UPDATE exam_attempts
+        SET status = 'in_progress',
+            session_token = NULL,
+            deadline_at = $2,
+            submitted_at = NULL,
+            auto_submitted = false,
+            reopened_by = $3,
+            reopened_at = NOW(),
+            reopen_count = reopen_count + 1
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(attempt.id)
+    .bind(deadline)
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => (
+            StatusCode::CONFLICT,
+            "El alumno ya tiene otro intento en curso".to_string(),
+        ),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()),
+    })?;
+
+    tracing::info!("Intento de examen {} reabierto por {}", attempt.id, claims.sub);
+    Ok(Json(attempt.into()))
+}
+
+/// GET /lessons/{id}/exam-attempts - Intentos del examen (instructor/admin)
+pub async fn list_exam_attempts(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(lesson_id): Path<Uuid>,
+) -> Result<Json<Vec<ExamAttemptView>>, (StatusCode, String)> {
+    let lesson = load_exam_lesson(&pool, org_ctx.id, lesson_id).await?;
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, lesson.course_id).await?;
+
+    let attempts = sqlx::query_as::<_, ExamAttempt>(
+        "SELECT * FROM exam_attempts WHERE lesson_id = $1 AND organization_id = $2 ORDER BY started_at DESC",
+    )
+    .bind(lesson_id)
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(attempts.into_iter().map(ExamAttemptView::from).collect()))
+}
+
+// ─── Adecuaciones de tiempo extra ────────────────────────────────────────────
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ExamAccommodation {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub course_id: Uuid,
+    pub lesson_id: Option<Uuid>,
+    pub extra_time_percent: i32,
+    pub notes: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpsertAccommodationPayload {
+    /// 50 = 50% de tiempo adicional
+    pub extra_time_percent: i32,
+    /// Limita la adecuación a un examen; si se omite aplica a todo el curso
+    pub lesson_id: Option<Uuid>,
+    pub notes: Option<String>,
+}
+
+/// GET /courses/{id}/exam-accommodations
+pub async fn list_exam_accommodations(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+) -> Result<Json<Vec<ExamAccommodation>>, (StatusCode, String)> {
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+
+    let accommodations = sqlx::query_as::<_, ExamAccommodation>(
+        r#"
+        SELECT id, user_id, course_id, lesson_id, extra_time_percent, notes, created_by, created_at, updated_at
+        FROM exam_accommodations
+        WHERE course_id = $1 AND organization_id = $2
+        ORDER BY created_at
+        "#,
+    )
+    .bind(course_id)
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(accommodations))
+}
+
+/// PUT /courses/{id}/exam-accommodations/{user_id} - Crea o actualiza el tiempo extra
+/// de un alumno. Aplica a los intentos que se inicien después.
+pub async fn upsert_exam_accommodation(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path((course_id, user_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpsertAccommodationPayload>,
+) -> Result<Json<ExamAccommodation>, (StatusCode, String)> {
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+    if !(0..=400).contains(&payload.extra_time_percent) {
+        return Err((StatusCode::BAD_REQUEST, "El tiempo extra debe estar entre 0% y 400%".to_string()));
+    }
+
+    let enrolled: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM enrollments WHERE user_id = $1 AND course_id = $2 AND organization_id = $3)",
+    )
+    .bind(user_id)
+    .bind(course_id)
+    .bind(org_ctx.id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    if !enrolled {
+        return Err((StatusCode::NOT_FOUND, "El alumno no está inscrito en este curso".to_string()));
+    }
+    if let Some(lesson_id) = payload.lesson_id {
+        let lesson = load_exam_lesson(&pool, org_ctx.id, lesson_id).await?;
+        if lesson.course_id != course_id {
+            return Err((StatusCode::BAD_REQUEST, "La lección no pertenece a este curso".to_string()));
+        }
+    }
+
+    let accommodation = sqlx::query_as::<_, ExamAccommodation>(
+        r#"
+        INSERT INTO exam_accommodations (organization_id, user_id, course_id, lesson_id, extra_time_percent, notes, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (user_id, course_id, COALESCE(lesson_id, '00000000-0000-0000-0000-000000000000'::uuid))
+        DO UPDATE SET extra_time_percent = EXCLUDED.extra_time_percent,
+                      notes = EXCLUDED.notes,
+                      updated_at = NOW()
+        RETURNING id, user_id, course_id, lesson_id, extra_time_percent, notes, created_by, created_at, updated_at
+        "#,
+    )
+    .bind(org_ctx.id)
+    .bind(user_id)
+    .bind(course_id)
+    .bind(payload.lesson_id)
+    .bind(payload.extra_time_percent)
+    .bind(&payload.notes)
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error al guardar la adecuación de examen: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+    })?;
+
+    Ok(Json(accommodation))
+}
+
+/// DELETE /exam-accommodations/{id}
+pub async fn delete_exam_accommodation(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let course_id: Uuid =
+        sqlx::query_scalar("SELECT course_id FROM exam_accommodations WHERE id = $1 AND organization_id = $2")
+            .bind(id)
+            .bind(org_ctx.id)
+            .fetch_optional(&pool)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+            .ok_or((StatusCode::NOT_FOUND, "Adecuación no encontrada".to_string()))?;
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+
+    sqlx::query("DELETE FROM exam_accommodations WHERE id = $1")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ─── Entrega automática al vencer el plazo ───────────────────────────────────
+
+/// Posiciones elegidas en las respuestas autoguardadas para la pregunta `item_id` del
+/// bloque: `quiz_answers[bloque].answers[pregunta]` (reproductor de cuestionarios) o la
+/// entrada de `item_responses` con ese `item_id` (y ese `block_id`, si lo indica).
+fn saved_selection(saved_metadata: Option<&Value>, block_id: &str, item_id: &str) -> Option<Vec<i64>> {
+    let saved_metadata = saved_metadata?;
+    if let Some(selected) = saved_metadata
+        .get("quiz_answers")
+        .and_then(|a| a.get(block_id))
+        .and_then(|b| b.get("answers"))
+        .and_then(|a| a.get(item_id))
+    {
+        return Some(crate::psychometrics::value_indices(selected));
+    }
+    saved_metadata
+        .get("item_responses")
+        .and_then(|r| r.as_array())?
+        .iter()
+        .find(|r| {
+            r.get("item_id").and_then(|v| v.as_str()) == Some(item_id)
+                && r.get("block_id").and_then(|v| v.as_str()).is_none_or(|b| b == block_id)
+        })
+        .and_then(|r| r.get("selected"))
+        .map(crate::psychometrics::value_indices)
+}
+
+/// Puntaje (0-1) de cada bloque de cuestionario con las respuestas correctas de la
+/// lección, por puntos como el reproductor. El puntaje que calculó el cliente no se usa:
+/// las preguntas sin responder o sin respuesta correcta conocida cuentan como fallo, y
+/// los demás bloques que solo corrige el navegador quedan sin puntaje (cuentan 0).
+fn quiz_block_scores(blocks: &[&Value], saved_metadata: Option<&Value>) -> serde_json::Map<String, Value> {
+    let mut scores = serde_json::Map::new();
+    for block in blocks {
+        let (Some(block_id), Some(questions)) = (
+            block.get("id").and_then(|v| v.as_str()),
+            block
+                .get("quiz_data")
+                .and_then(|q| q.get("questions"))
+                .and_then(|q| q.as_array()),
+        ) else {
+            continue;
+        };
+        let mut earned = 0i64;
+        let mut total = 0i64;
+        for question in questions {
+            let points = question.get("points").and_then(|p| p.as_i64()).filter(|p| *p > 0).unwrap_or(1);
+            total += points;
+            let item_id = match question.get("id") {
+                Some(Value::String(s)) => s.clone(),
+                Some(Value::Number(n)) => n.to_string(),
+                _ => continue,
+            };
+            let mut expected = question
+                .get("correct")
+                .map(crate::psychometrics::value_indices)
+                .unwrap_or_default();
+            let mut selected = saved_selection(saved_metadata, block_id, &item_id).unwrap_or_default();
+            expected.sort_unstable();
+            selected.sort_unstable();
+            if !expected.is_empty() && selected == expected {
+                earned += points;
+            }
+        }
+        let score = if total > 0 { earned as f64 / total as f64 } else { 0.0 };
+        scores.insert(block_id.to_string(), serde_json::json!(score));
+    }
+    scores
+}
+
+fn internal_error(e: sqlx::Error) -> (StatusCode, String) {
+    tracing::error!("Error al entregar un intento de examen vencido: {}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+}
+
+/// Entrega un intento vencido con las mismas reglas que una entrega del alumno: se
+/// califica en el servidor (variantes guardadas en las lecciones con pool, respuestas
+/// correctas de la lección en las demás), los bloques corregidos en el servidor
+/// conservan su puntaje guardado y se aplica la política de entrega tardía al plazo
+/// del intento. Devuelve la calificación registrada, o `None` si no se registró.
+async fn expire_attempt(pool: &PgPool, attempt_id: Uuid) -> Result<Option<common::models::UserGrade>, (StatusCode, String)> {
+    let mut tx = pool.begin().await.map_err(internal_error)?;
+    let attempt: Option<ExamAttempt> = sqlx::query_as(
+        "SELECT * FROM exam_attempts WHERE id = $1 AND status = 'in_progress' FOR UPDATE SKIP LOCKED",
+    )
+    .bind(attempt_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+    let Some(attempt) = attempt else {
+        return Ok(None);
+    };
+    crate::db_util::set_session_context(
+        &mut tx,
+        Some(attempt.user_id),
+        Some(attempt.organization_id),
+        None,
+        None,
+        Some("EVENTO_DEL_SISTEMA".to_string()),
+    )
+    .await
+    .map_err(internal_error)?;
+
+    sqlx::query(
+        "UPDATE exam_attempts SET status = 'expired', submitted_at = NOW(), auto_submitted = true, session_token = NULL WHERE id = $1",
+    )
+    .bind(attempt.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+
+    let lesson: Option<(Option<Value>, Option<Value>)> =
+        sqlx::query_as("SELECT metadata, content_blocks FROM lessons WHERE id = $1")
+            .bind(attempt.lesson_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+    let (lesson_metadata, content_blocks) = lesson.unwrap_or_default();
+
+    // Las lecciones adaptativas solo se califican con su propio flujo; la política de
+    // entrega tardía se evalúa al vencer el plazo del intento
+    let adaptive = lesson_metadata
+        .as_ref()
+        .and_then(|m| m.get("delivery_mode"))
+        .and_then(|m| m.as_str())
+        == Some("adaptive");
+    let status = crate::late_policies::deadline_status(
+        &mut tx,
+        attempt.user_id,
+        attempt.lesson_id,
+        attempt.deadline_at.unwrap_or_else(Utc::now),
+    )
+    .await
+    .map_err(internal_error)?;
+    if adaptive || status.outcome == crate::late_policies::LateOutcome::Rejected {
+        tx.commit().await.map_err(internal_error)?;
+        return Ok(None);
+    }
+
+    let stored: Option<(i32, Option<Value>)> = sqlx::query_as(
+        "SELECT attempts_count, metadata FROM user_grades WHERE user_id = $1 AND lesson_id = $2 AND organization_id = $3 FOR UPDATE",
+    )
+    .bind(attempt.user_id)
+    .bind(attempt.lesson_id)
+    .bind(attempt.organization_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+    let (attempts_count, stored) = stored.unwrap_or_default();
+    let saved_metadata = attempt.saved_answers.as_ref().and_then(|s| s.get("metadata"));
+
+    let pool_lesson = !crate::quiz_variants::pool_blocks(lesson_metadata.as_ref(), content_blocks.as_ref()).is_empty();
+    let (score, mut metadata) = if pool_lesson {
+        // Las respuestas autoguardadas usan las posiciones mostradas en la variante
+        let mut variants =
+            crate::quiz_variants::lock_variants(&mut tx, attempt.user_id, attempt.lesson_id, attempts_count + 1)
+                .await
+                .map_err(internal_error)?;
+        variants.retain(|v| v.submitted_at.is_none());
+        let (earned, total, item_responses) =
+            crate::quiz_variants::grade_variants(&mut tx, &mut variants, |block_id, item_id| {
+                saved_selection(saved_metadata, block_id, item_id)
+            })
+            .await
+            .map_err(internal_error)?;
+        let score = if total > 0 { earned as f32 / total as f32 } else { 0.0 };
+        let metadata = serde_json::json!({
+            "quiz_variant_attempt": attempts_count + 1,
+            "points_earned": earned,
+            "points_total": total,
+            "item_responses": item_responses,
+        });
+        (score, metadata)
+    } else {
+        let blocks = crate::grading::lesson_blocks(lesson_metadata.as_ref(), content_blocks.as_ref());
+        let block_scores = quiz_block_scores(&blocks, saved_metadata);
+        let mut metadata = serde_json::json!({ "block_scores": block_scores });
+        if let Some(quiz_answers) = saved_metadata.and_then(|m| m.get("quiz_answers")) {
+            metadata["quiz_answers"] = quiz_answers.clone();
+        }
+        let plugin_types = crate::grading::plugin_block_types(&mut tx, attempt.organization_id)
+            .await
+            .map_err(internal_error)?;
+        crate::grading::protect_server_scores(&blocks, &plugin_types, Some(&metadata), stored.as_ref())
+            .unwrap_or_else(|| (crate::grading::lesson_score(&blocks, &block_scores), metadata))
+    };
+    metadata["exam_attempt_id"] = Value::String(attempt.id.to_string());
+    metadata["auto_submitted"] = Value::Bool(true);
+
+    let grade = crate::grading::upsert_grade(
+        &mut tx,
+        attempt.organization_id,
+        attempt.user_id,
+        attempt.course_id,
+        attempt.lesson_id,
+        score,
+        Some(&metadata),
+        Some(status.outcome.penalty()),
+    )
+    .await?;
+
+    crate::psychometrics::record_item_responses(
+        &mut tx,
+        attempt.organization_id,
+        attempt.user_id,
+        attempt.course_id,
+        attempt.lesson_id,
+        grade.attempts_count,
+        &metadata,
+        if pool_lesson { None } else { stored.as_ref() },
+    )
+    .await
+    .map_err(internal_error)?;
+    crate::spaced_repetition::enqueue_missed_items(
+        &mut tx,
+        attempt.organization_id,
+        attempt.user_id,
+        attempt.course_id,
+        attempt.lesson_id,
+        grade.attempts_count,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    tx.commit().await.map_err(internal_error)?;
+
+    crate::grading::notify_grade_recorded(
+        pool,
+        attempt.organization_id,
+        attempt.user_id,
+        attempt.course_id,
+        attempt.lesson_id,
+        score,
+        grade.attempts_count,
+    )
+    .await;
+    Ok(Some(grade))
+}
+
+/// Trabajo periódico: entrega los intentos cuyo plazo (más el margen) ya venció.
+pub async fn expire_overdue_attempts(pool: PgPool) {
+    let overdue: Vec<Uuid> = match sqlx::query_scalar(
+        "SELECT id FROM exam_attempts WHERE status = 'in_progress' AND deadline_at < NOW() - make_interval(secs => $1) LIMIT 200",
+    )
+    .bind(SUBMISSION_GRACE_SECONDS as f64)
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(ids) => ids,
+        Err(e) => {
+            tracing::error!("Error al buscar intentos de examen vencidos: {}", e);
+            return;
+        }
+    };
+
+    for attempt_id in overdue {
+        match expire_attempt(&pool, attempt_id).await {
+            Ok(_) => tracing::info!("Intento de examen {} entregado automáticamente", attempt_id),
+            Err((_, e)) => tracing::error!("Error al entregar el intento vencido {}: {}", attempt_id, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadline_applies_accommodation_and_closing_time() {
+        let metadata = serde_json::json!({
+            "duration_minutes": 60,
+            "exam": { "available_until": "2026-05-01T12:00:00Z" },
+        });
+        let config = ExamConfig::from_metadata(&metadata).unwrap();
+        assert_eq!(config.time_limit_minutes, Some(60));
+
+        let start = DateTime::parse_from_rfc3339("2026-05-01T10:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(config.deadline(start, 0), Some(start + Duration::minutes(60)));
+        assert_eq!(config.deadline(start, 50), Some(start + Duration::minutes(90)));
+        // El cierre del examen limita el tiempo extra
+        assert_eq!(config.deadline(start, 200), config.available_until);
+
+        assert!(ExamConfig::from_metadata(&serde_json::json!({ "duration_minutes": 60 })).is_none());
+    }
+
+    #[test]
+    fn saved_answers_are_scored_with_the_lesson_keys() {
+        let lesson = serde_json::json!({
+            "blocks": [
+                { "id": "b1", "type": "quiz", "quiz_data": { "questions": [
+                    { "id": "q1", "correct": [1], "points": 3 },
+                    { "id": "q2", "correct": [0, 2] },
+                ] } },
+                { "id": "b2", "type": "quiz", "quiz_data": { "questions": [{ "id": 7, "correct": [0] }] } },
+                { "id": "b3", "type": "matching" },
+            ]
+        });
+        // El puntaje del cliente (1.0) y sus block_scores se ignoran
+        let saved = serde_json::json!({
+            "quiz_answers": { "b1": { "answers": { "q1": [1], "q2": [2] } } },
+            "item_responses": [{ "item_id": "7", "selected": 0 }],
+            "block_scores": { "b1": 1.0, "b2": 1.0, "b3": 1.0 },
+        });
+        let blocks = crate::grading::lesson_blocks(Some(&lesson), None);
+        let scores = quiz_block_scores(&blocks, Some(&saved));
+        assert_eq!(scores.get("b1").and_then(|v| v.as_f64()), Some(0.75));
+        assert_eq!(scores.get("b2").and_then(|v| v.as_f64()), Some(1.0));
+        assert!(!scores.contains_key("b3"));
+        assert!((crate::grading::lesson_score(&blocks, &scores) - 1.75 / 3.0).abs() < 1e-6);
+
+        assert_eq!(saved_selection(Some(&saved), "b1", "q2"), Some(vec![2]));
+        assert_eq!(saved_selection(Some(&saved), "b1", "q3"), None);
+        assert!(quiz_block_scores(&blocks, None).values().all(|v| v.as_f64() == Some(0.0)));
+    }
+}
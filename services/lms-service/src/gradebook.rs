@@ -0,0 +1,938 @@
+/// Motor del libro de calificaciones.
+///
+/// La nota final del curso se calcula con las reglas de `grading_categories`:
+/// - Nota efectiva de una lección: el ajuste manual vigente o `score × (1 − late_penalty)`.
+/// - Cada categoría promedia sus lecciones calificadas tras descartar las `drop_count`
+///   notas más bajas (siempre se conserva al menos una).
+/// - La nota final pondera por `weight` las categorías que ya tienen notas. Sin
+///   categorías en el curso se promedian todas las lecciones calificadas.
+/// - Las lecciones sin categoría (o con una que ya no existe) forman el grupo
+///   "Sin categoría" con peso 0: se informa su promedio, pero no cuenta en la nota
+///   final hasta que se les asigne una categoría.
+/// - Un ajuste sin lección reemplaza la nota final calculada.
+/// - La letra sale de la escala del curso (`grading_schemes`) o de la escala por defecto.
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::{DateTime, Utc};
+use common::{auth::Claims, middleware::Org};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LetterBand {
+    pub letter: String,
+    pub min_percent: f32,
+}
+
+fn default_bands() -> Vec<LetterBand> {
+    [("A", 90.0), ("B", 80.0), ("C", 70.0), ("D", 60.0), ("F", 0.0)]
+        .into_iter()
+        .map(|(letter, min_percent)| LetterBand { letter: letter.to_string(), min_percent })
+        .collect()
+}
+
+/// Letra de la banda más alta cuyo mínimo no supera el porcentaje.
+pub fn letter_for(percent: f32, bands: &[LetterBand]) -> Option<String> {
+    bands
+        .iter()
+        .filter(|b| percent >= b.min_percent)
+        .max_by(|a, b| a.min_percent.total_cmp(&b.min_percent))
+        .map(|b| b.letter.clone())
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct CategoryRule {
+    pub id: Uuid,
+    pub name: String,
+    pub weight: i32,
+    pub drop_count: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct GradeItem {
+    pub lesson_id: Uuid,
+    pub category_id: Option<Uuid>,
+    pub raw_score: Option<f32>,
+    pub late_penalty: f32,
+    pub override_score: Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LessonResult {
+    pub lesson_id: Uuid,
+    pub category_id: Option<Uuid>,
+    pub raw_score: Option<f32>,
+    pub late_penalty: f32,
+    pub override_score: Option<f32>,
+    pub effective_score: f32,
+    pub dropped: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryResult {
+    /// `None` en el grupo de lecciones sin categoría
+    pub category_id: Option<Uuid>,
+    pub name: String,
+    pub weight: i32,
+    pub average: Option<f32>,
+    pub counted: usize,
+    pub dropped: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GradeBreakdown {
+    pub lessons: Vec<LessonResult>,
+    pub categories: Vec<CategoryResult>,
+    /// Nota calculada por las reglas, 0.0 a 1.0
+    pub computed_score: Option<f32>,
+    pub final_override: Option<f32>,
+    pub final_score: Option<f32>,
+    pub letter: Option<String>,
+}
+
+/// Nombre del grupo de lecciones sin categoría
+const UNCATEGORIZED: &str = "Sin categoría";
+
+pub fn compute_breakdown(
+    categories: &[CategoryRule],
+    items: &[GradeItem],
+    final_override: Option<f32>,
+    bands: &[LetterBand],
+) -> GradeBreakdown {
+    let mut lessons: Vec<LessonResult> = items
+        .iter()
+        .filter_map(|item| {
+            let effective = item
+                .override_score
+                .or_else(|| item.raw_score.map(|s| s * (1.0 - item.late_penalty.clamp(0.0, 1.0))))?;
+            Some(LessonResult {
+                lesson_id: item.lesson_id,
+                category_id: item.category_id,
+                raw_score: item.raw_score,
+                late_penalty: item.late_penalty,
+                override_score: item.override_score,
+                effective_score: effective,
+                dropped: false,
+            })
+        })
+        .collect();
+
+    let mut groups: Vec<(CategoryRule, Vec<usize>)> = categories
+        .iter()
+        .map(|category| {
+            let indices = (0..lessons.len())
+                .filter(|&i| lessons[i].category_id == Some(category.id))
+                .collect();
+            (category.clone(), indices)
+        })
+        .collect();
+    let uncategorized: Vec<usize> = (0..lessons.len())
+        .filter(|&i| !categories.iter().any(|c| lessons[i].category_id == Some(c.id)))
+        .collect();
+    if !categories.is_empty() && !uncategorized.is_empty() {
+        let rule = CategoryRule { id: Uuid::nil(), name: UNCATEGORIZED.to_string(), weight: 0, drop_count: 0 };
+        groups.push((rule, uncategorized));
+    }
+
+    let mut category_results = Vec::new();
+    for (category, mut indices) in groups {
+        indices.sort_by(|&a, &b| lessons[a].effective_score.total_cmp(&lessons[b].effective_score));
+        let drops = (category.drop_count.max(0) as usize).min(indices.len().saturating_sub(1));
+        for &i in &indices[..drops] {
+            lessons[i].dropped = true;
+        }
+        let kept = &indices[drops..];
+        let average = (!kept.is_empty())
+            .then(|| kept.iter().map(|&i| lessons[i].effective_score).sum::<f32>() / kept.len() as f32);
+        category_results.push(CategoryResult {
+            category_id: (!category.id.is_nil()).then_some(category.id),
+            name: category.name,
+            weight: category.weight,
+            average,
+            counted: kept.len(),
+            dropped: drops,
+        });
+    }
+
+    let computed_score = if categories.is_empty() {
+        (!lessons.is_empty())
+            .then(|| lessons.iter().map(|l| l.effective_score).sum::<f32>() / lessons.len() as f32)
+    } else {
+        let (weighted, weights) = category_results
+            .iter()
+            .filter_map(|c| c.average.map(|avg| (avg * c.weight as f32, c.weight as f32)))
+            .fold((0.0, 0.0), |(sum, total), (w, weight)| (sum + w, total + weight));
+        (weights > 0.0).then(|| weighted / weights)
+    };
+
+    let final_score = final_override.or(computed_score);
+    GradeBreakdown {
+        lessons,
+        categories: category_results,
+        computed_score,
+        final_override,
+        final_score,
+        letter: final_score.and_then(|s| letter_for(s * 100.0, bands)),
+    }
+}
+
+// ─── Carga de datos ──────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct LessonColumn {
+    pub id: Uuid,
+    pub title: String,
+    pub grading_category_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct StudentRow {
+    id: Uuid,
+    full_name: String,
+    email: String,
+    cohort_name: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct GradeRow {
+    user_id: Uuid,
+    lesson_id: Uuid,
+    score: f32,
+    late_penalty: f32,
+}
+
+#[derive(sqlx::FromRow)]
+struct OverrideRow {
+    user_id: Uuid,
+    lesson_id: Option<Uuid>,
+    score: Option<f32>,
+}
+
+struct CourseGradebookData {
+    categories: Vec<CategoryRule>,
+    lessons: Vec<LessonColumn>,
+    students: Vec<StudentRow>,
+    grades: HashMap<(Uuid, Uuid), (f32, f32)>,
+    overrides: HashMap<(Uuid, Option<Uuid>), f32>,
+    bands: Vec<LetterBand>,
+}
+
+impl CourseGradebookData {
+    fn breakdown(&self, user_id: Uuid) -> GradeBreakdown {
+        let items: Vec<GradeItem> = self
+            .lessons
+            .iter()
+            .map(|lesson| {
+                let grade = self.grades.get(&(user_id, lesson.id));
+                GradeItem {
+                    lesson_id: lesson.id,
+                    category_id: lesson.grading_category_id,
+                    raw_score: grade.map(|g| g.0),
+                    late_penalty: grade.map(|g| g.1).unwrap_or(0.0),
+                    override_score: self.overrides.get(&(user_id, Some(lesson.id))).copied(),
+                }
+            })
+            .collect();
+        compute_breakdown(
+            &self.categories,
+            &items,
+            self.overrides.get(&(user_id, None)).copied(),
+            &self.bands,
+        )
+    }
+}
+
+async fn load_bands(pool: &PgPool, course_id: Uuid) -> Result<Vec<LetterBand>, sqlx::Error> {
+    let bands: Option<serde_json::Value> =
+        sqlx::query_scalar("SELECT bands FROM grading_schemes WHERE course_id = $1")
+            .bind(course_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(bands
+        .and_then(|b| serde_json::from_value::<Vec<LetterBand>>(b).ok())
+        .filter(|b| !b.is_empty())
+        .unwrap_or_else(default_bands))
+}
+
+async fn load_course_gradebook(
+    pool: &PgPool,
+    org_id: Uuid,
+    course_id: Uuid,
+    user_id: Option<Uuid>,
+) -> Result<CourseGradebookData, sqlx::Error> {
+    let categories = sqlx::query_as::<_, CategoryRule>(
+        "SELECT id, name, weight, drop_count FROM grading_categories WHERE course_id = $1 ORDER BY created_at",
+    )
+    .bind(course_id)
+    .fetch_all(pool)
+    .await?;
+
+    let lessons = sqlx::query_as::<_, LessonColumn>(
+        r#"
+        SELECT l.id, l.title, l.grading_category_id
+        FROM lessons l
+        JOIN modules m ON m.id = l.module_id
+        WHERE m.course_id = $1 AND l.is_graded = true
+        ORDER BY m.position, l.position
+        "#,
+    )
+    .bind(course_id)
+    .fetch_all(pool)
+    .await?;
+
+    let students = sqlx::query_as::<_, StudentRow>(
+        r#"
+        SELECT u.id, u.full_name, u.email,
+            (SELECT c.name FROM cohorts c JOIN user_cohorts uc ON c.id = uc.cohort_id WHERE uc.user_id = u.id LIMIT 1) AS cohort_name
+        FROM users u
+        JOIN enrollments e ON e.user_id = u.id AND e.course_id = $1
+        WHERE e.organization_id = $2 AND ($3::uuid IS NULL OR u.id = $3)
+        ORDER BY u.full_name
+        "#,
+    )
+    .bind(course_id)
+    .bind(org_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let grades = sqlx::query_as::<_, GradeRow>(
+        "SELECT user_id, lesson_id, score, late_penalty FROM user_grades WHERE course_id = $1 AND ($2::uuid IS NULL OR user_id = $2)",
+    )
+    .bind(course_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|g| ((g.user_id, g.lesson_id), (g.score, g.late_penalty)))
+    .collect();
+
+    // Ajuste vigente: el más reciente por alumno y lección (score NULL lo elimina)
+    let overrides = sqlx::query_as::<_, OverrideRow>(
+        r#"
+        SELECT DISTINCT ON (user_id, lesson_id) user_id, lesson_id, score
+        FROM grade_overrides
+        WHERE course_id = $1 AND ($2::uuid IS NULL OR user_id = $2)
+        ORDER BY user_id, lesson_id, created_at DESC
+        "#,
+    )
+    .bind(course_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .filter_map(|o| o.score.map(|score| ((o.user_id, o.lesson_id), score)))
+    .collect();
+
+    Ok(CourseGradebookData {
+        categories,
+        lessons,
+        students,
+        grades,
+        overrides,
+        bands: load_bands(pool, course_id).await?,
+    })
+}
+
+/// Nota final (0.0 a 1.0) de cada alumno inscrito en el curso.
+pub async fn final_scores(
+    pool: &PgPool,
+    org_id: Uuid,
+    course_id: Uuid,
+) -> Result<HashMap<Uuid, Option<f32>>, sqlx::Error> {
+    let data = load_course_gradebook(pool, org_id, course_id, None).await?;
+    Ok(data
+        .students
+        .iter()
+        .map(|s| (s.id, data.breakdown(s.id).final_score))
+        .collect())
+}
+
+// ─── Endpoints ───────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize)]
+pub struct GradebookStudent {
+    pub user_id: Uuid,
+    pub full_name: String,
+    pub email: String,
+    #[serde(flatten)]
+    pub breakdown: GradeBreakdown,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CourseGradebook {
+    pub course_id: Uuid,
+    pub categories: Vec<CategoryRule>,
+    pub lessons: Vec<LessonColumn>,
+    pub scheme: Vec<LetterBand>,
+    pub students: Vec<GradebookStudent>,
+}
+
+/// GET /courses/{id}/gradebook - Matriz completa del curso (instructor/admin)
+pub async fn get_course_gradebook(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+) -> Result<Json<CourseGradebook>, (StatusCode, String)> {
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+
+    let data = load_course_gradebook(&pool, org_ctx.id, course_id, None)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error al cargar el libro de calificaciones: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+        })?;
+
+    let students = data
+        .students
+        .iter()
+        .map(|s| GradebookStudent {
+            user_id: s.id,
+            full_name: s.full_name.clone(),
+            email: s.email.clone(),
+            breakdown: data.breakdown(s.id),
+        })
+        .collect();
+
+    Ok(Json(CourseGradebook {
+        course_id,
+        categories: data.categories,
+        lessons: data.lessons,
+        scheme: data.bands,
+        students,
+    }))
+}
+
+/// GET /courses/{id}/gradebook/students/{user_id} - Desglose de un alumno
+/// (el propio alumno o el personal del curso)
+pub async fn get_student_gradebook(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path((course_id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<GradebookStudent>, (StatusCode, String)> {
+    if user_id != claims.sub {
+        crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+    }
+
+    let data = load_course_gradebook(&pool, org_ctx.id, course_id, Some(user_id))
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let student = data
+        .students
+        .first()
+        .ok_or((StatusCode::NOT_FOUND, "El alumno no está inscrito en este curso".to_string()))?;
+
+    Ok(Json(GradebookStudent {
+        user_id: student.id,
+        full_name: student.full_name.clone(),
+        email: student.email.clone(),
+        breakdown: data.breakdown(student.id),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GradebookExportQuery {
+    /// "csv" (por defecto) o "xlsx"
+    pub format: Option<String>,
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Tabla exportable: encabezados y filas con texto o porcentajes (0-100).
+enum Cell {
+    Text(String),
+    Percent(Option<f32>),
+}
+
+/// Las columnas de categoría se emparejan por id; la de "Sin categoría" aparece en
+/// todas las filas cuando alguna lección del curso no tiene una categoría vigente.
+fn export_table(data: &CourseGradebookData) -> (Vec<String>, Vec<Vec<Cell>>) {
+    let mut columns: Vec<Option<Uuid>> = data.categories.iter().map(|c| Some(c.id)).collect();
+    let mut headers = vec!["Nombre".to_string(), "Email".to_string(), "Cohorte".to_string()];
+    headers.extend(data.lessons.iter().map(|l| l.title.clone()));
+    headers.extend(data.categories.iter().map(|c| format!("{} ({}%)", c.name, c.weight)));
+    let has_uncategorized = !data.categories.is_empty()
+        && data
+            .lessons
+            .iter()
+            .any(|l| !data.categories.iter().any(|c| l.grading_category_id == Some(c.id)));
+    if has_uncategorized {
+        columns.push(None);
+        headers.push(format!("{} (0%)", UNCATEGORIZED));
+    }
+    headers.extend(["Nota final".to_string(), "Letra".to_string(), "Ajustada".to_string()]);
+
+    let rows = data
+        .students
+        .iter()
+        .map(|student| {
+            let breakdown = data.breakdown(student.id);
+            let mut row = vec![
+                Cell::Text(student.full_name.clone()),
+                Cell::Text(student.email.clone()),
+                Cell::Text(student.cohort_name.clone().unwrap_or_default()),
+            ];
+            row.extend(data.lessons.iter().map(|lesson| {
+                Cell::Percent(
+                    breakdown
+                        .lessons
+                        .iter()
+                        .find(|l| l.lesson_id == lesson.id)
+                        .map(|l| l.effective_score * 100.0),
+                )
+            }));
+            row.extend(columns.iter().map(|column| {
+                Cell::Percent(
+                    breakdown
+                        .categories
+                        .iter()
+                        .find(|c| c.category_id == *column)
+                        .and_then(|c| c.average.map(|a| a * 100.0)),
+                )
+            }));
+            row.push(Cell::Percent(breakdown.final_score.map(|s| s * 100.0)));
+            row.push(Cell::Text(breakdown.letter.clone().unwrap_or_default()));
+            row.push(Cell::Text(if breakdown.final_override.is_some() { "Sí" } else { "" }.to_string()));
+            row
+        })
+        .collect();
+
+    (headers, rows)
+}
+
+fn build_xlsx(headers: &[String], rows: &[Vec<Cell>]) -> Result<Vec<u8>, rust_xlsxwriter::XlsxError> {
+    use rust_xlsxwriter::{Format, Workbook};
+
+    let mut workbook = Workbook::new();
+    let bold = Format::new().set_bold();
+    let percent = Format::new().set_num_format("0.0");
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Calificaciones")?;
+    for (col, header) in headers.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, header, &bold)?;
+    }
+    for (r, row) in rows.iter().enumerate() {
+        let r = r as u32 + 1;
+        for (col, cell) in row.iter().enumerate() {
+            match cell {
+                Cell::Text(text) => {
+                    sheet.write_string(r, col as u16, text)?;
+                }
+                Cell::Percent(Some(value)) => {
+                    sheet.write_number_with_format(r, col as u16, *value as f64, &percent)?;
+                }
+                Cell::Percent(None) => {}
+            }
+        }
+    }
+    sheet.set_freeze_panes(1, 1)?;
+    workbook.save_to_buffer()
+}
+
+/// GET /courses/{id}/gradebook/export?format=csv|xlsx - Matriz completa del curso
+pub async fn export_course_gradebook(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+    Query(query): Query<GradebookExportQuery>,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+
+    let data = load_course_gradebook(&pool, org_ctx.id, course_id, None)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let (headers, rows) = export_table(&data);
+
+    let (body, content_type, extension) = match query.format.as_deref() {
+        Some("xlsx") => {
+            let bytes = build_xlsx(&headers, &rows).map_err(|e| {
+                tracing::error!("Error al generar el XLSX de calificaciones: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "Error al generar el archivo".to_string())
+            })?;
+            (bytes, "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", "xlsx")
+        }
+        None | Some("csv") => {
+            let mut csv = headers.iter().map(|h| csv_field(h)).collect::<Vec<_>>().join(",");
+            csv.push('\n');
+            for row in &rows {
+                let line = row
+                    .iter()
+                    .map(|cell| match cell {
+                        Cell::Text(text) => csv_field(text),
+                        Cell::Percent(Some(value)) => format!("{:.1}", value),
+                        Cell::Percent(None) => String::new(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",");
+                csv.push_str(&line);
+                csv.push('\n');
+            }
+            (csv.into_bytes(), "text/csv", "csv")
+        }
+        Some(_) => return Err((StatusCode::BAD_REQUEST, "Formato no soportado (csv o xlsx)".to_string())),
+    };
+
+    let disposition = format!("attachment; filename=\"grades-{}.{}\"", course_id, extension);
+    axum::response::Response::builder()
+        .header(axum::http::header::CONTENT_TYPE, content_type)
+        .header(axum::http::header::CONTENT_DISPOSITION, disposition)
+        .body(axum::body::Body::from(body))
+        .map(|r| r.into_response())
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error al construir la respuesta".to_string()))
+}
+
+// ─── Ajustes manuales ────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct GradeOverride {
+    pub id: Uuid,
+    pub course_id: Uuid,
+    pub user_id: Uuid,
+    pub lesson_id: Option<Uuid>,
+    pub score: Option<f32>,
+    pub previous_score: Option<f32>,
+    pub comment: String,
+    pub created_by: Uuid,
+    pub created_by_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateGradeOverridePayload {
+    pub user_id: Uuid,
+    /// Sin lección se ajusta la nota final del curso
+    pub lesson_id: Option<Uuid>,
+    /// 0.0 a 1.0; `null` elimina el ajuste vigente
+    pub score: Option<f32>,
+    pub comment: String,
+}
+
+/// POST /courses/{id}/gradebook/overrides - Ajuste manual con comentario obligatorio
+pub async fn create_grade_override(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+    Json(payload): Json<CreateGradeOverridePayload>,
+) -> Result<Json<GradebookStudent>, (StatusCode, String)> {
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+
+    if payload.comment.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "El ajuste requiere un comentario".to_string()));
+    }
+    if payload.score.is_some_and(|s| !(0.0..=1.0).contains(&s)) {
+        return Err((StatusCode::BAD_REQUEST, "La nota debe estar entre 0.0 y 1.0".to_string()));
+    }
+
+    let data = load_course_gradebook(&pool, org_ctx.id, course_id, Some(payload.user_id))
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    if data.students.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "El alumno no está inscrito en este curso".to_string()));
+    }
+    if let Some(lesson_id) = payload.lesson_id
+        && !data.lessons.iter().any(|l| l.id == lesson_id)
+    {
+        return Err((StatusCode::BAD_REQUEST, "La lección no es calificada en este curso".to_string()));
+    }
+
+    let before = data.breakdown(payload.user_id);
+    let previous_score = match payload.lesson_id {
+        Some(lesson_id) => before
+            .lessons
+            .iter()
+            .find(|l| l.lesson_id == lesson_id)
+            .map(|l| l.effective_score),
+        None => before.final_score,
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO grade_overrides (organization_id, course_id, user_id, lesson_id, score, previous_score, comment, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        "#,
+    )
+    .bind(org_ctx.id)
+    .bind(course_id)
+    .bind(payload.user_id)
+    .bind(payload.lesson_id)
+    .bind(payload.score)
+    .bind(previous_score)
+    .bind(payload.comment.trim())
+    .bind(claims.sub)
+    .execute(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error al registrar el ajuste de calificación: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+    })?;
+
+    let data = load_course_gradebook(&pool, org_ctx.id, course_id, Some(payload.user_id))
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let student = &data.students[0];
+    Ok(Json(GradebookStudent {
+        user_id: student.id,
+        full_name: student.full_name.clone(),
+        email: student.email.clone(),
+        breakdown: data.breakdown(student.id),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GradeOverrideFilter {
+    pub user_id: Option<Uuid>,
+}
+
+/// GET /courses/{id}/gradebook/overrides - Historial de ajustes (auditoría)
+pub async fn list_grade_overrides(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+    Query(filter): Query<GradeOverrideFilter>,
+) -> Result<Json<Vec<GradeOverride>>, (StatusCode, String)> {
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+
+    let overrides = sqlx::query_as::<_, GradeOverride>(
+        r#"
+        SELECT o.id, o.course_id, o.user_id, o.lesson_id, o.score, o.previous_score, o.comment,
+               o.created_by, u.full_name AS created_by_name, o.created_at
+        FROM grade_overrides o
+        LEFT JOIN users u ON u.id = o.created_by
+        WHERE o.course_id = $1 AND o.organization_id = $2 AND ($3::uuid IS NULL OR o.user_id = $3)
+        ORDER BY o.created_at DESC
+        "#,
+    )
+    .bind(course_id)
+    .bind(org_ctx.id)
+    .bind(filter.user_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(overrides))
+}
+
+// ─── Escala de letras ────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GradingSchemePayload {
+    pub name: Option<String>,
+    pub bands: Vec<LetterBand>,
+}
+
+/// GET /courses/{id}/grading-scheme
+pub async fn get_grading_scheme(
+    Org(org_ctx): Org,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+) -> Result<Json<GradingSchemePayload>, (StatusCode, String)> {
+    let course_exists: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM courses WHERE id = $1 AND organization_id = $2)")
+            .bind(course_id)
+            .bind(org_ctx.id)
+            .fetch_one(&pool)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    if !course_exists {
+        return Err((StatusCode::NOT_FOUND, "Curso no encontrado".to_string()));
+    }
+
+    let name: Option<String> =
+        sqlx::query_scalar("SELECT name FROM grading_schemes WHERE course_id = $1 AND organization_id = $2")
+            .bind(course_id)
+            .bind(org_ctx.id)
+            .fetch_optional(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let bands = load_bands(&pool, course_id)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(GradingSchemePayload {
+        name: Some(name.unwrap_or_else(|| "Por defecto".to_string())),
+        bands,
+    }))
+}
+
+/// PUT /courses/{id}/grading-scheme
+pub async fn update_grading_scheme(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+    Json(payload): Json<GradingSchemePayload>,
+) -> Result<Json<GradingSchemePayload>, (StatusCode, String)> {
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+
+    if payload.bands.is_empty()
+        || payload
+            .bands
+            .iter()
+            .any(|b| b.letter.trim().is_empty() || !(0.0..=100.0).contains(&b.min_percent))
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Cada banda requiere una letra y un mínimo entre 0 y 100".to_string(),
+        ));
+    }
+    if !payload.bands.iter().any(|b| b.min_percent == 0.0) {
+        return Err((StatusCode::BAD_REQUEST, "La escala debe incluir una banda con mínimo 0".to_string()));
+    }
+
+    let bands = serde_json::to_value(&payload.bands)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    sqlx::query(
+        r#"
+        INSERT INTO grading_schemes (course_id, organization_id, name, bands, updated_by)
+        VALUES ($1, $2, COALESCE($3, 'Personalizada'), $4, $5)
+        ON CONFLICT (course_id) DO UPDATE SET
+            name = EXCLUDED.name,
+            bands = EXCLUDED.bands,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = NOW()
+        "#,
+    )
+    .bind(course_id)
+    .bind(org_ctx.id)
+    .bind(&payload.name)
+    .bind(&bands)
+    .bind(claims.sub)
+    .execute(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(lesson: u128, category: Uuid, score: f32) -> GradeItem {
+        GradeItem {
+            lesson_id: Uuid::from_u128(lesson),
+            category_id: Some(category),
+            raw_score: Some(score),
+            late_penalty: 0.0,
+            override_score: None,
+        }
+    }
+
+    #[test]
+    fn weights_categories_after_drops_penalties_and_overrides() {
+        let quizzes = Uuid::from_u128(1);
+        let exams = Uuid::from_u128(2);
+        let categories = vec![
+            CategoryRule { id: quizzes, name: "Quizzes".into(), weight: 40, drop_count: 1 },
+            CategoryRule { id: exams, name: "Exams".into(), weight: 60, drop_count: 0 },
+        ];
+        let mut items = vec![
+            item(10, quizzes, 0.2),
+            item(11, quizzes, 0.8),
+            item(12, quizzes, 1.0),
+            item(20, exams, 1.0),
+        ];
+        items[3].late_penalty = 0.25;
+
+        let result = compute_breakdown(&categories, &items, None, &default_bands());
+        // Quizzes: se descarta 0.2 → 0.9; Exams: 1.0 con 25% de penalización → 0.75
+        assert!(result.lessons[0].dropped);
+        assert!((result.categories[0].average.unwrap() - 0.9).abs() < 1e-6);
+        assert!((result.categories[1].average.unwrap() - 0.75).abs() < 1e-6);
+        assert!((result.computed_score.unwrap() - 0.81).abs() < 1e-6);
+        assert_eq!(result.letter.as_deref(), Some("B"));
+
+        items[3].override_score = Some(1.0);
+        let result = compute_breakdown(&categories, &items, Some(0.55), &default_bands());
+        assert!((result.computed_score.unwrap() - 0.96).abs() < 1e-6);
+        assert_eq!(result.final_score, Some(0.55));
+        assert_eq!(result.letter.as_deref(), Some("F"));
+    }
+
+    #[test]
+    fn uncategorized_lessons_are_reported_without_weight() {
+        let quizzes = Uuid::from_u128(1);
+        let exams = Uuid::from_u128(2);
+        let categories = vec![
+            CategoryRule { id: quizzes, name: "Quizzes".into(), weight: 20, drop_count: 0 },
+            CategoryRule { id: exams, name: "Exams".into(), weight: 60, drop_count: 0 },
+        ];
+        let mut loose = item(30, quizzes, 0.0);
+        loose.category_id = None;
+        let orphan = item(31, Uuid::from_u128(99), 1.0);
+        let items = vec![item(10, quizzes, 1.0), item(20, exams, 0.5), loose, orphan];
+
+        let result = compute_breakdown(&categories, &items, None, &default_bands());
+        let extra = &result.categories[2];
+        assert_eq!((extra.category_id, extra.weight, extra.counted), (None, 0, 2));
+        assert!((extra.average.unwrap() - 0.5).abs() < 1e-6);
+        // (1.0 × 20 + 0.5 × 60) / 80
+        assert!((result.computed_score.unwrap() - 50.0 / 80.0).abs() < 1e-6);
+
+        let result = compute_breakdown(&categories, &items[..2], None, &default_bands());
+        assert_eq!(result.categories.len(), 2);
+    }
+
+    #[test]
+    fn export_columns_match_categories_by_id() {
+        let quizzes = Uuid::from_u128(1);
+        let exams = Uuid::from_u128(2);
+        let lesson = |id: u128, category: Option<Uuid>| LessonColumn {
+            id: Uuid::from_u128(id),
+            title: format!("L{}", id),
+            grading_category_id: category,
+        };
+        let student = |id: u128| StudentRow {
+            id: Uuid::from_u128(id),
+            full_name: format!("S{}", id),
+            email: format!("s{}@example.com", id),
+            cohort_name: None,
+        };
+        let (with_loose, without_loose) = (Uuid::from_u128(100), Uuid::from_u128(101));
+        let data = CourseGradebookData {
+            categories: vec![
+                CategoryRule { id: quizzes, name: "Quizzes".into(), weight: 40, drop_count: 0 },
+                CategoryRule { id: exams, name: "Exams".into(), weight: 60, drop_count: 0 },
+            ],
+            lessons: vec![lesson(10, Some(quizzes)), lesson(20, Some(exams)), lesson(30, None)],
+            students: vec![student(100), student(101)],
+            grades: HashMap::from([
+                ((with_loose, Uuid::from_u128(20)), (0.5, 0.0)),
+                ((with_loose, Uuid::from_u128(30)), (1.0, 0.0)),
+                ((without_loose, Uuid::from_u128(20)), (0.8, 0.0)),
+            ]),
+            overrides: HashMap::new(),
+            bands: default_bands(),
+        };
+
+        let (headers, rows) = export_table(&data);
+        assert_eq!(&headers[6..9], ["Quizzes (40%)", "Exams (60%)", "Sin categoría (0%)"]);
+        let percent = |row: &[Cell], i: usize| match &row[i] {
+            Cell::Percent(value) => *value,
+            Cell::Text(_) => panic!("se esperaba un porcentaje"),
+        };
+        for row in &rows {
+            assert_eq!(row.len(), headers.len());
+            assert_eq!(percent(row, 6), None);
+        }
+        assert_eq!((percent(&rows[0], 7), percent(&rows[0], 8)), (Some(50.0), Some(100.0)));
+        assert_eq!((percent(&rows[1], 7), percent(&rows[1], 8)), (Some(80.0), None));
+    }
+}
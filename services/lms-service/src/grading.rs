@@ -0,0 +1,396 @@
+/// Calificaciones de bloques corregidos en el servidor.
+///
+/// El puntaje de una lección es el promedio (0-1) de los puntajes de sus
+/// bloques interactivos, guardados en `metadata.block_scores`. Los bloques que
+/// corrige el navegador llegan por `POST /grades`; los que corrige el servidor
+/// (code-lab con casos ocultos, plugins de interfaz con `grade.submit` y los tipos
+/// de bloque que califica un plugin wasm) se registran con `record_block_score`, que aplica
+/// las mismas reglas que `submit_lesson_score`: examen con intento vigente, política
+/// de entrega tardía y límite de intentos. `submit_lesson_score` conserva los
+/// puntajes guardados de esos bloques en lugar de los que envía el cliente.
+use axum::http::StatusCode;
+use serde_json::{Map, Value, json};
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Tipos de bloque cuyo puntaje solo puede fijar el servidor
+pub const SERVER_SCORED_BLOCKS: &[&str] = &["code-lab", "code", "plugin"];
+/// Bloques sin puntaje (mismo criterio que Experience)
+const NON_INTERACTIVE_BLOCKS: &[&str] = &["description", "media", "document"];
+
+/// Curso, `max_attempts`, `metadata` y `content_blocks` de la lección
+type LessonGradingRow = (Uuid, Option<i32>, Option<Value>, Option<Value>);
+
+fn internal_error() -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+}
+
+/// Bloques de la lección: `metadata.blocks` o, en lecciones antiguas, `content_blocks`.
+pub fn lesson_blocks<'a>(metadata: Option<&'a Value>, content_blocks: Option<&'a Value>) -> Vec<&'a Value> {
+    metadata
+        .and_then(|m| m.get("blocks"))
+        .and_then(Value::as_array)
+        .or_else(|| content_blocks.and_then(Value::as_array))
+        .map(|blocks| blocks.iter().collect())
+        .unwrap_or_default()
+}
+
+fn block_type(block: &Value) -> &str {
+    block.get("type").and_then(Value::as_str).unwrap_or_default()
+}
+
+fn block_id_of(block: &Value) -> Option<&str> {
+    block.get("id").and_then(Value::as_str)
+}
+
+/// Tipos de bloque que califica algún plugin wasm activo de la organización.
+pub async fn plugin_block_types(conn: &mut PgConnection, organization_id: Uuid) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT DISTINCT unnest(block_types) FROM org_plugins WHERE organization_id = $1 AND kind = 'wasm' AND enabled = TRUE",
+    )
+    .bind(organization_id)
+    .fetch_all(conn)
+    .await
+}
+
+fn is_server_scored(block: &Value, plugin_types: &[String]) -> bool {
+    let kind = block_type(block);
+    SERVER_SCORED_BLOCKS.contains(&kind) || plugin_types.iter().any(|t| t == kind)
+}
+
+/// Promedio 0-1 de los bloques interactivos; 1 si la lección no tiene ninguno.
+pub fn lesson_score(blocks: &[&Value], block_scores: &Map<String, Value>) -> f32 {
+    let scores: Vec<f64> = blocks
+        .iter()
+        .filter(|b| !NON_INTERACTIVE_BLOCKS.contains(&block_type(b)))
+        .map(|b| {
+            block_id_of(b)
+                .and_then(|id| block_scores.get(id))
+                .and_then(Value::as_f64)
+                .unwrap_or(0.0)
+                .clamp(0.0, 1.0)
+        })
+        .collect();
+    if scores.is_empty() {
+        return 1.0;
+    }
+    (scores.iter().sum::<f64>() / scores.len() as f64) as f32
+}
+
+/// Reemplaza en la entrega del cliente los puntajes de bloques corregidos en el
+/// servidor (incluidos los `plugin_types`) por los guardados y recalcula el puntaje.
+/// Devuelve `None` si la lección no tiene bloques de ese tipo.
+pub fn protect_server_scores(
+    blocks: &[&Value],
+    plugin_types: &[String],
+    client_metadata: Option<&Value>,
+    stored_metadata: Option<&Value>,
+) -> Option<(f32, Value)> {
+    let server_blocks: Vec<&str> = blocks
+        .iter()
+        .filter(|b| is_server_scored(b, plugin_types))
+        .filter_map(|b| block_id_of(b))
+        .collect();
+    if server_blocks.is_empty() {
+        return None;
+    }
+
+    let mut metadata = client_metadata.filter(|m| m.is_object()).cloned().unwrap_or_else(|| json!({}));
+    let stored = stored_metadata.and_then(|m| m.get("block_scores"));
+    let mut scores = metadata
+        .get("block_scores")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    for id in server_blocks {
+        match stored.and_then(|s| s.get(id)) {
+            Some(score) => scores.insert(id.to_string(), score.clone()),
+            None => scores.remove(id),
+        };
+    }
+    let score = lesson_score(blocks, &scores);
+    metadata["block_scores"] = Value::Object(scores);
+    Some((score, metadata))
+}
+
+/// Las lecciones adaptativas y las de cuestionarios con pool solo se califican con
+/// sus propios endpoints (`/adaptive-test/*`, `/quiz-variant/submit`).
+pub fn require_block_graded_lesson(metadata: Option<&Value>, content_blocks: Option<&Value>) -> Result<(), (StatusCode, String)> {
+    let adaptive = metadata.and_then(|m| m.get("delivery_mode")).and_then(Value::as_str) == Some("adaptive");
+    if adaptive || !crate::quiz_variants::pool_blocks(metadata, content_blocks).is_empty() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Esta lección se califica en el servidor con su propio flujo de entrega".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Rechaza la entrega si ya se alcanzó `max_attempts`.
+pub async fn check_attempt_limit(
+    tx: &mut Transaction<'_, Postgres>,
+    organization_id: Uuid,
+    user_id: Uuid,
+    lesson_id: Uuid,
+    max_attempts: Option<i32>,
+) -> Result<(), (StatusCode, String)> {
+    let existing_attempts: Option<i32> = sqlx::query_scalar(
+        "SELECT attempts_count FROM user_grades WHERE user_id = $1 AND lesson_id = $2 AND organization_id = $3",
+    )
+    .bind(user_id)
+    .bind(lesson_id)
+    .bind(organization_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|_| internal_error())?;
+
+    if let (Some(count), Some(max)) = (existing_attempts, max_attempts)
+        && count >= max
+    {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Se ha alcanzado el número máximo de intentos para esta evaluación".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// `fn_upsert_user_grade` más la penalización por entrega tardía.
+#[allow(clippy::too_many_arguments)]
+pub async fn upsert_grade(
+    tx: &mut Transaction<'_, Postgres>,
+    organization_id: Uuid,
+    user_id: Uuid,
+    course_id: Uuid,
+    lesson_id: Uuid,
+    score: f32,
+    metadata: Option<&Value>,
+    late_penalty: Option<f32>,
+) -> Result<common::models::UserGrade, (StatusCode, String)> {
+    let grade = sqlx::query_as::<_, common::models::UserGrade>(
+        "SELECT * FROM fn_upsert_user_grade($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(organization_id)
+    .bind(user_id)
+    .bind(course_id)
+    .bind(lesson_id)
+    .bind(score)
+    .bind(metadata)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(|_| internal_error())?;
+
+    if let Some(penalty) = late_penalty {
+        sqlx::query("UPDATE user_grades SET late_penalty = $1 WHERE id = $2")
+            .bind(penalty)
+            .bind(grade.id)
+            .execute(&mut **tx)
+            .await
+            .map_err(|_| internal_error())?;
+    }
+    Ok(grade)
+}
+
+/// Webhooks y eventos de plugins tras confirmar una calificación.
+pub async fn notify_grade_recorded(
+    pool: &PgPool,
+    organization_id: Uuid,
+    user_id: Uuid,
+    course_id: Uuid,
+    lesson_id: Uuid,
+    score: f32,
+    attempts_count: i32,
+) {
+    let webhook_service = common::webhooks::WebhookService::new(pool.clone());
+    webhook_service
+        .dispatch(
+            organization_id,
+            "lesson.completed",
+            &json!({
+                "user_id": user_id,
+                "course_id": course_id,
+                "lesson_id": lesson_id,
+                "score": score
+            }),
+        )
+        .await;
+
+    crate::plugin_host::dispatch_event(
+        pool,
+        organization_id,
+        "grade.submitted",
+        json!({
+            "user_id": user_id,
+            "course_id": course_id,
+            "lesson_id": lesson_id,
+            "score": score,
+            "attempts_count": attempts_count
+        }),
+    );
+
+    match crate::progress_tracking::calculate_course_completion(pool, user_id, course_id).await {
+        Ok(completion) if completion.completed => {
+            webhook_service
+                .dispatch(
+                    organization_id,
+                    "course.completed",
+                    &json!({
+                        "user_id": user_id,
+                        "course_id": course_id,
+                        "progress_percentage": completion.progress_percentage
+                    }),
+                )
+                .await;
+        }
+        Ok(_) => {}
+        Err(_) => tracing::warn!(
+            "No se pudo calcular la completitud real del curso {} para el usuario {}",
+            course_id,
+            user_id
+        ),
+    }
+}
+
+/// Registra el puntaje (0-1) de un bloque corregido en el servidor y recalcula el
+/// de la lección. `detail` queda en `metadata.block_results`.
+pub async fn record_block_score(
+    pool: &PgPool,
+    organization_id: Uuid,
+    user_id: Uuid,
+    lesson_id: Uuid,
+    block_id: &str,
+    block_score: f32,
+    detail: Value,
+) -> Result<common::models::UserGrade, (StatusCode, String)> {
+    let mut tx = pool.begin().await.map_err(|_| internal_error())?;
+    crate::db_util::set_session_context(
+        &mut tx,
+        Some(user_id),
+        Some(organization_id),
+        None,
+        None,
+        Some("EVENTO_DEL_SISTEMA".to_string()),
+    )
+    .await
+    .map_err(|_| internal_error())?;
+
+    let lesson: Option<LessonGradingRow> = sqlx::query_as(
+        r#"
+        SELECT m.course_id, l.max_attempts, l.metadata, l.content_blocks
+        FROM lessons l JOIN modules m ON l.module_id = m.id
+        WHERE l.id = $1 AND l.organization_id = $2
+        "#,
+    )
+    .bind(lesson_id)
+    .bind(organization_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| internal_error())?;
+    let (course_id, max_attempts, lesson_metadata, content_blocks) =
+        lesson.ok_or((StatusCode::NOT_FOUND, "Lección no encontrada".to_string()))?;
+    require_block_graded_lesson(lesson_metadata.as_ref(), content_blocks.as_ref())?;
+    let blocks = lesson_blocks(lesson_metadata.as_ref(), content_blocks.as_ref());
+    let plugin_types = plugin_block_types(&mut tx, organization_id).await.map_err(|_| internal_error())?;
+    if !blocks
+        .iter()
+        .any(|b| block_id_of(b) == Some(block_id) && is_server_scored(b, &plugin_types))
+    {
+        return Err((StatusCode::NOT_FOUND, "Bloque no encontrado".to_string()));
+    }
+
+    // Los intentos de examen ya validaron el límite al iniciarse
+    let exam_attempt =
+        crate::exam_attempts::require_open_attempt(&mut tx, user_id, lesson_id, lesson_metadata.as_ref()).await?;
+    let late_penalty = crate::late_policies::enforce_late_policy(&mut tx, user_id, lesson_id).await?;
+    if exam_attempt.is_none() {
+        check_attempt_limit(&mut tx, organization_id, user_id, lesson_id, max_attempts).await?;
+    }
+
+    let stored: Option<Value> = sqlx::query_scalar(
+        "SELECT metadata FROM user_grades WHERE user_id = $1 AND lesson_id = $2 AND organization_id = $3 FOR UPDATE",
+    )
+    .bind(user_id)
+    .bind(lesson_id)
+    .bind(organization_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| internal_error())?
+    .flatten();
+
+    let mut metadata = stored.filter(Value::is_object).unwrap_or_else(|| json!({}));
+    if !metadata["block_scores"].is_object() {
+        metadata["block_scores"] = json!({});
+    }
+    if !metadata["block_results"].is_object() {
+        metadata["block_results"] = json!({});
+    }
+    metadata["block_scores"][block_id] = json!(block_score.clamp(0.0, 1.0));
+    metadata["block_results"][block_id] = detail;
+    if let Some(attempt_id) = exam_attempt {
+        metadata["exam_attempt_id"] = json!(attempt_id);
+    }
+
+    let score = lesson_score(&blocks, metadata["block_scores"].as_object().expect("objeto"));
+
+    let grade = upsert_grade(
+        &mut tx,
+        organization_id,
+        user_id,
+        course_id,
+        lesson_id,
+        score,
+        Some(&metadata),
+        Some(late_penalty),
+    )
+    .await?;
+    tx.commit().await.map_err(|_| internal_error())?;
+
+    notify_grade_recorded(pool, organization_id, user_id, course_id, lesson_id, score, grade.attempts_count).await;
+    Ok(grade)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_cannot_set_server_scored_blocks() {
+        let blocks = json!([
+            {"id": "intro", "type": "description"},
+            {"id": "quiz", "type": "quiz"},
+            {"id": "lab", "type": "code-lab"}
+        ]);
+        let blocks: Vec<&Value> = blocks.as_array().unwrap().iter().collect();
+        let client = json!({"block_scores": {"quiz": 1.0, "lab": 1.0}});
+        let stored = json!({"block_scores": {"quiz": 0.0, "lab": 0.5}});
+
+        let (score, metadata) = protect_server_scores(&blocks, &[], Some(&client), Some(&stored)).unwrap();
+        assert_eq!(metadata["block_scores"]["lab"], json!(0.5));
+        assert_eq!(metadata["block_scores"]["quiz"], json!(1.0));
+        assert!((score - 0.75).abs() < 1e-6);
+
+        let (score, metadata) = protect_server_scores(&blocks, &[], Some(&client), None).unwrap();
+        assert!(metadata["block_scores"].get("lab").is_none());
+        assert!((score - 0.5).abs() < 1e-6);
+
+        let quiz_only: Vec<&Value> = blocks[..2].to_vec();
+        assert!(protect_server_scores(&quiz_only, &[], Some(&client), None).is_none());
+    }
+
+    #[test]
+    fn plugin_graded_block_types_are_protected() {
+        let blocks = json!([
+            {"id": "quiz", "type": "quiz"},
+            {"id": "chem", "type": "molecule-builder"}
+        ]);
+        let blocks: Vec<&Value> = blocks.as_array().unwrap().iter().collect();
+        let client = json!({"block_scores": {"quiz": 1.0, "chem": 1.0}});
+        let stored = json!({"block_scores": {"chem": 0.25}});
+        let plugin_types = vec!["molecule-builder".to_string()];
+
+        assert!(protect_server_scores(&blocks, &[], Some(&client), Some(&stored)).is_none());
+        let (score, metadata) = protect_server_scores(&blocks, &plugin_types, Some(&client), Some(&stored)).unwrap();
+        assert_eq!(metadata["block_scores"]["chem"], json!(0.25));
+        assert!((score - 0.625).abs() < 1e-6);
+    }
+}
@@ -17,6 +17,7 @@ use bcrypt::{hash, verify};
 use chrono::{DateTime, Utc};
 use common::auth::{Claims, create_jwt, auth_cookie_header};
 use common::middleware::Org;
+use common::tenancy::{self, TenantScope};
 use common::models::{
     AuthResponse, Course, CourseAnalytics, Enrollment, HeatmapPoint, Lesson, LessonAnalytics,
     Module, Notification, Organization, RecommendationResponse, User, UserResponse,
@@ -494,132 +495,22 @@ pub async fn bulk_enroll_users(
     }))
 }
 
+/// Exportación histórica en CSV; delega en el motor del libro de calificaciones
+/// para que la nota final respete pesos, descartes, penalizaciones y ajustes.
 pub async fn export_course_grades(
-    Org(org_ctx): Org,
+    org: Org,
     claims: Claims,
     State(pool): State<PgPool>,
     Path(course_id): Path<Uuid>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    if claims.role != "admin" && claims.role != "instructor" {
-        return Err((StatusCode::FORBIDDEN, "No autorizado".to_string()));
-    }
-
-    // 1. Obtener categorías
-    #[derive(sqlx::FromRow)]
-    struct Cat { id: Uuid, name: String }
-    let categories: Vec<Cat> = sqlx::query_as(
-        "SELECT id, name FROM grading_categories WHERE course_id = $1 ORDER BY name"
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    crate::gradebook::export_course_gradebook(
+        org,
+        claims,
+        State(pool),
+        Path(course_id),
+        Query(crate::gradebook::GradebookExportQuery { format: None }),
     )
-    .bind(course_id)
-    .fetch_all(&pool)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
-
-    // 2. Obtener datos generales de los estudiantes
-    #[derive(sqlx::FromRow)]
-    struct StudentRow {
-        id: Uuid,
-        full_name: String,
-        email: String,
-        progress: Option<f32>,
-        cohort_name: Option<String>,
-        average_score: Option<f32>,
-    }
-    let students: Vec<StudentRow> = sqlx::query_as(
-        r#"
-        SELECT 
-            u.id, 
-            u.full_name, 
-            u.email, 
-            0.0::float4 as progress,
-            (SELECT name FROM cohorts c JOIN user_cohorts uc ON c.id = uc.cohort_id WHERE uc.user_id = u.id LIMIT 1) as cohort_name,
-            AVG(g.score)::float4 as average_score
-        FROM users u
-        JOIN enrollments e ON u.id = e.user_id AND e.course_id = $1
-        LEFT JOIN user_grades g ON u.id = g.user_id AND g.course_id = $1
-        WHERE e.organization_id = $2
-        GROUP BY u.id, u.full_name, u.email
-        ORDER BY u.full_name
-        "#
-    )
-    .bind(course_id)
-    .bind(org_ctx.id)
-    .fetch_all(&pool)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
-
-    // 3. Obtener calificaciones detalladas por usuario/categoría
-    #[derive(sqlx::FromRow)]
-    struct UserCategoryGrade {
-        user_id: Uuid,
-        grading_category_id: Option<Uuid>,
-        avg_score: Option<f32>,
-    }
-
-    let detailed_grades: Vec<UserCategoryGrade> = sqlx::query_as(
-        r#"
-        SELECT 
-            g.user_id, 
-            l.grading_category_id, 
-            AVG(g.score)::float4 as avg_score
-        FROM user_grades g
-        JOIN lessons l ON g.lesson_id = l.id
-        WHERE g.course_id = $1
-        GROUP BY g.user_id, l.grading_category_id
-        "#
-    )
-    .bind(course_id)
-    .fetch_all(&pool)
     .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
-
-    // 4. Construir CSV
-    let mut csv = "Name,Email,Cohort,Progress,Overall Score".to_string();
-    for cat in &categories {
-        csv.push_str(&format!(",{}", cat.name));
-    }
-    csv.push('\n');
-
-    for s in students {
-        let cohort = s.cohort_name.unwrap_or_else(|| "N/A".to_string());
-        let progress = format!("{:.1}%", s.progress.unwrap_or(0.0) * 100.0);
-        let overall = s
-            .average_score
-            .map(|v| format!("{:.1}%", v * 100.0))
-            .unwrap_or_else(|| "N/A".to_string());
-
-        csv.push_str(&format!(
-            "\"{}\",{},\"{}\",{},{}",
-            s.full_name, s.email, cohort, progress, overall
-        ));
-
-        for cat in &categories {
-            let score = detailed_grades
-                .iter()
-                .find(|g| g.user_id == s.id && g.grading_category_id == Some(cat.id))
-                .and_then(|g| g.avg_score);
-
-            match score {
-                Some(v) => csv.push_str(&format!(", {:.1}%", v * 100.0)),
-                None => csv.push_str(",N/A"),
-            }
-        }
-        csv.push('\n');
-    }
-
-    let disposition = format!("attachment; filename=\"grades-{}.csv\"", course_id);
-
-    Ok(axum::response::Response::builder()
-        .header(axum::http::header::CONTENT_TYPE, "text/csv")
-        .header(axum::http::header::CONTENT_DISPOSITION, disposition)
-        .body(axum::body::Body::from(csv))
-        .map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Error al construir la respuesta".to_string(),
-            )
-        })?
-        .into_response())
 }
 
 pub async fn enroll_user(
@@ -732,12 +623,22 @@ pub async fn enroll_user(
             }),
         )
         .await;
+    crate::plugin_host::dispatch_event(
+        &pool,
+        org_ctx.id,
+        "user.enrolled",
+        serde_json::json!({
+            "user_id": user_id,
+            "course_id": course_id,
+            "enrollment_id": enrollment.id
+        }),
+    );
 
     // Email transaccional de bienvenida (fire-and-forget)
     {
         let pool_clone = pool.clone();
         let org_id = org_ctx.id;
-        tokio::spawn(async move {
+        tenancy::spawn(async move {
             let user_row = sqlx::query_as::<_, (String, Option<String>)>(
                 "SELECT email, full_name FROM users WHERE id = $1",
             )
@@ -798,6 +699,9 @@ pub struct AudioGradingResponse {
     pub feedback: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub transcript: Option<String>,
+    /// Evaluación de pronunciación por palabra cuando el bloque tiene texto de referencia
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pronunciation: Option<crate::pronunciation::PronunciationAssessment>,
 }
 
 #[derive(Deserialize)]
@@ -992,19 +896,24 @@ pub async fn get_course_catalog(
         query.organization_id,
         query.user_id
     );
-    let courses = if let Some(user_id) = query.user_id {
-        sqlx::query_as::<_, Course>(
-            "SELECT DISTINCT c.* FROM courses c 
-             LEFT JOIN enrollments e ON c.id = e.course_id AND e.user_id = $1"
-        )
-        .bind(user_id)
-        .fetch_all(&pool)
-        .await
-    } else {
-        sqlx::query_as::<_, Course>("SELECT * FROM courses")
+    // Ruta pública: las consultas se limitan por RLS a la organización solicitada
+    let scope = TenantScope::Organization(query.organization_id.unwrap_or(tenancy::DEFAULT_ORG_ID));
+    let courses = tenancy::with_scope(scope, async {
+        if let Some(user_id) = query.user_id {
+            sqlx::query_as::<_, Course>(
+                "SELECT DISTINCT c.* FROM courses c 
+                 LEFT JOIN enrollments e ON c.id = e.course_id AND e.user_id = $1"
+            )
+            .bind(user_id)
             .fetch_all(&pool)
             .await
-    }
+        } else {
+            sqlx::query_as::<_, Course>("SELECT * FROM courses")
+                .fetch_all(&pool)
+                .await
+        }
+    })
+    .await
     .map_err(|e: sqlx::Error| {
         tracing::error!("Catalog fetch failed: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
@@ -1170,6 +1079,13 @@ pub async fn ingest_course(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
         for lesson in &pub_module.lessons {
+            // Los casos ocultos de code-lab se guardan aparte y no llegan al estudiante
+            let mut metadata = lesson.metadata.clone();
+            let mut content_blocks = lesson.content_blocks.clone();
+            let mut hidden_tests =
+                crate::code_runner::take_hidden_tests(metadata.as_mut().and_then(|m| m.get_mut("blocks")));
+            hidden_tests.extend(crate::code_runner::take_hidden_tests(content_blocks.as_mut()));
+
             sqlx::query(
                 "INSERT INTO lessons (id, module_id, title, content_type, content_url, transcription, metadata, position, created_at, is_graded, grading_category_id, max_attempts, allow_retry, organization_id, summary, due_date, important_date_type, transcription_status, is_previewable, content_blocks)
                  VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20)"
@@ -1180,7 +1096,7 @@ pub async fn ingest_course(
             .bind(&lesson.content_type)
             .bind(&lesson.content_url)
             .bind(&lesson.transcription)
-            .bind(&lesson.metadata)
+            .bind(&metadata)
             .bind(lesson.position)
             .bind(lesson.created_at)
             .bind(lesson.is_graded)
@@ -1193,16 +1109,63 @@ pub async fn ingest_course(
             .bind(&lesson.important_date_type)
             .bind(&lesson.transcription_status)
             .bind(lesson.is_previewable)
-            .bind(&lesson.content_blocks)
+            .bind(&content_blocks)
             .execute(&mut *tx)
             .await
             .map_err(|e: sqlx::Error| {
                 tracing::error!("Error al insertar la lección: {}", e);
                 StatusCode::INTERNAL_SERVER_ERROR
             })?;
+
+            crate::code_runner::store_hidden_tests(&mut tx, org_id, lesson.id, hidden_tests)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Error al guardar los casos ocultos de code-lab: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
         }
     }
 
+    // 4.1 Dependencias y ramas adaptativas (las filas previas caen en cascada con las lecciones)
+    if let Some(dependencies) = &payload.dependencies {
+        let lesson_ids: std::collections::HashSet<Uuid> =
+            payload.modules.iter().flat_map(|m| m.lessons.iter().map(|l| l.id)).collect();
+        for dep in dependencies
+            .iter()
+            .filter(|d| lesson_ids.contains(&d.lesson_id) && lesson_ids.contains(&d.prerequisite_lesson_id))
+        {
+            sqlx::query(
+                "INSERT INTO lesson_dependencies (id, organization_id, lesson_id, prerequisite_lesson_id, min_score_percentage, branch_type, max_score_percentage, time_threshold_minutes, created_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)"
+            )
+            .bind(dep.id)
+            .bind(org_id)
+            .bind(dep.lesson_id)
+            .bind(dep.prerequisite_lesson_id)
+            .bind(dep.min_score_percentage)
+            .bind(&dep.branch_type)
+            .bind(dep.max_score_percentage)
+            .bind(dep.time_threshold_minutes)
+            .bind(dep.created_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e: sqlx::Error| {
+                tracing::error!("Error al insertar la dependencia de lección: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        }
+    }
+
+    // 4.2 Resultados de aprendizaje alineados (solo si el CMS los envía)
+    if let Some(outcomes) = &payload.outcomes {
+        crate::outcome_mastery::replace_course_outcomes(&mut tx, org_id, payload.course.id, outcomes)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error al guardar los resultados de aprendizaje: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+    }
+
     tx.commit()
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -1222,6 +1185,16 @@ pub async fn ingest_course(
         }
     }
 
+    crate::plugin_host::dispatch_event(
+        &pool,
+        org_id,
+        "course.published",
+        serde_json::json!({
+            "course_id": payload.course.id,
+            "title": payload.course.title
+        }),
+    );
+
     Ok(StatusCode::OK)
 }
 
@@ -1314,7 +1287,7 @@ pub async fn get_course_outline(
     // 5. Obtener lecciones
     let mut pub_modules = Vec::new();
     for module in modules {
-        let lessons = sqlx::query_as::<_, Lesson>(
+        let mut lessons = sqlx::query_as::<_, Lesson>(
             "SELECT * FROM lessons WHERE module_id = $1 ORDER BY position",
         )
         .bind(module.id)
@@ -1329,6 +1302,13 @@ pub async fn get_course_outline(
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
+        if claims.role != "admin" && claims.role != "instructor" {
+            for lesson in &mut lessons {
+                crate::adaptive_testing::redact_item_pool(&mut lesson.metadata);
+                crate::quiz_variants::redact_pool_items(&mut lesson.metadata, &mut lesson.content_blocks);
+            }
+        }
+
         pub_modules.push(common::models::PublishedModule { module, lessons });
     }
 
@@ -1368,6 +1348,7 @@ pub async fn get_course_outline(
         modules: pub_modules,
         instructors: Some(instructors),
         dependencies: Some(dependencies),
+        outcomes: None,
     }))
 }
 
@@ -1420,7 +1401,7 @@ pub async fn get_lesson_content(
         })?
     };
 
-    let lesson = match lesson {
+    let mut lesson = match lesson {
         Some(l) => l,
         None => {
             tracing::warn!(
@@ -1432,6 +1413,12 @@ pub async fn get_lesson_content(
         }
     };
 
+    // Los bancos de pruebas adaptativas y pools incluyen las respuestas correctas
+    if claims.role != "admin" && claims.role != "instructor" {
+        crate::adaptive_testing::redact_item_pool(&mut lesson.metadata);
+        crate::quiz_variants::redact_pool_items(&mut lesson.metadata, &mut lesson.content_blocks);
+    }
+
     // 2. Aplicar prerrequisitos (Omitir para vistas previas)
     if is_preview {
         return Ok(Json(lesson));
@@ -1449,7 +1436,7 @@ pub async fn get_lesson_content(
         LEFT JOIN user_grades ug ON ld.prerequisite_lesson_id = ug.lesson_id AND ug.user_id = $2
         LEFT JOIN lesson_interactions li ON ld.prerequisite_lesson_id = li.lesson_id 
             AND li.user_id = $2 AND li.event_type = 'complete'
-        WHERE ld.lesson_id = $1
+        WHERE ld.lesson_id = $1 AND ld.branch_type = 'prerequisite'
         AND (
             (p.is_graded = true AND (ug.score IS NULL OR (ug.score * 100.0) < COALESCE(ld.min_score_percentage, 0.0)))
             OR
@@ -1481,6 +1468,18 @@ pub async fn get_lesson_content(
         return Err(StatusCode::FORBIDDEN);
     }
 
+    // Las ramas remediales y de profundización solo se abren según el desempeño previo
+    let branch_locked = crate::adaptive_paths::is_branch_locked(&pool, lesson.organization_id, claims.sub, id)
+        .await
+        .map_err(|e| {
+            tracing::error!("get_lesson_content: failed to evaluate adaptive branch: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if branch_locked {
+        tracing::warn!("get_lesson_content: User {} blocked for adaptive branch lesson {}", claims.sub, id);
+        return Err(StatusCode::FORBIDDEN);
+    }
+
     Ok(Json(lesson))
 }
 
@@ -1489,6 +1488,7 @@ pub struct CollaborativeCanvasResponse {
     pub lesson_id: Uuid,
     pub canvas_state: serde_json::Value,
     pub revision: i64,
+    pub head_seq: i64,
     pub updated_at: Option<DateTime<Utc>>,
 }
 
@@ -1502,6 +1502,7 @@ pub struct UpdateCollaborativeCanvasPayload {
 pub struct UpdateCollaborativeCanvasResponse {
     pub lesson_id: Uuid,
     pub revision: i64,
+    pub head_seq: i64,
     pub updated_at: DateTime<Utc>,
 }
 
@@ -1535,12 +1536,14 @@ pub async fn get_lesson_collaborative_canvas(
     struct CanvasRow {
         canvas_state: serde_json::Value,
         revision: i64,
+        head_seq: i64,
+        compacted_seq: i64,
         updated_at: DateTime<Utc>,
     }
 
     let canvas = sqlx::query_as::<_, CanvasRow>(
         r#"
-        SELECT canvas_state, revision, updated_at
+        SELECT canvas_state, revision, head_seq, compacted_seq, updated_at
         FROM lesson_collaborative_canvases
         WHERE lesson_id = $1 AND organization_id = $2
         "#,
@@ -1559,10 +1562,28 @@ pub async fn get_lesson_collaborative_canvas(
     })?;
 
     let response = if let Some(canvas) = canvas {
+        // El snapshot puede ir detrás del log de operaciones hasta la próxima compactación
+        let canvas_state = crate::handlers_canvas_ops::materialize_canvas(
+            &pool,
+            id,
+            canvas.canvas_state,
+            canvas.compacted_seq,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!(
+                "get_lesson_collaborative_canvas: failed to apply pending ops for lesson {}: {}",
+                id,
+                e
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
         CollaborativeCanvasResponse {
             lesson_id: id,
-            canvas_state: canvas.canvas_state,
+            canvas_state,
             revision: canvas.revision,
+            head_seq: canvas.head_seq,
             updated_at: Some(canvas.updated_at),
         }
     } else {
@@ -1570,6 +1591,7 @@ pub async fn get_lesson_collaborative_canvas(
             lesson_id: id,
             canvas_state: json!({}),
             revision: 0,
+            head_seq: 0,
             updated_at: None,
         }
     };
@@ -1608,10 +1630,16 @@ pub async fn update_lesson_collaborative_canvas(
         return Err((StatusCode::NOT_FOUND, "Lección no encontrada".to_string()));
     }
 
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
     if let Some(expected_revision) = payload.expected_revision {
         #[derive(sqlx::FromRow)]
         struct RevisionRow {
             revision: i64,
+            head_seq: i64,
             updated_at: DateTime<Utc>,
         }
 
@@ -1622,11 +1650,13 @@ pub async fn update_lesson_collaborative_canvas(
                 canvas_state = $3,
                 updated_by = $4,
                 updated_at = NOW(),
-                revision = revision + 1
+                revision = revision + 1,
+                head_seq = head_seq + 1,
+                compacted_seq = head_seq + 1
             WHERE lesson_id = $1
               AND organization_id = $2
               AND revision = $5
-            RETURNING revision, updated_at
+            RETURNING revision, head_seq, updated_at
             "#,
         )
         .bind(id)
@@ -1634,7 +1664,7 @@ pub async fn update_lesson_collaborative_canvas(
         .bind(&payload.canvas_state)
         .bind(claims.sub)
         .bind(expected_revision)
-        .fetch_optional(&pool)
+        .fetch_optional(&mut *tx)
         .await
         .map_err(|e| {
             tracing::error!(
@@ -1649,9 +1679,11 @@ pub async fn update_lesson_collaborative_canvas(
         })?;
 
         if let Some(row) = updated {
+            record_canvas_replace(tx, id, org_ctx.id, claims.sub, row.head_seq, &payload.canvas_state).await?;
             return Ok(Json(UpdateCollaborativeCanvasResponse {
                 lesson_id: id,
                 revision: row.revision,
+                head_seq: row.head_seq,
                 updated_at: row.updated_at,
             }));
         }
@@ -1665,18 +1697,20 @@ pub async fn update_lesson_collaborative_canvas(
                     canvas_state,
                     updated_by,
                     revision,
+                    head_seq,
+                    compacted_seq,
                     updated_at
                 )
-                VALUES ($1, $2, $3, $4, 1, NOW())
+                VALUES ($1, $2, $3, $4, 1, 1, 1, NOW())
                 ON CONFLICT (lesson_id) DO NOTHING
-                RETURNING revision, updated_at
+                RETURNING revision, head_seq, updated_at
                 "#,
             )
             .bind(id)
             .bind(org_ctx.id)
             .bind(&payload.canvas_state)
             .bind(claims.sub)
-            .fetch_optional(&pool)
+            .fetch_optional(&mut *tx)
             .await
             .map_err(|e| {
                 tracing::error!(
@@ -1691,9 +1725,11 @@ pub async fn update_lesson_collaborative_canvas(
             })?;
 
             if let Some(row) = inserted {
+                record_canvas_replace(tx, id, org_ctx.id, claims.sub, row.head_seq, &payload.canvas_state).await?;
                 return Ok(Json(UpdateCollaborativeCanvasResponse {
                     lesson_id: id,
                     revision: row.revision,
+                    head_seq: row.head_seq,
                     updated_at: row.updated_at,
                 }));
             }
@@ -1708,6 +1744,7 @@ pub async fn update_lesson_collaborative_canvas(
     #[derive(sqlx::FromRow)]
     struct RevisionRow {
         revision: i64,
+        head_seq: i64,
         updated_at: DateTime<Utc>,
     }
 
@@ -1719,23 +1756,27 @@ pub async fn update_lesson_collaborative_canvas(
             canvas_state,
             updated_by,
             revision,
+            head_seq,
+            compacted_seq,
             updated_at
         )
-        VALUES ($1, $2, $3, $4, 1, NOW())
+        VALUES ($1, $2, $3, $4, 1, 1, 1, NOW())
         ON CONFLICT (lesson_id)
         DO UPDATE SET
             canvas_state = EXCLUDED.canvas_state,
             updated_by = EXCLUDED.updated_by,
             updated_at = NOW(),
-            revision = lesson_collaborative_canvases.revision + 1
-        RETURNING revision, updated_at
+            revision = lesson_collaborative_canvases.revision + 1,
+            head_seq = lesson_collaborative_canvases.head_seq + 1,
+            compacted_seq = lesson_collaborative_canvases.head_seq + 1
+        RETURNING revision, head_seq, updated_at
         "#,
     )
     .bind(id)
     .bind(org_ctx.id)
     .bind(&payload.canvas_state)
     .bind(claims.sub)
-    .fetch_one(&pool)
+    .fetch_one(&mut *tx)
     .await
     .map_err(|e| {
         tracing::error!(
@@ -1749,13 +1790,44 @@ pub async fn update_lesson_collaborative_canvas(
         )
     })?;
 
+    record_canvas_replace(tx, id, org_ctx.id, claims.sub, row.head_seq, &payload.canvas_state).await?;
+
     Ok(Json(UpdateCollaborativeCanvasResponse {
         lesson_id: id,
         revision: row.revision,
+        head_seq: row.head_seq,
         updated_at: row.updated_at,
     }))
 }
 
+/// Registra el reemplazo en el log de operaciones y confirma la transacción del PUT.
+async fn record_canvas_replace(
+    mut tx: sqlx::Transaction<'_, sqlx::Postgres>,
+    lesson_id: Uuid,
+    org_id: Uuid,
+    user_id: Uuid,
+    seq: i64,
+    canvas_state: &serde_json::Value,
+) -> Result<(), (StatusCode, String)> {
+    crate::handlers_canvas_ops::record_replace_op(&mut tx, lesson_id, org_id, user_id, seq, canvas_state)
+        .await
+        .map_err(|e| {
+            tracing::error!(
+                "update_lesson_collaborative_canvas: failed to log replace for lesson {} seq {}: {}",
+                lesson_id,
+                seq,
+                e
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error guardando canvas colaborativo".to_string(),
+            )
+        })?;
+    tx.commit()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))
+}
+
 pub async fn get_user_enrollments(
     Org(org_ctx): Org,
     State(pool): State<PgPool>,
@@ -1896,18 +1968,63 @@ pub async fn submit_lesson_score(
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
 
-    // 1. Obtener reglas de intentos de la lección
-    let max_attempts: Option<Option<i32>> =
-        sqlx::query_scalar("SELECT max_attempts FROM lessons WHERE id = $1")
+    // 1. Obtener reglas de intentos y bloques de la lección
+    let lesson: Option<(Option<i32>, Option<serde_json::Value>, Option<serde_json::Value>)> =
+        sqlx::query_as("SELECT max_attempts, metadata, content_blocks FROM lessons WHERE id = $1")
             .bind(payload.lesson_id)
             .fetch_optional(&mut *tx)
             .await
             .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let (max_attempts, lesson_metadata, content_blocks) =
+        lesson.ok_or((StatusCode::NOT_FOUND, "Lección no encontrada".to_string()))?;
 
-    if max_attempts.is_none() {
-        return Err((StatusCode::NOT_FOUND, "Lección no encontrada".into()));
+    // 1.1 Exámenes con tiempo: la entrega debe corresponder a un intento vigente
+    let exam_submission = crate::exam_attempts::authorize_exam_submission(
+        &mut tx,
+        &claims,
+        &headers,
+        payload.user_id,
+        payload.lesson_id,
+        payload.metadata.as_ref(),
+    )
+    .await?;
+
+    // 1.2 Política de entrega tardía (no aplica cuando el personal registra la nota de otro usuario)
+    let staff_entry = payload.user_id != claims.sub && (claims.role == "admin" || claims.role == "instructor");
+    let late_penalty = if staff_entry {
+        None
+    } else {
+        Some(crate::late_policies::enforce_late_policy(&mut tx, payload.user_id, payload.lesson_id).await?)
+    };
+
+    // 1.3 Las lecciones adaptativas y de pool solo se califican en el servidor, y los
+    // bloques corregidos en el servidor (code-lab, plugins) conservan el puntaje guardado
+    let mut score = payload.score;
+    let mut metadata = payload.metadata.clone();
+    let mut stored: Option<serde_json::Value> = None;
+    if !staff_entry {
+        crate::grading::require_block_graded_lesson(lesson_metadata.as_ref(), content_blocks.as_ref())?;
+        stored = sqlx::query_scalar(
+            "SELECT metadata FROM user_grades WHERE user_id = $1 AND lesson_id = $2 AND organization_id = $3 FOR UPDATE",
+        )
+        .bind(payload.user_id)
+        .bind(payload.lesson_id)
+        .bind(org_ctx.id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+        .flatten();
+        let blocks = crate::grading::lesson_blocks(lesson_metadata.as_ref(), content_blocks.as_ref());
+        let plugin_types = crate::grading::plugin_block_types(&mut tx, org_ctx.id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+        if let Some((protected_score, protected_metadata)) =
+            crate::grading::protect_server_scores(&blocks, &plugin_types, metadata.as_ref(), stored.as_ref())
+        {
+            score = protected_score;
+            metadata = Some(protected_metadata);
+        }
     }
-    let max_attempts = max_attempts.flatten();
 
     // 2. Comprobar calificación/intentos existentes
     let existing_attempts: Option<i32> = sqlx::query_scalar("SELECT attempts_count FROM user_grades WHERE user_id = $1 AND lesson_id = $2 AND organization_id = $3")
@@ -1918,30 +2035,63 @@ pub async fn submit_lesson_score(
         .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
 
-    if let Some(count) = existing_attempts {
-        if let Some(max) = max_attempts {
-            if count >= max {
-                return Err((
-                    StatusCode::FORBIDDEN,
-                    "Se ha alcanzado el número máximo de intentos para esta evaluación".into(),
-                ));
-            }
-        }
+    // Los intentos de examen ya validaron el límite al iniciarse (o fueron reabiertos)
+    if let Some(count) = existing_attempts.filter(|_| !exam_submission)
+        && let Some(max) = max_attempts
+        && count >= max
+    {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Se ha alcanzado el número máximo de intentos para esta evaluación".into(),
+        ));
     }
 
     // 3. Upsert con lógica de BD automatizada (XP, insignias)
-    let grade = sqlx::query_as::<_, common::models::UserGrade>(
-        "SELECT * FROM fn_upsert_user_grade($1, $2, $3, $4, $5, $6)",
+    let grade = crate::grading::upsert_grade(
+        &mut tx,
+        org_ctx.id,
+        payload.user_id,
+        payload.course_id,
+        payload.lesson_id,
+        score,
+        metadata.as_ref(),
+        late_penalty,
     )
-    .bind(org_ctx.id)
-    .bind(payload.user_id)
-    .bind(payload.course_id)
-    .bind(payload.lesson_id)
-    .bind(payload.score)
-    .bind(payload.metadata)
-    .fetch_one(&mut *tx)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    .await?;
+
+    // 3.0 Respuestas por ítem para el análisis psicométrico
+    if let Some(metadata) = &metadata {
+        crate::psychometrics::record_item_responses(
+            &mut tx,
+            org_ctx.id,
+            payload.user_id,
+            payload.course_id,
+            payload.lesson_id,
+            grade.attempts_count,
+            metadata,
+            stored.as_ref(),
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Error al registrar respuestas por ítem: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+        })?;
+
+        // 3.0.1 Preguntas falladas a la cola de repaso espaciado
+        crate::spaced_repetition::enqueue_missed_items(
+            &mut tx,
+            org_ctx.id,
+            payload.user_id,
+            payload.course_id,
+            payload.lesson_id,
+            grade.attempts_count,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Error al generar tarjetas de repaso: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+        })?;
+    }
 
     // 3.1 Sincronizar con MySQL externo si está disponible
     if let Some(mysql_pool) = mysql_pool {
@@ -1960,7 +2110,8 @@ pub async fn submit_lesson_score(
             let table = env::var("EXTERNAL_TABLE_GRADES").unwrap_or_else(|_| "notas".to_string());
 
             // La tabla MySQL externa usa exactamente la misma escala 0-100.
-            let nota = payload.score.round() as i32;
+            // La nota sincronizada ya incluye la penalización por entrega tardía.
+            let nota = (score * (1.0 - late_penalty.unwrap_or(0.0))).round() as i32;
 
             // Resolver idTipoNota desde la categoría de calificación de la lección (tipo_nota_id),
             // recurriendo a la variable de entorno EXTERNAL_ID_TIPO_NOTA.
@@ -2008,45 +2159,17 @@ pub async fn submit_lesson_score(
         .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
 
-    // 4. Enviar Webhooks
-    let webhook_service = common::webhooks::WebhookService::new(pool.clone());
-
-    // lesson.completed
-    webhook_service
-        .dispatch(
-            org_ctx.id,
-            "lesson.completed",
-            &serde_json::json!({
-                "user_id": payload.user_id,
-                "course_id": payload.course_id,
-                "lesson_id": payload.lesson_id,
-                "score": payload.score
-            }),
-        )
-        .await;
-
-    // Lógica de detección de finalización de curso
-    if let Ok(course_completion) = calculate_course_completion(&pool, payload.user_id, payload.course_id).await {
-        if course_completion.completed {
-            webhook_service
-                .dispatch(
-                    org_ctx.id,
-                    "course.completed",
-                    &serde_json::json!({
-                        "user_id": payload.user_id,
-                        "course_id": payload.course_id,
-                        "progress_percentage": course_completion.progress_percentage
-                    }),
-                )
-                .await;
-        }
-    } else {
-        tracing::warn!(
-            "No se pudo calcular la completitud real del curso {} para el usuario {}",
-            payload.course_id,
-            payload.user_id
-        );
-    }
+    // 4. Webhooks, eventos de plugins y finalización del curso
+    crate::grading::notify_grade_recorded(
+        &pool,
+        org_ctx.id,
+        payload.user_id,
+        payload.course_id,
+        payload.lesson_id,
+        score,
+        grade.attempts_count,
+    )
+    .await;
 
     Ok(Json(grade))
 }
@@ -2170,6 +2293,18 @@ pub async fn get_course_grades(
         (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
     })?;
 
+    // La nota media se sustituye por la nota final ponderada del libro de calificaciones
+    let final_scores = crate::gradebook::final_scores(&pool, org_ctx.id, course_id)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let rows = rows
+        .into_iter()
+        .map(|mut row| {
+            row.average_score = final_scores.get(&row.user_id).copied().flatten();
+            row
+        })
+        .collect();
+
     Ok(Json(rows))
 }
 
@@ -2473,9 +2608,27 @@ pub async fn get_advanced_analytics(
     State(pool): State<PgPool>,
     Path(course_id): Path<Uuid>,
 ) -> Result<Json<common::models::AdvancedAnalytics>, StatusCode> {
-    // 1. Análisis de cohorte usando función de BD
+    // 1. Cohortes por mes de inscripción, con el avance tomado de los rollups
     let cohort_data = sqlx::query_as::<_, common::models::CohortData>(
-        "SELECT period, student_count as count, completion_rate FROM fn_get_cohort_analytics($1, $2)",
+        r#"
+        WITH total AS (
+            SELECT COUNT(*)::float4 AS lessons
+            FROM lessons l JOIN modules m ON m.id = l.module_id
+            WHERE m.course_id = $1
+        )
+        SELECT TO_CHAR(e.enrolled_at, 'YYYY-MM') AS period,
+               COUNT(*)::bigint AS count,
+               COALESCE(AVG(COALESCE(done.lessons, 0) / NULLIF((SELECT lessons FROM total), 0)), 0)::float4 AS completion_rate
+        FROM enrollments e
+        LEFT JOIN LATERAL (
+            SELECT COUNT(*)::float4 AS lessons
+            FROM la_lesson_progress p
+            WHERE p.course_id = e.course_id AND p.user_id = e.user_id AND p.completed
+        ) done ON TRUE
+        WHERE e.course_id = $1 AND e.organization_id = $2
+        GROUP BY period
+        ORDER BY period DESC
+        "#,
     )
     .bind(course_id)
     .bind(org_ctx.id)
@@ -2486,9 +2639,24 @@ pub async fn get_advanced_analytics(
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    // 2. Análisis de retención usando función de BD
+    // 2. Retención por lección: alumnos que la abrieron y proporción de inscritos que la completó
     let retention_data = sqlx::query_as::<_, common::models::RetentionData>(
-        "SELECT lesson_id, lesson_title, student_count, completion_rate FROM fn_get_retention_data($1, $2)",
+        r#"
+        SELECT l.id AS lesson_id,
+               l.title AS lesson_title,
+               COUNT(p.user_id)::bigint AS student_count,
+               COALESCE(
+                   COUNT(p.user_id) FILTER (WHERE p.completed)::float4
+                       / NULLIF((SELECT COUNT(*) FROM enrollments WHERE course_id = $1 AND organization_id = $2), 0)::float4,
+                   0
+               )::float4 AS completion_rate
+        FROM lessons l
+        JOIN modules m ON m.id = l.module_id
+        LEFT JOIN la_lesson_progress p ON p.lesson_id = l.id
+        WHERE m.course_id = $1
+        GROUP BY l.id, l.title, m.position, l.position
+        ORDER BY m.position, l.position
+        "#,
     )
     .bind(course_id)
     .bind(org_ctx.id)
@@ -2538,11 +2706,11 @@ pub async fn get_lesson_heatmap(
     State(pool): State<PgPool>,
 ) -> Result<Json<Vec<HeatmapPoint>>, StatusCode> {
     let heatmap = sqlx::query_as::<_, HeatmapPoint>(
-        "SELECT floor(video_timestamp)::int as second, count(*)::bigint as count 
-         FROM lesson_interactions 
-         WHERE lesson_id = $1 AND organization_id = $2 AND video_timestamp IS NOT NULL
-         GROUP BY second 
-         ORDER BY second",
+        "SELECT h.second, h.views AS count
+         FROM la_video_heatmap h
+         JOIN lessons l ON l.id = h.lesson_id
+         WHERE h.lesson_id = $1 AND l.organization_id = $2
+         ORDER BY h.second",
     )
     .bind(lesson_id)
     .bind(org_ctx.id)
@@ -2600,10 +2768,12 @@ pub async fn mark_notification_as_read(
 
 /// GET /notifications/stream  (SSE)
 /// Emite eventos cuando el recuento de no leídas o la notificación más reciente cambia.
+/// Solo consulta la BD cuando el tópico `notifications:{user_id}` recibe un aviso.
 pub async fn stream_notifications(
     Org(org_ctx): Org,
     claims: Claims,
     State(pool): State<PgPool>,
+    Extension(bus): Extension<crate::event_bus::EventBus>,
 ) -> impl IntoResponse {
     use std::convert::Infallible;
     use tokio_stream::wrappers::ReceiverStream;
@@ -2611,9 +2781,10 @@ pub async fn stream_notifications(
     let user_id = claims.sub;
     let org_id = org_ctx.id;
 
+    let mut events = bus.subscribe(format!("notifications:{}", user_id));
     let (tx, rx) = tokio::sync::mpsc::channel::<Result<Event, Infallible>>(32);
 
-    tokio::spawn(async move {
+    tenancy::spawn(async move {
         #[derive(sqlx::FromRow)]
         struct NotifSnapshot {
             unread_count: i64,
@@ -2622,9 +2793,16 @@ pub async fn stream_notifications(
 
         let mut last_unread: i64 = -1;
         let mut last_latest: Option<Uuid> = None;
+        let mut initial = true;
 
         loop {
-            tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+            if !initial {
+                tokio::select! {
+                    _ = tx.closed() => break,
+                    _ = events.next_change() => {}
+                }
+            }
+            initial = false;
 
             let snap = sqlx::query_as::<_, NotifSnapshot>(
                 "SELECT COUNT(*) FILTER (WHERE is_read = FALSE) AS unread_count, \
@@ -3175,13 +3353,34 @@ pub async fn evaluate_audio_file(
         .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid block_id".into()))?;
 
     // Get course_id from lesson (lessons has module_id, modules has course_id)
-    let course_id: Uuid = sqlx::query_scalar(
-        "SELECT m.course_id FROM lessons l JOIN modules m ON l.module_id = m.id WHERE l.id = $1"
-    )
-    .bind(lesson_id)
-    .fetch_one(&pool)
-    .await
-    .map_err(|_| (StatusCode::NOT_FOUND, "Lesson not found".into()))?;
+    let (course_id, lesson_metadata, content_blocks): (Uuid, Option<serde_json::Value>, Option<serde_json::Value>) =
+        sqlx::query_as(
+            "SELECT m.course_id, l.metadata, l.content_blocks FROM lessons l JOIN modules m ON l.module_id = m.id WHERE l.id = $1 AND l.organization_id = $2"
+        )
+        .bind(lesson_id)
+        .bind(org_ctx.id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| (StatusCode::NOT_FOUND, "Lesson not found".into()))?;
+
+    // El texto de referencia, el enunciado y las palabras clave salen del bloque guardado,
+    // no del formulario: el alumno no puede fijar la respuesta con la que se le evalúa
+    let blocks = crate::grading::lesson_blocks(lesson_metadata.as_ref(), content_blocks.as_ref());
+    let block = blocks
+        .iter()
+        .find(|b| b.get("id").and_then(|v| v.as_str()) == Some(block_id_str.as_str()))
+        .ok_or((StatusCode::NOT_FOUND, "Bloque no encontrado".to_string()))?;
+    let expected_text = block
+        .get("expectedText")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+    if let Some(block_prompt) = block.get("prompt").and_then(|v| v.as_str()) {
+        prompt = block_prompt.to_string();
+    }
+    if let Some(block_keywords) = block.get("keywords").filter(|v| v.is_array()) {
+        keywords_str = block_keywords.to_string();
+    }
 
     // 1. Enviar a Whisper
     let whisper_url =
@@ -3194,7 +3393,8 @@ pub async fn evaluate_audio_file(
             reqwest::multipart::Part::bytes(audio_data.clone()).file_name(filename.clone()),
         )
         .text("model", "whisper-1")
-        .text("response_format", "json");
+        .text("response_format", "verbose_json")
+        .text("timestamp_granularities[]", "word");
 
     let response = client
         .post(format!("{}/v1/audio/transcriptions", whisper_url))
@@ -3247,6 +3447,14 @@ pub async fn evaluate_audio_file(
             .collect()
     };
 
+    // Evaluación de pronunciación con las marcas de tiempo por palabra de Whisper
+    let pronunciation = (!expected_text.trim().is_empty()).then(|| {
+        crate::pronunciation::assess(
+            &expected_text,
+            &crate::pronunciation::words_from_whisper(&transcription_result),
+        )
+    });
+
     // 2. Realizar calificación por IA
     let provider = env::var("AI_PROVIDER").unwrap_or_else(|_| "openai".to_string());
     let openai_api_key = if provider != "local" {
@@ -3278,10 +3486,17 @@ pub async fn evaluate_audio_file(
         Da retroalimentación constructiva en español sobre su pronunciación (basándote en la calidad de la transcripción) y contenido. \
         Devuelve ÚNICAMENTE un objeto JSON: { \"score\": number, \"found_keywords\": [string], \"feedback\": string }.";
 
-    let user_content = format!(
+    let mut user_content = format!(
         "Prompt: {}\nExpected Keywords: {:?}\nStudent Transcript: {}",
         prompt, keywords, transcript
     );
+    if let Some(assessment) = &pronunciation {
+        user_content.push_str(&format!(
+            "\nExpected Text: {}\nPronunciation Assessment: {}",
+            assessment.expected_text,
+            crate::pronunciation::summary(assessment)
+        ));
+    }
 
     let response = client
         .post(&url)
@@ -3324,6 +3539,7 @@ pub async fn evaluate_audio_file(
             })
     ).map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
     grading.transcript = Some(transcript.clone());
+    grading.pronunciation = pronunciation;
 
     // 3. Guardar respuesta de audio en la base de datos
     // Determinar estado basado en la evaluación
@@ -3404,8 +3620,8 @@ pub async fn evaluate_audio_file(
         r#"INSERT INTO audio_responses 
         (id, organization_id, user_id, course_id, lesson_id, block_id, prompt, transcript, audio_url, audio_data, 
          ai_score, ai_found_keywords, ai_feedback, ai_evaluated_at, 
-         status, attempt_number, duration_seconds)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, NOW(), $14, $15, $16)"#
+         status, attempt_number, duration_seconds, pronunciation)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, NOW(), $14, $15, $16, $17)"#
     )
     .bind(response_id)
     .bind(org_ctx.id)
@@ -3423,6 +3639,7 @@ pub async fn evaluate_audio_file(
     .bind(status)
     .bind(attempt_number)
     .bind(duration_seconds)
+    .bind(grading.pronunciation.as_ref().and_then(|p| serde_json::to_value(p).ok()))
     .execute(&pool)
     .await;
 
@@ -3452,6 +3669,7 @@ pub struct AudioResponseListItem {
     pub status: String,
     pub created_at: DateTime<Utc>,
     pub attempt_number: i32,
+    pub pronunciation: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -3462,7 +3680,7 @@ pub struct AudioResponseFilters {
     pub user_id: Option<Uuid>,
 }
 
-async fn instructor_has_course_access(
+pub async fn instructor_has_course_access(
     pool: &PgPool,
     org_id: Uuid,
     instructor_id: Uuid,
@@ -3493,6 +3711,26 @@ async fn instructor_has_course_access(
     Ok(has_access)
 }
 
+/// Exige rol admin, o instructor asignado al curso.
+pub async fn ensure_course_staff(
+    pool: &PgPool,
+    org_id: Uuid,
+    claims: &Claims,
+    course_id: Uuid,
+) -> Result<(), (StatusCode, String)> {
+    if claims.role != "admin" && claims.role != "instructor" {
+        return Err((StatusCode::FORBIDDEN, "Se requiere rol instructor o admin".to_string()));
+    }
+    if claims.role == "instructor"
+        && !instructor_has_course_access(pool, org_id, claims.sub, course_id)
+            .await
+            .map_err(|status| (status, "Error interno del servidor".to_string()))?
+    {
+        return Err((StatusCode::FORBIDDEN, "No tienes acceso a este curso".to_string()));
+    }
+    Ok(())
+}
+
 /// Obtener todas las respuestas de audio para profesores
 /// Filtros: course_id, lesson_id, estado (pending, ai_evaluated, teacher_evaluated, both_evaluated), user_id
 pub async fn get_audio_responses(
@@ -3530,7 +3768,8 @@ pub async fn get_audio_responses(
             ar.teacher_feedback,
             ar.status::text,
             ar.created_at,
-            ar.attempt_number
+            ar.attempt_number,
+            ar.pronunciation
         FROM audio_responses ar
         JOIN users u ON ar.user_id = u.id
         JOIN courses c ON ar.course_id = c.id
@@ -3607,7 +3846,8 @@ pub async fn get_audio_response_detail(
             ar.teacher_feedback,
             ar.status::text,
             ar.created_at,
-            ar.attempt_number
+            ar.attempt_number,
+            ar.pronunciation
         FROM audio_responses ar
         JOIN users u ON ar.user_id = u.id
         JOIN courses c ON ar.course_id = c.id
@@ -5023,6 +5263,7 @@ fn extract_block_content(metadata: &Option<serde_json::Value>) -> String {
 pub async fn stream_lesson_collaborative_canvas(
     Org(org_ctx): Org,
     State(pool): State<PgPool>,
+    Extension(bus): Extension<crate::event_bus::EventBus>,
     Path(id): Path<Uuid>,
 ) -> Result<impl IntoResponse, StatusCode> {
     use std::convert::Infallible;
@@ -5044,23 +5285,33 @@ pub async fn stream_lesson_collaborative_canvas(
         return Err(StatusCode::NOT_FOUND);
     }
 
+    let mut events = bus.subscribe(format!("canvas:{}", id));
     let (tx, rx) = tokio::sync::mpsc::channel::<Result<Event, Infallible>>(16);
 
-    tokio::spawn(async move {
+    tenancy::spawn(async move {
         #[derive(sqlx::FromRow)]
         struct CanvasRow {
             canvas_state: serde_json::Value,
             revision: i64,
+            head_seq: i64,
+            compacted_seq: i64,
             updated_at: DateTime<Utc>,
         }
 
         let mut last_revision: i64 = -1;
+        let mut initial = true;
 
         loop {
-            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+            if !initial {
+                tokio::select! {
+                    _ = tx.closed() => break,
+                    _ = events.next_change() => {}
+                }
+            }
+            initial = false;
 
             let result = sqlx::query_as::<_, CanvasRow>(
-                "SELECT canvas_state, revision, updated_at FROM lesson_collaborative_canvases WHERE lesson_id = $1 AND organization_id = $2",
+                "SELECT canvas_state, revision, head_seq, compacted_seq, updated_at FROM lesson_collaborative_canvases WHERE lesson_id = $1 AND organization_id = $2",
             )
             .bind(id)
             .bind(org_ctx.id)
@@ -5070,10 +5321,22 @@ pub async fn stream_lesson_collaborative_canvas(
             match result {
                 Ok(Some(row)) if row.revision != last_revision => {
                     last_revision = row.revision;
+                    let canvas_state = crate::handlers_canvas_ops::materialize_canvas(
+                        &pool,
+                        id,
+                        row.canvas_state,
+                        row.compacted_seq,
+                    )
+                    .await
+                    .unwrap_or_else(|e| {
+                        tracing::error!("stream_lesson_collaborative_canvas: failed to apply pending ops: {}", e);
+                        serde_json::json!({})
+                    });
                     let payload = serde_json::json!({
                         "lesson_id": id,
-                        "canvas_state": row.canvas_state,
+                        "canvas_state": canvas_state,
                         "revision": row.revision,
+                        "head_seq": row.head_seq,
                         "updated_at": row.updated_at.to_rfc3339(),
                     });
                     if tx.send(Ok(Event::default().data(payload.to_string()))).await.is_err() {
@@ -5082,7 +5345,7 @@ pub async fn stream_lesson_collaborative_canvas(
                 }
                 Ok(_) => {}
                 Err(e) => {
-                    tracing::error!("stream_lesson_collaborative_canvas: poll error: {}", e);
+                    tracing::error!("stream_lesson_collaborative_canvas: fetch error: {}", e);
                     let _ = tx.send(Ok(Event::default().event("error").data("poll_error"))).await;
                 }
             }
@@ -5313,6 +5576,7 @@ pub async fn update_lesson_collaborative_doc(
 pub async fn stream_lesson_collaborative_doc(
     Org(org_ctx): Org,
     State(pool): State<PgPool>,
+    Extension(bus): Extension<crate::event_bus::EventBus>,
     Path(id): Path<Uuid>,
 ) -> Result<impl IntoResponse, StatusCode> {
     use std::convert::Infallible;
@@ -5331,9 +5595,10 @@ pub async fn stream_lesson_collaborative_doc(
         return Err(StatusCode::NOT_FOUND);
     }
 
+    let mut events = bus.subscribe(format!("doc:{}", id));
     let (tx, rx) = tokio::sync::mpsc::channel::<Result<Event, Infallible>>(16);
 
-    tokio::spawn(async move {
+    tenancy::spawn(async move {
         #[derive(sqlx::FromRow)]
         struct DocRow {
             content: String,
@@ -5343,9 +5608,16 @@ pub async fn stream_lesson_collaborative_doc(
         }
 
         let mut last_revision: i64 = -1;
+        let mut initial = true;
 
         loop {
-            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+            if !initial {
+                tokio::select! {
+                    _ = tx.closed() => break,
+                    _ = events.next_change() => {}
+                }
+            }
+            initial = false;
 
             let result = sqlx::query_as::<_, DocRow>(
                 "SELECT content, revision, last_modified_by, updated_at FROM lesson_collaborative_docs WHERE lesson_id = $1 AND organization_id = $2",
@@ -5371,7 +5643,7 @@ pub async fn stream_lesson_collaborative_doc(
                 }
                 Ok(_) => {}
                 Err(e) => {
-                    tracing::error!("stream_lesson_collaborative_doc: poll error: {}", e);
+                    tracing::error!("stream_lesson_collaborative_doc: fetch error: {}", e);
                     let _ = tx.send(Ok(Event::default().event("error").data("poll_error"))).await;
                 }
             }
@@ -0,0 +1,699 @@
+/// Feeds iCalendar (RFC 5545) con fechas límite, reuniones y salas de estudio.
+///
+/// Cada feed se identifica con un token secreto en la URL, ya que los clientes de
+/// calendario no envían cabeceras de autenticación. Los UID de los eventos se derivan
+/// del id de la entidad de origen, de modo que los clientes actualizan el evento
+/// existente en lugar de duplicarlo.
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use common::{auth::Claims, middleware::Org};
+use rand::distributions::Alphanumeric;
+use rand::{Rng, thread_rng};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::env;
+use uuid::Uuid;
+
+/// Eventos pasados que se siguen publicando en el feed.
+const PAST_WINDOW_DAYS: i64 = 60;
+/// Duración asumida para salas de estudio sin hora de término.
+const DEFAULT_ROOM_MINUTES: i64 = 60;
+const UID_DOMAIN: &str = "openccb";
+
+// ─── Modelos ─────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize)]
+pub struct CalendarFeedInfo {
+    pub url: String,
+    pub webcal_url: String,
+    pub course_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(sqlx::FromRow)]
+struct FeedToken {
+    user_id: Uuid,
+    organization_id: Uuid,
+    course_id: Option<Uuid>,
+}
+
+#[derive(sqlx::FromRow)]
+struct DeadlineRow {
+    lesson_id: Uuid,
+    course_id: Uuid,
+    lesson_title: String,
+    course_title: String,
+    due_date: DateTime<Utc>,
+    extended: bool,
+    important_date_type: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct MeetingRow {
+    id: Uuid,
+    course_title: String,
+    title: String,
+    description: Option<String>,
+    start_at: DateTime<Utc>,
+    duration_minutes: i32,
+    join_url: Option<String>,
+    is_active: bool,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(sqlx::FromRow)]
+struct StudyRoomRow {
+    id: Uuid,
+    course_id: Uuid,
+    course_title: String,
+    title: String,
+    description: Option<String>,
+    status: String,
+    scheduled_at: Option<DateTime<Utc>>,
+    started_at: Option<DateTime<Utc>>,
+    ended_at: Option<DateTime<Utc>>,
+    updated_at: DateTime<Utc>,
+}
+
+/// Evento ya normalizado para serializar como `VEVENT`.
+struct CalendarEvent {
+    uid: String,
+    summary: String,
+    description: Option<String>,
+    url: Option<String>,
+    start: DateTime<Utc>,
+    end: Option<DateTime<Utc>>,
+    last_modified: Option<DateTime<Utc>>,
+    cancelled: bool,
+    /// Recordatorio antes del inicio, en minutos.
+    alarm_minutes: Option<i64>,
+}
+
+// ─── Serialización iCalendar ─────────────────────────────────────────────────
+
+fn ics_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+fn ics_datetime(dt: &DateTime<Utc>) -> String {
+    dt.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Pliega una línea de contenido a 75 octetos sin partir caracteres UTF-8 (RFC 5545 §3.1).
+fn fold_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + line.len() / 70 * 3);
+    let mut current = 0;
+    for ch in line.chars() {
+        let width = ch.len_utf8();
+        // Las líneas de continuación empiezan con un espacio que también cuenta
+        if current + width > 75 {
+            out.push_str("\r\n ");
+            current = 1;
+        }
+        out.push(ch);
+        current += width;
+    }
+    out.push_str("\r\n");
+    out
+}
+
+fn render_calendar(name: &str, events: &[CalendarEvent]) -> String {
+    let now = ics_datetime(&Utc::now());
+    let mut lines: Vec<String> = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//OpenCCB//LMS Calendar//ES".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", ics_escape(name)),
+        "REFRESH-INTERVAL;VALUE=DURATION:PT1H".to_string(),
+        "X-PUBLISHED-TTL:PT1H".to_string(),
+    ];
+
+    for event in events {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", event.uid));
+        lines.push(format!("DTSTAMP:{}", now));
+        lines.push(format!("DTSTART:{}", ics_datetime(&event.start)));
+        if let Some(end) = event.end {
+            lines.push(format!("DTEND:{}", ics_datetime(&end)));
+        }
+        if let Some(modified) = event.last_modified {
+            lines.push(format!("LAST-MODIFIED:{}", ics_datetime(&modified)));
+        }
+        lines.push(format!("SUMMARY:{}", ics_escape(&event.summary)));
+        if let Some(description) = &event.description {
+            lines.push(format!("DESCRIPTION:{}", ics_escape(description)));
+        }
+        if let Some(url) = &event.url {
+            lines.push(format!("URL:{}", url));
+        }
+        lines.push(format!(
+            "STATUS:{}",
+            if event.cancelled { "CANCELLED" } else { "CONFIRMED" }
+        ));
+        if let Some(minutes) = event.alarm_minutes.filter(|_| !event.cancelled) {
+            lines.push("BEGIN:VALARM".to_string());
+            lines.push("ACTION:DISPLAY".to_string());
+            lines.push(format!("DESCRIPTION:{}", ics_escape(&event.summary)));
+            lines.push(format!("TRIGGER:-PT{}M", minutes));
+            lines.push("END:VALARM".to_string());
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|l| fold_line(l)).collect()
+}
+
+// ─── Recolección de eventos ──────────────────────────────────────────────────
+
+fn experience_url() -> String {
+    env::var("EXPERIENCE_URL").unwrap_or_else(|_| "https://openccb.local".to_string())
+}
+
+fn deadline_label(kind: Option<&str>) -> &'static str {
+    match kind {
+        Some("exam") => "Examen",
+        Some("assignment") => "Entrega",
+        Some("milestone") => "Hito",
+        _ => "Fecha límite",
+    }
+}
+
+/// `user_id` es el dueño de un feed personal: sus fechas límite incluyen las
+/// prórrogas individuales, como en `late_policies::deadline_status`. El feed de curso
+/// del instructor usa las fechas de las lecciones.
+async fn collect_events(
+    pool: &PgPool,
+    organization_id: Uuid,
+    course_ids: &[Uuid],
+    user_id: Option<Uuid>,
+) -> Result<Vec<CalendarEvent>, sqlx::Error> {
+    let since = Utc::now() - Duration::days(PAST_WINDOW_DAYS);
+    let base_url = experience_url();
+    let mut events = Vec::new();
+
+    let deadlines = sqlx::query_as::<_, DeadlineRow>(&format!(
+        r#"
+        SELECT lesson_id, course_id, lesson_title, course_title,
+               COALESCE(extension, due_date) AS due_date, extension IS NOT NULL AS extended,
+               important_date_type
+        FROM (
+            SELECT l.id AS lesson_id, c.id AS course_id, l.title AS lesson_title,
+                   c.title AS course_title, l.due_date, {extension} AS extension,
+                   l.important_date_type
+            FROM lessons l
+            JOIN modules m ON m.id = l.module_id
+            JOIN courses c ON c.id = m.course_id
+            WHERE c.id = ANY($1)
+              AND c.organization_id = $2
+        ) d
+        WHERE COALESCE(extension, due_date) >= $3
+        ORDER BY 5
+        "#,
+        extension = crate::late_policies::extension_due_date_sql("$4"),
+    ))
+    .bind(course_ids)
+    .bind(organization_id)
+    .bind(since)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    for d in deadlines {
+        let label = deadline_label(d.important_date_type.as_deref());
+        let extended = if d.extended { " Incluye tu prórroga." } else { "" };
+        events.push(CalendarEvent {
+            uid: format!("deadline-{}@{}", d.lesson_id, UID_DOMAIN),
+            summary: format!("{}: {} ({})", label, d.lesson_title, d.course_title),
+            description: Some(format!("{} del curso \"{}\".{}", label, d.course_title, extended)),
+            url: Some(format!("{}/courses/{}/lessons/{}", base_url, d.course_id, d.lesson_id)),
+            start: d.due_date,
+            end: None,
+            last_modified: None,
+            cancelled: false,
+            alarm_minutes: Some(24 * 60),
+        });
+    }
+
+    let meetings = sqlx::query_as::<_, MeetingRow>(
+        r#"
+        SELECT m.id, c.title AS course_title, m.title, m.description, m.start_at,
+               m.duration_minutes, m.join_url, m.is_active, m.updated_at
+        FROM meetings m
+        JOIN courses c ON c.id = m.course_id
+        WHERE m.course_id = ANY($1)
+          AND m.organization_id = $2
+          AND m.start_at >= $3
+        ORDER BY m.start_at
+        "#,
+    )
+    .bind(course_ids)
+    .bind(organization_id)
+    .bind(since)
+    .fetch_all(pool)
+    .await?;
+
+    for m in meetings {
+        events.push(CalendarEvent {
+            uid: format!("meeting-{}@{}", m.id, UID_DOMAIN),
+            summary: format!("{} ({})", m.title, m.course_title),
+            description: m.description,
+            url: m.join_url,
+            start: m.start_at,
+            end: Some(m.start_at + Duration::minutes(m.duration_minutes as i64)),
+            last_modified: Some(m.updated_at),
+            cancelled: !m.is_active,
+            alarm_minutes: Some(15),
+        });
+    }
+
+    let rooms = sqlx::query_as::<_, StudyRoomRow>(
+        r#"
+        SELECT r.id, r.course_id, c.title AS course_title, r.title, r.description, r.status,
+               r.scheduled_at, r.started_at, r.ended_at, r.updated_at
+        FROM study_rooms r
+        JOIN courses c ON c.id = r.course_id
+        WHERE r.course_id = ANY($1)
+          AND r.organization_id = $2
+          AND COALESCE(r.scheduled_at, r.started_at) >= $3
+        ORDER BY COALESCE(r.scheduled_at, r.started_at)
+        "#,
+    )
+    .bind(course_ids)
+    .bind(organization_id)
+    .bind(since)
+    .fetch_all(pool)
+    .await?;
+
+    for r in rooms {
+        let Some(start) = r.scheduled_at.or(r.started_at) else {
+            continue;
+        };
+        let end = r
+            .ended_at
+            .filter(|end| *end > start)
+            .unwrap_or(start + Duration::minutes(DEFAULT_ROOM_MINUTES));
+        // El enlace de BBB incluye credenciales; el feed apunta a la sala dentro de la app
+        events.push(CalendarEvent {
+            uid: format!("study-room-{}@{}", r.id, UID_DOMAIN),
+            summary: format!("Sala de estudio: {} ({})", r.title, r.course_title),
+            description: r.description,
+            url: Some(format!("{}/courses/{}/study-rooms", base_url, r.course_id)),
+            start,
+            end: Some(end),
+            last_modified: Some(r.updated_at),
+            cancelled: r.status == "cancelled",
+            alarm_minutes: (r.status == "pending").then_some(15),
+        });
+    }
+
+    Ok(events)
+}
+
+// ─── Tokens ──────────────────────────────────────────────────────────────────
+
+fn feed_info(token: &str, course_id: Option<Uuid>, created_at: DateTime<Utc>) -> CalendarFeedInfo {
+    let url = format!("{}/lms-api/calendar/feeds/{}.ics", experience_url(), token);
+    let webcal_url = url
+        .replacen("https://", "webcal://", 1)
+        .replacen("http://", "webcal://", 1);
+    CalendarFeedInfo {
+        url,
+        webcal_url,
+        course_id,
+        created_at,
+    }
+}
+
+/// Devuelve el token activo del alcance o crea uno. Con `rotate` revoca el anterior.
+async fn get_or_create_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    organization_id: Uuid,
+    course_id: Option<Uuid>,
+    rotate: bool,
+) -> Result<CalendarFeedInfo, (StatusCode, String)> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    if rotate {
+        sqlx::query(
+            "UPDATE calendar_feed_tokens SET revoked_at = NOW()
+             WHERE user_id = $1 AND course_id IS NOT DISTINCT FROM $2 AND revoked_at IS NULL",
+        )
+        .bind(user_id)
+        .bind(course_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    } else {
+        let existing: Option<(String, DateTime<Utc>)> = sqlx::query_as(
+            "SELECT token, created_at FROM calendar_feed_tokens
+             WHERE user_id = $1 AND course_id IS NOT DISTINCT FROM $2 AND revoked_at IS NULL",
+        )
+        .bind(user_id)
+        .bind(course_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+        if let Some((token, created_at)) = existing {
+            return Ok(feed_info(&token, course_id, created_at));
+        }
+    }
+
+    let token: String = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(48)
+        .map(char::from)
+        .collect();
+
+    // Otra petición pudo crear el token activo a la vez; en ese caso se devuelve el suyo
+    let created_at: Option<DateTime<Utc>> = sqlx::query_scalar(
+        "INSERT INTO calendar_feed_tokens (user_id, organization_id, course_id, token)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (user_id, COALESCE(course_id, '00000000-0000-0000-0000-000000000000'::uuid))
+             WHERE revoked_at IS NULL DO NOTHING
+         RETURNING created_at",
+    )
+    .bind(user_id)
+    .bind(organization_id)
+    .bind(course_id)
+    .bind(&token)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let (token, created_at) = match created_at {
+        Some(created_at) => (token, created_at),
+        None => sqlx::query_as(
+            "SELECT token, created_at FROM calendar_feed_tokens
+             WHERE user_id = $1 AND course_id IS NOT DISTINCT FROM $2 AND revoked_at IS NULL",
+        )
+        .bind(user_id)
+        .bind(course_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?,
+    };
+
+    tx.commit()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(feed_info(&token, course_id, created_at))
+}
+
+async fn ensure_course_feed_access(
+    pool: &PgPool,
+    org_id: Uuid,
+    claims: &Claims,
+    course_id: Uuid,
+) -> Result<(), (StatusCode, String)> {
+    if claims.role != "admin" && claims.role != "instructor" {
+        return Err((StatusCode::FORBIDDEN, "Se requiere rol instructor o admin".to_string()));
+    }
+    if claims.role == "instructor"
+        && !crate::handlers::instructor_has_course_access(pool, org_id, claims.sub, course_id)
+            .await
+            .map_err(|status| (status, "Error interno del servidor".to_string()))?
+    {
+        return Err((StatusCode::FORBIDDEN, "No tienes acceso a este curso".to_string()));
+    }
+    Ok(())
+}
+
+// ─── Handlers ────────────────────────────────────────────────────────────────
+
+/// GET /calendar/feed
+/// URL del feed personal (cursos inscritos y cursos que el usuario dicta).
+pub async fn get_my_calendar_feed(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+) -> Result<Json<CalendarFeedInfo>, (StatusCode, String)> {
+    Ok(Json(get_or_create_token(&pool, claims.sub, org_ctx.id, None, false).await?))
+}
+
+/// POST /calendar/feed/rotate
+/// Invalida la URL anterior (p. ej. si se compartió por error).
+pub async fn rotate_my_calendar_feed(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+) -> Result<Json<CalendarFeedInfo>, (StatusCode, String)> {
+    Ok(Json(get_or_create_token(&pool, claims.sub, org_ctx.id, None, true).await?))
+}
+
+/// GET /courses/{id}/calendar/feed  (instructor/admin)
+pub async fn get_course_calendar_feed(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+) -> Result<Json<CalendarFeedInfo>, (StatusCode, String)> {
+    ensure_course_feed_access(&pool, org_ctx.id, &claims, course_id).await?;
+    Ok(Json(
+        get_or_create_token(&pool, claims.sub, org_ctx.id, Some(course_id), false).await?,
+    ))
+}
+
+/// POST /courses/{id}/calendar/feed/rotate  (instructor/admin)
+pub async fn rotate_course_calendar_feed(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+) -> Result<Json<CalendarFeedInfo>, (StatusCode, String)> {
+    ensure_course_feed_access(&pool, org_ctx.id, &claims, course_id).await?;
+    Ok(Json(
+        get_or_create_token(&pool, claims.sub, org_ctx.id, Some(course_id), true).await?,
+    ))
+}
+
+/// GET /calendar/feeds/{token}.ics
+/// Ruta pública: el token secreto es la autorización.
+pub async fn serve_calendar_feed(
+    State(pool): State<PgPool>,
+    Path(token): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let token = token.trim_end_matches(".ics");
+
+    let feed = sqlx::query_as::<_, FeedToken>(
+        "UPDATE calendar_feed_tokens SET last_accessed_at = NOW()
+         WHERE token = $1 AND revoked_at IS NULL
+         RETURNING user_id, organization_id, course_id",
+    )
+    .bind(token)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Feed no encontrado".to_string()))?;
+
+    let (course_ids, name): (Vec<Uuid>, String) = match feed.course_id {
+        Some(course_id) => {
+            // El feed de curso deja de servirse si el instructor pierde el acceso
+            let role: Option<String> = sqlx::query_scalar("SELECT role FROM users WHERE id = $1")
+                .bind(feed.user_id)
+                .fetch_optional(&pool)
+                .await
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+            let allowed = match role.as_deref() {
+                Some("admin") => true,
+                Some("instructor") => crate::handlers::instructor_has_course_access(
+                    &pool,
+                    feed.organization_id,
+                    feed.user_id,
+                    course_id,
+                )
+                .await
+                .map_err(|status| (status, "Error interno del servidor".to_string()))?,
+                _ => false,
+            };
+            if !allowed {
+                return Err((StatusCode::NOT_FOUND, "Feed no encontrado".to_string()));
+            }
+
+            let title: String = sqlx::query_scalar("SELECT title FROM courses WHERE id = $1")
+                .bind(course_id)
+                .fetch_optional(&pool)
+                .await
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+                .ok_or((StatusCode::NOT_FOUND, "Feed no encontrado".to_string()))?;
+            (vec![course_id], title)
+        }
+        None => {
+            let ids: Vec<Uuid> = sqlx::query_scalar(
+                r#"
+                SELECT course_id FROM enrollments WHERE user_id = $1
+                UNION
+                SELECT course_id FROM course_instructors WHERE user_id = $1
+                "#,
+            )
+            .bind(feed.user_id)
+            .fetch_all(&pool)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+            (ids, "OpenCCB".to_string())
+        }
+    };
+
+    let personal = feed.course_id.is_none().then_some(feed.user_id);
+    let events = collect_events(&pool, feed.organization_id, &course_ids, personal)
+        .await
+        .map_err(|e| {
+            tracing::error!("serve_calendar_feed: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+        })?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (header::CACHE_CONTROL, "private, max-age=900"),
+        ],
+        render_calendar(&name, &events),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_line_respects_octet_limit_and_utf8() {
+        let line = format!("SUMMARY:{}", "Fecha límite: evaluación ".repeat(8));
+        let folded = fold_line(&line);
+
+        for part in folded.split("\r\n").filter(|p| !p.is_empty()) {
+            assert!(part.len() <= 75, "línea de {} octetos", part.len());
+        }
+        let unfolded = folded.trim_end_matches("\r\n").replace("\r\n ", "");
+        assert_eq!(unfolded, line);
+    }
+
+    #[tokio::test]
+    async fn concurrent_feed_requests_share_one_token_and_cancelled_rooms_stay_in_the_feed() {
+        let Some(pool) = crate::db_util::test_pool(4).await else {
+            return;
+        };
+        let org_id = common::tenancy::DEFAULT_ORG_ID;
+        let (user_id, course_id) = (Uuid::new_v4(), Uuid::new_v4());
+        sqlx::query("INSERT INTO users (id, email, password_hash, full_name, organization_id, role) VALUES ($1, $2, 'x', 'Feed', $3, 'instructor')")
+            .bind(user_id)
+            .bind(format!("{}@example.com", user_id))
+            .bind(org_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO courses (id, title, instructor_id, organization_id) VALUES ($1, 'Feed', $2, $3)")
+            .bind(course_id)
+            .bind(user_id)
+            .bind(org_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let (a, b, c) = tokio::join!(
+            get_or_create_token(&pool, user_id, org_id, Some(course_id), false),
+            get_or_create_token(&pool, user_id, org_id, Some(course_id), false),
+            get_or_create_token(&pool, user_id, org_id, Some(course_id), false),
+        );
+        let (a, b, c) = (a.unwrap().url, b.unwrap().url, c.unwrap().url);
+        assert!(a == b && b == c);
+
+        sqlx::query(
+            "INSERT INTO study_rooms (organization_id, course_id, created_by, title, status, scheduled_at) VALUES ($1, $2, $3, 'Repaso', 'cancelled', NOW() + INTERVAL '1 day')",
+        )
+        .bind(org_id)
+        .bind(course_id)
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+        let events = collect_events(&pool, org_id, &[course_id], None).await.unwrap();
+        let room = events.iter().find(|e| e.uid.starts_with("study-room-")).unwrap();
+        assert!(room.cancelled);
+        assert!(render_calendar("Feed", &events).contains("STATUS:CANCELLED"));
+
+        sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM courses WHERE id = $1").bind(course_id).execute(&pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn personal_feed_shows_the_students_extended_deadline() {
+        let Some(pool) = crate::db_util::test_pool(4).await else {
+            return;
+        };
+        let org_id = common::tenancy::DEFAULT_ORG_ID;
+        let (user_id, course_id, module_id, lesson_id) =
+            (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        sqlx::query("INSERT INTO users (id, email, password_hash, full_name, organization_id, role) VALUES ($1, $2, 'x', 'Prórroga', $3, 'student')")
+            .bind(user_id)
+            .bind(format!("{}@example.com", user_id))
+            .bind(org_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO courses (id, title, instructor_id, organization_id) VALUES ($1, 'Prórroga', $2, $3)")
+            .bind(course_id)
+            .bind(user_id)
+            .bind(org_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO modules (id, organization_id, course_id, title, position) VALUES ($1, $2, $3, 'M', 1)")
+            .bind(module_id)
+            .bind(org_id)
+            .bind(course_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        // La fecha de la lección ya quedó fuera de la ventana; la prórroga no.
+        let due_date = Utc::now() - Duration::days(PAST_WINDOW_DAYS + 1);
+        let extension = Utc::now() + Duration::days(3);
+        sqlx::query(
+            "INSERT INTO lessons (id, organization_id, module_id, title, content_type, position, is_graded, due_date) VALUES ($1, $2, $3, 'Ensayo', 'activity', 1, true, $4)",
+        )
+        .bind(lesson_id)
+        .bind(org_id)
+        .bind(module_id)
+        .bind(due_date)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO due_date_extensions (organization_id, course_id, lesson_id, user_id, due_date) VALUES ($1, $2, $3, $4, $5)")
+            .bind(org_id)
+            .bind(course_id)
+            .bind(lesson_id)
+            .bind(user_id)
+            .bind(extension)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let uid = format!("deadline-{}@{}", lesson_id, UID_DOMAIN);
+        let course_feed = collect_events(&pool, org_id, &[course_id], None).await.unwrap();
+        assert!(course_feed.iter().all(|e| e.uid != uid));
+
+        let personal = collect_events(&pool, org_id, &[course_id], Some(user_id)).await.unwrap();
+        let deadline = personal.iter().find(|e| e.uid == uid).unwrap();
+        assert_eq!(deadline.start.timestamp(), extension.timestamp());
+
+        sqlx::query("DELETE FROM courses WHERE id = $1").bind(course_id).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(&pool).await.unwrap();
+    }
+}
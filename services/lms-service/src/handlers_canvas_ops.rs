@@ -0,0 +1,816 @@
+/// Log de operaciones incremental para la pizarra colaborativa.
+///
+/// Cada trazo se registra como una operación (`add`, `update`, `delete`) con una
+/// secuencia por lección asignada por el servidor. Las operaciones se aplican sobre
+/// elementos identificados por `id` dentro de `canvas_state.strokes`, por lo que dos
+/// usuarios dibujando a la vez nunca generan conflicto. El snapshot se compacta
+/// periódicamente y los checkpoints permiten reproducir el historial.
+///
+/// La difusión a otros clientes la hace el trigger de `lesson_canvas_ops` a través
+/// del bus de eventos (`canvas-ops:{lesson_id}`).
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use chrono::{DateTime, Utc};
+use common::{auth::Claims, middleware::Org};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::event_bus::EventBus;
+
+const MAX_OPS_PAGE: i64 = 1000;
+
+// ─── Modelos ─────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct CanvasOp {
+    pub lesson_id: Uuid,
+    pub seq: i64,
+    pub user_id: Option<Uuid>,
+    pub op_type: String,
+    pub element_id: Option<String>,
+    pub payload: Value,
+    pub undo_of: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubmitCanvasOpPayload {
+    pub op_type: String,
+    pub element_id: Option<String>,
+    #[serde(default)]
+    pub payload: Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CanvasOpsQuery {
+    pub since_seq: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CanvasReplayQuery {
+    pub at: Option<DateTime<Utc>>,
+    pub seq: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CanvasReplayResponse {
+    pub lesson_id: Uuid,
+    pub seq: i64,
+    pub canvas_state: Value,
+    pub ops: Vec<CanvasOp>,
+}
+
+/// Operación a registrar por `append_op`.
+struct NewCanvasOp<'a> {
+    op_type: &'a str,
+    element_id: &'a str,
+    payload: &'a Value,
+    undo_of: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UndoCanvasOpResponse {
+    pub undone_seq: i64,
+    pub op: Option<CanvasOp>,
+}
+
+// ─── Aplicación de operaciones ───────────────────────────────────────────────
+
+fn strokes_mut(state: &mut Value) -> &mut Vec<Value> {
+    if !state.is_object() {
+        *state = json!({});
+    }
+    let obj = state.as_object_mut().unwrap();
+    if !obj.get("strokes").map(Value::is_array).unwrap_or(false) {
+        obj.insert("strokes".to_string(), Value::Array(Vec::new()));
+    }
+    obj.get_mut("strokes").and_then(Value::as_array_mut).unwrap()
+}
+
+fn element_id_of(element: &Value) -> Option<&str> {
+    element.get("id").and_then(Value::as_str)
+}
+
+/// Devuelve el elemento con `id` dentro del estado, si existe.
+pub fn find_element(state: &Value, element_id: &str) -> Option<Value> {
+    state
+        .get("strokes")
+        .and_then(Value::as_array)?
+        .iter()
+        .find(|e| element_id_of(e) == Some(element_id))
+        .cloned()
+}
+
+/// Aplica una operación sobre el estado. Es tolerante: actualizar o borrar un
+/// elemento inexistente no hace nada, y `add` sobre un id existente lo reemplaza.
+pub fn apply_op(state: &mut Value, op_type: &str, element_id: Option<&str>, payload: &Value) {
+    if op_type == "replace" {
+        *state = payload.clone();
+        return;
+    }
+    let Some(element_id) = element_id else {
+        return;
+    };
+    let strokes = strokes_mut(state);
+    let position = strokes.iter().position(|e| element_id_of(e) == Some(element_id));
+
+    match op_type {
+        "add" => {
+            let mut element = payload.clone();
+            if let Some(obj) = element.as_object_mut() {
+                obj.insert("id".to_string(), Value::String(element_id.to_string()));
+            }
+            match position {
+                Some(idx) => strokes[idx] = element,
+                None => strokes.push(element),
+            }
+        }
+        "update" => {
+            // Merge-patch superficial: las claves con null se eliminan
+            let target = position.and_then(|idx| strokes[idx].as_object_mut());
+            if let (Some(target), Some(patch)) = (target, payload.as_object()) {
+                for (key, value) in patch {
+                    if key == "id" {
+                        continue;
+                    }
+                    if value.is_null() {
+                        target.remove(key);
+                    } else {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        "delete" => {
+            if let Some(idx) = position {
+                strokes.remove(idx);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn fold_ops(mut state: Value, ops: &[CanvasOp]) -> Value {
+    for op in ops {
+        apply_op(&mut state, &op.op_type, op.element_id.as_deref(), &op.payload);
+    }
+    state
+}
+
+/// Calcula la operación inversa de `op` a partir del estado previo del elemento.
+fn inverse_op(
+    op_type: &str,
+    payload: &Value,
+    prev_element: Option<&Value>,
+) -> Option<(&'static str, Value)> {
+    match (op_type, prev_element) {
+        ("add", None) => Some(("delete", json!({}))),
+        ("add", Some(prev)) | ("delete", Some(prev)) => Some(("add", prev.clone())),
+        ("update", Some(prev)) => {
+            let mut restore = prev.as_object().cloned().unwrap_or_default();
+            if let Some(patch) = payload.as_object() {
+                for key in patch.keys() {
+                    restore.entry(key.clone()).or_insert(Value::Null);
+                }
+            }
+            Some(("update", Value::Object(restore)))
+        }
+        _ => None,
+    }
+}
+
+// ─── Persistencia ────────────────────────────────────────────────────────────
+
+async fn ensure_lesson(pool: &PgPool, lesson_id: Uuid, org_id: Uuid) -> Result<(), (StatusCode, String)> {
+    let exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM lessons WHERE id = $1 AND organization_id = $2)",
+    )
+    .bind(lesson_id)
+    .bind(org_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    if !exists {
+        return Err((StatusCode::NOT_FOUND, "Lección no encontrada".to_string()));
+    }
+    Ok(())
+}
+
+async fn fetch_ops_range(
+    executor: impl sqlx::PgExecutor<'_>,
+    lesson_id: Uuid,
+    after_seq: i64,
+    up_to_seq: Option<i64>,
+    limit: i64,
+) -> Result<Vec<CanvasOp>, sqlx::Error> {
+    sqlx::query_as::<_, CanvasOp>(
+        r#"
+        SELECT lesson_id, seq, user_id, op_type, element_id, payload, undo_of, created_at
+        FROM lesson_canvas_ops
+        WHERE lesson_id = $1
+          AND seq > $2
+          AND ($3::bigint IS NULL OR seq <= $3)
+        ORDER BY seq
+        LIMIT $4
+        "#,
+    )
+    .bind(lesson_id)
+    .bind(after_seq)
+    .bind(up_to_seq)
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+}
+
+/// Estado actual = snapshot compactado + operaciones posteriores a `compacted_seq`.
+pub async fn materialize_canvas(
+    pool: &PgPool,
+    lesson_id: Uuid,
+    snapshot: Value,
+    compacted_seq: i64,
+) -> Result<Value, sqlx::Error> {
+    let pending = fetch_ops_range(pool, lesson_id, compacted_seq, None, i64::MAX).await?;
+    Ok(fold_ops(snapshot, &pending))
+}
+
+/// Registra una operación dentro de `tx`. El upsert sobre la fila del canvas
+/// serializa las escrituras de la misma lección y asigna la siguiente secuencia.
+async fn append_op(
+    tx: &mut Transaction<'_, Postgres>,
+    lesson_id: Uuid,
+    org_id: Uuid,
+    user_id: Uuid,
+    new_op: NewCanvasOp<'_>,
+) -> Result<CanvasOp, sqlx::Error> {
+    let element_id = new_op.element_id;
+    #[derive(sqlx::FromRow)]
+    struct HeadRow {
+        head_seq: i64,
+        compacted_seq: i64,
+        canvas_state: Value,
+    }
+
+    let head = sqlx::query_as::<_, HeadRow>(
+        r#"
+        INSERT INTO lesson_collaborative_canvases (
+            lesson_id, organization_id, updated_by, head_seq, revision, updated_at
+        )
+        VALUES ($1, $2, $3, 1, 1, NOW())
+        ON CONFLICT (lesson_id)
+        DO UPDATE SET
+            head_seq = lesson_collaborative_canvases.head_seq + 1,
+            revision = lesson_collaborative_canvases.revision + 1,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = NOW()
+        RETURNING head_seq, compacted_seq, canvas_state
+        "#,
+    )
+    .bind(lesson_id)
+    .bind(org_id)
+    .bind(user_id)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    // Estado previo del elemento: snapshot + operaciones pendientes sobre ese id
+    let element_ops = sqlx::query_as::<_, CanvasOp>(
+        r#"
+        SELECT lesson_id, seq, user_id, op_type, element_id, payload, undo_of, created_at
+        FROM lesson_canvas_ops
+        WHERE lesson_id = $1 AND element_id = $2 AND seq > $3
+        ORDER BY seq
+        "#,
+    )
+    .bind(lesson_id)
+    .bind(element_id)
+    .bind(head.compacted_seq)
+    .fetch_all(&mut **tx)
+    .await?;
+
+    let snapshot_element: Vec<Value> = find_element(&head.canvas_state, element_id).into_iter().collect();
+    let element_state = fold_ops(json!({ "strokes": snapshot_element }), &element_ops);
+    let prev_element = find_element(&element_state, element_id);
+
+    sqlx::query_as::<_, CanvasOp>(
+        r#"
+        INSERT INTO lesson_canvas_ops (
+            lesson_id, organization_id, seq, user_id, op_type, element_id, payload, prev_element, undo_of
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING lesson_id, seq, user_id, op_type, element_id, payload, undo_of, created_at
+        "#,
+    )
+    .bind(lesson_id)
+    .bind(org_id)
+    .bind(head.head_seq)
+    .bind(user_id)
+    .bind(new_op.op_type)
+    .bind(element_id)
+    .bind(new_op.payload)
+    .bind(prev_element)
+    .bind(new_op.undo_of)
+    .fetch_one(&mut **tx)
+    .await
+}
+
+/// Registra en el log el reemplazo completo realizado por `PUT /collaborative-canvas`,
+/// dentro de la misma transacción que actualiza el snapshot para que la reproducción
+/// no pueda divergir del canvas guardado.
+pub async fn record_replace_op(
+    tx: &mut Transaction<'_, Postgres>,
+    lesson_id: Uuid,
+    org_id: Uuid,
+    user_id: Uuid,
+    seq: i64,
+    canvas_state: &Value,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO lesson_canvas_ops (lesson_id, organization_id, seq, user_id, op_type, payload)
+        VALUES ($1, $2, $3, $4, 'replace', $5)
+        "#,
+    )
+    .bind(lesson_id)
+    .bind(org_id)
+    .bind(seq)
+    .bind(user_id)
+    .bind(canvas_state)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+// ─── Handlers ────────────────────────────────────────────────────────────────
+
+/// POST /lessons/{id}/collaborative-canvas/ops
+pub async fn submit_canvas_op(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<SubmitCanvasOpPayload>,
+) -> Result<(StatusCode, Json<CanvasOp>), (StatusCode, String)> {
+    if !matches!(payload.op_type.as_str(), "add" | "update" | "delete") {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "op_type debe ser 'add', 'update' o 'delete'".to_string(),
+        ));
+    }
+
+    let element_id = payload
+        .element_id
+        .clone()
+        .or_else(|| element_id_of(&payload.payload).map(str::to_string))
+        .filter(|s| !s.trim().is_empty())
+        .ok_or((StatusCode::UNPROCESSABLE_ENTITY, "element_id es requerido".to_string()))?;
+
+    if payload.op_type != "delete" && !payload.payload.is_object() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "payload debe ser un objeto JSON".to_string(),
+        ));
+    }
+
+    ensure_lesson(&pool, id, org_ctx.id).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let op = append_op(
+        &mut tx,
+        id,
+        org_ctx.id,
+        claims.sub,
+        NewCanvasOp {
+            op_type: &payload.op_type,
+            element_id: &element_id,
+            payload: &payload.payload,
+            undo_of: None,
+        },
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("submit_canvas_op: failed to append op for lesson {}: {}", id, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Error registrando operación".to_string())
+    })?;
+
+    tx.commit()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(op)))
+}
+
+/// GET /lessons/{id}/collaborative-canvas/ops?since_seq=
+pub async fn list_canvas_ops(
+    Org(org_ctx): Org,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<CanvasOpsQuery>,
+) -> Result<Json<Vec<CanvasOp>>, (StatusCode, String)> {
+    ensure_lesson(&pool, id, org_ctx.id).await?;
+
+    let limit = query.limit.unwrap_or(500).clamp(1, MAX_OPS_PAGE);
+    let ops = fetch_ops_range(&pool, id, query.since_seq.unwrap_or(0), None, limit)
+        .await
+        .map_err(|e| {
+            tracing::error!("list_canvas_ops: failed to fetch ops for lesson {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+        })?;
+
+    Ok(Json(ops))
+}
+
+/// POST /lessons/{id}/collaborative-canvas/undo
+/// Deshace la última operación del usuario actual registrando su inversa.
+pub async fn undo_canvas_op(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<UndoCanvasOpResponse>, (StatusCode, String)> {
+    ensure_lesson(&pool, id, org_ctx.id).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    sqlx::query("SELECT 1 FROM lesson_collaborative_canvases WHERE lesson_id = $1 FOR UPDATE")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    #[derive(sqlx::FromRow)]
+    struct UndoCandidate {
+        seq: i64,
+        op_type: String,
+        element_id: Option<String>,
+        payload: Value,
+        prev_element: Option<Value>,
+    }
+
+    // Un reemplazo completo posterior invalida el historial previo de deshacer
+    let candidate = sqlx::query_as::<_, UndoCandidate>(
+        r#"
+        SELECT seq, op_type, element_id, payload, prev_element
+        FROM lesson_canvas_ops
+        WHERE lesson_id = $1
+          AND organization_id = $2
+          AND user_id = $3
+          AND op_type <> 'replace'
+          AND undo_of IS NULL
+          AND undone_at IS NULL
+          AND seq > COALESCE((
+              SELECT MAX(seq) FROM lesson_canvas_ops
+              WHERE lesson_id = $1 AND op_type = 'replace'
+          ), 0)
+        ORDER BY seq DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(id)
+    .bind(org_ctx.id)
+    .bind(claims.sub)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "No hay operaciones para deshacer".to_string()))?;
+
+    let element_id = candidate.element_id.clone().unwrap_or_default();
+    let inverse = inverse_op(&candidate.op_type, &candidate.payload, candidate.prev_element.as_ref());
+
+    let op = match inverse {
+        Some((op_type, inverse_payload)) => Some(
+            append_op(
+                &mut tx,
+                id,
+                org_ctx.id,
+                claims.sub,
+                NewCanvasOp {
+                    op_type,
+                    element_id: &element_id,
+                    payload: &inverse_payload,
+                    undo_of: Some(candidate.seq),
+                },
+            )
+            .await
+            .map_err(|e| {
+                tracing::error!("undo_canvas_op: failed to append inverse for lesson {}: {}", id, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "Error deshaciendo operación".to_string())
+            })?,
+        ),
+        None => None,
+    };
+
+    sqlx::query("UPDATE lesson_canvas_ops SET undone_at = NOW() WHERE lesson_id = $1 AND seq = $2")
+        .bind(id)
+        .bind(candidate.seq)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(UndoCanvasOpResponse {
+        undone_seq: candidate.seq,
+        op,
+    }))
+}
+
+/// GET /lessons/{id}/collaborative-canvas/ops/stream?since_seq=  (SSE)
+/// Envía las operaciones posteriores a `since_seq` y luego cada nueva operación
+/// cuando el bus de eventos avisa de un cambio.
+pub async fn stream_canvas_ops(
+    Org(org_ctx): Org,
+    State(pool): State<PgPool>,
+    Extension(bus): Extension<EventBus>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<CanvasOpsQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    use std::convert::Infallible;
+    use tokio_stream::wrappers::ReceiverStream;
+
+    ensure_lesson(&pool, id, org_ctx.id).await?;
+
+    // Suscribirse antes de leer el backlog para no perder operaciones intermedias
+    let mut events = bus.subscribe(format!("canvas-ops:{}", id));
+    let (tx, rx) = tokio::sync::mpsc::channel::<Result<Event, Infallible>>(64);
+    let mut last_seq = query.since_seq.unwrap_or(0);
+
+    common::tenancy::spawn(async move {
+        loop {
+            // Páginas completas indican que queda backlog: seguir leyendo sin esperar aviso
+            loop {
+                let ops = match fetch_ops_range(&pool, id, last_seq, None, MAX_OPS_PAGE).await {
+                    Ok(ops) => ops,
+                    Err(e) => {
+                        tracing::error!("stream_canvas_ops: fetch failed for lesson {}: {}", id, e);
+                        break;
+                    }
+                };
+                let full_page = ops.len() as i64 == MAX_OPS_PAGE;
+                for op in ops {
+                    last_seq = op.seq;
+                    let event = Event::default()
+                        .event("op")
+                        .id(op.seq.to_string())
+                        .data(serde_json::to_string(&op).unwrap_or_default());
+                    if tx.send(Ok(event)).await.is_err() {
+                        return; // Cliente desconectado
+                    }
+                }
+                if !full_page {
+                    break;
+                }
+            }
+
+            tokio::select! {
+                _ = tx.closed() => break,
+                _ = events.next_change() => {}
+            }
+        }
+    });
+
+    Ok(Sse::new(ReceiverStream::new(rx)).keep_alive(KeepAlive::default()))
+}
+
+/// GET /lessons/{id}/collaborative-canvas/replay?at=|seq=
+/// Reconstruye el canvas en un instante dado (solo instructores/administradores).
+pub async fn replay_canvas(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<CanvasReplayQuery>,
+) -> Result<Json<CanvasReplayResponse>, (StatusCode, String)> {
+    if claims.role != "admin" && claims.role != "instructor" {
+        return Err((StatusCode::FORBIDDEN, "Acceso denegado".to_string()));
+    }
+
+    ensure_lesson(&pool, id, org_ctx.id).await?;
+
+    let target_seq: i64 = match (query.seq, query.at) {
+        (Some(seq), _) => seq,
+        (None, Some(at)) => sqlx::query_scalar::<_, Option<i64>>(
+            r#"
+            SELECT GREATEST(
+                (SELECT MAX(seq) FROM lesson_canvas_ops WHERE lesson_id = $1 AND created_at <= $2),
+                (SELECT MAX(seq) FROM lesson_canvas_snapshots WHERE lesson_id = $1 AND created_at <= $2)
+            )
+            "#,
+        )
+        .bind(id)
+        .bind(at)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+        .unwrap_or(0),
+        (None, None) => sqlx::query_scalar::<_, i64>(
+            "SELECT COALESCE((SELECT head_seq FROM lesson_collaborative_canvases WHERE lesson_id = $1), 0)",
+        )
+        .bind(id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?,
+    };
+
+    #[derive(sqlx::FromRow)]
+    struct CheckpointRow {
+        seq: i64,
+        canvas_state: Value,
+    }
+
+    let checkpoint = sqlx::query_as::<_, CheckpointRow>(
+        r#"
+        SELECT seq, canvas_state
+        FROM lesson_canvas_snapshots
+        WHERE lesson_id = $1 AND organization_id = $2 AND seq <= $3
+        ORDER BY seq DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(id)
+    .bind(org_ctx.id)
+    .bind(target_seq)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let (base_seq, base_state) = checkpoint
+        .map(|c| (c.seq, c.canvas_state))
+        .unwrap_or((0, json!({})));
+
+    let ops = fetch_ops_range(&pool, id, base_seq, Some(target_seq), i64::MAX)
+        .await
+        .map_err(|e| {
+            tracing::error!("replay_canvas: failed to fetch ops for lesson {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+        })?;
+
+    let canvas_state = fold_ops(base_state, &ops);
+
+    Ok(Json(CanvasReplayResponse {
+        lesson_id: id,
+        seq: ops.last().map(|op| op.seq).unwrap_or(base_seq),
+        canvas_state,
+        ops,
+    }))
+}
+
+// ─── Compactación ────────────────────────────────────────────────────────────
+
+fn ops_retention_days() -> i64 {
+    std::env::var("CANVAS_OPS_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30)
+}
+
+/// Incorpora las operaciones pendientes al snapshot, guarda un checkpoint y
+/// elimina las operaciones ya cubiertas por checkpoints fuera del período de retención.
+pub async fn compact_canvas_logs(pool: PgPool) {
+    let pending = sqlx::query_scalar::<_, Uuid>(
+        "SELECT lesson_id FROM lesson_collaborative_canvases WHERE head_seq > compacted_seq",
+    )
+    .fetch_all(&pool)
+    .await;
+
+    let pending = match pending {
+        Ok(ids) => ids,
+        Err(e) => {
+            tracing::error!("compact_canvas_logs: failed to list pending canvases: {}", e);
+            return;
+        }
+    };
+
+    for lesson_id in pending {
+        if let Err(e) = compact_canvas(&pool, lesson_id).await {
+            tracing::error!("compact_canvas_logs: lesson {} failed: {}", lesson_id, e);
+        }
+    }
+
+    let retention = ops_retention_days();
+    let pruned = sqlx::query(
+        r#"
+        WITH boundary AS (
+            SELECT lesson_id, MAX(seq) AS seq
+            FROM lesson_canvas_snapshots
+            WHERE created_at < NOW() - make_interval(days => $1::int)
+            GROUP BY lesson_id
+        ),
+        deleted_ops AS (
+            DELETE FROM lesson_canvas_ops o
+            USING boundary b
+            WHERE o.lesson_id = b.lesson_id AND o.seq <= b.seq
+        )
+        DELETE FROM lesson_canvas_snapshots s
+        USING boundary b
+        WHERE s.lesson_id = b.lesson_id AND s.seq < b.seq
+        "#,
+    )
+    .bind(retention as i32)
+    .execute(&pool)
+    .await;
+
+    if let Err(e) = pruned {
+        tracing::error!("compact_canvas_logs: failed to prune old ops: {}", e);
+    }
+}
+
+async fn compact_canvas(pool: &PgPool, lesson_id: Uuid) -> Result<(), sqlx::Error> {
+    #[derive(sqlx::FromRow)]
+    struct CanvasRow {
+        organization_id: Uuid,
+        canvas_state: Value,
+        head_seq: i64,
+        compacted_seq: i64,
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let row = sqlx::query_as::<_, CanvasRow>(
+        r#"
+        SELECT organization_id, canvas_state, head_seq, compacted_seq
+        FROM lesson_collaborative_canvases
+        WHERE lesson_id = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(lesson_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if row.head_seq <= row.compacted_seq {
+        return Ok(());
+    }
+
+    let ops = fetch_ops_range(&mut *tx, lesson_id, row.compacted_seq, Some(row.head_seq), i64::MAX).await?;
+    let canvas_state = fold_ops(row.canvas_state, &ops);
+
+    sqlx::query(
+        "UPDATE lesson_collaborative_canvases SET canvas_state = $2, compacted_seq = $3 WHERE lesson_id = $1",
+    )
+    .bind(lesson_id)
+    .bind(&canvas_state)
+    .bind(row.head_seq)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO lesson_canvas_snapshots (lesson_id, organization_id, seq, canvas_state)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (lesson_id, seq) DO NOTHING
+        "#,
+    )
+    .bind(lesson_id)
+    .bind(row.organization_id)
+    .bind(row.head_seq)
+    .bind(&canvas_state)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_then_inverse_restores_element() {
+        let mut state = json!({ "strokes": [] });
+        apply_op(&mut state, "add", Some("a"), &json!({ "color": "#000", "width": 2 }));
+
+        let prev = find_element(&state, "a");
+        let patch = json!({ "color": "#f00", "dash": true });
+        apply_op(&mut state, "update", Some("a"), &patch);
+        assert_eq!(find_element(&state, "a").unwrap()["color"], "#f00");
+
+        let (op_type, inverse) = inverse_op("update", &patch, prev.as_ref()).unwrap();
+        apply_op(&mut state, op_type, Some("a"), &inverse);
+        assert_eq!(find_element(&state, "a"), prev);
+    }
+
+    #[test]
+    fn ops_on_missing_elements_are_ignored() {
+        let mut state = json!({});
+        apply_op(&mut state, "update", Some("x"), &json!({ "color": "#fff" }));
+        apply_op(&mut state, "delete", Some("x"), &json!({}));
+        assert_eq!(state, json!({ "strokes": [] }));
+    }
+}
@@ -566,7 +566,7 @@ async fn issue_certificate_internal(
 use crate::handlers_email;
 
 fn _send_completion_email_spawn(pool: PgPool, org_id: Uuid, user_id: Uuid, user_name: String, course_title: String) {
-    tokio::spawn(async move {
+    common::tenancy::spawn(async move {
         let email_row = sqlx::query_as::<_, (String,)>(
             "SELECT email FROM users WHERE id = $1",
         )
@@ -3,6 +3,7 @@ use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
 };
+use crate::handlers_email::render_template;
 use crate::moderation::contains_inappropriate_language;
 use lettre::message::Mailbox;
 use lettre::transport::smtp::authentication::Credentials;
@@ -231,15 +232,6 @@ El equipo de {{organization_name}}".to_string(),
     }
 }
 
-fn render_template(template: &str, variables: &HashMap<&str, String>) -> String {
-    let mut result = template.to_string();
-    for (key, value) in variables {
-        let placeholder = format!("{{{{{}}}}}", key);
-        result = result.replace(&placeholder, value);
-    }
-    result
-}
-
 fn build_smtp_mailer(config: &SmtpConfig) -> Result<AsyncSmtpTransport<Tokio1Executor>, String> {
     let mut builder = if config.starttls {
         AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)
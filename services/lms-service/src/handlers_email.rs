@@ -149,6 +149,121 @@ pub async fn send_email(
     Ok(())
 }
 
+/// Igual que `send_email`, pero respeta el formato de la plantilla (texto o HTML)
+/// y agrega las cabeceras de desuscripción en un clic (RFC 8058).
+pub async fn send_email_with_unsubscribe(
+    config: &SmtpConfig,
+    to_email: &str,
+    to_name: &str,
+    subject: &str,
+    body: &str,
+    is_html: bool,
+    unsubscribe_url: &str,
+) -> Result<(), String> {
+    use lettre::message::header::{ContentType, HeaderName, HeaderValue};
+
+    if !config.enabled {
+        tracing::debug!("SMTP deshabilitado — email no enviado a {}", to_email);
+        return Ok(());
+    }
+
+    let from: Mailbox = config
+        .from
+        .parse()
+        .map_err(|e| format!("From inválido: {}", e))?;
+
+    let to_str = if to_name.is_empty() {
+        to_email.to_string()
+    } else {
+        format!("{} <{}>", to_name, to_email)
+    };
+    let to: Mailbox = to_str.parse().map_err(|e| format!("To inválido: {}", e))?;
+
+    let header_name = |name: &str| {
+        HeaderName::new_from_ascii(name.to_string()).map_err(|e| format!("Cabecera inválida: {}", e))
+    };
+
+    let email = Message::builder()
+        .from(from)
+        .to(to)
+        .subject(subject)
+        .header(if is_html { ContentType::TEXT_HTML } else { ContentType::TEXT_PLAIN })
+        .raw_header(HeaderValue::new(
+            header_name("List-Unsubscribe")?,
+            format!("<{}>", unsubscribe_url),
+        ))
+        .raw_header(HeaderValue::new(
+            header_name("List-Unsubscribe-Post")?,
+            "List-Unsubscribe=One-Click".to_string(),
+        ))
+        .body(body.to_string())
+        .map_err(|e| format!("Error construyendo email: {}", e))?;
+
+    let mailer = build_mailer(config)?;
+    mailer.send(email).await.map_err(|e| format!("Error enviando email: {}", e))?;
+    Ok(())
+}
+
+// ─── Plantillas por organización ───────────────────────────────────────────────
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OrgEmailTemplate {
+    pub template_key: String,
+    pub subject_template: String,
+    pub body_template: String,
+    pub is_html: bool,
+    pub is_enabled: bool,
+}
+
+/// Carga una plantilla de la organización. Las plantillas viven en la BD del CMS, así
+/// que se piden a su API con un token de servicio. `Ok(None)` si la organización no
+/// definió esa clave; quien llama decide el texto por defecto.
+pub async fn load_org_email_template(
+    organization_id: Uuid,
+    template_key: &str,
+) -> Result<Option<OrgEmailTemplate>, String> {
+    let cms_url = env::var("CMS_API_URL").unwrap_or_else(|_| "http://studio:3001".to_string());
+    // Token de servicio de corta duración firmado con el secreto compartido
+    let token = common::auth::create_jwt(Uuid::nil(), organization_id, "admin")
+        .map_err(|e| format!("Token de servicio: {}", e))?;
+
+    let res = reqwest::Client::new()
+        .get(format!("{}/organization/email-templates", cms_url.trim_end_matches('/')))
+        .bearer_auth(token)
+        .timeout(std::time::Duration::from_secs(15))
+        .send()
+        .await
+        .map_err(|e| format!("CMS no disponible: {}", e))?;
+    if !res.status().is_success() {
+        return Err(format!("el CMS respondió {}", res.status()));
+    }
+
+    let templates: Vec<OrgEmailTemplate> = res
+        .json()
+        .await
+        .map_err(|e| format!("Respuesta inválida del CMS: {}", e))?;
+    Ok(templates.into_iter().find(|t| t.template_key == template_key))
+}
+
+/// Escapa texto para insertarlo en el cuerpo de una plantilla HTML.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Reemplaza los marcadores `{{clave}}` de una plantilla.
+pub fn render_template(template: &str, variables: &std::collections::HashMap<&str, String>) -> String {
+    let mut result = template.to_string();
+    for (key, value) in variables {
+        let placeholder = format!("{{{{{}}}}}", key);
+        result = result.replace(&placeholder, value);
+    }
+    result
+}
+
 // ─── Password Reset ────────────────────────────────────────────────────────────
 
 #[derive(Deserialize)]
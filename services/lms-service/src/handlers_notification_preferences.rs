@@ -0,0 +1,756 @@
+/// Preferencias de notificación por usuario y resúmenes por email.
+///
+/// El canal de cada notificación se resuelve en la BD (`fn_apply_notification_preferences`)
+/// al insertarla, así que todos los orígenes (fechas límite, foros, anuncios, mensajes
+/// del instructor) respetan la preferencia. Este módulo expone la configuración y las
+/// tareas que envían los emails inmediatos y los resúmenes diarios/semanales.
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+    response::Html,
+};
+use chrono::{DateTime, Utc};
+use common::{auth::Claims, middleware::Org};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::env;
+use uuid::Uuid;
+
+use crate::handlers_email::{
+    OrgEmailTemplate, escape_html, load_org_email_template, load_smtp_config, render_template,
+    send_email_with_unsubscribe,
+};
+
+/// Tipos de notificación que emite la plataforma.
+pub const NOTIFICATION_TYPES: [&str; 7] = [
+    "deadline",
+    "announcement",
+    "forum_thread",
+    "forum_reply",
+    "instructor_message",
+    "grade",
+    "info",
+];
+
+pub const CHANNELS: [&str; 5] = ["in_app", "email_immediate", "daily_digest", "weekly_digest", "off"];
+
+const DEFAULT_CHANNEL: &str = "in_app";
+/// Intentos de envío de un email inmediato antes de abandonarlo.
+const MAX_EMAIL_ATTEMPTS: i32 = 5;
+/// Alcance del token de desuscripción que cubre todos los tipos.
+const ALL_TYPES: &str = "*";
+
+// ─── Modelos ─────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotificationPreference {
+    pub notification_type: String,
+    pub channel: String,
+    /// Envío por Web Push a los dispositivos registrados.
+    #[serde(default = "default_push")]
+    pub push: bool,
+}
+
+fn default_push() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateNotificationPreferencesPayload {
+    pub preferences: Vec<NotificationPreference>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnsubscribeQuery {
+    pub token: String,
+}
+
+// ─── Token de desuscripción ──────────────────────────────────────────────────
+
+fn unsubscribe_mac(user_id: Uuid, scope: &str) -> Hmac<Sha256> {
+    let secret = env::var("JWT_SECRET").expect("JWT_SECRET env var must be set");
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC acepta claves de cualquier longitud");
+    mac.update(format!("unsubscribe:{}:{}", user_id, scope).as_bytes());
+    mac
+}
+
+/// Token `{user_id}.{scope}.{firma}`; `scope` es un tipo de notificación o `*`.
+pub fn unsubscribe_token(user_id: Uuid, scope: &str) -> String {
+    let signature = hex::encode(unsubscribe_mac(user_id, scope).finalize().into_bytes());
+    format!("{}.{}.{}", user_id, scope, signature)
+}
+
+fn parse_unsubscribe_token(token: &str) -> Option<(Uuid, String)> {
+    let mut parts = token.splitn(3, '.');
+    let user_id = Uuid::parse_str(parts.next()?).ok()?;
+    let scope = parts.next()?.to_string();
+    let signature = hex::decode(parts.next()?).ok()?;
+
+    unsubscribe_mac(user_id, &scope)
+        .verify_slice(&signature)
+        .ok()?;
+    Some((user_id, scope))
+}
+
+fn unsubscribe_url(user_id: Uuid, scope: &str) -> String {
+    let base_url = env::var("EXPERIENCE_URL").unwrap_or_else(|_| "https://openccb.local".to_string());
+    format!(
+        "{}/lms-api/notifications/unsubscribe?token={}",
+        base_url,
+        unsubscribe_token(user_id, scope)
+    )
+}
+
+// ─── Handlers ────────────────────────────────────────────────────────────────
+
+/// GET /notifications/preferences
+/// Devuelve el canal efectivo de cada tipo (los no configurados usan `in_app`).
+pub async fn get_notification_preferences(
+    claims: Claims,
+    State(pool): State<PgPool>,
+) -> Result<Json<Vec<NotificationPreference>>, (StatusCode, String)> {
+    let stored: HashMap<String, (String, bool)> = sqlx::query_as::<_, (String, String, bool)>(
+        "SELECT notification_type, channel, push_enabled FROM notification_preferences WHERE user_id = $1",
+    )
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+    .into_iter()
+    .map(|(notification_type, channel, push)| (notification_type, (channel, push)))
+    .collect();
+
+    let preferences = NOTIFICATION_TYPES
+        .iter()
+        .map(|t| {
+            let (channel, push) = stored
+                .get(*t)
+                .cloned()
+                .unwrap_or_else(|| (DEFAULT_CHANNEL.to_string(), default_push()));
+            NotificationPreference {
+                notification_type: t.to_string(),
+                channel,
+                push,
+            }
+        })
+        .collect();
+
+    Ok(Json(preferences))
+}
+
+/// PUT /notifications/preferences
+pub async fn update_notification_preferences(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Json(payload): Json<UpdateNotificationPreferencesPayload>,
+) -> Result<Json<Vec<NotificationPreference>>, (StatusCode, String)> {
+    for pref in &payload.preferences {
+        if !NOTIFICATION_TYPES.contains(&pref.notification_type.as_str()) {
+            return Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Tipo de notificación desconocido: {}", pref.notification_type),
+            ));
+        }
+        if !CHANNELS.contains(&pref.channel.as_str()) {
+            return Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Canal inválido: {}", pref.channel),
+            ));
+        }
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    for pref in &payload.preferences {
+        sqlx::query(
+            r#"
+            INSERT INTO notification_preferences (user_id, organization_id, notification_type, channel, push_enabled)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id, notification_type)
+            DO UPDATE SET channel = EXCLUDED.channel, push_enabled = EXCLUDED.push_enabled, updated_at = NOW()
+            "#,
+        )
+        .bind(claims.sub)
+        .bind(org_ctx.id)
+        .bind(&pref.notification_type)
+        .bind(&pref.channel)
+        .bind(pref.push)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    get_notification_preferences(claims, State(pool)).await
+}
+
+fn verified_unsubscribe_scope(token: &str) -> Result<(Uuid, String), (StatusCode, String)> {
+    match parse_unsubscribe_token(token) {
+        Some((user_id, scope)) if scope == ALL_TYPES || NOTIFICATION_TYPES.contains(&scope.as_str()) => {
+            Ok((user_id, scope))
+        }
+        _ => Err((StatusCode::BAD_REQUEST, "Enlace de desuscripción inválido".to_string())),
+    }
+}
+
+/// GET /notifications/unsubscribe?token=
+/// Página de confirmación del enlace del email. No cambia nada: los escáneres de
+/// enlaces de los clientes de correo siguen los GET, así que la baja requiere el POST.
+pub async fn confirm_unsubscribe(
+    Query(query): Query<UnsubscribeQuery>,
+) -> Result<Html<String>, (StatusCode, String)> {
+    verified_unsubscribe_scope(&query.token)?;
+
+    Ok(Html(format!(
+        r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"></head>
+<body style="font-family:Arial,sans-serif;max-width:600px;margin:0 auto;padding:20px">
+  <h2>Dejar de recibir estos correos</h2>
+  <p>Seguirás viendo las notificaciones dentro de la plataforma y puedes cambiar tus
+  preferencias en cualquier momento desde tu perfil.</p>
+  <form method="post" action="?token={}">
+    <button type="submit">Confirmar desuscripción</button>
+  </form>
+</body>
+</html>"#,
+        escape_html(&query.token)
+    )))
+}
+
+/// POST /notifications/unsubscribe?token=
+/// Desuscripción en un clic (RFC 8058) o desde la página de confirmación: los canales
+/// por email del alcance del token pasan a `in_app`. Es una ruta pública; la
+/// autorización es la firma del token.
+pub async fn unsubscribe_notifications(
+    State(pool): State<PgPool>,
+    Query(query): Query<UnsubscribeQuery>,
+) -> Result<Html<String>, (StatusCode, String)> {
+    let (user_id, scope) = verified_unsubscribe_scope(&query.token)?;
+
+    sqlx::query(
+        r#"
+        UPDATE notification_preferences
+        SET channel = 'in_app', updated_at = NOW()
+        WHERE user_id = $1
+          AND channel IN ('email_immediate', 'daily_digest', 'weekly_digest')
+          AND ($2 = '*' OR notification_type = $2)
+        "#,
+    )
+    .bind(user_id)
+    .bind(&scope)
+    .execute(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    // Las notificaciones pendientes de email ya no deben enviarse
+    sqlx::query(
+        r#"
+        UPDATE notifications
+        SET delivery_channel = 'in_app'
+        WHERE user_id = $1
+          AND emailed_at IS NULL
+          AND delivery_channel <> 'in_app'
+          AND ($2 = '*' OR notification_type = $2)
+        "#,
+    )
+    .bind(user_id)
+    .bind(&scope)
+    .execute(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Html(
+        r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"></head>
+<body style="font-family:Arial,sans-serif;max-width:600px;margin:0 auto;padding:20px">
+  <h2>Desuscripción confirmada</h2>
+  <p>Ya no recibirás estos correos. Seguirás viendo las notificaciones dentro de la plataforma
+  y puedes cambiar tus preferencias en cualquier momento desde tu perfil.</p>
+</body>
+</html>"#
+            .to_string(),
+    ))
+}
+
+// ─── Tareas de envío ─────────────────────────────────────────────────────────
+
+const DEFAULT_IMMEDIATE_SUBJECT: &str = "{{notification_title}}";
+const DEFAULT_IMMEDIATE_BODY: &str = "Hola {{recipient_name}},
+
+{{notification_message}}
+
+Ver en la plataforma: {{notification_url}}
+
+Para dejar de recibir este tipo de correos: {{unsubscribe_url}}
+
+Saludos,
+El equipo de {{organization_name}}";
+
+const DEFAULT_DIGEST_SUBJECT: &str = "Tu resumen {{digest_period}}: {{notification_count}} notificaciones";
+const DEFAULT_DIGEST_BODY: &str = "Hola {{recipient_name}},
+
+Tienes {{notification_count}} notificaciones sin leer:
+
+{{notification_list}}
+
+Ver todas: {{notifications_url}}
+
+Para dejar de recibir estos resúmenes: {{unsubscribe_url}}
+
+Saludos,
+El equipo de {{organization_name}}";
+
+/// Plantilla de la organización o la predeterminada. `Ok(None)` si la organización
+/// deshabilitó la plantilla; `Err` si el CMS no respondió y conviene reintentar.
+async fn resolve_template(
+    organization_id: Uuid,
+    template_key: &str,
+    default_subject: &str,
+    default_body: &str,
+) -> Result<Option<OrgEmailTemplate>, String> {
+    Ok(match load_org_email_template(organization_id, template_key).await? {
+        Some(t) if !t.is_enabled => None,
+        Some(t) => Some(t),
+        None => Some(OrgEmailTemplate {
+            template_key: template_key.to_string(),
+            subject_template: default_subject.to_string(),
+            body_template: default_body.to_string(),
+            is_html: false,
+            is_enabled: true,
+        }),
+    })
+}
+
+/// Variables para el cuerpo del email: en plantillas HTML los valores se escapan.
+fn body_variables<'a>(variables: &HashMap<&'a str, String>, is_html: bool) -> HashMap<&'a str, String> {
+    if !is_html {
+        return variables.clone();
+    }
+    variables.iter().map(|(key, value)| (*key, escape_html(value))).collect()
+}
+
+/// Lista de notificaciones del resumen en el formato de la plantilla.
+fn digest_list(notifications: &[&PendingEmailRow], is_html: bool) -> String {
+    if is_html {
+        let items = notifications
+            .iter()
+            .map(|n| {
+                let link = escape_html(&absolute_link(n.link_url.as_deref()));
+                format!(
+                    "<li><strong>{}</strong> — {}<br><a href=\"{}\">{}</a></li>",
+                    escape_html(&n.title),
+                    escape_html(&n.message),
+                    link,
+                    link
+                )
+            })
+            .collect::<String>();
+        return format!("<ul>{}</ul>", items);
+    }
+    notifications
+        .iter()
+        .map(|n| format!("• {} — {}\n  {}", n.title, n.message, absolute_link(n.link_url.as_deref())))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+pub fn absolute_link(link_url: Option<&str>) -> String {
+    let base_url = env::var("EXPERIENCE_URL").unwrap_or_else(|_| "https://openccb.local".to_string());
+    match link_url {
+        Some(link) if link.starts_with("http") => link.to_string(),
+        Some(link) => format!("{}{}", base_url, link),
+        None => format!("{}/notifications", base_url),
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct PendingEmailRow {
+    id: Uuid,
+    organization_id: Uuid,
+    user_id: Uuid,
+    title: String,
+    message: String,
+    notification_type: String,
+    link_url: Option<String>,
+    created_at: DateTime<Utc>,
+    email: String,
+    full_name: Option<String>,
+    organization_name: String,
+}
+
+/// Envía por email las notificaciones con canal `email_immediate` aún no enviadas.
+/// Cada lote se reclama marcando `emailed_at` antes de enviar (`SKIP LOCKED` evita
+/// duplicados entre instancias), así no se mantienen bloqueos durante el envío SMTP.
+/// Un envío fallido devuelve la fila a la cola hasta `MAX_EMAIL_ATTEMPTS`; sin SMTP o
+/// con la plantilla deshabilitada la notificación queda solo en la plataforma.
+pub async fn deliver_immediate_notification_emails(pool: PgPool) {
+    let pending = sqlx::query_as::<_, PendingEmailRow>(
+        r#"
+        UPDATE notifications n
+        SET emailed_at = NOW(), email_attempts = n.email_attempts + 1
+        FROM (
+            SELECT n.id, u.email, u.full_name, o.name AS organization_name
+            FROM notifications n
+            JOIN users u ON u.id = n.user_id
+            JOIN organizations o ON o.id = n.organization_id
+            WHERE n.delivery_channel = 'email_immediate'
+              AND n.emailed_at IS NULL
+              AND n.email_attempts < $1
+              AND n.created_at > NOW() - INTERVAL '2 days'
+            ORDER BY n.created_at
+            LIMIT 200
+            FOR UPDATE OF n SKIP LOCKED
+        ) claimed
+        WHERE n.id = claimed.id
+        RETURNING n.id, n.organization_id, n.user_id, n.title, n.message,
+                  COALESCE(n.notification_type, 'info') AS notification_type,
+                  n.link_url, n.created_at, claimed.email, claimed.full_name,
+                  claimed.organization_name
+        "#,
+    )
+    .bind(MAX_EMAIL_ATTEMPTS)
+    .fetch_all(&pool)
+    .await;
+
+    let pending = match pending {
+        Ok(rows) => rows,
+        Err(e) => {
+            tracing::error!("deliver_immediate_notification_emails: failed to claim pending: {}", e);
+            return;
+        }
+    };
+
+    let mut templates: HashMap<Uuid, Option<OrgEmailTemplate>> = HashMap::new();
+    for row in pending {
+        let Some(smtp) = load_smtp_config(&pool, row.organization_id).await else {
+            continue;
+        };
+        let template = match templates.get(&row.organization_id) {
+            Some(template) => template.clone(),
+            None => match resolve_template(
+                row.organization_id,
+                "notification_immediate",
+                DEFAULT_IMMEDIATE_SUBJECT,
+                DEFAULT_IMMEDIATE_BODY,
+            )
+            .await
+            {
+                Ok(template) => {
+                    templates.insert(row.organization_id, template.clone());
+                    template
+                }
+                Err(e) => {
+                    tracing::warn!("Plantilla de notificación no disponible para {}: {}", row.organization_id, e);
+                    release_email_claim(&pool, row.id).await;
+                    continue;
+                }
+            },
+        };
+        let Some(template) = template else {
+            continue;
+        };
+
+        let recipient_name = row.full_name.clone().unwrap_or_else(|| "Usuario".to_string());
+        let unsubscribe = unsubscribe_url(row.user_id, &row.notification_type);
+        let mut variables = HashMap::new();
+        variables.insert("recipient_name", recipient_name.clone());
+        variables.insert("notification_title", row.title.clone());
+        variables.insert("notification_message", row.message.clone());
+        variables.insert("notification_url", absolute_link(row.link_url.as_deref()));
+        variables.insert("unsubscribe_url", unsubscribe.clone());
+        variables.insert("organization_name", row.organization_name.clone());
+
+        let subject = render_template(&template.subject_template, &variables);
+        let body = render_template(&template.body_template, &body_variables(&variables, template.is_html));
+
+        if let Err(e) = send_email_with_unsubscribe(
+            &smtp,
+            &row.email,
+            &recipient_name,
+            &subject,
+            &body,
+            template.is_html,
+            &unsubscribe,
+        )
+        .await
+        {
+            tracing::warn!(
+                "Email de notificación {} ({}) no enviado a {}: {}",
+                row.id,
+                row.created_at,
+                row.email,
+                e
+            );
+            release_email_claim(&pool, row.id).await;
+        }
+    }
+}
+
+/// Devuelve una notificación reclamada a la cola tras un envío fallido.
+async fn release_email_claim(pool: &PgPool, notification_id: Uuid) {
+    if let Err(e) = sqlx::query("UPDATE notifications SET emailed_at = NULL WHERE id = $1")
+        .bind(notification_id)
+        .execute(pool)
+        .await
+    {
+        tracing::error!("No se pudo liberar la notificación {}: {}", notification_id, e);
+    }
+}
+
+/// Envía los resúmenes diarios y semanales pendientes.
+pub async fn send_notification_digests(pool: PgPool) {
+    for (channel, state_column, period, label) in [
+        ("daily_digest", "last_daily_at", "1 day", "diario"),
+        ("weekly_digest", "last_weekly_at", "7 days", "semanal"),
+    ] {
+        let due_users = sqlx::query_scalar::<_, Uuid>(&format!(
+            r#"
+            SELECT DISTINCT n.user_id
+            FROM notifications n
+            LEFT JOIN notification_digest_state s ON s.user_id = n.user_id
+            WHERE n.delivery_channel = $1
+              AND n.emailed_at IS NULL
+              AND (s.{col} IS NULL OR s.{col} < NOW() - INTERVAL '{period}')
+            "#,
+            col = state_column,
+            period = period
+        ))
+        .bind(channel)
+        .fetch_all(&pool)
+        .await;
+
+        let due_users = match due_users {
+            Ok(ids) => ids,
+            Err(e) => {
+                tracing::error!("send_notification_digests: failed to list {} users: {}", channel, e);
+                continue;
+            }
+        };
+
+        for user_id in due_users {
+            if let Err(e) = send_user_digest(&pool, user_id, channel, state_column, period, label).await {
+                tracing::error!("send_notification_digests: user {} ({}) failed: {}", user_id, channel, e);
+            }
+        }
+    }
+}
+
+/// Como en los emails inmediatos, el resumen se reclama (estado y notificaciones) en
+/// una transacción corta y se envía después de confirmarla.
+async fn send_user_digest(
+    pool: &PgPool,
+    user_id: Uuid,
+    channel: &str,
+    state_column: &str,
+    period: &str,
+    label: &str,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // Reclamar el envío: solo una instancia gana la actualización del estado
+    let claimed = sqlx::query_scalar::<_, Uuid>(&format!(
+        r#"
+        INSERT INTO notification_digest_state (user_id, {col})
+        VALUES ($1, NOW())
+        ON CONFLICT (user_id) DO UPDATE SET {col} = NOW()
+        WHERE notification_digest_state.{col} IS NULL
+           OR notification_digest_state.{col} < NOW() - INTERVAL '{period}'
+        RETURNING user_id
+        "#,
+        col = state_column,
+        period = period
+    ))
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if claimed.is_none() {
+        return Ok(());
+    }
+
+    // Las notificaciones del resumen se marcan como enviadas en el mismo claim; si el
+    // envío falla se devuelven a la cola junto con el estado del resumen
+    let mut pending = sqlx::query_as::<_, PendingEmailRow>(
+        r#"
+        UPDATE notifications n
+        SET emailed_at = NOW()
+        FROM (
+            SELECT n.id, u.email, u.full_name, o.name AS organization_name
+            FROM notifications n
+            JOIN users u ON u.id = n.user_id
+            JOIN organizations o ON o.id = n.organization_id
+            WHERE n.user_id = $1
+              AND n.delivery_channel = $2
+              AND n.emailed_at IS NULL
+            FOR UPDATE OF n SKIP LOCKED
+        ) claimed
+        WHERE n.id = claimed.id
+        RETURNING n.id, n.organization_id, n.user_id, n.title, n.message,
+                  COALESCE(n.notification_type, 'info') AS notification_type,
+                  n.link_url, n.created_at, claimed.email, claimed.full_name,
+                  claimed.organization_name
+        "#,
+    )
+    .bind(user_id)
+    .bind(channel)
+    .fetch_all(&mut *tx)
+    .await?;
+    pending.sort_by_key(|n| n.created_at);
+
+    let ids: Vec<Uuid> = pending.iter().map(|n| n.id).collect();
+
+    // Las ya leídas en la plataforma no se repiten en el correo
+    let unread: Vec<&PendingEmailRow> = {
+        let read_ids: Vec<Uuid> = sqlx::query_scalar::<_, Uuid>(
+            "SELECT id FROM notifications WHERE id = ANY($1) AND is_read = TRUE",
+        )
+        .bind(&ids)
+        .fetch_all(&mut *tx)
+        .await?;
+        pending.iter().filter(|n| !read_ids.contains(&n.id)).collect()
+    };
+
+    // Sin bloqueos ni transacción abierta durante el envío SMTP
+    tx.commit().await?;
+
+    if let Some(first) = unread.first() {
+        let smtp = load_smtp_config(pool, first.organization_id).await;
+        let template = match resolve_template(
+            first.organization_id,
+            "notification_digest",
+            DEFAULT_DIGEST_SUBJECT,
+            DEFAULT_DIGEST_BODY,
+        )
+        .await
+        {
+            Ok(template) => template,
+            Err(e) => {
+                tracing::warn!("Plantilla de resumen no disponible para {}: {}", first.organization_id, e);
+                return release_digest_claim(pool, user_id, state_column, &ids).await;
+            }
+        };
+
+        if let (Some(smtp), Some(template)) = (smtp, template) {
+            let recipient_name = first.full_name.clone().unwrap_or_else(|| "Usuario".to_string());
+            let unsubscribe = unsubscribe_url(user_id, ALL_TYPES);
+            let mut variables = HashMap::new();
+            variables.insert("recipient_name", recipient_name.clone());
+            variables.insert("digest_period", label.to_string());
+            variables.insert("notification_count", unread.len().to_string());
+            variables.insert("notifications_url", absolute_link(None));
+            variables.insert("unsubscribe_url", unsubscribe.clone());
+            variables.insert("organization_name", first.organization_name.clone());
+
+            let subject = render_template(&template.subject_template, &variables);
+            let mut body_vars = body_variables(&variables, template.is_html);
+            body_vars.insert("notification_list", digest_list(&unread, template.is_html));
+            let body = render_template(&template.body_template, &body_vars);
+
+            if let Err(e) = send_email_with_unsubscribe(
+                &smtp,
+                &first.email,
+                &recipient_name,
+                &subject,
+                &body,
+                template.is_html,
+                &unsubscribe,
+            )
+            .await
+            {
+                tracing::warn!("Resumen {} no enviado a {}: {}", label, first.email, e);
+                return release_digest_claim(pool, user_id, state_column, &ids).await;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Devuelve a la cola un resumen reclamado cuyo envío falló, para reintentarlo en la
+/// próxima ejecución.
+async fn release_digest_claim(
+    pool: &PgPool,
+    user_id: Uuid,
+    state_column: &str,
+    notification_ids: &[Uuid],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE notifications SET emailed_at = NULL WHERE id = ANY($1)")
+        .bind(notification_ids)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(&format!(
+        "UPDATE notification_digest_state SET {col} = NULL WHERE user_id = $1",
+        col = state_column
+    ))
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(title: &str, message: &str) -> PendingEmailRow {
+        PendingEmailRow {
+            id: Uuid::new_v4(),
+            organization_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            title: title.to_string(),
+            message: message.to_string(),
+            notification_type: "forum_reply".to_string(),
+            link_url: Some("https://example.com/t?a=1&b=2".to_string()),
+            created_at: Utc::now(),
+            email: "ana@example.com".to_string(),
+            full_name: None,
+            organization_name: "Org".to_string(),
+        }
+    }
+
+    #[test]
+    fn html_templates_escape_variables() {
+        let mut variables = HashMap::new();
+        variables.insert("notification_title", "<script>alert(1)</script>".to_string());
+        variables.insert("recipient_name", "O'Brien & Co".to_string());
+
+        let html = body_variables(&variables, true);
+        assert_eq!(html["notification_title"], "&lt;script&gt;alert(1)&lt;/script&gt;");
+        assert_eq!(html["recipient_name"], "O&#39;Brien &amp; Co");
+
+        let text = body_variables(&variables, false);
+        assert_eq!(text["notification_title"], "<script>alert(1)</script>");
+    }
+
+    #[test]
+    fn digest_list_follows_template_format() {
+        let first = row("<b>Nuevo</b>", "Respuesta \"citada\"");
+        let second = row("Otro", "Texto");
+        let rows = vec![&first, &second];
+
+        let html = digest_list(&rows, true);
+        assert!(html.starts_with("<ul><li><strong>&lt;b&gt;Nuevo&lt;/b&gt;</strong>"));
+        assert!(html.contains("Respuesta &quot;citada&quot;"));
+        assert!(html.contains("href=\"https://example.com/t?a=1&amp;b=2\""));
+        assert_eq!(html.matches("<li>").count(), 2);
+
+        let text = digest_list(&rows, false);
+        assert!(text.starts_with("• <b>Nuevo</b> — Respuesta \"citada\"\n  https://example.com/t?a=1&b=2"));
+        assert!(text.contains("\n\n• Otro — Texto"));
+    }
+}
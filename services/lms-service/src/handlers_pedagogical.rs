@@ -40,10 +40,9 @@ pub struct CourseQualityMetrics {
 pub struct QuizDiscriminationItem {
     pub lesson_id: Uuid,
     pub lesson_title: String,
-    /// Texto truncado de la pregunta (block_id como proxy)
+    /// Id de la pregunta dentro de la lección (`quiz_item_responses.item_id`)
     pub block_id: String,
-    /// Correlación punto-biserial approx: diferencia de score medio
-    /// entre alumnos que respondieron bien vs mal esa pregunta
+    /// Correlación punto-biserial corregida: pregunta vs. puntaje del resto de la evaluación
     pub discrimination_index: f64,
     /// % de alumnos que acertaron esta pregunta
     pub facility_index: f64,
@@ -109,21 +108,16 @@ pub async fn get_lesson_quality_metrics(
             COUNT(DISTINCT g.user_id)::float8 / NULLIF($3::float8, 0)    AS completion_rate,
 
             -- Promedio de intentos entre quienes entregaron
-            COALESCE(AVG(g.attempts_count)::float8, 0)                   AS avg_attempts,
+            COALESCE(AVG(g.attempts)::float8, 0)                         AS avg_attempts,
 
             -- Puntaje medio
-            COALESCE(AVG(g.score)::float8, 0)                            AS avg_score,
+            COALESCE(AVG(g.last_score)::float8, 0)                       AS avg_score,
 
             -- Tasa de fallo (todos los intentos con score < 0.6)
-            (
-                SELECT COALESCE(
-                    COUNT(DISTINCT g2.user_id)::float8 / NULLIF(COUNT(DISTINCT g.user_id)::float8, 0),
-                    0
-                )
-                FROM user_grades g2
-                WHERE g2.lesson_id = l.id
-                  AND g2.organization_id = $2
-                  AND g2.score < 0.6
+            COALESCE(
+                COUNT(DISTINCT g.user_id) FILTER (WHERE g.best_score < 0.6)::float8
+                    / NULLIF(COUNT(DISTINCT g.user_id)::float8, 0),
+                0
             )                                                             AS failure_rate,
 
             -- Alumnos inscritos que NUNCA enviaron esta lección
@@ -133,8 +127,8 @@ pub async fn get_lesson_quality_metrics(
 
         FROM lessons l
         JOIN modules m ON l.module_id = m.id
-        LEFT JOIN user_grades g
-            ON g.lesson_id = l.id AND g.organization_id = $2
+        LEFT JOIN la_lesson_progress g
+            ON g.lesson_id = l.id AND g.organization_id = $2 AND g.attempts IS NOT NULL
         WHERE m.course_id = $1
           AND l.organization_id = $2
         GROUP BY l.id, l.title, l.position
@@ -173,9 +167,8 @@ pub async fn get_lesson_quality_metrics(
 // Endpoint 2: Índice de Discriminación de preguntas
 // GET /courses/{id}/pedagogical/discrimination-index
 //
-// Usa punto-biserial simplificado: compara el puntaje global del curso entre
-// alumnos que acertaron cada bloque vs los que fallaron.
-// La columna metadata->>'block_scores' guarda un objeto {block_id: score_0_1}.
+// Punto-biserial corregida por pregunta a partir del primer intento de cada alumno
+// en `quiz_item_responses` (ver `psychometrics`).
 // ─────────────────────────────────────────────────────────────────────────────
 
 pub async fn get_quiz_discrimination_index(
@@ -183,28 +176,17 @@ pub async fn get_quiz_discrimination_index(
     State(pool): State<PgPool>,
     Path(course_id): Path<Uuid>,
 ) -> Result<Json<CourseDiscriminationReport>, (StatusCode, String)> {
-    use std::collections::HashMap;
+    use std::collections::BTreeMap;
 
-    let rows = sqlx::query(
+    let rows: Vec<(Uuid, String, Uuid, String, f32)> = sqlx::query_as(
         r#"
-        SELECT
-            g.lesson_id,
-            l.title AS lesson_title,
-            bs.key   AS block_id,
-            (bs.value::text::float8) AS block_score,
-            g.score  AS overall_score
-        FROM user_grades g
-        JOIN lessons l ON l.id = g.lesson_id
-        JOIN LATERAL jsonb_each(
-            CASE
-                WHEN g.metadata ? 'block_scores'
-                    THEN g.metadata -> 'block_scores'
-                ELSE '{}'::jsonb
-            END
-        ) AS bs(key, value) ON TRUE
-        WHERE g.course_id = $1
-          AND g.organization_id = $2
-          AND (bs.value::text) ~ '^[0-9]+\.?[0-9]*$'
+        SELECT DISTINCT ON (r.lesson_id, r.user_id, r.item_id)
+               r.lesson_id, l.title, r.user_id, r.item_id, r.score
+        FROM quiz_item_responses r
+        JOIN lessons l ON l.id = r.lesson_id
+        WHERE r.course_id = $1
+          AND r.organization_id = $2
+        ORDER BY r.lesson_id, r.user_id, r.item_id, r.attempt ASC
         "#,
     )
     .bind(course_id)
@@ -213,68 +195,29 @@ pub async fn get_quiz_discrimination_index(
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
 
-    if rows.is_empty() {
-        return Ok(Json(CourseDiscriminationReport {
-            course_id,
-            items: vec![],
-        }));
-    }
-
-    struct BlockStats {
-        lesson_id: Uuid,
-        lesson_title: String,
-        block_id: String,
-        pass_scores: Vec<f64>,
-        fail_scores: Vec<f64>,
-        pass_count: i64,
-        total: i64,
+    let mut titles: BTreeMap<Uuid, String> = BTreeMap::new();
+    let mut lessons: BTreeMap<Uuid, Vec<(Uuid, String, f64)>> = BTreeMap::new();
+    for (lesson_id, lesson_title, user_id, item_id, score) in rows {
+        titles.entry(lesson_id).or_insert(lesson_title);
+        lessons.entry(lesson_id).or_default().push((user_id, item_id, score as f64));
     }
 
-    let mut map: HashMap<String, BlockStats> = HashMap::new();
-    for row in &rows {
-        let lesson_id: Uuid = row.get("lesson_id");
-        let lesson_title: String = row.get("lesson_title");
-        let block_id: String = row.get("block_id");
-        let block_score: f64 = row.get("block_score");
-        let overall_score: f64 = row.get("overall_score");
-
-        let key = format!("{}/{}", lesson_id, block_id);
-        let entry = map.entry(key).or_insert_with(|| BlockStats {
-            lesson_id,
-            lesson_title: lesson_title.clone(),
-            block_id: block_id.clone(),
-            pass_scores: vec![],
-            fail_scores: vec![],
-            pass_count: 0,
-            total: 0,
-        });
-        entry.total += 1;
-        if block_score >= 0.6 {
-            entry.pass_count += 1;
-            entry.pass_scores.push(overall_score);
-        } else {
-            entry.fail_scores.push(overall_score);
-        }
-    }
-
-    let mean = |v: &[f64]| -> f64 {
-        if v.is_empty() { 0.0 } else { v.iter().sum::<f64>() / v.len() as f64 }
-    };
-
-    let mut items: Vec<QuizDiscriminationItem> = map
-        .into_values()
-        .filter(|s| s.total >= 3)
-        .map(|s| {
-            let facility = s.pass_count as f64 / s.total as f64;
-            let discrimination = mean(&s.pass_scores) - mean(&s.fail_scores);
-            QuizDiscriminationItem {
-                lesson_id: s.lesson_id,
-                lesson_title: s.lesson_title,
-                block_id: s.block_id,
-                discrimination_index: (discrimination * 100.0).round() / 100.0,
-                facility_index: (facility * 100.0).round() / 100.0,
-                sample_size: s.total,
-            }
+    let round = |v: f64| (v * 100.0).round() / 100.0;
+    let mut items: Vec<QuizDiscriminationItem> = lessons
+        .into_iter()
+        .flat_map(|(lesson_id, responses)| {
+            let lesson_title = titles.get(&lesson_id).cloned().unwrap_or_default();
+            crate::psychometrics::item_classical_stats(responses)
+                .into_iter()
+                .filter(|(_, stats)| stats.responses >= 3)
+                .map(move |(item_id, stats)| QuizDiscriminationItem {
+                    lesson_id,
+                    lesson_title: lesson_title.clone(),
+                    block_id: item_id,
+                    discrimination_index: round(stats.point_biserial.unwrap_or(0.0)),
+                    facility_index: round(stats.p_value),
+                    sample_size: stats.responses,
+                })
         })
         .collect();
 
@@ -316,14 +259,14 @@ pub async fn get_curricular_suggestions(
             l.title                                                         AS lesson_title,
             l.position,
             COUNT(DISTINCT g.user_id)::float8 / $3::float8                 AS completion_rate,
-            COALESCE(AVG(g.score)::float8, 0)                              AS avg_score,
-            COALESCE(AVG(g.attempts_count)::float8, 0)                     AS avg_attempts,
+            COALESCE(AVG(g.last_score)::float8, 0)                         AS avg_score,
+            COALESCE(AVG(g.attempts)::float8, 0)                           AS avg_attempts,
             -- Tasa de abandono
             ($3 - COUNT(DISTINCT g.user_id))::float8 / $3::float8         AS abandonment_rate
         FROM lessons l
         JOIN modules m ON l.module_id = m.id
-        LEFT JOIN user_grades g
-            ON g.lesson_id = l.id AND g.organization_id = $2
+        LEFT JOIN la_lesson_progress g
+            ON g.lesson_id = l.id AND g.organization_id = $2 AND g.attempts IS NOT NULL
         WHERE m.course_id = $1
           AND l.organization_id = $2
         GROUP BY l.id, l.title, l.position
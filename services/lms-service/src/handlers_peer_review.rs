@@ -47,6 +47,7 @@ pub struct SubmissionDetail {
     pub updated_at: chrono::DateTime<chrono::Utc>,
     pub organization_id: Uuid,
     pub final_score: Option<f64>,
+    pub late_penalty: f32,
     pub review_count: i32,
     pub status: String,
 }
@@ -73,6 +74,14 @@ pub async fn submit_assignment(
     Path((course_id, lesson_id)): Path<(Uuid, Uuid)>,
     Json(payload): Json<SubmitAssignmentPayload>,
 ) -> Result<Json<CourseSubmission>, (StatusCode, String)> {
+    // La política de entrega tardía se evalúa en cada (re)entrega
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let late_penalty = crate::late_policies::enforce_late_policy(&mut conn, claims.sub, lesson_id).await?;
+    drop(conn);
+
     // Verificar si la entrega ya existe
     let existing: Option<CourseSubmission> = sqlx::query_as(
         "SELECT * FROM course_submissions WHERE user_id = $1 AND lesson_id = $2"
@@ -88,7 +97,7 @@ pub async fn submit_assignment(
         let updated: CourseSubmission = sqlx::query_as(
             r#"
             UPDATE course_submissions 
-            SET content = $1, updated_at = NOW() 
+            SET content = $1, late_penalty = $4, updated_at = NOW() 
             WHERE user_id = $2 AND lesson_id = $3
             RETURNING *
             "#
@@ -96,6 +105,7 @@ pub async fn submit_assignment(
         .bind(&payload.content)
         .bind(claims.sub)
         .bind(lesson_id)
+        .bind(late_penalty)
         .fetch_one(&pool)
         .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
@@ -106,8 +116,8 @@ pub async fn submit_assignment(
     // Crear nueva entrega
     let submission: CourseSubmission = sqlx::query_as(
         r#"
-        INSERT INTO course_submissions (user_id, course_id, lesson_id, organization_id, content)
-        VALUES ($1, $2, $3, $4, $5)
+        INSERT INTO course_submissions (user_id, course_id, lesson_id, organization_id, content, late_penalty)
+        VALUES ($1, $2, $3, $4, $5, $6)
         RETURNING *
         "#
     )
@@ -116,6 +126,7 @@ pub async fn submit_assignment(
     .bind(lesson_id)
     .bind(org_ctx.id)
     .bind(&payload.content)
+    .bind(late_penalty)
     .fetch_one(&pool)
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
@@ -510,8 +521,8 @@ pub async fn instructor_grade_submission(
     }
 
     // Verificar que la submission existe
-    let sub_exists: Option<Uuid> = sqlx::query_scalar(
-        "SELECT id FROM course_submissions WHERE id = $1 AND lesson_id = $2"
+    let submission: Option<(Uuid, Uuid)> = sqlx::query_as(
+        "SELECT user_id, course_id FROM course_submissions WHERE id = $1 AND lesson_id = $2"
     )
     .bind(payload.submission_id)
     .bind(lesson_id)
@@ -519,9 +530,9 @@ pub async fn instructor_grade_submission(
     .await
     .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
 
-    if sub_exists.is_none() {
+    let Some((student_id, course_id)) = submission else {
         return Err((StatusCode::NOT_FOUND, "Entrega no encontrada".to_string()));
-    }
+    };
 
     // Upsert: una sola calificación de instructor por entrega
     let review: PeerReviewWithFlag = sqlx::query_as(
@@ -547,6 +558,23 @@ pub async fn instructor_grade_submission(
     // Recalcular nota final ponderada
     recalculate_final_score(&pool, payload.submission_id, lesson_id).await?;
 
+    // Avisar al alumno de la nueva calificación
+    let _ = sqlx::query(
+        r#"
+        INSERT INTO notifications (organization_id, user_id, title, message, notification_type, link_url)
+        SELECT $1, $2, 'Nueva calificación: ' || l.title,
+               'Tu entrega en "' || l.title || '" fue calificada por el instructor.',
+               'grade', $3
+        FROM lessons l WHERE l.id = $4
+        "#
+    )
+    .bind(org_ctx.id)
+    .bind(student_id)
+    .bind(format!("/courses/{}/lessons/{}", course_id, lesson_id))
+    .bind(lesson_id)
+    .execute(&pool)
+    .await;
+
     Ok(Json(review))
 }
 
@@ -561,7 +589,7 @@ pub async fn get_my_submission(
     let sub: Option<SubmissionDetail> = sqlx::query_as(
         r#"
         SELECT id, user_id, course_id, lesson_id, content, submitted_at, updated_at,
-               organization_id, final_score, review_count, status
+               organization_id, final_score, late_penalty, review_count, status
         FROM course_submissions
         WHERE user_id = $1 AND lesson_id = $2
         "#
@@ -636,6 +664,14 @@ async fn recalculate_final_score(
         _ => None,
     };
 
+    // Penalización por entrega tardía registrada al entregar
+    let late_penalty: f32 = sqlx::query_scalar("SELECT late_penalty FROM course_submissions WHERE id = $1")
+        .bind(submission_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let final_score = final_score.map(|score| score * (1.0 - late_penalty as f64));
+
     let new_status = if final_score.is_some() { "graded" } else if peer_count > 0 { "under_review" } else { "pending" };
 
     sqlx::query(
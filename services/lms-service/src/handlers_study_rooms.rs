@@ -94,7 +94,7 @@ pub async fn list_course_study_rooms(
                bbb_meeting_id, join_url, scheduled_at, started_at, ended_at,
                max_participants, created_at, updated_at
         FROM study_rooms
-        WHERE course_id = $1 AND organization_id = $2
+        WHERE course_id = $1 AND organization_id = $2 AND status <> 'cancelled'
         ORDER BY created_at DESC
         "#,
     )
@@ -252,7 +252,7 @@ pub async fn join_study_room(
     }
 
     let room = sqlx::query_as::<_, RoomRow>(
-        "SELECT bbb_meeting_id, attendee_pw, moderator_pw, status, created_by FROM study_rooms WHERE id = $1 AND course_id = $2 AND organization_id = $3",
+        "SELECT bbb_meeting_id, attendee_pw, moderator_pw, status, created_by FROM study_rooms WHERE id = $1 AND course_id = $2 AND organization_id = $3 AND status <> 'cancelled'",
     )
     .bind(room_id)
     .bind(course_id)
@@ -315,7 +315,7 @@ pub async fn end_study_room(
     }
 
     let room = sqlx::query_as::<_, RoomRow>(
-        "SELECT bbb_meeting_id, moderator_pw, created_by FROM study_rooms WHERE id = $1 AND course_id = $2 AND organization_id = $3",
+        "SELECT bbb_meeting_id, moderator_pw, created_by FROM study_rooms WHERE id = $1 AND course_id = $2 AND organization_id = $3 AND status <> 'cancelled'",
     )
     .bind(room_id)
     .bind(course_id)
@@ -365,8 +365,8 @@ pub async fn delete_study_room(
     State(pool): State<PgPool>,
     Path((course_id, room_id)): Path<(Uuid, Uuid)>,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    let created_by = sqlx::query_scalar::<_, Uuid>(
-        "SELECT created_by FROM study_rooms WHERE id = $1 AND course_id = $2 AND organization_id = $3",
+    let (created_by, status, scheduled_at) = sqlx::query_as::<_, (Uuid, String, Option<DateTime<Utc>>)>(
+        "SELECT created_by, status, scheduled_at FROM study_rooms WHERE id = $1 AND course_id = $2 AND organization_id = $3 AND status <> 'cancelled'",
     )
     .bind(room_id)
     .bind(course_id)
@@ -380,7 +380,14 @@ pub async fn delete_study_room(
         return Err((StatusCode::FORBIDDEN, "Solo el creador puede eliminar la sala".to_string()));
     }
 
-    sqlx::query("DELETE FROM study_rooms WHERE id = $1")
+    // Una sala programada que no llegó a empezar se conserva como cancelada para que
+    // los calendarios suscritos reciban la cancelación
+    let query = if status == "pending" && scheduled_at.is_some() {
+        "UPDATE study_rooms SET status = 'cancelled', updated_at = NOW() WHERE id = $1"
+    } else {
+        "DELETE FROM study_rooms WHERE id = $1"
+    };
+    sqlx::query(query)
         .bind(room_id)
         .execute(&pool)
         .await
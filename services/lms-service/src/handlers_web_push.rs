@@ -0,0 +1,621 @@
+/// Notificaciones Web Push (VAPID, RFC 8292) con payload cifrado (RFC 8291, `aes128gcm`).
+///
+/// Cada dispositivo registra su suscripción del service worker. Una tarea en segundo
+/// plano toma las notificaciones recientes aún no enviadas y las entrega a todos los
+/// dispositivos del usuario, respetando el interruptor `push_enabled` de sus
+/// preferencias. Las suscripciones que el servicio de push da por expiradas
+/// (404/410) se eliminan. Solo se aceptan endpoints de los servicios de push
+/// conocidos, ya que el servidor hace peticiones a esa URL.
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use common::{auth::Claims, middleware::Org};
+use ring::{aead, agreement, hkdf, rand::SecureRandom, rand::SystemRandom, signature};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::env;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::handlers_notification_preferences::absolute_link;
+
+/// Tipos de notificación que se envían por push.
+pub const PUSH_TYPES: [&str; 4] = ["deadline", "announcement", "forum_reply", "grade"];
+
+/// Tamaño de registro declarado en la cabecera `aes128gcm`; el payload cabe en uno solo.
+const RECORD_SIZE: u32 = 4096;
+/// Máximo de caracteres del mensaje incluido en el payload.
+const MAX_BODY_CHARS: usize = 500;
+/// Fallos consecutivos tras los que se descarta una suscripción.
+const MAX_FAILURES: i32 = 10;
+/// Tiempo que el servicio de push conserva un mensaje si el dispositivo está offline.
+const PUSH_TTL_SECS: u32 = 24 * 3600;
+/// Servicios de push de los navegadores (el host debe ser uno de estos o un subdominio).
+/// `WEB_PUSH_ALLOWED_HOSTS` añade otros, separados por comas.
+const PUSH_SERVICE_HOSTS: [&str; 5] = [
+    "fcm.googleapis.com",
+    "android.googleapis.com",
+    "push.services.mozilla.com",
+    "notify.windows.com",
+    "push.apple.com",
+];
+
+// ─── Modelos ─────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct PushSubscriptionKeys {
+    pub p256dh: String,
+    pub auth: String,
+}
+
+/// Forma de `PushSubscription.toJSON()` en el navegador.
+#[derive(Debug, Deserialize)]
+pub struct RegisterPushSubscriptionPayload {
+    pub endpoint: String,
+    pub keys: PushSubscriptionKeys,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RemovePushSubscriptionPayload {
+    pub endpoint: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct PushSubscriptionInfo {
+    pub id: Uuid,
+    pub endpoint: String,
+    pub user_agent: Option<String>,
+    pub failure_count: i32,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(sqlx::FromRow)]
+struct PushTarget {
+    id: Uuid,
+    endpoint: String,
+    p256dh: String,
+    auth: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct PendingPush {
+    id: Uuid,
+    user_id: Uuid,
+    title: String,
+    message: String,
+    notification_type: String,
+    link_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PushMessage<'a> {
+    id: Uuid,
+    title: &'a str,
+    body: String,
+    url: String,
+    #[serde(rename = "type")]
+    notification_type: &'a str,
+}
+
+// ─── Configuración VAPID ─────────────────────────────────────────────────────
+
+pub struct VapidConfig {
+    /// Clave pública P-256 sin comprimir (65 bytes).
+    public_key: Vec<u8>,
+    /// Escalar privado P-256 (32 bytes).
+    private_key: Vec<u8>,
+    subject: String,
+}
+
+/// Decodifica base64url con o sin relleno (los navegadores y herramientas varían).
+fn decode_b64url(value: &str) -> Option<Vec<u8>> {
+    let normalized: String = value
+        .trim()
+        .trim_end_matches('=')
+        .chars()
+        .map(|c| match c {
+            '+' => '-',
+            '/' => '_',
+            other => other,
+        })
+        .collect();
+    URL_SAFE_NO_PAD.decode(normalized).ok()
+}
+
+/// Comprueba que `endpoint` apunte por HTTPS a un servicio de push permitido.
+/// Se rechazan IPs literales, puertos no estándar y credenciales en la URL.
+fn is_allowed_push_endpoint(endpoint: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(endpoint) else {
+        return false;
+    };
+    if url.scheme() != "https" || url.port().is_some_and(|port| port != 443) {
+        return false;
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return false;
+    }
+    let Some(host) = url.domain().map(|h| h.trim_end_matches('.').to_ascii_lowercase()) else {
+        return false;
+    };
+
+    let extra = env::var("WEB_PUSH_ALLOWED_HOSTS").unwrap_or_default();
+    PUSH_SERVICE_HOSTS
+        .iter()
+        .copied()
+        .chain(extra.split(',').map(str::trim).filter(|h| !h.is_empty()))
+        .any(|allowed| {
+            let allowed = allowed.to_ascii_lowercase();
+            host == allowed || host.ends_with(&format!(".{}", allowed))
+        })
+}
+
+/// Lee `VAPID_PUBLIC_KEY`, `VAPID_PRIVATE_KEY` (base64url, formato de
+/// `web-push generate-vapid-keys`) y `VAPID_SUBJECT`.
+pub fn vapid_config() -> Option<VapidConfig> {
+    let public_key = decode_b64url(&env::var("VAPID_PUBLIC_KEY").ok()?)?;
+    let private_key = decode_b64url(&env::var("VAPID_PRIVATE_KEY").ok()?)?;
+    if public_key.len() != 65 || private_key.len() != 32 {
+        tracing::error!("web_push: claves VAPID con longitud inválida");
+        return None;
+    }
+    let subject = env::var("VAPID_SUBJECT").unwrap_or_else(|_| "mailto:admin@openccb.local".to_string());
+    Some(VapidConfig {
+        public_key,
+        private_key,
+        subject,
+    })
+}
+
+/// Cabecera `Authorization: vapid t=<JWT ES256>, k=<clave pública>` para `endpoint`.
+fn vapid_authorization(config: &VapidConfig, endpoint: &str) -> Result<String, String> {
+    let url = reqwest::Url::parse(endpoint).map_err(|e| format!("endpoint inválido: {}", e))?;
+    let audience = url.origin().ascii_serialization();
+    let exp = Utc::now().timestamp() + 12 * 3600;
+
+    let header = URL_SAFE_NO_PAD.encode(br#"{"typ":"JWT","alg":"ES256"}"#);
+    let claims = URL_SAFE_NO_PAD.encode(
+        serde_json::json!({ "aud": audience, "exp": exp, "sub": config.subject }).to_string(),
+    );
+    let signing_input = format!("{}.{}", header, claims);
+
+    let rng = SystemRandom::new();
+    let key_pair = signature::EcdsaKeyPair::from_private_key_and_public_key(
+        &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+        &config.private_key,
+        &config.public_key,
+        &rng,
+    )
+    .map_err(|e| format!("clave VAPID rechazada: {}", e))?;
+    let sig = key_pair
+        .sign(&rng, signing_input.as_bytes())
+        .map_err(|_| "no se pudo firmar el JWT VAPID".to_string())?;
+
+    Ok(format!(
+        "vapid t={}.{}, k={}",
+        signing_input,
+        URL_SAFE_NO_PAD.encode(sig.as_ref()),
+        URL_SAFE_NO_PAD.encode(&config.public_key)
+    ))
+}
+
+// ─── Cifrado RFC 8291 ────────────────────────────────────────────────────────
+
+struct OkmLen(usize);
+
+impl hkdf::KeyType for OkmLen {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+fn hkdf_derive(salt: &[u8], ikm: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>, ring::error::Unspecified> {
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, salt).extract(ikm);
+    let mut out = vec![0u8; len];
+    prk.expand(&[info], OkmLen(len))?.fill(&mut out)?;
+    Ok(out)
+}
+
+/// Deriva la clave de contenido y el nonce a partir del secreto ECDH (RFC 8291 §3.3–3.4).
+fn derive_content_keys(
+    ecdh_secret: &[u8],
+    auth_secret: &[u8],
+    ua_public: &[u8],
+    as_public: &[u8],
+    salt: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), ring::error::Unspecified> {
+    let mut key_info = b"WebPush: info\0".to_vec();
+    key_info.extend_from_slice(ua_public);
+    key_info.extend_from_slice(as_public);
+    let ikm = hkdf_derive(auth_secret, ecdh_secret, &key_info, 32)?;
+
+    let cek = hkdf_derive(salt, &ikm, b"Content-Encoding: aes128gcm\0", 16)?;
+    let nonce = hkdf_derive(salt, &ikm, b"Content-Encoding: nonce\0", 12)?;
+    Ok((cek, nonce))
+}
+
+/// Cifra `plaintext` para la suscripción (`p256dh`, `auth`) en un único registro `aes128gcm`.
+pub fn encrypt_payload(
+    ua_public: &[u8],
+    auth_secret: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, ring::error::Unspecified> {
+    let rng = SystemRandom::new();
+    let as_private = agreement::EphemeralPrivateKey::generate(&agreement::ECDH_P256, &rng)?;
+    let as_public = as_private.compute_public_key()?.as_ref().to_vec();
+
+    let mut salt = [0u8; 16];
+    rng.fill(&mut salt)?;
+
+    let ecdh_secret = agreement::agree_ephemeral(
+        as_private,
+        &agreement::UnparsedPublicKey::new(&agreement::ECDH_P256, ua_public),
+        |secret| secret.to_vec(),
+    )?;
+
+    encrypt_record(&ecdh_secret, auth_secret, ua_public, &as_public, &salt, plaintext)
+}
+
+/// Construye el cuerpo `aes128gcm` a partir del secreto ECDH ya acordado.
+fn encrypt_record(
+    ecdh_secret: &[u8],
+    auth_secret: &[u8],
+    ua_public: &[u8],
+    as_public: &[u8],
+    salt: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, ring::error::Unspecified> {
+    let (cek, nonce) = derive_content_keys(ecdh_secret, auth_secret, ua_public, as_public, salt)?;
+
+    // Delimitador 0x02: último (y único) registro, sin relleno
+    let mut record = plaintext.to_vec();
+    record.push(0x02);
+
+    let key = aead::LessSafeKey::new(aead::UnboundKey::new(&aead::AES_128_GCM, &cek)?);
+    key.seal_in_place_append_tag(
+        aead::Nonce::try_assume_unique_for_key(&nonce)?,
+        aead::Aad::empty(),
+        &mut record,
+    )?;
+
+    // Cabecera: salt (16) | rs (4) | idlen (1) | keyid (clave pública efímera)
+    let mut body = Vec::with_capacity(21 + as_public.len() + record.len());
+    body.extend_from_slice(salt);
+    body.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+    body.push(as_public.len() as u8);
+    body.extend_from_slice(as_public);
+    body.extend_from_slice(&record);
+    Ok(body)
+}
+
+// ─── Envío ───────────────────────────────────────────────────────────────────
+
+enum PushOutcome {
+    Delivered,
+    /// El servicio de push indica que la suscripción ya no existe.
+    Gone,
+    Failed(String),
+}
+
+async fn send_push(
+    client: &reqwest::Client,
+    config: &VapidConfig,
+    target: &PushTarget,
+    payload: &[u8],
+    urgency: &str,
+) -> PushOutcome {
+    // Suscripciones registradas antes de la lista de servicios permitidos
+    if !is_allowed_push_endpoint(&target.endpoint) {
+        return PushOutcome::Gone;
+    }
+    let (Some(ua_public), Some(auth_secret)) = (decode_b64url(&target.p256dh), decode_b64url(&target.auth)) else {
+        return PushOutcome::Gone;
+    };
+
+    let body = match encrypt_payload(&ua_public, &auth_secret, payload) {
+        Ok(body) => body,
+        // Una clave de navegador inválida no se va a corregir sola
+        Err(_) => return PushOutcome::Gone,
+    };
+
+    let authorization = match vapid_authorization(config, &target.endpoint) {
+        Ok(value) => value,
+        Err(e) => return PushOutcome::Failed(e),
+    };
+
+    let response = client
+        .post(&target.endpoint)
+        .header("Authorization", authorization)
+        .header("Content-Encoding", "aes128gcm")
+        .header("Content-Type", "application/octet-stream")
+        .header("TTL", PUSH_TTL_SECS.to_string())
+        .header("Urgency", urgency)
+        .body(body)
+        .send()
+        .await;
+
+    match response {
+        Ok(resp) if resp.status().is_success() => PushOutcome::Delivered,
+        Ok(resp) if resp.status() == reqwest::StatusCode::NOT_FOUND || resp.status() == reqwest::StatusCode::GONE => {
+            PushOutcome::Gone
+        }
+        Ok(resp) => PushOutcome::Failed(format!("HTTP {}", resp.status())),
+        Err(e) => PushOutcome::Failed(e.to_string()),
+    }
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((idx, _)) => format!("{}...", &text[..idx]),
+        None => text.to_string(),
+    }
+}
+
+/// Entrega por push las notificaciones recientes de los tipos en `PUSH_TYPES`.
+/// Cada lote se reclama marcando `pushed_at` antes de enviar (`SKIP LOCKED` evita
+/// duplicados entre instancias), así no se mantienen bloqueos durante las peticiones
+/// HTTP; una notificación cuyo envío falla no se reintenta.
+pub async fn deliver_push_notifications(pool: PgPool) {
+    let Some(config) = vapid_config() else {
+        return;
+    };
+
+    let pending = sqlx::query_as::<_, PendingPush>(
+        r#"
+        UPDATE notifications n
+        SET pushed_at = NOW()
+        FROM (
+            SELECT n.id
+            FROM notifications n
+            LEFT JOIN notification_preferences p
+                   ON p.user_id = n.user_id
+                  AND p.notification_type = COALESCE(n.notification_type, 'info')
+            WHERE n.pushed_at IS NULL
+              AND n.is_read IS NOT TRUE
+              AND n.created_at > NOW() - INTERVAL '1 hour'
+              AND COALESCE(n.notification_type, 'info') = ANY($1)
+              AND COALESCE(p.push_enabled, TRUE)
+              AND EXISTS (SELECT 1 FROM push_subscriptions s WHERE s.user_id = n.user_id)
+            ORDER BY n.created_at
+            LIMIT 200
+            FOR UPDATE OF n SKIP LOCKED
+        ) claimed
+        WHERE n.id = claimed.id
+        RETURNING n.id, n.user_id, n.title, n.message,
+                  COALESCE(n.notification_type, 'info') AS notification_type, n.link_url
+        "#,
+    )
+    .bind(PUSH_TYPES.iter().map(|t| t.to_string()).collect::<Vec<_>>())
+    .fetch_all(&pool)
+    .await;
+
+    let pending = match pending {
+        Ok(rows) => rows,
+        Err(e) => {
+            tracing::error!("deliver_push_notifications: failed to load pending: {}", e);
+            return;
+        }
+    };
+
+    // Sin redirecciones: el destino tiene que ser el servicio de push validado
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new());
+
+    for notification in pending {
+        let targets = sqlx::query_as::<_, PushTarget>(
+            "SELECT id, endpoint, p256dh, auth FROM push_subscriptions WHERE user_id = $1",
+        )
+        .bind(notification.user_id)
+        .fetch_all(&pool)
+        .await
+        .unwrap_or_default();
+
+        let message = PushMessage {
+            id: notification.id,
+            title: &notification.title,
+            body: truncate_chars(&notification.message, MAX_BODY_CHARS),
+            url: absolute_link(notification.link_url.as_deref()),
+            notification_type: &notification.notification_type,
+        };
+        let payload = serde_json::to_vec(&message).unwrap_or_default();
+        let urgency = if notification.notification_type == "deadline" { "high" } else { "normal" };
+
+        for target in targets {
+            match send_push(&client, &config, &target, &payload, urgency).await {
+                PushOutcome::Delivered => {
+                    let _ = sqlx::query(
+                        "UPDATE push_subscriptions SET failure_count = 0, last_success_at = NOW() WHERE id = $1",
+                    )
+                    .bind(target.id)
+                    .execute(&pool)
+                    .await;
+                }
+                PushOutcome::Gone => {
+                    tracing::info!("web_push: suscripción {} expirada, eliminando", target.id);
+                    let _ = sqlx::query("DELETE FROM push_subscriptions WHERE id = $1")
+                        .bind(target.id)
+                        .execute(&pool)
+                        .await;
+                }
+                PushOutcome::Failed(e) => {
+                    tracing::warn!("web_push: envío a {} falló: {}", target.id, e);
+                    let _ = sqlx::query(
+                        "UPDATE push_subscriptions SET failure_count = failure_count + 1 WHERE id = $1",
+                    )
+                    .bind(target.id)
+                    .execute(&pool)
+                    .await;
+                }
+            }
+        }
+    }
+
+    // Limpieza de suscripciones que fallan de forma persistente
+    let _ = sqlx::query("DELETE FROM push_subscriptions WHERE failure_count >= $1")
+        .bind(MAX_FAILURES)
+        .execute(&pool)
+        .await;
+}
+
+// ─── Handlers ────────────────────────────────────────────────────────────────
+
+/// GET /push/vapid-public-key
+/// Clave pública para `pushManager.subscribe({ applicationServerKey })`.
+pub async fn get_vapid_public_key(_claims: Claims) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let config = vapid_config().ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Las notificaciones push no están configuradas".to_string(),
+    ))?;
+    Ok(Json(serde_json::json!({
+        "public_key": URL_SAFE_NO_PAD.encode(&config.public_key)
+    })))
+}
+
+/// GET /push/subscriptions
+pub async fn list_push_subscriptions(
+    claims: Claims,
+    State(pool): State<PgPool>,
+) -> Result<Json<Vec<PushSubscriptionInfo>>, (StatusCode, String)> {
+    let subscriptions = sqlx::query_as::<_, PushSubscriptionInfo>(
+        r#"
+        SELECT id, endpoint, user_agent, failure_count, last_success_at, created_at
+        FROM push_subscriptions
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(subscriptions))
+}
+
+/// POST /push/subscriptions
+/// Registra (o reasigna) la suscripción del dispositivo actual.
+pub async fn register_push_subscription(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    headers: HeaderMap,
+    Json(payload): Json<RegisterPushSubscriptionPayload>,
+) -> Result<Json<PushSubscriptionInfo>, (StatusCode, String)> {
+    if !is_allowed_push_endpoint(&payload.endpoint) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "El endpoint no pertenece a un servicio de push permitido".to_string(),
+        ));
+    }
+    let valid_keys = matches!(decode_b64url(&payload.keys.p256dh), Some(k) if k.len() == 65)
+        && matches!(decode_b64url(&payload.keys.auth), Some(k) if k.len() == 16);
+    if !valid_keys {
+        return Err((StatusCode::BAD_REQUEST, "Claves de suscripción inválidas".to_string()));
+    }
+
+    let user_agent = headers
+        .get(axum::http::header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+
+    let subscription = sqlx::query_as::<_, PushSubscriptionInfo>(
+        r#"
+        INSERT INTO push_subscriptions (user_id, organization_id, endpoint, p256dh, auth, user_agent)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (endpoint) DO UPDATE SET
+            user_id = EXCLUDED.user_id,
+            organization_id = EXCLUDED.organization_id,
+            p256dh = EXCLUDED.p256dh,
+            auth = EXCLUDED.auth,
+            user_agent = EXCLUDED.user_agent,
+            failure_count = 0,
+            updated_at = NOW()
+        RETURNING id, endpoint, user_agent, failure_count, last_success_at, created_at
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(org_ctx.id)
+    .bind(&payload.endpoint)
+    .bind(&payload.keys.p256dh)
+    .bind(&payload.keys.auth)
+    .bind(user_agent)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(subscription))
+}
+
+/// DELETE /push/subscriptions
+pub async fn remove_push_subscription(
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Json(payload): Json<RemovePushSubscriptionPayload>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sqlx::query("DELETE FROM push_subscriptions WHERE user_id = $1 AND endpoint = $2")
+        .bind(claims.sub)
+        .bind(&payload.endpoint)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn b64(value: &str) -> Vec<u8> {
+        decode_b64url(value).unwrap()
+    }
+
+    /// Ejemplo del Apéndice A de RFC 8291.
+    #[test]
+    fn encryption_matches_rfc8291_appendix_a() {
+        let ua_public = b64(
+            "BCVxsr7N_eNgVRqvHtD0zTZsEc6-VV-JvLexhqUzORcxaOzi6-AYWXvTBHm4bjyPjs7Vd8pZGH6SRpkNtoIAiw4",
+        );
+        let as_public = b64(
+            "BP4z9KsN6nGRTbVYI_c7VJSPQTBtkgcy27mlmlMoZIIgDll6e3vCYLocInmYWAmS6TlzAC8wEqKK6PBru3jl7A8",
+        );
+        let auth_secret = b64("BTBZMqHH6r4Tts7J_aSIgg");
+        let salt = b64("DGv6ra1nlYgDCS1FRnbzlw");
+        let ecdh_secret = b64("kyrL1jIIOHEzg3sM2ZWRHDRB62YACZhhSlknJ672kSs");
+        let plaintext = b64("V2hlbiBJIGdyb3cgdXAsIEkgd2FudCB0byBiZSBhIHdhdGVybWVsb24");
+
+        let (cek, nonce) = derive_content_keys(&ecdh_secret, &auth_secret, &ua_public, &as_public, &salt).unwrap();
+        assert_eq!(cek, b64("oIhVW04MRdy2XN9CiKLxTg"));
+        assert_eq!(nonce, b64("4h_95klXJ5E_qnoN"));
+
+        let body = encrypt_record(&ecdh_secret, &auth_secret, &ua_public, &as_public, &salt, &plaintext).unwrap();
+        assert_eq!(
+            URL_SAFE_NO_PAD.encode(body),
+            "DGv6ra1nlYgDCS1FRnbzlwAAEABBBP4z9KsN6nGRTbVYI_c7VJSPQTBtkgcy27mlmlMoZIIgDll6e3vCYLocInmYWAmS6TlzAC8wEqKK6PBru3jl7A_yl95bQpu6cVPTpK4Mqgkf1CXztLVBSt2Ks3oZwbuwXPXLWyouBWLVWGNWQexSgSxsj_Qulcy4a-fN"
+        );
+    }
+
+    #[test]
+    fn only_known_push_services_are_accepted() {
+        assert!(is_allowed_push_endpoint("https://fcm.googleapis.com/fcm/send/abc"));
+        assert!(is_allowed_push_endpoint("https://updates.push.services.mozilla.com/wpush/v2/abc"));
+        assert!(is_allowed_push_endpoint("https://web.push.apple.com/QAbc"));
+        assert!(is_allowed_push_endpoint("https://wns2-by3p.notify.windows.com/w/?token=abc"));
+
+        assert!(!is_allowed_push_endpoint("http://fcm.googleapis.com/fcm/send/abc"));
+        assert!(!is_allowed_push_endpoint("https://fcm.googleapis.com:8443/fcm/send/abc"));
+        assert!(!is_allowed_push_endpoint("https://user@fcm.googleapis.com/fcm/send/abc"));
+        assert!(!is_allowed_push_endpoint("https://fcm.googleapis.com.evil.test/abc"));
+        assert!(!is_allowed_push_endpoint("https://evilfcm.googleapis.com/abc"));
+        assert!(!is_allowed_push_endpoint("https://127.0.0.1/abc"));
+        assert!(!is_allowed_push_endpoint("https://[::1]/abc"));
+        assert!(!is_allowed_push_endpoint("https://lms-service:3002/notifications"));
+    }
+}
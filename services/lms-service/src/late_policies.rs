@@ -0,0 +1,503 @@
+/// Políticas de entrega tardía y prórrogas individuales.
+///
+/// - La fecha límite efectiva es la prórroga del alumno o `lessons.due_date`.
+/// - La política de la lección prevalece sobre la de su categoría de calificación.
+/// - Dentro del periodo de gracia no hay penalización; después se descuenta
+///   `penalty_percent_per_day` por día iniciado, hasta `max_penalty_percent`.
+/// - Tras `hard_cutoff_days` (o siempre, si `accept_late` es falso) la entrega se rechaza.
+///
+/// En las lecciones calificadas la penalización se guarda aparte de la nota
+/// (`user_grades.late_penalty`) y el libro de calificaciones la aplica y la muestra.
+/// En las entregas con revisión entre pares se guarda en `course_submissions.late_penalty`
+/// y ya viene descontada en su `final_score`.
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Duration, Utc};
+use common::{auth::Claims, middleware::Org};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct LatePolicy {
+    pub id: Uuid,
+    pub course_id: Uuid,
+    pub lesson_id: Option<Uuid>,
+    pub grading_category_id: Option<Uuid>,
+    pub accept_late: bool,
+    pub penalty_percent_per_day: f32,
+    pub max_penalty_percent: f32,
+    pub grace_minutes: i32,
+    pub hard_cutoff_days: Option<i32>,
+    pub updated_at: DateTime<Utc>,
+}
+
+const POLICY_COLUMNS: &str = "id, course_id, lesson_id, grading_category_id, accept_late, penalty_percent_per_day, \
+     max_penalty_percent, grace_minutes, hard_cutoff_days, updated_at";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum LateOutcome {
+    OnTime,
+    Late { days_late: i64, penalty: f32 },
+    Rejected,
+}
+
+impl LateOutcome {
+    pub fn penalty(&self) -> f32 {
+        match self {
+            LateOutcome::Late { penalty, .. } => *penalty,
+            _ => 0.0,
+        }
+    }
+}
+
+impl LatePolicy {
+    pub fn evaluate(&self, due: DateTime<Utc>, submitted_at: DateTime<Utc>) -> LateOutcome {
+        let late_by = submitted_at - due;
+        if late_by <= Duration::minutes(self.grace_minutes as i64) {
+            return LateOutcome::OnTime;
+        }
+        let cutoff_passed = self
+            .hard_cutoff_days
+            .is_some_and(|days| late_by > Duration::days(days as i64));
+        if !self.accept_late || cutoff_passed {
+            return LateOutcome::Rejected;
+        }
+        // Cada día iniciado cuenta completo
+        let days_late = (late_by.num_seconds() + 86_399) / 86_400;
+        let percent = (days_late as f32 * self.penalty_percent_per_day).min(self.max_penalty_percent);
+        LateOutcome::Late { days_late, penalty: percent.clamp(0.0, 100.0) / 100.0 }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeadlineStatus {
+    pub lesson_id: Uuid,
+    pub due_date: Option<DateTime<Utc>>,
+    pub extended: bool,
+    pub policy: Option<LatePolicy>,
+    /// Resultado si se entregara ahora
+    pub outcome: LateOutcome,
+}
+
+/// Prórroga individual de la lección `l` para el usuario del parámetro `user_param`
+/// (p. ej. `"$2"`), o NULL. La fecha límite efectiva es
+/// `COALESCE(<prórroga>, l.due_date)`, aquí y en el feed de calendario.
+pub fn extension_due_date_sql(user_param: &str) -> String {
+    format!("(SELECT x.due_date FROM due_date_extensions x WHERE x.lesson_id = l.id AND x.user_id = {user_param})")
+}
+
+/// Fecha límite efectiva, política aplicable y resultado de una entrega en `at`.
+pub async fn deadline_status(
+    conn: &mut sqlx::PgConnection,
+    user_id: Uuid,
+    lesson_id: Uuid,
+    at: DateTime<Utc>,
+) -> Result<DeadlineStatus, sqlx::Error> {
+    #[derive(sqlx::FromRow)]
+    struct LessonDue {
+        due_date: Option<DateTime<Utc>>,
+        extension: Option<DateTime<Utc>>,
+    }
+    let lesson = sqlx::query_as::<_, LessonDue>(&format!(
+        "SELECT l.due_date, {} AS extension FROM lessons l WHERE l.id = $1",
+        extension_due_date_sql("$2")
+    ))
+    .bind(lesson_id)
+    .bind(user_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+    let (lesson_due, extension) = lesson.map(|l| (l.due_date, l.extension)).unwrap_or_default();
+
+    let policy = sqlx::query_as::<_, LatePolicy>(&format!(
+        r#"
+        SELECT {POLICY_COLUMNS} FROM late_policies p
+        WHERE p.lesson_id = $1
+           OR p.grading_category_id = (SELECT grading_category_id FROM lessons WHERE id = $1)
+        ORDER BY p.lesson_id NULLS LAST
+        LIMIT 1
+        "#
+    ))
+    .bind(lesson_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let due_date = extension.or(lesson_due);
+    let outcome = match (due_date, &policy) {
+        (Some(due), Some(policy)) => policy.evaluate(due, at),
+        // Sin política las entregas tardías se aceptan como hasta ahora
+        _ => LateOutcome::OnTime,
+    };
+
+    Ok(DeadlineStatus { lesson_id, due_date, extended: extension.is_some(), policy, outcome })
+}
+
+/// Penalización de una entrega hecha ahora; rechaza la entrega fuera de plazo.
+pub async fn enforce_late_policy(
+    conn: &mut sqlx::PgConnection,
+    user_id: Uuid,
+    lesson_id: Uuid,
+) -> Result<f32, (StatusCode, String)> {
+    let status = deadline_status(conn, user_id, lesson_id, Utc::now())
+        .await
+        .map_err(|e| {
+            tracing::error!("Error al evaluar la política de entrega tardía: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+        })?;
+    match status.outcome {
+        LateOutcome::Rejected => Err((
+            StatusCode::FORBIDDEN,
+            "La fecha límite de entrega ha vencido y no se aceptan entregas tardías".to_string(),
+        )),
+        outcome => Ok(outcome.penalty()),
+    }
+}
+
+// ─── Endpoints ───────────────────────────────────────────────────────────────
+
+/// GET /lessons/{id}/deadline - Fecha límite y penalización vigente para el alumno
+pub async fn get_my_deadline(
+    Org(_org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(lesson_id): Path<Uuid>,
+) -> Result<Json<DeadlineStatus>, (StatusCode, String)> {
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let status = deadline_status(&mut conn, claims.sub, lesson_id, Utc::now())
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    Ok(Json(status))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LatePolicyPayload {
+    #[serde(default = "default_accept_late")]
+    pub accept_late: bool,
+    #[serde(default)]
+    pub penalty_percent_per_day: f32,
+    pub max_penalty_percent: Option<f32>,
+    #[serde(default)]
+    pub grace_minutes: i32,
+    pub hard_cutoff_days: Option<i32>,
+}
+
+fn default_accept_late() -> bool {
+    true
+}
+
+impl LatePolicyPayload {
+    fn validate(&self) -> Result<(), (StatusCode, String)> {
+        let percent = 0.0..=100.0;
+        if !percent.contains(&self.penalty_percent_per_day)
+            || self.max_penalty_percent.is_some_and(|p| !percent.contains(&p))
+        {
+            return Err((StatusCode::BAD_REQUEST, "Los porcentajes deben estar entre 0 y 100".to_string()));
+        }
+        if self.grace_minutes < 0 || self.hard_cutoff_days.is_some_and(|d| d < 0) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "El periodo de gracia y el corte no pueden ser negativos".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Ámbito de la política: la lección o la categoría, con su curso.
+enum PolicyScope {
+    Lesson(Uuid),
+    Category(Uuid),
+}
+
+async fn scope_course(pool: &PgPool, org_id: Uuid, scope: &PolicyScope) -> Result<Uuid, (StatusCode, String)> {
+    let (query, id) = match scope {
+        PolicyScope::Lesson(id) => (
+            "SELECT m.course_id FROM lessons l JOIN modules m ON m.id = l.module_id WHERE l.id = $1 AND l.organization_id = $2",
+            id,
+        ),
+        PolicyScope::Category(id) => (
+            "SELECT course_id FROM grading_categories WHERE id = $1 AND organization_id = $2",
+            id,
+        ),
+    };
+    sqlx::query_scalar(query)
+        .bind(id)
+        .bind(org_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Lección o categoría no encontrada".to_string()))
+}
+
+async fn upsert_policy(
+    pool: &PgPool,
+    org_id: Uuid,
+    claims: &Claims,
+    scope: PolicyScope,
+    payload: LatePolicyPayload,
+) -> Result<Json<LatePolicy>, (StatusCode, String)> {
+    payload.validate()?;
+    let course_id = scope_course(pool, org_id, &scope).await?;
+    crate::handlers::ensure_course_staff(pool, org_id, claims, course_id).await?;
+
+    let (lesson_id, category_id, conflict) = match scope {
+        PolicyScope::Lesson(id) => (Some(id), None, "lesson_id"),
+        PolicyScope::Category(id) => (None, Some(id), "grading_category_id"),
+    };
+    let policy = sqlx::query_as::<_, LatePolicy>(&format!(
+        r#"
+        INSERT INTO late_policies (organization_id, course_id, lesson_id, grading_category_id, accept_late,
+            penalty_percent_per_day, max_penalty_percent, grace_minutes, hard_cutoff_days, updated_by)
+        VALUES ($1, $2, $3, $4, $5, $6, COALESCE($7, 100), $8, $9, $10)
+        ON CONFLICT ({conflict}) DO UPDATE SET
+            accept_late = EXCLUDED.accept_late,
+            penalty_percent_per_day = EXCLUDED.penalty_percent_per_day,
+            max_penalty_percent = EXCLUDED.max_penalty_percent,
+            grace_minutes = EXCLUDED.grace_minutes,
+            hard_cutoff_days = EXCLUDED.hard_cutoff_days,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = NOW()
+        RETURNING {POLICY_COLUMNS}
+        "#
+    ))
+    .bind(org_id)
+    .bind(course_id)
+    .bind(lesson_id)
+    .bind(category_id)
+    .bind(payload.accept_late)
+    .bind(payload.penalty_percent_per_day)
+    .bind(payload.max_penalty_percent)
+    .bind(payload.grace_minutes)
+    .bind(payload.hard_cutoff_days)
+    .bind(claims.sub)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error al guardar la política de entrega tardía: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+    })?;
+
+    Ok(Json(policy))
+}
+
+/// PUT /lessons/{id}/late-policy
+pub async fn upsert_lesson_late_policy(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(lesson_id): Path<Uuid>,
+    Json(payload): Json<LatePolicyPayload>,
+) -> Result<Json<LatePolicy>, (StatusCode, String)> {
+    upsert_policy(&pool, org_ctx.id, &claims, PolicyScope::Lesson(lesson_id), payload).await
+}
+
+/// PUT /grading-categories/{id}/late-policy
+pub async fn upsert_category_late_policy(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(category_id): Path<Uuid>,
+    Json(payload): Json<LatePolicyPayload>,
+) -> Result<Json<LatePolicy>, (StatusCode, String)> {
+    upsert_policy(&pool, org_ctx.id, &claims, PolicyScope::Category(category_id), payload).await
+}
+
+/// GET /courses/{id}/late-policies
+pub async fn list_late_policies(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+) -> Result<Json<Vec<LatePolicy>>, (StatusCode, String)> {
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+
+    let policies = sqlx::query_as::<_, LatePolicy>(&format!(
+        "SELECT {POLICY_COLUMNS} FROM late_policies WHERE course_id = $1 AND organization_id = $2 ORDER BY created_at"
+    ))
+    .bind(course_id)
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(policies))
+}
+
+/// DELETE /late-policies/{id}
+pub async fn delete_late_policy(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let course_id: Uuid =
+        sqlx::query_scalar("SELECT course_id FROM late_policies WHERE id = $1 AND organization_id = $2")
+            .bind(id)
+            .bind(org_ctx.id)
+            .fetch_optional(&pool)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+            .ok_or((StatusCode::NOT_FOUND, "Política no encontrada".to_string()))?;
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+
+    sqlx::query("DELETE FROM late_policies WHERE id = $1")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ─── Prórrogas ───────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct DueDateExtension {
+    pub id: Uuid,
+    pub course_id: Uuid,
+    pub lesson_id: Uuid,
+    pub user_id: Uuid,
+    pub due_date: DateTime<Utc>,
+    pub reason: Option<String>,
+    pub granted_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+const EXTENSION_COLUMNS: &str = "id, course_id, lesson_id, user_id, due_date, reason, granted_by, created_at, updated_at";
+
+#[derive(Debug, Deserialize)]
+pub struct DueDateExtensionPayload {
+    pub due_date: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+/// PUT /lessons/{id}/extensions/{user_id} - Concede o modifica la prórroga de un alumno
+pub async fn upsert_due_date_extension(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path((lesson_id, user_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<DueDateExtensionPayload>,
+) -> Result<Json<DueDateExtension>, (StatusCode, String)> {
+    let course_id = scope_course(&pool, org_ctx.id, &PolicyScope::Lesson(lesson_id)).await?;
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+
+    let extension = sqlx::query_as::<_, DueDateExtension>(&format!(
+        r#"
+        INSERT INTO due_date_extensions (organization_id, course_id, lesson_id, user_id, due_date, reason, granted_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (lesson_id, user_id) DO UPDATE SET
+            due_date = EXCLUDED.due_date,
+            reason = EXCLUDED.reason,
+            granted_by = EXCLUDED.granted_by,
+            updated_at = NOW()
+        RETURNING {EXTENSION_COLUMNS}
+        "#
+    ))
+    .bind(org_ctx.id)
+    .bind(course_id)
+    .bind(lesson_id)
+    .bind(user_id)
+    .bind(payload.due_date)
+    .bind(&payload.reason)
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error al guardar la prórroga: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+    })?;
+
+    Ok(Json(extension))
+}
+
+/// GET /courses/{id}/due-date-extensions
+pub async fn list_due_date_extensions(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+) -> Result<Json<Vec<DueDateExtension>>, (StatusCode, String)> {
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+
+    let extensions = sqlx::query_as::<_, DueDateExtension>(&format!(
+        "SELECT {EXTENSION_COLUMNS} FROM due_date_extensions WHERE course_id = $1 AND organization_id = $2 ORDER BY due_date"
+    ))
+    .bind(course_id)
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(extensions))
+}
+
+/// DELETE /due-date-extensions/{id}
+pub async fn delete_due_date_extension(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let course_id: Uuid =
+        sqlx::query_scalar("SELECT course_id FROM due_date_extensions WHERE id = $1 AND organization_id = $2")
+            .bind(id)
+            .bind(org_ctx.id)
+            .fetch_optional(&pool)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+            .ok_or((StatusCode::NOT_FOUND, "Prórroga no encontrada".to_string()))?;
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+
+    sqlx::query("DELETE FROM due_date_extensions WHERE id = $1")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn penalizes_per_started_day_after_grace_until_cutoff() {
+        let policy = LatePolicy {
+            id: Uuid::nil(),
+            course_id: Uuid::nil(),
+            lesson_id: None,
+            grading_category_id: None,
+            accept_late: true,
+            penalty_percent_per_day: 10.0,
+            max_penalty_percent: 25.0,
+            grace_minutes: 15,
+            hard_cutoff_days: Some(5),
+            updated_at: Utc::now(),
+        };
+        let due = Utc::now();
+
+        assert_eq!(policy.evaluate(due, due + Duration::minutes(10)), LateOutcome::OnTime);
+        assert_eq!(
+            policy.evaluate(due, due + Duration::hours(1)),
+            LateOutcome::Late { days_late: 1, penalty: 0.1 }
+        );
+        assert_eq!(
+            policy.evaluate(due, due + Duration::hours(49)),
+            LateOutcome::Late { days_late: 3, penalty: 0.25 }
+        );
+        assert_eq!(policy.evaluate(due, due + Duration::days(6)), LateOutcome::Rejected);
+
+        let strict = LatePolicy { accept_late: false, ..policy };
+        assert_eq!(strict.evaluate(due, due + Duration::hours(1)), LateOutcome::Rejected);
+    }
+}
@@ -0,0 +1,259 @@
+/// Almacén de eventos de aprendizaje y sus rollups.
+///
+/// `learning_events` recibe por triggers las interacciones, calificaciones y mensajes
+/// de foro; `fn_refresh_learning_rollups` vuelca los eventos nuevos a
+/// `la_daily_learner_activity`, `la_lesson_progress` y `la_video_heatmap`. Los
+/// handlers de analítica leen solo de los rollups, que avanzan hasta la transacción
+/// abierta más antigua.
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use chrono::NaiveDate;
+use common::{auth::Claims, middleware::Org};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Crea las particiones del mes actual y el siguiente y aplica los eventos pendientes.
+pub async fn refresh_rollups(pool: PgPool) {
+    if let Err(e) = sqlx::query("SELECT fn_ensure_learning_event_partitions(CURRENT_DATE, 2)")
+        .execute(&pool)
+        .await
+    {
+        tracing::error!("Error al crear las particiones de learning_events: {}", e);
+    }
+
+    loop {
+        match sqlx::query_scalar::<_, i32>("SELECT fn_refresh_learning_rollups()")
+            .fetch_one(&pool)
+            .await
+        {
+            Ok(0) => break,
+            Ok(processed) => tracing::debug!("Rollups de aprendizaje: {} eventos aplicados", processed),
+            Err(e) => {
+                tracing::error!("Error al actualizar los rollups de aprendizaje: {}", e);
+                break;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityQuery {
+    pub days: Option<i32>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct DailyActivity {
+    pub day: NaiveDate,
+    pub active_learners: i64,
+    pub events: i64,
+    pub posts: i64,
+    pub time_on_task_minutes: f64,
+}
+
+/// GET /courses/{id}/analytics/activity - Alumnos activos y tiempo de dedicación por día
+pub async fn get_course_activity(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<Json<Vec<DailyActivity>>, (StatusCode, String)> {
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+
+    let activity = sqlx::query_as::<_, DailyActivity>(
+        r#"
+        SELECT day,
+               COUNT(*)::bigint AS active_learners,
+               SUM(events)::bigint AS events,
+               SUM(posts)::bigint AS posts,
+               (SUM(time_on_task_seconds) / 60.0)::float8 AS time_on_task_minutes
+        FROM la_daily_learner_activity
+        WHERE course_id = $1 AND organization_id = $2 AND day > CURRENT_DATE - $3
+        GROUP BY day
+        ORDER BY day
+        "#,
+    )
+    .bind(course_id)
+    .bind(org_ctx.id)
+    .bind(query.days.unwrap_or(30).clamp(1, 365))
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(activity))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct FunnelStep {
+    pub lesson_id: Uuid,
+    pub lesson_title: String,
+    pub started: i64,
+    pub submitted: i64,
+    pub completed: i64,
+    pub avg_time_on_task_minutes: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompletionFunnel {
+    pub course_id: Uuid,
+    pub enrolled: i64,
+    pub lessons: Vec<FunnelStep>,
+}
+
+/// GET /courses/{id}/analytics/funnel - Embudo de finalización por lección
+pub async fn get_completion_funnel(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+) -> Result<Json<CompletionFunnel>, (StatusCode, String)> {
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+
+    let enrolled: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM enrollments WHERE course_id = $1 AND organization_id = $2")
+            .bind(course_id)
+            .bind(org_ctx.id)
+            .fetch_one(&pool)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let lessons = sqlx::query_as::<_, FunnelStep>(
+        r#"
+        SELECT l.id AS lesson_id,
+               l.title AS lesson_title,
+               COUNT(p.user_id)::bigint AS started,
+               COUNT(p.user_id) FILTER (WHERE p.attempts IS NOT NULL)::bigint AS submitted,
+               COUNT(p.user_id) FILTER (WHERE p.completed)::bigint AS completed,
+               COALESCE(AVG(p.time_on_task_seconds) / 60.0, 0)::float8 AS avg_time_on_task_minutes
+        FROM lessons l
+        JOIN modules m ON m.id = l.module_id
+        LEFT JOIN la_lesson_progress p ON p.lesson_id = l.id AND p.organization_id = l.organization_id
+        WHERE m.course_id = $1 AND l.organization_id = $2
+        GROUP BY l.id, l.title, m.position, l.position
+        ORDER BY m.position, l.position
+        "#,
+    )
+    .bind(course_id)
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(CompletionFunnel { course_id, enrolled, lessons }))
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    async fn insert_event(conn: &mut sqlx::PgConnection, course_id: Uuid, occurred_at: &str) {
+        sqlx::query(
+            r#"
+            INSERT INTO learning_events (organization_id, user_id, course_id, event_type, occurred_at)
+            VALUES ($1, $2, $3, 'lesson.view', $4::timestamptz)
+            "#,
+        )
+        .bind(common::tenancy::DEFAULT_ORG_ID)
+        .bind(Uuid::new_v4())
+        .bind(course_id)
+        .bind(occurred_at)
+        .execute(conn)
+        .await
+        .unwrap();
+    }
+
+    async fn rolled_up_events(pool: &sqlx::PgPool, course_id: Uuid) -> i64 {
+        sqlx::query_scalar("SELECT COALESCE(SUM(events), 0)::bigint FROM la_daily_learner_activity WHERE course_id = $1")
+            .bind(course_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn long_transactions_are_not_skipped_by_the_rollup() {
+        let Some(pool) = crate::db_util::test_pool(3).await else {
+            return;
+        };
+        sqlx::query("SELECT fn_ensure_learning_event_partitions(CURRENT_DATE, 1)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let course_id = Uuid::new_v4();
+        let now = chrono::Utc::now().to_rfc3339();
+
+        // La transacción larga toma su seq antes que el evento que se confirma primero
+        let mut long = pool.begin().await.unwrap();
+        insert_event(&mut long, course_id, &now).await;
+        let mut quick = pool.acquire().await.unwrap();
+        insert_event(&mut quick, course_id, &now).await;
+
+        sqlx::query("SELECT fn_refresh_learning_rollups()").execute(&pool).await.unwrap();
+        assert_eq!(rolled_up_events(&pool, course_id).await, 0);
+
+        long.commit().await.unwrap();
+        // Otras pruebas pueden retener el horizonte un momento
+        for _ in 0..50 {
+            sqlx::query("SELECT fn_refresh_learning_rollups()").execute(&pool).await.unwrap();
+            if rolled_up_events(&pool, course_id).await == 2 {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        panic!("los eventos de la transacción larga no llegaron a los rollups");
+    }
+
+    #[tokio::test]
+    async fn partitions_absorb_rows_already_in_default() {
+        let Some(pool) = crate::db_util::test_pool(1).await else {
+            return;
+        };
+        // Un mes lejano y distinto en cada ejecución, sin partición propia
+        let year = 2200 + (Uuid::new_v4().as_u128() % 700) as i32;
+        let month = format!("{year}-03-01");
+        let partition = format!("learning_events_{year}03");
+        let course_id = Uuid::new_v4();
+
+        let mut conn = pool.acquire().await.unwrap();
+        insert_event(&mut conn, course_id, &format!("{year}-03-15T12:00:00Z")).await;
+        let in_default: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM learning_events_default WHERE course_id = $1")
+                .bind(course_id)
+                .fetch_one(&mut *conn)
+                .await
+                .unwrap();
+        assert_eq!(in_default, 1);
+
+        sqlx::query("SELECT fn_ensure_learning_event_partitions($1::date, 1)")
+            .bind(&month)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        let moved: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {partition} WHERE course_id = $1"))
+            .bind(course_id)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        let left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM learning_events_default WHERE course_id = $1")
+            .bind(course_id)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!((moved, left), (1, 0));
+
+        // Fuera de la reparticion, DEFAULT sigue siendo de solo inserción
+        insert_event(&mut conn, course_id, "2199-01-01T00:00:00Z").await;
+        let deleted = sqlx::query("DELETE FROM learning_events_default WHERE course_id = $1")
+            .bind(course_id)
+            .execute(&mut *conn)
+            .await;
+        assert!(deleted.is_err());
+
+        sqlx::query(&format!("DROP TABLE {partition}")).execute(&mut *conn).await.unwrap();
+    }
+}
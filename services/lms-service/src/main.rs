@@ -1,10 +1,21 @@
+mod adaptive_paths;
+mod adaptive_testing;
 mod db_util;
+mod dsar;
+mod event_bus;
+mod exam_attempts;
+mod gradebook;
+mod grading;
 mod handlers;
 mod handlers_announcements;
+mod handlers_calendar;
+mod handlers_canvas_ops;
 mod handlers_pedagogical;
 mod handlers_lti_consumer;
 mod handlers_study_rooms;
 mod handlers_email;
+mod handlers_notification_preferences;
+mod handlers_web_push;
 mod handlers_scorm;
 mod handlers_search;
 mod handlers_cohorts;
@@ -20,22 +31,32 @@ mod handlers_certificates;
 mod progress_tracking;
 mod lti;
 mod jwks;
+mod late_policies;
+mod learning_events;
 mod predictive;
+mod psychometrics;
+mod quiz_variants;
+mod spaced_repetition;
 mod live;
 mod portfolio;
 mod external_db;
 mod openapi;
+mod outcome_mastery;
+mod code_runner;
+mod pronunciation;
+mod plugin_api;
+mod plugin_host;
 mod moderation;
 
 use axum::{
     Router, middleware,
+    extract::DefaultBodyLimit,
     routing::{delete, get, post, put},
     response::Html,
     http::{Method, header},
 };
 use common::health::{self, HealthState};
 use dotenvy::dotenv;
-use sqlx::postgres::PgPoolOptions;
 use std::env;
 use std::net::SocketAddr;
 use std::time::Duration;
@@ -57,8 +78,14 @@ async fn main() {
 
     tracing_subscriber::fmt::init();
 
+    // Ejecutor aislado de code-lab: no usa la base de datos ni el resto del LMS
+    if std::env::args().nth(1).as_deref() == Some("code-runner") {
+        code_runner::serve().await;
+        return;
+    }
+
     let db_url = env::var("DATABASE_URL").expect("DATABASE_URL debe estar configurada");
-    let pool = PgPoolOptions::new()
+    let pool = common::tenancy::pool_options()
         .max_connections(10)
         .min_connections(2)
         .acquire_timeout(Duration::from_secs(30))
@@ -86,13 +113,85 @@ async fn main() {
         }
     });
 
+    // Compactación periódica del log de operaciones de la pizarra colaborativa
+    let pool_clone = pool.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(600)).await;
+            handlers_canvas_ops::compact_canvas_logs(pool_clone.clone()).await;
+        }
+    });
+
+    // Envío de notificaciones por email (inmediatas cada minuto, resúmenes cada hora)
+    let pool_clone = pool.clone();
+    tokio::spawn(async move {
+        loop {
+            handlers_notification_preferences::deliver_immediate_notification_emails(pool_clone.clone()).await;
+            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+        }
+    });
+
+    let pool_clone = pool.clone();
+    tokio::spawn(async move {
+        loop {
+            handlers_notification_preferences::send_notification_digests(pool_clone.clone()).await;
+            tokio::time::sleep(tokio::time::Duration::from_secs(3600)).await;
+        }
+    });
+
+    // Entrega de notificaciones Web Push a los dispositivos registrados
+    let pool_clone = pool.clone();
+    tokio::spawn(async move {
+        loop {
+            handlers_web_push::deliver_push_notifications(pool_clone.clone()).await;
+            tokio::time::sleep(tokio::time::Duration::from_secs(15)).await;
+        }
+    });
+
+    // Entrega automática de los intentos de examen vencidos
+    let pool_clone = pool.clone();
+    tokio::spawn(async move {
+        loop {
+            exam_attempts::expire_overdue_attempts(pool_clone.clone()).await;
+            tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
+        }
+    });
+
+    // Particiones mensuales y rollups incrementales de los eventos de aprendizaje
+    let pool_clone = pool.clone();
+    tokio::spawn(async move {
+        loop {
+            learning_events::refresh_rollups(pool_clone.clone()).await;
+            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+        }
+    });
+
+    // Cola de solicitudes de titulares de datos (exportación y supresión)
+    let pool_clone = pool.clone();
+    tokio::spawn(async move {
+        loop {
+            dsar::process_dsar_queue(pool_clone.clone()).await;
+            tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
+        }
+    });
+
+    // Bus de eventos en tiempo real (LISTEN/NOTIFY) para los streams SSE
+    let event_bus = event_bus::EventBus::default();
+    event_bus::spawn_listener(db_url.clone(), event_bus.clone());
+
     // Configuración de CORS - Permitir múltiples orígenes para desarrollo y producción
     // Usando un cierre de predicado para soportar subdominios comodín para norteamericano.cl
     use tower_http::cors::AllowOrigin;
     
     let cors = CorsLayer::new()
-        .allow_origin(AllowOrigin::predicate(|origin: &http::HeaderValue, _request: &http::request::Parts| -> bool {
+        .allow_origin(AllowOrigin::predicate(|origin: &http::HeaderValue, request: &http::request::Parts| -> bool {
             let origin_str = origin.to_str().unwrap_or("");
+
+            // Los plugins se sirven desde su propio origen y se autentican con un token
+            // de plugin en la cabecera (nunca con cookies)
+            if request.uri.path().starts_with("/plugin-api/") {
+                return true;
+            }
             
             // Allowlist explícita de orígenes permitidos
             let allowed_origins = [
@@ -117,6 +216,7 @@ async fn main() {
             header::AUTHORIZATION,
             header::HeaderName::from_static("x-requested-with"),
             header::HeaderName::from_static("x-organization-id"),
+            header::HeaderName::from_static("x-exam-session"),
         ])
         .expose_headers([header::CONTENT_LENGTH, header::CONTENT_TYPE]);
 
@@ -165,6 +265,22 @@ async fn main() {
             "/lessons/{id}/collaborative-canvas/stream",
             get(handlers::stream_lesson_collaborative_canvas),
         )
+        .route(
+            "/lessons/{id}/collaborative-canvas/ops",
+            get(handlers_canvas_ops::list_canvas_ops).post(handlers_canvas_ops::submit_canvas_op),
+        )
+        .route(
+            "/lessons/{id}/collaborative-canvas/ops/stream",
+            get(handlers_canvas_ops::stream_canvas_ops),
+        )
+        .route(
+            "/lessons/{id}/collaborative-canvas/undo",
+            post(handlers_canvas_ops::undo_canvas_op),
+        )
+        .route(
+            "/lessons/{id}/collaborative-canvas/replay",
+            get(handlers_canvas_ops::replay_canvas),
+        )
         .route(
             "/lessons/{id}/collaborative-doc",
             get(handlers::get_lesson_collaborative_doc)
@@ -215,20 +331,174 @@ async fn main() {
             "/courses/{id}/export-grades",
             get(handlers::export_course_grades),
         )
+        .route(
+            "/courses/{id}/gradebook",
+            get(gradebook::get_course_gradebook),
+        )
+        .route(
+            "/courses/{id}/gradebook/students/{user_id}",
+            get(gradebook::get_student_gradebook),
+        )
+        .route(
+            "/courses/{id}/gradebook/export",
+            get(gradebook::export_course_gradebook),
+        )
+        .route(
+            "/courses/{id}/gradebook/overrides",
+            get(gradebook::list_grade_overrides).post(gradebook::create_grade_override),
+        )
+        .route(
+            "/courses/{id}/adaptive-path",
+            get(adaptive_paths::get_my_adaptive_path),
+        )
+        .route(
+            "/courses/{id}/adaptive-path/students/{user_id}",
+            get(adaptive_paths::get_student_adaptive_path),
+        )
+        .route(
+            "/courses/{id}/outcomes",
+            get(outcome_mastery::get_course_outcomes),
+        )
+        .route(
+            "/courses/{id}/outcomes/mastery",
+            get(outcome_mastery::get_course_mastery),
+        )
+        .route(
+            "/courses/{id}/outcomes/mastery/me",
+            get(outcome_mastery::get_my_mastery),
+        )
+        .route(
+            "/courses/{id}/outcomes/mastery/students/{user_id}",
+            get(outcome_mastery::get_student_mastery),
+        )
+        .route("/reviews/due", get(spaced_repetition::get_due_reviews))
+        .route("/reviews/stats", get(spaced_repetition::get_review_stats))
+        .route(
+            "/reviews/{id}/answer",
+            post(spaced_repetition::answer_review),
+        )
+        .route(
+            "/courses/{id}/grading-scheme",
+            get(gradebook::get_grading_scheme).put(gradebook::update_grading_scheme),
+        )
         .route(
             "/courses/{id}/analytics/advanced",
             get(handlers::get_advanced_analytics),
         )
+        .route(
+            "/courses/{id}/analytics/activity",
+            get(learning_events::get_course_activity),
+        )
+        .route(
+            "/courses/{id}/analytics/funnel",
+            get(learning_events::get_completion_funnel),
+        )
         .route(
             "/courses/{id}/recommendations",
             get(handlers::get_recommendations),
         )
+        .route(
+            "/lessons/{id}/psychometrics",
+            get(psychometrics::get_lesson_psychometrics),
+        )
+        .route(
+            "/psychometrics/calibration",
+            get(psychometrics::get_question_bank_calibration),
+        )
+        .route(
+            "/lessons/{id}/adaptive-attempts",
+            post(adaptive_testing::start_adaptive_attempt)
+                .get(adaptive_testing::list_lesson_adaptive_attempts),
+        )
+        .route(
+            "/adaptive-attempts/{id}",
+            get(adaptive_testing::get_adaptive_attempt),
+        )
+        .route(
+            "/adaptive-attempts/{id}/responses",
+            post(adaptive_testing::answer_adaptive_item),
+        )
+        .route(
+            "/lessons/{id}/exam-attempts",
+            post(exam_attempts::start_exam_attempt).get(exam_attempts::list_exam_attempts),
+        )
+        .route(
+            "/exam-attempts/{id}",
+            get(exam_attempts::get_exam_attempt),
+        )
+        .route(
+            "/exam-attempts/{id}/answers",
+            put(exam_attempts::save_exam_answers),
+        )
+        .route(
+            "/exam-attempts/{id}/reopen",
+            post(exam_attempts::reopen_exam_attempt),
+        )
+        .route(
+            "/courses/{id}/exam-accommodations",
+            get(exam_attempts::list_exam_accommodations),
+        )
+        .route(
+            "/courses/{id}/exam-accommodations/{user_id}",
+            put(exam_attempts::upsert_exam_accommodation),
+        )
+        .route(
+            "/exam-accommodations/{id}",
+            delete(exam_attempts::delete_exam_accommodation),
+        )
+        .route(
+            "/lessons/{id}/deadline",
+            get(late_policies::get_my_deadline),
+        )
+        .route(
+            "/lessons/{id}/late-policy",
+            put(late_policies::upsert_lesson_late_policy),
+        )
+        .route(
+            "/grading-categories/{id}/late-policy",
+            put(late_policies::upsert_category_late_policy),
+        )
+        .route(
+            "/courses/{id}/late-policies",
+            get(late_policies::list_late_policies),
+        )
+        .route(
+            "/late-policies/{id}",
+            delete(late_policies::delete_late_policy),
+        )
+        .route(
+            "/lessons/{id}/extensions/{user_id}",
+            put(late_policies::upsert_due_date_extension),
+        )
+        .route(
+            "/courses/{id}/due-date-extensions",
+            get(late_policies::list_due_date_extensions),
+        )
+        .route(
+            "/due-date-extensions/{id}",
+            delete(late_policies::delete_due_date_extension),
+        )
+        .route(
+            "/lessons/{id}/quiz-variant",
+            get(quiz_variants::get_quiz_variant),
+        )
+        .route(
+            "/lessons/{id}/quiz-variant/submit",
+            post(quiz_variants::submit_quiz_variant),
+        )
         .route(
             "/courses/{id}/dropout-risks",
             get(predictive::get_course_dropout_risks),
         )
         // Aprendizaje en Vivo (Live Learning)
         .route("/courses/{id}/meetings", get(live::get_course_meetings).post(live::create_meeting))
+        .route("/calendar/feed", get(handlers_calendar::get_my_calendar_feed))
+        .route("/calendar/feed/rotate", post(handlers_calendar::rotate_my_calendar_feed))
+        .route("/courses/{id}/calendar/feed", get(handlers_calendar::get_course_calendar_feed))
+        .route(
+            "/courses/{id}/calendar/feed/rotate",
+            post(handlers_calendar::rotate_course_calendar_feed),
+        )
         .route("/courses/{id}/meetings/{meeting_id}", delete(live::delete_meeting))
         // LTI 1.3 Tool Consumer (Fase 36)
         .route(
@@ -286,6 +556,19 @@ async fn main() {
             post(handlers::record_interaction),
         )
         .route("/lessons/{id}/heatmap", get(handlers::get_lesson_heatmap))
+        // Plugins: host WebAssembly y tokens de plugin
+        .route("/plugins/{id}/token", post(plugin_api::mint_plugin_token))
+        .route("/plugins/{id}/invocations", get(plugin_host::list_plugin_invocations))
+        .route("/plugins/grade", post(plugin_host::grade_plugin_block))
+        // Solicitudes de titulares de datos (GDPR/LGPD)
+        .route(
+            "/dsar/requests",
+            get(dsar::list_dsar_requests).post(dsar::create_dsar_request),
+        )
+        .route("/dsar/requests/{id}", get(dsar::get_dsar_request))
+        .route("/dsar/requests/{id}/decision", post(dsar::decide_dsar_request))
+        .route("/dsar/requests/{id}/retry", post(dsar::retry_dsar_request))
+        .route("/dsar/requests/{id}/download", get(dsar::download_dsar_export))
         .route("/audio/evaluate", post(handlers::evaluate_audio_response))
         .route("/audio/evaluate-file", post(handlers::evaluate_audio_file))
         // Rutas de Profesor para Respuesta de Audio
@@ -297,9 +580,27 @@ async fn main() {
         .route("/lessons/{id}/chat", post(handlers::chat_with_tutor))
         .route("/lessons/{id}/chat-role-play", post(handlers::chat_role_play))
         .route("/lessons/{id}/code-hint", post(handlers::get_code_hint))
+        .route("/lessons/{id}/code-lab/{block_id}/run", post(code_runner::run_code_lab))
+        .route(
+            "/lessons/{id}/code-lab/{block_id}/runs/{run_id}/submit",
+            post(code_runner::submit_code_lab_run),
+        )
         .route("/lessons/{id}/feedback", get(handlers::get_lesson_feedback))
         .route("/notifications", get(handlers::get_notifications))
         .route("/notifications/stream", get(handlers::stream_notifications))
+        .route(
+            "/notifications/preferences",
+            get(handlers_notification_preferences::get_notification_preferences)
+                .put(handlers_notification_preferences::update_notification_preferences),
+        )
+        .route("/push/vapid-public-key", get(handlers_web_push::get_vapid_public_key))
+        .route(
+            "/push/subscriptions",
+            get(handlers_web_push::list_push_subscriptions)
+                .post(handlers_web_push::register_push_subscription)
+                .delete(handlers_web_push::remove_push_subscription),
+        )
+        .route("/realtime/stats", get(event_bus::get_event_bus_stats))
         .route(
             "/notifications/{id}/read",
             post(handlers::mark_notification_as_read),
@@ -513,6 +814,12 @@ async fn main() {
         // Rutas de comprobación de salud (Health check)
         .merge(health::health_routes(pool.clone()).with_state(health_state))
         .route("/catalog", get(handlers::get_course_catalog))
+        .route("/calendar/feeds/{token}", get(handlers_calendar::serve_calendar_feed))
+        .route(
+            "/notifications/unsubscribe",
+            get(handlers_notification_preferences::confirm_unsubscribe)
+                .post(handlers_notification_preferences::unsubscribe_notifications),
+        )
         .route("/ingest", post(handlers::ingest_course))
         .merge(
             Router::new()
@@ -541,6 +848,24 @@ async fn main() {
         )
         .route("/lti/jwks", get(jwks::lti_jwks_handler))
         .route("/lti/deep-linking/response", post(lti::lti_deep_linking_response))
+        // API para plugins de interfaz, autenticada con tokens de plugin
+        .merge(
+            Router::new()
+                .route("/plugin-api/context", get(plugin_api::get_plugin_context))
+                .route("/plugin-api/score", post(plugin_api::submit_plugin_score))
+                .route_layer(middleware::from_fn(plugin_api::plugin_token_middleware)),
+        )
+        // Réplica de plugins desde el CMS, autenticada con tokens entre servicios
+        .merge(
+            Router::new()
+                .route(
+                    "/plugins/{id}/sync",
+                    put(plugin_host::sync_plugin)
+                        .layer(DefaultBodyLimit::max(16 * 1024 * 1024))
+                        .delete(plugin_host::delete_plugin),
+                )
+                .route_layer(middleware::from_fn(plugin_host::sync_token_middleware)),
+        )
         .merge(protected_routes)
         // Encabezados de seguridad (Security headers)
         .layer(SetResponseHeaderLayer::overriding(
@@ -565,7 +890,8 @@ async fn main() {
         ))
         .layer(cors)
         .with_state(pool)
-        .layer(axum::Extension(mysql_pool));
+        .layer(axum::Extension(mysql_pool))
+        .layer(axum::Extension(event_bus));
 
     let addr = SocketAddr::from(([0, 0, 0, 0], 3002));
     tracing::info!("LMS Service escuchando en {} con limitación de tasa y encabezados de seguridad", addr);
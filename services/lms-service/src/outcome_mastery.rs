@@ -0,0 +1,501 @@
+/// Dominio de resultados de aprendizaje a partir de la evidencia calificada.
+///
+/// El CMS publica los resultados del curso y su alineación (`course_outcomes`,
+/// `course_outcome_alignments`). La evidencia de cada alineación es:
+/// - lección: nota de `user_grades` con la penalización por retraso aplicada;
+/// - bloque de quiz: `user_grades.metadata.block_scores[block_id]`;
+/// - pregunta del banco: promedio de `quiz_item_responses` de esa pregunta en el curso;
+/// - criterio de rúbrica: nota final de la entrega de la lección que usa la rúbrica
+///   (el LMS no guarda puntajes por criterio).
+///
+/// El dominio de un resultado es el promedio ponderado de su evidencia y la de sus
+/// descendientes, de modo que un descriptor general resume a sus "can-do".
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use common::models::PublishedOutcomes;
+use common::{auth::Claims, middleware::Org};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Promedio a partir del cual un resultado se considera dominado
+const MASTERY_THRESHOLD: f32 = 0.8;
+const DEVELOPING_THRESHOLD: f32 = 0.5;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct CourseOutcome {
+    pub outcome_id: Uuid,
+    pub framework_id: Uuid,
+    pub framework_name: String,
+    pub parent_id: Option<Uuid>,
+    pub code: String,
+    pub description: String,
+    pub level: Option<String>,
+    pub position: i32,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct CourseOutcomeAlignment {
+    pub outcome_id: Uuid,
+    pub lesson_id: Option<Uuid>,
+    pub block_id: Option<String>,
+    pub question_bank_id: Option<Uuid>,
+    pub rubric_criterion_id: Option<Uuid>,
+    pub weight: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Evidence {
+    pub outcome_id: Uuid,
+    pub score: f32,
+    pub weight: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutcomeMastery {
+    pub outcome_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub framework_name: String,
+    pub code: String,
+    pub description: String,
+    pub level: Option<String>,
+    /// 0.0 a 1.0; `None` sin evidencia
+    pub score: Option<f32>,
+    pub evidence_count: usize,
+    /// not_assessed | beginning | developing | mastered
+    pub status: &'static str,
+}
+
+fn mastery_status(score: Option<f32>) -> &'static str {
+    match score {
+        None => "not_assessed",
+        Some(s) if s >= MASTERY_THRESHOLD => "mastered",
+        Some(s) if s >= DEVELOPING_THRESHOLD => "developing",
+        Some(_) => "beginning",
+    }
+}
+
+pub fn compute_mastery(outcomes: &[CourseOutcome], evidence: &[Evidence]) -> Vec<OutcomeMastery> {
+    let parents: HashMap<Uuid, Option<Uuid>> = outcomes.iter().map(|o| (o.outcome_id, o.parent_id)).collect();
+    // (suma ponderada, suma de pesos, cantidad) por resultado, incluyendo ancestros
+    let mut totals: HashMap<Uuid, (f32, f32, usize)> = HashMap::new();
+    for item in evidence {
+        let mut current = Some(item.outcome_id);
+        let mut depth = 0;
+        while let Some(id) = current.filter(|_| depth <= outcomes.len()) {
+            let entry = totals.entry(id).or_default();
+            entry.0 += item.score * item.weight;
+            entry.1 += item.weight;
+            entry.2 += 1;
+            current = parents.get(&id).copied().flatten();
+            depth += 1;
+        }
+    }
+
+    outcomes
+        .iter()
+        .map(|outcome| {
+            let (weighted, weights, count) = totals.get(&outcome.outcome_id).copied().unwrap_or_default();
+            let score = (weights > 0.0).then(|| weighted / weights);
+            OutcomeMastery {
+                outcome_id: outcome.outcome_id,
+                parent_id: outcome.parent_id,
+                framework_name: outcome.framework_name.clone(),
+                code: outcome.code.clone(),
+                description: outcome.description.clone(),
+                level: outcome.level.clone(),
+                score,
+                evidence_count: count,
+                status: mastery_status(score),
+            }
+        })
+        .collect()
+}
+
+// ─── Ingesta ─────────────────────────────────────────────────────────────────
+
+/// Reemplaza la instantánea de resultados del curso publicada por el CMS.
+pub async fn replace_course_outcomes(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    org_id: Uuid,
+    course_id: Uuid,
+    published: &PublishedOutcomes,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM course_outcome_alignments WHERE course_id = $1")
+        .bind(course_id)
+        .execute(&mut **tx)
+        .await?;
+    sqlx::query("DELETE FROM course_outcomes WHERE course_id = $1")
+        .bind(course_id)
+        .execute(&mut **tx)
+        .await?;
+
+    for outcome in &published.outcomes {
+        sqlx::query(
+            r#"
+            INSERT INTO course_outcomes
+                (course_id, outcome_id, organization_id, framework_id, framework_name, parent_id, code, description, level, position)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "#,
+        )
+        .bind(course_id)
+        .bind(outcome.id)
+        .bind(org_id)
+        .bind(outcome.framework_id)
+        .bind(&outcome.framework_name)
+        .bind(outcome.parent_id)
+        .bind(&outcome.code)
+        .bind(&outcome.description)
+        .bind(&outcome.level)
+        .bind(outcome.position)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    for alignment in &published.alignments {
+        sqlx::query(
+            r#"
+            INSERT INTO course_outcome_alignments
+                (course_id, outcome_id, lesson_id, block_id, question_bank_id, rubric_id, rubric_criterion_id, weight)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(course_id)
+        .bind(alignment.outcome_id)
+        .bind(alignment.lesson_id)
+        .bind(&alignment.block_id)
+        .bind(alignment.question_bank_id)
+        .bind(alignment.rubric_id)
+        .bind(alignment.rubric_criterion_id)
+        .bind(alignment.weight)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+// ─── Evidencia ───────────────────────────────────────────────────────────────
+
+#[derive(sqlx::FromRow)]
+struct GradeEvidence {
+    user_id: Uuid,
+    lesson_id: Uuid,
+    score: f32,
+    late_penalty: f32,
+    block_scores: Option<Value>,
+}
+
+#[derive(sqlx::FromRow)]
+struct SubmissionEvidence {
+    user_id: Uuid,
+    lesson_id: Uuid,
+    final_score: f64,
+}
+
+#[derive(sqlx::FromRow)]
+struct ItemEvidence {
+    user_id: Uuid,
+    question_bank_id: Uuid,
+    score: f32,
+}
+
+struct CourseMasteryData {
+    outcomes: Vec<CourseOutcome>,
+    alignments: Vec<CourseOutcomeAlignment>,
+    grades: HashMap<(Uuid, Uuid), GradeEvidence>,
+    submissions: HashMap<(Uuid, Uuid), f32>,
+    items: HashMap<(Uuid, Uuid), f32>,
+}
+
+impl CourseMasteryData {
+    fn evidence_for(&self, user_id: Uuid) -> Vec<Evidence> {
+        self.alignments
+            .iter()
+            .filter_map(|alignment| {
+                let grade = alignment.lesson_id.and_then(|lesson| self.grades.get(&(user_id, lesson)));
+                let submission = alignment.lesson_id.and_then(|lesson| self.submissions.get(&(user_id, lesson)).copied());
+                let lesson_score = grade.map(|g| g.score * (1.0 - g.late_penalty.clamp(0.0, 1.0)));
+
+                let score = if let Some(question) = alignment.question_bank_id {
+                    self.items.get(&(user_id, question)).copied()
+                } else if let Some(block) = &alignment.block_id {
+                    grade
+                        .and_then(|g| g.block_scores.as_ref())
+                        .and_then(|scores| scores.get(block))
+                        .and_then(|s| s.as_f64())
+                        .map(|s| s as f32)
+                } else if alignment.rubric_criterion_id.is_some() {
+                    submission.or(lesson_score)
+                } else {
+                    lesson_score.or(submission)
+                }?;
+
+                Some(Evidence {
+                    outcome_id: alignment.outcome_id,
+                    score: score.clamp(0.0, 1.0),
+                    weight: alignment.weight,
+                })
+            })
+            .collect()
+    }
+}
+
+async fn load_outcomes(
+    pool: &PgPool,
+    course_id: Uuid,
+) -> Result<(Vec<CourseOutcome>, Vec<CourseOutcomeAlignment>), sqlx::Error> {
+    let outcomes = sqlx::query_as::<_, CourseOutcome>(
+        r#"
+        SELECT outcome_id, framework_id, framework_name, parent_id, code, description, level, position
+        FROM course_outcomes WHERE course_id = $1
+        ORDER BY framework_name, position, code
+        "#,
+    )
+    .bind(course_id)
+    .fetch_all(pool)
+    .await?;
+
+    let alignments = sqlx::query_as::<_, CourseOutcomeAlignment>(
+        r#"
+        SELECT outcome_id, lesson_id, block_id, question_bank_id, rubric_criterion_id, weight
+        FROM course_outcome_alignments WHERE course_id = $1
+        "#,
+    )
+    .bind(course_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok((outcomes, alignments))
+}
+
+async fn load_mastery_data(
+    pool: &PgPool,
+    course_id: Uuid,
+    user_id: Option<Uuid>,
+) -> Result<CourseMasteryData, sqlx::Error> {
+    let (outcomes, alignments) = load_outcomes(pool, course_id).await?;
+
+    let grades = sqlx::query_as::<_, GradeEvidence>(
+        r#"
+        SELECT user_id, lesson_id, score, late_penalty, metadata -> 'block_scores' AS block_scores
+        FROM user_grades
+        WHERE course_id = $1 AND ($2::uuid IS NULL OR user_id = $2)
+        "#,
+    )
+    .bind(course_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|g| ((g.user_id, g.lesson_id), g))
+    .collect();
+
+    // Las notas de evaluación por pares están en escala 0-100
+    let submissions = sqlx::query_as::<_, SubmissionEvidence>(
+        r#"
+        SELECT user_id, lesson_id, final_score
+        FROM course_submissions
+        WHERE course_id = $1 AND final_score IS NOT NULL AND ($2::uuid IS NULL OR user_id = $2)
+        "#,
+    )
+    .bind(course_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|s| ((s.user_id, s.lesson_id), (s.final_score / 100.0) as f32))
+    .collect();
+
+    let items = sqlx::query_as::<_, ItemEvidence>(
+        r#"
+        SELECT user_id, question_bank_id, AVG(score)::float4 AS score
+        FROM quiz_item_responses
+        WHERE course_id = $1 AND question_bank_id IS NOT NULL AND ($2::uuid IS NULL OR user_id = $2)
+        GROUP BY user_id, question_bank_id
+        "#,
+    )
+    .bind(course_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|i| ((i.user_id, i.question_bank_id), i.score))
+    .collect();
+
+    Ok(CourseMasteryData { outcomes, alignments, grades, submissions, items })
+}
+
+// ─── Endpoints ───────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize)]
+pub struct CourseOutcomesView {
+    pub outcomes: Vec<CourseOutcome>,
+    pub alignments: Vec<CourseOutcomeAlignment>,
+}
+
+/// GET /courses/{id}/outcomes - Resultados publicados y su alineación
+pub async fn get_course_outcomes(
+    Org(_org_ctx): Org,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+) -> Result<Json<CourseOutcomesView>, (StatusCode, String)> {
+    let (outcomes, alignments) = load_outcomes(&pool, course_id)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    Ok(Json(CourseOutcomesView { outcomes, alignments }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct StudentMasteryReport {
+    pub user_id: Uuid,
+    pub course_id: Uuid,
+    pub mastered: usize,
+    pub assessed: usize,
+    pub outcomes: Vec<OutcomeMastery>,
+}
+
+fn student_report(data: &CourseMasteryData, course_id: Uuid, user_id: Uuid) -> StudentMasteryReport {
+    let outcomes = compute_mastery(&data.outcomes, &data.evidence_for(user_id));
+    StudentMasteryReport {
+        user_id,
+        course_id,
+        mastered: outcomes.iter().filter(|o| o.status == "mastered").count(),
+        assessed: outcomes.iter().filter(|o| o.score.is_some()).count(),
+        outcomes,
+    }
+}
+
+/// GET /courses/{id}/outcomes/mastery/students/{user_id} - Reporte de un alumno
+/// (el propio alumno o el personal del curso)
+pub async fn get_student_mastery(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path((course_id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<StudentMasteryReport>, (StatusCode, String)> {
+    if user_id != claims.sub {
+        crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+    }
+    let data = load_mastery_data(&pool, course_id, Some(user_id))
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    Ok(Json(student_report(&data, course_id, user_id)))
+}
+
+/// GET /courses/{id}/outcomes/mastery/me
+pub async fn get_my_mastery(
+    org: Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+) -> Result<Json<StudentMasteryReport>, (StatusCode, String)> {
+    let user_id = claims.sub;
+    get_student_mastery(org, claims, State(pool), Path((course_id, user_id))).await
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutcomeClassSummary {
+    pub outcome_id: Uuid,
+    pub code: String,
+    pub description: String,
+    pub average_score: Option<f32>,
+    pub assessed_students: usize,
+    pub mastered_students: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CourseMasteryReport {
+    pub course_id: Uuid,
+    pub summary: Vec<OutcomeClassSummary>,
+    pub students: Vec<StudentMasteryReport>,
+}
+
+/// GET /courses/{id}/outcomes/mastery - Reporte del curso para el instructor
+pub async fn get_course_mastery(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(course_id): Path<Uuid>,
+) -> Result<Json<CourseMasteryReport>, (StatusCode, String)> {
+    crate::handlers::ensure_course_staff(&pool, org_ctx.id, &claims, course_id).await?;
+
+    let data = load_mastery_data(&pool, course_id, None)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let student_ids: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT user_id FROM enrollments WHERE course_id = $1 AND organization_id = $2 ORDER BY enrolled_at",
+    )
+    .bind(course_id)
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let students: Vec<StudentMasteryReport> = student_ids
+        .into_iter()
+        .map(|user_id| student_report(&data, course_id, user_id))
+        .collect();
+
+    let summary = data
+        .outcomes
+        .iter()
+        .enumerate()
+        .map(|(index, outcome)| {
+            let scores: Vec<f32> = students.iter().filter_map(|s| s.outcomes[index].score).collect();
+            OutcomeClassSummary {
+                outcome_id: outcome.outcome_id,
+                code: outcome.code.clone(),
+                description: outcome.description.clone(),
+                average_score: (!scores.is_empty()).then(|| scores.iter().sum::<f32>() / scores.len() as f32),
+                assessed_students: scores.len(),
+                mastered_students: students.iter().filter(|s| s.outcomes[index].status == "mastered").count(),
+            }
+        })
+        .collect();
+
+    Ok(Json(CourseMasteryReport { course_id, summary, students }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(id: u128, parent: Option<u128>) -> CourseOutcome {
+        CourseOutcome {
+            outcome_id: Uuid::from_u128(id),
+            framework_id: Uuid::nil(),
+            framework_name: "CEFR".into(),
+            parent_id: parent.map(Uuid::from_u128),
+            code: format!("O{id}"),
+            description: String::new(),
+            level: None,
+            position: 0,
+        }
+    }
+
+    #[test]
+    fn rolls_child_evidence_up_to_parent_outcomes() {
+        let outcomes = vec![outcome(1, None), outcome(2, Some(1)), outcome(3, Some(1)), outcome(4, None)];
+        let evidence = vec![
+            Evidence { outcome_id: Uuid::from_u128(2), score: 1.0, weight: 1.0 },
+            Evidence { outcome_id: Uuid::from_u128(2), score: 0.7, weight: 1.0 },
+            Evidence { outcome_id: Uuid::from_u128(3), score: 0.2, weight: 2.0 },
+        ];
+
+        let mastery = compute_mastery(&outcomes, &evidence);
+        assert_eq!(mastery[1].status, "mastered");
+        assert!((mastery[1].score.unwrap() - 0.85).abs() < 1e-6);
+        assert_eq!(mastery[2].status, "beginning");
+        // Padre: (1.0 + 0.7 + 0.2 × 2) / 4
+        assert!((mastery[0].score.unwrap() - 0.525).abs() < 1e-6);
+        assert_eq!(mastery[0].evidence_count, 3);
+        assert_eq!(mastery[0].status, "developing");
+        assert_eq!(mastery[3].status, "not_assessed");
+    }
+}
@@ -0,0 +1,235 @@
+/// API para plugins de interfaz (Web Components en iframe).
+///
+/// Al renderizar un bloque de plugin, Experience pide un token de plugin
+/// (`POST /plugins/{id}/token`): un JWT de 5 minutos ligado al usuario, la lección
+/// y un bloque `plugin` de esa lección que apunta al plugin, con los permisos que
+/// declara el manifiesto firmado. El iframe lo recibe por `postMessage` y solo
+/// puede usarlo en `/plugin-api/*`; el middleware de usuarios lo rechaza porque no
+/// lleva `role`. El puntaje que envía el plugin es el de su bloque y se registra con
+/// `grading::record_block_score`.
+use axum::{
+    Extension, Json,
+    extract::{Path, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, TimeZone, Utc};
+use common::{
+    auth::{Claims, PluginClaims},
+    middleware::Org,
+    tenancy::{self, TenantScope},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub const PERMISSION_READ_CONTEXT: &str = "lesson.read_context";
+pub const PERMISSION_SUBMIT_SCORE: &str = "grade.submit";
+pub const PERMISSION_READ_USER_NAME: &str = "user.read_name";
+
+#[derive(Debug, Deserialize)]
+pub struct MintPluginTokenPayload {
+    pub lesson_id: Uuid,
+    pub block_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PluginTokenResponse {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+    pub permissions: Vec<String>,
+    /// Ruta base, relativa a la API del LMS, de los endpoints para plugins
+    pub api_base: String,
+}
+
+/// POST /plugins/{id}/token - Token de corta duración para un plugin renderizado en una lección
+pub async fn mint_plugin_token(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(plugin_id): Path<Uuid>,
+    Json(payload): Json<MintPluginTokenPayload>,
+) -> Result<Json<PluginTokenResponse>, (StatusCode, String)> {
+    let permissions: Vec<String> = sqlx::query_scalar(
+        "SELECT permissions FROM org_plugins WHERE id = $1 AND organization_id = $2 AND kind = 'component' AND enabled = TRUE",
+    )
+    .bind(plugin_id)
+    .bind(org_ctx.id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Plugin no encontrado".to_string()))?;
+
+    let lesson: Option<(Uuid, Option<Value>, Option<Value>)> = sqlx::query_as(
+        "SELECT m.course_id, l.metadata, l.content_blocks FROM lessons l JOIN modules m ON l.module_id = m.id WHERE l.id = $1 AND l.organization_id = $2",
+    )
+    .bind(payload.lesson_id)
+    .bind(org_ctx.id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let (course_id, metadata, content_blocks) =
+        lesson.ok_or((StatusCode::NOT_FOUND, "Lección no encontrada".to_string()))?;
+
+    // El token solo se emite para un bloque de la lección que embebe este plugin
+    let plugin_id_str = plugin_id.to_string();
+    let embeds_plugin = crate::grading::lesson_blocks(metadata.as_ref(), content_blocks.as_ref())
+        .iter()
+        .any(|block| {
+            block.get("id").and_then(Value::as_str) == Some(payload.block_id.as_str())
+                && block.get("type").and_then(Value::as_str) == Some("plugin")
+                && block.get("plugin_id").and_then(Value::as_str) == Some(plugin_id_str.as_str())
+        });
+    if !embeds_plugin {
+        return Err((StatusCode::NOT_FOUND, "Bloque de plugin no encontrado en la lección".to_string()));
+    }
+
+    if claims.role != "admin" && claims.role != "instructor" {
+        let enrolled: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM enrollments WHERE user_id = $1 AND course_id = $2)")
+                .bind(claims.sub)
+                .bind(course_id)
+                .fetch_one(&pool)
+                .await
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+        if !enrolled {
+            return Err((StatusCode::FORBIDDEN, "No estás inscrito en este curso".to_string()));
+        }
+    }
+
+    let (token, plugin_claims) = common::auth::create_plugin_token(
+        claims.sub,
+        org_ctx.id,
+        plugin_id,
+        payload.lesson_id,
+        Some(payload.block_id),
+        permissions,
+    )
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(PluginTokenResponse {
+        token,
+        expires_at: Utc.timestamp_opt(plugin_claims.exp, 0).single().unwrap_or_else(Utc::now),
+        permissions: plugin_claims.permissions,
+        api_base: "/plugin-api".to_string(),
+    }))
+}
+
+/// Valida el token de plugin (`Authorization: Bearer`) y limita las consultas a su organización.
+pub async fn plugin_token_middleware(mut req: Request, next: Next) -> Result<Response, StatusCode> {
+    let token = req
+        .headers()
+        .get("authorization")
+        .and_then(|header| header.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let claims = common::auth::decode_plugin_token(token).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let scope = TenantScope::Organization(claims.org);
+    req.extensions_mut().insert(claims);
+
+    Ok(tenancy::scoped_response(scope, next.run(req)).await)
+}
+
+fn require(claims: &PluginClaims, permission: &str) -> Result<(), (StatusCode, String)> {
+    if claims.has_permission(permission) {
+        Ok(())
+    } else {
+        Err((StatusCode::FORBIDDEN, format!("El plugin no tiene el permiso {}", permission)))
+    }
+}
+
+/// GET /plugin-api/context - Contexto de la lección y del usuario según los permisos del token
+pub async fn get_plugin_context(
+    Extension(claims): Extension<PluginClaims>,
+    State(pool): State<PgPool>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let mut context = json!({
+        "plugin_id": claims.plugin_id,
+        "lesson_id": claims.lesson_id,
+        "block_id": claims.block_id,
+        "permissions": claims.permissions,
+    });
+
+    if claims.has_permission(PERMISSION_READ_CONTEXT) {
+        let lesson: Option<(String, String, Uuid, String)> = sqlx::query_as(
+            r#"
+            SELECT l.title, l.content_type, m.course_id, c.title
+            FROM lessons l
+            JOIN modules m ON l.module_id = m.id
+            JOIN courses c ON m.course_id = c.id
+            WHERE l.id = $1 AND l.organization_id = $2
+            "#,
+        )
+        .bind(claims.lesson_id)
+        .bind(claims.org)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+        let (title, content_type, course_id, course_title) =
+            lesson.ok_or((StatusCode::NOT_FOUND, "Lección no encontrada".to_string()))?;
+
+        context["lesson"] = json!({
+            "id": claims.lesson_id,
+            "title": title,
+            "content_type": content_type,
+            "course_id": course_id,
+            "course_title": course_title,
+        });
+    }
+
+    if claims.has_permission(PERMISSION_READ_USER_NAME) {
+        let full_name: Option<String> =
+            sqlx::query_scalar("SELECT full_name FROM users WHERE id = $1 AND organization_id = $2")
+                .bind(claims.sub)
+                .bind(claims.org)
+                .fetch_optional(&pool)
+                .await
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+        context["user"] = json!({ "full_name": full_name });
+    }
+
+    Ok(Json(context))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PluginScorePayload {
+    /// Puntaje de 0 a 100
+    pub score: f32,
+    pub metadata: Option<Value>,
+}
+
+/// POST /plugin-api/score - Registra el puntaje del bloque del token y recalcula la lección
+pub async fn submit_plugin_score(
+    Extension(claims): Extension<PluginClaims>,
+    State(pool): State<PgPool>,
+    Json(payload): Json<PluginScorePayload>,
+) -> Result<Json<common::models::UserGrade>, (StatusCode, String)> {
+    require(&claims, PERMISSION_SUBMIT_SCORE)?;
+    if !(0.0..=100.0).contains(&payload.score) {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, "score debe estar entre 0 y 100".to_string()));
+    }
+    let block_id = claims
+        .block_id
+        .as_deref()
+        .ok_or((StatusCode::FORBIDDEN, "El token no está ligado a un bloque".to_string()))?;
+
+    let grade = crate::grading::record_block_score(
+        &pool,
+        claims.org,
+        claims.sub,
+        claims.lesson_id,
+        block_id,
+        payload.score / 100.0,
+        json!({
+            "plugin_id": claims.plugin_id,
+            "score": payload.score,
+            "plugin_data": payload.metadata,
+        }),
+    )
+    .await?;
+
+    Ok(Json(grade))
+}
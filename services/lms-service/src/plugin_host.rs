@@ -0,0 +1,1012 @@
+/// Host de plugins de servidor en WebAssembly (WASI preview1).
+///
+/// Los plugins `kind = 'wasm'` se gestionan con el CRUD de `org_plugins` del CMS,
+/// que los replica aquí (`PUT /plugins/{id}/sync`, solo con un token entre servicios
+/// del CMS, que antes verificó el manifiesto firmado). Cada ejecución usa una
+/// instancia nueva, sin sistema de archivos, red ni variables de entorno, con el
+/// combustible (`fuel_limit`) y la memoria (`memory_limit_mb`) del plugin y un
+/// tiempo máximo de reloj (interrupción por época). `poll_oneoff` no está
+/// disponible, así que un plugin no puede dormir sin consumir combustible.
+///
+/// ABI del módulo:
+/// - Exporta `memory` y `openccb_alloc(len: i32) -> i32`.
+/// - Exporta `on_event(ptr, len) -> i64` para los eventos suscritos
+///   (`user.enrolled`, `grade.submitted`, `course.published`) y/o
+///   `grade(ptr, len) -> i64` para los tipos de bloque que califica.
+///   La entrada es JSON UTF-8; la salida es `(ptr << 32) | len` con JSON, o 0.
+/// - Puede importar de `openccb`: `kv_get(kp, kl) -> i64` (-1 si no existe),
+///   `kv_set(kp, kl, vp, vl) -> i32`, `kv_delete(kp, kl) -> i32` y `log(p, l)`.
+///
+/// El almacén clave-valor es propio de cada plugin; sus cambios solo se guardan si
+/// la ejecución termina sin error. La ejecución parte de una copia leída sin
+/// conexión retenida y guarda sus cambios en una transacción corta que comprueba la
+/// versión del almacén (`org_plugins.kv_version`); si otra ejecución escribió
+/// entretanto, se repite con la copia nueva.
+///
+/// Los tipos de bloque que califica un plugin se corrigen en el servidor:
+/// `POST /plugins/grade` registra el puntaje con `grading::record_block_score`.
+use axum::{
+    Extension, Json,
+    extract::{Path, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use base64::Engine as _;
+use common::{
+    auth::{Claims, ServiceClaims},
+    middleware::Org,
+    tenancy::{self, TenantScope},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use uuid::Uuid;
+use wasmtime::{Caller, Config, Engine, Instance, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+use wasmtime_wasi::{WasiCtxBuilder, pipe::MemoryOutputPipe, preview1::WasiP1Ctx};
+
+pub const SUPPORTED_EVENTS: &[&str] = &["user.enrolled", "grade.submitted", "course.published"];
+
+const MAX_IO_BYTES: usize = 1 << 20;
+const MAX_LOG_BYTES: usize = 16 * 1024;
+const MAX_KV_KEYS: usize = 1000;
+const MAX_KV_KEY_LEN: usize = 128;
+const MAX_KV_VALUE_BYTES: usize = 64 * 1024;
+const MODULE_CACHE_SIZE: usize = 64;
+/// Tiempo máximo de reloj de una ejecución
+const WALL_TIME_LIMIT: Duration = Duration::from_secs(5);
+/// Intervalo con el que avanza la época del motor
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+/// `ENOSYS` de WASI preview1
+const WASI_ERRNO_NOSYS: i32 = 52;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PluginError {
+    #[error("el plugin agotó su combustible")]
+    OutOfFuel,
+    #[error("el plugin superó el tiempo máximo de ejecución")]
+    Timeout,
+    #[error("el plugin no exporta `{0}`")]
+    MissingExport(String),
+    #[error("módulo inválido: {0}")]
+    InvalidModule(String),
+    #[error("salida inválida: {0}")]
+    InvalidOutput(String),
+    #[error("error de ejecución: {0}")]
+    Trap(String),
+    #[error("error de almacenamiento: {0}")]
+    Storage(#[from] sqlx::Error),
+    #[error("el almacén del plugin cambió durante la ejecución")]
+    KvConflict,
+}
+
+pub struct PluginLimits {
+    pub fuel: u64,
+    pub memory_bytes: usize,
+    pub wall_time: Duration,
+}
+
+/// Resultado de una ejecución; el combustible y los logs se conservan aunque falle.
+pub struct Invocation {
+    pub result: Result<Option<Value>, PluginError>,
+    pub fuel_consumed: u64,
+    pub kv_changes: BTreeMap<String, Option<Value>>,
+    pub logs: Vec<String>,
+}
+
+struct HostState {
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+    kv: BTreeMap<String, Value>,
+    kv_changes: BTreeMap<String, Option<Value>>,
+    logs: Vec<String>,
+    log_bytes: usize,
+}
+
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        config.epoch_interruption(true);
+        let engine = Engine::new(&config).expect("configuración de wasmtime inválida");
+
+        // El combustible no cuenta el tiempo bloqueado; la época acota el tiempo de reloj
+        let ticker = engine.clone();
+        std::thread::Builder::new()
+            .name("wasm-epoch".to_string())
+            .spawn(move || {
+                loop {
+                    std::thread::sleep(EPOCH_TICK);
+                    ticker.increment_epoch();
+                }
+            })
+            .expect("no se pudo iniciar el temporizador de wasm");
+        engine
+    })
+}
+
+fn pack(ptr: u32, len: u32) -> i64 {
+    ((ptr as i64) << 32) | len as i64
+}
+
+fn unpack(packed: i64) -> (usize, usize) {
+    ((packed as u64 >> 32) as usize, (packed as u64 & 0xffff_ffff) as usize)
+}
+
+fn read_guest(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> anyhow::Result<Vec<u8>> {
+    let len = usize::try_from(len)?;
+    anyhow::ensure!(len <= MAX_IO_BYTES, "bloque de memoria demasiado grande");
+    let memory = caller
+        .get_export("memory")
+        .and_then(|e| e.into_memory())
+        .ok_or_else(|| anyhow::anyhow!("el plugin no exporta `memory`"))?;
+    let mut buf = vec![0; len];
+    memory.read(&caller, ptr as u32 as usize, &mut buf)?;
+    Ok(buf)
+}
+
+fn read_key(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> anyhow::Result<String> {
+    anyhow::ensure!(len as usize <= MAX_KV_KEY_LEN, "clave demasiado larga");
+    Ok(String::from_utf8(read_guest(caller, ptr, len)?)?)
+}
+
+/// Copia `bytes` a memoria del plugin usando su `openccb_alloc`.
+fn write_guest(caller: &mut Caller<'_, HostState>, bytes: &[u8]) -> anyhow::Result<i64> {
+    let alloc = caller
+        .get_export("openccb_alloc")
+        .and_then(|e| e.into_func())
+        .ok_or_else(|| anyhow::anyhow!("el plugin no exporta `openccb_alloc`"))?
+        .typed::<i32, i32>(&caller)?;
+    let ptr = alloc.call(&mut *caller, bytes.len() as i32)?;
+    let memory = caller
+        .get_export("memory")
+        .and_then(|e| e.into_memory())
+        .ok_or_else(|| anyhow::anyhow!("el plugin no exporta `memory`"))?;
+    memory.write(&mut *caller, ptr as u32 as usize, bytes)?;
+    Ok(pack(ptr as u32, bytes.len() as u32))
+}
+
+fn linker() -> &'static Linker<HostState> {
+    static LINKER: OnceLock<Linker<HostState>> = OnceLock::new();
+    LINKER.get_or_init(|| {
+        let mut linker = Linker::new(engine());
+        wasmtime_wasi::preview1::add_to_linker_sync(&mut linker, |s: &mut HostState| &mut s.wasi)
+            .expect("no se pudo enlazar WASI");
+
+        // `poll_oneoff` permite dormir en el host sin consumir combustible ni avanzar
+        // la época dentro de wasm
+        linker.allow_shadowing(true);
+        linker
+            .func_wrap(
+                "wasi_snapshot_preview1",
+                "poll_oneoff",
+                |_caller: Caller<'_, HostState>, _in: i32, _out: i32, _count: i32, _events: i32| WASI_ERRNO_NOSYS,
+            )
+            .expect("no se pudo reemplazar poll_oneoff");
+        linker.allow_shadowing(false);
+
+        linker
+            .func_wrap("openccb", "log", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                let bytes = read_guest(&mut caller, ptr, len)?;
+                let state = caller.data_mut();
+                if state.log_bytes + bytes.len() <= MAX_LOG_BYTES {
+                    state.log_bytes += bytes.len();
+                    state.logs.push(String::from_utf8_lossy(&bytes).into_owned());
+                }
+                Ok(())
+            })
+            .expect("no se pudo enlazar openccb.log");
+
+        linker
+            .func_wrap("openccb", "kv_get", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                let key = read_key(&mut caller, ptr, len)?;
+                match caller.data().kv.get(&key).map(serde_json::to_vec) {
+                    Some(value) => write_guest(&mut caller, &value?),
+                    None => Ok(-1),
+                }
+            })
+            .expect("no se pudo enlazar openccb.kv_get");
+
+        linker
+            .func_wrap(
+                "openccb",
+                "kv_set",
+                |mut caller: Caller<'_, HostState>, kp: i32, kl: i32, vp: i32, vl: i32| {
+                    let key = read_key(&mut caller, kp, kl)?;
+                    if vl as usize > MAX_KV_VALUE_BYTES {
+                        return Ok(-1);
+                    }
+                    let Ok(value) = serde_json::from_slice::<Value>(&read_guest(&mut caller, vp, vl)?) else {
+                        return Ok(-1);
+                    };
+                    let state = caller.data_mut();
+                    if !state.kv.contains_key(&key) && state.kv.len() >= MAX_KV_KEYS {
+                        return Ok(-1);
+                    }
+                    state.kv.insert(key.clone(), value.clone());
+                    state.kv_changes.insert(key, Some(value));
+                    Ok(0)
+                },
+            )
+            .expect("no se pudo enlazar openccb.kv_set");
+
+        linker
+            .func_wrap("openccb", "kv_delete", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                let key = read_key(&mut caller, ptr, len)?;
+                let state = caller.data_mut();
+                state.kv.remove(&key);
+                state.kv_changes.insert(key, None);
+                Ok(0i32)
+            })
+            .expect("no se pudo enlazar openccb.kv_delete");
+
+        linker
+    })
+}
+
+fn classify(error: anyhow::Error) -> PluginError {
+    match error.downcast_ref::<Trap>() {
+        Some(Trap::OutOfFuel) => PluginError::OutOfFuel,
+        Some(Trap::Interrupt) => PluginError::Timeout,
+        _ => PluginError::Trap(format!("{:#}", error).chars().take(500).collect()),
+    }
+}
+
+/// Compila (o reutiliza) un módulo y comprueba las exportaciones obligatorias.
+pub fn compile(sha256: &str, bytes: &[u8], required_exports: &[&str]) -> Result<Module, PluginError> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Module>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(Default::default);
+
+    let cached = cache.lock().unwrap().get(sha256).cloned();
+    let module = match cached {
+        Some(module) => module,
+        None => {
+            let module = Module::new(engine(), bytes).map_err(|e| PluginError::InvalidModule(e.to_string()))?;
+            let mut cache = cache.lock().unwrap();
+            if cache.len() >= MODULE_CACHE_SIZE {
+                cache.clear();
+            }
+            cache.insert(sha256.to_string(), module.clone());
+            module
+        }
+    };
+
+    for name in ["memory", "openccb_alloc"].iter().chain(required_exports) {
+        if module.get_export(name).is_none() {
+            return Err(PluginError::MissingExport(name.to_string()));
+        }
+    }
+    Ok(module)
+}
+
+fn call_export(store: &mut Store<HostState>, module: &Module, export: &str, input: &[u8]) -> Result<Option<Value>, PluginError> {
+    let instance: Instance = linker().instantiate(&mut *store, module).map_err(classify)?;
+    let func = instance
+        .get_typed_func::<(i32, i32), i64>(&mut *store, export)
+        .map_err(|_| PluginError::MissingExport(export.to_string()))?;
+    let alloc = instance
+        .get_typed_func::<i32, i32>(&mut *store, "openccb_alloc")
+        .map_err(|_| PluginError::MissingExport("openccb_alloc".to_string()))?;
+    let memory = instance
+        .get_memory(&mut *store, "memory")
+        .ok_or_else(|| PluginError::MissingExport("memory".to_string()))?;
+
+    let ptr = alloc.call(&mut *store, input.len() as i32).map_err(classify)?;
+    memory
+        .write(&mut *store, ptr as u32 as usize, input)
+        .map_err(|e| PluginError::Trap(e.to_string()))?;
+
+    let packed = func.call(&mut *store, (ptr, input.len() as i32)).map_err(classify)?;
+    if packed == 0 {
+        return Ok(None);
+    }
+    let (out_ptr, out_len) = unpack(packed);
+    if out_len > MAX_IO_BYTES {
+        return Err(PluginError::InvalidOutput("salida demasiado grande".to_string()));
+    }
+    let mut out = vec![0; out_len];
+    memory
+        .read(&*store, out_ptr, &mut out)
+        .map_err(|e| PluginError::InvalidOutput(e.to_string()))?;
+    serde_json::from_slice(&out).map(Some).map_err(|e| PluginError::InvalidOutput(e.to_string()))
+}
+
+/// Ejecuta `export` en una instancia nueva. Bloqueante: usar desde `spawn_blocking`.
+pub fn invoke(
+    module: &Module,
+    limits: &PluginLimits,
+    export: &str,
+    input: &Value,
+    kv: BTreeMap<String, Value>,
+) -> Invocation {
+    let stdout = MemoryOutputPipe::new(MAX_LOG_BYTES);
+    let stderr = MemoryOutputPipe::new(MAX_LOG_BYTES);
+    let wasi = WasiCtxBuilder::new().stdout(stdout.clone()).stderr(stderr.clone()).build_p1();
+    let state = HostState {
+        wasi,
+        limits: StoreLimitsBuilder::new()
+            .memory_size(limits.memory_bytes)
+            .instances(1)
+            .memories(1)
+            .tables(4)
+            .table_elements(100_000)
+            .build(),
+        kv,
+        kv_changes: BTreeMap::new(),
+        logs: Vec::new(),
+        log_bytes: 0,
+    };
+
+    let mut store = Store::new(engine(), state);
+    store.limiter(|s| &mut s.limits);
+    store.set_epoch_deadline(limits.wall_time.as_millis().div_ceil(EPOCH_TICK.as_millis()).max(1) as u64);
+
+    let result = match store.set_fuel(limits.fuel) {
+        Ok(()) => serde_json::to_vec(input)
+            .map_err(|e| PluginError::InvalidOutput(e.to_string()))
+            .and_then(|bytes| call_export(&mut store, module, export, &bytes)),
+        Err(e) => Err(PluginError::Trap(e.to_string())),
+    };
+
+    let fuel_consumed = limits.fuel.saturating_sub(store.get_fuel().unwrap_or(0));
+    let state = store.into_data();
+    let mut logs = state.logs;
+    for pipe in [stdout, stderr] {
+        let contents = pipe.contents();
+        if !contents.is_empty() {
+            logs.push(String::from_utf8_lossy(&contents).into_owned());
+        }
+    }
+
+    Invocation {
+        result,
+        fuel_consumed,
+        kv_changes: state.kv_changes,
+        logs,
+    }
+}
+
+// ==================== Ejecución desde el LMS ====================
+
+#[derive(Debug, sqlx::FromRow)]
+struct WasmPlugin {
+    id: Uuid,
+    organization_id: Uuid,
+    name: String,
+    config: Value,
+    wasm_module: Vec<u8>,
+    wasm_sha256: String,
+    fuel_limit: i64,
+    memory_limit_mb: i32,
+}
+
+const WASM_PLUGIN_COLUMNS: &str =
+    "id, organization_id, name, config, wasm_module, wasm_sha256, fuel_limit, memory_limit_mb";
+
+/// Ejecuciones de un plugin cuyo almacén cambió mientras corrían
+const KV_CONFLICT_ATTEMPTS: usize = 3;
+
+async fn run_plugin(pool: &PgPool, plugin: &WasmPlugin, export: &str, hook: &str, input: Value) -> Result<Option<Value>, PluginError> {
+    let mut attempt = 1;
+    loop {
+        match run_plugin_once(pool, plugin, export, hook, input.clone()).await {
+            Err(PluginError::KvConflict) if attempt < KV_CONFLICT_ATTEMPTS => attempt += 1,
+            result => return result,
+        }
+    }
+}
+
+/// Guarda los cambios de una ejecución si la versión del almacén sigue siendo la de
+/// la copia con la que partió.
+async fn save_kv_changes(
+    pool: &PgPool,
+    plugin: &WasmPlugin,
+    kv_version: i64,
+    changes: &BTreeMap<String, Option<Value>>,
+) -> Result<(), PluginError> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let mut tx = pool.begin().await?;
+    let updated = sqlx::query("UPDATE org_plugins SET kv_version = kv_version + 1 WHERE id = $1 AND kv_version = $2")
+        .bind(plugin.id)
+        .bind(kv_version)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    if updated == 0 {
+        return Err(PluginError::KvConflict);
+    }
+    for (key, value) in changes {
+        match value {
+            Some(value) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO plugin_kv (plugin_id, organization_id, key, value)
+                    VALUES ($1, $2, $3, $4)
+                    ON CONFLICT (plugin_id, key) DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()
+                    "#,
+                )
+                .bind(plugin.id)
+                .bind(plugin.organization_id)
+                .bind(key)
+                .bind(value)
+                .execute(&mut *tx)
+                .await?;
+            }
+            None => {
+                sqlx::query("DELETE FROM plugin_kv WHERE plugin_id = $1 AND key = $2")
+                    .bind(plugin.id)
+                    .bind(key)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn run_plugin_once(pool: &PgPool, plugin: &WasmPlugin, export: &str, hook: &str, input: Value) -> Result<Option<Value>, PluginError> {
+    // Copia del almacén y su versión, leídas en una sola consulta; la conexión se
+    // libera antes de ejecutar el módulo
+    let snapshot: Vec<(i64, Option<String>, Option<Value>)> = sqlx::query_as(
+        r#"
+        SELECT p.kv_version, kv.key, kv.value
+        FROM org_plugins p
+        LEFT JOIN plugin_kv kv ON kv.plugin_id = p.id
+        WHERE p.id = $1
+        "#,
+    )
+    .bind(plugin.id)
+    .fetch_all(pool)
+    .await?;
+    let kv_version = snapshot.first().map(|row| row.0).unwrap_or_default();
+    let kv: BTreeMap<String, Value> = snapshot
+        .into_iter()
+        .filter_map(|(_, key, value)| Some((key?, value?)))
+        .collect();
+
+    let sha256 = plugin.wasm_sha256.clone();
+    let bytes = plugin.wasm_module.clone();
+    let limits = PluginLimits {
+        fuel: plugin.fuel_limit as u64,
+        memory_bytes: plugin.memory_limit_mb as usize * 1024 * 1024,
+        wall_time: WALL_TIME_LIMIT,
+    };
+    let export_name = export.to_string();
+    let started = std::time::Instant::now();
+    let invocation = tokio::task::spawn_blocking(move || {
+        compile(&sha256, &bytes, &[]).map(|module| invoke(&module, &limits, &export_name, &input, kv))
+    })
+    .await
+    .map_err(|e| PluginError::Trap(e.to_string()))?;
+    let duration_ms = started.elapsed().as_millis() as i32;
+
+    let (result, fuel_consumed) = match invocation {
+        Ok(invocation) => {
+            for line in &invocation.logs {
+                tracing::info!("[plugin {}] {}", plugin.name, line.trim_end());
+            }
+            let result = match invocation.result {
+                Ok(output) => save_kv_changes(pool, plugin, kv_version, &invocation.kv_changes)
+                    .await
+                    .map(|_| output),
+                Err(e) => Err(e),
+            };
+            (result, invocation.fuel_consumed)
+        }
+        Err(e) => (Err(e), 0),
+    };
+
+    let (status, error) = match &result {
+        Ok(_) => ("ok", None),
+        Err(PluginError::OutOfFuel) => ("out_of_fuel", Some(PluginError::OutOfFuel.to_string())),
+        Err(e) => ("error", Some(e.to_string())),
+    };
+    let _ = sqlx::query(
+        r#"
+        INSERT INTO plugin_invocations (plugin_id, organization_id, hook, status, fuel_consumed, duration_ms, error)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(plugin.id)
+    .bind(plugin.organization_id)
+    .bind(hook)
+    .bind(status)
+    .bind(fuel_consumed as i64)
+    .bind(duration_ms)
+    .bind(error)
+    .execute(pool)
+    .await;
+
+    result
+}
+
+/// Entrega un evento a los plugins wasm suscritos (en segundo plano).
+pub fn dispatch_event(pool: &PgPool, organization_id: Uuid, event: &'static str, data: Value) {
+    let pool = pool.clone();
+    tenancy::spawn(async move {
+        let plugins = match sqlx::query_as::<_, WasmPlugin>(&format!(
+            "SELECT {} FROM org_plugins WHERE organization_id = $1 AND kind = 'wasm' AND enabled = TRUE AND $2 = ANY(events) ORDER BY created_at",
+            WASM_PLUGIN_COLUMNS
+        ))
+        .bind(organization_id)
+        .bind(event)
+        .fetch_all(&pool)
+        .await
+        {
+            Ok(plugins) => plugins,
+            Err(e) => {
+                tracing::error!("Error al obtener los plugins del evento {}: {}", event, e);
+                return;
+            }
+        };
+
+        for plugin in plugins {
+            let input = json!({
+                "event": event,
+                "organization_id": organization_id,
+                "data": data,
+                "config": plugin.config,
+            });
+            if let Err(e) = run_plugin(&pool, &plugin, "on_event", event, input).await {
+                tracing::warn!("El plugin {} falló en el evento {}: {}", plugin.name, event, e);
+            }
+        }
+    });
+}
+
+// ==================== Endpoints ====================
+
+#[derive(Debug, Deserialize)]
+pub struct SyncPluginPayload {
+    pub name: String,
+    pub kind: String,
+    #[serde(default)]
+    pub component_url: String,
+    pub config: Value,
+    pub enabled: bool,
+    pub version: Option<String>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    /// Módulo en base64 (solo `kind = "wasm"`)
+    pub wasm_module: Option<String>,
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub block_types: Vec<String>,
+    pub fuel_limit: i64,
+    pub memory_limit_mb: i32,
+}
+
+/// Valida el token entre servicios del CMS (`scope = plugins.sync`); los tokens de
+/// usuario no sirven, así que los permisos solo llegan desde un manifiesto verificado.
+pub async fn sync_token_middleware(mut req: Request, next: Next) -> Result<Response, StatusCode> {
+    let token = req
+        .headers()
+        .get("authorization")
+        .and_then(|header| header.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let claims = common::auth::decode_service_token(token, common::auth::SERVICE_SCOPE_PLUGIN_SYNC)
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let scope = TenantScope::Organization(claims.org);
+    req.extensions_mut().insert(claims);
+
+    Ok(tenancy::scoped_response(scope, next.run(req)).await)
+}
+
+/// PUT /plugins/{id}/sync - Réplica de un plugin desde el CMS
+pub async fn sync_plugin(
+    Extension(service): Extension<ServiceClaims>,
+    State(pool): State<PgPool>,
+    Path(plugin_id): Path<Uuid>,
+    Json(payload): Json<SyncPluginPayload>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let is_wasm = payload.kind == "wasm";
+    if let Some(permission) = payload
+        .permissions
+        .iter()
+        .find(|p| !common::auth::PLUGIN_PERMISSIONS.contains(&p.as_str()))
+    {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, format!("Permiso desconocido: {}", permission)));
+    }
+    if let Some(event) = payload.events.iter().find(|e| !SUPPORTED_EVENTS.contains(&e.as_str())) {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, format!("Evento no soportado: {}", event)));
+    }
+
+    let module = match (is_wasm, payload.wasm_module.as_deref()) {
+        (true, Some(encoded)) => {
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|_| (StatusCode::UNPROCESSABLE_ENTITY, "wasm_module no es base64 válido".to_string()))?;
+            let sha256 = hex::encode(Sha256::digest(&bytes));
+
+            // El módulo debe compilar y exportar los puntos de entrada que declara
+            let mut required = Vec::new();
+            if !payload.events.is_empty() {
+                required.push("on_event");
+            }
+            if !payload.block_types.is_empty() {
+                required.push("grade");
+            }
+            let (module_sha, module_bytes) = (sha256.clone(), bytes.clone());
+            tokio::task::spawn_blocking(move || compile(&module_sha, &module_bytes, &required).map(|_| ()))
+                .await
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+                .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+            Some((bytes, sha256))
+        }
+        (true, None) => {
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, "wasm_module es obligatorio".to_string()));
+        }
+        (false, _) => None,
+    };
+    let (wasm_module, wasm_sha256) = module.unzip();
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO org_plugins (id, organization_id, name, component_url, config, enabled, kind, wasm_module,
+                                 wasm_sha256, events, block_types, fuel_limit, memory_limit_mb, version, permissions)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+        ON CONFLICT (id) DO UPDATE SET
+            name = EXCLUDED.name,
+            component_url = EXCLUDED.component_url,
+            config = EXCLUDED.config,
+            enabled = EXCLUDED.enabled,
+            wasm_module = EXCLUDED.wasm_module,
+            wasm_sha256 = EXCLUDED.wasm_sha256,
+            events = EXCLUDED.events,
+            block_types = EXCLUDED.block_types,
+            fuel_limit = EXCLUDED.fuel_limit,
+            memory_limit_mb = EXCLUDED.memory_limit_mb,
+            version = EXCLUDED.version,
+            permissions = EXCLUDED.permissions,
+            updated_at = NOW()
+        WHERE org_plugins.organization_id = EXCLUDED.organization_id AND org_plugins.kind = EXCLUDED.kind
+        "#,
+    )
+    .bind(plugin_id)
+    .bind(service.org)
+    .bind(&payload.name)
+    .bind(&payload.component_url)
+    .bind(&payload.config)
+    .bind(payload.enabled)
+    .bind(&payload.kind)
+    .bind(&wasm_module)
+    .bind(&wasm_sha256)
+    .bind(&payload.events)
+    .bind(&payload.block_types)
+    .bind(payload.fuel_limit)
+    .bind(payload.memory_limit_mb)
+    .bind(&payload.version)
+    .bind(&payload.permissions)
+    .execute(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error al replicar el plugin {}: {}", plugin_id, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Plugin no encontrado".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /plugins/{id}/sync - Retira la réplica de un plugin
+pub async fn delete_plugin(
+    Extension(service): Extension<ServiceClaims>,
+    State(pool): State<PgPool>,
+    Path(plugin_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sqlx::query("DELETE FROM org_plugins WHERE id = $1 AND organization_id = $2")
+        .bind(plugin_id)
+        .bind(service.org)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PluginGradePayload {
+    pub lesson_id: Uuid,
+    pub block_id: String,
+    pub response: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PluginGradeResult {
+    pub score: f64,
+    pub feedback: Option<String>,
+    #[serde(default)]
+    pub details: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PluginGradeResponse {
+    #[serde(flatten)]
+    pub result: PluginGradeResult,
+    /// Calificación de la lección actualizada (solo estudiantes inscritos)
+    pub grade: Option<common::models::UserGrade>,
+    /// Motivo por el que el puntaje no se registró (intentos agotados, fuera de plazo...)
+    pub grade_error: Option<String>,
+}
+
+/// POST /plugins/grade - Califica la respuesta a un bloque de un tipo aportado por un
+/// plugin y registra el puntaje del bloque en la calificación de la lección
+pub async fn grade_plugin_block(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Json(payload): Json<PluginGradePayload>,
+) -> Result<Json<PluginGradeResponse>, (StatusCode, String)> {
+    let lesson: Option<(Option<Value>, Option<Value>, bool, bool)> = sqlx::query_as(
+        r#"
+        SELECT l.metadata, l.content_blocks, COALESCE(l.is_previewable, false),
+               EXISTS(SELECT 1 FROM enrollments e WHERE e.user_id = $3 AND e.course_id = m.course_id)
+        FROM lessons l
+        JOIN modules m ON l.module_id = m.id
+        WHERE l.id = $1 AND l.organization_id = $2
+        "#,
+    )
+    .bind(payload.lesson_id)
+    .bind(org_ctx.id)
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let (metadata, content_blocks, is_previewable, is_enrolled) =
+        lesson.ok_or((StatusCode::NOT_FOUND, "Lección no encontrada".to_string()))?;
+
+    let is_staff = claims.role == "admin" || claims.role == "instructor";
+    if !is_staff && !is_enrolled && !is_previewable {
+        return Err((StatusCode::FORBIDDEN, "No tienes acceso a esta lección".into()));
+    }
+
+    let block = [metadata.as_ref().and_then(|m| m.get("blocks")), content_blocks.as_ref()]
+        .into_iter()
+        .flatten()
+        .filter_map(|blocks| blocks.as_array())
+        .flatten()
+        .find(|block| block.get("id").and_then(|id| id.as_str()) == Some(payload.block_id.as_str()))
+        .cloned()
+        .ok_or((StatusCode::NOT_FOUND, "Bloque no encontrado".to_string()))?;
+    let block_type = block.get("type").and_then(|t| t.as_str()).unwrap_or_default();
+
+    let plugin = sqlx::query_as::<_, WasmPlugin>(&format!(
+        "SELECT {} FROM org_plugins WHERE organization_id = $1 AND kind = 'wasm' AND enabled = TRUE AND $2 = ANY(block_types) ORDER BY created_at LIMIT 1",
+        WASM_PLUGIN_COLUMNS
+    ))
+    .bind(org_ctx.id)
+    .bind(block_type)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Ningún plugin califica este tipo de bloque".to_string()))?;
+
+    let input = json!({
+        "block": block,
+        "response": payload.response,
+        "user_id": claims.sub,
+        "lesson_id": payload.lesson_id,
+        "config": plugin.config,
+    });
+    let output = run_plugin(&pool, &plugin, "grade", &format!("grade:{}", block_type), input)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?
+        .ok_or((StatusCode::BAD_GATEWAY, "El plugin no devolvió una calificación".to_string()))?;
+
+    let result: PluginGradeResult = serde_json::from_value(output)
+        .ok()
+        .filter(|r: &PluginGradeResult| (0.0..=100.0).contains(&r.score))
+        .ok_or((StatusCode::BAD_GATEWAY, "El plugin devolvió una calificación inválida".to_string()))?;
+
+    // Solo las respuestas de estudiantes inscritos cuentan para la calificación
+    let (grade, grade_error) = if is_enrolled && !is_staff {
+        match crate::grading::record_block_score(
+            &pool,
+            org_ctx.id,
+            claims.sub,
+            payload.lesson_id,
+            &payload.block_id,
+            (result.score / 100.0) as f32,
+            json!({ "plugin_id": plugin.id, "score": result.score, "details": result.details }),
+        )
+        .await
+        {
+            Ok(grade) => (Some(grade), None),
+            Err((_, message)) => (None, Some(message)),
+        }
+    } else {
+        (None, None)
+    };
+
+    Ok(Json(PluginGradeResponse { result, grade, grade_error }))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct PluginInvocationRecord {
+    pub id: Uuid,
+    pub hook: String,
+    pub status: String,
+    pub fuel_consumed: i64,
+    pub duration_ms: i32,
+    pub error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// GET /plugins/{id}/invocations - Últimas ejecuciones de un plugin (administradores)
+pub async fn list_plugin_invocations(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(plugin_id): Path<Uuid>,
+) -> Result<Json<Vec<PluginInvocationRecord>>, (StatusCode, String)> {
+    if claims.role != "admin" {
+        return Err((StatusCode::FORBIDDEN, "Acceso denegado".to_string()));
+    }
+
+    let records = sqlx::query_as::<_, PluginInvocationRecord>(
+        r#"
+        SELECT id, hook, status, fuel_consumed, duration_ms, error, created_at
+        FROM plugin_invocations
+        WHERE plugin_id = $1 AND organization_id = $2
+        ORDER BY created_at DESC
+        LIMIT 100
+        "#,
+    )
+    .bind(plugin_id)
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(records))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_PLUGIN: &str = r#"
+        (module
+          (import "openccb" "kv_set" (func $kv_set (param i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (global $heap (mut i32) (i32.const 1024))
+          (data (i32.const 0) "count")
+          (data (i32.const 16) "{\"score\":100}")
+          (data (i32.const 32) "7")
+          (func (export "openccb_alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $heap))
+            (global.set $heap (i32.add (global.get $heap) (local.get $len)))
+            (local.get $ptr))
+          (func (export "grade") (param i32 i32) (result i64)
+            (drop (call $kv_set (i32.const 0) (i32.const 5) (i32.const 32) (i32.const 1)))
+            (i64.or (i64.shl (i64.const 16) (i64.const 32)) (i64.const 13)))
+          (func (export "on_event") (param i32 i32) (result i64)
+            (loop $spin (br $spin))
+            (i64.const 0)))
+    "#;
+
+    fn limits() -> PluginLimits {
+        PluginLimits {
+            fuel: 1_000_000,
+            memory_bytes: 1 << 20,
+            wall_time: WALL_TIME_LIMIT,
+        }
+    }
+
+    #[test]
+    fn grade_returns_output_and_records_kv_changes() {
+        let module = compile("test-grade", TEST_PLUGIN.as_bytes(), &["grade"]).unwrap();
+        let invocation = invoke(&module, &limits(), "grade", &json!({"response": 1}), BTreeMap::new());
+
+        assert_eq!(invocation.result.unwrap(), Some(json!({"score": 100})));
+        assert_eq!(invocation.kv_changes.get("count"), Some(&Some(json!(7))));
+        assert!(invocation.fuel_consumed > 0);
+    }
+
+    #[test]
+    fn runaway_plugin_stops_when_fuel_runs_out() {
+        let module = compile("test-fuel", TEST_PLUGIN.as_bytes(), &["on_event"]).unwrap();
+        let invocation = invoke(&module, &limits(), "on_event", &json!({}), BTreeMap::new());
+
+        assert!(matches!(invocation.result, Err(PluginError::OutOfFuel)));
+        assert!(compile("test-missing", TEST_PLUGIN.as_bytes(), &["render"]).is_err());
+    }
+
+    #[test]
+    fn wall_clock_deadline_interrupts_plugin_with_fuel_left() {
+        let module = compile("test-wall", TEST_PLUGIN.as_bytes(), &["on_event"]).unwrap();
+        let limits = PluginLimits {
+            fuel: u64::MAX,
+            memory_bytes: 1 << 20,
+            wall_time: Duration::from_millis(200),
+        };
+        let started = std::time::Instant::now();
+        let invocation = invoke(&module, &limits, "on_event", &json!({}), BTreeMap::new());
+
+        assert!(matches!(invocation.result, Err(PluginError::Timeout)));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn poll_oneoff_is_not_available_to_plugins() {
+        // Suscripción a un reloj monotónico relativo de una hora: con WASI completo la
+        // llamada dormiría en el host. Si devuelve ENOSYS el plugin termina con 0.
+        const SLEEPER: &str = r#"
+            (module
+              (import "wasi_snapshot_preview1" "poll_oneoff"
+                (func $poll (param i32 i32 i32 i32) (result i32)))
+              (memory (export "memory") 1)
+              (data (i32.const 16) "\01")
+              (data (i32.const 24) "\00\a0\b8\30\46\03\00\00")
+              (func (export "openccb_alloc") (param i32) (result i32) (i32.const 1024))
+              (func (export "on_event") (param i32 i32) (result i64)
+                (if (result i64) (i32.eq (call $poll (i32.const 0) (i32.const 256) (i32.const 1) (i32.const 512)) (i32.const 52))
+                  (then (i64.const 0))
+                  (else (i64.const -1)))))
+        "#;
+        let module = compile("test-poll", SLEEPER.as_bytes(), &["on_event"]).unwrap();
+        let started = std::time::Instant::now();
+        let invocation = invoke(&module, &limits(), "on_event", &json!({}), BTreeMap::new());
+
+        assert_eq!(invocation.result.unwrap(), None);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn kv_changes_are_saved_only_against_the_version_they_read() {
+        let Some(pool) = crate::db_util::test_pool(2).await else {
+            return;
+        };
+        let plugin: WasmPlugin = sqlx::query_as(&format!(
+            r#"
+            INSERT INTO org_plugins (organization_id, name, component_url, kind, wasm_module, wasm_sha256, block_types)
+            VALUES ($1, 'kv-test', '', 'wasm', $2, 'test-kv-version', '{{kv-test}}')
+            RETURNING {}
+            "#,
+            WASM_PLUGIN_COLUMNS
+        ))
+        .bind(common::tenancy::DEFAULT_ORG_ID)
+        .bind(TEST_PLUGIN.as_bytes())
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        let output = run_plugin(&pool, &plugin, "grade", "grade:kv-test", json!({})).await.unwrap();
+        assert_eq!(output, Some(json!({ "score": 100 })));
+        let (version, count): (i64, Value) = sqlx::query_as(
+            "SELECT p.kv_version, kv.value FROM org_plugins p JOIN plugin_kv kv ON kv.plugin_id = p.id WHERE p.id = $1",
+        )
+        .bind(plugin.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!((version, count), (1, json!(7)));
+
+        // Una ejecución que partió de la versión anterior no pisa el almacén
+        let stale = BTreeMap::from([("count".to_string(), Some(json!(1)))]);
+        assert!(matches!(
+            save_kv_changes(&pool, &plugin, 0, &stale).await,
+            Err(PluginError::KvConflict)
+        ));
+
+        sqlx::query("DELETE FROM org_plugins WHERE id = $1")
+            .bind(plugin.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
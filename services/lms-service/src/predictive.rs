@@ -59,35 +59,44 @@ pub async fn calculate_risks_for_course(
     course_id: Uuid,
     organization_id: Uuid,
 ) -> Result<(), sqlx::Error> {
-    let enrollments = sqlx::query("SELECT user_id FROM enrollments WHERE course_id = $1 AND organization_id = $2")
-        .bind(course_id)
-        .bind(organization_id)
-        .fetch_all(pool)
-        .await?;
-
-    for enrollment in enrollments {
-        let user_id: Uuid = enrollment.get("user_id");
-        
-        let avg_grade: f32 = sqlx::query("SELECT COALESCE(AVG(score), 0.0) FROM user_grades WHERE user_id = $1 AND course_id = $2")
-            .bind(user_id)
-            .bind(course_id)
-            .fetch_one(pool)
-            .await?
-            .get::<f64, _>(0) as f32; // AVG returns f64 usually
-
-        let last_activity_count: i64 = sqlx::query("SELECT COUNT(*) FROM lesson_interactions WHERE user_id = $1 AND created_at > $2")
-            .bind(user_id)
-            .bind(Utc::now() - Duration::days(7))
-            .fetch_one(pool)
-            .await?
-            .get(0);
+    // Señales por alumno desde los rollups de eventos de aprendizaje. La participación
+    // en foros se cuenta en toda la organización, no solo en el curso
+    let signals = sqlx::query(
+        r#"
+        SELECT e.user_id,
+               COALESCE(grades.avg_score, 0.0)::float8 AS avg_grade,
+               COALESCE(recent.events, 0)::bigint AS last_activity_count,
+               COALESCE(social.posts, 0)::bigint AS forum_posts
+        FROM enrollments e
+        LEFT JOIN LATERAL (
+            SELECT AVG(p.last_score) AS avg_score
+            FROM la_lesson_progress p
+            WHERE p.course_id = e.course_id AND p.user_id = e.user_id AND p.last_score IS NOT NULL
+        ) grades ON TRUE
+        LEFT JOIN LATERAL (
+            SELECT SUM(a.events - a.posts) AS events
+            FROM la_daily_learner_activity a
+            WHERE a.course_id = e.course_id AND a.user_id = e.user_id AND a.day >= $3
+        ) recent ON TRUE
+        LEFT JOIN LATERAL (
+            SELECT COUNT(*) AS posts
+            FROM discussion_posts d
+            WHERE d.author_id = e.user_id AND d.organization_id = e.organization_id
+        ) social ON TRUE
+        WHERE e.course_id = $1 AND e.organization_id = $2
+        "#,
+    )
+    .bind(course_id)
+    .bind(organization_id)
+    .bind((Utc::now() - Duration::days(7)).date_naive())
+    .fetch_all(pool)
+    .await?;
 
-        let forum_posts: i64 = sqlx::query("SELECT COUNT(*) FROM discussion_posts WHERE author_id = $1 AND organization_id = $2")
-            .bind(user_id)
-            .bind(organization_id)
-            .fetch_one(pool)
-            .await?
-            .get(0);
+    for signal in signals {
+        let user_id: Uuid = signal.get("user_id");
+        let avg_grade = signal.get::<f64, _>("avg_grade") as f32;
+        let last_activity_count: i64 = signal.get("last_activity_count");
+        let forum_posts: i64 = signal.get("forum_posts");
 
         let perf_risk = (1.0 - avg_grade).max(0.0);
         let activity_risk = (1.0 / (last_activity_count as f32 + 1.0)).min(1.0);
@@ -0,0 +1,377 @@
+/// Evaluación de pronunciación a nivel de palabra.
+///
+/// Whisper devuelve las palabras reconocidas con sus marcas de tiempo (y, en los
+/// servidores compatibles basados en faster-whisper, la probabilidad de cada una).
+/// Se alinean con el texto de referencia del bloque mediante distancia de edición
+/// ponderada por similitud de caracteres y se calculan tres métricas de 0 a 100:
+///
+/// - `accuracy`: parecido de cada palabra pronunciada con la esperada, ponderado
+///   por la confianza del reconocedor.
+/// - `fluency`: velocidad de habla (palabras por minuto) penalizada por pausas.
+/// - `completeness`: proporción de palabras de la referencia que se dijeron.
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Pausa mínima entre palabras que cuenta como vacilación (segundos)
+const PAUSE_SECONDS: f64 = 0.5;
+/// Pausa que se considera larga y se penaliza aparte (segundos)
+const LONG_PAUSE_SECONDS: f64 = 1.5;
+/// Rango de velocidad natural en palabras por minuto
+const NATURAL_RATE_WPM: (f64, f64) = (90.0, 170.0);
+/// Por debajo de esta similitud la palabra se trata como omitida más una inserción
+const MIN_MATCH_SIMILARITY: f64 = 0.5;
+/// Precisión mínima para considerar una palabra bien pronunciada
+const CORRECT_ACCURACY: f64 = 80.0;
+
+/// Palabra reconocida por Whisper.
+#[derive(Debug, Clone)]
+pub struct SpokenWord {
+    pub word: String,
+    pub start: f64,
+    pub end: f64,
+    pub probability: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WordAssessment {
+    /// Palabra de la referencia (ausente si el estudiante añadió una palabra)
+    pub expected: Option<String>,
+    /// Palabra reconocida (ausente si se omitió)
+    pub spoken: Option<String>,
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+    pub accuracy: f64,
+    /// correct, mispronounced, omitted o inserted
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FluencyMetrics {
+    pub speech_rate_wpm: f64,
+    pub speaking_seconds: f64,
+    pub pause_count: i32,
+    pub long_pause_count: i32,
+    pub total_pause_seconds: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PronunciationAssessment {
+    pub expected_text: String,
+    /// Media ponderada de las tres métricas
+    pub score: f64,
+    pub accuracy: f64,
+    pub fluency: f64,
+    pub completeness: f64,
+    pub fluency_metrics: FluencyMetrics,
+    pub words: Vec<WordAssessment>,
+}
+
+/// Extrae las palabras con marca de tiempo de una respuesta `verbose_json` de Whisper.
+///
+/// La API de OpenAI las devuelve en `words`; los servidores locales suelen
+/// anidarlas en `segments[].words`.
+pub fn words_from_whisper(response: &Value) -> Vec<SpokenWord> {
+    let parse = |items: &Vec<Value>| -> Vec<SpokenWord> {
+        items
+            .iter()
+            .filter_map(|w| {
+                Some(SpokenWord {
+                    word: w["word"].as_str()?.trim().to_string(),
+                    start: w["start"].as_f64()?,
+                    end: w["end"].as_f64()?,
+                    probability: w["probability"].as_f64(),
+                })
+            })
+            .filter(|w| !normalize(&w.word).is_empty())
+            .collect()
+    };
+
+    if let Some(words) = response["words"].as_array() {
+        return parse(words);
+    }
+    response["segments"]
+        .as_array()
+        .map(|segments| {
+            segments
+                .iter()
+                .filter_map(|s| s["words"].as_array())
+                .flat_map(parse)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Minúsculas y solo caracteres alfanuméricos o apóstrofos.
+fn normalize(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric() || *c == '\'')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Similitud de 0 a 1 basada en la distancia de Levenshtein entre caracteres.
+fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
+
+enum Step {
+    Match(usize, usize),
+    Omit(usize),
+    Insert(usize),
+}
+
+/// Alineación de mínimo coste entre la referencia y lo reconocido.
+fn align(expected: &[String], spoken: &[String]) -> Vec<Step> {
+    let (n, m) = (expected.len(), spoken.len());
+    let mut cost = vec![vec![0.0_f64; m + 1]; n + 1];
+    for (i, row) in cost.iter_mut().enumerate() {
+        row[0] = i as f64;
+    }
+    for (j, cell) in cost[0].iter_mut().enumerate() {
+        *cell = j as f64;
+    }
+    for i in 1..=n {
+        for j in 1..=m {
+            let sim = similarity(&expected[i - 1], &spoken[j - 1]);
+            let substitution = if sim >= MIN_MATCH_SIMILARITY { 1.0 - sim } else { f64::INFINITY };
+            cost[i][j] = (cost[i - 1][j - 1] + substitution)
+                .min(cost[i - 1][j] + 1.0)
+                .min(cost[i][j - 1] + 1.0);
+        }
+    }
+
+    let mut steps = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        if i > 0 && j > 0 {
+            let sim = similarity(&expected[i - 1], &spoken[j - 1]);
+            if sim >= MIN_MATCH_SIMILARITY && (cost[i][j] - (cost[i - 1][j - 1] + 1.0 - sim)).abs() < 1e-9 {
+                steps.push(Step::Match(i - 1, j - 1));
+                i -= 1;
+                j -= 1;
+                continue;
+            }
+        }
+        if i > 0 && (j == 0 || (cost[i][j] - (cost[i - 1][j] + 1.0)).abs() < 1e-9) {
+            steps.push(Step::Omit(i - 1));
+            i -= 1;
+        } else {
+            steps.push(Step::Insert(j - 1));
+            j -= 1;
+        }
+    }
+    steps.reverse();
+    steps
+}
+
+/// 100 dentro del rango natural y decae linealmente hasta 0 a 60 ppm de distancia.
+fn rate_score(wpm: f64) -> f64 {
+    let (low, high) = NATURAL_RATE_WPM;
+    let distance = if wpm < low {
+        low - wpm
+    } else if wpm > high {
+        wpm - high
+    } else {
+        0.0
+    };
+    (100.0 - distance * 100.0 / 60.0).max(0.0)
+}
+
+fn fluency(words: &[SpokenWord]) -> (f64, FluencyMetrics) {
+    let (Some(first), Some(last)) = (words.first(), words.last()) else {
+        return (
+            0.0,
+            FluencyMetrics {
+                speech_rate_wpm: 0.0,
+                speaking_seconds: 0.0,
+                pause_count: 0,
+                long_pause_count: 0,
+                total_pause_seconds: 0.0,
+            },
+        );
+    };
+
+    let span = (last.end - first.start).max(0.1);
+    let (mut pause_count, mut long_pause_count, mut total_pause_seconds) = (0, 0, 0.0);
+    for pair in words.windows(2) {
+        let gap = pair[1].start - pair[0].end;
+        if gap >= PAUSE_SECONDS {
+            pause_count += 1;
+            total_pause_seconds += gap;
+            if gap >= LONG_PAUSE_SECONDS {
+                long_pause_count += 1;
+            }
+        }
+    }
+    let speech_rate_wpm = words.len() as f64 * 60.0 / span;
+    let pause_ratio = total_pause_seconds / span;
+    let score = (rate_score(speech_rate_wpm) * (1.0 - pause_ratio) - 10.0 * long_pause_count as f64).clamp(0.0, 100.0);
+
+    (
+        score,
+        FluencyMetrics {
+            speech_rate_wpm: round1(speech_rate_wpm),
+            speaking_seconds: round1(span - total_pause_seconds),
+            pause_count,
+            long_pause_count,
+            total_pause_seconds: round1(total_pause_seconds),
+        },
+    )
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+/// Evalúa la pronunciación de `spoken` frente al texto de referencia.
+pub fn assess(expected_text: &str, spoken: &[SpokenWord]) -> PronunciationAssessment {
+    let expected: Vec<String> = expected_text
+        .split_whitespace()
+        .map(normalize)
+        .filter(|w| !w.is_empty())
+        .collect();
+    let recognized: Vec<String> = spoken.iter().map(|w| normalize(&w.word)).collect();
+
+    let mut words = Vec::with_capacity(expected.len());
+    let mut matched_accuracy = Vec::new();
+    for step in align(&expected, &recognized) {
+        words.push(match step {
+            Step::Match(i, j) => {
+                let word = &spoken[j];
+                let accuracy = round1(similarity(&expected[i], &recognized[j]) * word.probability.unwrap_or(1.0) * 100.0);
+                matched_accuracy.push(accuracy);
+                WordAssessment {
+                    expected: Some(expected[i].clone()),
+                    spoken: Some(word.word.clone()),
+                    start: Some(word.start),
+                    end: Some(word.end),
+                    accuracy,
+                    error: if accuracy >= CORRECT_ACCURACY { "correct" } else { "mispronounced" }.to_string(),
+                }
+            }
+            Step::Omit(i) => WordAssessment {
+                expected: Some(expected[i].clone()),
+                spoken: None,
+                start: None,
+                end: None,
+                accuracy: 0.0,
+                error: "omitted".to_string(),
+            },
+            Step::Insert(j) => WordAssessment {
+                expected: None,
+                spoken: Some(spoken[j].word.clone()),
+                start: Some(spoken[j].start),
+                end: Some(spoken[j].end),
+                accuracy: 0.0,
+                error: "inserted".to_string(),
+            },
+        });
+    }
+
+    let accuracy = if matched_accuracy.is_empty() {
+        0.0
+    } else {
+        matched_accuracy.iter().sum::<f64>() / matched_accuracy.len() as f64
+    };
+    let completeness = if expected.is_empty() {
+        0.0
+    } else {
+        matched_accuracy.len() as f64 * 100.0 / expected.len() as f64
+    };
+    let (fluency_score, fluency_metrics) = fluency(spoken);
+    let score = 0.5 * accuracy + 0.25 * fluency_score + 0.25 * completeness;
+
+    PronunciationAssessment {
+        expected_text: expected_text.trim().to_string(),
+        score: round1(score),
+        accuracy: round1(accuracy),
+        fluency: round1(fluency_score),
+        completeness: round1(completeness),
+        fluency_metrics,
+        words,
+    }
+}
+
+/// Resumen breve para dar contexto al modelo que redacta la retroalimentación.
+pub fn summary(assessment: &PronunciationAssessment) -> String {
+    let list = |error: &str| -> Vec<String> {
+        assessment
+            .words
+            .iter()
+            .filter(|w| w.error == error)
+            .filter_map(|w| w.expected.clone().or_else(|| w.spoken.clone()))
+            .collect()
+    };
+    format!(
+        "Accuracy {:.0}, fluency {:.0} ({:.0} wpm, {} pauses), completeness {:.0}. Mispronounced: {:?}. Omitted: {:?}. Inserted: {:?}",
+        assessment.accuracy,
+        assessment.fluency,
+        assessment.fluency_metrics.speech_rate_wpm,
+        assessment.fluency_metrics.pause_count,
+        assessment.completeness,
+        list("mispronounced"),
+        list("omitted"),
+        list("inserted"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn aligns_words_and_flags_errors() {
+        let response = json!({
+            "text": "The quick brun fox um",
+            "words": [
+                { "word": " The", "start": 0.0, "end": 0.3 },
+                { "word": " quick", "start": 0.35, "end": 0.7 },
+                { "word": " brun", "start": 0.75, "end": 1.1 },
+                { "word": " fox", "start": 1.15, "end": 1.5 },
+                { "word": " um.", "start": 3.2, "end": 3.5 }
+            ]
+        });
+        let spoken = words_from_whisper(&response);
+        let result = assess("The quick brown fox jumps", &spoken);
+
+        let errors: Vec<&str> = result.words.iter().map(|w| w.error.as_str()).collect();
+        assert_eq!(errors, ["correct", "correct", "mispronounced", "correct", "inserted", "omitted"]);
+        assert_eq!(result.completeness, 80.0);
+        assert_eq!(result.fluency_metrics.pause_count, 1);
+        assert_eq!(result.fluency_metrics.long_pause_count, 1);
+        assert!(result.fluency < 100.0);
+    }
+
+    #[test]
+    fn reads_segment_words_with_probability() {
+        let response = json!({
+            "segments": [
+                { "words": [
+                    { "word": "Hello", "start": 0.0, "end": 0.4, "probability": 0.5 },
+                    { "word": "world", "start": 0.45, "end": 0.8, "probability": 1.0 }
+                ] }
+            ]
+        });
+        let result = assess("Hello, world!", &words_from_whisper(&response));
+        assert_eq!(result.words[0].accuracy, 50.0);
+        assert_eq!(result.words[0].error, "mispronounced");
+        assert_eq!(result.completeness, 100.0);
+        assert_eq!(result.accuracy, 75.0);
+    }
+}
@@ -0,0 +1,1039 @@
+/// Psicometría de evaluaciones a partir de `quiz_item_responses`.
+///
+/// - TRI 1PL (Rasch) y 2PL estimados por máxima verosimilitud conjunta (JML).
+/// - Correlación punto-biserial corregida (ítem vs. puntaje del resto del test).
+/// - Alfa de Cronbach por evaluación.
+/// - Análisis de distractores (opciones que nadie elige).
+///
+/// Se usa el primer intento de cada alumno: los reintentos están contaminados por la
+/// retroalimentación recibida y sesgarían la dificultad hacia abajo.
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use common::{auth::Claims, middleware::Org};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+/// Respuestas mínimas para reportar la calibración de un ítem del banco.
+const DEFAULT_MIN_RESPONSES: i64 = 30;
+/// Respuestas mínimas para afirmar que un distractor no funciona.
+const MIN_DISTRACTOR_SAMPLE: i64 = 20;
+const MAX_ITERATIONS: usize = 200;
+const CONVERGENCE_TOLERANCE: f64 = 1e-4;
+const THETA_BOUND: f64 = 6.0;
+const DISCRIMINATION_BOUNDS: (f64, f64) = (0.2, 4.0);
+
+// ─── Estimación TRI ──────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrtModel {
+    OnePl,
+    TwoPl,
+}
+
+impl IrtModel {
+    pub fn parse(value: Option<&str>) -> Self {
+        match value {
+            Some("1pl") | Some("rasch") => IrtModel::OnePl,
+            _ => IrtModel::TwoPl,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IrtModel::OnePl => "1pl",
+            IrtModel::TwoPl => "2pl",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ItemParams {
+    pub discrimination: f64,
+    pub difficulty: f64,
+    pub se_difficulty: f64,
+}
+
+#[derive(Debug)]
+pub struct IrtFit {
+    /// `None` para ítems sin variabilidad (todos aciertan o todos fallan).
+    pub items: Vec<Option<ItemParams>>,
+    pub converged: bool,
+    pub iterations: usize,
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+fn logit(p: f64) -> f64 {
+    (p / (1.0 - p)).ln()
+}
+
+/// Ajusta un modelo 1PL/2PL sobre respuestas dicotómicas `(persona, ítem, acierto)`.
+/// Los datos pueden ser dispersos: cada persona solo aporta los ítems que respondió.
+pub fn fit_irt(n_persons: usize, n_items: usize, responses: &[(usize, usize, bool)], model: IrtModel) -> IrtFit {
+    // Excluir iterativamente puntajes extremos, cuya estimación JML diverge
+    let mut person_active = vec![true; n_persons];
+    let mut item_active = vec![true; n_items];
+    loop {
+        let mut p_counts = vec![(0usize, 0usize); n_persons];
+        let mut i_counts = vec![(0usize, 0usize); n_items];
+        for &(p, i, u) in responses {
+            if person_active[p] && item_active[i] {
+                p_counts[p].1 += 1;
+                i_counts[i].1 += 1;
+                if u {
+                    p_counts[p].0 += 1;
+                    i_counts[i].0 += 1;
+                }
+            }
+        }
+        let mut changed = false;
+        for (i, &(c, t)) in i_counts.iter().enumerate() {
+            if item_active[i] && (t < 2 || c == 0 || c == t) {
+                item_active[i] = false;
+                changed = true;
+            }
+        }
+        for (p, &(c, t)) in p_counts.iter().enumerate() {
+            if person_active[p] && (t == 0 || c == 0 || c == t) {
+                person_active[p] = false;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let active: Vec<(usize, usize, f64)> = responses
+        .iter()
+        .filter(|(p, i, _)| person_active[*p] && item_active[*i])
+        .map(|&(p, i, u)| (p, i, if u { 1.0 } else { 0.0 }))
+        .collect();
+
+    // Valores iniciales a partir de las proporciones observadas
+    let mut theta = vec![0.0; n_persons];
+    let mut a = vec![1.0; n_items];
+    let mut b = vec![0.0; n_items];
+    {
+        let mut p_sum = vec![(0.0, 0.0); n_persons];
+        let mut i_sum = vec![(0.0, 0.0); n_items];
+        for &(p, i, u) in &active {
+            p_sum[p].0 += u;
+            p_sum[p].1 += 1.0;
+            i_sum[i].0 += u;
+            i_sum[i].1 += 1.0;
+        }
+        for (p, (c, t)) in p_sum.into_iter().enumerate() {
+            if t > 0.0 {
+                theta[p] = logit((c + 0.5) / (t + 1.0));
+            }
+        }
+        for (i, (c, t)) in i_sum.into_iter().enumerate() {
+            if t > 0.0 {
+                b[i] = -logit((c + 0.5) / (t + 1.0));
+            }
+        }
+    }
+
+    let mut converged = active.is_empty();
+    let mut iterations = 0;
+    while !converged && iterations < MAX_ITERATIONS {
+        iterations += 1;
+        let mut max_change: f64 = 0.0;
+
+        // Paso de Fisher scoring para los parámetros de ítems
+        let mut g_a = vec![0.0; n_items];
+        let mut g_b = vec![0.0; n_items];
+        let mut i_aa = vec![0.0; n_items];
+        let mut i_bb = vec![0.0; n_items];
+        let mut i_ab = vec![0.0; n_items];
+        for &(p, i, u) in &active {
+            let d = theta[p] - b[i];
+            let prob = sigmoid(a[i] * d);
+            let r = u - prob;
+            let w = prob * (1.0 - prob);
+            g_a[i] += r * d;
+            g_b[i] += -a[i] * r;
+            i_aa[i] += w * d * d;
+            i_bb[i] += a[i] * a[i] * w;
+            i_ab[i] += -a[i] * w * d;
+        }
+        for i in 0..n_items {
+            if !item_active[i] || i_bb[i] <= 0.0 {
+                continue;
+            }
+            let (mut step_a, mut step_b) = (0.0, g_b[i] / i_bb[i]);
+            if model == IrtModel::TwoPl {
+                let det = i_aa[i] * i_bb[i] - i_ab[i] * i_ab[i];
+                if det > 1e-9 {
+                    step_a = (i_bb[i] * g_a[i] - i_ab[i] * g_b[i]) / det;
+                    step_b = (i_aa[i] * g_b[i] - i_ab[i] * g_a[i]) / det;
+                }
+            }
+            let new_a = (a[i] + step_a.clamp(-1.0, 1.0)).clamp(DISCRIMINATION_BOUNDS.0, DISCRIMINATION_BOUNDS.1);
+            let new_b = (b[i] + step_b.clamp(-1.0, 1.0)).clamp(-THETA_BOUND, THETA_BOUND);
+            max_change = max_change.max((new_a - a[i]).abs()).max((new_b - b[i]).abs());
+            a[i] = new_a;
+            b[i] = new_b;
+        }
+
+        // Paso de Newton para las habilidades
+        let mut g_t = vec![0.0; n_persons];
+        let mut i_tt = vec![0.0; n_persons];
+        for &(p, i, u) in &active {
+            let prob = sigmoid(a[i] * (theta[p] - b[i]));
+            g_t[p] += a[i] * (u - prob);
+            i_tt[p] += a[i] * a[i] * prob * (1.0 - prob);
+        }
+        for p in 0..n_persons {
+            if !person_active[p] || i_tt[p] <= 0.0 {
+                continue;
+            }
+            let new_theta = (theta[p] + (g_t[p] / i_tt[p]).clamp(-1.0, 1.0)).clamp(-THETA_BOUND, THETA_BOUND);
+            max_change = max_change.max((new_theta - theta[p]).abs());
+            theta[p] = new_theta;
+        }
+
+        // Identificación de la escala: habilidades con media 0 (y desviación 1 en 2PL)
+        let active_thetas: Vec<f64> = (0..n_persons).filter(|p| person_active[*p]).map(|p| theta[p]).collect();
+        if !active_thetas.is_empty() {
+            let mean = active_thetas.iter().sum::<f64>() / active_thetas.len() as f64;
+            let sd = if model == IrtModel::TwoPl && active_thetas.len() > 1 {
+                let var = active_thetas.iter().map(|t| (t - mean).powi(2)).sum::<f64>()
+                    / (active_thetas.len() - 1) as f64;
+                if var > 1e-9 { var.sqrt() } else { 1.0 }
+            } else {
+                1.0
+            };
+            for p in (0..n_persons).filter(|p| person_active[*p]) {
+                theta[p] = (theta[p] - mean) / sd;
+            }
+            for i in (0..n_items).filter(|i| item_active[*i]) {
+                b[i] = (b[i] - mean) / sd;
+                a[i] = (a[i] * sd).clamp(DISCRIMINATION_BOUNDS.0, DISCRIMINATION_BOUNDS.1);
+            }
+        }
+
+        converged = max_change < CONVERGENCE_TOLERANCE;
+    }
+
+    // Error estándar de la dificultad a partir de la información de Fisher
+    let mut info_b = vec![0.0; n_items];
+    for &(p, i, _) in &active {
+        let prob = sigmoid(a[i] * (theta[p] - b[i]));
+        info_b[i] += a[i] * a[i] * prob * (1.0 - prob);
+    }
+
+    // Corrección de sesgo JML de Wright para Rasch: (L - 1) / L
+    let n_active_items = item_active.iter().filter(|x| **x).count();
+    let bias = if model == IrtModel::OnePl && n_active_items > 1 {
+        (n_active_items as f64 - 1.0) / n_active_items as f64
+    } else {
+        1.0
+    };
+
+    let items = (0..n_items)
+        .map(|i| {
+            (item_active[i] && info_b[i] > 0.0).then(|| ItemParams {
+                discrimination: a[i],
+                difficulty: b[i] * bias,
+                se_difficulty: 1.0 / info_b[i].sqrt(),
+            })
+        })
+        .collect();
+
+    IrtFit {
+        items,
+        converged,
+        iterations,
+    }
+}
+
+// ─── Teoría clásica ──────────────────────────────────────────────────────────
+
+fn pearson(x: &[f64], y: &[f64]) -> Option<f64> {
+    let n = x.len();
+    if n < 3 || n != y.len() {
+        return None;
+    }
+    let mx = x.iter().sum::<f64>() / n as f64;
+    let my = y.iter().sum::<f64>() / n as f64;
+    let (mut sxy, mut sxx, mut syy) = (0.0, 0.0, 0.0);
+    for (xi, yi) in x.iter().zip(y) {
+        sxy += (xi - mx) * (yi - my);
+        sxx += (xi - mx).powi(2);
+        syy += (yi - my).powi(2);
+    }
+    if sxx <= 0.0 || syy <= 0.0 {
+        return None;
+    }
+    Some(sxy / (sxx.sqrt() * syy.sqrt()))
+}
+
+/// Alfa de Cronbach sobre una matriz completa (filas = personas, columnas = ítems).
+pub fn cronbach_alpha(matrix: &[Vec<f64>]) -> Option<f64> {
+    let k = matrix.first()?.len();
+    if k < 2 || matrix.len() < 3 {
+        return None;
+    }
+    let variance = |values: &[f64]| -> f64 {
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)
+    };
+
+    let item_variances: f64 = (0..k)
+        .map(|j| variance(&matrix.iter().map(|row| row[j]).collect::<Vec<_>>()))
+        .sum();
+    let total_variance = variance(&matrix.iter().map(|row| row.iter().sum()).collect::<Vec<f64>>());
+    if total_variance <= 0.0 {
+        return None;
+    }
+    Some(k as f64 / (k as f64 - 1.0) * (1.0 - item_variances / total_variance))
+}
+
+// ─── Análisis por ítem ───────────────────────────────────────────────────────
+
+/// Respuesta ya indexada por persona e ítem.
+pub struct ScoredResponse {
+    pub person: usize,
+    pub item: usize,
+    pub score: f64,
+    pub selected: Vec<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DistractorStat {
+    pub option_index: i64,
+    pub selections: i64,
+    pub rate: f64,
+    pub is_correct: bool,
+    pub never_selected: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ItemStats {
+    pub responses: i64,
+    pub p_value: f64,
+    pub point_biserial: Option<f64>,
+    pub irt_difficulty: Option<f64>,
+    pub irt_discrimination: Option<f64>,
+    pub irt_se_difficulty: Option<f64>,
+}
+
+struct Analysis {
+    items: Vec<ItemStats>,
+    converged: bool,
+}
+
+/// Estadísticos clásicos de un ítem.
+#[derive(Debug)]
+pub struct ClassicalStats {
+    pub responses: i64,
+    pub p_value: f64,
+    pub point_biserial: Option<f64>,
+}
+
+fn classical_stats(n_persons: usize, n_items: usize, responses: &[ScoredResponse]) -> Vec<ClassicalStats> {
+    let mut totals = vec![0.0; n_persons];
+    for r in responses {
+        totals[r.person] += r.score;
+    }
+
+    let mut per_item: Vec<(Vec<f64>, Vec<f64>)> = vec![(Vec::new(), Vec::new()); n_items];
+    for r in responses {
+        per_item[r.item].0.push(r.score);
+        per_item[r.item].1.push(totals[r.person] - r.score);
+    }
+
+    per_item
+        .iter()
+        .map(|(scores, rest)| ClassicalStats {
+            responses: scores.len() as i64,
+            p_value: if scores.is_empty() { 0.0 } else { scores.iter().sum::<f64>() / scores.len() as f64 },
+            point_biserial: pearson(scores, rest),
+        })
+        .collect()
+}
+
+/// Estadísticos clásicos por ítem de una evaluación a partir de respuestas
+/// `(alumno, item_id, puntaje)`, una por alumno e ítem.
+pub fn item_classical_stats(responses: Vec<(Uuid, String, f64)>) -> Vec<(String, ClassicalStats)> {
+    let rows = responses
+        .into_iter()
+        .map(|(user_id, item_key, score)| ResponseRow {
+            user_id,
+            item_key,
+            selected: None,
+            score: score as f32,
+        })
+        .collect();
+    let (keys, n_persons, responses) = index_responses(rows);
+    let stats = classical_stats(n_persons, keys.len(), &responses);
+    keys.into_iter().zip(stats).collect()
+}
+
+fn analyze(n_persons: usize, n_items: usize, responses: &[ScoredResponse], model: IrtModel) -> Analysis {
+    let classical = classical_stats(n_persons, n_items, responses);
+
+    let dichotomous: Vec<(usize, usize, bool)> =
+        responses.iter().map(|r| (r.person, r.item, r.score >= 0.5)).collect();
+    let fit = fit_irt(n_persons, n_items, &dichotomous, model);
+    tracing::debug!(
+        "psychometrics: {} {} personas × {} ítems, {} iteraciones (convergió: {})",
+        model.as_str(),
+        n_persons,
+        n_items,
+        fit.iterations,
+        fit.converged
+    );
+
+    let items = classical
+        .into_iter()
+        .zip(&fit.items)
+        .map(|(stats, params)| ItemStats {
+            responses: stats.responses,
+            p_value: stats.p_value,
+            point_biserial: stats.point_biserial,
+            irt_difficulty: params.map(|p| p.difficulty),
+            irt_discrimination: params.map(|p| p.discrimination),
+            irt_se_difficulty: params.map(|p| p.se_difficulty),
+        })
+        .collect();
+
+    Analysis {
+        items,
+        converged: fit.converged,
+    }
+}
+
+fn distractor_stats(option_count: usize, correct: &[i64], selections: &[Vec<i64>]) -> Vec<DistractorStat> {
+    let total = selections.len() as i64;
+    let mut counts = vec![0i64; option_count];
+    for selected in selections {
+        for &idx in selected {
+            if idx >= 0 && (idx as usize) < option_count {
+                counts[idx as usize] += 1;
+            }
+        }
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(idx, selections)| {
+            let is_correct = correct.contains(&(idx as i64));
+            DistractorStat {
+                option_index: idx as i64,
+                selections,
+                rate: if total > 0 { selections as f64 / total as f64 } else { 0.0 },
+                is_correct,
+                never_selected: !is_correct && selections == 0 && total >= MIN_DISTRACTOR_SAMPLE,
+            }
+        })
+        .collect()
+}
+
+// ─── Metadatos de las preguntas ──────────────────────────────────────────────
+
+#[derive(Debug, Clone, Default)]
+pub struct QuizItemMeta {
+    pub question: Option<String>,
+    pub option_count: usize,
+    pub correct: Vec<i64>,
+    pub question_bank_id: Option<Uuid>,
+}
+
+pub fn value_indices(value: &Value) -> Vec<i64> {
+    match value {
+        Value::Number(n) => n.as_i64().into_iter().collect(),
+        Value::Array(items) => items.iter().filter_map(|v| v.as_i64()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Preguntas de un cuestionario, tanto de plantillas (`metadata.questions`), de bloques
+/// (`metadata.blocks[].quiz_data.questions` y los pools `quiz_data.pool.items`) como del
+/// banco de una prueba adaptativa (`metadata.adaptive.pool`), indexadas por su `id`.
+pub fn quiz_items(metadata: &Value) -> HashMap<String, QuizItemMeta> {
+    let mut lists: Vec<&Vec<Value>> = Vec::new();
+    if let Some(questions) = metadata.get("questions").and_then(|q| q.as_array()) {
+        lists.push(questions);
+    }
+    if let Some(pool) = metadata.get("adaptive").and_then(|a| a.get("pool")).and_then(|p| p.as_array()) {
+        lists.push(pool);
+    }
+    if let Some(blocks) = metadata.get("blocks").and_then(|b| b.as_array()) {
+        lists.extend(
+            blocks
+                .iter()
+                .filter_map(|b| b.get("quiz_data").and_then(|q| q.get("questions")).and_then(|q| q.as_array())),
+        );
+        lists.extend(blocks.iter().filter_map(|b| {
+            b.get("quiz_data")
+                .and_then(|q| q.get("pool"))
+                .and_then(|p| p.get("items"))
+                .and_then(|i| i.as_array())
+        }));
+    }
+
+    let mut items = HashMap::new();
+    for question in lists.into_iter().flatten() {
+        let Some(id) = question.get("id").and_then(|v| match v {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        }) else {
+            continue;
+        };
+        items.insert(
+            id,
+            QuizItemMeta {
+                question: question.get("question").and_then(|q| q.as_str()).map(|s| s.to_string()),
+                option_count: question.get("options").and_then(|o| o.as_array()).map(|o| o.len()).unwrap_or(0),
+                correct: question.get("correct").map(value_indices).unwrap_or_default(),
+                question_bank_id: question
+                    .get("question_bank_id")
+                    .and_then(|v| v.as_str())
+                    .and_then(|s| Uuid::parse_str(s).ok()),
+            },
+        );
+    }
+    items
+}
+
+/// Respuesta a un ítem lista para guardar en `quiz_item_responses`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemResponse {
+    pub item_id: String,
+    pub question_bank_id: Option<Uuid>,
+    pub selected: Vec<i64>,
+    pub score: f64,
+}
+
+/// Acierto exacto contra la respuesta correcta conocida; `None` si la pregunta no la tiene.
+fn grade_selection(meta: &QuizItemMeta, selected: &[i64]) -> Option<f64> {
+    if meta.correct.is_empty() {
+        return None;
+    }
+    let mut expected = meta.correct.clone();
+    let mut given = selected.to_vec();
+    expected.sort_unstable();
+    given.sort_unstable();
+    Some(if expected == given { 1.0 } else { 0.0 })
+}
+
+/// Respuestas por ítem de una entrega. Acepta dos formatos:
+///
+/// - `item_responses: [{ "item_id": "...", "selected": 1 | [0, 2], "score": 0.0..1.0 }]`,
+///   que generan el servidor (variantes, pruebas adaptativas) y los exámenes. El `score`
+///   del cliente solo se usa si la pregunta no tiene respuesta correcta conocida;
+/// - `quiz_answers: { block_id: { "answers": { question_id: [índices] } } }`, que envía el
+///   reproductor de cuestionarios. Se califican contra la lección y las preguntas del
+///   bloque sin responder cuentan como fallo. El cliente reenvía el metadata completo
+///   al completar otros bloques, así que se omiten los bloques iguales a `previous`
+///   (el metadata guardado antes de esta entrega).
+pub fn submitted_responses(
+    lesson_metadata: Option<&Value>,
+    submission: &Value,
+    previous: Option<&Value>,
+) -> Vec<ItemResponse> {
+    let items = lesson_metadata.map(quiz_items).unwrap_or_default();
+
+    if let Some(responses) = submission.get("item_responses").and_then(|r| r.as_array()) {
+        return responses
+            .iter()
+            .filter_map(|response| {
+                let item_id = response.get("item_id").and_then(|v| v.as_str())?;
+                let meta = items.get(item_id).cloned().unwrap_or_default();
+                let selected = response.get("selected").map(value_indices).unwrap_or_default();
+                let score = match grade_selection(&meta, &selected).filter(|_| !selected.is_empty()) {
+                    Some(score) => score,
+                    None => response.get("score").and_then(|s| s.as_f64())?.clamp(0.0, 1.0),
+                };
+                Some(ItemResponse {
+                    item_id: item_id.to_string(),
+                    question_bank_id: meta.question_bank_id,
+                    selected,
+                    score,
+                })
+            })
+            .collect();
+    }
+
+    let Some(quiz_answers) = submission.get("quiz_answers").and_then(|a| a.as_object()) else {
+        return Vec::new();
+    };
+    let blocks = lesson_metadata
+        .and_then(|m| m.get("blocks"))
+        .and_then(|b| b.as_array())
+        .map(|b| b.as_slice())
+        .unwrap_or_default();
+
+    let mut responses = Vec::new();
+    let previous_answers = previous.and_then(|p| p.get("quiz_answers"));
+    for (block_id, entry) in quiz_answers {
+        if previous_answers.and_then(|p| p.get(block_id)) == Some(entry) {
+            continue;
+        }
+        let Some(answers) = entry.get("answers").and_then(|a| a.as_object()) else {
+            continue;
+        };
+        let block_questions: Vec<String> = blocks
+            .iter()
+            .find(|b| b.get("id").and_then(|id| id.as_str()) == Some(block_id.as_str()))
+            .and_then(|b| b.get("quiz_data"))
+            .and_then(|q| q.get("questions"))
+            .and_then(|q| q.as_array())
+            .map(|questions| {
+                questions
+                    .iter()
+                    .filter_map(|q| match q.get("id")? {
+                        Value::String(s) => Some(s.clone()),
+                        Value::Number(n) => Some(n.to_string()),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_else(|| answers.keys().cloned().collect());
+
+        for item_id in block_questions {
+            let Some(meta) = items.get(&item_id) else {
+                continue;
+            };
+            let selected = answers.get(&item_id).map(value_indices).unwrap_or_default();
+            if let Some(score) = grade_selection(meta, &selected) {
+                responses.push(ItemResponse {
+                    item_id,
+                    question_bank_id: meta.question_bank_id,
+                    selected,
+                    score,
+                });
+            }
+        }
+    }
+    responses
+}
+
+/// Registra en `quiz_item_responses` las respuestas por ítem de una entrega
+/// (ver [`submitted_responses`]).
+#[allow(clippy::too_many_arguments)]
+pub async fn record_item_responses(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    org_id: Uuid,
+    user_id: Uuid,
+    course_id: Uuid,
+    lesson_id: Uuid,
+    attempt: i32,
+    submission: &Value,
+    previous: Option<&Value>,
+) -> Result<(), sqlx::Error> {
+    if submission.get("item_responses").is_none() && submission.get("quiz_answers").is_none() {
+        return Ok(());
+    }
+
+    let lesson_metadata: Option<Value> = sqlx::query_scalar("SELECT metadata FROM lessons WHERE id = $1")
+        .bind(lesson_id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .flatten();
+
+    for response in submitted_responses(lesson_metadata.as_ref(), submission, previous) {
+        sqlx::query(
+            r#"
+            INSERT INTO quiz_item_responses
+                (organization_id, user_id, course_id, lesson_id, item_id, question_bank_id, attempt, selected, score)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (user_id, lesson_id, item_id, attempt) DO UPDATE SET
+                selected = EXCLUDED.selected,
+                score = EXCLUDED.score
+            "#,
+        )
+        .bind(org_id)
+        .bind(user_id)
+        .bind(course_id)
+        .bind(lesson_id)
+        .bind(&response.item_id)
+        .bind(response.question_bank_id)
+        .bind(attempt)
+        .bind(serde_json::json!(response.selected))
+        .bind(response.score as f32)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+// ─── Handlers ────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct PsychometricsQuery {
+    pub model: Option<String>,
+    pub min_responses: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ItemPsychometrics {
+    pub item_id: String,
+    pub question_bank_id: Option<Uuid>,
+    pub question: Option<String>,
+    #[serde(flatten)]
+    pub stats: ItemStats,
+    pub distractors: Vec<DistractorStat>,
+    pub flags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AssessmentPsychometrics {
+    pub lesson_id: Uuid,
+    pub model: String,
+    pub respondents: i64,
+    /// Alumnos que respondieron todos los ítems (base del alfa).
+    pub complete_respondents: i64,
+    pub cronbach_alpha: Option<f64>,
+    pub converged: bool,
+    pub items: Vec<ItemPsychometrics>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ItemCalibration {
+    pub question_bank_id: Uuid,
+    pub model: String,
+    pub responses: i64,
+    pub p_value: f64,
+    pub point_biserial: Option<f64>,
+    pub difficulty: f64,
+    pub discrimination: f64,
+    pub se_difficulty: Option<f64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ResponseRow {
+    user_id: Uuid,
+    item_key: String,
+    selected: Option<Value>,
+    score: f32,
+}
+
+fn index_responses(rows: Vec<ResponseRow>) -> (Vec<String>, usize, Vec<ScoredResponse>) {
+    let mut persons: HashMap<Uuid, usize> = HashMap::new();
+    let mut item_index: BTreeMap<String, usize> = BTreeMap::new();
+    for row in &rows {
+        let next = item_index.len();
+        item_index.entry(row.item_key.clone()).or_insert(next);
+    }
+    let responses = rows
+        .into_iter()
+        .map(|row| {
+            let next = persons.len();
+            let person = *persons.entry(row.user_id).or_insert(next);
+            ScoredResponse {
+                person,
+                item: item_index[&row.item_key],
+                score: row.score as f64,
+                selected: row.selected.as_ref().map(value_indices).unwrap_or_default(),
+            }
+        })
+        .collect();
+
+    let mut keys = vec![String::new(); item_index.len()];
+    for (key, idx) in item_index {
+        keys[idx] = key;
+    }
+    (keys, persons.len(), responses)
+}
+
+fn item_flags(stats: &ItemStats, distractors: &[DistractorStat]) -> Vec<String> {
+    let mut flags = Vec::new();
+    if stats.irt_difficulty.is_none() {
+        flags.push("not_calibrated".to_string());
+    }
+    if stats.p_value > 0.95 {
+        flags.push("too_easy".to_string());
+    }
+    if stats.p_value < 0.05 {
+        flags.push("too_hard".to_string());
+    }
+    match stats.point_biserial {
+        Some(r) if r < 0.0 => flags.push("negative_discrimination".to_string()),
+        Some(r) if r < 0.2 => flags.push("low_discrimination".to_string()),
+        _ => {}
+    }
+    if distractors.iter().any(|d| d.never_selected) {
+        flags.push("non_functioning_distractor".to_string());
+    }
+    flags
+}
+
+/// GET /lessons/{id}/psychometrics?model=1pl|2pl  (instructor/admin)
+pub async fn get_lesson_psychometrics(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(lesson_id): Path<Uuid>,
+    Query(query): Query<PsychometricsQuery>,
+) -> Result<Json<AssessmentPsychometrics>, (StatusCode, String)> {
+    if claims.role != "admin" && claims.role != "instructor" {
+        return Err((StatusCode::FORBIDDEN, "Se requiere rol instructor o admin".to_string()));
+    }
+
+    let lesson: Option<(Uuid, Option<Value>)> = sqlx::query_as(
+        "SELECT m.course_id, l.metadata FROM lessons l JOIN modules m ON m.id = l.module_id WHERE l.id = $1",
+    )
+    .bind(lesson_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let (course_id, metadata) = lesson.ok_or((StatusCode::NOT_FOUND, "Lección no encontrada".to_string()))?;
+
+    if claims.role == "instructor"
+        && !crate::handlers::instructor_has_course_access(&pool, org_ctx.id, claims.sub, course_id)
+            .await
+            .map_err(|status| (status, "Error interno del servidor".to_string()))?
+    {
+        return Err((StatusCode::FORBIDDEN, "No tienes acceso a este curso".to_string()));
+    }
+
+    let model = IrtModel::parse(query.model.as_deref());
+    let rows = sqlx::query_as::<_, ResponseRow>(
+        r#"
+        SELECT DISTINCT ON (user_id, item_id)
+               user_id, item_id AS item_key, selected, score
+        FROM quiz_item_responses
+        WHERE lesson_id = $1 AND organization_id = $2
+        ORDER BY user_id, item_id, attempt ASC
+        "#,
+    )
+    .bind(lesson_id)
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let items_meta = metadata.as_ref().map(quiz_items).unwrap_or_default();
+    let (keys, n_persons, responses) = index_responses(rows);
+    let analysis = analyze(n_persons, keys.len(), &responses, model);
+
+    // Matriz completa para el alfa: solo alumnos que respondieron todos los ítems
+    let mut matrix: Vec<Vec<Option<f64>>> = vec![vec![None; keys.len()]; n_persons];
+    let mut selections: Vec<Vec<Vec<i64>>> = vec![Vec::new(); keys.len()];
+    for r in &responses {
+        matrix[r.person][r.item] = Some(r.score);
+        selections[r.item].push(r.selected.clone());
+    }
+    let complete: Vec<Vec<f64>> = matrix
+        .into_iter()
+        .filter_map(|row| row.into_iter().collect::<Option<Vec<f64>>>())
+        .collect();
+
+    let items = keys
+        .into_iter()
+        .zip(analysis.items)
+        .zip(selections)
+        .map(|((item_id, stats), selected)| {
+            let meta = items_meta.get(&item_id).cloned().unwrap_or_default();
+            let distractors = distractor_stats(meta.option_count, &meta.correct, &selected);
+            ItemPsychometrics {
+                flags: item_flags(&stats, &distractors),
+                item_id,
+                question_bank_id: meta.question_bank_id,
+                question: meta.question,
+                stats,
+                distractors,
+            }
+        })
+        .collect();
+
+    Ok(Json(AssessmentPsychometrics {
+        lesson_id,
+        model: model.as_str().to_string(),
+        respondents: n_persons as i64,
+        complete_respondents: complete.len() as i64,
+        cronbach_alpha: cronbach_alpha(&complete),
+        converged: analysis.converged,
+        items,
+    }))
+}
+
+/// GET /psychometrics/calibration?model=1pl|2pl&min_responses=30  (instructor/admin)
+/// Calibración conjunta de los ítems del banco de preguntas de la organización usando
+/// todas las evaluaciones donde aparecen. La consume el CMS para guardarla en `question_bank`.
+pub async fn get_question_bank_calibration(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Query(query): Query<PsychometricsQuery>,
+) -> Result<Json<Vec<ItemCalibration>>, (StatusCode, String)> {
+    if claims.role != "admin" && claims.role != "instructor" {
+        return Err((StatusCode::FORBIDDEN, "Se requiere rol instructor o admin".to_string()));
+    }
+
+    let model = IrtModel::parse(query.model.as_deref());
+    let min_responses = query.min_responses.unwrap_or(DEFAULT_MIN_RESPONSES).max(1);
+
+    let rows = sqlx::query_as::<_, ResponseRow>(
+        r#"
+        SELECT DISTINCT ON (user_id, question_bank_id)
+               user_id, question_bank_id::text AS item_key, selected, score
+        FROM quiz_item_responses
+        WHERE organization_id = $1 AND question_bank_id IS NOT NULL
+        ORDER BY user_id, question_bank_id, created_at ASC
+        "#,
+    )
+    .bind(org_ctx.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let (keys, n_persons, responses) = index_responses(rows);
+    let analysis = analyze(n_persons, keys.len(), &responses, model);
+
+    let calibrations = keys
+        .into_iter()
+        .zip(analysis.items)
+        .filter(|(_, stats)| stats.responses >= min_responses)
+        .filter_map(|(key, stats)| {
+            Some(ItemCalibration {
+                question_bank_id: Uuid::parse_str(&key).ok()?,
+                model: model.as_str().to_string(),
+                responses: stats.responses,
+                p_value: stats.p_value,
+                point_biserial: stats.point_biserial,
+                difficulty: stats.irt_difficulty?,
+                discrimination: stats.irt_discrimination?,
+                se_difficulty: stats.irt_se_difficulty,
+            })
+        })
+        .collect();
+
+    Ok(Json(calibrations))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cronbach_alpha_of_parallel_items_is_one() {
+        let matrix = vec![
+            vec![0.0, 0.0, 0.0],
+            vec![1.0, 1.0, 1.0],
+            vec![0.0, 0.0, 0.0],
+            vec![1.0, 1.0, 1.0],
+        ];
+        assert!((cronbach_alpha(&matrix).unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn irt_orders_items_by_difficulty() {
+        // Ítem k lo aciertan las personas con habilidad > k (patrón de Guttman con ruido)
+        let mut responses = Vec::new();
+        for person in 0..40usize {
+            let ability = person % 10;
+            for item in 0..4usize {
+                let threshold = item * 2 + 1;
+                let noise = (person * 7 + item * 3) % 11 == 0;
+                responses.push((person, item, (ability > threshold) != noise));
+            }
+        }
+
+        for model in [IrtModel::OnePl, IrtModel::TwoPl] {
+            let fit = fit_irt(40, 4, &responses, model);
+            let b: Vec<f64> = fit.items.iter().map(|p| p.unwrap().difficulty).collect();
+            assert!(b.windows(2).all(|w| w[0] < w[1]), "{:?}: {:?}", model, b);
+        }
+    }
+
+    fn block_lesson() -> Value {
+        serde_json::json!({
+            "blocks": [{
+                "id": "b1",
+                "type": "quiz",
+                "quiz_data": {"questions": [
+                    {"id": "q1", "options": ["a", "b"], "correct": [1], "question_bank_id": "8f6d3c8e-2f5b-4c7a-9a43-3f1d2c6b7e10"},
+                    {"id": "q2", "options": ["a", "b", "c"], "correct": [0, 2]},
+                    {"id": "q3", "options": ["a", "b"], "correct": 0}
+                ]}
+            }]
+        })
+    }
+
+    #[test]
+    fn quiz_answers_are_graded_on_the_server() {
+        let lesson = block_lesson();
+        let submission = serde_json::json!({
+            "quiz_answers": {"b1": {"answers": {"q1": [1], "q2": [2, 0]}, "submitted_at": "2026-01-01T00:00:00Z"}}
+        });
+
+        let mut responses = submitted_responses(Some(&lesson), &submission, None);
+        responses.sort_by(|a, b| a.item_id.cmp(&b.item_id));
+        let scores: Vec<(&str, f64)> = responses.iter().map(|r| (r.item_id.as_str(), r.score)).collect();
+        // q3 sin responder cuenta como fallo
+        assert_eq!(scores, vec![("q1", 1.0), ("q2", 1.0), ("q3", 0.0)]);
+        assert_eq!(
+            responses[0].question_bank_id,
+            Some(Uuid::parse_str("8f6d3c8e-2f5b-4c7a-9a43-3f1d2c6b7e10").unwrap())
+        );
+        assert!(responses[2].selected.is_empty());
+    }
+
+    #[test]
+    fn resent_quiz_answers_are_not_recorded_again() {
+        let lesson = block_lesson();
+        let stored = serde_json::json!({
+            "quiz_answers": {"b1": {"answers": {"q1": [0]}, "submitted_at": "2026-01-01T00:00:00Z"}}
+        });
+        let resent = serde_json::json!({
+            "quiz_answers": stored["quiz_answers"].clone(),
+            "block_scores": {"b2": 1.0}
+        });
+        assert!(submitted_responses(Some(&lesson), &resent, Some(&stored)).is_empty());
+
+        let retried = serde_json::json!({
+            "quiz_answers": {"b1": {"answers": {"q1": [0]}, "submitted_at": "2026-01-02T00:00:00Z"}}
+        });
+        assert_eq!(submitted_responses(Some(&lesson), &retried, Some(&stored)).len(), 3);
+    }
+
+    #[test]
+    fn item_responses_use_the_client_score_only_without_a_key() {
+        let lesson = block_lesson();
+        let submission = serde_json::json!({
+            "item_responses": [
+                {"item_id": "q1", "selected": [0], "score": 1.0},
+                {"item_id": "essay", "score": 0.75},
+                {"item_id": "missing"}
+            ]
+        });
+
+        let responses = submitted_responses(Some(&lesson), &submission, None);
+        let scores: Vec<(&str, f64)> = responses.iter().map(|r| (r.item_id.as_str(), r.score)).collect();
+        assert_eq!(scores, vec![("q1", 0.0), ("essay", 0.75)]);
+    }
+
+    #[test]
+    fn classical_stats_rank_discriminating_items_higher() {
+        let student = |n: u128| Uuid::from_u128(n);
+        let mut responses = Vec::new();
+        for n in 0..6u128 {
+            let strong = n >= 3;
+            // "good" lo aciertan los alumnos fuertes; "noise" no depende de la habilidad
+            responses.push((student(n), "good".to_string(), if strong { 1.0 } else { 0.0 }));
+            responses.push((student(n), "anchor".to_string(), if strong { 1.0 } else { 0.0 }));
+            responses.push((student(n), "noise".to_string(), if n % 2 == 0 { 1.0 } else { 0.0 }));
+        }
+
+        let stats: HashMap<String, ClassicalStats> = item_classical_stats(responses).into_iter().collect();
+        assert_eq!(stats["good"].responses, 6);
+        assert!((stats["good"].p_value - 0.5).abs() < 1e-9);
+        assert!(stats["good"].point_biserial.unwrap() > stats["noise"].point_biserial.unwrap());
+    }
+}
@@ -0,0 +1,651 @@
+/// Variantes de cuestionario por alumno desde pools del banco de preguntas.
+///
+/// Un bloque de cuestionario con `quiz_data.pool = { count, shuffle_options, items }`
+/// (los `items` los copia el CMS al publicar) no muestra preguntas fijas: para cada
+/// alumno e intento se sortean `count` ítems y se barajan sus opciones con una semilla
+/// derivada de (alumno, lección, bloque, intento). La variante se persiste para que la
+/// calificación y la revisión reproduzcan exactamente lo que vio el alumno.
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::{DateTime, Utc};
+use common::{auth::Claims, middleware::Org};
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_points() -> i32 {
+    1
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PoolItem {
+    pub id: String,
+    #[serde(default)]
+    pub question_bank_id: Option<Uuid>,
+    #[serde(default, rename = "type")]
+    pub question_type: Option<String>,
+    pub question: String,
+    #[serde(default)]
+    pub options: Vec<Value>,
+    #[serde(default)]
+    pub correct: Vec<i64>,
+    #[serde(default)]
+    pub explanation: Option<String>,
+    #[serde(default = "default_points")]
+    pub points: i32,
+    #[serde(default)]
+    pub audio_url: Option<String>,
+    #[serde(default)]
+    pub media_url: Option<String>,
+    #[serde(default)]
+    pub media_type: Option<String>,
+}
+
+/// `quiz_data.pool` de un bloque de cuestionario.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PoolSpec {
+    pub count: usize,
+    #[serde(default = "default_true")]
+    pub shuffle_options: bool,
+    #[serde(default)]
+    pub items: Vec<PoolItem>,
+}
+
+/// Pregunta tal como se le presentó al alumno. `option_order[i]` es el índice original
+/// (en el banco) de la opción mostrada en la posición `i`; `correct` usa las posiciones
+/// mostradas.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariantQuestion {
+    pub id: String,
+    pub question_bank_id: Option<Uuid>,
+    #[serde(rename = "type")]
+    pub question_type: Option<String>,
+    pub question: String,
+    pub options: Vec<Value>,
+    pub option_order: Vec<usize>,
+    pub correct: Vec<i64>,
+    pub explanation: Option<String>,
+    pub points: i32,
+    pub audio_url: Option<String>,
+    pub media_url: Option<String>,
+    pub media_type: Option<String>,
+}
+
+impl VariantQuestion {
+    /// Sin respuesta correcta ni explicación, para un intento en curso.
+    fn public(&self) -> Value {
+        serde_json::json!({
+            "id": self.id,
+            "type": self.question_type,
+            "question": self.question,
+            "options": self.options,
+            "points": self.points,
+            "audio_url": self.audio_url,
+            "media_url": self.media_url,
+            "media_type": self.media_type,
+        })
+    }
+}
+
+/// Bloques de cuestionario con pool, en `metadata.blocks` y en `content_blocks`.
+pub fn pool_blocks(metadata: Option<&Value>, content_blocks: Option<&Value>) -> Vec<(String, PoolSpec)> {
+    let lists = [metadata.and_then(|m| m.get("blocks")), content_blocks];
+    lists
+        .into_iter()
+        .flatten()
+        .filter_map(|blocks| blocks.as_array())
+        .flat_map(|blocks| blocks.iter().enumerate())
+        .filter_map(|(index, block)| {
+            let pool = block.get("quiz_data")?.get("pool")?;
+            let spec = serde_json::from_value::<PoolSpec>(pool.clone()).ok()?;
+            let id = block
+                .get("id")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| format!("block-{}", index));
+            Some((id, spec))
+        })
+        .collect()
+}
+
+/// Quita los ítems candidatos (con sus respuestas) de los bloques enviados a los alumnos.
+pub fn redact_pool_items(metadata: &mut Option<Value>, content_blocks: &mut Option<Value>) {
+    let lists = [metadata.as_mut().and_then(|m| m.get_mut("blocks")), content_blocks.as_mut()];
+    for blocks in lists.into_iter().flatten() {
+        let Some(blocks) = blocks.as_array_mut() else {
+            continue;
+        };
+        for block in blocks {
+            let Some(pool) = block
+                .get_mut("quiz_data")
+                .and_then(|q| q.get_mut("pool"))
+                .and_then(|p| p.as_object_mut())
+            else {
+                continue;
+            };
+            if let Some(Value::Array(items)) = pool.remove("items") {
+                pool.insert("available".to_string(), Value::from(items.len()));
+            }
+        }
+    }
+}
+
+fn variant_seed(user_id: Uuid, lesson_id: Uuid, block_id: &str, attempt: i32) -> i64 {
+    let digest = Sha256::digest(format!("{}:{}:{}:{}", user_id, lesson_id, block_id, attempt));
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    i64::from_be_bytes(bytes)
+}
+
+/// Sortea los ítems y baraja sus opciones de forma reproducible a partir de la semilla.
+pub fn draw_variant(spec: &PoolSpec, seed: i64) -> Vec<VariantQuestion> {
+    let mut rng = StdRng::seed_from_u64(seed as u64);
+    let mut items: Vec<&PoolItem> = spec.items.iter().collect();
+    items.shuffle(&mut rng);
+    items.truncate(spec.count);
+
+    items
+        .into_iter()
+        .map(|item| {
+            let mut order: Vec<usize> = (0..item.options.len()).collect();
+            if spec.shuffle_options {
+                order.shuffle(&mut rng);
+            }
+            let correct = item
+                .correct
+                .iter()
+                .filter_map(|&original| order.iter().position(|&o| o as i64 == original).map(|p| p as i64))
+                .collect();
+            VariantQuestion {
+                id: item.id.clone(),
+                question_bank_id: item.question_bank_id,
+                question_type: item.question_type.clone(),
+                question: item.question.clone(),
+                options: order.iter().map(|&o| item.options[o].clone()).collect(),
+                option_order: order,
+                correct,
+                explanation: item.explanation.clone(),
+                points: item.points,
+                audio_url: item.audio_url.clone(),
+                media_url: item.media_url.clone(),
+                media_type: item.media_type.clone(),
+            }
+        })
+        .collect()
+}
+
+// ─── Endpoints ───────────────────────────────────────────────────────────────
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct QuizVariant {
+    pub id: Uuid,
+    pub block_id: String,
+    pub questions: Value,
+    pub responses: Option<Value>,
+    pub score: Option<f32>,
+    pub submitted_at: Option<DateTime<Utc>>,
+}
+
+const VARIANT_COLUMNS: &str = "id, block_id, questions, responses, score, submitted_at";
+
+#[derive(Debug, Serialize)]
+pub struct VariantBlockView {
+    pub block_id: String,
+    pub questions: Vec<Value>,
+    pub responses: Option<Value>,
+    pub score: Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuizVariantResponse {
+    pub lesson_id: Uuid,
+    pub attempt: i32,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub blocks: Vec<VariantBlockView>,
+}
+
+impl QuizVariantResponse {
+    /// Las respuestas correctas solo se incluyen una vez entregado el intento.
+    fn new(lesson_id: Uuid, attempt: i32, variants: &[QuizVariant]) -> Self {
+        let blocks = variants
+            .iter()
+            .map(|variant| {
+                let questions: Vec<VariantQuestion> =
+                    serde_json::from_value(variant.questions.clone()).unwrap_or_default();
+                VariantBlockView {
+                    block_id: variant.block_id.clone(),
+                    questions: questions
+                        .iter()
+                        .map(|q| match variant.submitted_at {
+                            Some(_) => serde_json::to_value(q).unwrap_or(Value::Null),
+                            None => q.public(),
+                        })
+                        .collect(),
+                    responses: variant.responses.clone(),
+                    score: variant.score,
+                }
+            })
+            .collect();
+        QuizVariantResponse {
+            lesson_id,
+            attempt,
+            submitted_at: variants.iter().filter_map(|v| v.submitted_at).max(),
+            blocks,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuizVariantQuery {
+    /// Intento a revisar; por defecto, el siguiente intento del alumno
+    pub attempt: Option<i32>,
+}
+
+struct PoolLesson {
+    course_id: Uuid,
+    max_attempts: Option<i32>,
+    blocks: Vec<(String, PoolSpec)>,
+}
+
+async fn load_pool_lesson(
+    pool: &PgPool,
+    org_id: Uuid,
+    user_id: Uuid,
+    lesson_id: Uuid,
+) -> Result<PoolLesson, (StatusCode, String)> {
+    #[derive(sqlx::FromRow)]
+    struct LessonRow {
+        course_id: Uuid,
+        metadata: Option<Value>,
+        content_blocks: Option<Value>,
+        max_attempts: Option<i32>,
+    }
+    let lesson: Option<LessonRow> = sqlx::query_as(
+        "SELECT m.course_id, l.metadata, l.content_blocks, l.max_attempts FROM lessons l JOIN modules m ON m.id = l.module_id WHERE l.id = $1 AND l.organization_id = $2",
+    )
+    .bind(lesson_id)
+    .bind(org_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let lesson = lesson.ok_or((StatusCode::NOT_FOUND, "Lección no encontrada".to_string()))?;
+
+    let enrolled: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM enrollments WHERE user_id = $1 AND course_id = $2)")
+            .bind(user_id)
+            .bind(lesson.course_id)
+            .fetch_one(pool)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    if !enrolled {
+        return Err((StatusCode::FORBIDDEN, "No estás inscrito en este curso".to_string()));
+    }
+
+    let blocks = pool_blocks(lesson.metadata.as_ref(), lesson.content_blocks.as_ref());
+    if blocks.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "La lección no tiene pools de preguntas".to_string()));
+    }
+    Ok(PoolLesson {
+        course_id: lesson.course_id,
+        max_attempts: lesson.max_attempts,
+        blocks,
+    })
+}
+
+/// GET /lessons/{id}/quiz-variant - Variante del alumno para el siguiente intento
+/// (se sortea la primera vez) o, con `?attempt=`, la revisión de un intento anterior.
+pub async fn get_quiz_variant(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(lesson_id): Path<Uuid>,
+    Query(query): Query<QuizVariantQuery>,
+) -> Result<Json<QuizVariantResponse>, (StatusCode, String)> {
+    let lesson = load_pool_lesson(&pool, org_ctx.id, claims.sub, lesson_id).await?;
+
+    let attempts_count: i32 = sqlx::query_scalar(
+        "SELECT attempts_count FROM user_grades WHERE user_id = $1 AND lesson_id = $2 AND organization_id = $3",
+    )
+    .bind(claims.sub)
+    .bind(lesson_id)
+    .bind(org_ctx.id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+    .unwrap_or(0);
+
+    let next_attempt = attempts_count + 1;
+    let attempt = query.attempt.unwrap_or(next_attempt);
+    if attempt < 1 || attempt > next_attempt {
+        return Err((StatusCode::BAD_REQUEST, "Intento inválido".to_string()));
+    }
+    if attempt == next_attempt && lesson.max_attempts.is_some_and(|max| attempts_count >= max) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Se ha alcanzado el número máximo de intentos para esta evaluación".to_string(),
+        ));
+    }
+
+    if attempt == next_attempt {
+        for (block_id, spec) in &lesson.blocks {
+            let seed = variant_seed(claims.sub, lesson_id, block_id, attempt);
+            let questions = serde_json::to_value(draw_variant(spec, seed))
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+            sqlx::query(
+                r#"
+                INSERT INTO quiz_variants (organization_id, user_id, course_id, lesson_id, block_id, attempt, seed, questions)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (user_id, lesson_id, block_id, attempt) DO NOTHING
+                "#,
+            )
+            .bind(org_ctx.id)
+            .bind(claims.sub)
+            .bind(lesson.course_id)
+            .bind(lesson_id)
+            .bind(block_id)
+            .bind(attempt)
+            .bind(seed)
+            .bind(&questions)
+            .execute(&pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error al guardar la variante del cuestionario: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+            })?;
+        }
+    }
+
+    let variants = sqlx::query_as::<_, QuizVariant>(&format!(
+        "SELECT {} FROM quiz_variants WHERE user_id = $1 AND lesson_id = $2 AND attempt = $3 ORDER BY created_at, block_id",
+        VARIANT_COLUMNS
+    ))
+    .bind(claims.sub)
+    .bind(lesson_id)
+    .bind(attempt)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    if variants.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "No hay variante para este intento".to_string()));
+    }
+
+    Ok(Json(QuizVariantResponse::new(lesson_id, attempt, &variants)))
+}
+
+/// Variantes del intento `attempt` del alumno, bloqueadas para calificarlas.
+pub(crate) async fn lock_variants(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: Uuid,
+    lesson_id: Uuid,
+    attempt: i32,
+) -> Result<Vec<QuizVariant>, sqlx::Error> {
+    sqlx::query_as::<_, QuizVariant>(&format!(
+        "SELECT {} FROM quiz_variants WHERE user_id = $1 AND lesson_id = $2 AND attempt = $3 ORDER BY created_at, block_id FOR UPDATE",
+        VARIANT_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(lesson_id)
+    .bind(attempt)
+    .fetch_all(&mut **tx)
+    .await
+}
+
+/// Califica y marca como entregadas las variantes con las posiciones elegidas
+/// (tal como se mostraron) que devuelve `selected_for(block_id, item_id)`.
+/// Devuelve los puntos obtenidos, los puntos totales y las respuestas por ítem
+/// con el orden original de las opciones, para el análisis psicométrico.
+pub(crate) async fn grade_variants(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    variants: &mut [QuizVariant],
+    selected_for: impl Fn(&str, &str) -> Option<Vec<i64>>,
+) -> Result<(i64, i64, Vec<Value>), sqlx::Error> {
+    let mut earned = 0i64;
+    let mut total = 0i64;
+    let mut item_responses = Vec::new();
+    for variant in variants.iter_mut() {
+        let questions: Vec<VariantQuestion> = serde_json::from_value(variant.questions.clone()).unwrap_or_default();
+        let mut block_earned = 0i64;
+        let mut block_total = 0i64;
+        let mut block_responses = Vec::new();
+        for question in &questions {
+            let answer = selected_for(&variant.block_id, &question.id);
+            let mut selected = answer.clone().unwrap_or_default();
+            selected.sort_unstable();
+            let mut correct_options = question.correct.clone();
+            correct_options.sort_unstable();
+            let correct = !selected.is_empty() && selected == correct_options;
+
+            block_total += question.points as i64;
+            if correct {
+                block_earned += question.points as i64;
+            }
+            block_responses.push(serde_json::json!({
+                "item_id": question.id,
+                "selected": selected,
+                "correct": correct,
+            }));
+            if answer.is_some() {
+                // El análisis psicométrico usa el orden original de las opciones
+                let original: Vec<usize> = selected
+                    .iter()
+                    .filter_map(|&i| question.option_order.get(i as usize).copied())
+                    .collect();
+                item_responses.push(serde_json::json!({ "item_id": question.id, "selected": original }));
+            }
+        }
+        earned += block_earned;
+        total += block_total;
+        variant.responses = Some(Value::Array(block_responses));
+        variant.score = Some(if block_total > 0 { block_earned as f32 / block_total as f32 } else { 0.0 });
+        variant.submitted_at = Some(Utc::now());
+
+        sqlx::query("UPDATE quiz_variants SET responses = $1, score = $2, submitted_at = $3 WHERE id = $4")
+            .bind(&variant.responses)
+            .bind(variant.score)
+            .bind(variant.submitted_at)
+            .bind(variant.id)
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok((earned, total, item_responses))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VariantAnswer {
+    pub block_id: String,
+    pub item_id: String,
+    /// Posición(es) de las opciones elegidas, tal como se mostraron
+    pub selected: Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubmitQuizVariantPayload {
+    pub responses: Vec<VariantAnswer>,
+    /// Intento en curso cuando la lección es un examen con tiempo
+    #[serde(default)]
+    pub exam_attempt_id: Option<Uuid>,
+}
+
+/// POST /lessons/{id}/quiz-variant/submit - Califica en el servidor la variante del
+/// intento en curso y registra la calificación de la lección.
+pub async fn submit_quiz_variant(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(lesson_id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(payload): Json<SubmitQuizVariantPayload>,
+) -> Result<Json<QuizVariantResponse>, (StatusCode, String)> {
+    let lesson = load_pool_lesson(&pool, org_ctx.id, claims.sub, lesson_id).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    // Mismas reglas que POST /grades: intento de examen vigente y política de entrega tardía
+    let exam_metadata = payload
+        .exam_attempt_id
+        .map(|id| serde_json::json!({ "exam_attempt_id": id }));
+    let exam_submission = crate::exam_attempts::authorize_exam_submission(
+        &mut tx,
+        &claims,
+        &headers,
+        claims.sub,
+        lesson_id,
+        exam_metadata.as_ref(),
+    )
+    .await?;
+    let late_penalty = crate::late_policies::enforce_late_policy(&mut tx, claims.sub, lesson_id).await?;
+
+    let attempts_count: i32 = sqlx::query_scalar(
+        "SELECT attempts_count FROM user_grades WHERE user_id = $1 AND lesson_id = $2 AND organization_id = $3 FOR UPDATE",
+    )
+    .bind(claims.sub)
+    .bind(lesson_id)
+    .bind(org_ctx.id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+    .unwrap_or(0);
+    // Los intentos de examen ya validaron el límite al iniciarse (o fueron reabiertos)
+    if !exam_submission && lesson.max_attempts.is_some_and(|max| attempts_count >= max) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Se ha alcanzado el número máximo de intentos para esta evaluación".to_string(),
+        ));
+    }
+    let attempt = attempts_count + 1;
+
+    let mut variants = lock_variants(&mut tx, claims.sub, lesson_id, attempt)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    if variants.is_empty() {
+        return Err((StatusCode::CONFLICT, "No se ha generado la variante de este intento".to_string()));
+    }
+    if variants.iter().any(|v| v.submitted_at.is_some()) {
+        return Err((StatusCode::CONFLICT, "El intento ya fue entregado".to_string()));
+    }
+
+    let (earned, total, item_responses) = grade_variants(&mut tx, &mut variants, |block_id, item_id| {
+        payload
+            .responses
+            .iter()
+            .find(|r| r.block_id == block_id && r.item_id == item_id)
+            .map(|a| crate::psychometrics::value_indices(&a.selected))
+    })
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let score = if total > 0 { earned as f32 / total as f32 } else { 0.0 };
+    let mut metadata = serde_json::json!({
+        "quiz_variant_attempt": attempt,
+        "points_earned": earned,
+        "points_total": total,
+        "item_responses": item_responses,
+    });
+    if let Some(id) = payload.exam_attempt_id.filter(|_| exam_submission) {
+        metadata["exam_attempt_id"] = serde_json::json!(id);
+    }
+
+    let grade = crate::grading::upsert_grade(
+        &mut tx,
+        org_ctx.id,
+        claims.sub,
+        lesson.course_id,
+        lesson_id,
+        score,
+        Some(&metadata),
+        Some(late_penalty),
+    )
+    .await?;
+
+    crate::psychometrics::record_item_responses(
+        &mut tx,
+        org_ctx.id,
+        claims.sub,
+        lesson.course_id,
+        lesson_id,
+        grade.attempts_count,
+        &metadata,
+        None,
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("Error al registrar respuestas por ítem: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+    })?;
+
+    crate::spaced_repetition::enqueue_missed_items(
+        &mut tx,
+        org_ctx.id,
+        claims.sub,
+        lesson.course_id,
+        lesson_id,
+        grade.attempts_count,
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("Error al generar tarjetas de repaso: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+    })?;
+
+    tx.commit()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(QuizVariantResponse::new(lesson_id, attempt, &variants)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_reproducible_variants_with_remapped_answers() {
+        let items: Vec<Value> = (0..10)
+            .map(|i| {
+                serde_json::json!({
+                    "id": format!("q{}", i),
+                    "question": format!("Question {}", i),
+                    "options": ["a", "b", "c", "d"],
+                    "correct": [2],
+                })
+            })
+            .collect();
+        let spec: PoolSpec = serde_json::from_value(serde_json::json!({ "count": 4, "items": items })).unwrap();
+
+        let seed = variant_seed(Uuid::nil(), Uuid::nil(), "block-0", 1);
+        let first = draw_variant(&spec, seed);
+        let again = draw_variant(&spec, seed);
+        assert_eq!(first.len(), 4);
+        assert_eq!(
+            first.iter().map(|q| (&q.id, &q.option_order)).collect::<Vec<_>>(),
+            again.iter().map(|q| (&q.id, &q.option_order)).collect::<Vec<_>>()
+        );
+
+        for question in &first {
+            assert_eq!(question.correct.len(), 1);
+            let shown = question.correct[0] as usize;
+            assert_eq!(question.options[shown], Value::from("c"));
+            assert_eq!(question.option_order[shown], 2);
+        }
+
+        let other = draw_variant(&spec, variant_seed(Uuid::nil(), Uuid::nil(), "block-0", 2));
+        assert_ne!(
+            first.iter().map(|q| (&q.id, &q.option_order)).collect::<Vec<_>>(),
+            other.iter().map(|q| (&q.id, &q.option_order)).collect::<Vec<_>>()
+        );
+    }
+}
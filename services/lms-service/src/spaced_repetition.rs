@@ -0,0 +1,524 @@
+/// Repaso espaciado de las preguntas falladas.
+///
+/// Al calificar una entrega (`POST /grades`, variantes de cuestionario, pruebas
+/// adaptativas y exámenes cerrados al vencer) las preguntas de `quiz_item_responses`
+/// con puntaje menor a 1 en ese intento se agregan como tarjetas de repaso. Fallar de
+/// nuevo una tarjeta existente la vuelve a poner en aprendizaje.
+///
+/// La programación sigue SM-2: calidad 0-5, factor de facilidad mínimo 1.3 e
+/// intervalos de 1, 6 y luego `intervalo × facilidad` días; solo se responden las
+/// tarjetas ya vencidas. El primer repaso de cada día (UTC) otorga XP según la racha
+/// de días consecutivos con repasos.
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use common::{auth::Claims, middleware::Org};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const MIN_EASE: f32 = 1.3;
+/// XP por día de racha en el primer repaso del día (la racha cuenta hasta 7 días)
+const STREAK_XP_PER_DAY: i32 = 5;
+const MAX_STREAK_BONUS_DAYS: i32 = 7;
+/// Inicio del día UTC, el mismo calendario que usa la racha
+const UTC_DAY_START: &str = "date_trunc('day', NOW() AT TIME ZONE 'UTC') AT TIME ZONE 'UTC'";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sm2State {
+    pub ease_factor: f32,
+    pub interval_days: i32,
+    pub repetitions: i32,
+    pub lapses: i32,
+}
+
+/// Siguiente estado SM-2 tras un repaso con calidad `quality` (0-5).
+pub fn sm2(state: Sm2State, quality: i16) -> Sm2State {
+    let q = quality.clamp(0, 5) as f32;
+    let ease_factor = (state.ease_factor + 0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02)).max(MIN_EASE);
+    if quality < 3 {
+        return Sm2State { ease_factor, interval_days: 1, repetitions: 0, lapses: state.lapses + 1 };
+    }
+    let repetitions = state.repetitions + 1;
+    let interval_days = match repetitions {
+        1 => 1,
+        2 => 6,
+        _ => (state.interval_days as f32 * state.ease_factor).round() as i32,
+    };
+    Sm2State { ease_factor, interval_days, repetitions, lapses: state.lapses }
+}
+
+/// Días consecutivos con repasos que terminan hoy (o ayer, si hoy aún no hubo).
+pub fn streak_days(mut dates: Vec<NaiveDate>, today: NaiveDate) -> i32 {
+    dates.sort_unstable_by(|a, b| b.cmp(a));
+    dates.dedup();
+    let mut expected = match dates.first() {
+        Some(&d) if d == today || d == today - Duration::days(1) => d,
+        _ => return 0,
+    };
+    let mut streak = 0;
+    for date in dates {
+        if date != expected {
+            break;
+        }
+        streak += 1;
+        expected -= Duration::days(1);
+    }
+    streak
+}
+
+// ─── Alta de tarjetas ────────────────────────────────────────────────────────
+
+/// Pregunta `item_id` del metadata de la lección y el bloque que la contiene.
+fn find_question<'a>(metadata: &'a Value, item_id: &str) -> Option<(Option<&'a str>, &'a Value)> {
+    let matches = |q: &&Value| {
+        q.get("id").is_some_and(|id| match id {
+            Value::String(s) => s == item_id,
+            Value::Number(n) => n.to_string() == item_id,
+            _ => false,
+        })
+    };
+    for block in metadata.get("blocks").and_then(|b| b.as_array()).into_iter().flatten() {
+        let quiz = block.get("quiz_data");
+        let lists = [
+            quiz.and_then(|q| q.get("questions")),
+            quiz.and_then(|q| q.get("pool")).and_then(|p| p.get("items")),
+        ];
+        for list in lists.into_iter().flatten().filter_map(|l| l.as_array()) {
+            if let Some(question) = list.iter().find(matches) {
+                return Some((block.get("id").and_then(|id| id.as_str()), question));
+            }
+        }
+    }
+    [metadata.get("questions"), metadata.get("adaptive").and_then(|a| a.get("pool"))]
+        .into_iter()
+        .flatten()
+        .filter_map(|l| l.as_array())
+        .find_map(|list| list.iter().find(matches))
+        .map(|question| (None, question))
+}
+
+fn card_content(question: &Value) -> Value {
+    json!({
+        "question": question.get("question"),
+        "options": question.get("options"),
+        "correct": question.get("correct"),
+        "explanation": question.get("explanation"),
+    })
+}
+
+/// Preguntas falladas en la entrega: `(block_id, item_id, pregunta)`.
+fn missed_questions<'a>(lesson_metadata: &'a Value, missed_item_ids: &[String]) -> Vec<(Option<String>, String, &'a Value)> {
+    missed_item_ids
+        .iter()
+        .filter_map(|item_id| {
+            find_question(lesson_metadata, item_id)
+                .map(|(block, question)| (block.map(str::to_string), item_id.clone(), question))
+        })
+        .collect()
+}
+
+/// Agrega a la cola de repaso las preguntas falladas en el intento `attempt`, según
+/// lo registrado por `psychometrics::record_item_responses` en la misma transacción.
+pub async fn enqueue_missed_items(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    org_id: Uuid,
+    user_id: Uuid,
+    course_id: Uuid,
+    lesson_id: Uuid,
+    attempt: i32,
+) -> Result<usize, sqlx::Error> {
+    let lesson_metadata: Option<Value> = sqlx::query_scalar("SELECT metadata FROM lessons WHERE id = $1")
+        .bind(lesson_id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .flatten();
+    let Some(lesson_metadata) = lesson_metadata else {
+        return Ok(0);
+    };
+
+    let missed_item_ids: Vec<String> = sqlx::query_scalar(
+        "SELECT item_id FROM quiz_item_responses WHERE user_id = $1 AND lesson_id = $2 AND attempt = $3 AND score < 1",
+    )
+    .bind(user_id)
+    .bind(lesson_id)
+    .bind(attempt)
+    .fetch_all(&mut **tx)
+    .await?;
+
+    let missed = missed_questions(&lesson_metadata, &missed_item_ids);
+    for (block_id, item_id, question) in &missed {
+        sqlx::query(
+            r#"
+            INSERT INTO review_cards (organization_id, user_id, course_id, lesson_id, block_id, item_id, content)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (user_id, lesson_id, item_id) DO UPDATE SET
+                content = EXCLUDED.content,
+                repetitions = 0,
+                interval_days = 0,
+                lapses = review_cards.lapses + 1,
+                due_at = LEAST(review_cards.due_at, NOW())
+            "#,
+        )
+        .bind(org_id)
+        .bind(user_id)
+        .bind(course_id)
+        .bind(lesson_id)
+        .bind(block_id)
+        .bind(item_id)
+        .bind(card_content(question))
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(missed.len())
+}
+
+// ─── Endpoints ───────────────────────────────────────────────────────────────
+
+#[derive(Debug, sqlx::FromRow)]
+struct ReviewCardRow {
+    id: Uuid,
+    course_id: Uuid,
+    lesson_id: Uuid,
+    content: Value,
+    ease_factor: f32,
+    interval_days: i32,
+    repetitions: i32,
+    lapses: i32,
+    due_at: DateTime<Utc>,
+}
+
+const CARD_COLUMNS: &str =
+    "id, course_id, lesson_id, content, ease_factor, interval_days, repetitions, lapses, due_at";
+
+#[derive(Debug, Serialize)]
+pub struct ReviewCard {
+    pub id: Uuid,
+    pub course_id: Uuid,
+    pub lesson_id: Uuid,
+    pub question: Value,
+    pub options: Value,
+    pub interval_days: i32,
+    pub repetitions: i32,
+    pub due_at: DateTime<Utc>,
+}
+
+impl From<ReviewCardRow> for ReviewCard {
+    fn from(row: ReviewCardRow) -> Self {
+        // La respuesta correcta y la explicación se revelan al responder
+        ReviewCard {
+            id: row.id,
+            course_id: row.course_id,
+            lesson_id: row.lesson_id,
+            question: row.content.get("question").cloned().unwrap_or(Value::Null),
+            options: row.content.get("options").cloned().unwrap_or(Value::Null),
+            interval_days: row.interval_days,
+            repetitions: row.repetitions,
+            due_at: row.due_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DueReviewsQuery {
+    pub course_id: Option<Uuid>,
+    pub limit: Option<i64>,
+}
+
+/// GET /reviews/due - Tarjetas pendientes del alumno, las más atrasadas primero
+pub async fn get_due_reviews(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Query(query): Query<DueReviewsQuery>,
+) -> Result<Json<Vec<ReviewCard>>, (StatusCode, String)> {
+    let cards = sqlx::query_as::<_, ReviewCardRow>(&format!(
+        r#"
+        SELECT {CARD_COLUMNS} FROM review_cards
+        WHERE user_id = $1 AND organization_id = $2 AND due_at <= NOW()
+          AND ($3::uuid IS NULL OR course_id = $3)
+        ORDER BY due_at
+        LIMIT $4
+        "#
+    ))
+    .bind(claims.sub)
+    .bind(org_ctx.id)
+    .bind(query.course_id)
+    .bind(query.limit.unwrap_or(20).clamp(1, 100))
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(cards.into_iter().map(ReviewCard::from).collect()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewAnswerPayload {
+    /// Opción(es) elegidas; la tarjeta se califica en el servidor
+    pub selected: Option<Value>,
+    /// Autoevaluación SM-2 (0-5) cuando la tarjeta no tiene respuesta cerrada
+    pub quality: Option<i16>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReviewAnswerResponse {
+    pub card_id: Uuid,
+    pub correct: Option<bool>,
+    pub quality: i16,
+    pub correct_answer: Value,
+    pub explanation: Value,
+    pub interval_days: i32,
+    pub next_due_at: DateTime<Utc>,
+    pub streak_days: i32,
+    pub xp_awarded: i32,
+}
+
+/// POST /reviews/{id}/answer - Registra el repaso y reprograma la tarjeta
+pub async fn answer_review(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+    Path(card_id): Path<Uuid>,
+    Json(payload): Json<ReviewAnswerPayload>,
+) -> Result<Json<ReviewAnswerResponse>, (StatusCode, String)> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let card = sqlx::query_as::<_, ReviewCardRow>(&format!(
+        "SELECT {CARD_COLUMNS} FROM review_cards WHERE id = $1 AND user_id = $2 AND organization_id = $3 FOR UPDATE"
+    ))
+    .bind(card_id)
+    .bind(claims.sub)
+    .bind(org_ctx.id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Tarjeta no encontrada".to_string()))?;
+    // Repasar antes de tiempo alargaría el intervalo sin que la tarjeta haya vencido
+    if card.due_at > Utc::now() {
+        return Err((StatusCode::CONFLICT, "La tarjeta todavía no está pendiente de repaso".to_string()));
+    }
+
+    let expected = card.content.get("correct").map(crate::psychometrics::value_indices).unwrap_or_default();
+    let (correct, quality) = match (&payload.selected, payload.quality) {
+        (Some(selected), _) if !expected.is_empty() => {
+            let mut given = crate::psychometrics::value_indices(selected);
+            let mut expected = expected.clone();
+            given.sort_unstable();
+            expected.sort_unstable();
+            let correct = given == expected;
+            // Sin autoevaluación: acierto = 4 (correcto con esfuerzo), fallo = 1
+            let quality = match payload.quality {
+                Some(q) if correct => q.clamp(3, 5),
+                _ if correct => 4,
+                _ => 1,
+            };
+            (Some(correct), quality)
+        }
+        (_, Some(q)) if (0..=5).contains(&q) => (None, q),
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Se requiere la respuesta elegida o una calidad entre 0 y 5".to_string(),
+            ));
+        }
+    };
+
+    let next = sm2(
+        Sm2State {
+            ease_factor: card.ease_factor,
+            interval_days: card.interval_days,
+            repetitions: card.repetitions,
+            lapses: card.lapses,
+        },
+        quality,
+    );
+    let next_due_at = Utc::now() + Duration::days(next.interval_days as i64);
+
+    sqlx::query(
+        r#"
+        UPDATE review_cards
+        SET ease_factor = $1, interval_days = $2, repetitions = $3, lapses = $4,
+            due_at = $5, last_reviewed_at = NOW()
+        WHERE id = $6
+        "#,
+    )
+    .bind(next.ease_factor)
+    .bind(next.interval_days)
+    .bind(next.repetitions)
+    .bind(next.lapses)
+    .bind(next_due_at)
+    .bind(card.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let first_today: bool = sqlx::query_scalar(&format!(
+        "SELECT NOT EXISTS (SELECT 1 FROM review_log WHERE user_id = $1 AND reviewed_at >= {UTC_DAY_START})"
+    ))
+    .bind(claims.sub)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    sqlx::query(
+        "INSERT INTO review_log (organization_id, card_id, user_id, quality, interval_days) VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(org_ctx.id)
+    .bind(card.id)
+    .bind(claims.sub)
+    .bind(quality)
+    .bind(next.interval_days)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let dates: Vec<NaiveDate> = sqlx::query_scalar(
+        "SELECT DISTINCT (reviewed_at AT TIME ZONE 'UTC')::date FROM review_log WHERE user_id = $1 AND reviewed_at > NOW() - INTERVAL '400 days'",
+    )
+    .bind(claims.sub)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    let streak = streak_days(dates, Utc::now().date_naive());
+
+    let xp_awarded = if first_today { STREAK_XP_PER_DAY * streak.min(MAX_STREAK_BONUS_DAYS) } else { 0 };
+    if xp_awarded > 0 {
+        sqlx::query("SELECT fn_award_xp($1, $2, $3, 'review_streak', 'review_card', $4)")
+            .bind(claims.sub)
+            .bind(org_ctx.id)
+            .bind(xp_awarded)
+            .bind(card.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error al otorgar XP por racha de repaso: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string())
+            })?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    Ok(Json(ReviewAnswerResponse {
+        card_id: card.id,
+        correct,
+        quality,
+        correct_answer: card.content.get("correct").cloned().unwrap_or(Value::Null),
+        explanation: card.content.get("explanation").cloned().unwrap_or(Value::Null),
+        interval_days: next.interval_days,
+        next_due_at,
+        streak_days: streak,
+        xp_awarded,
+    }))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ReviewStats {
+    pub total_cards: i64,
+    pub due_now: i64,
+    pub reviewed_today: i64,
+    #[sqlx(skip)]
+    pub streak_days: i32,
+}
+
+/// GET /reviews/stats - Resumen de la cola y racha del alumno
+pub async fn get_review_stats(
+    Org(org_ctx): Org,
+    claims: Claims,
+    State(pool): State<PgPool>,
+) -> Result<Json<ReviewStats>, (StatusCode, String)> {
+    let mut stats = sqlx::query_as::<_, ReviewStats>(&format!(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM review_cards WHERE user_id = $1 AND organization_id = $2) AS total_cards,
+            (SELECT COUNT(*) FROM review_cards WHERE user_id = $1 AND organization_id = $2 AND due_at <= NOW()) AS due_now,
+            (SELECT COUNT(*) FROM review_log WHERE user_id = $1 AND reviewed_at >= {UTC_DAY_START}) AS reviewed_today
+        "#
+    ))
+    .bind(claims.sub)
+    .bind(org_ctx.id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+
+    let dates: Vec<NaiveDate> = sqlx::query_scalar(
+        "SELECT DISTINCT (reviewed_at AT TIME ZONE 'UTC')::date FROM review_log WHERE user_id = $1 AND reviewed_at > NOW() - INTERVAL '400 days'",
+    )
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor".to_string()))?;
+    stats.streak_days = streak_days(dates, Utc::now().date_naive());
+
+    Ok(Json(stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sm2_schedules_and_resets_on_lapse() {
+        let new = Sm2State { ease_factor: 2.5, interval_days: 0, repetitions: 0, lapses: 0 };
+        let first = sm2(new, 4);
+        assert_eq!((first.interval_days, first.repetitions), (1, 1));
+        let second = sm2(first, 5);
+        assert_eq!(second.interval_days, 6);
+        let third = sm2(second, 4);
+        assert_eq!(third.interval_days, (6.0 * second.ease_factor).round() as i32);
+
+        let lapse = sm2(third, 1);
+        assert_eq!((lapse.interval_days, lapse.repetitions, lapse.lapses), (1, 0, 1));
+        assert!(lapse.ease_factor >= MIN_EASE && lapse.ease_factor < third.ease_factor);
+
+        let today = NaiveDate::from_ymd_opt(2026, 5, 10).unwrap();
+        let days = |offsets: &[i64]| offsets.iter().map(|o| today - Duration::days(*o)).collect::<Vec<_>>();
+        assert_eq!(streak_days(days(&[0, 1, 2, 4]), today), 3);
+        assert_eq!(streak_days(days(&[1, 2]), today), 2);
+        assert_eq!(streak_days(days(&[2, 3]), today), 0);
+    }
+
+    #[test]
+    fn only_missed_items_become_cards_wherever_they_live() {
+        let metadata = json!({
+            "blocks": [
+                {"id": "b1", "quiz_data": {"questions": [
+                    {"id": "q1", "question": "¿Uno?", "correct": [0]},
+                    {"id": "q2", "question": "¿Dos?", "correct": [1]}
+                ]}},
+                {"id": "b2", "quiz_data": {"pool": {"items": [{"id": 7, "question": "¿Pool?"}]}}}
+            ],
+            "adaptive": {"pool": [{"id": "bank-1", "question": "¿Adaptativa?"}]}
+        });
+
+        let missed = missed_questions(&metadata, &["q2".to_string(), "7".to_string(), "bank-1".to_string()]);
+        let found: Vec<(Option<&str>, &str)> = missed.iter().map(|(b, id, _)| (b.as_deref(), id.as_str())).collect();
+        assert_eq!(found, vec![(Some("b1"), "q2"), (Some("b2"), "7"), (None, "bank-1")]);
+        assert!(missed_questions(&metadata, &[]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn day_start_is_utc_regardless_of_session_time_zone() {
+        let Some(pool) = crate::db_util::test_pool(1).await else {
+            return;
+        };
+        let mut conn = pool.acquire().await.unwrap();
+        sqlx::query("SET TIME ZONE 'America/Lima'").execute(&mut *conn).await.unwrap();
+        let start: DateTime<Utc> = sqlx::query_scalar(&format!("SELECT {UTC_DAY_START}"))
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        sqlx::query("RESET TIME ZONE").execute(&mut *conn).await.unwrap();
+
+        let today = Utc::now().date_naive();
+        assert_eq!(start, today.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+}
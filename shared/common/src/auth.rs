@@ -80,3 +80,194 @@ pub fn create_preview_token(
         &EncodingKey::from_secret(secret.as_ref()),
     )
 }
+
+/// Permisos que un plugin puede declarar en su manifiesto.
+pub const PLUGIN_PERMISSIONS: &[&str] = &["lesson.read_context", "grade.submit", "user.read_name"];
+
+/// Vigencia de los tokens de plugin, emitidos cada vez que se renderiza el bloque.
+pub const PLUGIN_TOKEN_TTL_SECONDS: i64 = 300;
+
+/// Claims de un token de plugin: sin `role`, por lo que no sirven como token de usuario.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginClaims {
+    pub sub: Uuid,
+    pub org: Uuid,
+    pub exp: i64,
+    pub plugin_id: Uuid,
+    pub lesson_id: Uuid,
+    pub block_id: Option<String>,
+    pub permissions: Vec<String>,
+    pub token_type: String, // "plugin"
+}
+
+impl PluginClaims {
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|p| p == permission)
+    }
+}
+
+pub fn create_plugin_token(
+    user_id: Uuid,
+    organization_id: Uuid,
+    plugin_id: Uuid,
+    lesson_id: Uuid,
+    block_id: Option<String>,
+    permissions: Vec<String>,
+) -> Result<(String, PluginClaims), jsonwebtoken::errors::Error> {
+    let claims = PluginClaims {
+        sub: user_id,
+        org: organization_id,
+        exp: (Utc::now() + Duration::seconds(PLUGIN_TOKEN_TTL_SECONDS)).timestamp(),
+        plugin_id,
+        lesson_id,
+        block_id,
+        permissions,
+        token_type: "plugin".to_string(),
+    };
+
+    let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET env var must be set");
+    let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_ref()))?;
+    Ok((token, claims))
+}
+
+pub fn decode_plugin_token(token: &str) -> Result<PluginClaims, jsonwebtoken::errors::Error> {
+    let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET env var must be set");
+    decode_plugin_token_with_secret(token, &secret)
+}
+
+fn decode_plugin_token_with_secret(token: &str, secret: &str) -> Result<PluginClaims, jsonwebtoken::errors::Error> {
+    let claims = jsonwebtoken::decode::<PluginClaims>(
+        token,
+        &jsonwebtoken::DecodingKey::from_secret(secret.as_ref()),
+        &jsonwebtoken::Validation::default(),
+    )?
+    .claims;
+
+    if claims.token_type != "plugin" {
+        return Err(jsonwebtoken::errors::ErrorKind::InvalidToken.into());
+    }
+    Ok(claims)
+}
+
+/// Alcance de la réplica de plugins del CMS en el LMS (`/plugins/{id}/sync`).
+pub const SERVICE_SCOPE_PLUGIN_SYNC: &str = "plugins.sync";
+
+/// Alcance de la exportación y supresión de datos de titulares en el CMS
+/// (`/dsar/users/*`), que orquesta el LMS.
+pub const SERVICE_SCOPE_DSAR: &str = "dsar.process";
+
+/// Vigencia de los tokens entre servicios, emitidos por cada llamada.
+pub const SERVICE_TOKEN_TTL_SECONDS: i64 = 60;
+
+/// Claims de una llamada entre servicios (CMS → LMS): sin `role`, por lo que no
+/// sirven como token de usuario, y limitados a un `scope`. `sub` es el usuario que
+/// originó la acción en el CMS.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServiceClaims {
+    pub sub: Uuid,
+    pub org: Uuid,
+    pub exp: i64,
+    pub scope: String,
+    pub token_type: String, // "service"
+}
+
+pub fn create_service_token(
+    user_id: Uuid,
+    organization_id: Uuid,
+    scope: &str,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let claims = ServiceClaims {
+        sub: user_id,
+        org: organization_id,
+        exp: (Utc::now() + Duration::seconds(SERVICE_TOKEN_TTL_SECONDS)).timestamp(),
+        scope: scope.to_string(),
+        token_type: "service".to_string(),
+    };
+
+    let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET env var must be set");
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_ref()))
+}
+
+pub fn decode_service_token(token: &str, scope: &str) -> Result<ServiceClaims, jsonwebtoken::errors::Error> {
+    let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET env var must be set");
+    decode_service_token_with_secret(token, scope, &secret)
+}
+
+fn decode_service_token_with_secret(
+    token: &str,
+    scope: &str,
+    secret: &str,
+) -> Result<ServiceClaims, jsonwebtoken::errors::Error> {
+    let claims = jsonwebtoken::decode::<ServiceClaims>(
+        token,
+        &jsonwebtoken::DecodingKey::from_secret(secret.as_ref()),
+        &jsonwebtoken::Validation::default(),
+    )?
+    .claims;
+
+    if claims.token_type != "service" || claims.scope != scope {
+        return Err(jsonwebtoken::errors::ErrorKind::InvalidToken.into());
+    }
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plugin_tokens_are_not_user_tokens() {
+        let secret = "test-secret";
+        let claims = PluginClaims {
+            sub: Uuid::new_v4(),
+            org: Uuid::new_v4(),
+            exp: (Utc::now() + Duration::seconds(60)).timestamp(),
+            plugin_id: Uuid::new_v4(),
+            lesson_id: Uuid::new_v4(),
+            block_id: None,
+            permissions: vec!["grade.submit".to_string()],
+            token_type: "plugin".to_string(),
+        };
+        let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_ref())).unwrap();
+
+        let decoded = decode_plugin_token_with_secret(&token, secret).unwrap();
+        assert!(decoded.has_permission("grade.submit"));
+        assert!(!decoded.has_permission("user.read_name"));
+
+        // El middleware de usuarios decodifica `Claims`, que exige `role`
+        let as_user = jsonwebtoken::decode::<Claims>(
+            &token,
+            &jsonwebtoken::DecodingKey::from_secret(secret.as_ref()),
+            &jsonwebtoken::Validation::default(),
+        );
+        assert!(as_user.is_err());
+    }
+
+    #[test]
+    fn user_tokens_are_not_service_tokens() {
+        let secret = "test-secret";
+        let user = Claims {
+            sub: Uuid::new_v4(),
+            org: Uuid::new_v4(),
+            exp: (Utc::now() + Duration::seconds(60)).timestamp(),
+            role: "instructor".to_string(),
+            course_id: None,
+            token_type: Some("access".to_string()),
+        };
+        let user_token = encode(&Header::default(), &user, &EncodingKey::from_secret(secret.as_ref())).unwrap();
+        assert!(decode_service_token_with_secret(&user_token, SERVICE_SCOPE_PLUGIN_SYNC, secret).is_err());
+
+        let service = ServiceClaims {
+            sub: user.sub,
+            org: user.org,
+            exp: user.exp,
+            scope: SERVICE_SCOPE_PLUGIN_SYNC.to_string(),
+            token_type: "service".to_string(),
+        };
+        let token = encode(&Header::default(), &service, &EncodingKey::from_secret(secret.as_ref())).unwrap();
+        assert!(decode_service_token_with_secret(&token, SERVICE_SCOPE_PLUGIN_SYNC, secret).is_ok());
+        assert!(decode_service_token_with_secret(&token, SERVICE_SCOPE_DSAR, secret).is_err());
+        assert!(decode_service_token_with_secret(&token, "otro.alcance", secret).is_err());
+        assert!(decode_plugin_token_with_secret(&token, secret).is_err());
+    }
+}
@@ -6,3 +6,4 @@ pub mod utils;
 pub mod webhooks;
 pub mod health;
 pub mod token_limits;
+pub mod tenancy;
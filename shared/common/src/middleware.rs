@@ -8,6 +8,7 @@ use jsonwebtoken::{DecodingKey, Validation, decode};
 use uuid::Uuid;
 
 use crate::auth::Claims;
+use crate::tenancy::{self, TenantScope};
 
 /// Contexto de la organización extraído del JWT.
 #[derive(Debug, Clone)]
@@ -69,18 +70,25 @@ pub async fn org_extractor_middleware(
     .map_err(|_| StatusCode::UNAUTHORIZED)?
     .claims;
 
+    // Solo el superadministrador puede cambiar de organización con la cabecera
+    let is_super_admin = claims.role == "admin" && claims.org == tenancy::DEFAULT_ORG_ID;
     let org_id = req
         .headers()
         .get("x-organization-id")
         .and_then(|value| value.to_str().ok())
         .and_then(|value| Uuid::parse_str(value).ok())
+        .filter(|id| is_super_admin || *id == claims.org)
         .unwrap_or(claims.org);
 
+    let scope = TenantScope::for_request(org_id, &claims.role, claims.org);
+
     // Insertamos el contexto y las claims en las extensiones de la petición.
     req.extensions_mut().insert(OrgContext { id: org_id });
     req.extensions_mut().insert(claims);
 
-    Ok(next.run(req).await)
+    // Las consultas del handler (y de su cuerpo en streaming) se ejecutan con RLS
+    // limitado a la organización
+    Ok(tenancy::scoped_response(scope, next.run(req)).await)
 }
 
 impl<S> FromRequestParts<S> for Claims
@@ -486,6 +486,8 @@ pub struct PublishedCourse {
     pub instructors: Option<Vec<CourseInstructor>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dependencies: Option<Vec<LessonDependency>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcomes: Option<PublishedOutcomes>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1016,6 +1018,7 @@ mod tests {
             modules: vec![pub_module],
             instructors: None,
             dependencies: None,
+            outcomes: None,
         };
 
         let course_with_price = Course {
@@ -1132,9 +1135,54 @@ pub struct LessonDependency {
     pub lesson_id: Uuid,
     pub prerequisite_lesson_id: Uuid,
     pub min_score_percentage: Option<f64>,
+    /// `prerequisite`, `remedial` o `enrichment`
+    #[serde(default = "default_branch_type")]
+    pub branch_type: String,
+    #[serde(default)]
+    pub max_score_percentage: Option<f64>,
+    #[serde(default)]
+    pub time_threshold_minutes: Option<i32>,
     pub created_at: DateTime<Utc>,
 }
 
+fn default_branch_type() -> String {
+    "prerequisite".to_string()
+}
+
+// ==================== Resultados de Aprendizaje / Competencias ====================
+
+/// Instantánea de los resultados de aprendizaje alineados a un curso publicado.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PublishedOutcomes {
+    pub outcomes: Vec<PublishedOutcome>,
+    pub alignments: Vec<PublishedOutcomeAlignment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PublishedOutcome {
+    pub id: Uuid,
+    pub framework_id: Uuid,
+    pub framework_name: String,
+    pub parent_id: Option<Uuid>,
+    pub code: String,
+    pub description: String,
+    pub level: Option<String>,
+    pub position: i32,
+}
+
+/// Evidencia de un resultado: la lección completa, un bloque de quiz, una pregunta
+/// del banco o un criterio de rúbrica (mediante las lecciones que usan la rúbrica).
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PublishedOutcomeAlignment {
+    pub outcome_id: Uuid,
+    pub lesson_id: Option<Uuid>,
+    pub block_id: Option<String>,
+    pub question_bank_id: Option<Uuid>,
+    pub rubric_id: Option<Uuid>,
+    pub rubric_criterion_id: Option<Uuid>,
+    pub weight: f32,
+}
+
 // ==================== Aprendizaje en Vivo (Reuniones) ====================
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
@@ -1417,6 +1465,21 @@ pub struct QuestionBank {
     pub embedding_updated_at: Option<chrono::DateTime<chrono::Utc>>,
     pub source_asset_id: Option<Uuid>, // Activo de audio/video que originó este fragmento RAG
     pub unit_number: Option<i32>,      // Número de unidad del sílabo desde la estructura de carpetas ZIP
+    // Calibración TRI estimada por el LMS a partir de las respuestas
+    #[sqlx(default)]
+    pub irt_model: Option<String>,
+    #[sqlx(default)]
+    pub irt_difficulty: Option<f64>,
+    #[sqlx(default)]
+    pub irt_discrimination: Option<f64>,
+    #[sqlx(default)]
+    pub irt_se_difficulty: Option<f64>,
+    #[sqlx(default)]
+    pub irt_sample_size: Option<i32>,
+    #[sqlx(default)]
+    pub point_biserial: Option<f64>,
+    #[sqlx(default)]
+    pub irt_calibrated_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -1463,6 +1526,8 @@ pub struct QuestionBankFilters {
     pub source: Option<String>,
     pub search: Option<String>,
     pub has_audio: Option<bool>,
+    pub min_irt_difficulty: Option<f64>, // Rango de dificultad calibrada (escala logit)
+    pub max_irt_difficulty: Option<f64>,
 }
 
 // ==================== MODELOS DE RESPUESTA DE AUDIO ====================
@@ -1518,6 +1583,8 @@ pub struct AudioResponse {
     pub attempt_number: i32,
     pub duration_seconds: Option<i32>,
     pub metadata: Option<serde_json::Value>,
+    /// Evaluación de pronunciación por palabra (precisión, fluidez y completitud)
+    pub pronunciation: Option<serde_json::Value>,
     
     // Timestamps
     pub created_at: DateTime<Utc>,
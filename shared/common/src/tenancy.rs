@@ -0,0 +1,199 @@
+//! Aislamiento de organizaciones a nivel de conexión.
+//!
+//! Cada petición autenticada se ejecuta dentro de un `TenantScope` (ver
+//! `middleware::org_extractor_middleware`). El pool creado con `pool_options()`
+//! aplica ese ámbito a cada conexión al entregarla: fija el rol de Postgres y
+//! `app.current_org_id`, que son la base de las políticas RLS de las migraciones.
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use axum::body::{Body, Bytes};
+use axum::response::Response;
+use http_body::{Frame, SizeHint};
+use sqlx::{PgConnection, postgres::PgPoolOptions};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// Rol sujeto a las políticas `tenant_*` por organización.
+pub const TENANT_ROLE: &str = "openccb_tenant";
+/// Rol del superadministrador, con la política `platform_admin_bypass`.
+pub const PLATFORM_ADMIN_ROLE: &str = "openccb_platform_admin";
+/// Organización por defecto (instalaciones de un solo tenant).
+pub const DEFAULT_ORG_ID: Uuid = Uuid::from_u128(1);
+
+/// Ámbito con el que se ejecutan las consultas de la tarea actual.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TenantScope {
+    /// Solo filas de la organización indicada.
+    Organization(Uuid),
+    /// Superadministrador: acceso a todas las organizaciones.
+    PlatformAdmin(Uuid),
+    /// Procesos internos (jobs, ingesta, rutas públicas): rol propietario.
+    System,
+}
+
+impl TenantScope {
+    /// Ámbito de una petición autenticada según sus claims.
+    pub fn for_request(org_id: Uuid, role: &str, claims_org: Uuid) -> Self {
+        if role == "admin" && claims_org == DEFAULT_ORG_ID {
+            TenantScope::PlatformAdmin(org_id)
+        } else {
+            TenantScope::Organization(org_id)
+        }
+    }
+
+    fn role(&self) -> &'static str {
+        match self {
+            TenantScope::Organization(_) => TENANT_ROLE,
+            TenantScope::PlatformAdmin(_) => PLATFORM_ADMIN_ROLE,
+            TenantScope::System => "none",
+        }
+    }
+
+    fn org_setting(&self) -> String {
+        match self {
+            TenantScope::Organization(id) | TenantScope::PlatformAdmin(id) => id.to_string(),
+            TenantScope::System => String::new(),
+        }
+    }
+}
+
+tokio::task_local! {
+    static CURRENT_SCOPE: TenantScope;
+}
+
+/// Ámbito de la tarea actual; `System` fuera de una petición.
+pub fn current_scope() -> TenantScope {
+    CURRENT_SCOPE.try_with(|scope| *scope).unwrap_or(TenantScope::System)
+}
+
+/// Ejecuta `fut` con el ámbito indicado. Las tareas lanzadas con `tokio::spawn`
+/// no lo heredan y vuelven a `System`; para conservarlo usar `spawn`.
+pub async fn with_scope<F: Future>(scope: TenantScope, fut: F) -> F::Output {
+    CURRENT_SCOPE.scope(scope, fut).await
+}
+
+/// Lanza `fut` en segundo plano con el ámbito de la tarea actual.
+pub fn spawn<F>(fut: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(with_scope(current_scope(), fut))
+}
+
+/// Ejecuta el handler de una petición con `scope` y mantiene ese ámbito
+/// mientras se sondea el cuerpo de la respuesta. Sin esto, los cuerpos en
+/// streaming (SSE, descargas) consultan la base de datos como `System` una vez
+/// que el middleware ha devuelto la respuesta.
+pub async fn scoped_response<F>(scope: TenantScope, fut: F) -> Response
+where
+    F: Future<Output = Response>,
+{
+    with_scope(scope, fut)
+        .await
+        .map(|inner| Body::new(ScopedBody { scope, inner }))
+}
+
+/// Cuerpo que sondea `inner` dentro de `scope`.
+struct ScopedBody {
+    scope: TenantScope,
+    inner: Body,
+}
+
+impl http_body::Body for ScopedBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        CURRENT_SCOPE.sync_scope(this.scope, || Pin::new(&mut this.inner).poll_frame(cx))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// Fija el rol y la organización de la sesión de `conn`.
+pub async fn apply_scope(conn: &mut PgConnection, scope: TenantScope) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT set_config('role', $1, false), set_config('app.current_org_id', $2, false)")
+        .bind(scope.role())
+        .bind(scope.org_setting())
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Opciones de pool que aplican el ámbito actual a cada conexión entregada.
+pub fn pool_options() -> PgPoolOptions {
+    PgPoolOptions::new()
+        .after_connect(|conn, _meta| Box::pin(async move { apply_scope(conn, current_scope()).await }))
+        .before_acquire(|conn, _meta| {
+            Box::pin(async move {
+                apply_scope(conn, current_scope()).await?;
+                Ok(true)
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn scope_is_task_local_and_defaults_to_system() {
+        let org = Uuid::new_v4();
+        assert_eq!(current_scope(), TenantScope::System);
+
+        let inner = with_scope(TenantScope::for_request(org, "instructor", org), async {
+            let spawned = tokio::spawn(async { current_scope() }).await.unwrap();
+            (current_scope(), spawned)
+        })
+        .await;
+        assert_eq!(inner, (TenantScope::Organization(org), TenantScope::System));
+
+        assert_eq!(
+            TenantScope::for_request(org, "admin", DEFAULT_ORG_ID),
+            TenantScope::PlatformAdmin(org)
+        );
+        assert_eq!(TenantScope::for_request(org, "admin", org), TenantScope::Organization(org));
+    }
+
+    #[tokio::test]
+    async fn spawn_inherits_the_current_scope() {
+        let org = Uuid::new_v4();
+        let spawned = with_scope(TenantScope::Organization(org), async {
+            spawn(async { current_scope() }).await.unwrap()
+        })
+        .await;
+        assert_eq!(spawned, TenantScope::Organization(org));
+    }
+
+    #[tokio::test]
+    async fn response_body_is_polled_within_the_request_scope() {
+        use http_body_util::BodyExt;
+
+        let org = Uuid::new_v4();
+        // El cuerpo se genera de forma perezosa, como un stream SSE.
+        let stream = futures_util::stream::once(async {
+            Ok::<_, std::convert::Infallible>(format!("{:?}", current_scope()))
+        });
+        let response = scoped_response(TenantScope::Organization(org), async move {
+            Response::new(Body::from_stream(stream))
+        })
+        .await;
+
+        // Se lee fuera de cualquier ámbito, como hace hyper tras el middleware.
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, format!("{:?}", TenantScope::Organization(org)));
+    }
+}
@@ -117,4 +117,38 @@ self.addEventListener("fetch", (event) => {
       })
     );
   }
-});
\ No newline at end of file
+});
+
+// Notificaciones Web Push enviadas por lms-service (payload JSON cifrado por el servidor)
+self.addEventListener("push", (event) => {
+  let data = {};
+  try {
+    data = event.data ? event.data.json() : {};
+  } catch {
+    data = { title: "OpenCCB", body: event.data ? event.data.text() : "" };
+  }
+
+  event.waitUntil(
+    self.registration.showNotification(data.title || "OpenCCB", {
+      body: data.body || "",
+      icon: "/pwa-icon-192.svg",
+      badge: "/pwa-icon-192.svg",
+      tag: data.id,
+      data: { url: data.url || "/" }
+    })
+  );
+});
+
+self.addEventListener("notificationclick", (event) => {
+  event.notification.close();
+  const target = event.notification.data && event.notification.data.url ? event.notification.data.url : "/";
+
+  event.waitUntil(
+    self.clients.matchAll({ type: "window", includeUncontrolled: true }).then((clients) => {
+      for (const client of clients) {
+        if (client.url === target && "focus" in client) return client.focus();
+      }
+      return self.clients.openWindow(target);
+    })
+  );
+});
@@ -1,9 +1,9 @@
 "use client";
 
 import { useEffect, useState } from "react";
-import { lmsApi, Lesson, Course, Module, UserGrade } from "@/lib/api";
+import { lmsApi, Lesson, Course, Module, UserGrade, ExamSession } from "@/lib/api";
 import Link from "next/link";
-import { ChevronLeft, ChevronRight, Menu, CheckCircle2, Bookmark } from "lucide-react";
+import { ChevronLeft, ChevronRight, Menu, CheckCircle2, Bookmark, Timer } from "lucide-react";
 import { useAuth } from "@/context/AuthContext";
 
 import DescriptionPlayer from "@/components/blocks/DescriptionPlayer";
@@ -44,6 +44,11 @@ export default function LessonPlayerPage({ params }: { params: { id: string, les
     const [userGrade, setUserGrade] = useState<UserGrade | null>(null);
     const [allGrades, setAllGrades] = useState<UserGrade[]>([]);
     const [isBookmarked, setIsBookmarked] = useState(false);
+    // Intento de examen con tiempo: respuestas por bloque que se autoguardan y se entregan juntas
+    const [examSession, setExamSession] = useState<ExamSession | null>(null);
+    const [examAnswers, setExamAnswers] = useState<Record<string, { score: number; answers: unknown }>>({});
+    const [examRemaining, setExamRemaining] = useState<number | null>(null);
+    const [examError, setExamError] = useState<string | null>(null);
     const { user } = useAuth();
     const userId = user?.id ?? null;
 
@@ -76,6 +81,28 @@ export default function LessonPlayerPage({ params }: { params: { id: string, les
         fetchAll();
     }, [params.id, params.lessonId, userId]);
 
+    useEffect(() => {
+        if (!examSession?.deadline_at) {
+            setExamRemaining(null);
+            return;
+        }
+        // El plazo lo fija el servidor; se corrige el desfase del reloj local
+        const skew = new Date(examSession.server_time).getTime() - Date.now();
+        const deadline = new Date(examSession.deadline_at).getTime();
+        const tick = () => {
+            const seconds = Math.max(0, Math.floor((deadline - (Date.now() + skew)) / 1000));
+            setExamRemaining(seconds);
+            if (seconds === 0) {
+                // Al vencer, el servidor entrega lo autoguardado
+                setExamSession(null);
+                setExamError("El tiempo del examen terminó. Se entregaron tus respuestas guardadas.");
+            }
+        };
+        tick();
+        const interval = setInterval(tick, 1000);
+        return () => clearInterval(interval);
+    }, [examSession]);
+
     useEffect(() => {
         if (typeof window === "undefined") return;
 
@@ -104,6 +131,19 @@ export default function LessonPlayerPage({ params }: { params: { id: string, les
         }
     };
 
+    const applyGrade = (res: UserGrade) => {
+        setUserGrade(res);
+        setAllGrades(prev => {
+            const idx = prev.findIndex(g => g.lesson_id === params.lessonId);
+            if (idx >= 0) {
+                const newGrades = [...prev];
+                newGrades[idx] = res;
+                return newGrades;
+            }
+            return [...prev, res];
+        });
+    };
+
     const handleBlockComplete = async (blockId: string, score: number) => {
         if (user) {
             try {
@@ -135,22 +175,54 @@ export default function LessonPlayerPage({ params }: { params: { id: string, les
                     newOverallScore,
                     { ...userGrade?.metadata, block_scores: newBlockScores }
                 );
-                setUserGrade(res);
-                setAllGrades(prev => {
-                    const idx = prev.findIndex(g => g.lesson_id === params.lessonId);
-                    if (idx >= 0) {
-                        const newGrades = [...prev];
-                        newGrades[idx] = res;
-                        return newGrades;
-                    }
-                    return [...prev, res];
-                });
+                applyGrade(res);
             } catch (err) {
                 console.error(`Failed to submit score for block ${blockId}`, err);
             }
         }
     };
 
+    const examAnswersMetadata = (answers: Record<string, { score: number; answers: unknown }>) => ({
+        quiz_answers: Object.fromEntries(Object.entries(answers).map(([blockId, a]) => [blockId, a.answers])),
+        block_scores: Object.fromEntries(Object.entries(answers).map(([blockId, a]) => [blockId, a.score])),
+    });
+
+    const examScore = (answers: Record<string, { score: number; answers: unknown }>) => {
+        const quizBlocks = (lesson.content_blocks || []).filter(b => b.type === 'quiz');
+        if (quizBlocks.length === 0) return 0;
+        return quizBlocks.reduce((sum, b) => sum + (answers[b.id]?.score || 0), 0) / quizBlocks.length;
+    };
+
+    const handleStartExam = async () => {
+        setExamError(null);
+        try {
+            setExamSession(await lmsApi.startExamAttempt(params.lessonId));
+        } catch (err) {
+            // El intento puede estar abierto en otra pestaña o dispositivo
+            if (!confirm("Si ya tienes el examen abierto en otra sesión, continuar aquí la cerrará. ¿Continuar el examen en esta sesión?")) {
+                setExamError(err instanceof Error ? err.message : "No se pudo iniciar el examen");
+                return;
+            }
+            try {
+                setExamSession(await lmsApi.startExamAttempt(params.lessonId, true));
+            } catch (takeoverErr) {
+                setExamError(takeoverErr instanceof Error ? takeoverErr.message : "No se pudo iniciar el examen");
+            }
+        }
+    };
+
+    const handleExamAttempt = async (blockId: string, score: number, answers: unknown) => {
+        if (!examSession) return;
+        const next = { ...examAnswers, [blockId]: { score, answers } };
+        setExamAnswers(next);
+        try {
+            await lmsApi.saveExamAnswers(examSession, examScore(next), examAnswersMetadata(next));
+        } catch (err) {
+            console.error("Error al autoguardar el examen", err);
+            setExamError(err instanceof Error ? err.message : "No se pudieron guardar tus respuestas");
+        }
+    };
+
     const handleToggleBookmark = async () => {
         try {
             await lmsApi.toggleBookmark(params.lessonId);
@@ -307,6 +379,13 @@ export default function LessonPlayerPage({ params }: { params: { id: string, les
                                     </div>
                                 </div>
                                 <h1 id="lesson-title" className="text-4xl font-black tracking-tighter text-gray-900 dark:text-white">{lesson.title}</h1>
+                                {examSession && examRemaining !== null && (
+                                    <div role="timer" aria-live="off" className={`inline-flex items-center gap-2 px-4 py-2 rounded-xl text-sm font-black tabular-nums ${examRemaining < 300 ? 'bg-red-500/10 text-red-600 dark:text-red-400' : 'bg-blue-500/10 text-blue-600 dark:text-blue-400'}`}>
+                                        <Timer className="w-4 h-4" />
+                                        {Math.floor(examRemaining / 60)}:{String(examRemaining % 60).padStart(2, '0')}
+                                    </div>
+                                )}
+                                {examSession && examError && <p role="alert" className="text-sm text-red-500">{examError}</p>}
                             </div>
 
                             {lesson.summary && (
@@ -338,6 +417,23 @@ export default function LessonPlayerPage({ params }: { params: { id: string, les
                                     grade={userGrade}
                                     maxAttempts={lesson.max_attempts}
                                 />
+                            ) : lesson.metadata?.exam && !examSession ? (
+                                <div className="p-10 rounded-3xl glass border border-black/10 dark:border-white/10 text-center space-y-6">
+                                    <Timer className="w-10 h-10 mx-auto text-blue-600 dark:text-blue-400" />
+                                    <p className="text-sm font-bold text-gray-700 dark:text-gray-300">
+                                        {lesson.metadata.exam.time_limit_minutes
+                                            ? `Este examen tiene un límite de ${lesson.metadata.exam.time_limit_minutes} minutos desde que lo inicies.`
+                                            : "Este examen debe iniciarse para poder responderlo."}
+                                    </p>
+                                    {examError && <p role="alert" className="text-sm text-red-500">{examError}</p>}
+                                    <button
+                                        type="button"
+                                        onClick={handleStartExam}
+                                        className="btn-premium px-12 py-4 rounded-2xl focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-blue-500/70"
+                                    >
+                                        <span className="font-black italic">INICIAR EXAMEN</span>
+                                    </button>
+                                </div>
                             ) : (
                                 (lesson.content_blocks || []).length > 0 ? (
                                     <div className="space-y-24">
@@ -414,7 +510,9 @@ export default function LessonPlayerPage({ params }: { params: { id: string, les
                                                                         : undefined
                                                                 }
                                                                 onAttempt={async (score, answers) => {
-                                                                    if (user) {
+                                                                    if (examSession) {
+                                                                        await handleExamAttempt(block.id, score, answers);
+                                                                    } else if (user) {
                                                                         try {
                                                                             // Submit the score for this specific quiz block
                                                                             const res = await lmsApi.submitScore(
@@ -468,22 +566,24 @@ export default function LessonPlayerPage({ params }: { params: { id: string, les
                                                         return (
                                                             <CodeExercisePlayer
                                                                 lessonId={params.lessonId}
+                                                                blockId={block.id}
                                                                 title={block.title}
                                                                 instructions={block.instructions || ""}
                                                                 initialCode={block.initial_code || ""}
                                                                 language={block.language}
                                                                 testCases={block.test_cases}
-                                                                onComplete={(score) => handleBlockComplete(block.id, score)}
+                                                                onGraded={applyGrade}
                                                             />
                                                         );
                                                     case 'code':
                                                         return (
                                                             <CodeExercisePlayer
                                                                 lessonId={params.lessonId}
+                                                                blockId={block.id}
                                                                 title={block.title}
                                                                 instructions={block.instructions || ""}
                                                                 initialCode={block.initialCode || ""}
-                                                                onComplete={(score) => handleBlockComplete(block.id, score)}
+                                                                onGraded={applyGrade}
                                                             />
                                                         );
                                                     case 'hotspot':
@@ -530,7 +630,9 @@ export default function LessonPlayerPage({ params }: { params: { id: string, les
                                                     case 'plugin':
                                                         return (
                                                             <PluginBlock
-                                                                pluginId={block.id}
+                                                                pluginId={block.plugin_id}
+                                                                blockId={block.id}
+                                                                lessonId={params.lessonId}
                                                                 name={block.title || 'Plugin'}
                                                                 componentUrl={block.component_url || ''}
                                                                 config={block.config as Record<string, unknown> | undefined}
@@ -603,6 +705,22 @@ export default function LessonPlayerPage({ params }: { params: { id: string, les
                                                 onClick={async () => {
                                                     if (user) {
                                                         try {
+                                                            if (lesson.metadata?.exam) {
+                                                                if (!examSession) return;
+                                                                const res = await lmsApi.submitScore(
+                                                                    user.id,
+                                                                    params.id,
+                                                                    params.lessonId,
+                                                                    examScore(examAnswers),
+                                                                    examAnswersMetadata(examAnswers),
+                                                                    examSession
+                                                                );
+                                                                setExamSession(null);
+                                                                setExamAnswers({});
+                                                                applyGrade(res);
+                                                                alert("¡Examen entregado con éxito!");
+                                                                return;
+                                                            }
                                                             // In a real scenario, we'd calculate the actual score from blocks
                                                             const res = await lmsApi.submitScore(user.id, params.id, params.lessonId, 1.0);
                                                             setUserGrade(res);
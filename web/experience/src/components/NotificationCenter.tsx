@@ -1,15 +1,33 @@
 "use client";
 
 import { useState, useEffect, useRef } from "react";
-import { lmsApi, getLmsApiUrl, getToken, Notification } from "@/lib/api";
+import { lmsApi, getLmsApiUrl, getToken, Notification, PushSubscriptionPayload } from "@/lib/api";
 import { useAuth } from "@/context/AuthContext";
-import { Bell, X, Calendar, Info, AlertTriangle, CheckCircle2 } from "lucide-react";
+import { Bell, BellOff, BellRing, X, Calendar, Info, AlertTriangle, CheckCircle2 } from "lucide-react";
 import Link from "next/link";
 
+type PushState = "unsupported" | "denied" | "off" | "on";
+
+// applicationServerKey espera los bytes de la clave VAPID, no su base64url
+function base64UrlToBytes(value: string): ArrayBuffer {
+    const padded = (value + "=".repeat((4 - (value.length % 4)) % 4)).replace(/-/g, "+").replace(/_/g, "/");
+    const raw = atob(padded);
+    const buffer = new ArrayBuffer(raw.length);
+    const bytes = new Uint8Array(buffer);
+    for (let i = 0; i < raw.length; i++) bytes[i] = raw.charCodeAt(i);
+    return buffer;
+}
+
+function pushSupported(): boolean {
+    return typeof window !== "undefined" && "serviceWorker" in navigator && "PushManager" in window && "Notification" in window;
+}
+
 export default function NotificationCenter() {
     const [notifications, setNotifications] = useState<Notification[]>([]);
     const [isOpen, setIsOpen] = useState(false);
     const [loading, setLoading] = useState(true);
+    const [pushState, setPushState] = useState<PushState>("unsupported");
+    const [pushBusy, setPushBusy] = useState(false);
     const sseRef = useRef<EventSource | null>(null);
 
     const { user } = useAuth();
@@ -47,6 +65,67 @@ export default function NotificationCenter() {
         };
     }, [user]);
 
+    useEffect(() => {
+        if (!user || !pushSupported()) return;
+        if (window.Notification.permission === "denied") {
+            setPushState("denied");
+            return;
+        }
+
+        // Si el dispositivo ya está suscrito, se vuelve a registrar para el usuario actual
+        navigator.serviceWorker.ready
+            .then((registration) => registration.pushManager.getSubscription())
+            .then((subscription) => {
+                if (!subscription) {
+                    setPushState("off");
+                    return;
+                }
+                setPushState("on");
+                lmsApi.registerPushSubscription(subscription.toJSON() as PushSubscriptionPayload).catch(() => {});
+            })
+            .catch(() => setPushState("off"));
+    }, [user]);
+
+    const enablePush = async () => {
+        setPushBusy(true);
+        try {
+            const permission = await window.Notification.requestPermission();
+            if (permission !== "granted") {
+                setPushState(permission === "denied" ? "denied" : "off");
+                return;
+            }
+            const { public_key } = await lmsApi.getVapidPublicKey();
+            const registration = await navigator.serviceWorker.ready;
+            const subscription = await registration.pushManager.subscribe({
+                userVisibleOnly: true,
+                applicationServerKey: base64UrlToBytes(public_key),
+            });
+            await lmsApi.registerPushSubscription(subscription.toJSON() as PushSubscriptionPayload);
+            setPushState("on");
+        } catch (err) {
+            console.error("Failed to enable push notifications", err);
+        } finally {
+            setPushBusy(false);
+        }
+    };
+
+    const disablePush = async () => {
+        setPushBusy(true);
+        try {
+            const registration = await navigator.serviceWorker.ready;
+            const subscription = await registration.pushManager.getSubscription();
+            if (subscription) {
+                await lmsApi.removePushSubscription(subscription.endpoint).catch(() => {});
+                await subscription.unsubscribe();
+            }
+            setPushState("off");
+        } catch (err) {
+            console.error("Failed to disable push notifications", err);
+        } finally {
+            setPushBusy(false);
+        }
+    };
+
     const markAsRead = async (id: string) => {
         try {
             await lmsApi.markNotificationAsRead(id);
@@ -97,13 +176,26 @@ export default function NotificationCenter() {
                     >
                         <div className="p-4 border-b border-white/5 flex items-center justify-between bg-white/5">
                             <h3 className="text-sm font-black uppercase tracking-widest text-white">Notificaciones</h3>
-                            <button
-                                onClick={() => setIsOpen(false)}
-                                className="text-gray-500 hover:text-white p-1"
-                                aria-label="Close notifications"
-                            >
-                                <X size={18} />
-                            </button>
+                            <div className="flex items-center gap-1">
+                                {pushState !== "unsupported" && (
+                                    <button
+                                        onClick={() => (pushState === "on" ? disablePush() : enablePush())}
+                                        disabled={pushBusy || pushState === "denied"}
+                                        className="text-gray-500 hover:text-white p-1 disabled:opacity-40"
+                                        aria-label={pushState === "on" ? "Desactivar notificaciones push" : "Activar notificaciones push"}
+                                        title={pushState === "denied" ? "Las notificaciones están bloqueadas en el navegador" : undefined}
+                                    >
+                                        {pushState === "on" ? <BellRing size={18} /> : <BellOff size={18} />}
+                                    </button>
+                                )}
+                                <button
+                                    onClick={() => setIsOpen(false)}
+                                    className="text-gray-500 hover:text-white p-1"
+                                    aria-label="Close notifications"
+                                >
+                                    <X size={18} />
+                                </button>
+                            </div>
                         </div>
 
                         <div className="max-h-[400px] overflow-y-auto">
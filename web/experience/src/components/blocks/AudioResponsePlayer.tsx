@@ -1,7 +1,7 @@
 "use client";
 
 import { useState, useRef, useEffect } from "react";
-import { lmsApi } from "@/lib/api";
+import { lmsApi, PronunciationAssessment } from "@/lib/api";
 import { Mic, Square, Play, RotateCcw, Check, X, Clock, BrainCircuit } from "lucide-react";
 
 interface AudioResponsePlayerProps {
@@ -31,7 +31,7 @@ export default function AudioResponsePlayer({
     const [isTranscribing, setIsTranscribing] = useState(false);
     const [recordingTime, setRecordingTime] = useState(0);
     const [submitted, setSubmitted] = useState(false);
-    const [evaluation, setEvaluation] = useState<{ score: number; foundKeywords: string[]; feedback: string } | null>(null);
+    const [evaluation, setEvaluation] = useState<{ score: number; foundKeywords: string[]; feedback: string; pronunciation?: PronunciationAssessment } | null>(null);
 
     const mediaRecorderRef = useRef<MediaRecorder | null>(null);
     const audioChunksRef = useRef<Blob[]>([]);
@@ -216,7 +216,8 @@ export default function AudioResponsePlayer({
             setEvaluation({
                 score: result.score,
                 foundKeywords: result.found_keywords,
-                feedback: result.feedback
+                feedback: result.feedback,
+                pronunciation: result.pronunciation
             });
             setSubmitted(true);
 
@@ -414,6 +415,40 @@ export default function AudioResponsePlayer({
                                     </div>
                                 </div>
                             )}
+
+                            {evaluation.pronunciation && (
+                                <div className="space-y-3 mt-4">
+                                    <p className="text-xs text-gray-500 uppercase tracking-wider">Pronunciation:</p>
+                                    <div className="grid grid-cols-3 gap-2 text-center">
+                                        {([
+                                            ['Accuracy', evaluation.pronunciation.accuracy],
+                                            ['Fluency', evaluation.pronunciation.fluency],
+                                            ['Completeness', evaluation.pronunciation.completeness],
+                                        ] as const).map(([label, value]) => (
+                                            <div key={label} className="p-2 bg-black/5 dark:bg-white/5 rounded-lg">
+                                                <p className="text-lg font-black text-gray-900 dark:text-white">{Math.round(value)}</p>
+                                                <p className="text-[10px] text-gray-500 uppercase tracking-wider">{label}</p>
+                                            </div>
+                                        ))}
+                                    </div>
+                                    <div className="flex flex-wrap gap-1">
+                                        {evaluation.pronunciation.words.filter(w => w.error !== 'inserted').map((w, i) => (
+                                            <span
+                                                key={i}
+                                                title={w.spoken ? `"${w.spoken}" · ${Math.round(w.accuracy)}%` : 'Omitted'}
+                                                className={`px-2 py-0.5 rounded text-sm font-bold ${w.error === 'correct'
+                                                    ? 'text-green-700 dark:text-green-300'
+                                                    : w.error === 'mispronounced'
+                                                        ? 'bg-yellow-500/20 text-yellow-800 dark:text-yellow-300'
+                                                        : 'line-through text-red-600 dark:text-red-400'
+                                                    }`}
+                                            >
+                                                {w.expected}
+                                            </span>
+                                        ))}
+                                    </div>
+                                </div>
+                            )}
                         </div>
 
                         {evaluation.feedback && (
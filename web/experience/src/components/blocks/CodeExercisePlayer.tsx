@@ -1,28 +1,56 @@
 "use client";
 
 import React, { useState } from "react";
-import { Play, CheckCircle, XCircle, Code2, RefreshCcw, Wand2, Sparkles, Loader2 } from "lucide-react";
-import { lmsApi } from "@/lib/api";
+import { Play, CheckCircle, XCircle, Code2, RefreshCcw, Wand2, Sparkles, Loader2, Send } from "lucide-react";
+import { lmsApi, CodeLabTestResult, UserGrade } from "@/lib/api";
 
 interface CodeExercisePlayerProps {
     lessonId: string;
+    blockId: string;
     title: string;
     instructions: string;
     initialCode: string;
     language?: string;
-    testCases?: { description: string; expected: string }[];
+    testCases?: { description: string; input?: string; expected: string }[];
     expectedOutput?: string;
-    onComplete: (score: number) => void;
+    /** Submitting a run grades the block on the server and returns the updated lesson grade */
+    onGraded: (grade: UserGrade) => void;
+}
+
+const STATUS_LABELS: Record<CodeLabTestResult["status"], string> = {
+    passed: "Superada",
+    failed: "Salida incorrecta",
+    runtime_error: "Error de ejecución",
+    timeout: "Tiempo agotado",
+    compile_error: "Error de compilación",
+    sandbox_error: "Ejecutor no disponible",
+};
+
+function formatResults(results: CodeLabTestResult[]): string {
+    return results
+        .map((r, idx) => {
+            const name = r.hidden ? `Prueba oculta ${idx + 1}` : r.description || `Prueba ${idx + 1}`;
+            const lines = [`${r.status === "passed" ? "✅" : "❌"} ${name}: ${STATUS_LABELS[r.status]}`];
+            if (r.status !== "passed" && !r.hidden) {
+                if (r.input) lines.push(`   Entrada: ${r.input.trim()}`);
+                if (r.expected !== null) lines.push(`   Esperado: ${r.expected.trim()}`);
+                if (r.actual !== null) lines.push(`   Obtenido: ${r.actual.trim()}`);
+            }
+            if (r.stderr) lines.push(r.stderr.trim());
+            return lines.join("\n");
+        })
+        .join("\n\n");
 }
 
 export default function CodeExercisePlayer({
     lessonId,
+    blockId,
     title,
     instructions,
     initialCode,
     language = "python",
     testCases = [],
-    onComplete
+    onGraded
 }: CodeExercisePlayerProps) {
     const [code, setCode] = useState(initialCode);
     const [output, setOutput] = useState<string | null>(null);
@@ -30,24 +58,45 @@ export default function CodeExercisePlayer({
     const [hint, setHint] = useState<string | null>(null);
     const [isGettingHint, setIsGettingHint] = useState(false);
 
-    const runCode = () => {
+    const [score, setScore] = useState<number | null>(null);
+    // Última ejecución: ejecutar no califica, se entrega explícitamente
+    const [lastRunId, setLastRunId] = useState<string | null>(null);
+    const [submitting, setSubmitting] = useState(false);
+    const [submitNote, setSubmitNote] = useState<string | null>(null);
+
+    const runCode = async () => {
         setStatus("running");
         setOutput("Ejecutando pruebas...\n");
         setHint(null);
+        setSubmitNote(null);
 
-        setTimeout(() => {
-            // Mock validation logic
-            const isCorrect = code.trim() !== initialCode.trim();
-
-            if (isCorrect) {
-                setStatus("success");
-                setOutput("✅ ¡Pruebas superadas!\n\nSalida:\n" + (testCases[0]?.expected || "Hello, World!"));
-                onComplete(1.0);
-            } else {
-                setStatus("error");
-                setOutput("❌ Las pruebas fallaron.\n\nError:\nAssertionError: El resultado no coincide con lo esperado.");
-            }
-        }, 1500);
+        try {
+            const run = await lmsApi.runCodeLab(lessonId, blockId, code);
+            const summary = `${run.passed}/${run.total} pruebas superadas\n\n${formatResults(run.results)}`;
+            setScore(run.score);
+            setLastRunId(run.run_id);
+            setOutput(summary);
+            setStatus(run.passed === run.total ? "success" : "error");
+        } catch (error) {
+            setStatus("error");
+            setScore(null);
+            setLastRunId(null);
+            setOutput(`❌ No se pudo ejecutar el código.\n\n${error instanceof Error ? error.message : ""}`);
+        }
+    };
+
+    const submitRun = async () => {
+        if (!lastRunId || submitting) return;
+        setSubmitting(true);
+        try {
+            onGraded(await lmsApi.submitCodeLabRun(lessonId, blockId, lastRunId));
+            setLastRunId(null);
+            setSubmitNote("✅ Entrega registrada.");
+        } catch (error) {
+            setSubmitNote(`⚠️ No se pudo entregar: ${error instanceof Error ? error.message : ""}`);
+        } finally {
+            setSubmitting(false);
+        }
     };
 
     const getAIHint = async () => {
@@ -74,6 +123,9 @@ export default function CodeExercisePlayer({
         setStatus("idle");
         setOutput(null);
         setHint(null);
+        setScore(null);
+        setLastRunId(null);
+        setSubmitNote(null);
     };
 
     return (
@@ -99,6 +151,7 @@ export default function CodeExercisePlayer({
                             {testCases.map((tc, idx) => (
                                 <div key={idx} className="p-3 rounded-xl bg-black/5 dark:bg-white/5 border border-black/5 dark:border-white/5 flex flex-col gap-1">
                                     <span className="text-[10px] font-bold text-gray-500">{tc.description}</span>
+                                    {tc.input && <code className="text-[10px] text-gray-500 font-mono">Entrada: {tc.input}</code>}
                                     <code className="text-[10px] text-indigo-500 font-mono">Esperado: {tc.expected}</code>
                                 </div>
                             ))}
@@ -111,7 +164,7 @@ export default function CodeExercisePlayer({
                 {/* Editor Area */}
                 <div className="flex flex-col rounded-2xl overflow-hidden border border-black/5 dark:border-white/5 bg-[#1a1c21]">
                     <div className="px-4 py-2 bg-black/40 dark:bg-white/5 border-b border-black/5 dark:border-white/5 flex items-center justify-between text-[10px] font-black uppercase tracking-widest text-gray-400 dark:text-gray-500">
-                        <span>main.{language === 'python' ? 'py' : language === 'javascript' ? 'js' : language === 'sql' ? 'sql' : 'sh'}</span>
+                        <span>main.{language === 'python' ? 'py' : language === 'javascript' ? 'js' : language === 'rust' ? 'rs' : language === 'sql' ? 'sql' : 'sh'}</span>
                         <div className="flex gap-2">
                             <div className="w-2 h-2 rounded-full bg-red-500/20" />
                             <div className="w-2 h-2 rounded-full bg-amber-500/20" />
@@ -126,7 +179,7 @@ export default function CodeExercisePlayer({
                     />
                     <div className="p-4 bg-black/40 dark:bg-black/20 border-t border-black/5 dark:border-white/5 flex gap-2">
                         <button
-                            onClick={runCode}
+                            onClick={() => void runCode()}
                             disabled={status === "running"}
                             className="flex-[2] py-2.5 bg-indigo-600 hover:bg-indigo-500 disabled:opacity-50 text-white rounded-xl font-bold flex items-center justify-center gap-2 transition-all shadow-lg shadow-indigo-500/20"
                         >
@@ -137,6 +190,15 @@ export default function CodeExercisePlayer({
                             )}
                             Ejecutar Código
                         </button>
+                        <button
+                            onClick={() => void submitRun()}
+                            disabled={!lastRunId || submitting || status === "running"}
+                            className="flex-1 py-2.5 bg-green-600 hover:bg-green-500 disabled:opacity-50 text-white rounded-xl font-bold flex items-center justify-center gap-2 transition-all"
+                            title="Entrega la última ejecución para calificar"
+                        >
+                            {submitting ? <Loader2 size={16} className="animate-spin" /> : <Send size={16} />}
+                            Entregar
+                        </button>
                         <button
                             onClick={getAIHint}
                             disabled={isGettingHint || status === "running"}
@@ -171,6 +233,8 @@ export default function CodeExercisePlayer({
                             </pre>
                         )}
 
+                        {submitNote && <p className="mt-4 text-sm text-gray-400">{submitNote}</p>}
+
                         {hint && (
                             <div className="mt-6 p-4 rounded-xl bg-indigo-500/10 border border-indigo-500/20 animate-in slide-in-from-top-2 duration-300">
                                 <div className="flex items-center gap-2 mb-1">
@@ -197,7 +261,9 @@ export default function CodeExercisePlayer({
                             <XCircle className="text-red-400" />
                             <div>
                                 <div className="text-sm font-bold text-red-400">Falla en la Ejecución</div>
-                                <p className="text-[10px] text-red-500/80 uppercase font-black tracking-widest">Intenta de nuevo</p>
+                                <p className="text-[10px] text-red-500/80 uppercase font-black tracking-widest">
+                                    {score !== null ? `Puntaje: ${Math.round(score)}% · ` : ""}Intenta de nuevo
+                                </p>
                             </div>
                         </div>
                     )}
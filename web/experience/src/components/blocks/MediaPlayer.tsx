@@ -1,8 +1,8 @@
 "use client";
 
-import { useState, useEffect } from "react";
-import { Play, Lock, AlertCircle } from "lucide-react";
-import { lmsApi, getCmsApiUrl, getImageUrl } from "@/lib/api";
+import { useState, useEffect, useRef } from "react";
+import { Play, Lock, AlertCircle, Captions } from "lucide-react";
+import { lmsApi, getCmsApiUrl, getImageUrl, SubtitleLanguage } from "@/lib/api";
 
 interface MediaPlayerProps {
     id: string;
@@ -38,6 +38,9 @@ export default function MediaPlayer({ id, lessonId, title, url, media_type, conf
     const [lastTime, setLastTime] = useState(0);
     const [feedback, setFeedback] = useState<{ isCorrect: boolean } | null>(null);
     const [a11yStatus, setA11yStatus] = useState("");
+    const [subtitles, setSubtitles] = useState<SubtitleLanguage[]>([]);
+    const [subtitleLang, setSubtitleLang] = useState<string>("off");
+    const videoRef = useRef<HTMLVideoElement>(null);
 
     useEffect(() => {
         if (initialPlayCount !== undefined) {
@@ -46,6 +49,23 @@ export default function MediaPlayer({ id, lessonId, title, url, media_type, conf
     }, [initialPlayCount]);
 
     const maxPlays = config?.maxPlays || 0;
+    const showSubtitles = !!lessonId && !!hasTranscription && config?.show_transcript !== false;
+
+    useEffect(() => {
+        if (!showSubtitles || !lessonId) return;
+        lmsApi.getAvailableSubtitles(lessonId)
+            .then(setSubtitles)
+            .catch(() => setSubtitles([]));
+    }, [showSubtitles, lessonId]);
+
+    // Solo una pista visible a la vez; el resto queda deshabilitado
+    useEffect(() => {
+        const video = videoRef.current;
+        if (!video) return;
+        Array.from(video.textTracks).forEach(track => {
+            track.mode = track.language === subtitleLang ? 'showing' : 'disabled';
+        });
+    }, [subtitleLang, subtitles]);
 
 
 
@@ -135,23 +155,42 @@ export default function MediaPlayer({ id, lessonId, title, url, media_type, conf
     const getToken = () => null; // Token se envía automáticamente via httpOnly cookie
     const selectedOrgId = typeof window !== 'undefined' ? localStorage.getItem('experience_selected_org_id') : null;
 
-    // Construct VTT URLs with auth if possible, or assume public/handled by backend
-    // Since browser <track> doesn't support custom headers easily, 
-    // we might need to handle this via a proxy or temporary signed URLs.
-    // For now, we'll assume the backend allows VTT access if requested with the correct lesson ID.
-    const vttEn = lessonId ? `${getCmsApiUrl()}/lessons/${lessonId}/vtt?lang=en` : null;
-    const vttEs = lessonId ? `${getCmsApiUrl()}/lessons/${lessonId}/vtt?lang=es` : null;
+    // <track> no admite cabeceras personalizadas; el backend autentica la petición VTT por cookie.
+    // Solo se listan el idioma original y las pistas aprobadas.
+    const vttUrl = (lang: string) => `${getCmsApiUrl()}/lessons/${lessonId}/vtt?lang=${encodeURIComponent(lang)}`;
 
     return (
         <div className="space-y-6" id={id}>
             <p className="sr-only" aria-live="polite" aria-atomic="true">{a11yStatus}</p>
             <div className="flex items-center justify-between">
                 <h3 className="text-xs font-black uppercase tracking-widest text-gray-500 dark:text-gray-400">{title || "Contenido Multimedia"}</h3>
-                {maxPlays > 0 && (
-                    <span className="text-[10px] font-bold uppercase tracking-widest px-3 py-1 rounded-full bg-black/5 dark:bg-white/5 border border-black/5 dark:border-white/5 text-gray-500 dark:text-gray-400">
-                        {playCount} / {maxPlays} REPRODUCCIONES
-                    </span>
-                )}
+                <div className="flex items-center gap-3">
+                    {isLocalFile && !imageType && subtitles.length > 0 && (
+                        <label className="flex items-center gap-2 text-[10px] font-bold uppercase tracking-widest text-gray-500 dark:text-gray-400">
+                            <Captions size={14} aria-hidden="true" />
+                            <span className="sr-only">Idioma de subtítulos</span>
+                            <select
+                                value={subtitleLang}
+                                onChange={(e) => {
+                                    setSubtitleLang(e.target.value);
+                                    const selected = subtitles.find(s => s.language === e.target.value);
+                                    setA11yStatus(selected ? `Subtítulos en ${selected.label}.` : "Subtítulos desactivados.");
+                                }}
+                                className="px-2 py-1 rounded-lg bg-black/5 dark:bg-white/5 border border-black/5 dark:border-white/10 text-gray-700 dark:text-gray-200 focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-blue-500/70"
+                            >
+                                <option value="off">Sin subtítulos</option>
+                                {subtitles.map(s => (
+                                    <option key={s.language} value={s.language}>{s.label}</option>
+                                ))}
+                            </select>
+                        </label>
+                    )}
+                    {maxPlays > 0 && (
+                        <span className="text-[10px] font-bold uppercase tracking-widest px-3 py-1 rounded-full bg-black/5 dark:bg-white/5 border border-black/5 dark:border-white/5 text-gray-500 dark:text-gray-400">
+                            {playCount} / {maxPlays} REPRODUCCIONES
+                        </span>
+                    )}
+                </div>
             </div>
 
             <div className="glass-card !p-2 overflow-hidden aspect-video relative group">
@@ -163,6 +202,7 @@ export default function MediaPlayer({ id, lessonId, title, url, media_type, conf
                     />
                 ) : isLocalFile ? (
                     <video
+                        ref={videoRef}
                         src={getFullUrl(url)}
                         controls
                         crossOrigin="anonymous"
@@ -196,12 +236,9 @@ export default function MediaPlayer({ id, lessonId, title, url, media_type, conf
                             }
                         }}
                     >
-                        {hasTranscription && config?.show_transcript !== false && vttEn && (
-                            <track kind="subtitles" src={vttEn} srcLang="en" label="English" />
-                        )}
-                        {hasTranscription && config?.show_transcript !== false && vttEs && (
-                            <track kind="subtitles" src={vttEs} srcLang="es" label="Español" />
-                        )}
+                        {showSubtitles && subtitles.map(s => (
+                            <track key={s.language} kind="subtitles" src={vttUrl(s.language)} srcLang={s.language} label={s.label} />
+                        ))}
                     </video>
                 ) : (
                     <iframe
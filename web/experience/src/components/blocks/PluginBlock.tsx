@@ -1,49 +1,102 @@
 "use client";
 
-import React, { useEffect, useRef, useState } from "react";
+import React, { useCallback, useEffect, useRef, useState } from "react";
 import { Puzzle, AlertTriangle, ExternalLink } from "lucide-react";
+import { getLmsApiUrl, lmsApi } from "@/lib/api";
 
 interface PluginBlockProps {
-    pluginId: string;
+    /** Plugin instalado en la organización; sin él no se emite token */
+    pluginId?: string;
+    blockId: string;
+    lessonId: string;
     name: string;
     componentUrl: string;
     config?: Record<string, unknown>;
 }
 
+/** Versión del contrato postMessage documentado en docs/PLUGINS.md */
+const PLUGIN_PROTOCOL_VERSION = 1;
+
 /**
  * Renderiza un Web Component externo dentro de un iframe sandboxed.
  * El sandbox permite scripts y same-origin pero bloquea navegación superior,
  * formularios externos y acceso a cámara/micrófono sin permiso explícito.
+ *
+ * Al cargar envía `OPENCCB_PLUGIN_INIT` con un token de plugin de corta duración
+ * limitado a los permisos de su manifiesto, y lo renueva cuando el plugin envía
+ * `OPENCCB_PLUGIN_TOKEN_REFRESH`.
  */
-export default function PluginBlock({ pluginId, name, componentUrl, config = {} }: PluginBlockProps) {
+export default function PluginBlock({ pluginId, blockId, lessonId, name, componentUrl, config = {} }: PluginBlockProps) {
     const iframeRef = useRef<HTMLIFrameElement>(null);
     const [error, setError] = useState<string | null>(null);
     const [loaded, setLoaded] = useState(false);
+    const [height, setHeight] = useState(400);
 
     // Solo permitir HTTPS
     const isSecure = componentUrl.startsWith("https://");
 
+    const mintToken = useCallback(async () => {
+        if (!pluginId) return null;
+        try {
+            const minted = await lmsApi.mintPluginToken(pluginId, lessonId, blockId);
+            return {
+                token: minted.token,
+                expiresAt: minted.expires_at,
+                permissions: minted.permissions,
+                apiBase: `${getLmsApiUrl()}${minted.api_base}`,
+            };
+        } catch {
+            setError("No se pudo autorizar el plugin.");
+            return null;
+        }
+    }, [pluginId, lessonId, blockId]);
+
     useEffect(() => {
         if (!isSecure) {
             setError("Este plugin no puede cargarse: la URL debe usar HTTPS.");
             return;
         }
 
-        // Enviar config al iframe cuando cargue vía postMessage
-        const handleLoad = () => {
+        const origin = new URL(componentUrl).origin;
+        const post = (message: Record<string, unknown>) =>
+            iframeRef.current?.contentWindow?.postMessage(message, origin);
+
+        // Enviar config y credenciales al iframe cuando cargue vía postMessage
+        const handleLoad = async () => {
             setLoaded(true);
-            iframeRef.current?.contentWindow?.postMessage(
-                { type: "OPENCCB_PLUGIN_CONFIG", pluginId, config },
-                new URL(componentUrl).origin
-            );
+            post({ type: "OPENCCB_PLUGIN_CONFIG", pluginId: pluginId ?? blockId, config });
+            const session = await mintToken();
+            post({
+                type: "OPENCCB_PLUGIN_INIT",
+                version: PLUGIN_PROTOCOL_VERSION,
+                pluginId: pluginId ?? null,
+                lessonId,
+                blockId,
+                config,
+                ...(session ?? { token: null, expiresAt: null, permissions: [], apiBase: null }),
+            });
+        };
+
+        // Solo se atienden mensajes del iframe de este bloque y de su origen
+        const handleMessage = async (event: MessageEvent) => {
+            if (event.origin !== origin || event.source !== iframeRef.current?.contentWindow) return;
+            const data = event.data as { type?: string; height?: number } | null;
+            if (data?.type === "OPENCCB_PLUGIN_TOKEN_REFRESH") {
+                const session = await mintToken();
+                if (session) post({ type: "OPENCCB_PLUGIN_TOKEN", version: PLUGIN_PROTOCOL_VERSION, ...session });
+            } else if (data?.type === "OPENCCB_PLUGIN_RESIZE" && typeof data.height === "number") {
+                setHeight(Math.min(Math.max(data.height, 200), 2000));
+            }
         };
 
         const iframe = iframeRef.current;
-        if (iframe) {
-            iframe.addEventListener("load", handleLoad);
-            return () => iframe.removeEventListener("load", handleLoad);
-        }
-    }, [componentUrl, config, isSecure, pluginId]);
+        window.addEventListener("message", handleMessage);
+        iframe?.addEventListener("load", handleLoad);
+        return () => {
+            window.removeEventListener("message", handleMessage);
+            iframe?.removeEventListener("load", handleLoad);
+        };
+    }, [componentUrl, config, isSecure, pluginId, blockId, lessonId, mintToken]);
 
     if (!isSecure) {
         return (
@@ -86,7 +139,7 @@ export default function PluginBlock({ pluginId, name, componentUrl, config = {}
                 src={componentUrl}
                 title={name}
                 className={`w-full transition-opacity duration-300 ${loaded ? "opacity-100" : "opacity-0 h-0"}`}
-                style={{ minHeight: loaded ? "400px" : "0px", border: "none" }}
+                style={{ minHeight: loaded ? `${height}px` : "0px", border: "none" }}
                 sandbox="allow-scripts allow-same-origin allow-forms allow-popups"
                 loading="lazy"
                 onError={() => setError("No se pudo cargar el plugin.")}
@@ -115,11 +115,37 @@ export interface RecommendationResponse {
     recommendations: Recommendation[];
 }
 
+export interface PronunciationWord {
+    expected: string | null;
+    spoken: string | null;
+    start: number | null;
+    end: number | null;
+    accuracy: number;
+    error: 'correct' | 'mispronounced' | 'omitted' | 'inserted';
+}
+
+export interface PronunciationAssessment {
+    expected_text: string;
+    score: number;
+    accuracy: number;
+    fluency: number;
+    completeness: number;
+    fluency_metrics: {
+        speech_rate_wpm: number;
+        speaking_seconds: number;
+        pause_count: number;
+        long_pause_count: number;
+        total_pause_seconds: number;
+    };
+    words: PronunciationWord[];
+}
+
 export interface AudioGradingResponse {
     score: number;
     found_keywords: string[];
     feedback: string;
     transcript?: string;
+    pronunciation?: PronunciationAssessment;
 }
 
 export interface Course {
@@ -201,6 +227,7 @@ export interface Block {
     instructions?: string;
     initialCode?: string;
     keywords?: string[];
+    expectedText?: string; // Texto de referencia para evaluar la pronunciación
     timeLimit?: number;
     description?: string;
     reviewCriteria?: string;
@@ -224,12 +251,13 @@ export interface Block {
     language?: string;
     initial_code?: string;
     solution?: string;
-    test_cases?: { description: string; expected: string }[];
+    test_cases?: { description: string; input?: string; expected: string }[];
     // SCORM/xAPI fields
     launch_url?: string;
     metadata?: any;
     // Plugin fields
     component_url?: string;
+    plugin_id?: string;
 }
 
 export interface OrgPlugin {
@@ -245,6 +273,33 @@ export interface OrgPlugin {
     updated_at: string;
 }
 
+export interface CodeLabTestResult {
+    description: string;
+    hidden: boolean;
+    status: 'passed' | 'failed' | 'runtime_error' | 'timeout' | 'compile_error' | 'sandbox_error';
+    input: string | null;
+    expected: string | null;
+    actual: string | null;
+    stderr: string | null;
+    duration_ms: number;
+}
+
+export interface CodeLabRunResult {
+    run_id: string;
+    language: string;
+    passed: number;
+    total: number;
+    score: number;
+    results: CodeLabTestResult[];
+}
+
+export interface PluginToken {
+    token: string;
+    expires_at: string;
+    permissions: string[];
+    api_base: string;
+}
+
 export interface TrackXapiPayload {
     course_id: string;
     lesson_id: string;
@@ -266,6 +321,12 @@ export interface CourseInstructor {
     full_name: string;
 }
 
+export interface SubtitleLanguage {
+    language: string;
+    label: string;
+    original: boolean;
+}
+
 export interface Lesson {
     id: string;
     module_id: string;
@@ -276,10 +337,15 @@ export interface Lesson {
     transcription?: {
         en?: string;
         es?: string;
-        cues?: { start: number; end: number; text: string }[];
+        cues?: { start: number; end: number; text: string; speaker?: string }[];
     } | null;
     metadata?: {
         blocks: Block[];
+        exam?: {
+            time_limit_minutes?: number;
+            available_from?: string;
+            available_until?: string;
+        };
     };
     content_blocks?: Block[];
     is_graded: boolean;
@@ -343,6 +409,21 @@ export interface UserGrade {
     created_at: string;
 }
 
+export interface ExamAttempt {
+    id: string;
+    lesson_id: string;
+    status: 'in_progress' | 'submitted' | 'expired';
+    started_at: string;
+    deadline_at: string | null;
+    remaining_seconds: number | null;
+    server_time: string;
+}
+
+/** Sesión activa de un intento: el token viaja en `X-Exam-Session`. */
+export interface ExamSession extends ExamAttempt {
+    session_token: string;
+}
+
 export interface CourseSubmission {
     id: string;
     user_id: string;
@@ -400,6 +481,11 @@ export interface Notification {
     created_at: string;
 }
 
+export interface PushSubscriptionPayload {
+    endpoint: string;
+    keys: { p256dh: string; auth: string };
+}
+
 export interface DailyProgress {
     date: string;
     count: number;
@@ -1009,8 +1095,24 @@ export const lmsApi = {
         });
     },
 
-    async submitScore(userId: string, course_id: string, lessonId: string, score: number, metadata: Record<string, unknown> = {}): Promise<UserGrade> {
+    async submitScore(userId: string, course_id: string, lessonId: string, score: number, metadata: Record<string, unknown> = {}, examSession?: ExamSession): Promise<UserGrade> {
         const url = '/grades';
+
+        // La entrega de un examen se valida contra la sesión activa y no se encola sin conexión
+        if (examSession) {
+            return apiFetch(url, {
+                method: 'POST',
+                headers: { 'X-Exam-Session': examSession.session_token },
+                body: JSON.stringify({
+                    user_id: userId,
+                    course_id,
+                    lesson_id: lessonId,
+                    score,
+                    metadata: { ...metadata, exam_attempt_id: examSession.id },
+                }),
+            });
+        }
+
         const body = JSON.stringify({ user_id: userId, course_id, lesson_id: lessonId, score, metadata });
 
         if (await enqueueIfOffline('grade', url, 'POST', body)) {
@@ -1055,6 +1157,21 @@ export const lmsApi = {
         }
     },
 
+    async startExamAttempt(lessonId: string, takeover: boolean = false): Promise<ExamSession> {
+        return apiFetch(`/lessons/${lessonId}/exam-attempts`, {
+            method: 'POST',
+            body: JSON.stringify({ takeover })
+        });
+    },
+
+    async saveExamAnswers(session: ExamSession, score: number, metadata: Record<string, unknown>): Promise<ExamAttempt> {
+        return apiFetch(`/exam-attempts/${session.id}/answers`, {
+            method: 'PUT',
+            headers: { 'X-Exam-Session': session.session_token },
+            body: JSON.stringify({ score, metadata })
+        });
+    },
+
     async getUserGrades(userId: string, courseId: string): Promise<UserGrade[]> {
         return apiFetch(`/users/${userId}/courses/${courseId}/grades`);
     },
@@ -1071,6 +1188,10 @@ export const lmsApi = {
         return apiFetch('/branding', {}, true);
     },
 
+    async getAvailableSubtitles(lessonId: string): Promise<SubtitleLanguage[]> {
+        return apiFetch(`/lessons/${lessonId}/subtitles/available`, {}, true);
+    },
+
     async getCourseLanguageConfig(courseId: string): Promise<{ language_setting: 'auto' | 'fixed'; fixed_language: string | null }> {
         return apiFetch(`/courses/${courseId}/language-config`);
     },
@@ -1137,6 +1258,21 @@ export const lmsApi = {
             method: 'POST'
         });
     },
+    async getVapidPublicKey(): Promise<{ public_key: string }> {
+        return apiFetch('/push/vapid-public-key');
+    },
+    async registerPushSubscription(subscription: PushSubscriptionPayload): Promise<void> {
+        return apiFetch('/push/subscriptions', {
+            method: 'POST',
+            body: JSON.stringify(subscription)
+        });
+    },
+    async removePushSubscription(endpoint: string): Promise<void> {
+        return apiFetch('/push/subscriptions', {
+            method: 'DELETE',
+            body: JSON.stringify({ endpoint })
+        });
+    },
     async getRecommendations(courseId: string): Promise<RecommendationResponse> {
         return apiFetch(`/courses/${courseId}/recommendations`);
     },
@@ -1193,6 +1329,17 @@ export const lmsApi = {
             body: JSON.stringify(payload)
         });
     },
+    async runCodeLab(lessonId: string, blockId: string, code: string): Promise<CodeLabRunResult> {
+        return apiFetch(`/lessons/${lessonId}/code-lab/${encodeURIComponent(blockId)}/run`, {
+            method: 'POST',
+            body: JSON.stringify({ code })
+        });
+    },
+    async submitCodeLabRun(lessonId: string, blockId: string, runId: string): Promise<UserGrade> {
+        return apiFetch(`/lessons/${lessonId}/code-lab/${encodeURIComponent(blockId)}/runs/${runId}/submit`, {
+            method: 'POST'
+        });
+    },
     async getLessonFeedback(lessonId: string): Promise<{ response: string, session_id: string }> {
         return apiFetch(`/lessons/${lessonId}/feedback`);
     },
@@ -1399,6 +1546,13 @@ export const lmsApi = {
         return apiFetch('/plugins/enabled', {}, true);
     },
 
+    mintPluginToken(pluginId: string, lessonId: string, blockId: string): Promise<PluginToken> {
+        return apiFetch(`/plugins/${pluginId}/token`, {
+            method: 'POST',
+            body: JSON.stringify({ lesson_id: lessonId, block_id: blockId })
+        });
+    },
+
     listCourseStudyRooms(courseId: string): Promise<StudyRoom[]> {
         return apiFetch(`/courses/${courseId}/study-rooms`);
     },
@@ -376,6 +376,65 @@ export default function AudioEvaluationsPage() {
                                     </div>
                                 )}
 
+                                {/* Pronunciation Breakdown */}
+                                {selectedEvaluation.pronunciation && (
+                                    <div className="p-4 bg-purple-500/10 border border-purple-500/20 rounded-2xl space-y-4">
+                                        <div className="flex items-center justify-between">
+                                            <div className="flex items-center gap-2">
+                                                <Mic className="w-5 h-5 text-purple-600 dark:text-purple-400" />
+                                                <label className="text-xs font-bold text-purple-600 dark:text-purple-400 uppercase tracking-wider">Pronunciación</label>
+                                            </div>
+                                            <p className={`text-2xl font-black ${getScoreColor(Math.round(selectedEvaluation.pronunciation.score))}`}>
+                                                {Math.round(selectedEvaluation.pronunciation.score)}%
+                                            </p>
+                                        </div>
+                                        <div className="grid grid-cols-3 gap-3 text-center">
+                                            {([
+                                                ['Precisión', selectedEvaluation.pronunciation.accuracy],
+                                                ['Fluidez', selectedEvaluation.pronunciation.fluency],
+                                                ['Completitud', selectedEvaluation.pronunciation.completeness],
+                                            ] as const).map(([label, value]) => (
+                                                <div key={label} className="p-3 bg-white/60 dark:bg-black/20 rounded-xl">
+                                                    <p className={`text-xl font-black ${getScoreColor(Math.round(value))}`}>{Math.round(value)}</p>
+                                                    <p className="text-[10px] font-bold text-gray-500 uppercase tracking-wider">{label}</p>
+                                                </div>
+                                            ))}
+                                        </div>
+                                        <p className="text-xs text-gray-600 dark:text-gray-400">
+                                            {selectedEvaluation.pronunciation.fluency_metrics.speech_rate_wpm} palabras/min · {selectedEvaluation.pronunciation.fluency_metrics.pause_count} pausas ({selectedEvaluation.pronunciation.fluency_metrics.long_pause_count} largas, {selectedEvaluation.pronunciation.fluency_metrics.total_pause_seconds}s)
+                                        </p>
+                                        <div>
+                                            <label className="text-[10px] font-bold text-gray-500 uppercase tracking-wider">Texto esperado</label>
+                                            <div className="mt-1 flex flex-wrap gap-1">
+                                                {selectedEvaluation.pronunciation.words.map((word, i) => (
+                                                    <span
+                                                        key={i}
+                                                        title={
+                                                            word.error === 'omitted'
+                                                                ? 'Omitida'
+                                                                : `"${word.spoken}" · ${Math.round(word.accuracy)}%${word.start !== null ? ` · ${word.start.toFixed(1)}s` : ''}`
+                                                        }
+                                                        className={`px-2 py-0.5 rounded-lg text-sm font-bold ${
+                                                            word.error === 'correct'
+                                                                ? 'bg-green-500/10 text-green-700 dark:text-green-300'
+                                                                : word.error === 'mispronounced'
+                                                                    ? 'bg-yellow-500/20 text-yellow-800 dark:text-yellow-300'
+                                                                    : word.error === 'omitted'
+                                                                        ? 'bg-red-500/10 text-red-600 dark:text-red-400 line-through'
+                                                                        : 'bg-gray-500/10 text-gray-500 italic'
+                                                        }`}
+                                                    >
+                                                        {word.expected ?? `+${word.spoken}`}
+                                                    </span>
+                                                ))}
+                                            </div>
+                                            <p className="mt-2 text-[10px] text-gray-500">
+                                                Verde: correcta · Amarillo: mal pronunciada · Tachada: omitida · Cursiva: palabra añadida
+                                            </p>
+                                        </div>
+                                    </div>
+                                )}
+
                                 {/* Teacher Evaluation */}
                                 <div className="space-y-4">
                                     <div>
@@ -32,12 +32,22 @@ function CreatePluginModal({
         component_url: "",
         icon_url: "",
     });
+    const [manifestText, setManifestText] = useState("");
+    const [signature, setSignature] = useState("");
     const [saving, setSaving] = useState(false);
     const [error, setError] = useState<string | null>(null);
 
     const handleSubmit = async (e: React.FormEvent) => {
         e.preventDefault();
-        if (!form.component_url.startsWith("https://")) {
+        let manifest: Record<string, unknown> | undefined;
+        if (manifestText.trim()) {
+            try {
+                manifest = JSON.parse(manifestText);
+            } catch {
+                setError("El manifiesto no es JSON válido");
+                return;
+            }
+        } else if (!form.component_url.startsWith("https://")) {
             setError("La URL del componente debe comenzar con https://");
             return;
         }
@@ -49,6 +59,8 @@ function CreatePluginModal({
                 description: form.description || undefined,
                 component_url: form.component_url,
                 icon_url: form.icon_url || undefined,
+                manifest,
+                signature: manifest ? signature.trim() : undefined,
             });
             onCreated(plugin);
         } catch (err: unknown) {
@@ -74,7 +86,7 @@ function CreatePluginModal({
                     <div className="space-y-1">
                         <label className="text-sm font-medium">Nombre *</label>
                         <input
-                            required
+                            required={!manifestText.trim()}
                             className="w-full rounded-lg border border-black/10 dark:border-white/10 bg-black/5 dark:bg-white/5 px-3 py-2 text-sm outline-none focus:ring-2 focus:ring-indigo-500"
                             value={form.name}
                             onChange={e => setForm(f => ({ ...f, name: e.target.value }))}
@@ -93,7 +105,7 @@ function CreatePluginModal({
                     <div className="space-y-1">
                         <label className="text-sm font-medium">URL del Web Component *</label>
                         <input
-                            required
+                            required={!manifestText.trim()}
                             type="url"
                             className="w-full rounded-lg border border-black/10 dark:border-white/10 bg-black/5 dark:bg-white/5 px-3 py-2 text-sm outline-none focus:ring-2 focus:ring-indigo-500 font-mono"
                             value={form.component_url}
@@ -112,6 +124,26 @@ function CreatePluginModal({
                             placeholder="https://…/icon.svg (opcional)"
                         />
                     </div>
+                    <div className="space-y-1">
+                        <label className="text-sm font-medium">Manifiesto firmado</label>
+                        <textarea
+                            rows={5}
+                            className="w-full rounded-lg border border-black/10 dark:border-white/10 bg-black/5 dark:bg-white/5 px-3 py-2 text-xs outline-none focus:ring-2 focus:ring-indigo-500 font-mono"
+                            value={manifestText}
+                            onChange={e => setManifestText(e.target.value)}
+                            placeholder='{"manifest_version": 1, "name": "…", "version": "1.0.0", "publisher": "…", …} (opcional)'
+                        />
+                        {manifestText.trim() && (
+                            <input
+                                required
+                                className="w-full rounded-lg border border-black/10 dark:border-white/10 bg-black/5 dark:bg-white/5 px-3 py-2 text-sm outline-none focus:ring-2 focus:ring-indigo-500 font-mono"
+                                value={signature}
+                                onChange={e => setSignature(e.target.value)}
+                                placeholder="Firma Ed25519 en base64"
+                            />
+                        )}
+                        <p className="text-xs text-black/40 dark:text-white/40">Con manifiesto, el nombre, la URL y los permisos se toman de él. El editor debe estar registrado como de confianza.</p>
+                    </div>
 
                     {error && (
                         <div className="flex items-center gap-2 p-3 rounded-lg bg-red-50 dark:bg-red-900/20 text-sm text-red-700 dark:text-red-300 border border-red-200 dark:border-red-800">
@@ -184,6 +216,18 @@ function PluginCard({
             <div className="flex-1 min-w-0">
                 <div className="flex items-center gap-2">
                     <span className="font-medium text-sm">{plugin.name}</span>
+                    {plugin.version && (
+                        <span className="text-xs text-black/40 dark:text-white/40 font-mono">v{plugin.version}</span>
+                    )}
+                    {plugin.signed_by && (
+                        <span
+                            title={`Firmado por ${plugin.publisher}`}
+                            className="inline-flex items-center gap-1 text-xs px-2 py-0.5 rounded-full bg-indigo-100 text-indigo-700 dark:bg-indigo-900/30 dark:text-indigo-400"
+                        >
+                            <CheckCircle2 className="w-3 h-3" />
+                            {plugin.publisher}
+                        </span>
+                    )}
                     {plugin.enabled ? (
                         <span className="text-xs px-2 py-0.5 rounded-full bg-green-100 text-green-700 dark:bg-green-900/30 dark:text-green-400">Activo</span>
                     ) : (
@@ -193,6 +237,11 @@ function PluginCard({
                 {plugin.description && (
                     <p className="text-xs text-black/50 dark:text-white/50 mt-0.5">{plugin.description}</p>
                 )}
+                {plugin.permissions?.length > 0 && (
+                    <p className="text-xs text-black/40 dark:text-white/40 mt-0.5 font-mono">
+                        Permisos: {plugin.permissions.join(", ")}
+                    </p>
+                )}
                 <a
                     href={plugin.component_url}
                     target="_blank"
@@ -167,7 +167,7 @@ function DiscriminationPanel({ data }: { data: CourseDiscriminationReport }) {
 
             {data.items.length === 0 && (
                 <p className="text-sm text-black/40 dark:text-white/40 italic">
-                    No hay datos de <code>block_scores</code> en los metadatos de entregas todavía.
+                    Todavía no hay respuestas por pregunta registradas en las evaluaciones del curso.
                 </p>
             )}
 
@@ -176,7 +176,7 @@ function DiscriminationPanel({ data }: { data: CourseDiscriminationReport }) {
                     <thead>
                         <tr className="text-left text-xs text-black/40 dark:text-white/40 uppercase tracking-wider">
                             <th className="pb-2 pr-4">Lección</th>
-                            <th className="pb-2 pr-4">Bloque</th>
+                            <th className="pb-2 pr-4">Pregunta</th>
                             <th className="pb-2 pr-4">Índice Discriminación</th>
                             <th className="pb-2 pr-4">Facilidad</th>
                             <th className="pb-2">Muestra</th>
@@ -44,6 +44,7 @@ import SaveToLibraryModal from "@/components/modals/SaveToLibraryModal";
 import LibraryPanel from "@/components/LibraryPanel";
 import Modal from "@/components/Modal";
 import MediaPlayer from "@/components/MediaPlayer";
+import SubtitleTracksPanel from "@/components/SubtitleTracksPanel";
 
 export default function LessonEditor({ params }: { params: { id: string; lessonId: string } }) {
     const defaultExerciseSettings: OrganizationExerciseSettings = {
@@ -1116,6 +1117,7 @@ export default function LessonEditor({ params }: { params: { id: string; lessonI
                                     title={block.title}
                                     prompt={block.prompt || ""}
                                     keywords={block.keywords || []}
+                                    expectedText={block.expectedText}
                                     timeLimit={block.timeLimit}
                                     editMode={editMode}
                                     onChange={(updates) => updateBlock(block.id, updates)}
@@ -1184,6 +1186,7 @@ export default function LessonEditor({ params }: { params: { id: string; lessonI
                                     initial_code={block.initial_code}
                                     solution={block.solution}
                                     test_cases={block.test_cases}
+                                    hidden_test_cases={block.hidden_test_cases}
                                     editMode={editMode}
                                     lessonId={params.lessonId}
                                     aiGenerationEnabled={exerciseSettings.code_lab_enabled}
@@ -1219,6 +1222,12 @@ export default function LessonEditor({ params }: { params: { id: string; lessonI
                     </div>
                 )}
 
+                {editMode && lesson.transcription?.cues && lesson.transcription.cues.length > 0 && (
+                    <div className="mb-12">
+                        <SubtitleTracksPanel lessonId={params.lessonId} transcriptionCues={lesson.transcription.cues} />
+                    </div>
+                )}
+
                 {editMode && (
                     <div className="pt-20 border-t border-slate-100 dark:border-white/5">
                         <div className="flex flex-col items-center gap-12">
@@ -8,7 +8,7 @@ interface MediaPlayerProps {
     transcription?: {
         en?: string;
         es?: string;
-        cues?: { start: number; end: number; text: string }[];
+        cues?: { start: number; end: number; text: string; speaker?: string }[];
         cues_en?: { start: number; end: number; text: string }[];
     } | null;
     locked?: boolean;
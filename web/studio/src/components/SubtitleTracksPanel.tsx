@@ -0,0 +1,329 @@
+"use client";
+
+import { useCallback, useEffect, useState } from "react";
+import { Captions, CheckCircle2, Languages, Loader2, Pencil, Plus, Trash2, AlertCircle } from "lucide-react";
+import { cmsApi, SubtitleCue, SubtitleTrackList, SubtitleTrackStatus } from "@/lib/api";
+import Modal from "./Modal";
+
+interface SubtitleTracksPanelProps {
+    lessonId: string;
+    transcriptionCues: SubtitleCue[];
+}
+
+const TARGET_LANGUAGES: { code: string; label: string }[] = [
+    { code: "en", label: "English" },
+    { code: "es", label: "Español" },
+    { code: "pt", label: "Português" },
+    { code: "fr", label: "Français" },
+    { code: "de", label: "Deutsch" },
+    { code: "it", label: "Italiano" },
+];
+
+const STATUS_STYLES: Record<SubtitleTrackStatus, string> = {
+    translating: "bg-blue-500/10 text-blue-600 dark:text-blue-400",
+    draft: "bg-amber-500/10 text-amber-600 dark:text-amber-400",
+    approved: "bg-green-500/10 text-green-600 dark:text-green-400",
+    failed: "bg-red-500/10 text-red-600 dark:text-red-400",
+};
+
+const formatTime = (seconds: number) => {
+    const m = Math.floor(seconds / 60);
+    const s = (seconds % 60).toFixed(2).padStart(5, "0");
+    return `${m}:${s}`;
+};
+
+const parseTime = (value: string): number => {
+    const [m, s] = value.includes(":") ? value.split(":") : ["0", value];
+    return Number(m) * 60 + Number(s);
+};
+
+export default function SubtitleTracksPanel({ lessonId, transcriptionCues }: SubtitleTracksPanelProps) {
+    const [list, setList] = useState<SubtitleTrackList | null>(null);
+    const [selected, setSelected] = useState<string[]>([]);
+    const [overwrite, setOverwrite] = useState(false);
+    const [busy, setBusy] = useState(false);
+    const [error, setError] = useState<string | null>(null);
+
+    const [editingLang, setEditingLang] = useState<string | null>(null);
+    const [editingLabel, setEditingLabel] = useState("");
+    const [cues, setCues] = useState<SubtitleCue[]>([]);
+    const [saving, setSaving] = useState(false);
+
+    const load = useCallback(async () => {
+        try {
+            setList(await cmsApi.listSubtitleTracks(lessonId));
+        } catch (err) {
+            console.error("Failed to load subtitle tracks", err);
+        }
+    }, [lessonId]);
+
+    useEffect(() => {
+        load();
+    }, [load]);
+
+    // Las traducciones corren en segundo plano: se consulta hasta que terminen
+    const translating = list?.tracks.some(t => t.status === "translating");
+    useEffect(() => {
+        if (!translating) return;
+        const interval = setInterval(load, 4000);
+        return () => clearInterval(interval);
+    }, [translating, load]);
+
+    const original = list?.original_language || null;
+
+    const handleTranslate = async () => {
+        if (selected.length === 0) return;
+        setBusy(true);
+        setError(null);
+        try {
+            await cmsApi.translateSubtitles(lessonId, selected, overwrite);
+            setSelected([]);
+            await load();
+        } catch (err) {
+            setError(err instanceof Error ? err.message : "Translation failed");
+        } finally {
+            setBusy(false);
+        }
+    };
+
+    const openEditor = async (lang: string) => {
+        setError(null);
+        try {
+            const track = await cmsApi.getSubtitleTrack(lessonId, lang);
+            setEditingLabel(track.label);
+            setCues(track.cues);
+        } catch {
+            // La pista original todavía no existe: se parte de la transcripción
+            if (lang !== original) return;
+            setEditingLabel(TARGET_LANGUAGES.find(l => l.code === lang)?.label || lang.toUpperCase());
+            setCues(transcriptionCues);
+        }
+        setEditingLang(lang);
+    };
+
+    const updateCue = (index: number, updates: Partial<SubtitleCue>) => {
+        setCues(prev => prev.map((cue, i) => (i === index ? { ...cue, ...updates } : cue)));
+    };
+
+    const addCueAfter = (index: number) => {
+        setCues(prev => {
+            const after = prev[index];
+            const start = after ? after.end : 0;
+            const next = [...prev];
+            next.splice(index + 1, 0, { start, end: start + 2, text: "" });
+            return next;
+        });
+    };
+
+    const handleSave = async () => {
+        if (!editingLang) return;
+        setSaving(true);
+        setError(null);
+        try {
+            await cmsApi.updateSubtitleTrack(lessonId, editingLang, { label: editingLabel, cues });
+            setEditingLang(null);
+            await load();
+        } catch (err) {
+            setError(err instanceof Error ? err.message : "Could not save track");
+        } finally {
+            setSaving(false);
+        }
+    };
+
+    const handleApprove = async (lang: string) => {
+        setError(null);
+        try {
+            await cmsApi.approveSubtitleTrack(lessonId, lang);
+            await load();
+        } catch (err) {
+            setError(err instanceof Error ? err.message : "Could not approve track");
+        }
+    };
+
+    const handleDelete = async (lang: string) => {
+        if (!confirm(`Delete the ${lang.toUpperCase()} subtitle track?`)) return;
+        try {
+            await cmsApi.deleteSubtitleTrack(lessonId, lang);
+            await load();
+        } catch (err) {
+            setError(err instanceof Error ? err.message : "Could not delete track");
+        }
+    };
+
+    if (!list || list.original_cue_count === 0) return null;
+
+    const originalTrack = list.tracks.find(t => t.language === original);
+    const translations = list.tracks.filter(t => t.language !== original);
+
+    return (
+        <div className="p-8 bg-slate-50 dark:bg-white/5 border border-slate-200 dark:border-white/10 rounded-[2rem] space-y-6">
+            <div className="flex items-center gap-3">
+                <Captions size={18} className="text-blue-500" />
+                <h3 className="text-[10px] font-black uppercase tracking-[0.2em] text-slate-500 dark:text-gray-400">Subtitle Tracks</h3>
+            </div>
+
+            {error && (
+                <div className="flex items-center gap-2 p-3 rounded-xl bg-red-500/10 text-red-600 dark:text-red-400 text-xs font-bold">
+                    <AlertCircle size={14} /> {error}
+                </div>
+            )}
+
+            <div className="space-y-2">
+                <div className="flex items-center justify-between p-4 rounded-2xl bg-white dark:bg-black/20 border border-slate-200 dark:border-white/10">
+                    <div className="space-y-1">
+                        <p className="text-sm font-black">{(original || "—").toUpperCase()} · Original</p>
+                        <p className="text-[10px] font-bold uppercase tracking-widest text-slate-400">
+                            {originalTrack ? `${originalTrack.cue_count} cues · ${originalTrack.status}` : `${list.original_cue_count} cues · transcription`}
+                        </p>
+                    </div>
+                    {original && (
+                        <div className="flex items-center gap-2">
+                            <button type="button" onClick={() => openEditor(original)} className="p-2 rounded-xl hover:bg-blue-500/10 text-blue-600" aria-label="Edit original subtitles">
+                                <Pencil size={16} />
+                            </button>
+                            {originalTrack?.status === "draft" && (
+                                <button type="button" onClick={() => handleApprove(original)} className="p-2 rounded-xl hover:bg-green-500/10 text-green-600" aria-label="Approve original subtitles">
+                                    <CheckCircle2 size={16} />
+                                </button>
+                            )}
+                        </div>
+                    )}
+                </div>
+
+                {translations.map(track => (
+                    <div key={track.language} className="flex items-center justify-between p-4 rounded-2xl bg-white dark:bg-black/20 border border-slate-200 dark:border-white/10">
+                        <div className="space-y-1">
+                            <div className="flex items-center gap-2">
+                                <p className="text-sm font-black">{track.label}</p>
+                                <span className={`text-[9px] font-black uppercase tracking-widest px-2 py-0.5 rounded-full ${STATUS_STYLES[track.status]}`}>
+                                    {track.status === "translating" && <Loader2 size={10} className="inline animate-spin mr-1" />}
+                                    {track.status}
+                                </span>
+                            </div>
+                            <p className="text-[10px] font-bold uppercase tracking-widest text-slate-400">
+                                {track.cue_count} cues · {track.origin === "machine" ? "machine translated" : "edited"}
+                            </p>
+                            {track.error && <p className="text-[10px] text-red-500">{track.error}</p>}
+                        </div>
+                        <div className="flex items-center gap-2">
+                            {track.status !== "translating" && track.status !== "failed" && (
+                                <button type="button" onClick={() => openEditor(track.language)} className="p-2 rounded-xl hover:bg-blue-500/10 text-blue-600" aria-label={`Edit ${track.label} subtitles`}>
+                                    <Pencil size={16} />
+                                </button>
+                            )}
+                            {track.status === "draft" && (
+                                <button type="button" onClick={() => handleApprove(track.language)} className="p-2 rounded-xl hover:bg-green-500/10 text-green-600" aria-label={`Approve ${track.label} subtitles`}>
+                                    <CheckCircle2 size={16} />
+                                </button>
+                            )}
+                            {track.status !== "translating" && (
+                                <button type="button" onClick={() => handleDelete(track.language)} className="p-2 rounded-xl hover:bg-red-500/10 text-red-500" aria-label={`Delete ${track.label} subtitles`}>
+                                    <Trash2 size={16} />
+                                </button>
+                            )}
+                        </div>
+                    </div>
+                ))}
+            </div>
+
+            <div className="space-y-3">
+                <label className="text-[10px] font-black text-slate-400 dark:text-gray-500 uppercase tracking-[0.2em]">Translate to</label>
+                <div className="flex flex-wrap gap-2">
+                    {TARGET_LANGUAGES.filter(l => l.code !== original).map(lang => {
+                        const active = selected.includes(lang.code);
+                        return (
+                            <button
+                                key={lang.code}
+                                type="button"
+                                aria-pressed={active}
+                                onClick={() => setSelected(prev => active ? prev.filter(c => c !== lang.code) : [...prev, lang.code])}
+                                className={`px-4 py-2 rounded-xl text-xs font-bold border transition-all ${active ? "bg-blue-600 text-white border-blue-600" : "bg-white dark:bg-black/20 border-slate-200 dark:border-white/10"}`}
+                            >
+                                {lang.label}
+                            </button>
+                        );
+                    })}
+                </div>
+                <div className="flex items-center justify-between gap-4">
+                    <label className="flex items-center gap-2 text-xs text-slate-500">
+                        <input type="checkbox" checked={overwrite} onChange={(e) => setOverwrite(e.target.checked)} />
+                        Replace existing tracks (including edited ones)
+                    </label>
+                    <button
+                        type="button"
+                        onClick={handleTranslate}
+                        disabled={busy || selected.length === 0}
+                        className="flex items-center gap-2 px-5 py-2.5 rounded-xl bg-blue-600 text-white text-xs font-black uppercase tracking-widest disabled:opacity-40"
+                    >
+                        {busy ? <Loader2 size={14} className="animate-spin" /> : <Languages size={14} />}
+                        Translate
+                    </button>
+                </div>
+            </div>
+
+            <Modal isOpen={editingLang !== null} onClose={() => setEditingLang(null)} title={`Edit subtitles · ${editingLang?.toUpperCase() || ""}`}>
+                <div className="space-y-4">
+                    <input
+                        type="text"
+                        value={editingLabel}
+                        onChange={(e) => setEditingLabel(e.target.value)}
+                        placeholder="Track label"
+                        className="w-full bg-white dark:bg-black/40 border border-slate-200 dark:border-white/10 rounded-xl px-4 py-2 text-sm"
+                    />
+                    {error && <p className="text-xs font-bold text-red-500">{error}</p>}
+                    <p className="text-[10px] text-slate-400">Up to 2 lines of 42 characters per cue; longer text is rewrapped on save. Saving returns the track to draft.</p>
+                    <div className="max-h-[55vh] overflow-y-auto space-y-3 pr-2">
+                        {cues.map((cue, index) => (
+                            <div key={`${index}-${cue.start}-${cue.end}`} className="grid grid-cols-[6rem_6rem_1fr_auto] gap-2 items-start">
+                                <input
+                                    type="text"
+                                    defaultValue={formatTime(cue.start)}
+                                    onBlur={(e) => updateCue(index, { start: parseTime(e.target.value) })}
+                                    aria-label={`Cue ${index + 1} start`}
+                                    className="bg-white dark:bg-black/40 border border-slate-200 dark:border-white/10 rounded-lg px-2 py-1 text-xs font-mono"
+                                />
+                                <input
+                                    type="text"
+                                    defaultValue={formatTime(cue.end)}
+                                    onBlur={(e) => updateCue(index, { end: parseTime(e.target.value) })}
+                                    aria-label={`Cue ${index + 1} end`}
+                                    className="bg-white dark:bg-black/40 border border-slate-200 dark:border-white/10 rounded-lg px-2 py-1 text-xs font-mono"
+                                />
+                                <div className="space-y-1">
+                                    {cue.speaker && <p className="text-[10px] font-bold text-slate-400">{cue.speaker}</p>}
+                                    <textarea
+                                        value={cue.text}
+                                        rows={2}
+                                        onChange={(e) => updateCue(index, { text: e.target.value })}
+                                        aria-label={`Cue ${index + 1} text`}
+                                        className="w-full bg-white dark:bg-black/40 border border-slate-200 dark:border-white/10 rounded-lg px-2 py-1 text-sm resize-none"
+                                    />
+                                </div>
+                                <div className="flex flex-col gap-1">
+                                    <button type="button" onClick={() => addCueAfter(index)} className="p-1 rounded-lg hover:bg-blue-500/10 text-blue-600" aria-label={`Add cue after ${index + 1}`}>
+                                        <Plus size={14} />
+                                    </button>
+                                    <button type="button" onClick={() => setCues(prev => prev.filter((_, i) => i !== index))} className="p-1 rounded-lg hover:bg-red-500/10 text-red-500" aria-label={`Remove cue ${index + 1}`}>
+                                        <Trash2 size={14} />
+                                    </button>
+                                </div>
+                            </div>
+                        ))}
+                    </div>
+                    <div className="flex justify-end gap-2">
+                        <button type="button" onClick={() => setEditingLang(null)} className="px-4 py-2 rounded-xl text-xs font-bold">Cancel</button>
+                        <button
+                            type="button"
+                            onClick={handleSave}
+                            disabled={saving || cues.length === 0}
+                            className="flex items-center gap-2 px-5 py-2 rounded-xl bg-blue-600 text-white text-xs font-black uppercase tracking-widest disabled:opacity-40"
+                        >
+                            {saving && <Loader2 size={14} className="animate-spin" />}
+                            Save Draft
+                        </button>
+                    </div>
+                </div>
+            </Modal>
+        </div>
+    );
+}
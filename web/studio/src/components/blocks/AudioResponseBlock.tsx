@@ -1,15 +1,16 @@
 "use client";
 
-import { Mic, Clock, Tag } from "lucide-react";
+import { Mic, Clock, Tag, AudioLines } from "lucide-react";
 
 interface AudioResponseBlockProps {
     id: string;
     title?: string;
     prompt: string;
     keywords?: string[];
+    expectedText?: string; // reference text read aloud, enables pronunciation scoring
     timeLimit?: number; // in seconds
     editMode: boolean;
-    onChange: (updates: { title?: string; prompt?: string; keywords?: string[]; timeLimit?: number }) => void;
+    onChange: (updates: { title?: string; prompt?: string; keywords?: string[]; expectedText?: string; timeLimit?: number }) => void;
 }
 
 export default function AudioResponseBlock({
@@ -17,6 +18,7 @@ export default function AudioResponseBlock({
     title,
     prompt,
     keywords = [],
+    expectedText,
     timeLimit,
     editMode,
     onChange
@@ -75,6 +77,22 @@ export default function AudioResponseBlock({
                         </p>
                     </div>
 
+                    <div className="p-8 bg-white dark:bg-white/5 border border-slate-100 dark:border-white/10 rounded-[2.5rem] space-y-4 shadow-sm">
+                        <label className="text-[10px] font-black text-slate-400 dark:text-gray-500 uppercase tracking-[0.2em] flex items-center gap-3">
+                            <AudioLines className="w-4 h-4 text-purple-600" />
+                            Reference Text (Pronunciation Scoring)
+                        </label>
+                        <textarea
+                            value={expectedText || ""}
+                            onChange={(e) => onChange({ expectedText: e.target.value })}
+                            className="w-full bg-slate-50 dark:bg-black/40 border border-slate-100 dark:border-white/10 rounded-2xl p-6 min-h-[100px] text-sm font-bold text-slate-700 dark:text-gray-300 focus:outline-none focus:ring-4 focus:ring-purple-500/10 transition-all shadow-inner"
+                            placeholder="The exact sentence the student should read aloud..."
+                        />
+                        <p className="text-[9px] text-slate-400 dark:text-gray-600 uppercase font-black italic pl-1 italic">
+                            Optional. Enables word-level accuracy, fluency and completeness scoring.
+                        </p>
+                    </div>
+
                     <div className="p-8 bg-white dark:bg-white/5 border border-slate-100 dark:border-white/10 rounded-[2.5rem] space-y-4 shadow-sm">
                         <label className="text-[10px] font-black text-slate-400 dark:text-gray-500 uppercase tracking-[0.2em] flex items-center gap-3">
                             <Clock className="w-4 h-4 text-purple-600" />
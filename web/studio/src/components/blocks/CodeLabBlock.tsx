@@ -4,6 +4,16 @@ import { useState } from "react";
 import { Wand2, Loader2, Code2, Plus, Trash2 } from "lucide-react";
 import { cmsApi } from "@/lib/api";
 
+export interface CodeLabTestCase {
+    description: string;
+    /** Entrada estándar del programa */
+    input?: string;
+    /** Salida estándar esperada */
+    expected: string;
+}
+
+type TestCaseList = "test_cases" | "hidden_test_cases";
+
 interface CodeLabBlockProps {
     id: string;
     title?: string;
@@ -11,7 +21,8 @@ interface CodeLabBlockProps {
     instructions?: string;
     initial_code?: string;
     solution?: string;
-    test_cases?: { description: string; expected: string }[];
+    test_cases?: CodeLabTestCase[];
+    hidden_test_cases?: CodeLabTestCase[];
     editMode: boolean;
     lessonId: string;
     aiGenerationEnabled?: boolean;
@@ -21,7 +32,8 @@ interface CodeLabBlockProps {
         instructions?: string;
         initial_code?: string;
         solution?: string;
-        test_cases?: { description: string; expected: string }[]
+        test_cases?: CodeLabTestCase[];
+        hidden_test_cases?: CodeLabTestCase[];
     }) => void;
 }
 
@@ -33,6 +45,7 @@ export default function CodeLabBlock({
     initial_code = "",
     solution = "",
     test_cases = [],
+    hidden_test_cases = [],
     editMode,
     lessonId,
     aiGenerationEnabled = true,
@@ -68,22 +81,82 @@ export default function CodeLabBlock({
         }
     };
 
-    const addTestCase = () => {
-        const newTestCases = [...test_cases, { description: "", expected: "" }];
-        onChange({ test_cases: newTestCases });
+    const lists: Record<TestCaseList, CodeLabTestCase[]> = { test_cases, hidden_test_cases };
+
+    const addTestCase = (list: TestCaseList) => {
+        onChange({ [list]: [...lists[list], { description: "", input: "", expected: "" }] });
     };
 
-    const updateTestCase = (index: number, field: "description" | "expected", value: string) => {
-        const newTestCases = [...test_cases];
+    const updateTestCase = (list: TestCaseList, index: number, field: keyof CodeLabTestCase, value: string) => {
+        const newTestCases = [...lists[list]];
         newTestCases[index] = { ...newTestCases[index], [field]: value };
-        onChange({ test_cases: newTestCases });
+        onChange({ [list]: newTestCases });
     };
 
-    const removeTestCase = (index: number) => {
-        const newTestCases = test_cases.filter((_, i) => i !== index);
-        onChange({ test_cases: newTestCases });
+    const removeTestCase = (list: TestCaseList, index: number) => {
+        onChange({ [list]: lists[list].filter((_, i) => i !== index) });
     };
 
+    const renderTestCases = (list: TestCaseList, label: string, help: string) => (
+        <div className="pt-8 border-t border-slate-100 dark:border-white/5">
+            <div className="flex items-center justify-between mb-2">
+                <label className="text-[10px] font-black uppercase tracking-[0.2em] text-slate-400 pl-1">{label}</label>
+                <button
+                    onClick={() => addTestCase(list)}
+                    className="flex items-center gap-2 px-4 py-2 bg-slate-100 dark:bg-white/5 hover:bg-indigo-600 hover:text-white text-slate-600 dark:text-gray-300 rounded-xl text-[10px] font-black uppercase tracking-widest transition-all"
+                >
+                    <Plus size={14} /> Añadir Caso
+                </button>
+            </div>
+            <p className="text-[11px] text-slate-400 pl-1 mb-6">{help}</p>
+
+            <div className="grid grid-cols-1 gap-4">
+                {lists[list].map((tc, index) => (
+                    <div key={index} className="flex gap-4 items-start bg-slate-50 dark:bg-black/20 p-6 rounded-2xl border border-slate-100 dark:border-white/5 group/tc">
+                        <div className="flex-1 grid grid-cols-1 md:grid-cols-3 gap-4">
+                            <div className="space-y-2">
+                                <span className="text-[9px] font-black uppercase text-slate-400">Descripción del Caso</span>
+                                <input
+                                    type="text"
+                                    value={tc.description}
+                                    onChange={(e) => updateTestCase(list, index, "description", e.target.value)}
+                                    placeholder="Ej. Entrada válida: 5"
+                                    className="w-full bg-white dark:bg-black/40 border border-slate-100 dark:border-white/10 rounded-xl px-4 py-2 text-xs font-bold"
+                                />
+                            </div>
+                            <div className="space-y-2">
+                                <span className="text-[9px] font-black uppercase text-slate-400">Entrada (stdin)</span>
+                                <textarea
+                                    value={tc.input ?? ""}
+                                    onChange={(e) => updateTestCase(list, index, "input", e.target.value)}
+                                    placeholder="Ej. 5"
+                                    rows={2}
+                                    className="w-full bg-white dark:bg-black/40 border border-slate-100 dark:border-white/10 rounded-xl px-4 py-2 text-xs font-mono"
+                                />
+                            </div>
+                            <div className="space-y-2">
+                                <span className="text-[9px] font-black uppercase text-slate-400">Salida Esperada (stdout)</span>
+                                <textarea
+                                    value={tc.expected}
+                                    onChange={(e) => updateTestCase(list, index, "expected", e.target.value)}
+                                    placeholder="Ej. 120"
+                                    rows={2}
+                                    className="w-full bg-white dark:bg-black/40 border border-slate-100 dark:border-white/10 rounded-xl px-4 py-2 text-xs font-mono"
+                                />
+                            </div>
+                        </div>
+                        <button
+                            onClick={() => removeTestCase(list, index)}
+                            className="p-2 text-slate-300 hover:text-red-500 opacity-0 group-hover/tc:opacity-100 transition-all mt-6"
+                        >
+                            <Trash2 size={16} />
+                        </button>
+                    </div>
+                ))}
+            </div>
+        </div>
+    );
+
     if (!editMode) {
         return (
             <div className="space-y-6" id={id}>
@@ -146,8 +219,9 @@ export default function CodeLabBlock({
                             >
                                 <option value="python">Python</option>
                                 <option value="javascript">JavaScript</option>
-                                <option value="sql">SQL</option>
-                                <option value="bash">Bash</option>
+                                <option value="rust">Rust</option>
+                                <option value="sql">SQL (sin ejecución)</option>
+                                <option value="bash">Bash (sin ejecución)</option>
                             </select>
                         </div>
 
@@ -211,52 +285,16 @@ export default function CodeLabBlock({
                     </div>
                 </div>
 
-                <div className="pt-8 border-t border-slate-100 dark:border-white/5">
-                    <div className="flex items-center justify-between mb-6">
-                        <label className="text-[10px] font-black uppercase tracking-[0.2em] text-slate-400 pl-1">Casos de Prueba</label>
-                        <button
-                            onClick={addTestCase}
-                            className="flex items-center gap-2 px-4 py-2 bg-slate-100 dark:bg-white/5 hover:bg-indigo-600 hover:text-white text-slate-600 dark:text-gray-300 rounded-xl text-[10px] font-black uppercase tracking-widest transition-all"
-                        >
-                            <Plus size={14} /> Añadir Caso
-                        </button>
-                    </div>
-
-                    <div className="grid grid-cols-1 gap-4">
-                        {test_cases.map((tc, index) => (
-                            <div key={index} className="flex gap-4 items-start bg-slate-50 dark:bg-black/20 p-6 rounded-2xl border border-slate-100 dark:border-white/5 group/tc">
-                                <div className="flex-1 grid grid-cols-1 md:grid-cols-2 gap-4">
-                                    <div className="space-y-2">
-                                        <span className="text-[9px] font-black uppercase text-slate-400">Descripción del Caso</span>
-                                        <input
-                                            type="text"
-                                            value={tc.description}
-                                            onChange={(e) => updateTestCase(index, "description", e.target.value)}
-                                            placeholder="Ej. Entrada válida: 5"
-                                            className="w-full bg-white dark:bg-black/40 border border-slate-100 dark:border-white/10 rounded-xl px-4 py-2 text-xs font-bold"
-                                        />
-                                    </div>
-                                    <div className="space-y-2">
-                                        <span className="text-[9px] font-black uppercase text-slate-400">Resultado Esperado (Texto)</span>
-                                        <input
-                                            type="text"
-                                            value={tc.expected}
-                                            onChange={(e) => updateTestCase(index, "expected", e.target.value)}
-                                            placeholder="Ej. 120"
-                                            className="w-full bg-white dark:bg-black/40 border border-slate-100 dark:border-white/10 rounded-xl px-4 py-2 text-xs font-bold"
-                                        />
-                                    </div>
-                                </div>
-                                <button
-                                    onClick={() => removeTestCase(index)}
-                                    className="p-2 text-slate-300 hover:text-red-500 opacity-0 group-hover/tc:opacity-100 transition-all mt-6"
-                                >
-                                    <Trash2 size={16} />
-                                </button>
-                            </div>
-                        ))}
-                    </div>
-                </div>
+                {renderTestCases(
+                    "test_cases",
+                    "Casos de Prueba",
+                    "Visibles para el estudiante. Cada caso envía la entrada por stdin y compara la salida estándar."
+                )}
+                {renderTestCases(
+                    "hidden_test_cases",
+                    "Casos de Prueba Ocultos",
+                    "Cuentan para el puntaje, pero el estudiante solo ve si se superaron."
+                )}
             </div>
         </div>
     );
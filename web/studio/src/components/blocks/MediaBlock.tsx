@@ -37,7 +37,7 @@ interface MediaBlockProps {
     transcription?: {
         en?: string;
         es?: string;
-        cues?: { start: number; end: number; text: string }[];
+        cues?: { start: number; end: number; text: string; speaker?: string }[];
     } | null;
     isGraded?: boolean;
 }
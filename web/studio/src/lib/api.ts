@@ -178,6 +178,7 @@ export interface Block {
     prompt?: string;
     correctAnswers?: string[];
     keywords?: string[];
+    expectedText?: string; // Texto de referencia para evaluar la pronunciación
     timeLimit?: number;
     markers?: {
         timestamp: number;
@@ -207,7 +208,8 @@ export interface Block {
     instructions?: string;
     initial_code?: string;
     solution?: string;
-    test_cases?: { description: string; expected: string }[];
+    test_cases?: { description: string; input?: string; expected: string }[];
+    hidden_test_cases?: { description: string; input?: string; expected: string }[];
     // LTI Tool fields
     lti_tool_id?: string;
     launch_url?: string;
@@ -233,13 +235,50 @@ export interface Lesson {
     transcription?: {
         en?: string;
         es?: string;
-        cues?: { start: number; end: number; text: string }[];
+        language?: string;
+        cues?: SubtitleCue[];
     } | null;
     transcription_status?: 'idle' | 'queued' | 'processing' | 'completed' | 'failed';
     is_previewable: boolean;
     created_at: string;
 }
 
+export interface SubtitleCue {
+    start: number;
+    end: number;
+    text: string;
+    speaker?: string;
+}
+
+export type SubtitleTrackStatus = 'translating' | 'draft' | 'approved' | 'failed';
+
+export interface SubtitleTrackSummary {
+    language: string;
+    label: string;
+    source_language: string | null;
+    origin: 'machine' | 'manual';
+    status: SubtitleTrackStatus;
+    cue_count: number;
+    error: string | null;
+    approved_at: string | null;
+    updated_at: string;
+}
+
+export interface SubtitleTrack extends Omit<SubtitleTrackSummary, 'cue_count'> {
+    id: string;
+    lesson_id: string;
+    cues: SubtitleCue[];
+    edited_by: string | null;
+    approved_by: string | null;
+    created_at: string;
+}
+
+export interface SubtitleTrackList {
+    original_language: string | null;
+    original_cue_count: number;
+    tracks: SubtitleTrackSummary[];
+}
+
 export interface Organization {
     id: string;
     name: string;
@@ -1069,6 +1108,12 @@ export const cmsApi = {
     getLesson: (id: string): Promise<Lesson> => apiFetch(`/lessons/${id}`),
     updateLesson: (id: string, payload: Partial<Lesson>): Promise<Lesson> => apiFetch(`/lessons/${id}`, { method: 'PUT', body: JSON.stringify(payload) }),
     summarizeLesson: (id: string): Promise<Lesson> => apiFetch(`/lessons/${id}/summarize`, { method: 'POST' }),
+    listSubtitleTracks: (lessonId: string): Promise<SubtitleTrackList> => apiFetch(`/lessons/${lessonId}/subtitles`),
+    translateSubtitles: (lessonId: string, languages: string[], overwrite = false): Promise<void> => apiFetch(`/lessons/${lessonId}/subtitles/translate`, { method: 'POST', body: JSON.stringify({ languages, overwrite }) }),
+    getSubtitleTrack: (lessonId: string, lang: string): Promise<SubtitleTrack> => apiFetch(`/lessons/${lessonId}/subtitles/${lang}`),
+    updateSubtitleTrack: (lessonId: string, lang: string, payload: { label?: string; cues: SubtitleCue[] }): Promise<SubtitleTrack> => apiFetch(`/lessons/${lessonId}/subtitles/${lang}`, { method: 'PUT', body: JSON.stringify(payload) }),
+    approveSubtitleTrack: (lessonId: string, lang: string): Promise<SubtitleTrack> => apiFetch(`/lessons/${lessonId}/subtitles/${lang}/approve`, { method: 'POST' }),
+    deleteSubtitleTrack: (lessonId: string, lang: string): Promise<void> => apiFetch(`/lessons/${lessonId}/subtitles/${lang}`, { method: 'DELETE' }),
     async generateQuiz(lessonId: string, payload: { prompt_hint?: string, quiz_type?: string }): Promise<{ questions: QuizQuestion[] }> {
         return apiFetch(`/lessons/${lessonId}/generate-quiz`, {
             method: 'POST',
@@ -2132,6 +2177,12 @@ export interface OrgPlugin {
     icon_url: string | null;
     config: Record<string, unknown>;
     enabled: boolean;
+    kind: 'component' | 'wasm';
+    manifest: Record<string, unknown> | null;
+    version: string | null;
+    publisher: string | null;
+    permissions: string[];
+    signed_by: string | null;
     created_at: string;
     updated_at: string;
 }
@@ -2141,6 +2192,10 @@ export interface CreatePluginPayload {
     component_url: string;
     icon_url?: string;
     config?: Record<string, unknown>;
+    /** Manifiesto firmado; sus campos prevalecen sobre los anteriores */
+    manifest?: Record<string, unknown>;
+    /** Firma Ed25519 del manifiesto canónico, en base64 */
+    signature?: string;
 }
 export interface UpdatePluginPayload {
     name?: string;
@@ -2149,6 +2204,8 @@ export interface UpdatePluginPayload {
     icon_url?: string;
     config?: Record<string, unknown>;
     enabled?: boolean;
+    manifest?: Record<string, unknown>;
+    signature?: string;
 }
 
 // Fase 36: LTI 1.3 Tool Consumer
@@ -2365,6 +2422,30 @@ export interface AudioResponse {
     status: 'pending' | 'ai_evaluated' | 'teacher_evaluated' | 'both_evaluated';
     created_at: string;
     attempt_number: number;
+    pronunciation: PronunciationAssessment | null;
+}
+
+export interface PronunciationAssessment {
+    expected_text: string;
+    score: number;
+    accuracy: number;
+    fluency: number;
+    completeness: number;
+    fluency_metrics: {
+        speech_rate_wpm: number;
+        speaking_seconds: number;
+        pause_count: number;
+        long_pause_count: number;
+        total_pause_seconds: number;
+    };
+    words: {
+        expected: string | null;
+        spoken: string | null;
+        start: number | null;
+        end: number | null;
+        accuracy: number;
+        error: 'correct' | 'mispronounced' | 'omitted' | 'inserted';
+    }[];
 }
 
 export interface AudioResponseStats {